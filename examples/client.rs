@@ -24,6 +24,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             connect: Some(Duration::from_secs(15)),
             read: Some(Duration::from_secs(45)),
             write: Some(Duration::from_secs(15)),
+            ..ClientTimeouts::default()
         })
         .follow_redirects(true);
 
@@ -12,19 +12,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut connection =
         <H1Connection as HttpConnection>::connect(connect_options).await?;
 
-    let client_clone = connection.client().clone();
-    let write_timeout = client_clone.get_timeouts().write;
-    {
-        let stream = connection.stream_mut();
-        client_clone
-            .write_to_stream(stream, req.as_bytes(), write_timeout)
-            .await?;
-    }
+    connection.write_raw(req.as_bytes()).await?;
 
     let response = <H1Connection as HttpConnection>::read_response(&mut connection, true)
     .await?;
 
     println!("{}", response.status);
+    println!("{:?}", connection.stats());
 
     Ok(())
 }
@@ -23,6 +23,7 @@ async fn send(url: &str) -> Result<(), Box<dyn std::error::Error>> {
     let connect_options = H2ConnectOptions {
         target: url.to_string(),
         timeouts: timeout.clone(),
+        mode: None,
     };
     let mut connection =
         <H2Connection as HttpConnection>::connect(connect_options).await?;
@@ -51,6 +52,7 @@ async fn send_with_event_handler(url: &str) -> Result<(), Box<dyn std::error::Er
     let connect_options = H2ConnectOptions {
         target: url.to_string(),
         timeouts: timeout.clone(),
+        mode: None,
     };
     let mut connection =
         <H2Connection as HttpConnection>::connect(connect_options).await?;
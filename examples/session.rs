@@ -32,6 +32,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         connect: Some(std::time::Duration::from_secs(5)),
         read: Some(std::time::Duration::from_secs(10)),
         write: Some(std::time::Duration::from_secs(5)),
+        ..ClientTimeouts::default()
     };
 
     let second_response = session
@@ -0,0 +1,138 @@
+//! Version-negotiating client and crate-root request shortcuts.
+//!
+//! ```ignore
+//! let response = riphttplib::get("https://example.com/").await?;
+//! let created = riphttplib::post("https://example.com/items")
+//!     .json(serde_json::json!({"name": "x"}))
+//!     .await?;
+//! ```
+
+use crate::pool::PoolControl;
+use crate::types::{
+    Client, ClientConfig, ClientRequest, ClientTimeouts, DefaultClient, HttpProtocol, Protocol,
+    ProtocolError, Request, Response, Target, UserAgent,
+};
+use crate::utils::parse_target;
+use crate::{H1, H2};
+use async_trait::async_trait;
+
+/// Picks the HTTP version per request: HTTP/2 for `https` and `h2c` targets,
+/// retried over HTTP/1.1 when the server does not speak HTTP/2, and HTTP/1.1
+/// for other `http` targets and proxied requests.
+#[derive(Clone)]
+pub struct Auto {
+    config: ClientConfig,
+    h1: H1,
+    h2: H2,
+}
+
+impl Auto {
+    pub fn new() -> Self {
+        Self::timeouts(ClientTimeouts::default())
+    }
+
+    pub fn timeouts(timeouts: ClientTimeouts) -> Self {
+        Self::with_config(ClientConfig::new().timeouts(timeouts))
+    }
+
+    pub fn with_config(config: ClientConfig) -> Self {
+        // The fallback shares the HTTP/1.1 pool with plain `http` requests.
+        let h2 = H2::with_config(config.clone()).with_http1_fallback(true);
+        Self {
+            h1: h2.http1().clone(),
+            h2,
+            config,
+        }
+    }
+
+    /// Opens the connection a request to `origin` would use, HTTP/2 where
+    /// the server speaks it, and parks it in that client's pool.
+    pub async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        let target = parse_target(origin)?;
+        if speaks_h2(&target) && self.config.proxies.is_none() {
+            self.h2.preconnect(origin).await
+        } else {
+            self.h1.preconnect(origin).await
+        }
+    }
+
+    /// `User-Agent` for requests that do not pick their own.
+    pub fn with_user_agent(self, user_agent: UserAgent) -> Self {
+        let config = self.config.user_agent(user_agent);
+        Self::with_config(config)
+    }
+
+    pub fn get_config(&self) -> &ClientConfig {
+        &self.config
+    }
+}
+
+impl Default for Auto {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl Protocol for Auto {
+    async fn execute(&self, request: &Request) -> Result<Response, ProtocolError> {
+        if speaks_h2(&request.target) && request.proxies.is_none() {
+            self.h2.execute(request).await
+        } else {
+            self.h1.execute(request).await
+        }
+    }
+
+    async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        Auto::preconnect(self, origin).await
+    }
+
+    /// `h1` shares its pool with the HTTP/2 client's fallback.
+    fn pools(&self) -> Vec<&dyn PoolControl> {
+        self.h2.pools()
+    }
+
+    fn config(&self) -> Option<&ClientConfig> {
+        Some(&self.config)
+    }
+}
+
+/// HTTPS negotiates HTTP/2 through ALPN; cleartext targets only when their
+/// scheme asks for h2c, e.g. `h2c://`.
+fn speaks_h2(target: &Target) -> bool {
+    target.scheme() == "https" || target.protocols.contains(&HttpProtocol::H2C)
+}
+
+impl DefaultClient for Auto {
+    fn default_client() -> Self {
+        Auto::new()
+    }
+}
+
+pub fn request(method: &str, url: &str) -> ClientRequest<Auto> {
+    Client::<Auto>::request(method, url)
+}
+
+pub fn get(url: &str) -> ClientRequest<Auto> {
+    request("GET", url)
+}
+
+pub fn head(url: &str) -> ClientRequest<Auto> {
+    request("HEAD", url)
+}
+
+pub fn post(url: &str) -> ClientRequest<Auto> {
+    request("POST", url)
+}
+
+pub fn put(url: &str) -> ClientRequest<Auto> {
+    request("PUT", url)
+}
+
+pub fn patch(url: &str) -> ClientRequest<Auto> {
+    request("PATCH", url)
+}
+
+pub fn delete(url: &str) -> ClientRequest<Auto> {
+    request("DELETE", url)
+}
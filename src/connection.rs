@@ -1,6 +1,9 @@
 use async_trait::async_trait;
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
-use crate::types::{ProtocolError, Response};
+use crate::types::{Header, ProtocolError, Response};
 
 /// Common abstraction for raw HTTP connection types (H1/H2/H3).
 ///
@@ -24,3 +27,110 @@ pub trait HttpConnection: Sized {
         options: Self::ReadOptions,
     ) -> Result<Response, ProtocolError>;
 }
+
+/// Hand-driven sending side of one request stream (HTTP/2 or HTTP/3).
+///
+/// Nothing is combined or reordered: headers, each DATA chunk and the
+/// trailers go out as separate frames in the order they are written, so the
+/// caller decides how a body is split and how long the gaps between frames
+/// are. Over HTTP/2 frames are buffered until `flush`; QUIC sends them as
+/// soon as they are written.
+#[async_trait(?Send)]
+pub trait StreamWriter {
+    fn stream_id(&self) -> u32;
+
+    /// Sends the request header block; ends the stream if `end_stream` is set.
+    async fn headers(&mut self, headers: &[Header], end_stream: bool) -> Result<(), ProtocolError>;
+
+    /// Sends `chunk` as DATA, splitting it only where frame size or flow
+    /// control requires.
+    async fn data(&mut self, chunk: Bytes, end_stream: bool) -> Result<(), ProtocolError>;
+
+    async fn flush(&mut self) -> Result<(), ProtocolError>;
+
+    /// Flushes, then waits `duration` before the next write.
+    async fn delay(&mut self, duration: Duration) -> Result<(), ProtocolError> {
+        self.flush().await?;
+        tokio::time::sleep(duration).await;
+        Ok(())
+    }
+
+    /// Sends a trailer block, which ends the stream.
+    async fn trailers(&mut self, trailers: &[Header]) -> Result<(), ProtocolError>;
+
+    /// Ends the stream without further content, unless it already ended.
+    async fn finish(&mut self) -> Result<(), ProtocolError>;
+
+    fn is_finished(&self) -> bool;
+}
+
+/// Lifecycle change on an HTTP/2 or HTTP/3 connection, delivered to every
+/// receiver returned by the connection's `subscribe_events`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// A non-ACK SETTINGS frame from the peer, as `(identifier, value)` pairs
+    /// in the order received.
+    SettingsReceived {
+        settings: Vec<(u64, u64)>,
+    },
+    /// The peer acknowledged one of our SETTINGS frames.
+    SettingsAcknowledged,
+    /// `last_stream_id` is the HTTP/2 last stream ID or the HTTP/3 stream or
+    /// push ID carried by the frame; HTTP/3 GOAWAY has no error code.
+    GoAwayReceived {
+        last_stream_id: u64,
+        error_code: Option<u64>,
+        debug_data: Bytes,
+    },
+    /// Round trip of an HTTP/2 PING we sent, measured when its ACK arrived.
+    PingRtt {
+        rtt: Duration,
+    },
+    StreamOpened {
+        stream_id: u32,
+    },
+    /// The stream reached the closed state or was abandoned.
+    StreamClosed {
+        stream_id: u32,
+    },
+    /// The connection no longer accepts new streams.
+    Closed,
+    /// A frame of a type the connection does not handle, such as an
+    /// extension (ALTSVC, ORIGIN, PRIORITY_UPDATE) or GREASE. `stream_id` is
+    /// 0 for HTTP/2 connection-level frames and frames on the HTTP/3 control
+    /// stream; frames on a request stream are also captured with its
+    /// response.
+    UnknownFrame {
+        stream_id: u32,
+        frame_type: u64,
+        payload: Bytes,
+    },
+}
+
+/// Fan-out of [`ConnectionEvent`]s to any number of subscribers. Receivers
+/// that were dropped are forgotten on the next event.
+#[derive(Debug, Default)]
+pub struct ConnectionEvents {
+    subscribers: Vec<mpsc::UnboundedSender<ConnectionEvent>>,
+}
+
+impl ConnectionEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        !self.subscribers.is_empty()
+    }
+
+    pub fn emit(&mut self, event: ConnectionEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
@@ -1,12 +1,18 @@
+use crate::h1::codec::parse_status_line;
 use crate::h1::protocol::H1;
 use crate::h2::connection::H2Connection;
+use crate::h2::consts::{ACK_FLAG, CONNECTION_PREFACE, FRAME_HEADER_SIZE, SETTINGS_FRAME_TYPE};
+use crate::stream::create_stream;
 use crate::types::protocol::HttpProtocol;
-use crate::types::{ClientTimeouts, ProtocolError};
+use crate::types::{ClientTimeouts, FrameH2, ProtocolError};
 use crate::utils::{header_value, parse_target};
 use crate::Client;
 use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::Instant;
 
 const DETECTION_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_H2C_REPLY_SIZE: usize = 16 * 1024;
 
 pub struct DetectedProtocol {
     pub protocol: HttpProtocol,
@@ -35,6 +41,120 @@ pub fn extract_alt_svc_port(header: Option<&str>) -> Option<u16> {
     }
 }
 
+/// How a server reacted to the HTTP/2 connection preface sent in cleartext
+/// (h2c with prior knowledge).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum H2cProbe {
+    /// Answered with a SETTINGS frame; the settings it advertised.
+    Settings(Vec<(u16, u32)>),
+    /// Ignored the preface and answered with an HTTP/1.x response.
+    Http1 { status: u16, raw: Vec<u8> },
+    /// Closed or reset the connection without sending anything.
+    Closed,
+    /// Sent nothing before the timeout.
+    Silent,
+    /// Sent bytes that are neither a SETTINGS frame nor an HTTP/1.x response.
+    Unrecognized(Vec<u8>),
+}
+
+impl H2cProbe {
+    pub fn supports_h2c(&self) -> bool {
+        matches!(self, H2cProbe::Settings(_))
+    }
+}
+
+/// Classifies the first bytes a server sent back after the preface, or
+/// returns `None` while more are needed to decide.
+pub fn classify_h2c_reply(reply: &[u8]) -> Option<H2cProbe> {
+    const HTTP1_PREFIX: &[u8] = b"HTTP/1.";
+    if reply.len() < HTTP1_PREFIX.len() && HTTP1_PREFIX.starts_with(reply) {
+        return None;
+    }
+    if reply.starts_with(HTTP1_PREFIX) {
+        let complete = reply.windows(4).any(|w| w == b"\r\n\r\n");
+        return (complete || reply.len() >= MAX_H2C_REPLY_SIZE).then(|| finish_h2c_reply(reply));
+    }
+    if reply.len() < FRAME_HEADER_SIZE {
+        return None;
+    }
+
+    let length = u32::from_be_bytes([0, reply[0], reply[1], reply[2]]) as usize;
+    let stream_id = u32::from_be_bytes([reply[5], reply[6], reply[7], reply[8]]) & 0x7fff_ffff;
+    let is_settings = reply[3] == SETTINGS_FRAME_TYPE && reply[4] & ACK_FLAG == 0 && stream_id == 0;
+    if !is_settings || length % 6 != 0 || length > MAX_H2C_REPLY_SIZE {
+        return Some(H2cProbe::Unrecognized(reply.to_vec()));
+    }
+    if reply.len() < FRAME_HEADER_SIZE + length {
+        return None;
+    }
+    let settings = reply[FRAME_HEADER_SIZE..FRAME_HEADER_SIZE + length]
+        .chunks_exact(6)
+        .map(|entry| {
+            let id = u16::from_be_bytes([entry[0], entry[1]]);
+            let value = u32::from_be_bytes([entry[2], entry[3], entry[4], entry[5]]);
+            (id, value)
+        })
+        .collect();
+    Some(H2cProbe::Settings(settings))
+}
+
+/// Classifies a reply that ended before [`classify_h2c_reply`] could decide.
+fn finish_h2c_reply(reply: &[u8]) -> H2cProbe {
+    if reply.is_empty() {
+        return H2cProbe::Closed;
+    }
+    let text = String::from_utf8_lossy(reply);
+    let status = text
+        .split_once("\r\n")
+        .and_then(|(line, _)| parse_status_line(line).ok());
+    match status {
+        Some((status, protocol)) if protocol.starts_with("HTTP/1.") => H2cProbe::Http1 {
+            status,
+            raw: reply.to_vec(),
+        },
+        _ => H2cProbe::Unrecognized(reply.to_vec()),
+    }
+}
+
+/// Sends the HTTP/2 preface and an empty SETTINGS frame over plain TCP to
+/// the target's host and port and reports how the server reacted. Only a
+/// failure to connect is an error.
+pub async fn probe_h2c(url: &str, timeout: Duration) -> Result<H2cProbe, ProtocolError> {
+    let target = parse_target(url)?;
+    let host = target
+        .host()
+        .ok_or_else(|| ProtocolError::InvalidTarget("Target missing host".to_string()))?;
+    let port = target
+        .port()
+        .ok_or_else(|| ProtocolError::InvalidTarget("Target missing port".to_string()))?;
+    let mut stream = create_stream("http", host, port, Some(timeout))
+        .await
+        .map_err(ProtocolError::from_connect_error)?;
+
+    let mut hello = CONNECTION_PREFACE.to_vec();
+    hello.extend_from_slice(&FrameH2::settings(&[]).serialize()?);
+    let deadline = Instant::now() + timeout;
+    match tokio::time::timeout_at(deadline, stream.write_all(&hello)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(_)) => return Ok(H2cProbe::Closed),
+        Err(_) => return Ok(H2cProbe::Silent),
+    }
+
+    let mut reply = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        match tokio::time::timeout_at(deadline, stream.read(&mut chunk)).await {
+            Ok(Ok(0)) | Ok(Err(_)) => return Ok(finish_h2c_reply(&reply)),
+            Ok(Ok(read)) => reply.extend_from_slice(&chunk[..read]),
+            Err(_) if reply.is_empty() => return Ok(H2cProbe::Silent),
+            Err(_) => return Ok(finish_h2c_reply(&reply)),
+        }
+        if let Some(probe) = classify_h2c_reply(&reply) {
+            return Ok(probe);
+        }
+    }
+}
+
 pub async fn detect_protocol(url: &str) -> Result<Vec<DetectedProtocol>, ProtocolError> {
     let target = parse_target(url)?;
     let scheme = target.scheme().to_string();
@@ -45,6 +165,7 @@ pub async fn detect_protocol(url: &str) -> Result<Vec<DetectedProtocol>, Protoco
         connect: Some(DETECTION_TIMEOUT),
         read: Some(DETECTION_TIMEOUT),
         write: Some(DETECTION_TIMEOUT),
+        ..ClientTimeouts::default()
     };
 
     // detect h1
@@ -65,7 +186,14 @@ pub async fn detect_protocol(url: &str) -> Result<Vec<DetectedProtocol>, Protoco
         Err(_) => {}
     }
     // detect h2
-    if H2Connection::connect(url, &timeouts).await.is_ok() {
+    let h2 = if scheme == "http" {
+        probe_h2c(url, DETECTION_TIMEOUT)
+            .await
+            .map_or(false, |probe| probe.supports_h2c())
+    } else {
+        H2Connection::connect(url, &timeouts).await.is_ok()
+    };
+    if h2 {
         supported.push(DetectedProtocol {
             protocol: if scheme == "http" {
                 HttpProtocol::H2C
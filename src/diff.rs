@@ -0,0 +1,241 @@
+//! Side-by-side comparison of one request over HTTP/1.1, HTTP/2 and HTTP/3.
+//!
+//! Servers and intermediaries often route or normalize the protocols
+//! differently; [`diff`] sends the same request over each version at once
+//! and lines up what came back:
+//!
+//! ```ignore
+//! let report = riphttplib::diff(&Request::new("https://example.com/", "GET")?).await;
+//! if !report.statuses_match() || !report.bodies_match() {
+//!     for result in &report.results {
+//!         println!("{}: {:?} in {:?}", result.protocol, result.summary().map(|s| s.status), result.elapsed);
+//!     }
+//! }
+//! for difference in report.header_differences() {
+//!     println!("{}: {:?}", difference.name, difference.values);
+//! }
+//! ```
+
+use crate::types::{HttpProtocol, Protocol, Request, Response};
+use crate::utils::join_bounded;
+use crate::{H1, H2, H3};
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/// Headers HTTP/2 and HTTP/3 forbid, so their absence there is not a difference.
+const CONNECTION_SPECIFIC_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// What one protocol returned, reduced to the parts that are compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseSummary {
+    pub status: u16,
+    /// Header names lowercased, in the order received.
+    pub headers: Vec<(String, String)>,
+    /// Lowercase hex SHA-256 of the raw body.
+    pub body_hash: String,
+    pub body_len: usize,
+}
+
+impl ResponseSummary {
+    pub fn from_response(response: &Response) -> Self {
+        Self {
+            status: response.status,
+            headers: response
+                .headers
+                .iter()
+                .map(|header| {
+                    (
+                        header.name.to_ascii_lowercase(),
+                        header.value.clone().unwrap_or_default(),
+                    )
+                })
+                .collect(),
+            body_hash: response.body_hash(),
+            body_len: response.body.len(),
+        }
+    }
+
+    /// Values of header `name`, in the order received.
+    pub fn header_values(&self, name: &str) -> Vec<&str> {
+        self.headers
+            .iter()
+            .filter(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    fn header_names(&self) -> Vec<&str> {
+        let mut seen = BTreeSet::new();
+        self.headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| seen.insert(*name))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct ProtocolResult {
+    pub protocol: HttpProtocol,
+    /// The response, or the error message if the request failed.
+    pub outcome: Result<ResponseSummary, String>,
+    /// From sending the request to the end of the response, redirects included.
+    pub elapsed: Duration,
+}
+
+impl ProtocolResult {
+    pub fn summary(&self) -> Option<&ResponseSummary> {
+        self.outcome.as_ref().ok()
+    }
+}
+
+/// A header whose values are not the same across the protocols that answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderDifference {
+    pub name: String,
+    /// Values per protocol, empty where the header was missing.
+    pub values: Vec<(HttpProtocol, Vec<String>)>,
+}
+
+#[derive(Debug)]
+pub struct ProtocolDiff {
+    pub results: Vec<ProtocolResult>,
+}
+
+impl ProtocolDiff {
+    /// Sends `request` with every client at once.
+    pub async fn run(request: &Request, clients: &[(HttpProtocol, &dyn Protocol)]) -> Self {
+        let runs = clients.iter().map(|(protocol, client)| {
+            let request = request.clone();
+            async move {
+                let started = Instant::now();
+                let outcome = client.send_request(request).await;
+                ProtocolResult {
+                    protocol: protocol.clone(),
+                    outcome: outcome
+                        .map(|response| ResponseSummary::from_response(&response))
+                        .map_err(|err| err.to_string()),
+                    elapsed: started.elapsed(),
+                }
+            }
+        });
+        Self {
+            results: join_bounded(runs, clients.len()).await,
+        }
+    }
+
+    fn summaries(&self) -> impl Iterator<Item = (&HttpProtocol, &ResponseSummary)> {
+        self.results
+            .iter()
+            .filter_map(|result| Some((&result.protocol, result.summary()?)))
+    }
+
+    /// Protocols whose request failed.
+    pub fn failures(&self) -> Vec<&HttpProtocol> {
+        self.results
+            .iter()
+            .filter(|result| result.outcome.is_err())
+            .map(|result| &result.protocol)
+            .collect()
+    }
+
+    /// True if every protocol that answered sent the same status.
+    pub fn statuses_match(&self) -> bool {
+        all_equal(self.summaries().map(|(_, summary)| summary.status))
+    }
+
+    /// True if every protocol that answered sent the same body bytes.
+    pub fn bodies_match(&self) -> bool {
+        all_equal(self.summaries().map(|(_, summary)| &summary.body_hash))
+    }
+
+    /// Headers missing from some responses or carrying different values,
+    /// sorted by name. Connection-specific headers are left out.
+    pub fn header_differences(&self) -> Vec<HeaderDifference> {
+        let names: BTreeSet<&str> = self
+            .summaries()
+            .flat_map(|(_, summary)| summary.headers.iter().map(|(name, _)| name.as_str()))
+            .filter(|name| !CONNECTION_SPECIFIC_HEADERS.contains(name))
+            .collect();
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let values: Vec<(HttpProtocol, Vec<String>)> = self
+                    .summaries()
+                    .map(|(protocol, summary)| {
+                        let values = summary.header_values(name);
+                        (
+                            protocol.clone(),
+                            values.into_iter().map(str::to_string).collect(),
+                        )
+                    })
+                    .collect();
+                (!all_equal(values.iter().map(|(_, values)| values))).then(|| HeaderDifference {
+                    name: name.to_string(),
+                    values,
+                })
+            })
+            .collect()
+    }
+
+    /// True if the headers every response has arrived in the same relative order.
+    pub fn header_order_matches(&self) -> bool {
+        let summaries: Vec<&ResponseSummary> =
+            self.summaries().map(|(_, summary)| summary).collect();
+        let common: Vec<&str> = match summaries.first() {
+            Some(first) => first
+                .header_names()
+                .into_iter()
+                .filter(|name| {
+                    summaries
+                        .iter()
+                        .all(|summary| !summary.header_values(name).is_empty())
+                })
+                .collect(),
+            None => return true,
+        };
+        all_equal(summaries.iter().map(|summary| {
+            summary
+                .header_names()
+                .into_iter()
+                .filter(|name| common.contains(name))
+                .collect::<Vec<_>>()
+        }))
+    }
+
+    /// The protocol that answered first, if any did.
+    pub fn fastest(&self) -> Option<&ProtocolResult> {
+        self.results
+            .iter()
+            .filter(|result| result.outcome.is_ok())
+            .min_by_key(|result| result.elapsed)
+    }
+}
+
+fn all_equal<T: PartialEq>(mut items: impl Iterator<Item = T>) -> bool {
+    match items.next() {
+        Some(first) => items.all(|item| item == first),
+        None => true,
+    }
+}
+
+/// Sends `request` over HTTP/1.1, HTTP/2 and HTTP/3 with default clients.
+pub async fn diff(request: &Request) -> ProtocolDiff {
+    let (h1, h2, h3) = (H1::new(), H2::new(), H3::new());
+    ProtocolDiff::run(
+        request,
+        &[
+            (HttpProtocol::Http1, &h1 as &dyn Protocol),
+            (HttpProtocol::Http2, &h2),
+            (HttpProtocol::Http3, &h3),
+        ],
+    )
+    .await
+}
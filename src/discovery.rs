@@ -0,0 +1,227 @@
+//! Content discovery with wildcard filtering.
+//!
+//! Many servers answer unknown paths with a 200 or a styled error page
+//! instead of a plain 404. [`ContentDiscovery`] first requests random paths
+//! to learn what that catch-all response looks like, then drops every result
+//! that matches it, so only paths with distinct content are reported.
+
+use crate::types::{
+    normalize_body, text_similarity, PageSignature, Protocol, ProtocolError, Request, Response,
+};
+use crate::utils::{join_bounded, parse_target};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::sync::Arc;
+use url::Url;
+
+const DEFAULT_CONCURRENCY: usize = 16;
+const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.95;
+
+/// What the server returns for a path that cannot exist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wildcard {
+    /// Random path the calibration request used.
+    pub probe_path: String,
+    pub signature: PageSignature,
+    /// Normalized body with the probe path removed.
+    body: String,
+}
+
+impl Wildcard {
+    fn from_response(probe_path: &str, response: &Response) -> Self {
+        Self {
+            probe_path: probe_path.to_string(),
+            signature: response.page_signature(),
+            body: reflection_free_body(response, probe_path),
+        }
+    }
+
+    /// True if `response`, fetched for `path`, looks like this wildcard.
+    /// Occurrences of the requested path are ignored, since catch-all pages
+    /// often echo it back.
+    pub fn matches(&self, path: &str, response: &Response, threshold: f64) -> bool {
+        if response.status != self.signature.status {
+            return false;
+        }
+        let body = reflection_free_body(response, path);
+        body == self.body || text_similarity(&body, &self.body) >= threshold
+    }
+}
+
+fn reflection_free_body(response: &Response, path: &str) -> String {
+    let body = response.normalized_body();
+    let path = normalize_body(path.trim_start_matches('/'));
+    if path.is_empty() {
+        body
+    } else {
+        body.replace(&path, "")
+    }
+}
+
+/// A path whose response did not match any wildcard.
+#[derive(Debug)]
+pub struct DiscoveryHit {
+    pub path: String,
+    pub response: Response,
+}
+
+#[derive(Debug, Default)]
+pub struct DiscoveryReport {
+    /// Catch-all responses learned during calibration.
+    pub wildcards: Vec<Wildcard>,
+    pub hits: Vec<DiscoveryHit>,
+    /// Paths dropped because they matched a wildcard.
+    pub filtered: Vec<String>,
+    pub errors: Vec<(String, ProtocolError)>,
+}
+
+/// Requests a list of paths below a base URL and reports the ones that do
+/// not look like the server's wildcard response.
+pub struct ContentDiscovery<P: Protocol> {
+    protocol: P,
+    base: Url,
+    method: String,
+    concurrency: usize,
+    similarity_threshold: f64,
+    calibration: bool,
+    configure: Option<Arc<dyn Fn(&mut Request)>>,
+}
+
+impl<P: Protocol> ContentDiscovery<P> {
+    /// `base` is the directory paths are resolved against; a missing
+    /// trailing slash is added so `https://host/app` scans below `/app/`.
+    pub fn new(protocol: P, base: &str) -> Result<Self, ProtocolError> {
+        let mut base = parse_target(base)?.url;
+        if !base.path().ends_with('/') {
+            let path = format!("{}/", base.path());
+            base.set_path(&path);
+        }
+        Ok(Self {
+            protocol,
+            base,
+            method: "GET".to_string(),
+            concurrency: DEFAULT_CONCURRENCY,
+            similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD,
+            calibration: true,
+            configure: None,
+        })
+    }
+
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.method = method.into();
+        self
+    }
+
+    /// Maximum number of requests in flight.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// How similar, in `0.0..=1.0`, a response must be to a wildcard to be
+    /// filtered.
+    pub fn similarity_threshold(mut self, threshold: f64) -> Self {
+        self.similarity_threshold = threshold;
+        self
+    }
+
+    /// Skips the calibration requests and reports every response.
+    pub fn calibration(mut self, enabled: bool) -> Self {
+        self.calibration = enabled;
+        self
+    }
+
+    /// Adjusts each request before it is sent, e.g. to add headers or cookies.
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: Fn(&mut Request) + 'static,
+    {
+        self.configure = Some(Arc::new(configure));
+        self
+    }
+
+    fn build_request(&self, path: &str) -> Result<Request, ProtocolError> {
+        let url = self
+            .base
+            .join(path.trim_start_matches('/'))
+            .map_err(|e| ProtocolError::InvalidTarget(format!("{}: {}", path, e)))?;
+        let mut request = Request::new(url.as_str(), self.method.clone())?;
+        // A redirect to a login page or the site root is itself a signal.
+        request.set_follow_redirects(false);
+        if let Some(configure) = &self.configure {
+            configure(&mut request);
+        }
+        Ok(request)
+    }
+
+    async fn fetch(&self, path: &str) -> Result<Response, ProtocolError> {
+        let request = self.build_request(path)?;
+        self.protocol.send_request(request).await
+    }
+
+    /// Requests a random file name and a random directory, returning the
+    /// distinct catch-all responses they produced.
+    pub async fn calibrate(&self) -> Result<Vec<Wildcard>, ProtocolError> {
+        let token = random_token()?;
+        let probes = [token.clone(), format!("{}/", token)];
+        let mut wildcards: Vec<Wildcard> = Vec::new();
+        for probe in probes {
+            let response = self.fetch(&probe).await?;
+            let known = wildcards
+                .iter()
+                .any(|w| w.matches(&probe, &response, self.similarity_threshold));
+            if !known {
+                wildcards.push(Wildcard::from_response(&probe, &response));
+            }
+        }
+        Ok(wildcards)
+    }
+
+    /// Calibrates (unless disabled), then requests every path and sorts the
+    /// responses into hits, filtered wildcards and errors. Failed calibration
+    /// aborts the run, as results could not be trusted.
+    pub async fn run<I, S>(&self, paths: I) -> Result<DiscoveryReport, ProtocolError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let wildcards = if self.calibration {
+            self.calibrate().await?
+        } else {
+            Vec::new()
+        };
+
+        let paths: Vec<String> = paths.into_iter().map(Into::into).collect();
+        let results =
+            join_bounded(paths.iter().map(|path| self.fetch(path)), self.concurrency).await;
+
+        let mut report = DiscoveryReport {
+            wildcards,
+            ..DiscoveryReport::default()
+        };
+        for (path, result) in paths.into_iter().zip(results) {
+            match result {
+                Ok(response) => {
+                    let wildcard = report
+                        .wildcards
+                        .iter()
+                        .any(|w| w.matches(&path, &response, self.similarity_threshold));
+                    if wildcard {
+                        report.filtered.push(path);
+                    } else {
+                        report.hits.push(DiscoveryHit { path, response });
+                    }
+                }
+                Err(err) => report.errors.push((path, err)),
+            }
+        }
+        Ok(report)
+    }
+}
+
+fn random_token() -> Result<String, ProtocolError> {
+    let mut bytes = [0u8; 12];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| {
+        ProtocolError::RequestFailed("Failed to generate calibration path".to_string())
+    })?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
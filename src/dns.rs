@@ -0,0 +1,313 @@
+//! Shared cache in front of a pluggable [`Resolver`], the system one by
+//! default.
+//!
+//! Every connector in the crate resolves through [`DnsCache::global`], so a
+//! scan that opens thousands of connections to one host looks it up once:
+//!
+//! ```ignore
+//! // Pin a name for a reproducible replay, whatever DNS says later.
+//! DnsCache::global().insert("example.com", vec!["93.184.216.34".parse()?], None);
+//!
+//! // Skip the cache for one request; its answer replaces the cached one
+//! // unless that one is pinned.
+//! let request = Request::new("https://example.com/", "GET")?.fresh_dns();
+//! ```
+//!
+//! Answers are kept for their records' TTL when the resolver reports it and
+//! for the cache's `ttl` otherwise; failures for its `negative_ttl`. Past
+//! `max_entries` the entry closest to expiry makes room.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::net::lookup_host;
+
+const DEFAULT_TTL: Duration = Duration::from_secs(60);
+const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+static GLOBAL: OnceLock<DnsCache> = OnceLock::new();
+
+/// Looks up the addresses of host names the cache has no fresh entry for.
+#[async_trait(?Send)]
+pub trait Resolver: Send + Sync {
+    /// Addresses of `host`, already normalized: lowercase, without a
+    /// trailing dot. `port` is only a hint; the cache adds it to the answer.
+    async fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<IpAddr>>;
+
+    /// [`lookup`](Self::lookup) with the smallest TTL of the answer's
+    /// records, for resolvers that see them.
+    async fn lookup_with_ttl(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> io::Result<(Vec<IpAddr>, Option<Duration>)> {
+        Ok((self.lookup(host, port).await?, None))
+    }
+}
+
+/// The operating system's resolver.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+#[async_trait(?Send)]
+impl Resolver for SystemResolver {
+    async fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<IpAddr>> {
+        let addrs = lookup_host((host, port)).await?;
+        Ok(addrs.map(|addr| addr.ip()).collect())
+    }
+}
+
+#[derive(Debug)]
+pub struct DnsCache {
+    state: Mutex<DnsState>,
+}
+
+struct DnsState {
+    ttl: Duration,
+    negative_ttl: Duration,
+    max_entries: usize,
+    entries: HashMap<String, DnsEntry>,
+    resolver: Arc<dyn Resolver>,
+}
+
+impl fmt::Debug for DnsState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsState")
+            .field("ttl", &self.ttl)
+            .field("negative_ttl", &self.negative_ttl)
+            .field("max_entries", &self.max_entries)
+            .field("entries", &self.entries)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DnsEntry {
+    result: Result<Vec<IpAddr>, (io::ErrorKind, String)>,
+    /// `None` for entries pinned with [`DnsCache::insert`] and no TTL.
+    expires: Option<Instant>,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_NEGATIVE_TTL)
+    }
+}
+
+impl DnsCache {
+    /// A zero `ttl` or `negative_ttl` turns off caching of answers or of failures.
+    pub fn new(ttl: Duration, negative_ttl: Duration) -> Self {
+        Self {
+            state: Mutex::new(DnsState {
+                ttl,
+                negative_ttl,
+                max_entries: DEFAULT_MAX_ENTRIES,
+                entries: HashMap::new(),
+                resolver: Arc::new(SystemResolver),
+            }),
+        }
+    }
+
+    /// The cache all connectors use.
+    pub fn global() -> &'static DnsCache {
+        GLOBAL.get_or_init(DnsCache::default)
+    }
+
+    /// Applies to lookups made from now on; cached entries keep their expiry.
+    pub fn set_ttl(&self, ttl: Duration, negative_ttl: Duration) {
+        let mut state = self.lock();
+        state.ttl = ttl;
+        state.negative_ttl = negative_ttl;
+    }
+
+    /// Caps the looked-up entries kept; pinned entries are never evicted.
+    pub fn set_max_entries(&self, max_entries: usize) {
+        self.lock().max_entries = max_entries;
+    }
+
+    /// Sends lookups from now on to `resolver`; cached entries stay.
+    pub fn set_resolver(&self, resolver: Arc<dyn Resolver>) {
+        self.lock().resolver = resolver;
+    }
+
+    pub fn resolver(&self) -> Arc<dyn Resolver> {
+        self.lock().resolver.clone()
+    }
+
+    /// Resolves `host`, answering from the cache while the entry is fresh.
+    /// IP literals are returned as they are.
+    pub async fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        if let Some(addrs) = ip_literal(host, port) {
+            return Ok(addrs);
+        }
+        let key = normalize(host);
+        let ips = match self.cached(&key) {
+            Some(result) => result?,
+            None => self.lookup(&key, port).await?,
+        };
+        Ok(with_port(&ips, port))
+    }
+
+    /// Resolves `host` with the resolver even if a fresh entry
+    /// exists, and caches the new answer unless the entry is pinned.
+    pub async fn resolve_fresh(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        if let Some(addrs) = ip_literal(host, port) {
+            return Ok(addrs);
+        }
+        let ips = self.lookup(&normalize(host), port).await?;
+        Ok(with_port(&ips, port))
+    }
+
+    /// [`resolve_fresh`](Self::resolve_fresh) when `fresh`, otherwise
+    /// [`resolve`](Self::resolve).
+    pub async fn resolve_for(
+        &self,
+        host: &str,
+        port: u16,
+        fresh: bool,
+    ) -> io::Result<Vec<SocketAddr>> {
+        if fresh {
+            self.resolve_fresh(host, port).await
+        } else {
+            self.resolve(host, port).await
+        }
+    }
+
+    /// Pins `host` to `addrs`, for `ttl` or until removed when `ttl` is `None`.
+    pub fn insert(&self, host: &str, addrs: Vec<IpAddr>, ttl: Option<Duration>) {
+        let expires = ttl.and_then(|ttl| Instant::now().checked_add(ttl));
+        self.lock().entries.insert(
+            normalize(host),
+            DnsEntry {
+                result: Ok(addrs),
+                expires,
+            },
+        );
+    }
+
+    /// Drops the entry for `host`, so its next lookup goes to the resolver.
+    pub fn remove(&self, host: &str) {
+        self.lock().entries.remove(&normalize(host));
+    }
+
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    /// Number of entries, expired ones included until they are looked up again.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    async fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<IpAddr>> {
+        let resolver = self.resolver();
+        let (result, ttl) = match resolver.lookup_with_ttl(host, port).await {
+            Ok((ips, _)) if ips.is_empty() => (
+                Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "no addresses found",
+                )),
+                None,
+            ),
+            Ok((ips, ttl)) => (Ok(ips), ttl),
+            Err(err) => (Err(err), None),
+        };
+        self.remember(host, &result, ttl);
+        result
+    }
+
+    fn cached(&self, host: &str) -> Option<io::Result<Vec<IpAddr>>> {
+        let mut state = self.lock();
+        let expires = state.entries.get(host)?.expires;
+        if expires.map_or(false, |expires| expires <= Instant::now()) {
+            state.entries.remove(host);
+            return None;
+        }
+        let result = state.entries.get(host)?.result.clone();
+        Some(result.map_err(|(kind, message)| io::Error::new(kind, message)))
+    }
+
+    /// Caches `result` for `record_ttl` if the resolver reported one.
+    fn remember(&self, host: &str, result: &io::Result<Vec<IpAddr>>, record_ttl: Option<Duration>) {
+        let mut state = self.lock();
+        let (ttl, stored) = match result {
+            Ok(ips) if state.ttl.is_zero() => (Duration::ZERO, Ok(ips.clone())),
+            Ok(ips) => (record_ttl.unwrap_or(state.ttl), Ok(ips.clone())),
+            Err(err) => (state.negative_ttl, Err((err.kind(), err.to_string()))),
+        };
+        if ttl.is_zero() {
+            return;
+        }
+        match state.entries.get(host) {
+            Some(entry) if entry.expires.is_none() => return,
+            Some(_) => {}
+            None => {
+                if !state.make_room() {
+                    return;
+                }
+            }
+        }
+        state.entries.insert(
+            host.to_string(),
+            DnsEntry {
+                result: stored,
+                expires: Instant::now().checked_add(ttl),
+            },
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DnsState> {
+        // The state stays consistent even if a holder panicked.
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl DnsState {
+    /// Frees a slot below `max_entries`: expired entries go first, then the
+    /// ones closest to expiry. False when only pinned entries are left.
+    fn make_room(&mut self) -> bool {
+        if self.entries.len() < self.max_entries {
+            return true;
+        }
+        let now = Instant::now();
+        self.entries
+            .retain(|_, entry| entry.expires.map_or(true, |expires| expires > now));
+        while self.entries.len() >= self.max_entries {
+            let soonest = self
+                .entries
+                .iter()
+                .filter_map(|(host, entry)| entry.expires.map(|expires| (expires, host)))
+                .min()
+                .map(|(_, host)| host.clone());
+            match soonest {
+                Some(host) => self.entries.remove(&host),
+                None => return false,
+            };
+        }
+        true
+    }
+}
+
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn ip_literal(host: &str, port: u16) -> Option<Vec<SocketAddr>> {
+    let ip = host.trim_start_matches('[').trim_end_matches(']');
+    ip.parse::<IpAddr>()
+        .ok()
+        .map(|ip| vec![SocketAddr::new(ip, port)])
+}
+
+fn with_port(ips: &[IpAddr], port: u16) -> Vec<SocketAddr> {
+    ips.iter().map(|ip| SocketAddr::new(*ip, port)).collect()
+}
@@ -0,0 +1,300 @@
+//! DNS-over-HTTPS (RFC 8484) over the crate's own HTTP/2 client.
+//!
+//! Installed on the shared [`DnsCache`](crate::DnsCache), a [`DohResolver`]
+//! sends every lookup the cache misses to the resolver encrypted:
+//!
+//! ```ignore
+//! let doh = DohResolver::new(
+//!     "https://cloudflare-dns.com/dns-query",
+//!     vec!["1.1.1.1".parse()?, "1.0.0.1".parse()?],
+//! )?;
+//! DnsCache::global().set_resolver(Arc::new(doh));
+//! ```
+//!
+//! The resolver's own host name answers from the bootstrap addresses, so
+//! connecting to it never needs a lookup of its own.
+
+use crate::dns::Resolver;
+use crate::h2::protocol::H2;
+use crate::types::{Protocol, ProtocolError, Request};
+use crate::utils::parse_target;
+use async_trait::async_trait;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+const DNS_MESSAGE: &str = "application/dns-message";
+const HEADER_LEN: usize = 12;
+const CLASS_IN: u16 = 1;
+
+/// Record types a [`DohResolver`] asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordType {
+    A,
+    Aaaa,
+}
+
+impl RecordType {
+    pub fn code(&self) -> u16 {
+        match self {
+            RecordType::A => 1,
+            RecordType::Aaaa => 28,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DohResolver {
+    url: String,
+    /// Normalized host of `url`.
+    host: String,
+    bootstrap: Vec<IpAddr>,
+    client: H2,
+    ipv6: bool,
+}
+
+impl DohResolver {
+    /// Queries the resolver at `url`, reaching its host at `bootstrap`.
+    /// `bootstrap` may be empty only when `url` names an IP address.
+    pub fn new(url: &str, bootstrap: Vec<IpAddr>) -> Result<Self, ProtocolError> {
+        let target = parse_target(url)?;
+        if target.scheme() != "https" {
+            return Err(ProtocolError::InvalidTarget(format!(
+                "DoH resolver must use https: {}",
+                url
+            )));
+        }
+        let host = target
+            .host()
+            .ok_or_else(|| ProtocolError::InvalidTarget("Target missing host".to_string()))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+        if bootstrap.is_empty() && host.parse::<IpAddr>().is_err() {
+            return Err(ProtocolError::InvalidTarget(format!(
+                "DoH resolver {} needs bootstrap addresses",
+                host
+            )));
+        }
+        Ok(Self {
+            url: url.to_string(),
+            host,
+            bootstrap,
+            client: H2::new(),
+            ipv6: true,
+        })
+    }
+
+    /// Sends the queries with `client`, e.g. to set timeouts.
+    pub fn client(mut self, client: H2) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Also asks for AAAA records; on by default.
+    pub fn ipv6(mut self, enabled: bool) -> Self {
+        self.ipv6 = enabled;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Addresses of `host` of one record type, as the resolver answers.
+    pub async fn query(&self, host: &str, record: RecordType) -> io::Result<Vec<IpAddr>> {
+        Ok(self.query_with_ttl(host, record).await?.0)
+    }
+
+    /// [`query`](Self::query) with the smallest TTL of the address records.
+    pub async fn query_with_ttl(
+        &self,
+        host: &str,
+        record: RecordType,
+    ) -> io::Result<(Vec<IpAddr>, Option<Duration>)> {
+        let request = Request::new(&self.url, "POST")
+            .map_err(to_io_error)?
+            .header(&format!("Content-Type: {}", DNS_MESSAGE))
+            .header(&format!("Accept: {}", DNS_MESSAGE))
+            .body(encode_query(host, record)?);
+        let response = self
+            .client
+            .send_request(request)
+            .await
+            .map_err(to_io_error)?;
+        if response.status != 200 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("DoH resolver answered {}", response.status),
+            ));
+        }
+        parse_answer_with_ttl(&response.body, record)
+    }
+}
+
+#[async_trait(?Send)]
+impl Resolver for DohResolver {
+    async fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<IpAddr>> {
+        Ok(self.lookup_with_ttl(host, port).await?.0)
+    }
+
+    async fn lookup_with_ttl(
+        &self,
+        host: &str,
+        _port: u16,
+    ) -> io::Result<(Vec<IpAddr>, Option<Duration>)> {
+        if host == self.host {
+            return Ok((self.bootstrap.clone(), None));
+        }
+        let mut answer = self.query_with_ttl(host, RecordType::A).await;
+        if self.ipv6 {
+            let v6 = self.query_with_ttl(host, RecordType::Aaaa).await;
+            answer = match (answer, v6) {
+                (Ok((mut v4, v4_ttl)), Ok((v6, v6_ttl))) => {
+                    v4.extend(v6);
+                    let ttl = match (v4_ttl, v6_ttl) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    };
+                    Ok((v4, ttl))
+                }
+                (Ok(v4), Err(_)) if !v4.0.is_empty() => Ok(v4),
+                (Err(_), Ok(v6)) if !v6.0.is_empty() => Ok(v6),
+                (Err(err), _) | (_, Err(err)) => Err(err),
+            };
+        }
+        answer
+    }
+}
+
+/// A recursive query for `host` in DNS wire format, with ID 0 as RFC 8484
+/// recommends for cacheable requests.
+pub fn encode_query(host: &str, record: RecordType) -> io::Result<Vec<u8>> {
+    let name = host.trim_end_matches('.');
+    let mut message = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    // ID 0, RD set, one question.
+    message.extend_from_slice(&[0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid_input(format!("Invalid DNS name: {}", host)));
+        }
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    if message.len() - HEADER_LEN > 255 {
+        return Err(invalid_input(format!("DNS name too long: {}", host)));
+    }
+    message.extend_from_slice(&record.code().to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(message)
+}
+
+/// The `record` addresses in the answer section of a DNS response. Other
+/// records, such as the CNAMEs leading to them, are skipped.
+pub fn parse_answer(message: &[u8], record: RecordType) -> io::Result<Vec<IpAddr>> {
+    Ok(parse_answer_with_ttl(message, record)?.0)
+}
+
+/// [`parse_answer`] with the smallest TTL of the address records, `None`
+/// when there are none.
+pub fn parse_answer_with_ttl(
+    message: &[u8],
+    record: RecordType,
+) -> io::Result<(Vec<IpAddr>, Option<Duration>)> {
+    if message.len() < HEADER_LEN {
+        return Err(invalid_data("DNS response shorter than its header"));
+    }
+    let flags = read_u16(message, 2)?;
+    if flags & 0x8000 == 0 {
+        return Err(invalid_data("DNS message is not a response"));
+    }
+    match flags & 0x000f {
+        0 => {}
+        3 => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no such domain (NXDOMAIN)",
+            ))
+        }
+        rcode => {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("DNS server answered with rcode {}", rcode),
+            ))
+        }
+    }
+    let questions = read_u16(message, 4)?;
+    let answers = read_u16(message, 6)?;
+
+    let mut offset = HEADER_LEN;
+    for _ in 0..questions {
+        offset = skip_name(message, offset)? + 4;
+    }
+    let mut ips = Vec::new();
+    let mut ttl: Option<u32> = None;
+    for _ in 0..answers {
+        offset = skip_name(message, offset)?;
+        let kind = read_u16(message, offset)?;
+        let class = read_u16(message, offset + 2)?;
+        let record_ttl =
+            (read_u16(message, offset + 4)? as u32) << 16 | read_u16(message, offset + 6)? as u32;
+        let length = read_u16(message, offset + 8)? as usize;
+        let start = offset + 10;
+        let data = message
+            .get(start..start + length)
+            .ok_or_else(|| invalid_data("DNS record runs past the message"))?;
+        offset = start + length;
+        if kind != record.code() || class != CLASS_IN {
+            continue;
+        }
+        match (record, data.len()) {
+            (RecordType::A, 4) => ips.push(IpAddr::V4(Ipv4Addr::new(
+                data[0], data[1], data[2], data[3],
+            ))),
+            (RecordType::Aaaa, 16) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(data);
+                ips.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => return Err(invalid_data("DNS address record of the wrong length")),
+        }
+        ttl = Some(ttl.map_or(record_ttl, |ttl| ttl.min(record_ttl)));
+    }
+    Ok((ips, ttl.map(|ttl| Duration::from_secs(ttl as u64))))
+}
+
+/// Offset just past the name at `offset`, which may end in a compression
+/// pointer.
+fn skip_name(message: &[u8], mut offset: usize) -> io::Result<usize> {
+    loop {
+        let length = *message
+            .get(offset)
+            .ok_or_else(|| invalid_data("DNS name runs past the message"))?;
+        match length {
+            0 => return Ok(offset + 1),
+            length if length & 0xc0 == 0xc0 => return Ok(offset + 2),
+            length => offset += 1 + length as usize,
+        }
+    }
+}
+
+fn read_u16(message: &[u8], offset: usize) -> io::Result<u16> {
+    message
+        .get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| invalid_data("DNS message truncated"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn to_io_error(err: ProtocolError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
@@ -0,0 +1,294 @@
+//! Degraded-network simulation for transport streams.
+//!
+//! [`FaultyStream`] wraps any tokio stream and delays, truncates or resets
+//! its traffic according to a [`FaultConfig`]. Wrap a connection's transport
+//! with [`TransportStream::with_faults`] to exercise retry and timeout logic
+//! without external tooling:
+//!
+//! ```ignore
+//! let transport = create_stream("https", "example.com", 443, None).await?
+//!     .with_faults(FaultConfig::new().latency(Duration::from_millis(200)).reset_reads_after(4096));
+//! let mut connection = H1Connection::from_stream(transport, ClientTimeouts::default());
+//! ```
+
+use crate::stream::TransportStream;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+
+/// Faults applied by a [`FaultyStream`]. Byte limits count from the moment
+/// the stream is wrapped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FaultConfig {
+    /// Delay before every read and write.
+    pub latency: Duration,
+    /// Extra random delay per operation, uniform in `0..=jitter`.
+    pub jitter: Duration,
+    /// Reads report end of stream once this many bytes were delivered.
+    pub truncate_reads_after: Option<u64>,
+    /// Writes past this many bytes are reported as sent but discarded.
+    pub truncate_writes_after: Option<u64>,
+    /// Reads fail with `ConnectionReset` once this many bytes were delivered.
+    pub reset_reads_after: Option<u64>,
+    /// Writes fail with `ConnectionReset` once this many bytes were sent.
+    pub reset_writes_after: Option<u64>,
+    /// Seed for the jitter generator so runs can be reproduced; 0 seeds from the clock.
+    pub seed: u64,
+}
+
+impl FaultConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn truncate_reads_after(mut self, bytes: u64) -> Self {
+        self.truncate_reads_after = Some(bytes);
+        self
+    }
+
+    pub fn truncate_writes_after(mut self, bytes: u64) -> Self {
+        self.truncate_writes_after = Some(bytes);
+        self
+    }
+
+    pub fn reset_reads_after(mut self, bytes: u64) -> Self {
+        self.reset_reads_after = Some(bytes);
+        self
+    }
+
+    pub fn reset_writes_after(mut self, bytes: u64) -> Self {
+        self.reset_writes_after = Some(bytes);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Stream wrapper that injects the faults described by a [`FaultConfig`].
+pub struct FaultyStream<S> {
+    inner: S,
+    config: FaultConfig,
+    rng: u64,
+    read_delay: Option<Pin<Box<Sleep>>>,
+    write_delay: Option<Pin<Box<Sleep>>>,
+    bytes_read: u64,
+    bytes_written: u64,
+}
+
+impl<S> FaultyStream<S> {
+    pub fn new(inner: S, config: FaultConfig) -> Self {
+        let seed = match config.seed {
+            0 => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0x9E37_79B9, |d| d.as_nanos() as u64),
+            seed => seed,
+        };
+        Self {
+            inner,
+            config,
+            // xorshift must not start from zero
+            rng: seed | 1,
+            read_delay: None,
+            write_delay: None,
+            bytes_read: 0,
+            bytes_written: 0,
+        }
+    }
+
+    pub fn config(&self) -> &FaultConfig {
+        &self.config
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Bytes delivered to the reader so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Bytes accepted from the writer so far, including discarded ones.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let jitter = self.config.jitter.as_nanos() as u64;
+        if jitter == 0 {
+            return self.config.latency;
+        }
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.config.latency + Duration::from_nanos(self.rng % (jitter + 1))
+    }
+
+    /// Waits out the delay for the current read or write. The timer is kept
+    /// until the operation completes so a pending inner stream is not delayed twice.
+    fn poll_delay(&mut self, cx: &mut Context<'_>, write: bool) -> Poll<()> {
+        if self.config.latency.is_zero() && self.config.jitter.is_zero() {
+            return Poll::Ready(());
+        }
+        let armed = if write {
+            self.write_delay.is_some()
+        } else {
+            self.read_delay.is_some()
+        };
+        if !armed {
+            let sleep = Box::pin(tokio::time::sleep(self.next_delay()));
+            if write {
+                self.write_delay = Some(sleep);
+            } else {
+                self.read_delay = Some(sleep);
+            }
+        }
+        let slot = if write {
+            &mut self.write_delay
+        } else {
+            &mut self.read_delay
+        };
+        match slot.as_mut() {
+            Some(sleep) => sleep.as_mut().poll(cx),
+            None => Poll::Ready(()),
+        }
+    }
+}
+
+fn connection_reset() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionReset, "injected connection reset")
+}
+
+/// Bytes left before the nearest of `limits`, if any applies.
+fn remaining_before(position: u64, limits: &[Option<u64>]) -> Option<u64> {
+    limits
+        .iter()
+        .flatten()
+        .map(|limit| limit.saturating_sub(position))
+        .min()
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for FaultyStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(limit) = this.config.reset_reads_after {
+            if this.bytes_read >= limit {
+                return Poll::Ready(Err(connection_reset()));
+            }
+        }
+        if let Some(limit) = this.config.truncate_reads_after {
+            if this.bytes_read >= limit {
+                return Poll::Ready(Ok(()));
+            }
+        }
+        ready!(this.poll_delay(cx, false));
+
+        let limits = [
+            this.config.reset_reads_after,
+            this.config.truncate_reads_after,
+        ];
+        let cap = remaining_before(this.bytes_read, &limits);
+        let result = match cap {
+            // Read through a smaller buffer so the limit lands on its exact byte.
+            Some(cap) if (cap as usize) < buf.remaining() => {
+                let mut scratch = vec![0u8; cap as usize];
+                let mut limited = ReadBuf::new(&mut scratch);
+                let result = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut limited));
+                buf.put_slice(limited.filled());
+                result.map(|()| limited.filled().len())
+            }
+            _ => {
+                let before = buf.filled().len();
+                let result = ready!(Pin::new(&mut this.inner).poll_read(cx, buf));
+                result.map(|()| buf.filled().len() - before)
+            }
+        };
+
+        this.read_delay = None;
+        let read = result?;
+        this.bytes_read += read as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for FaultyStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if let Some(limit) = this.config.reset_writes_after {
+            if this.bytes_written >= limit {
+                return Poll::Ready(Err(connection_reset()));
+            }
+        }
+        ready!(this.poll_delay(cx, true));
+
+        let mut allowed = buf.len();
+        if let Some(left) = remaining_before(this.bytes_written, &[this.config.reset_writes_after])
+        {
+            allowed = allowed.min(left as usize);
+        }
+        if let Some(left) =
+            remaining_before(this.bytes_written, &[this.config.truncate_writes_after])
+        {
+            if left == 0 {
+                this.write_delay = None;
+                this.bytes_written += allowed as u64;
+                return Poll::Ready(Ok(allowed));
+            }
+            allowed = allowed.min(left as usize);
+        }
+
+        let result = ready!(Pin::new(&mut this.inner).poll_write(cx, &buf[..allowed]));
+        this.write_delay = None;
+        let written = result?;
+        this.bytes_written += written as u64;
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl TransportStream {
+    /// Wraps this transport so its traffic suffers the faults in `config`.
+    pub fn with_faults(self, config: FaultConfig) -> TransportStream {
+        TransportStream::Faulty(Box::new(FaultyStream::new(self, config)))
+    }
+}
@@ -0,0 +1,557 @@
+//! HTTP/1.1 wire codec shared by [`H1`](super::H1) and [`H1Connection`](super::H1Connection).
+//!
+//! Everything here works on plain byte buffers, so the same serialization and
+//! parsing can be applied to data that never went through this crate's
+//! transports.
+
+use crate::proxy::proxy_authorization;
+use crate::types::{
+    decode_content, DecompressionLimits, Header, HeaderLimits, HttpProtocol, ProtocolError,
+    ProxyConfig, Request, Response, Truncation,
+};
+use crate::utils::{
+    parse_header, CHUNKED_ENCODING, CONNECTION_HEADER, CONTENT_LENGTH_HEADER, CRLF, HOST_HEADER,
+    HTTP_VERSION_1_1, TRANSFER_ENCODING_HEADER,
+};
+use bytes::Bytes;
+
+const PROXY_AUTHORIZATION_HEADER: &str = "proxy-authorization";
+
+/// Serializes a request exactly as `H1` writes it: request line, headers
+/// (adding `Host` and `Content-Length`/`Transfer-Encoding` when missing) and body.
+///
+/// Requests forwarded through an HTTP proxy also get `Proxy-Authorization`
+/// when the proxy URL carries credentials. An attached request signer runs
+/// last, over the complete header list.
+pub fn encode_request(request: &Request) -> Result<Bytes, ProtocolError> {
+    let mut req = Vec::new();
+    let path = request.request_target();
+
+    let mut headers = request.prepare_headers_for(&HttpProtocol::Http1);
+    // TODO add connection header
+    let trailers = request.trailers.clone();
+
+    req.extend_from_slice(
+        format!("{} {} {}{}", request.method, path, HTTP_VERSION_1_1, CRLF).as_bytes(),
+    );
+
+    let has_host = headers
+        .iter()
+        .any(|h| h.name.eq_ignore_ascii_case(HOST_HEADER));
+    if !has_host && request.auto_headers {
+        headers.push(Header::new(
+            HOST_HEADER.to_string(),
+            request.host_header_value(),
+        ));
+    }
+
+    if let Some(proxy_url) = request.forward_proxy() {
+        let has_proxy_auth = headers
+            .iter()
+            .any(|h| h.name.eq_ignore_ascii_case(PROXY_AUTHORIZATION_HEADER));
+        if !has_proxy_auth {
+            if let Some(auth) = proxy_authorization(&ProxyConfig::http(proxy_url.clone())) {
+                headers.push(Header::new(PROXY_AUTHORIZATION_HEADER.to_string(), auth));
+            }
+        }
+    }
+
+    let has_content_length = headers
+        .iter()
+        .any(|h| h.name.eq_ignore_ascii_case(CONTENT_LENGTH_HEADER));
+    let has_chunked = is_chunked(&headers);
+
+    let use_chunked = has_chunked || !trailers.is_empty();
+    let body_len = request.body.as_ref().map(|b| b.len());
+    let should_generate_content_length =
+        request.auto_headers && body_len.is_some() && !use_chunked && !has_content_length;
+
+    if should_generate_content_length {
+        headers.push(Header::new(
+            CONTENT_LENGTH_HEADER.to_string(),
+            body_len.unwrap().to_string(),
+        ));
+    } else if request.auto_headers && use_chunked && !has_chunked {
+        headers.push(Header::new(
+            TRANSFER_ENCODING_HEADER.to_string(),
+            CHUNKED_ENCODING.to_string(),
+        ));
+    }
+
+    request.sign_headers(&mut headers)?;
+
+    for header in &headers {
+        req.extend_from_slice(format!("{}{}", header.to_string(), CRLF).as_bytes());
+    }
+
+    req.extend_from_slice(CRLF.as_bytes());
+
+    let empty_body = Bytes::new();
+    if use_chunked {
+        let body = request.body.as_ref().unwrap_or(&empty_body);
+        req.extend_from_slice(&encode_chunked_body(body, trailers.as_slice()));
+    } else if let Some(body) = request.body.as_ref() {
+        req.extend_from_slice(body);
+    }
+
+    Ok(Bytes::from(req))
+}
+
+/// Encodes `body` as a single chunk followed by the last chunk and `trailers`.
+pub fn encode_chunked_body(body: &Bytes, trailers: &[Header]) -> Vec<u8> {
+    let mut chunked_body = Vec::new();
+
+    if !body.is_empty() {
+        let chunk_size = format!("{:x}{}", body.len(), CRLF);
+        chunked_body.extend_from_slice(chunk_size.as_bytes());
+        chunked_body.extend_from_slice(body);
+        chunked_body.extend_from_slice(CRLF.as_bytes());
+    }
+
+    let final_chunk = format!("0{}", CRLF);
+    chunked_body.extend_from_slice(final_chunk.as_bytes());
+
+    for trailer in trailers {
+        let mut trailer_line = format!("{}{}", trailer.to_string(), CRLF);
+        if matches!(trailer.to_string().as_str(), "\n" | "\r" | "\r\n") {
+            trailer_line = trailer.to_string();
+        }
+        chunked_body.extend_from_slice(trailer_line.as_bytes());
+    }
+
+    chunked_body.extend_from_slice(CRLF.as_bytes());
+
+    chunked_body
+}
+
+/// Decodes the first response in `buf`.
+///
+/// The buffer is treated as everything the server sent, so a body without
+/// `Content-Length` or chunked framing extends to the end of `buf`. Pass
+/// `read_body = false` for responses to HEAD.
+pub fn decode_response(buf: &[u8], read_body: bool) -> Result<Response, ProtocolError> {
+    match decode(buf, read_body, true)? {
+        Some((response, _)) => Ok(response),
+        None => Err(ProtocolError::InvalidResponse(
+            "Incomplete HTTP/1.1 response".to_string(),
+        )),
+    }
+}
+
+/// Incrementally parses the first response in `buf`.
+///
+/// Returns the response and the number of bytes it occupied, or `Ok(None)`
+/// when `buf` ends before the response does; append more data and call again.
+/// Any bytes past `consumed` belong to the next (pipelined) response.
+///
+/// A response delimited only by connection close never completes here; once
+/// the peer has closed, hand the remaining bytes to [`decode_response`].
+pub fn parse_response(
+    buf: &[u8],
+    read_body: bool,
+) -> Result<Option<(Response, usize)>, ProtocolError> {
+    decode(buf, read_body, false)
+}
+
+/// Core parser: `Ok(None)` means `buf` ends before the response does. With
+/// `eof` set the data is final, so a truncated message is an error instead.
+fn decode(
+    buf: &[u8],
+    read_body: bool,
+    eof: bool,
+) -> Result<Option<(Response, usize)>, ProtocolError> {
+    let mut pos = 0;
+    loop {
+        let status_line = match next_line(buf, &mut pos) {
+            Some(line) => line,
+            None if eof && buf[pos..].iter().all(|b| b.is_ascii_whitespace()) => {
+                return Err(ProtocolError::ConnectionFailed(
+                    "Connection closed by server before receiving response".to_string(),
+                ))
+            }
+            None => return incomplete(eof),
+        };
+        if status_line.trim().is_empty() {
+            continue;
+        }
+
+        let (status, protocol) = parse_status_line(&status_line)?;
+        let headers = match read_header_lines(buf, &mut pos) {
+            Some(headers) => headers,
+            None => return incomplete(eof),
+        };
+
+        let (body, trailers) = if !read_body || !response_has_body(status) {
+            (Bytes::new(), Vec::new())
+        } else {
+            match response_framing(&headers) {
+                BodyFraming::Chunked => match decode_chunked_body(buf, &mut pos)? {
+                    Some(decoded) => decoded,
+                    None => return incomplete(eof),
+                },
+                BodyFraming::Length(length) => {
+                    if buf.len() - pos < length {
+                        return incomplete(eof);
+                    }
+                    let body = Bytes::copy_from_slice(&buf[pos..pos + length]);
+                    pos += length;
+                    (body, Vec::new())
+                }
+                BodyFraming::Close if eof => {
+                    let body = Bytes::copy_from_slice(&buf[pos..]);
+                    pos = buf.len();
+                    (body, Vec::new())
+                }
+                BodyFraming::Close => return Ok(None),
+            }
+        };
+        let (body, transfer_codings) =
+            remove_transfer_codings(&headers, body, &DecompressionLimits::default());
+
+        let cookies = Response::collect_cookies(&headers);
+        let response = Response {
+            status,
+            protocol,
+            headers,
+            body,
+            trailers: if trailers.is_empty() {
+                None
+            } else {
+                Some(trailers)
+            },
+            frames: None,
+            header_blocks: None,
+            cookies,
+            decoded_body: None,
+            frame_times: None,
+            digest: None,
+            transfer_codings,
+            truncated: false,
+            truncation: None,
+        };
+        return Ok(Some((response, pos)));
+    }
+}
+
+fn incomplete<T>(eof: bool) -> Result<Option<T>, ProtocolError> {
+    if eof {
+        Err(ProtocolError::InvalidResponse(
+            "Response truncated before end of message".to_string(),
+        ))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns the next LF-terminated line (terminator included) and advances `pos`.
+fn next_line(buf: &[u8], pos: &mut usize) -> Option<String> {
+    let rest = &buf[*pos..];
+    let end = rest.iter().position(|&b| b == b'\n')? + 1;
+    *pos += end;
+    Some(String::from_utf8_lossy(&rest[..end]).into_owned())
+}
+
+fn read_header_lines(buf: &[u8], pos: &mut usize) -> Option<Vec<Header>> {
+    let mut headers = Vec::new();
+    loop {
+        let line = next_line(buf, pos)?;
+        if line.trim().is_empty() {
+            return Some(headers);
+        }
+        if let Some(header) = parse_header(line.trim()) {
+            headers.push(header);
+        }
+    }
+}
+
+fn decode_chunked_body(
+    buf: &[u8],
+    pos: &mut usize,
+) -> Result<Option<(Bytes, Vec<Header>)>, ProtocolError> {
+    let mut decoder = ChunkedDecoder::new(HeaderLimits::default());
+    let mut body = Vec::new();
+    while !decoder.is_done() {
+        let rest = &buf[*pos..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        match decoder.step(rest)? {
+            ChunkedStep::Data(len) => {
+                body.extend_from_slice(&rest[..len]);
+                *pos += len;
+            }
+            ChunkedStep::Framing(len) => *pos += len,
+        }
+    }
+    Ok(Some((Bytes::from(body), decoder.take_trailers())))
+}
+
+/// What [`ChunkedDecoder::step`] took from the front of its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChunkedStep {
+    /// That many bytes of body data.
+    Data(usize),
+    /// That many bytes of chunk sizes, CRLFs or trailers, already handled.
+    Framing(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkedState {
+    Size,
+    Data { size: usize, left: usize },
+    DataEnd,
+    Trailers,
+    Done,
+}
+
+/// Incremental parser for a chunked body (RFC 9112 §7.1), shared by every
+/// HTTP/1.1 read path. It is handed whatever bytes have arrived and only
+/// keeps the framing line it is in the middle of, so a caller can stream
+/// body data straight out of its own buffer.
+#[derive(Debug, Clone)]
+pub(crate) struct ChunkedDecoder {
+    state: ChunkedState,
+    limits: HeaderLimits,
+    line: Vec<u8>,
+    trailers: Vec<Header>,
+}
+
+impl ChunkedDecoder {
+    /// Size and trailer lines are held to `limits.max_line`.
+    pub(crate) fn new(limits: HeaderLimits) -> Self {
+        Self {
+            state: ChunkedState::Size,
+            limits,
+            line: Vec::new(),
+            trailers: Vec::new(),
+        }
+    }
+
+    /// Takes at least one byte from the front of `input`, which must not be
+    /// empty, and says what they were.
+    pub(crate) fn step(&mut self, input: &[u8]) -> Result<ChunkedStep, ProtocolError> {
+        if let ChunkedState::Data { size, left } = self.state {
+            let len = left.min(input.len());
+            self.state = if len == left {
+                ChunkedState::DataEnd
+            } else {
+                ChunkedState::Data {
+                    size,
+                    left: left - len,
+                }
+            };
+            return Ok(ChunkedStep::Data(len));
+        }
+
+        let (len, complete) = match input.iter().position(|&b| b == b'\n') {
+            Some(end) => (end + 1, true),
+            None => (input.len(), false),
+        };
+        self.line.extend_from_slice(&input[..len]);
+        self.limits.check_line(self.line.len())?;
+        if complete {
+            let line = std::mem::take(&mut self.line);
+            self.end_line(&line)?;
+        }
+        Ok(ChunkedStep::Framing(len))
+    }
+
+    fn end_line(&mut self, line: &[u8]) -> Result<(), ProtocolError> {
+        match self.state {
+            ChunkedState::Size => {
+                let size = parse_chunk_size(&String::from_utf8_lossy(line))?;
+                self.state = if size == 0 {
+                    ChunkedState::Trailers
+                } else {
+                    ChunkedState::Data { size, left: size }
+                };
+            }
+            ChunkedState::DataEnd => {
+                if line != b"\r\n" {
+                    return Err(ProtocolError::InvalidResponse(
+                        "Chunk data not followed by CRLF".to_string(),
+                    ));
+                }
+                self.state = ChunkedState::Size;
+            }
+            ChunkedState::Trailers => {
+                let line = String::from_utf8_lossy(line);
+                if line.trim().is_empty() {
+                    self.state = ChunkedState::Done;
+                } else if let Some(trailer) = parse_header(line.trim()) {
+                    self.trailers.push(trailer);
+                }
+            }
+            ChunkedState::Data { .. } | ChunkedState::Done => {}
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.state == ChunkedState::Done
+    }
+
+    /// Whether the last chunk has been read and only trailers may follow.
+    pub(crate) fn in_trailers(&self) -> bool {
+        self.state == ChunkedState::Trailers
+    }
+
+    pub(crate) fn take_trailers(&mut self) -> Vec<Header> {
+        std::mem::take(&mut self.trailers)
+    }
+
+    /// Where the body stopped, for a connection that closed before its end.
+    pub(crate) fn truncation(&self) -> Truncation {
+        match self.state {
+            ChunkedState::Data { size, left } => Truncation::Chunk {
+                size,
+                received: size - left,
+            },
+            _ => Truncation::LastChunk,
+        }
+    }
+}
+
+pub fn parse_status_line(status_line: &str) -> Result<(u16, String), ProtocolError> {
+    // TODO what if it is HTTP/0.9
+    let parts: Vec<&str> = status_line.trim().split_whitespace().collect();
+    if parts.len() < 2 {
+        return Err(ProtocolError::InvalidResponse(
+            "Invalid status line".to_string(),
+        ));
+    }
+
+    let protocol = parts[0].to_string();
+    let status_code = parts[1]
+        .parse::<u16>()
+        .map_err(|_| ProtocolError::InvalidResponse("Invalid status code".to_string()))?;
+
+    Ok((status_code, protocol))
+}
+
+/// Parses a chunk-size line, ignoring chunk extensions.
+pub(crate) fn parse_chunk_size(line: &str) -> Result<usize, ProtocolError> {
+    let size_str = line.trim().split(';').next().unwrap_or(" ").trim();
+    usize::from_str_radix(size_str, 16)
+        .map_err(|_| ProtocolError::InvalidResponse("Invalid chunk size".to_string()))
+}
+
+// TODO write better
+pub(crate) fn response_has_body(status: u16) -> bool {
+    if (100..200).contains(&status) {
+        return false;
+    }
+
+    !matches!(status, 204 | 205 | 304)
+}
+
+pub(crate) fn is_chunked(headers: &[Header]) -> bool {
+    headers.iter().any(|h| {
+        h.name.eq_ignore_ascii_case(TRANSFER_ENCODING_HEADER)
+            && h.value
+                .as_ref()
+                .map_or(false, |v| v.to_lowercase().contains(CHUNKED_ENCODING))
+    })
+}
+
+/// Codings from every `Transfer-Encoding` line, lowercased and in the
+/// order they were applied, parameters dropped.
+pub(crate) fn transfer_codings(headers: &[Header]) -> Vec<String> {
+    headers
+        .iter()
+        .filter(|h| h.name.eq_ignore_ascii_case(TRANSFER_ENCODING_HEADER))
+        .filter_map(|h| h.value.as_deref())
+        .flat_map(|value| value.split(','))
+        .map(|coding| {
+            let name = coding.split(';').next().unwrap_or_default();
+            name.trim().to_ascii_lowercase()
+        })
+        .filter(|coding| !coding.is_empty())
+        .collect()
+}
+
+/// True if the connection may carry another request after this exchange
+/// (RFC 9112 §9.3): HTTP/1.1 unless either side sent `Connection: close`,
+/// HTTP/1.0 only if the response sent `Connection: keep-alive`.
+pub(crate) fn keeps_alive(
+    request_headers: &[Header],
+    protocol: &str,
+    response_headers: &[Header],
+) -> bool {
+    if has_connection_option(request_headers, "close")
+        || has_connection_option(response_headers, "close")
+    {
+        return false;
+    }
+    protocol.eq_ignore_ascii_case(HTTP_VERSION_1_1)
+        || has_connection_option(response_headers, "keep-alive")
+}
+
+fn has_connection_option(headers: &[Header], option: &str) -> bool {
+    headers
+        .iter()
+        .filter(|h| h.name.eq_ignore_ascii_case(CONNECTION_HEADER))
+        .filter_map(|h| h.value.as_deref())
+        .flat_map(|value| value.split(','))
+        .any(|token| token.trim().eq_ignore_ascii_case(option))
+}
+
+/// How a response body is delimited (RFC 9112 §6.3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BodyFraming {
+    Chunked,
+    Length(usize),
+    /// Read until the connection closes. Also used when `Transfer-Encoding`
+    /// is present but does not end in `chunked`.
+    Close,
+}
+
+pub(crate) fn response_framing(headers: &[Header]) -> BodyFraming {
+    let codings = transfer_codings(headers);
+    match codings.last() {
+        Some(last) if last == CHUNKED_ENCODING => BodyFraming::Chunked,
+        Some(_) => BodyFraming::Close,
+        None => match content_length(headers) {
+            Some(length) => BodyFraming::Length(length),
+            None => BodyFraming::Close,
+        },
+    }
+}
+
+/// Undoes the transfer codings of a body whose framing has been read,
+/// last coding first, and returns the codings that were removed in header
+/// order. `chunked` counts as removed when it ends the list. Decoding stops
+/// at the first coding that is unsupported, does not decode or exceeds
+/// `limits`, leaving the body encoded from there on.
+pub(crate) fn remove_transfer_codings(
+    headers: &[Header],
+    body: Bytes,
+    limits: &DecompressionLimits,
+) -> (Bytes, Vec<String>) {
+    let mut codings = transfer_codings(headers);
+    let mut applied = Vec::new();
+    if codings.last().map(String::as_str) == Some(CHUNKED_ENCODING) {
+        applied.extend(codings.pop());
+    }
+    let mut body = body;
+    while let Some(coding) = codings.pop() {
+        if coding == CHUNKED_ENCODING {
+            break;
+        }
+        match decode_content(&coding, &body, limits) {
+            Ok(Some(decoded)) => body = decoded,
+            Ok(None) | Err(_) => break,
+        }
+        applied.push(coding);
+    }
+    applied.reverse();
+    (body, applied)
+}
+
+pub(crate) fn content_length(headers: &[Header]) -> Option<usize> {
+    headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case(CONTENT_LENGTH_HEADER))
+        .and_then(|h| h.value.as_ref())
+        .and_then(|v| v.parse::<usize>().ok())
+}
@@ -1,10 +1,20 @@
+mod body;
+mod mirror;
+
 use async_trait::async_trait;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader, ReadBuf};
 
 use crate::connection::HttpConnection;
+use crate::h1::codec;
 use crate::h1::protocol::H1;
-use crate::stream::{create_stream, TransportStream};
-use crate::types::{ClientTimeouts, ProtocolError, Response};
+use crate::handshake::{HandshakePhase, HandshakeTracker};
+use crate::stream::{create_traced_stream, create_unix_stream, TransportStream};
+use crate::types::{ClientTimeouts, ProtocolError, Request, Response, SocketOptions};
 use crate::utils::{parse_target, timeout_result};
+use crate::wire::{WireDirection, WireMirror};
 
 /// Options required to establish an HTTP/1.1 connection.
 #[derive(Debug, Clone)]
@@ -19,9 +29,54 @@ pub struct H1ConnectOptions {
 pub struct H1Connection {
     client: H1,
     stream: TransportStream,
+    stats: H1ConnectionStats,
+    /// Bytes read past the end of the last response: the start of the
+    /// next pipelined or keep-alive response.
+    read_ahead: Vec<u8>,
+    handshake: HandshakeTracker,
+    /// Copy of the raw bytes going in and out, see `set_wire_mirror`.
+    wire_mirror: WireMirror,
+}
+
+/// Counters returned by [`H1Connection::stats`].
+///
+/// Only traffic that goes through the connection's own methods is counted;
+/// writes made directly on [`H1Connection::stream_mut`] are invisible to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct H1ConnectionStats {
+    pub requests_sent: u64,
+    pub responses_received: u64,
+    pub wire_bytes_sent: u64,
+    /// Includes read-ahead buffered past the end of a response.
+    pub wire_bytes_received: u64,
+    /// Request bodies as they were before chunked framing.
+    pub payload_bytes_sent: u64,
+    /// Response bodies after de-chunking.
+    pub payload_bytes_received: u64,
 }
 
 impl H1Connection {
+    /// Wraps an already established transport, e.g. one from
+    /// [`TransportStream::with_faults`].
+    pub fn from_stream(stream: TransportStream, timeouts: ClientTimeouts) -> Self {
+        Self::from_client(H1::timeouts(timeouts), stream)
+    }
+
+    /// Wraps a transport `client` opened, reading and writing with its
+    /// config.
+    pub(crate) fn from_client(client: H1, stream: TransportStream) -> Self {
+        let handshake = HandshakeTracker::new();
+        handshake.enter(HandshakePhase::Ready);
+        Self {
+            client,
+            stream,
+            stats: H1ConnectionStats::default(),
+            read_ahead: Vec::new(),
+            handshake,
+            wire_mirror: WireMirror::default(),
+        }
+    }
+
     pub fn client(&self) -> &H1 {
         &self.client
     }
@@ -30,17 +85,34 @@ impl H1Connection {
         &mut self.client
     }
 
+    /// Reads made directly on the stream miss anything in
+    /// [`read_ahead`](Self::read_ahead).
     pub fn stream_mut(&mut self) -> &mut TransportStream {
         &mut self.stream
     }
-}
 
-#[async_trait(?Send)]
-impl HttpConnection for H1Connection {
-    type ConnectOptions = H1ConnectOptions;
-    type ReadOptions = bool;
+    /// Bytes already received that belong to the next response; the next
+    /// `read_response` starts with them.
+    pub fn read_ahead(&self) -> &[u8] {
+        &self.read_ahead
+    }
 
-    async fn connect(options: Self::ConnectOptions) -> Result<Self, ProtocolError> {
+    pub fn stats(&self) -> H1ConnectionStats {
+        self.stats
+    }
+
+    /// Phases of the handshake that set up the connection, with when each
+    /// started.
+    pub fn handshake(&self) -> &HandshakeTracker {
+        &self.handshake
+    }
+
+    /// Connects like [`HttpConnection::connect`], recording each phase of
+    /// the handshake in `tracker` as it starts.
+    pub async fn connect_traced(
+        options: H1ConnectOptions,
+        tracker: HandshakeTracker,
+    ) -> Result<Self, ProtocolError> {
         let H1ConnectOptions { target, timeouts } = options;
 
         let parsed_target = parse_target(&target)?;
@@ -53,30 +125,122 @@ impl HttpConnection for H1Connection {
         let scheme = parsed_target.scheme().to_string();
         let host_owned = host.to_string();
         let connect_timeout = timeouts.connect;
+        let stream_tracker = tracker.clone();
 
         let stream = timeout_result(connect_timeout, async move {
-            create_stream(&scheme, &host_owned, port, connect_timeout)
-                .await
-                .map_err(|e| ProtocolError::ConnectionFailed(e.to_string()))
+            match &parsed_target.unix_socket {
+                Some(socket) => create_unix_stream(socket, connect_timeout).await,
+                None => {
+                    create_traced_stream(
+                        &scheme,
+                        &host_owned,
+                        port,
+                        connect_timeout,
+                        &SocketOptions::default(),
+                        &stream_tracker,
+                    )
+                    .await
+                }
+            }
+            .map_err(ProtocolError::from_connect_error)
         })
         .await?;
+        tracker.enter(HandshakePhase::Ready);
 
         Ok(Self {
             client: H1::timeouts(timeouts),
             stream,
+            stats: H1ConnectionStats::default(),
+            read_ahead: Vec::new(),
+            handshake: tracker,
+            wire_mirror: WireMirror::default(),
         })
     }
 
+    /// Serializes and writes `request`, counting it in [`stats`](Self::stats).
+    pub async fn write_request(&mut self, request: &Request) -> Result<(), ProtocolError> {
+        let encoded = codec::encode_request(request)?;
+        self.write_raw(&encoded).await?;
+        self.stats.requests_sent += 1;
+        self.stats.payload_bytes_sent += request.body.as_ref().map_or(0, |b| b.len()) as u64;
+        Ok(())
+    }
+
+    /// Writes bytes verbatim, e.g. a hand-crafted request; they are on the
+    /// wire when this returns.
+    pub async fn write_raw(&mut self, data: &[u8]) -> Result<(), ProtocolError> {
+        let write_timeout = self.client.get_timeouts().write;
+        self.client
+            .write_to_stream(&mut self.stream, data, write_timeout)
+            .await?;
+        self.stats.wire_bytes_sent += data.len() as u64;
+        self.wire_mirror.record(None, WireDirection::Sent, data);
+        Ok(())
+    }
+}
+
+/// Reader adapter that adds every byte it yields to a counter, and mirrors
+/// it if given a mirror.
+pub(crate) struct CountingReader<'a, R> {
+    pub(crate) inner: R,
+    pub(crate) count: &'a mut u64,
+    pub(crate) mirror: Option<&'a mut WireMirror>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CountingReader<'_, R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            *this.count += (buf.filled().len() - before) as u64;
+            if let Some(mirror) = this.mirror.as_mut() {
+                mirror.record(None, WireDirection::Received, &buf.filled()[before..]);
+            }
+        }
+        poll
+    }
+}
+
+#[async_trait(?Send)]
+impl HttpConnection for H1Connection {
+    type ConnectOptions = H1ConnectOptions;
+    type ReadOptions = bool;
+
+    async fn connect(options: Self::ConnectOptions) -> Result<Self, ProtocolError> {
+        Self::connect_traced(options, HandshakeTracker::new()).await
+    }
+
     async fn read_response(
         &mut self,
         read_body: Self::ReadOptions,
     ) -> Result<Response, ProtocolError> {
-        self.client
-            .read_response(
-                &mut self.stream,
-                read_body,
-                self.client.get_timeouts(),
-            )
-            .await
+        let count = &mut self.stats.wire_bytes_received;
+        let timeouts = self.client.get_timeouts();
+        let socket = CountingReader {
+            inner: &mut self.stream,
+            count,
+            mirror: Some(&mut self.wire_mirror),
+        };
+        let read_ahead = std::mem::take(&mut self.read_ahead);
+        let mut reader = BufReader::new(read_ahead.as_slice().chain(socket));
+        let result = self
+            .client
+            .read_response_buffered(&mut reader, read_body, timeouts, None)
+            .await;
+        // The reader stops at the end of the response; what it buffered or
+        // has yet to hand out from the old read-ahead begins the next one.
+        let mut rest = reader.buffer().to_vec();
+        let (unread, _) = reader.into_inner().into_inner();
+        rest.extend_from_slice(unread);
+        self.read_ahead = rest;
+        let response = result?;
+        self.stats.responses_received += 1;
+        self.stats.payload_bytes_received += response.body.len() as u64;
+        Ok(response)
     }
 }
@@ -0,0 +1,234 @@
+use super::{CountingReader, H1Connection};
+use crate::h1::codec::{self, ChunkedDecoder, ChunkedStep};
+use crate::h1::protocol::{is_close_notify, unexpected_eof};
+use crate::types::{
+    BodyConnection, BodySource, Header, ProtocolError, ResponseBody, StreamingResponse, TimeoutKind,
+};
+use crate::utils::{phase_timeout, time_remaining};
+use crate::wire::WireDirection;
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, BufReader};
+
+impl H1Connection {
+    /// Reads the response head and returns without reading the body, which
+    /// comes off the connection as the returned body is consumed. Chunked
+    /// framing is removed, other transfer codings are left in place. Once
+    /// the body has been read to its end the connection is ready for the
+    /// next response.
+    pub async fn read_response_streaming(
+        &mut self,
+        read_body: bool,
+    ) -> Result<StreamingResponse<'_>, ProtocolError> {
+        let head = self.read_streaming_head(read_body).await?;
+        Ok(head.into_response(BodyConnection::Borrowed(self)))
+    }
+
+    /// Like [`read_response_streaming`](Self::read_response_streaming), with
+    /// the body taking the connection along; it is closed once the body is
+    /// dropped.
+    pub async fn into_response_streaming(
+        self,
+        read_body: bool,
+    ) -> Result<StreamingResponse<'static>, ProtocolError> {
+        let mut connection = Box::new(self);
+        let head = connection.read_streaming_head(read_body).await?;
+        Ok(head.into_response(BodyConnection::Owned(connection)))
+    }
+
+    async fn read_streaming_head(&mut self, read_body: bool) -> Result<Head, ProtocolError> {
+        let timeouts = self.client.get_timeouts();
+        let socket = CountingReader {
+            inner: &mut self.stream,
+            count: &mut self.stats.wire_bytes_received,
+            mirror: Some(&mut self.wire_mirror),
+        };
+        let read_ahead = std::mem::take(&mut self.read_ahead);
+        let mut reader = BufReader::new(read_ahead.as_slice().chain(socket));
+        let result = self.client.read_head(&mut reader, timeouts, None).await;
+        let mut rest = reader.buffer().to_vec();
+        let (unread, _) = reader.into_inner().into_inner();
+        rest.extend_from_slice(unread);
+        self.read_ahead = rest;
+        let (status, protocol, headers) = result?;
+        self.stats.responses_received += 1;
+
+        let framing = if read_body && codec::response_has_body(status) {
+            Framing::of(&headers)
+        } else {
+            Framing::Done
+        };
+        Ok(Head {
+            status,
+            protocol,
+            headers,
+            framing,
+            decoder: ChunkedDecoder::new(self.client.get_config().header_limits),
+            deadline: timeouts.body_total_timeout().map(|d| Instant::now() + d),
+        })
+    }
+}
+
+/// A response head read by `read_streaming_head`, waiting for the
+/// connection its body is read from.
+struct Head {
+    status: u16,
+    protocol: String,
+    headers: Vec<Header>,
+    framing: Framing,
+    decoder: ChunkedDecoder,
+    deadline: Option<Instant>,
+}
+
+impl Head {
+    fn into_response<'a>(
+        self,
+        connection: BodyConnection<'a, H1Connection>,
+    ) -> StreamingResponse<'a> {
+        StreamingResponse {
+            status: self.status,
+            protocol: self.protocol,
+            headers: self.headers,
+            body: ResponseBody::new(H1Body {
+                connection,
+                framing: self.framing,
+                decoder: self.decoder,
+                trailers: Vec::new(),
+                deadline: self.deadline,
+            }),
+        }
+    }
+}
+
+/// Where an [`H1Body`] is within the body's framing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    /// Bytes left of a `Content-Length` body.
+    Length(usize),
+    /// Chunks, parsed by the body's [`ChunkedDecoder`].
+    Chunked,
+    /// Everything until the connection closes.
+    Close,
+    Done,
+}
+
+impl Framing {
+    fn of(headers: &[Header]) -> Self {
+        match codec::response_framing(headers) {
+            codec::BodyFraming::Chunked => Framing::Chunked,
+            codec::BodyFraming::Length(length) => Framing::Length(length),
+            codec::BodyFraming::Close => Framing::Close,
+        }
+    }
+}
+
+/// Reads a response body off an [`H1Connection`], through its read-ahead
+/// buffer, so whatever follows the body is left for the next response.
+struct H1Body<'a> {
+    connection: BodyConnection<'a, H1Connection>,
+    framing: Framing,
+    decoder: ChunkedDecoder,
+    trailers: Vec<Header>,
+    deadline: Option<Instant>,
+}
+
+#[async_trait(?Send)]
+impl BodySource for H1Body<'_> {
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, ProtocolError>> {
+        let remaining = time_remaining(self.deadline);
+        match phase_timeout(remaining, TimeoutKind::BodyTotal, self.read_chunk()).await {
+            Ok(chunk) => chunk.map(Ok),
+            Err(e) => {
+                self.framing = Framing::Done;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn trailers(&mut self) -> Option<Vec<Header>> {
+        if self.trailers.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.trailers))
+        }
+    }
+}
+
+impl H1Body<'_> {
+    async fn read_chunk(&mut self) -> Result<Option<Bytes>, ProtocolError> {
+        loop {
+            match self.framing {
+                Framing::Done => return Ok(None),
+                Framing::Length(0) => self.framing = Framing::Done,
+                Framing::Length(left) => {
+                    let chunk = self.take(left).await?;
+                    self.framing = Framing::Length(left - chunk.len());
+                    return Ok(Some(chunk));
+                }
+                Framing::Chunked if self.decoder.is_done() => {
+                    self.trailers = self.decoder.take_trailers();
+                    self.framing = Framing::Done;
+                }
+                Framing::Chunked => {
+                    if self.connection.read_ahead.is_empty() && self.fill().await? == 0 {
+                        // The connection ended early. Missing trailers are no loss.
+                        if !self.decoder.in_trailers() {
+                            return Err(unexpected_eof(None));
+                        }
+                        self.trailers = self.decoder.take_trailers();
+                        self.framing = Framing::Done;
+                        continue;
+                    }
+                    match self.decoder.step(&self.connection.read_ahead)? {
+                        ChunkedStep::Data(len) => return self.take(len).await.map(Some),
+                        ChunkedStep::Framing(len) => {
+                            self.connection.read_ahead.drain(..len);
+                        }
+                    }
+                }
+                Framing::Close => {
+                    if self.connection.read_ahead.is_empty() && self.fill().await? == 0 {
+                        self.framing = Framing::Done;
+                        return Ok(None);
+                    }
+                    return self.take(usize::MAX).await.map(Some);
+                }
+            }
+        }
+    }
+
+    /// Up to `max` body bytes, reading more only when none are buffered.
+    async fn take(&mut self, max: usize) -> Result<Bytes, ProtocolError> {
+        if self.connection.read_ahead.is_empty() && self.fill().await? == 0 {
+            return Err(unexpected_eof(None));
+        }
+        let len = max.min(self.connection.read_ahead.len());
+        let chunk: Vec<u8> = self.connection.read_ahead.drain(..len).collect();
+        self.connection.stats.payload_bytes_received += len as u64;
+        Ok(Bytes::from(chunk))
+    }
+
+    /// Reads from the socket into the read-ahead buffer; 0 at EOF.
+    async fn fill(&mut self) -> Result<usize, ProtocolError> {
+        let idle_timeout = self.connection.client.get_timeouts().body_idle_timeout();
+        let stream = &mut self.connection.stream;
+        let mut buffer = [0u8; 8192];
+        let read = phase_timeout(idle_timeout, TimeoutKind::BodyIdle, async {
+            match stream.read(&mut buffer).await {
+                Ok(n) => Ok(n),
+                Err(e) if is_close_notify(&e) => Ok(0),
+                Err(e) => Err(ProtocolError::Io(e)),
+            }
+        })
+        .await?;
+        self.connection
+            .read_ahead
+            .extend_from_slice(&buffer[..read]);
+        self.connection.stats.wire_bytes_received += read as u64;
+        self.connection
+            .wire_mirror
+            .record(None, WireDirection::Received, &buffer[..read]);
+        Ok(read)
+    }
+}
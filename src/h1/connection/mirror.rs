@@ -0,0 +1,22 @@
+use super::H1Connection;
+use crate::wire::{WireChunk, WireSink};
+use tokio::sync::mpsc;
+
+impl H1Connection {
+    /// Mirrors every byte the connection writes or reads from now on to
+    /// `mirror`, replacing an earlier one; `None` stops mirroring. Bytes
+    /// written or read directly on [`stream_mut`](Self::stream_mut) are not
+    /// seen.
+    pub fn set_wire_mirror(&mut self, mirror: Option<Box<dyn WireSink>>) {
+        self.wire_mirror.set(mirror);
+    }
+
+    /// Mirrors every byte from now on into a channel.
+    pub fn mirror_wire(&mut self) -> mpsc::UnboundedReceiver<WireChunk> {
+        self.wire_mirror.channel()
+    }
+
+    pub fn is_mirroring_wire(&self) -> bool {
+        self.wire_mirror.is_active()
+    }
+}
@@ -1,5 +1,7 @@
+pub mod codec;
 pub mod connection;
 pub mod protocol;
 
-pub use connection::{H1ConnectOptions, H1Connection};
+pub use codec::{decode_response, encode_request, parse_response};
+pub use connection::{H1ConnectOptions, H1Connection, H1ConnectionStats};
 pub use protocol::H1;
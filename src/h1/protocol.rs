@@ -1,16 +1,28 @@
-use crate::stream::{create_stream, TransportStream};
-use crate::types::{ClientTimeouts, Header, Protocol, ProtocolError, Request, Response};
-use crate::utils::{
-    timeout_result, CHUNKED_ENCODING, CONTENT_LENGTH_HEADER, CRLF, HOST_HEADER, HTTP_VERSION_1_1,
-    TRANSFER_ENCODING_HEADER,
+use crate::h1::codec;
+use crate::h1::connection::{CountingReader, H1Connection};
+use crate::hedge::is_idempotent;
+use crate::pool::{pool_key, ConnectionPool, PoolControl};
+use crate::stream::{
+    create_guarded_stream, create_stream_with_options, create_unix_stream, TransportStream,
 };
+use crate::types::{
+    is_interim_status, ClientConfig, ClientTimeouts, DecompressionLimits, Header, HeaderLimits,
+    HttpProtocol, InformationalResponse, Protocol, ProtocolError, Request, Response,
+    StreamingResponse, TimeoutKind, Truncation, UserAgent,
+};
+use crate::utils::{phase_timeout, time_remaining, timeout_result};
 use async_trait::async_trait;
 use bytes::Bytes;
-use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use url::Url;
 
 #[derive(Clone)]
 pub struct H1 {
-    timeouts: ClientTimeouts,
+    config: ClientConfig,
+    pool: Arc<ConnectionPool<TransportStream>>,
+    keep_alive: bool,
 }
 
 impl H1 {
@@ -19,30 +31,191 @@ impl H1 {
     }
 
     pub fn timeouts(timeouts: ClientTimeouts) -> Self {
-        Self { timeouts }
+        Self::with_config(ClientConfig::new().timeouts(timeouts))
+    }
+
+    pub fn with_config(config: ClientConfig) -> Self {
+        Self {
+            config,
+            pool: Arc::new(ConnectionPool::default()),
+            keep_alive: true,
+        }
+    }
+
+    /// With `false`, every request gets a connection of its own and the
+    /// connection is dropped with the response; parked connections from
+    /// `preconnect` are still used.
+    pub fn with_keep_alive(mut self, keep_alive: bool) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// `User-Agent` for requests that do not pick their own.
+    pub fn with_user_agent(mut self, user_agent: UserAgent) -> Self {
+        self.config.user_agent = user_agent;
+        self
     }
 
     pub fn get_timeouts(&self) -> &ClientTimeouts {
-        &self.timeouts
+        &self.config.timeouts
+    }
+
+    pub fn get_config(&self) -> &ClientConfig {
+        &self.config
     }
 
     pub fn session(&self) -> crate::session::H1Session {
         crate::session::H1Session::new(self.clone())
     }
 
+    /// Connections parked by [`preconnect`](Self::preconnect) or kept alive
+    /// after a response, shared with clones of this client. Connections
+    /// through a proxy are parked per proxy, e.g. under
+    /// `https://example.com:443 via http://proxy:8080`.
+    pub fn pool(&self) -> &ConnectionPool<TransportStream> {
+        &self.pool
+    }
+
+    /// Connects to `origin`, TLS included, and parks the connection for the
+    /// next request there.
+    pub async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        let mut request = Request::new(origin, "GET")?;
+        self.config.apply(&mut request);
+        if let Some(scope) = &self.config.host_scope {
+            scope.check(&request.target).await?;
+        }
+        let key = h1_pool_key(&request).ok_or_else(|| {
+            ProtocolError::RequestFailed(
+                "Guarded or fresh-DNS connections are not pooled".to_string(),
+            )
+        })?;
+        let timeouts = request.timeouts(&self.config.timeouts);
+        let stream = self
+            .pool
+            .timed_connect(Some(&key), self.open_stream(&request, &timeouts))
+            .await?;
+        self.pool.park_and_close_evicted(&key, stream).await;
+        Ok(())
+    }
+
     pub async fn send_request(&self, request: Request) -> Result<Response, ProtocolError> {
         <Self as Protocol>::response(self, request).await
     }
 
-    async fn perform_request(&self, request: &Request) -> Result<Response, ProtocolError> {
-        let timeouts = request.timeouts(&self.timeouts);
-        let mut stream = self.open_stream(request, &timeouts).await?;
-        self.write_request(&mut stream, request, &timeouts).await?;
+    /// Sends `request` on a connection of its own and returns once the
+    /// response head is in. The body comes off the connection as it is
+    /// consumed, and the connection is closed with it. Redirects are not
+    /// followed.
+    pub async fn send_request_streaming(
+        &self,
+        mut request: Request,
+    ) -> Result<StreamingResponse<'static>, ProtocolError> {
+        self.apply_defaults(&mut request);
+        if let Some(scope) = &self.config.host_scope {
+            scope.check(&request.target).await?;
+        }
+        request.check_headers(&HttpProtocol::Http1)?;
+        let timeouts = request.timeouts(&self.config.timeouts);
+        let mut stream = self.open_stream(&request, &timeouts).await?;
+        self.write_request(&mut stream, &request, &timeouts).await?;
+
+        let mut client = self.clone();
+        client.config.timeouts = timeouts;
         let read_body = !request.method.eq_ignore_ascii_case("HEAD");
-        self.read_response(&mut stream, read_body, &timeouts)
+        H1Connection::from_client(client, stream)
+            .into_response_streaming(read_body)
             .await
     }
 
+    async fn perform_request(&self, request: &Request) -> Result<Response, ProtocolError> {
+        request.check_headers(&HttpProtocol::Http1)?;
+        let timeouts = request.timeouts(&self.config.timeouts);
+        let key = h1_pool_key(request);
+        if let Some(stream) = key.as_deref().and_then(|key| self.pool.take(key)) {
+            match self
+                .exchange(stream, request, key.as_deref(), &timeouts)
+                .await
+            {
+                // The server closed the parked connection while it sat idle.
+                Err((_, true)) => {}
+                result => return result.map_err(|(err, _)| err),
+            }
+        }
+        let stream = self
+            .pool
+            .timed_connect(key.as_deref(), self.open_stream(request, &timeouts))
+            .await?;
+        self.exchange(stream, request, key.as_deref(), &timeouts)
+            .await
+            .map_err(|(err, _)| err)
+    }
+
+    /// Sends `request` on `stream` and reads the response, then parks the
+    /// connection under `key` if both sides let it carry another request.
+    /// The bytes written and read are charged to the request's traffic
+    /// meter, whether or not the exchange succeeds. Errors come with whether
+    /// the request can safely go out again: the connection failed before
+    /// any of the response arrived, and the request either never went out
+    /// whole or has an idempotent method.
+    async fn exchange(
+        &self,
+        stream: TransportStream,
+        request: &Request,
+        key: Option<&str>,
+        timeouts: &ClientTimeouts,
+    ) -> Result<Response, (ProtocolError, bool)> {
+        let (mut sent, mut received) = (0, 0);
+        let result = self
+            .exchange_counted(stream, request, key, timeouts, &mut sent, &mut received)
+            .await;
+        request.charge_traffic(sent, received);
+        result.map_err(|err| {
+            let retryable = is_stale_connection(&err)
+                && received == 0
+                && (sent == 0 || is_idempotent(&request.method));
+            (err, retryable)
+        })
+    }
+
+    async fn exchange_counted(
+        &self,
+        mut stream: TransportStream,
+        request: &Request,
+        key: Option<&str>,
+        timeouts: &ClientTimeouts,
+        sent: &mut u64,
+        received: &mut u64,
+    ) -> Result<Response, ProtocolError> {
+        let encoded = codec::encode_request(request)?;
+        self.write_to_stream(&mut stream, &encoded, timeouts.write)
+            .await?;
+        *sent += encoded.len() as u64;
+        let read_body = !request.method.eq_ignore_ascii_case("HEAD");
+        let mut reader = BufReader::new(CountingReader {
+            inner: stream,
+            count: received,
+            mirror: None,
+        });
+        let response = self
+            .read_response_buffered(&mut reader, read_body, timeouts, Some(request))
+            .await?;
+
+        let reusable = self.keep_alive
+            && reader.buffer().is_empty()
+            && !response.truncated
+            && !request.method.eq_ignore_ascii_case("CONNECT")
+            && !(read_body
+                && codec::response_has_body(response.status)
+                && codec::response_framing(&response.headers) == codec::BodyFraming::Close)
+            && codec::keeps_alive(&request.headers, &response.protocol, &response.headers);
+        if let Some(key) = key.filter(|_| reusable) {
+            self.pool
+                .park_and_close_evicted(key, reader.into_inner().inner)
+                .await;
+        }
+        Ok(response)
+    }
+
     pub async fn open_stream(
         &self,
         request: &Request,
@@ -59,8 +232,35 @@ impl H1 {
         let scheme = target.scheme().to_string();
         let connect_timeout = timeouts.connect;
 
+        // A Unix socket is reached directly; proxies only lead to hosts.
+        if let Some(socket) = &target.unix_socket {
+            if request.ssrf_guard.is_some() {
+                return Err(ProtocolError::RequestFailed(
+                    "An SSRF guard cannot vet a Unix socket".to_string(),
+                ));
+            }
+            return create_unix_stream(socket, connect_timeout)
+                .await
+                .map_err(ProtocolError::from_connect_error);
+        }
+
         // Handle proxy if configured
         if let Some(proxy_settings) = &request.proxies {
+            // The proxy resolves the target itself; this catches what local DNS can.
+            if let Some(guard) = &request.ssrf_guard {
+                guard.resolve(host, port).await?;
+                // The proxy is a destination of its own.
+                if let Some(proxy) = proxy_url(request) {
+                    let proxy_host = proxy.host_str().ok_or_else(|| {
+                        ProtocolError::InvalidTarget("Proxy missing host".to_string())
+                    })?;
+                    let proxy_port = proxy.port_or_known_default().ok_or_else(|| {
+                        ProtocolError::InvalidTarget("Proxy missing port".to_string())
+                    })?;
+                    guard.resolve_proxy(proxy_host, proxy_port).await?;
+                }
+            }
+
             // First check for SOCKS proxy
             if let Some(socks_proxy) = &proxy_settings.socks {
                 return timeout_result(connect_timeout, async move {
@@ -70,6 +270,7 @@ impl H1 {
                             host,
                             port,
                             connect_timeout,
+                            request.on_informational.as_ref(),
                         )
                         .await
                     } else {
@@ -78,6 +279,7 @@ impl H1 {
                             host,
                             port,
                             connect_timeout,
+                            request.on_informational.as_ref(),
                         )
                         .await
                     }
@@ -95,6 +297,14 @@ impl H1 {
                 proxy_settings.http.as_ref()
             };
 
+            if let Some(proxy_url) = request.forward_proxy() {
+                let proxy_config = crate::types::ProxyConfig::http(proxy_url.clone());
+                return timeout_result(connect_timeout, async move {
+                    crate::proxy::connect_to_forward_proxy(&proxy_config, connect_timeout).await
+                })
+                .await;
+            }
+
             if let Some(proxy_url) = proxy_url {
                 let proxy_config = if target.scheme() == "https" {
                     crate::types::ProxyConfig::https(proxy_url.clone())
@@ -109,6 +319,7 @@ impl H1 {
                             host,
                             port,
                             connect_timeout,
+                            request.on_informational.as_ref(),
                         )
                         .await
                     } else {
@@ -117,6 +328,7 @@ impl H1 {
                             host,
                             port,
                             connect_timeout,
+                            request.on_informational.as_ref(),
                         )
                         .await
                     }
@@ -126,11 +338,27 @@ impl H1 {
         }
 
         // Direct connection
+        let socket_options = self.config.socket_options_for(request);
+        if let Some(guard) = &request.ssrf_guard {
+            return timeout_result(
+                connect_timeout,
+                create_guarded_stream(
+                    &scheme,
+                    host,
+                    port,
+                    connect_timeout,
+                    guard,
+                    &socket_options,
+                    None,
+                ),
+            )
+            .await;
+        }
         let host_owned = host.to_string();
         timeout_result(connect_timeout, async move {
-            create_stream(&scheme, &host_owned, port, connect_timeout)
+            create_stream_with_options(&scheme, &host_owned, port, connect_timeout, &socket_options)
                 .await
-                .map_err(|e| ProtocolError::ConnectionFailed(e.to_string()))
+                .map_err(ProtocolError::from_connect_error)
         })
         .await
     }
@@ -141,70 +369,7 @@ impl H1 {
         request: &Request,
         timeouts: &ClientTimeouts,
     ) -> Result<(), ProtocolError> {
-        let mut req = Vec::new();
-        let path = request.path();
-
-        let mut headers = request.prepare_headers();
-        // TODO add connection header
-        let trailers = request.trailers.clone();
-
-        req.extend_from_slice(
-            format!("{} {} {}{}", request.method, path, HTTP_VERSION_1_1, CRLF).as_bytes(),
-        );
-
-        let has_host = headers
-            .iter()
-            .any(|h| h.name.eq_ignore_ascii_case(HOST_HEADER));
-        if !has_host {
-            let authority = request
-                .target
-                .authority()
-                .unwrap_or_else(|| request.target.host().unwrap_or_default().to_string());
-            headers.push(Header::new(HOST_HEADER.to_string(), authority));
-        }
-
-        let has_content_length = headers
-            .iter()
-            .any(|h| h.name.eq_ignore_ascii_case(CONTENT_LENGTH_HEADER));
-        let has_chunked = headers.iter().any(|h| {
-            h.name.eq_ignore_ascii_case(TRANSFER_ENCODING_HEADER)
-                && h.value
-                    .as_ref()
-                    .map_or(false, |v| v.to_lowercase().contains(CHUNKED_ENCODING))
-        });
-
-        let use_chunked = has_chunked || !trailers.is_empty();
-        let body_len = request.body.as_ref().map(|b| b.len());
-        let should_generate_content_length =
-            body_len.is_some() && !use_chunked && !has_content_length;
-
-        if should_generate_content_length {
-            headers.push(Header::new(
-                CONTENT_LENGTH_HEADER.to_string(),
-                body_len.unwrap().to_string(),
-            ));
-        } else if use_chunked && !has_chunked {
-            headers.push(Header::new(
-                TRANSFER_ENCODING_HEADER.to_string(),
-                CHUNKED_ENCODING.to_string(),
-            ));
-        }
-
-        for header in &headers {
-            req.extend_from_slice(format!("{}{}", header.to_string(), CRLF).as_bytes());
-        }
-
-        req.extend_from_slice(CRLF.as_bytes());
-
-        let empty_body = Bytes::new();
-        if use_chunked {
-            let body = request.body.as_ref().unwrap_or(&empty_body);
-            let chunked_body = Self::build_chunked_body(body, trailers.as_slice());
-            req.extend_from_slice(&chunked_body);
-        } else if let Some(body) = request.body.as_ref() {
-            req.extend_from_slice(body);
-        }
-
+        let req = codec::encode_request(request)?;
         self.write_to_stream(stream, &req, timeouts.write).await
     }
 
@@ -212,13 +377,10 @@ impl H1 {
         &self,
         stream: &mut TransportStream,
         data: &[u8],
-        write_timeout: Option<std::time::Duration>,
+        write_timeout: Option<Duration>,
     ) -> Result<(), ProtocolError> {
-        timeout_result(write_timeout, async {
-            match stream {
-                TransportStream::Tcp(tcp) => tcp.write_all(data).await.map_err(ProtocolError::Io),
-                TransportStream::Tls(tls) => tls.write_all(data).await.map_err(ProtocolError::Io),
-            }
+        phase_timeout(write_timeout, TimeoutKind::Write, async {
+            stream.write_all(data).await.map_err(ProtocolError::Io)
         })
         .await
     }
@@ -229,49 +391,103 @@ impl H1 {
         read_body: bool,
         timeouts: &ClientTimeouts,
     ) -> Result<Response, ProtocolError> {
-        match stream {
-            TransportStream::Tcp(tcp) => {
-                let mut reader = BufReader::new(tcp);
-                self.read_response_from_reader(&mut reader, read_body, timeouts)
-                    .await
-            }
-            TransportStream::Tls(tls) => {
-                let mut reader = BufReader::new(tls);
-                self.read_response_from_reader(&mut reader, read_body, timeouts)
-                    .await
-            }
-        }
+        self.read_response_from(stream, read_body, timeouts, None)
+            .await
     }
 
-    async fn read_response_from_reader<R: AsyncBufRead + Unpin>(
+    /// Reads a response from any reader, e.g. one wrapped to count bytes.
+    /// With a `request`, interim 1xx responses are passed to its
+    /// `on_informational` and a body cut short by the connection is kept if
+    /// it sets `allow_truncated_body`.
+    pub(crate) async fn read_response_from<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        read_body: bool,
+        timeouts: &ClientTimeouts,
+        request: Option<&Request>,
+    ) -> Result<Response, ProtocolError> {
+        let mut reader = BufReader::new(reader);
+        self.read_response_buffered(&mut reader, read_body, timeouts, request)
+            .await
+    }
+
+    /// Like [`read_response_from`](Self::read_response_from) on a reader the
+    /// caller keeps. Nothing past the end of the response is consumed, so
+    /// what is left in `reader` is the start of the next response.
+    pub(crate) async fn read_response_buffered<R: AsyncBufRead + Unpin>(
         &self,
         reader: &mut R,
         read_body: bool,
         timeouts: &ClientTimeouts,
+        request: Option<&Request>,
     ) -> Result<Response, ProtocolError> {
+        let allow_truncated = request.map_or(false, |request| request.allow_truncated_body);
+        let (status, protocol, headers) = self.read_head(reader, timeouts, request).await?;
+        let (body, trailers, truncation) = if !read_body || !codec::response_has_body(status) {
+            (Bytes::new(), Vec::new(), None)
+        } else {
+            phase_timeout(
+                timeouts.body_total_timeout(),
+                TimeoutKind::BodyTotal,
+                self.read_body(reader, &headers, timeouts, allow_truncated),
+            )
+            .await?
+        };
+        let limits = request.map_or_else(DecompressionLimits::default, |request| {
+            request.decompression_limits
+        });
+        let (body, transfer_codings) = codec::remove_transfer_codings(&headers, body, &limits);
+
+        let cookies = Response::collect_cookies(&headers);
+
+        Ok(Response {
+            status,
+            protocol,
+            headers,
+            body,
+            trailers: if trailers.is_empty() {
+                None
+            } else {
+                Some(trailers)
+            },
+            frames: None,
+            header_blocks: None,
+            cookies,
+            decoded_body: None,
+            frame_times: None,
+            digest: None,
+            transfer_codings,
+            truncated: truncation.is_some(),
+            truncation,
+        })
+    }
+
+    /// Reads up to the end of the final response head, passing interim 1xx
+    /// responses to the `on_informational` of `request`.
+    pub(crate) async fn read_head<R: AsyncBufRead + Unpin>(
+        &self,
+        reader: &mut R,
+        timeouts: &ClientTimeouts,
+        request: Option<&Request>,
+    ) -> Result<(u16, String, Vec<Header>), ProtocolError> {
+        let on_informational = request.and_then(|request| request.on_informational.as_ref());
+        if timeouts.first_byte.is_some() {
+            phase_timeout(timeouts.first_byte, TimeoutKind::FirstByte, async {
+                // Errors and EOF surface from the status line read below.
+                let _ = reader.fill_buf().await;
+                Ok(())
+            })
+            .await?;
+        }
+        let header_deadline = timeouts.header_timeout().map(|d| Instant::now() + d);
+        let limits = self.config.header_limits;
         loop {
             let mut status_line = String::new();
-            let bytes = timeout_result(timeouts.read, async {
-                match reader.read_line(&mut status_line).await {
-                    Ok(bytes) => Ok(bytes),
-                    Err(e) => {
-                        // Handle UTF-8 and TLS close_notify errors gracefully
-                        if let Some(custom_error) = e.get_ref() {
-                            let error_msg = custom_error.to_string();
-                            if error_msg
-                                .contains("peer closed connection without sending TLS close_notify")
-                                || error_msg.contains("stream did not contain valid UTF-8")
-                            {
-                                return Ok(0); // Treat as EOF
-                            }
-                        }
-                        if e.kind() == std::io::ErrorKind::InvalidData {
-                            return Ok(0); // Treat UTF-8 errors as EOF
-                        }
-                        Err(ProtocolError::Io(e))
-                    }
-                }
-            })
+            let bytes = phase_timeout(
+                time_remaining(header_deadline),
+                TimeoutKind::Headers,
+                read_line_limited(reader, &mut status_line, &limits),
+            )
             .await?;
 
             if bytes == 0 {
@@ -286,66 +502,49 @@ impl H1 {
                 continue;
             }
 
-            let (status, protocol) = Self::parse_status_line(&status_line)?;
-            let headers = self.read_header_block(reader, timeouts).await?;
-
-            let (body, trailers) = if !read_body || !Self::response_has_body(status) {
-                (Bytes::new(), Vec::new())
-            } else {
-                self.read_body(reader, &headers, timeouts).await?
-            };
+            let (status, protocol) = codec::parse_status_line(&status_line)?;
+            let headers = self
+                .read_header_block(reader, header_deadline, status_line.len())
+                .await?;
 
-            let cookies = Response::collect_cookies(&headers);
+            if is_interim_status(status) {
+                if let Some(handler) = on_informational {
+                    handler.call(&InformationalResponse {
+                        status,
+                        headers,
+                        from_proxy: false,
+                    });
+                }
+                continue;
+            }
 
-            return Ok(Response {
-                status,
-                protocol,
-                headers,
-                body,
-                trailers: if trailers.is_empty() {
-                    None
-                } else {
-                    Some(trailers)
-                },
-                frames: None,
-                cookies,
-            });
+            return Ok((status, protocol, headers));
         }
     }
 
     async fn read_header_block<R: AsyncBufRead + Unpin>(
         &self,
         reader: &mut R,
-        timeouts: &ClientTimeouts,
+        deadline: Option<Instant>,
+        status_line_len: usize,
     ) -> Result<Vec<Header>, ProtocolError> {
+        let limits = self.config.header_limits;
+        let mut total = status_line_len;
         let mut headers = Vec::new();
         loop {
             let mut line = String::new();
-            match timeout_result(timeouts.read, async {
-                match reader.read_line(&mut line).await {
-                    Ok(bytes) => Ok(bytes),
-                    Err(e) => {
-                        // Handle UTF-8 and TLS close_notify errors gracefully
-                        if let Some(custom_error) = e.get_ref() {
-                            let error_msg = custom_error.to_string();
-                            if error_msg
-                                .contains("peer closed connection without sending TLS close_notify")
-                                || error_msg.contains("stream did not contain valid UTF-8")
-                            {
-                                return Ok(0); // Treat as EOF
-                            }
-                        }
-                        if e.kind() == std::io::ErrorKind::InvalidData {
-                            return Ok(0); // Treat UTF-8 errors as EOF
-                        }
-                        Err(ProtocolError::Io(e))
-                    }
-                }
-            })
+            match phase_timeout(
+                time_remaining(deadline),
+                TimeoutKind::Headers,
+                read_line_limited(reader, &mut line, &limits),
+            )
             .await
             {
                 Ok(0) => break, // EOF or error treated as EOF
-                Ok(_) => {}
+                Ok(read) => {
+                    total += read;
+                    limits.check_total(total)?;
+                }
                 Err(e) => return Err(e),
             }
 
@@ -365,211 +564,241 @@ impl H1 {
         reader: &mut R,
         headers: &[Header],
         timeouts: &ClientTimeouts,
-    ) -> Result<(Bytes, Vec<Header>), ProtocolError> {
-        let is_chunked = headers.iter().any(|h| {
-            h.name.to_lowercase() == TRANSFER_ENCODING_HEADER
-                && h.value
-                    .as_ref()
-                    .map_or(false, |v| v.to_lowercase().contains(CHUNKED_ENCODING))
-        });
-
-        if is_chunked {
-            self.read_chunked_body(reader, timeouts).await
-        } else {
-            let content_length = headers
-                .iter()
-                .find(|h| h.name.to_lowercase() == CONTENT_LENGTH_HEADER)
-                .and_then(|h| h.value.as_ref())
-                .and_then(|v| v.parse::<usize>().ok());
-
-            if let Some(length) = content_length {
-                let mut body = vec![0u8; length];
-                timeout_result(timeouts.read, async {
-                    reader
-                        .read_exact(&mut body)
-                        .await
-                        .map_err(ProtocolError::Io)
-                })
-                .await?;
-                Ok((Bytes::from(body), Vec::new()))
-            } else {
+        allow_truncated: bool,
+    ) -> Result<(Bytes, Vec<Header>, Option<Truncation>), ProtocolError> {
+        match codec::response_framing(headers) {
+            codec::BodyFraming::Chunked => {
+                self.read_chunked_body(reader, timeouts, allow_truncated)
+                    .await
+            }
+            codec::BodyFraming::Length(length) => {
+                let (body, error) = self.read_partial(reader, length, timeouts).await?;
+                if body.len() == length {
+                    return Ok((Bytes::from(body), Vec::new(), None));
+                }
+                if !allow_truncated {
+                    return Err(unexpected_eof(error));
+                }
+                let truncation = Truncation::ContentLength {
+                    expected: length,
+                    received: body.len(),
+                };
+                Ok((Bytes::from(body), Vec::new(), Some(truncation)))
+            }
+            codec::BodyFraming::Close => {
                 let mut body = Vec::new();
-                let _result = timeout_result(timeouts.read, async {
-                    // Use a custom reading loop to handle TLS close_notify gracefully
-                    loop {
-                        let mut buffer = [0u8; 8192];
-                        match reader.read(&mut buffer).await {
-                            Ok(0) => break, // Normal EOF
-                            Ok(n) => body.extend_from_slice(&buffer[..n]),
-                            Err(e) => {
-                                // Handle TLS close_notify issue gracefully
-                                if let Some(custom_error) = e.get_ref() {
-                                    if custom_error.to_string().contains(
-                                        "peer closed connection without sending TLS close_notify",
-                                    ) {
-                                        // This is a common occurrence with HTTPS servers, treat as successful EOF
-                                        break;
-                                    }
-                                }
-                                return Err(ProtocolError::Io(e));
+                // Use a custom reading loop to handle TLS close_notify gracefully
+                loop {
+                    let mut buffer = [0u8; 8192];
+                    let read =
+                        phase_timeout(timeouts.body_idle_timeout(), TimeoutKind::BodyIdle, async {
+                            match reader.read(&mut buffer).await {
+                                Ok(n) => Ok(n),
+                                Err(e) if is_close_notify(&e) => Ok(0),
+                                Err(e) => Err(ProtocolError::Io(e)),
                             }
-                        }
+                        })
+                        .await?;
+                    if read == 0 {
+                        break; // Normal EOF
                     }
-                    Ok(())
-                })
-                .await?;
-                Ok((Bytes::from(body), Vec::new()))
+                    body.extend_from_slice(&buffer[..read]);
+                }
+                Ok((Bytes::from(body), Vec::new(), None))
             }
         }
     }
 
+    /// Reads `len` bytes or as many as arrive before EOF or a read error,
+    /// which is returned alongside them. Only a timeout fails the read.
+    async fn read_partial<R: AsyncBufRead + Unpin>(
+        &self,
+        reader: &mut R,
+        len: usize,
+        timeouts: &ClientTimeouts,
+    ) -> Result<(Vec<u8>, Option<std::io::Error>), ProtocolError> {
+        let mut data = vec![0u8; len];
+        let mut filled = 0;
+        while filled < len {
+            let read = phase_timeout(timeouts.body_idle_timeout(), TimeoutKind::BodyIdle, async {
+                Ok(reader.read(&mut data[filled..]).await)
+            })
+            .await?;
+            match read {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) => {
+                    data.truncate(filled);
+                    return Ok((data, Some(e)));
+                }
+            }
+        }
+        data.truncate(filled);
+        Ok((data, None))
+    }
+
     async fn read_chunked_body<R: AsyncBufRead + Unpin>(
         &self,
         reader: &mut R,
         timeouts: &ClientTimeouts,
-    ) -> Result<(Bytes, Vec<Header>), ProtocolError> {
+        allow_truncated: bool,
+    ) -> Result<(Bytes, Vec<Header>, Option<Truncation>), ProtocolError> {
+        let mut decoder = codec::ChunkedDecoder::new(self.config.header_limits);
         let mut body = Vec::new();
-        let mut trailers = Vec::new();
 
-        loop {
-            let mut size_line = String::new();
-            timeout_result(timeouts.read, async {
-                match reader.read_line(&mut size_line).await {
-                    Ok(bytes) => Ok(bytes),
-                    Err(e) => {
-                        // Handle UTF-8 and TLS close_notify errors gracefully
-                        if let Some(custom_error) = e.get_ref() {
-                            let error_msg = custom_error.to_string();
-                            if error_msg
-                                .contains("peer closed connection without sending TLS close_notify")
-                                || error_msg.contains("stream did not contain valid UTF-8")
-                            {
-                                return Ok(0); // Treat as EOF
-                            }
-                        }
-                        if e.kind() == std::io::ErrorKind::InvalidData {
-                            return Ok(0); // Treat UTF-8 errors as EOF
-                        }
-                        Err(ProtocolError::Io(e))
-                    }
+        while !decoder.is_done() {
+            let read = phase_timeout(timeouts.body_idle_timeout(), TimeoutKind::BodyIdle, async {
+                match reader.fill_buf().await {
+                    Ok(available) => Ok(available.len()),
+                    Err(e) if is_close_notify(&e) => Ok(0),
+                    Err(e) => Err(ProtocolError::Io(e)),
                 }
             })
-            .await?;
-
-            let size_str = size_line.trim().split(';').next().unwrap_or(" ").trim();
-            let chunk_size = usize::from_str_radix(size_str, 16)
-                .map_err(|_| ProtocolError::InvalidResponse("Invalid chunk size".to_string()))?;
-
-            if chunk_size == 0 {
-                loop {
-                    let mut line = String::new();
-                    timeout_result(timeouts.read, async {
-                        match reader.read_line(&mut line).await {
-                            Ok(bytes) => Ok(bytes),
-                            Err(e) => {
-                                // Handle UTF-8 and TLS close_notify errors gracefully
-                                if let Some(custom_error) = e.get_ref() {
-                                    let error_msg = custom_error.to_string();
-                                    if error_msg.contains(
-                                        "peer closed connection without sending TLS close_notify",
-                                    ) || error_msg.contains("stream did not contain valid UTF-8")
-                                    {
-                                        return Ok(0); // Treat as EOF
-                                    }
-                                }
-                                if e.kind() == std::io::ErrorKind::InvalidData {
-                                    return Ok(0); // Treat UTF-8 errors as EOF
-                                }
-                                Err(ProtocolError::Io(e))
-                            }
+            .await;
+            let error = match read {
+                Ok(0) => None,
+                Ok(_) => {
+                    // Already buffered, so this returns without reading.
+                    let available = reader.fill_buf().await.map_err(ProtocolError::Io)?;
+                    match decoder.step(available)? {
+                        codec::ChunkedStep::Data(len) => {
+                            body.extend_from_slice(&available[..len]);
+                            reader.consume(len);
                         }
-                    })
-                    .await?;
-
-                    if line.trim().is_empty() {
-                        break;
-                    }
-
-                    if let Some(trailer) = crate::utils::parse_header(line.trim()) {
-                        trailers.push(trailer);
+                        codec::ChunkedStep::Framing(len) => reader.consume(len),
                     }
+                    continue;
                 }
+                Err(ProtocolError::Io(e)) => Some(e),
+                Err(e) => return Err(e),
+            };
+
+            // The connection ended early. Missing trailers are no loss.
+            if decoder.in_trailers() {
                 break;
             }
-
-            let mut chunk = vec![0u8; chunk_size];
-            timeout_result(timeouts.read, async {
-                reader
-                    .read_exact(&mut chunk)
-                    .await
-                    .map_err(ProtocolError::Io)
-            })
-            .await?;
-            body.extend_from_slice(&chunk);
-
-            let mut crlf = [0u8; 2];
-            timeout_result(timeouts.read, async {
-                reader
-                    .read_exact(&mut crlf)
-                    .await
-                    .map_err(ProtocolError::Io)
-            })
-            .await?;
+            if !allow_truncated {
+                return Err(unexpected_eof(error));
+            }
+            let truncation = decoder.truncation();
+            return Ok((Bytes::from(body), decoder.take_trailers(), Some(truncation)));
         }
 
-        Ok((Bytes::from(body), trailers))
+        Ok((Bytes::from(body), decoder.take_trailers(), None))
     }
 
-    // TODO write better
-    fn response_has_body(status: u16) -> bool {
-        if (100..200).contains(&status) {
-            return false;
-        }
-
-        !matches!(status, 204 | 205 | 304)
+    pub fn parse_status_line(status_line: &str) -> Result<(u16, String), ProtocolError> {
+        codec::parse_status_line(status_line)
     }
+}
 
-    fn build_chunked_body(body: &Bytes, trailers: &[Header]) -> Vec<u8> {
-        let mut chunked_body = Vec::new();
-
-        if !body.is_empty() {
-            let chunk_size = format!("{:x}{}", body.len(), CRLF);
-            chunked_body.extend_from_slice(chunk_size.as_bytes());
-            chunked_body.extend_from_slice(body);
-            chunked_body.extend_from_slice(CRLF.as_bytes());
-        }
-
-        let final_chunk = format!("0{}", CRLF);
-        chunked_body.extend_from_slice(final_chunk.as_bytes());
-
-        for trailer in trailers {
-            let mut trailer_line = format!("{}{}", trailer.to_string(), CRLF);
-            if matches!(trailer.to_string().as_str(), "\n" | "\r" | "\r\n") {
-                trailer_line = trailer.to_string();
+/// `read_line` that gives up once a line outgrows `limits.max_line`. A read
+/// error is EOF when it is a TLS close_notify or UTF-8 problem, as the
+/// server is done either way.
+async fn read_line_limited<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    line: &mut String,
+    limits: &HeaderLimits,
+) -> Result<usize, ProtocolError> {
+    let mut raw = Vec::new();
+    loop {
+        let available = match reader.fill_buf().await {
+            Ok(available) => available,
+            Err(e) => {
+                if is_close_notify(&e) || e.kind() == std::io::ErrorKind::InvalidData {
+                    return Ok(0); // Treat as EOF
+                }
+                return Err(ProtocolError::Io(e));
             }
-            chunked_body.extend_from_slice(trailer_line.as_bytes());
+        };
+        if available.is_empty() {
+            break;
+        }
+        let (take, complete) = match available.iter().position(|&b| b == b'\n') {
+            Some(end) => (end + 1, true),
+            None => (available.len(), false),
+        };
+        raw.extend_from_slice(&available[..take]);
+        reader.consume(take);
+        limits.check_line(raw.len())?;
+        if complete {
+            break;
         }
+    }
+    match String::from_utf8(raw) {
+        Ok(text) => {
+            line.push_str(&text);
+            Ok(text.len())
+        }
+        Err(_) => Ok(0), // Treat UTF-8 errors as EOF
+    }
+}
 
-        chunked_body.extend_from_slice(CRLF.as_bytes());
+/// Whether `err` is a TLS connection closed without close_notify. Many
+/// HTTPS servers do that, and the data they sent is complete, so read paths
+/// treat it as EOF.
+pub(crate) fn is_close_notify(err: &std::io::Error) -> bool {
+    err.get_ref().map_or(false, |inner| {
+        inner
+            .to_string()
+            .contains("peer closed connection without sending TLS close_notify")
+    })
+}
+
+/// The error a strict read reports for a body the connection cut short.
+pub(crate) fn unexpected_eof(error: Option<std::io::Error>) -> ProtocolError {
+    ProtocolError::Io(error.unwrap_or_else(|| std::io::ErrorKind::UnexpectedEof.into()))
+}
 
-        chunked_body
+/// Pool key for `request`: its origin, followed by the proxy it goes
+/// through, as proxied connections lead to the proxy rather than the origin.
+fn h1_pool_key(request: &Request) -> Option<String> {
+    let origin = pool_key(request)?;
+    match proxy_route(request) {
+        Some(proxy) => Some(format!("{} via {}", origin, proxy)),
+        None => Some(origin),
     }
+}
 
-    pub fn parse_status_line(status_line: &str) -> Result<(u16, String), ProtocolError> {
-        // TODO what if it is HTTP/0.9
-        let parts: Vec<&str> = status_line.trim().split_whitespace().collect();
-        if parts.len() < 2 {
-            return Err(ProtocolError::InvalidResponse(
-                "Invalid status line".to_string(),
-            ));
+/// The proxy `open_stream` connects through.
+fn proxy_url(request: &Request) -> Option<&Url> {
+    let proxies = request.proxies.as_ref()?;
+    match &proxies.socks {
+        Some(socks) => Some(&socks.url),
+        None if request.target.scheme() == "https" => {
+            proxies.https.as_ref().or(proxies.http.as_ref())
         }
+        None => proxies.http.as_ref(),
+    }
+}
 
-        let protocol = parts[0].to_string();
-        let status_code = parts[1]
-            .parse::<u16>()
-            .map_err(|_| ProtocolError::InvalidResponse("Invalid status code".to_string()))?;
+/// The proxy `open_stream` connects through, without its password.
+fn proxy_route(request: &Request) -> Option<String> {
+    let url = proxy_url(request)?;
+    let user = match url.username() {
+        "" => String::new(),
+        user => format!("{}@", user),
+    };
+    Some(format!(
+        "{}://{}{}:{}",
+        url.scheme(),
+        user,
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    ))
+}
 
-        Ok((status_code, protocol))
+/// Errors a parked connection the server has since closed fails with.
+fn is_stale_connection(err: &ProtocolError) -> bool {
+    match err {
+        ProtocolError::ConnectionFailed(_) => true,
+        ProtocolError::Io(err) => matches!(
+            err.kind(),
+            std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+        ),
+        _ => false,
     }
 }
 
@@ -579,6 +808,18 @@ impl Protocol for H1 {
         self.perform_request(request).await
     }
 
+    async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        H1::preconnect(self, origin).await
+    }
+
+    fn pools(&self) -> Vec<&dyn PoolControl> {
+        vec![self.pool.as_ref() as &dyn PoolControl]
+    }
+
+    fn config(&self) -> Option<&ClientConfig> {
+        Some(&self.config)
+    }
+
     async fn send_raw(&self, target: &str, raw_request: Bytes) -> Result<Response, ProtocolError> {
         if raw_request.is_empty() {
             return Err(ProtocolError::RequestFailed(
@@ -587,7 +828,10 @@ impl Protocol for H1 {
         }
 
         let request = Request::new(target, "RAW")?;
-        let timeouts = request.timeouts(&self.timeouts);
+        if let Some(scope) = &self.config.host_scope {
+            scope.check(&request.target).await?;
+        }
+        let timeouts = request.timeouts(&self.config.timeouts);
         let mut stream = self.open_stream(&request, &timeouts).await?;
 
         self.write_to_stream(&mut stream, raw_request.as_ref(), timeouts.write)
@@ -0,0 +1,207 @@
+//! Helpers for HTTP/2 conformance testing of servers.
+//!
+//! [`PriorityTree`] builds PRIORITY dependency trees, including the cycles
+//! and self-dependencies RFC 9113 Section 5.3.1 forbids, and
+//! [`measure_schedule`] records the order in which the server then delivers
+//! response DATA:
+//!
+//! ```ignore
+//! // streams 1, 3 and 5 carry GET requests sent without END_STREAM delay
+//! PriorityTree::new()
+//!     .depend(3, 1, 255)
+//!     .depend(5, 3, 0)
+//!     .send(&mut connection)
+//!     .await?;
+//! let trace = measure_schedule(&mut connection, &[1, 3, 5], Some(Duration::from_secs(5))).await?;
+//! println!("first DATA order: {:?}", trace.first_data_order());
+//! ```
+
+use crate::h2::connection::H2Connection;
+use crate::types::{FrameH2, FrameType, FrameTypeH2, ProtocolError};
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// One PRIORITY frame: `stream_id` depends on `depends_on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityEntry {
+    pub stream_id: u32,
+    pub depends_on: u32,
+    /// As written on the wire, i.e. the weight minus one.
+    pub weight: u8,
+    pub exclusive: bool,
+}
+
+impl PriorityEntry {
+    pub fn frame(&self) -> Result<FrameH2, ProtocolError> {
+        FrameH2::priority(
+            self.stream_id,
+            5,
+            self.depends_on,
+            self.weight,
+            self.exclusive,
+        )
+    }
+}
+
+/// Dependency tree sent as a sequence of PRIORITY frames, in insertion order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PriorityTree {
+    entries: Vec<PriorityEntry>,
+}
+
+impl PriorityTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn depend(self, stream_id: u32, depends_on: u32, weight: u8) -> Self {
+        self.entry(stream_id, depends_on, weight, false)
+    }
+
+    /// Makes `stream_id` the only child of `depends_on`, adopting its
+    /// previous children.
+    pub fn depend_exclusive(self, stream_id: u32, depends_on: u32, weight: u8) -> Self {
+        self.entry(stream_id, depends_on, weight, true)
+    }
+
+    /// Each stream depends on the one before it; the first on the root.
+    /// A server honoring the tree sends nothing for a stream until every
+    /// stream above it is done, which makes starvation easy to observe.
+    pub fn chain(mut self, streams: &[u32], weight: u8) -> Self {
+        let mut parent = 0;
+        for &stream_id in streams {
+            self = self.depend(stream_id, parent, weight);
+            parent = stream_id;
+        }
+        self
+    }
+
+    /// Each stream depends on the next and the last on the first.
+    pub fn cycle(mut self, streams: &[u32], weight: u8) -> Self {
+        for (i, &stream_id) in streams.iter().enumerate() {
+            let parent = streams[(i + 1) % streams.len()];
+            self = self.depend(stream_id, parent, weight);
+        }
+        self
+    }
+
+    /// A stream depending on itself, a stream error per RFC 9113 Section 5.3.1.
+    pub fn self_dependency(self, stream_id: u32, weight: u8) -> Self {
+        self.depend(stream_id, stream_id, weight)
+    }
+
+    fn entry(mut self, stream_id: u32, depends_on: u32, weight: u8, exclusive: bool) -> Self {
+        self.entries.push(PriorityEntry {
+            stream_id,
+            depends_on,
+            weight,
+            exclusive,
+        });
+        self
+    }
+
+    pub fn entries(&self) -> &[PriorityEntry] {
+        &self.entries
+    }
+
+    /// True if following the latest dependency of each stream ever leads
+    /// back to where it started, self-dependencies included.
+    pub fn has_cycle(&self) -> bool {
+        let parent_of = |stream_id: u32| {
+            self.entries
+                .iter()
+                .rev()
+                .find(|entry| entry.stream_id == stream_id)
+                .map(|entry| entry.depends_on)
+        };
+        self.entries.iter().any(|entry| {
+            let mut seen = HashSet::new();
+            let mut current = entry.stream_id;
+            while let Some(parent) = parent_of(current) {
+                if !seen.insert(current) {
+                    return true;
+                }
+                current = parent;
+            }
+            false
+        })
+    }
+
+    pub fn frames(&self) -> Result<Vec<FrameH2>, ProtocolError> {
+        self.entries.iter().map(PriorityEntry::frame).collect()
+    }
+
+    pub async fn send(&self, connection: &mut H2Connection) -> Result<(), ProtocolError> {
+        for frame in self.frames()? {
+            frame.send(connection).await?;
+        }
+        connection.flush().await
+    }
+}
+
+/// Order in which a server delivered response DATA across streams.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScheduleTrace {
+    /// `(stream_id, payload bytes)` for every DATA frame, in arrival order.
+    pub data_frames: Vec<(u32, usize)>,
+    /// Streams in the order their responses ended or were reset.
+    pub completion_order: Vec<u32>,
+}
+
+impl ScheduleTrace {
+    /// Streams in the order their first DATA frame arrived.
+    pub fn first_data_order(&self) -> Vec<u32> {
+        let mut order = Vec::new();
+        for (stream_id, _) in &self.data_frames {
+            if !order.contains(stream_id) {
+                order.push(*stream_id);
+            }
+        }
+        order
+    }
+
+    /// DATA bytes of other streams delivered before `stream_id` received its
+    /// first byte; all of them if it never did.
+    pub fn bytes_before_first_data(&self, stream_id: u32) -> usize {
+        self.data_frames
+            .iter()
+            .take_while(|(id, len)| *id != stream_id || *len == 0)
+            .filter(|(id, _)| *id != stream_id)
+            .map(|(_, len)| len)
+            .sum()
+    }
+}
+
+/// Reads frames until every stream in `streams` has ended, recording DATA
+/// arrival order. `timeout` bounds each frame read. Responses stay queued
+/// and can still be read with `read_response` afterwards.
+pub async fn measure_schedule(
+    connection: &mut H2Connection,
+    streams: &[u32],
+    timeout: Option<Duration>,
+) -> Result<ScheduleTrace, ProtocolError> {
+    let mut trace = ScheduleTrace::default();
+    let mut pending: HashSet<u32> = streams.iter().copied().collect();
+    while !pending.is_empty() {
+        let frame = connection.read_next_frame(timeout).await?;
+        if !pending.contains(&frame.stream_id) {
+            continue;
+        }
+        let ended = match &frame.frame_type {
+            FrameType::H2(FrameTypeH2::Data) => {
+                trace
+                    .data_frames
+                    .push((frame.stream_id, frame.payload.len()));
+                frame.is_end_stream()
+            }
+            FrameType::H2(FrameTypeH2::Headers) => frame.is_end_stream(),
+            FrameType::H2(FrameTypeH2::RstStream) => true,
+            _ => false,
+        };
+        if ended {
+            pending.remove(&frame.stream_id);
+            trace.completion_order.push(frame.stream_id);
+        }
+    }
+    Ok(trace)
+}
@@ -1,24 +1,57 @@
+mod bdp;
+mod budget;
+mod debug;
+mod flood;
+mod handler;
+mod header_stream;
+mod mirror;
+mod reserve;
 mod state;
-
-pub use state::{ConnectionState, StreamEvent, StreamInfo, StreamState};
-
-use crate::connection::HttpConnection;
+mod stats;
+mod step;
+mod streaming;
+mod writer;
+
+pub use crate::wire::{WireChunk, WireDirection, WireSink, WireWriter};
+pub use debug::{H2DebugState, H2GoAwayState, H2StreamDebugState};
+pub use flood::H2FloodLimits;
+pub use handler::{AsyncEventHandler, StreamEventHandler};
+pub use header_stream::HeaderStreaming;
+pub use state::{ConnectionState, ReadBudget, StreamEvent, StreamInfo, StreamState};
+pub use stats::{H2ConnectionStats, H2StreamStats, H2TrafficStats, HpackTableStats};
+pub use streaming::{H2BodyReader, H2StreamingResponse};
+pub use writer::H2StreamWriter;
+
+use crate::connection::{ConnectionEvent, ConnectionEvents, HttpConnection};
+use crate::h1::protocol::H1;
 use crate::h2::consts::*;
 use crate::h2::framing::RstErrorCode;
-use crate::h2::hpack::HpackCodec;
-use crate::stream::{create_stream, TransportStream};
+use crate::h2::hpack::{HeaderCodec, HpackCodec};
+use crate::h2::scheduler::DataScheduler;
+use crate::handshake::{HandshakePhase, HandshakeTracker};
+use crate::stream::{
+    create_guarded_stream, create_traced_stream, create_unix_stream, TransportStream,
+};
 use crate::types::{
     ClientTimeouts, FrameH2, FrameSink, FrameType, FrameTypeH2, H2ConnectionErrorKind, H2ErrorCode,
-    H2StreamErrorKind, Header, ProtocolError, ResponseFrame,
+    H2FloodKind, H2StreamErrorKind, Header, HeaderBlockInfo, HeaderLimitKind, MemoryBudget,
+    ProtocolError, Request, ResponseFrame, SocketOptions, SsrfGuard, StreamQueueLimits, Target,
 };
-use crate::utils::timeout_result;
+use crate::utils::{base64url_encode, time_remaining, timeout_result};
+use crate::wire::WireMirror;
 use crate::Response;
 use async_trait::async_trait;
-use bytes::Bytes;
+use bdp::{BdpEstimator, BDP_PING_PAYLOAD};
+use budget::{captured_frame_size, event_size, MemoryLedger};
+use bytes::{Bytes, BytesMut};
+use flood::FloodGuard;
+use reserve::PreparedHeaders;
 use state::PendingHeaderBlock;
-use std::collections::HashMap;
-use std::time::Duration;
+use stats::StatsRecorder;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
 
 pub struct H2Connection {
     pub stream: TransportStream,
@@ -30,40 +63,266 @@ pub struct H2Connection {
     pub recv_connection_window: i32,
     pub next_stream_id: u32,
     pub last_stream_id: u32,
-    hpack: HpackCodec,
+    hpack: Box<dyn HeaderCodec + Send>,
     initial_settings_received: bool,
     peer_allows_push: bool,
     goaway_reason: Option<(H2ErrorCode, String)>,
     goaway_last_stream_id: Option<u32>,
     goaway_received: bool,
+    /// Set by `shutdown_graceful`; no new streams are opened afterwards.
+    goaway_sent: bool,
     pending_writes: Vec<Bytes>,
     pending_write_bytes: usize,
     auto_flush_bytes: Option<usize>,
     timeouts: ClientTimeouts,
-    captured_frames: HashMap<u32, Vec<FrameH2>>,
+    captured_frames: HashMap<u32, Vec<(Instant, FrameH2)>>,
+    captured_header_blocks: HashMap<u32, Vec<HeaderBlockInfo>>,
+    settings_ack_timeout: Option<Duration>,
+    settings_sent_at: VecDeque<Instant>,
+    lax_stream_states: bool,
+    data_scheduler: DataScheduler,
+    bdp: Option<BdpEstimator>,
+    stats: StatsRecorder,
+    /// Connection-level credit left behind by dropped streaming bodies,
+    /// returned with the next WINDOW_UPDATE.
+    deferred_connection_credit: u32,
+    flood: FloodGuard,
+    /// Bytes read from the transport but not yet consumed as frames.
+    read_buffer: BytesMut,
+    /// Per-stream deadlines set with `set_stream_timeout`.
+    stream_deadlines: HashMap<u32, Instant>,
+    stream_limit_policy: StreamLimitPolicy,
+    events: ConnectionEvents,
+    /// Payloads of PINGs awaiting their ACK, with the time they were queued,
+    /// oldest first.
+    pings_in_flight: VecDeque<([u8; 8], Instant)>,
+    /// Header blocks encoded by `prepare_headers`, in encoding order.
+    prepared_headers: VecDeque<PreparedHeaders>,
+    /// Copy of the raw bytes going in and out, see `set_wire_mirror`.
+    wire_mirror: WireMirror,
+    handshake: HandshakeTracker,
+    /// Set with `set_header_streaming`.
+    header_streaming: Option<HeaderStreaming>,
+    /// Set with `set_memory_budget`.
+    memory_budget: Option<MemoryBudget>,
+    memory: MemoryLedger,
+    /// Set with `set_stream_queue_limits`.
+    queue_limits: Option<StreamQueueLimits>,
+    /// Set with `set_step_mode`; frames only move on `step_read` and
+    /// `step_write`.
+    step_mode: bool,
+}
+
+/// Outcome of validating a received frame against its stream's state.
+enum InboundCheck {
+    Accept,
+    Ignore,
+    StreamClosed,
+    ProtocolViolation(String),
 }
 
 #[derive(Debug, Clone)]
 pub struct H2ConnectOptions {
     pub target: String,
     pub timeouts: ClientTimeouts,
+    /// Connection establishment mode; inferred from the target scheme when `None`.
+    pub mode: Option<H2Mode>,
+}
+
+/// How HTTP/2 is negotiated on top of the transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum H2Mode {
+    /// TLS connection negotiating `h2` through ALPN.
+    TlsAlpn,
+    /// Cleartext connection sending the preface immediately (h2c prior knowledge).
+    PriorKnowledge,
+    /// Cleartext HTTP/1.1 request carrying `Upgrade: h2c`, switching on `101`.
+    /// The response to the upgrade request arrives on stream 1.
+    Upgrade,
+}
+
+impl H2Mode {
+    /// Default mode for a target scheme: `https` uses ALPN, `http` uses prior knowledge.
+    pub fn from_scheme(scheme: &str) -> Option<Self> {
+        match scheme {
+            "https" => Some(H2Mode::TlsAlpn),
+            "http" => Some(H2Mode::PriorKnowledge),
+            _ => None,
+        }
+    }
+}
+
+/// What `create_stream` does once the peer's MAX_CONCURRENT_STREAMS is
+/// reached. Streams count once their HEADERS are sent until they are closed
+/// or reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StreamLimitPolicy {
+    /// Processes incoming frames until a stream closes, bounded by the read
+    /// timeout (the connect timeout when it is unset). Frames for other
+    /// streams are queued for their readers. Fails at once on a limit of 0.
+    #[default]
+    Wait,
+    /// Fails with [`ProtocolError::StreamLimitReached`].
+    Error,
+    /// Opens the stream anyway, e.g. to test how the peer enforces the limit.
+    Ignore,
+}
+
+const MAX_UPGRADE_RESPONSE_SIZE: usize = 64 * 1024;
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+/// PINGs awaiting their ACK that are remembered for `PingRtt`; the oldest is
+/// forgotten when another is sent, so a peer that never answers cannot grow
+/// the map.
+const MAX_PINGS_IN_FLIGHT: usize = 32;
+/// How long `shutdown_graceful` waits for in-flight streams when the caller
+/// gives no `drain_timeout`.
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What a new connection sends while it is being set up.
+#[derive(Default)]
+struct Setup<'a> {
+    /// Sent as the HTTP/1.1 upgrade request in [`H2Mode::Upgrade`]; a GET
+    /// for the target when `None`.
+    upgrade_request: Option<&'a Request>,
+    /// Advertised in the initial SETTINGS instead of the default.
+    max_header_list_size: Option<u32>,
 }
 
 impl H2Connection {
     pub async fn connect(
         target: &str,
         timeouts: &ClientTimeouts, // TODO make optional
+    ) -> Result<Self, ProtocolError> {
+        Self::connect_with_mode(target, timeouts, None).await
+    }
+
+    pub async fn connect_with_mode(
+        target: &str,
+        timeouts: &ClientTimeouts,
+        mode: Option<H2Mode>,
+    ) -> Result<Self, ProtocolError> {
+        Self::connect_guarded(target, timeouts, mode, None).await
+    }
+
+    /// Connects like [`connect_with_mode`](Self::connect_with_mode), refusing
+    /// addresses that `guard` blocks.
+    pub async fn connect_guarded(
+        target: &str,
+        timeouts: &ClientTimeouts,
+        mode: Option<H2Mode>,
+        guard: Option<&SsrfGuard>,
+    ) -> Result<Self, ProtocolError> {
+        Self::connect_with_socket_options(target, timeouts, mode, guard, &SocketOptions::default())
+            .await
+    }
+
+    /// Connects like [`connect_guarded`](Self::connect_guarded), binding the
+    /// local end of the TCP connection per `socket_options`.
+    pub async fn connect_with_socket_options(
+        target: &str,
+        timeouts: &ClientTimeouts,
+        mode: Option<H2Mode>,
+        guard: Option<&SsrfGuard>,
+        socket_options: &SocketOptions,
+    ) -> Result<Self, ProtocolError> {
+        let tracker = HandshakeTracker::new();
+        Self::connect_traced(target, timeouts, mode, guard, socket_options, tracker).await
+    }
+
+    /// Connects like
+    /// [`connect_with_socket_options`](Self::connect_with_socket_options),
+    /// recording each phase of the handshake in `tracker` as it starts.
+    pub async fn connect_traced(
+        target: &str,
+        timeouts: &ClientTimeouts,
+        mode: Option<H2Mode>,
+        guard: Option<&SsrfGuard>,
+        socket_options: &SocketOptions,
+        tracker: HandshakeTracker,
     ) -> Result<Self, ProtocolError> {
         let target = crate::utils::parse_target(target)?;
-        let scheme = target.scheme();
-        let is_tls = scheme == "https";
-        let is_h2c = scheme == "http";
+        Self::connect_inner(
+            target,
+            timeouts,
+            mode,
+            guard,
+            socket_options,
+            tracker,
+            Setup::default(),
+        )
+        .await
+    }
 
-        if !is_tls && !is_h2c {
-            return Err(ProtocolError::RequestFailed(
-                "HTTP/2 requires https, h2, h2c, or http schemes".to_string(),
-            ));
-        }
+    /// Connects like
+    /// [`connect_with_socket_options`](Self::connect_with_socket_options),
+    /// advertising `max_header_list_size`, if set, in the initial SETTINGS.
+    pub async fn connect_with_header_list_size(
+        target: &str,
+        timeouts: &ClientTimeouts,
+        mode: Option<H2Mode>,
+        guard: Option<&SsrfGuard>,
+        socket_options: &SocketOptions,
+        max_header_list_size: Option<u32>,
+    ) -> Result<Self, ProtocolError> {
+        let target = crate::utils::parse_target(target)?;
+        let setup = Setup {
+            upgrade_request: None,
+            max_header_list_size,
+        };
+        Self::connect_inner(
+            target,
+            timeouts,
+            mode,
+            guard,
+            socket_options,
+            HandshakeTracker::new(),
+            setup,
+        )
+        .await
+    }
+
+    /// Connects in [`H2Mode::Upgrade`] with `request` itself as the HTTP/1.1
+    /// upgrade request, as RFC 7540 Section 3.2 intends. It is sent once,
+    /// with its method and body, and its response is read from stream 1.
+    /// `max_header_list_size`, if set, goes into the initial SETTINGS.
+    pub async fn connect_upgrade(
+        request: &Request,
+        timeouts: &ClientTimeouts,
+        guard: Option<&SsrfGuard>,
+        socket_options: &SocketOptions,
+        max_header_list_size: Option<u32>,
+    ) -> Result<Self, ProtocolError> {
+        let setup = Setup {
+            upgrade_request: Some(request),
+            max_header_list_size,
+        };
+        Self::connect_inner(
+            request.target.clone(),
+            timeouts,
+            Some(H2Mode::Upgrade),
+            guard,
+            socket_options,
+            HandshakeTracker::new(),
+            setup,
+        )
+        .await
+    }
+
+    async fn connect_inner(
+        target: Target,
+        timeouts: &ClientTimeouts,
+        mode: Option<H2Mode>,
+        guard: Option<&SsrfGuard>,
+        socket_options: &SocketOptions,
+        tracker: HandshakeTracker,
+        setup: Setup<'_>,
+    ) -> Result<Self, ProtocolError> {
+        let mode = match mode {
+            Some(mode) => mode,
+            None => H2Mode::from_scheme(target.scheme()).ok_or_else(|| {
+                ProtocolError::RequestFailed("HTTP/2 requires https or http schemes".to_string())
+            })?,
+        };
 
         let host = target
             .host()
@@ -72,21 +331,101 @@ impl H2Connection {
             .port()
             .ok_or_else(|| ProtocolError::InvalidTarget("Target missing port".to_string()))?;
 
-        let transport = if is_tls {
-            create_stream("h2", host, port, timeouts.connect)
-                .await
-                .map_err(|e| ProtocolError::ConnectionFailed(e.to_string()))?
-        } else {
-            create_stream("http", host, port, timeouts.connect)
-                .await
-                .map_err(|e| ProtocolError::ConnectionFailed(e.to_string()))?
+        let scheme = match mode {
+            H2Mode::TlsAlpn => "h2",
+            H2Mode::PriorKnowledge | H2Mode::Upgrade => "http",
+        };
+        let transport = match (&target.unix_socket, guard) {
+            (Some(_), Some(_)) => {
+                return Err(ProtocolError::RequestFailed(
+                    "An SSRF guard cannot vet a Unix socket".to_string(),
+                ))
+            }
+            (Some(socket), None) if mode != H2Mode::TlsAlpn => {
+                create_unix_stream(socket, timeouts.connect)
+                    .await
+                    .map_err(ProtocolError::from_connect_error)?
+            }
+            (Some(_), None) => {
+                return Err(ProtocolError::RequestFailed(
+                    "HTTP/2 over a Unix socket needs prior knowledge or an upgrade".to_string(),
+                ))
+            }
+            (None, Some(guard)) => {
+                create_guarded_stream(
+                    scheme,
+                    host,
+                    port,
+                    timeouts.connect,
+                    guard,
+                    socket_options,
+                    Some(&tracker),
+                )
+                .await?
+            }
+            (None, None) => create_traced_stream(
+                scheme,
+                host,
+                port,
+                timeouts.connect,
+                socket_options,
+                &tracker,
+            )
+            .await
+            .map_err(ProtocolError::from_connect_error)?,
         };
+        if mode == H2Mode::TlsAlpn {
+            Self::ensure_h2_alpn(&transport)?;
+        }
+
+        let mut connection = Self::new(transport, timeouts.clone());
+        connection.handshake = tracker;
+        if let Some(size) = setup.max_header_list_size {
+            connection
+                .settings
+                .insert(SETTINGS_MAX_HEADER_LIST_SIZE, size);
+        }
+        if mode == H2Mode::Upgrade {
+            connection
+                .perform_upgrade(&target, setup.upgrade_request)
+                .await?;
+        }
+        connection.perform_handshake().await?;
+        Ok(connection)
+    }
 
+    /// Performs the HTTP/2 handshake over an already established transport,
+    /// e.g. one wrapped with [`TransportStream::with_faults`]. TLS transports
+    /// must have negotiated `h2`; plain ones are spoken to with prior knowledge.
+    pub async fn connect_over(
+        transport: TransportStream,
+        timeouts: &ClientTimeouts,
+    ) -> Result<Self, ProtocolError> {
+        Self::ensure_h2_alpn(&transport)?;
         let mut connection = Self::new(transport, timeouts.clone());
         connection.perform_handshake().await?;
         Ok(connection)
     }
 
+    fn ensure_h2_alpn(transport: &TransportStream) -> Result<(), ProtocolError> {
+        match transport {
+            TransportStream::Tls(tls) => {
+                if let Some(protocol) = tls.get_ref().1.alpn_protocol() {
+                    if protocol != b"h2" {
+                        return Err(ProtocolError::AlpnMismatch {
+                            negotiated: Some(String::from_utf8_lossy(protocol).into_owned()),
+                        });
+                    }
+                }
+            }
+            TransportStream::Faulty(faulty) => return Self::ensure_h2_alpn(faulty.get_ref()),
+            TransportStream::Tcp(_) => {}
+            #[cfg(unix)]
+            TransportStream::Unix(_) => {}
+        }
+        Ok(())
+    }
+
     pub fn new(stream: TransportStream, timeouts: ClientTimeouts) -> Self {
         let mut settings = HashMap::new();
         settings.insert(SETTINGS_HEADER_TABLE_SIZE, DEFAULT_HEADER_TABLE_SIZE);
@@ -104,10 +443,10 @@ impl H2Connection {
             .get(&SETTINGS_HEADER_TABLE_SIZE)
             .copied()
             .unwrap_or(DEFAULT_HEADER_TABLE_SIZE) as usize;
-        let hpack = HpackCodec::new(
+        let hpack = Box::new(HpackCodec::new(
             local_table_size,
             DEFAULT_HEADER_TABLE_SIZE.max(4096) as usize,
-        );
+        ));
 
         Self {
             stream,
@@ -125,56 +464,157 @@ impl H2Connection {
             goaway_reason: None,
             goaway_last_stream_id: None,
             goaway_received: false,
+            goaway_sent: false,
             pending_writes: Vec::new(),
             pending_write_bytes: 0,
             auto_flush_bytes: None,
             timeouts,
             captured_frames: HashMap::new(),
+            captured_header_blocks: HashMap::new(),
+            settings_ack_timeout: Some(DEFAULT_SETTINGS_ACK_TIMEOUT),
+            settings_sent_at: VecDeque::new(),
+            lax_stream_states: false,
+            data_scheduler: DataScheduler::new(),
+            bdp: None,
+            stats: StatsRecorder::new(),
+            deferred_connection_credit: 0,
+            flood: FloodGuard::new(H2FloodLimits::default()),
+            read_buffer: BytesMut::new(),
+            stream_deadlines: HashMap::new(),
+            stream_limit_policy: StreamLimitPolicy::default(),
+            events: ConnectionEvents::new(),
+            pings_in_flight: VecDeque::new(),
+            prepared_headers: VecDeque::new(),
+            wire_mirror: WireMirror::default(),
+            handshake: HandshakeTracker::new(),
+            header_streaming: None,
+            memory_budget: None,
+            memory: MemoryLedger::default(),
+            queue_limits: None,
+            step_mode: false,
         }
     }
 
     async fn perform_handshake(&mut self) -> Result<(), ProtocolError> {
         // 1. Send HTTP/2 connection preface
+        self.handshake.enter(HandshakePhase::Preface);
         self.write_to_stream(CONNECTION_PREFACE).await?;
 
         // 2. Send initial SETTINGS frame
-        FrameH2::settings(&[
-            (
-                SETTINGS_HEADER_TABLE_SIZE,
-                self.settings[&SETTINGS_HEADER_TABLE_SIZE],
-            ),
-            (SETTINGS_ENABLE_PUSH, self.settings[&SETTINGS_ENABLE_PUSH]),
-            (
-                SETTINGS_MAX_CONCURRENT_STREAMS,
-                self.settings[&SETTINGS_MAX_CONCURRENT_STREAMS],
-            ),
-            (
-                SETTINGS_INITIAL_WINDOW_SIZE,
-                self.settings[&SETTINGS_INITIAL_WINDOW_SIZE],
-            ),
-            (
-                SETTINGS_MAX_FRAME_SIZE,
-                self.settings[&SETTINGS_MAX_FRAME_SIZE],
-            ),
-            (
-                SETTINGS_MAX_HEADER_LIST_SIZE,
-                self.settings[&SETTINGS_MAX_HEADER_LIST_SIZE],
-            ),
-        ])
-        .send(self)
-        .await?;
+        FrameH2::settings(&self.local_settings_list())
+            .send(self)
+            .await?;
 
         self.flush().await?;
 
         // 3. Await the peer's initial SETTINGS frame before proceeding.
+        self.handshake.enter(HandshakePhase::Settings);
         self.await_initial_settings().await?;
 
         // 3. Connection is now open and ready for frames
         // Remote SETTINGS will be handled asynchronously in handle_frame()
         self.state = ConnectionState::Open;
+        self.handshake.enter(HandshakePhase::Ready);
+        Ok(())
+    }
+
+    fn local_settings_list(&self) -> Vec<(u16, u32)> {
+        [
+            SETTINGS_HEADER_TABLE_SIZE,
+            SETTINGS_ENABLE_PUSH,
+            SETTINGS_MAX_CONCURRENT_STREAMS,
+            SETTINGS_INITIAL_WINDOW_SIZE,
+            SETTINGS_MAX_FRAME_SIZE,
+            SETTINGS_MAX_HEADER_LIST_SIZE,
+        ]
+        .iter()
+        .filter_map(|id| self.settings.get(id).map(|value| (*id, *value)))
+        .collect()
+    }
+
+    /// Performs the HTTP/1.1 `Upgrade: h2c` exchange (RFC 7540 Section 3.2),
+    /// upgrading with `request`, or with a GET for the target when `None`.
+    ///
+    /// On success the upgrade request becomes stream 1, already half-closed
+    /// (local); its response is read like any other stream.
+    async fn perform_upgrade(
+        &mut self,
+        target: &Target,
+        request: Option<&Request>,
+    ) -> Result<(), ProtocolError> {
+        self.handshake.enter(HandshakePhase::Preface);
+        let settings_frame = FrameH2::settings(&self.local_settings_list());
+        let settings = base64url_encode(&settings_frame.payload);
+        let raw = match request {
+            Some(request) => {
+                let mut request = request.clone();
+                request.headers.extend([
+                    Header::new(
+                        "Connection".to_string(),
+                        "Upgrade, HTTP2-Settings".to_string(),
+                    ),
+                    Header::new("Upgrade".to_string(), "h2c".to_string()),
+                    Header::new("HTTP2-Settings".to_string(), settings),
+                ]);
+                crate::h1::codec::encode_request(&request)?.to_vec()
+            }
+            None => {
+                let authority = target.authority().ok_or_else(|| {
+                    ProtocolError::InvalidTarget("Target missing host".to_string())
+                })?;
+                let mut path = target.path().to_string();
+                if let Some(query) = target.url.query() {
+                    path.push('?');
+                    path.push_str(query);
+                }
+                format!(
+                    "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\nHTTP2-Settings: {}\r\n\r\n",
+                    path, authority, settings
+                )
+                .into_bytes()
+            }
+        };
+        self.write_to_stream(&raw).await?;
+
+        let raw = self.read_upgrade_response().await?;
+        let text = String::from_utf8_lossy(&raw);
+        let status_line = text.lines().next().unwrap_or_default();
+        let (status, _) = H1::parse_status_line(status_line)?;
+        if status != 101 {
+            return Err(ProtocolError::ConnectionFailed(format!(
+                "Server refused the h2c upgrade: {}",
+                status_line
+            )));
+        }
+
+        let mut stream_info = StreamInfo::new(
+            self.peer_initial_stream_window(),
+            self.local_initial_stream_window(),
+        );
+        stream_info.state = StreamState::HalfClosedLocal;
+        stream_info.headers_sent = true;
+        stream_info.end_stream_sent = true;
+        self.streams.insert(1, stream_info);
+        self.next_stream_id = 3;
         Ok(())
     }
 
+    async fn read_upgrade_response(&mut self) -> Result<Vec<u8>, ProtocolError> {
+        // Read byte-wise so no HTTP/2 frame following the 101 is consumed.
+        let mut raw = Vec::new();
+        let mut byte = [0u8; 1];
+        while !raw.ends_with(b"\r\n\r\n") {
+            if raw.len() >= MAX_UPGRADE_RESPONSE_SIZE {
+                return Err(ProtocolError::InvalidResponse(
+                    "h2c upgrade response headers too large".to_string(),
+                ));
+            }
+            self.read_from_stream(&mut byte).await?;
+            raw.push(byte[0]);
+        }
+        Ok(raw)
+    }
+
     async fn await_initial_settings(&mut self) -> Result<(), ProtocolError> {
         while !self.initial_settings_received {
             let frame = self.read_frame_from_wire().await?;
@@ -197,11 +637,14 @@ impl H2Connection {
     async fn handle_settings_frame(&mut self, frame: &FrameH2) -> Result<(), ProtocolError> {
         if let FrameType::H2(FrameTypeH2::Settings) = &frame.frame_type {
             if frame.is_ack() {
-                // Settings ACK - no action needed
+                self.settings_sent_at.pop_front();
+                self.events.emit(ConnectionEvent::SettingsAcknowledged);
                 return Ok(());
             }
+            self.check_flood(H2FloodKind::Settings).await?;
 
             // Parse settings payload
+            let mut received = Vec::new();
             let mut offset = 0;
             while offset + 6 <= frame.payload.len() {
                 let id = u16::from_be_bytes([frame.payload[offset], frame.payload[offset + 1]]);
@@ -212,9 +655,17 @@ impl H2Connection {
                     frame.payload[offset + 5],
                 ]);
 
-                self.apply_setting(id, value)?;
+                if let Err(err) = self.apply_setting(id, value) {
+                    if matches!(err, ProtocolError::H2FlowControlError(_)) {
+                        self.fail_flow_control().await;
+                    }
+                    return Err(err);
+                }
+                received.push((id as u64, value as u64));
                 offset += 6;
             }
+            self.events
+                .emit(ConnectionEvent::SettingsReceived { settings: received });
 
             // Send SETTINGS ACK response
             FrameH2::settings_ack().send(self).await?;
@@ -236,20 +687,30 @@ impl H2Connection {
                 self.remote_settings.insert(id, value);
             }
             SETTINGS_INITIAL_WINDOW_SIZE => {
-                if value > 0x7FFFFFFF {
-                    return Err(ProtocolError::InvalidResponse(
-                        "Invalid INITIAL_WINDOW_SIZE value".to_string(),
+                if value as i64 > MAX_WINDOW_SIZE {
+                    return Err(ProtocolError::H2FlowControlError(
+                        "INITIAL_WINDOW_SIZE exceeds 2^31-1".to_string(),
                     ));
                 }
                 let old_value = self
                     .remote_settings
                     .get(&id)
                     .unwrap_or(&DEFAULT_INITIAL_WINDOW_SIZE);
-                let delta = value as i32 - *old_value as i32;
-
-                // Update all stream window sizes
+                let delta = value as i64 - *old_value as i64;
+
+                // Windows may legitimately go negative (RFC 9113 Section 6.9.2);
+                // only growth past 2^31-1 is an error.
+                if self
+                    .streams
+                    .values()
+                    .any(|stream| stream.send_window as i64 + delta > MAX_WINDOW_SIZE)
+                {
+                    return Err(ProtocolError::H2FlowControlError(
+                        "INITIAL_WINDOW_SIZE change overflows a stream window".to_string(),
+                    ));
+                }
                 for stream in self.streams.values_mut() {
-                    stream.send_window = (stream.send_window + delta).clamp(0, 0x7FFF_FFFF);
+                    stream.send_window = (stream.send_window as i64 + delta) as i32;
                 }
 
                 self.remote_settings.insert(id, value);
@@ -273,6 +734,13 @@ impl H2Connection {
     }
 
     pub async fn create_stream(&mut self) -> Result<u32, ProtocolError> {
+        self.check_can_open_stream()?;
+        self.wait_for_stream_slot().await?;
+        Ok(self.open_next_stream())
+    }
+
+    /// Fails if no new stream may be opened, concurrency limit aside.
+    fn check_can_open_stream(&self) -> Result<(), ProtocolError> {
         if !self.initial_settings_received {
             return Err(ProtocolError::RequestFailed(
                 "HTTP/2 handshake not complete".to_string(),
@@ -284,6 +752,11 @@ impl H2Connection {
             ));
         }
 
+        if self.goaway_sent {
+            return Err(ProtocolError::RequestFailed(
+                "Connection is shutting down: new streams are not allowed".to_string(),
+            ));
+        }
         if let Some(last) = self.goaway_last_stream_id {
             if self.next_stream_id > last {
                 return Err(ProtocolError::RequestFailed(
@@ -291,7 +764,11 @@ impl H2Connection {
                 ));
             }
         }
+        Ok(())
+    }
 
+    /// Takes the next stream ID and tracks it as idle.
+    fn open_next_stream(&mut self) -> u32 {
         let stream_id = self.next_stream_id;
         self.next_stream_id += 2;
 
@@ -300,8 +777,62 @@ impl H2Connection {
 
         let stream_info = StreamInfo::new(send_window, recv_window);
         self.streams.insert(stream_id, stream_info);
+        stream_id
+    }
+
+    async fn wait_for_stream_slot(&mut self) -> Result<(), ProtocolError> {
+        let limit = self.get_max_concurrent_streams();
+        if self.reserved_stream_count() < limit as usize {
+            return Ok(());
+        }
+        match self.stream_limit_policy {
+            StreamLimitPolicy::Ignore => Ok(()),
+            StreamLimitPolicy::Error => Err(ProtocolError::StreamLimitReached { limit }),
+            // No stream will ever close to make room.
+            StreamLimitPolicy::Wait if limit == 0 => {
+                Err(ProtocolError::StreamLimitReached { limit })
+            }
+            StreamLimitPolicy::Wait => {
+                let bound = self.timeouts.read.or(self.timeouts.connect);
+                timeout_result(bound, self.pump_until_stream_slot()).await
+            }
+        }
+    }
+
+    async fn pump_until_stream_slot(&mut self) -> Result<(), ProtocolError> {
+        self.flush().await?;
+        // Re-read the limit each round: a SETTINGS frame may raise it.
+        while self.reserved_stream_count() >= self.get_max_concurrent_streams() as usize {
+            if matches!(self.state, ConnectionState::Closed) {
+                return Err(self.goaway_error());
+            }
+            self.pump_incoming().await?;
+        }
+        Ok(())
+    }
+
+    /// Streams we created that count against the peer's limit: open or
+    /// half-closed ones (RFC 9113 Section 5.1.2).
+    fn reserved_stream_count(&self) -> usize {
+        self.streams
+            .iter()
+            .filter(|(id, stream)| {
+                *id % 2 == 1 && !matches!(stream.state, StreamState::Idle | StreamState::Closed)
+            })
+            .count()
+    }
+
+    pub fn set_stream_limit_policy(&mut self, policy: StreamLimitPolicy) {
+        self.stream_limit_policy = policy;
+    }
+
+    pub fn stream_limit_policy(&self) -> StreamLimitPolicy {
+        self.stream_limit_policy
+    }
 
-        Ok(stream_id)
+    /// Streams that can still be created before the peer's limit is hit.
+    pub fn available_stream_slots(&self) -> usize {
+        (self.get_max_concurrent_streams() as usize).saturating_sub(self.reserved_stream_count())
     }
 
     pub fn get_stream_state(&self, stream_id: u32) -> Option<&StreamState> {
@@ -330,30 +861,76 @@ impl H2Connection {
         headers: &[Header],
         end_stream: bool,
     ) -> Result<(), ProtocolError> {
+        self.ensure_nothing_prepared()?;
+        self.check_header_list_size(headers)?;
+
         let frames = self.encode_headers_frames(stream_id, headers, end_stream)?;
         for frame in frames {
             frame.send(self).await?;
         }
 
-        if let Some(stream) = self.streams.get_mut(&stream_id) {
-            stream.headers_sent = true;
-            if end_stream {
-                stream.end_stream_sent = true;
-                stream.state = StreamState::HalfClosedLocal;
-            } else {
-                stream.state = StreamState::Open;
+        Ok(())
+    }
+
+    fn check_header_list_size(&self, headers: &[Header]) -> Result<(), ProtocolError> {
+        if let Some(limit) = self.peer_max_header_list_size() {
+            let size = Self::header_list_size(headers);
+            if size > limit as usize {
+                return Err(ProtocolError::HeaderEncodingError(format!(
+                    "Header list of {} bytes exceeds the peer's SETTINGS_MAX_HEADER_LIST_SIZE of {}",
+                    size, limit
+                )));
             }
         }
+        Ok(())
+    }
+
+    /// Uncompressed header list size as defined in RFC 9113 Section 6.5.2.
+    pub fn header_list_size(headers: &[Header]) -> usize {
+        headers
+            .iter()
+            .map(|h| h.name.len() + h.value.as_ref().map_or(0, |v| v.len()) + 32)
+            .sum()
+    }
 
+    /// The peer's advertised SETTINGS_MAX_HEADER_LIST_SIZE, if any.
+    pub fn peer_max_header_list_size(&self) -> Option<u32> {
+        self.remote_settings
+            .get(&SETTINGS_MAX_HEADER_LIST_SIZE)
+            .copied()
+    }
+
+    /// Changes the SETTINGS_MAX_HEADER_LIST_SIZE we advertise so peers may send
+    /// larger response headers. An open connection announces it immediately.
+    pub async fn set_max_header_list_size(&mut self, size: u32) -> Result<(), ProtocolError> {
+        self.settings.insert(SETTINGS_MAX_HEADER_LIST_SIZE, size);
+        if self.initial_settings_received && self.is_connection_open() {
+            FrameH2::settings(&[(SETTINGS_MAX_HEADER_LIST_SIZE, size)])
+                .send(self)
+                .await?;
+        }
         Ok(())
     }
 
+    /// Replaces the connection's header codec. Do this before any header block is
+    /// exchanged; swapping codecs mid-connection desynchronizes HPACK state.
+    pub fn set_header_codec(&mut self, codec: Box<dyn HeaderCodec + Send>) {
+        self.hpack = codec;
+    }
+
+    /// The codec used for this connection's header blocks, e.g. for
+    /// `FrameH2::header_with_codec` so raw frames share its dynamic table.
+    pub fn header_codec_mut(&mut self) -> &mut dyn HeaderCodec {
+        self.hpack.as_mut()
+    }
+
     pub fn build_headers_frames(
         &mut self,
         stream_id: u32,
         headers: &[Header],
         end_stream: bool,
     ) -> Result<Vec<FrameH2>, ProtocolError> {
+        self.ensure_nothing_prepared()?;
         self.encode_headers_frames(stream_id, headers, end_stream)
     }
 
@@ -364,6 +941,11 @@ impl H2Connection {
         end_stream: bool,
     ) -> Result<Vec<FrameH2>, ProtocolError> {
         let mut encoded = self.hpack.encode(headers)?;
+        self.stats.encoder_table.apply(&HeaderBlockInfo::hpack(
+            stream_id,
+            encoded.clone(),
+            headers,
+        ));
         let max_frame = self.max_frame_size();
         let mut first = true;
         let mut frames = Vec::new();
@@ -461,6 +1043,51 @@ impl H2Connection {
         Ok(())
     }
 
+    /// Queues body bytes for `stream_id`; nothing is written until
+    /// `flush_scheduled_data` runs.
+    pub fn queue_data(&mut self, stream_id: u32, data: Bytes, end_stream: bool) {
+        self.data_scheduler.push(stream_id, data, end_stream);
+    }
+
+    /// Sets how many DATA frames `stream_id` may send per scheduling round.
+    pub fn set_stream_weight(&mut self, stream_id: u32, weight: u8) {
+        self.data_scheduler.set_weight(stream_id, weight);
+    }
+
+    /// Writes all queued bodies, interleaving DATA frames across streams and
+    /// waiting for WINDOW_UPDATEs when every stream is blocked.
+    pub async fn flush_scheduled_data(&mut self) -> Result<(), ProtocolError> {
+        while !self.data_scheduler.is_empty() {
+            let max_frame_size = self.max_frame_size();
+            let streams = &self.streams;
+            let next = self.data_scheduler.next_frame(
+                self.send_connection_window,
+                |id| streams.get(&id).map_or(0, |stream| stream.send_window),
+                max_frame_size,
+            );
+
+            match next {
+                Some(frame) => {
+                    self.send_data(frame.stream_id, &frame.payload, frame.end_stream)
+                        .await?;
+                }
+                None => {
+                    if matches!(self.state, ConnectionState::Closed) {
+                        return Err(self.goaway_error());
+                    }
+                    self.pump_incoming().await?;
+                    let streams = &self.streams;
+                    self.data_scheduler.retain(|id| {
+                        streams
+                            .get(&id)
+                            .map_or(false, |stream| stream.state != StreamState::Closed)
+                    });
+                }
+            }
+        }
+        self.flush().await
+    }
+
     pub async fn send_window_update(
         &mut self,
         stream_id: u32,
@@ -471,22 +1098,33 @@ impl H2Connection {
                 "WINDOW_UPDATE increment must be greater than zero".to_string(),
             ));
         }
+        let current = if stream_id == 0 {
+            Some(self.recv_connection_window)
+        } else {
+            self.streams
+                .get(&stream_id)
+                .map(|stream| stream.recv_window)
+        };
+        if let Some(current) = current {
+            if current as i64 + increment as i64 > MAX_WINDOW_SIZE {
+                return Err(ProtocolError::H2FlowControlError(format!(
+                    "WINDOW_UPDATE of {} would push the receive window of stream {} past 2^31-1",
+                    increment, stream_id
+                )));
+            }
+        }
+
         FrameH2::window_update(stream_id, increment)?
             .send(self)
             .await?;
 
+        let increment = Self::clamp_window(increment);
         if stream_id == 0 {
             // Connection-level window update
-            let new_window = self
-                .recv_connection_window
-                .saturating_add(Self::clamp_window(increment));
-            self.recv_connection_window = new_window;
+            self.recv_connection_window += increment;
         } else if let Some(stream) = self.streams.get_mut(&stream_id) {
             // Stream-level window update
-            let new_window = stream
-                .recv_window
-                .saturating_add(Self::clamp_window(increment));
-            stream.recv_window = new_window;
+            stream.recv_window += increment;
         }
 
         Ok(())
@@ -518,7 +1156,7 @@ impl H2Connection {
             .send(self)
             .await?;
 
-        self.state = ConnectionState::Closed;
+        self.mark_closed();
         Ok(())
     }
 
@@ -569,6 +1207,12 @@ impl H2Connection {
 
         let data_size = frame.payload.len() as u32;
         if data_size == 0 {
+            // Keep one credit entry per DATA event for streaming readers.
+            if let Some(stream) = self.streams.get_mut(&stream_id) {
+                if stream.manual_flow_control {
+                    stream.pending_credits.push_back(0);
+                }
+            }
             return Ok(());
         }
 
@@ -596,9 +1240,27 @@ impl H2Connection {
         }
         self.recv_connection_window -= data_window;
 
-        // Release flow control credit now that the payload has been consumed.
-        self.send_window_update(stream_id, data_size).await?;
-        self.send_window_update(0, data_size).await?;
+        let manual = self
+            .streams
+            .get(&stream_id)
+            .map_or(false, |stream| stream.manual_flow_control);
+        if manual {
+            // Released by the streaming body reader as the payload is consumed.
+            if let Some(stream) = self.streams.get_mut(&stream_id) {
+                stream.pending_credits.push_back(data_size);
+            }
+        } else {
+            // Release flow control credit now that the payload has been consumed.
+            self.credit_data(stream_id, data_size).await?;
+        }
+
+        let probe = self
+            .bdp
+            .as_mut()
+            .map_or(false, |bdp| bdp.on_data(data_size as usize));
+        if probe {
+            FrameH2::ping(BDP_PING_PAYLOAD).send(self).await?;
+        }
 
         if frame.is_end_stream() {
             if let Some(stream) = self.streams.get_mut(&stream_id) {
@@ -634,27 +1296,68 @@ impl H2Connection {
             ));
         }
 
-        let increment_val = Self::clamp_window(increment);
-
         if frame.stream_id == 0 {
             // Connection-level window update
-            let new_window = self
-                .send_connection_window
-                .saturating_add(increment_val)
-                .clamp(0, 0x7FFF_FFFF);
-            self.send_connection_window = new_window;
+            let new_window = self.send_connection_window as i64 + increment as i64;
+            if new_window > MAX_WINDOW_SIZE {
+                self.fail_flow_control().await;
+                return Err(ProtocolError::H2FlowControlError(
+                    "WINDOW_UPDATE overflows the connection window".to_string(),
+                ));
+            }
+            self.send_connection_window = new_window as i32;
         } else if let Some(stream) = self.streams.get_mut(&frame.stream_id) {
             // Stream-level window update
-            let new_window = stream
-                .send_window
-                .saturating_add(increment_val)
-                .clamp(0, 0x7FFF_FFFF);
-            stream.send_window = new_window;
+            let new_window = stream.send_window as i64 + increment as i64;
+            if new_window > MAX_WINDOW_SIZE {
+                // A stream error only; the connection carries on (RFC 9113
+                // Section 6.9.1).
+                stream.flow_control_violated = true;
+                return self
+                    .send_rst(frame.stream_id, RstErrorCode::FlowControlError)
+                    .await;
+            }
+            stream.send_window = new_window as i32;
         }
 
         Ok(())
     }
 
+    /// Tears the connection down with FLOW_CONTROL_ERROR (RFC 9113 Section 6.9.1).
+    async fn fail_flow_control(&mut self) {
+        let last_stream_id = self.last_stream_id;
+        let _ = self
+            .send_goaway(last_stream_id, H2ErrorCode::FlowControlError as u32, None)
+            .await;
+        let _ = self.flush().await;
+    }
+
+    /// Inbound control frame caps; see [`H2FloodLimits`].
+    pub fn set_flood_limits(&mut self, limits: H2FloodLimits) {
+        self.flood = FloodGuard::new(limits);
+    }
+
+    pub fn flood_limits(&self) -> H2FloodLimits {
+        self.flood.limits
+    }
+
+    /// Counts a received control frame, tearing the connection down with
+    /// ENHANCE_YOUR_CALM once a flood limit is exceeded.
+    async fn check_flood(&mut self, kind: H2FloodKind) -> Result<(), ProtocolError> {
+        let limit = match self.flood.record(kind) {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        let last_stream_id = self.last_stream_id;
+        let _ = self
+            .send_goaway(last_stream_id, H2ErrorCode::EnhanceYourCalm as u32, None)
+            .await;
+        let _ = self.flush().await;
+        Err(ProtocolError::H2ConnectionError(
+            H2ConnectionErrorKind::FloodDetected { kind, limit },
+        ))
+    }
+
     async fn handle_rst_stream_frame(&mut self, frame: &FrameH2) -> Result<(), ProtocolError> {
         if frame.payload.len() != 4 {
             return Err(ProtocolError::H2ProtocolError(
@@ -662,6 +1365,8 @@ impl H2Connection {
             ));
         }
 
+        self.check_flood(H2FloodKind::RstStream).await?;
+
         let error_code = u32::from_be_bytes([
             frame.payload[0],
             frame.payload[1],
@@ -681,12 +1386,77 @@ impl H2Connection {
 
     async fn handle_ping_frame(&mut self, frame: &FrameH2) -> Result<(), ProtocolError> {
         if !frame.is_ack() {
+            self.check_flood(H2FloodKind::Ping).await?;
             // Send PING ACK with same data
             if frame.payload.len() == 8 {
                 let mut data = [0u8; 8];
                 data.copy_from_slice(&frame.payload);
                 let _ = FrameH2::ping_ack(data).send(self).await;
             }
+            return Ok(());
+        }
+        if let Some(sent_at) = <[u8; 8]>::try_from(frame.payload.as_ref())
+            .ok()
+            .and_then(|data| self.take_ping(data))
+        {
+            self.events.emit(ConnectionEvent::PingRtt {
+                rtt: sent_at.elapsed(),
+            });
+        }
+        if frame.payload.as_ref() == BDP_PING_PAYLOAD {
+            if let Some(window) = self.bdp.as_mut().and_then(|bdp| bdp.on_ping_ack()) {
+                self.grow_receive_window(window).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Timeouts for reads and writes from now on, e.g. those of the next
+    /// request on a reused connection.
+    pub fn set_timeouts(&mut self, timeouts: ClientTimeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Enables BDP-based receive window auto-tuning: DATA arrival is sampled with
+    /// PING round trips and the stream/connection windows grow when they limit
+    /// throughput.
+    pub fn set_adaptive_window(&mut self, enabled: bool) {
+        self.bdp = if enabled {
+            Some(BdpEstimator::new(self.local_initial_stream_window() as u32))
+        } else {
+            None
+        };
+    }
+
+    /// Current auto-tuned stream window, if adaptive windows are enabled.
+    pub fn adaptive_window(&self) -> Option<u32> {
+        self.bdp.as_ref().map(|bdp| bdp.window())
+    }
+
+    /// Smoothed round-trip time measured by BDP probes.
+    pub fn estimated_rtt(&self) -> Option<Duration> {
+        self.bdp.as_ref().and_then(|bdp| bdp.rtt())
+    }
+
+    async fn grow_receive_window(&mut self, window: u32) -> Result<(), ProtocolError> {
+        let delta = window as i64 - self.local_initial_stream_window() as i64;
+        if delta <= 0 {
+            return Ok(());
+        }
+
+        // The peer applies the SETTINGS delta to every open stream window.
+        self.settings.insert(SETTINGS_INITIAL_WINDOW_SIZE, window);
+        for stream in self.streams.values_mut() {
+            stream.recv_window = (stream.recv_window as i64 + delta).min(MAX_WINDOW_SIZE) as i32;
+        }
+        FrameH2::settings(&[(SETTINGS_INITIAL_WINDOW_SIZE, window)])
+            .send(self)
+            .await?;
+
+        let connection_room = MAX_WINDOW_SIZE - self.recv_connection_window as i64;
+        let increment = delta.min(connection_room);
+        if increment > 0 {
+            self.send_window_update(0, increment as u32).await?;
         }
         Ok(())
     }
@@ -735,6 +1505,20 @@ impl H2Connection {
                 stream.state = StreamState::Closed;
             }
         }
+        self.events.emit(ConnectionEvent::GoAwayReceived {
+            last_stream_id: last_stream_id as u64,
+            error_code: Some(error_code as u64),
+            debug_data: frame.payload.slice(8..),
+        });
+        let abandoned: Vec<u32> = self
+            .streams
+            .keys()
+            .copied()
+            .filter(|&id| id > last_stream_id)
+            .collect();
+        for stream_id in abandoned {
+            self.report_stream_closed(stream_id);
+        }
 
         Err(ProtocolError::H2ConnectionError(
             H2ConnectionErrorKind::GoAway(h2_error, debug_data),
@@ -754,40 +1538,215 @@ impl H2Connection {
         self.ensure_stream(stream_id);
 
         loop {
-            if let Some(stream) = self.streams.get_mut(&stream_id) {
-                if let Some(event) = stream.inbound_events.pop_front() {
-                    return Ok(event);
-                }
+            if let Some(event) = self.pop_stream_event(stream_id) {
+                self.release_withheld_credit().await?;
+                return Ok(event);
+            }
+            if let Some((events, bytes)) = self.streams.get(&stream_id).and_then(|s| s.overflow) {
+                return Err(ProtocolError::H2StreamError(
+                    H2StreamErrorKind::QueueOverflow { events, bytes },
+                ));
+            }
+            if self
+                .streams
+                .get(&stream_id)
+                .map_or(false, |s| s.flow_control_violated)
+            {
+                return Err(ProtocolError::H2StreamError(
+                    H2StreamErrorKind::FlowControlViolation,
+                ));
+            }
+            if let Some(kind) = self
+                .streams
+                .get(&stream_id)
+                .and_then(|s| s.header_limit.clone())
+            {
+                return Err(ProtocolError::HeaderLimitExceeded(kind));
             }
 
             if matches!(self.state, ConnectionState::Closed) {
                 return Err(self.goaway_error());
             }
 
-            match self.pump_incoming().await {
-                Ok(()) => {}
-                Err(err) => return Err(err),
+            let deadline = match self.stream_deadlines.get(&stream_id) {
+                Some(deadline) => *deadline,
+                None => {
+                    self.pump_incoming().await?;
+                    continue;
+                }
+            };
+            // Only the frame read is cut short, never the handling of a
+            // frame, so frames for other streams are processed intact.
+            let read = tokio::time::timeout_at(deadline.into(), self.read_frame_from_wire()).await;
+            match read {
+                Ok(frame) => self.process_incoming_frame(frame?).await?,
+                Err(_) => {
+                    self.abandon_stream(stream_id).await?;
+                    return Err(ProtocolError::H2StreamError(
+                        H2StreamErrorKind::DeadlineExceeded,
+                    ));
+                }
             }
         }
     }
 
+    /// Gives `stream_id` until `timeout` from now to finish; a read that is
+    /// still waiting for it then resets the stream with CANCEL and fails with
+    /// [`H2StreamErrorKind::DeadlineExceeded`]. Independent of the
+    /// connection's own timeouts, so other streams keep their budget.
+    /// `None` removes the deadline.
+    pub fn set_stream_timeout(&mut self, stream_id: u32, timeout: Option<Duration>) {
+        match timeout {
+            Some(timeout) => {
+                self.stream_deadlines
+                    .insert(stream_id, Instant::now() + timeout);
+            }
+            None => {
+                self.stream_deadlines.remove(&stream_id);
+            }
+        }
+    }
+
+    pub fn stream_deadline(&self, stream_id: u32) -> Option<Instant> {
+        self.stream_deadlines.get(&stream_id).copied()
+    }
+
+    /// Resets a stream the caller is no longer interested in and drops
+    /// everything kept for it: queued events and outgoing data, captured
+    /// frames and its deadline. Receive credit held for unread DATA goes
+    /// back to the connection window. Frames the peer already sent are
+    /// discarded on arrival without disturbing other streams.
+    pub async fn abandon_stream(&mut self, stream_id: u32) -> Result<(), ProtocolError> {
+        self.stream_deadlines.remove(&stream_id);
+        self.data_scheduler.retain(|id| id != stream_id);
+        self.take_captured_frames(stream_id);
+        self.take_header_blocks(stream_id);
+        self.memory.withheld.remove(&stream_id);
+
+        let (needs_reset, credit) = match self.streams.get_mut(&stream_id) {
+            Some(stream) => {
+                stream.inbound_events.clear();
+                let queued = std::mem::take(&mut stream.queued_bytes);
+                self.memory.queued_events = self.memory.queued_events.saturating_sub(queued);
+                let credit: u32 = stream.pending_credits.drain(..).sum();
+                let open = !matches!(stream.state, StreamState::Idle | StreamState::Closed);
+                (open, credit)
+            }
+            None => (false, 0),
+        };
+        if needs_reset {
+            self.send_rst(stream_id, RstErrorCode::Cancel).await?;
+        }
+        let credit = credit + std::mem::take(&mut self.deferred_connection_credit);
+        if credit > 0 {
+            self.send_window_update(0, credit).await?;
+        }
+        self.flush().await
+    }
+
+    /// Handles a frame for a stream we reset or no longer track, keeping the
+    /// connection consistent: DATA still counts against the connection
+    /// window and header blocks still update the HPACK table.
+    async fn discard_frame(&mut self, frame: &FrameH2) -> Result<(), ProtocolError> {
+        match &frame.frame_type {
+            FrameType::H2(FrameTypeH2::Data) if !frame.payload.is_empty() => {
+                let size = frame.payload.len() as u32;
+                let window = Self::clamp_window(size);
+                if self.recv_connection_window < window {
+                    return Err(ProtocolError::H2FlowControlError(
+                        "Peer violated connection flow control".to_string(),
+                    ));
+                }
+                self.recv_connection_window -= window;
+                self.send_window_update(0, size).await
+            }
+            FrameType::H2(FrameTypeH2::Headers) | FrameType::H2(FrameTypeH2::Continuation)
+                if self.streams.contains_key(&frame.stream_id) =>
+            {
+                self.handle_header_block_fragment(frame).map(|_| ())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Flushes, then handles incoming frames for `duration` without sending
+    /// anything beyond what they require (ACKs, WINDOW_UPDATEs). Responses
+    /// that arrive meanwhile are queued for `read_response`.
+    pub async fn process_frames_for(&mut self, duration: Duration) -> Result<(), ProtocolError> {
+        self.flush().await?;
+        let deadline = Instant::now() + duration;
+        loop {
+            if matches!(self.state, ConnectionState::Closed) {
+                return Err(self.goaway_error());
+            }
+            let read = tokio::time::timeout_at(deadline.into(), self.read_frame_from_wire()).await;
+            match read {
+                Ok(frame) => self.process_incoming_frame(frame?).await?,
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+
+    /// Reads and handles one frame, returning a copy of it. Only the wire
+    /// read is bounded by `timeout`; stream events it produces are queued
+    /// for `read_response` as usual.
+    pub async fn read_next_frame(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<FrameH2, ProtocolError> {
+        self.flush().await?;
+        let frame = timeout_result(timeout, self.read_frame_from_wire()).await?;
+        self.process_incoming_frame(frame.clone()).await?;
+        Ok(frame)
+    }
+
     async fn pump_incoming(&mut self) -> Result<(), ProtocolError> {
         let frame = self.read_frame_from_wire().await?;
         self.process_incoming_frame(frame).await
     }
 
     async fn process_incoming_frame(&mut self, frame: FrameH2) -> Result<(), ProtocolError> {
+        let stream_id = frame.stream_id;
+        let result = self.dispatch_incoming_frame(frame).await;
+        self.report_stream_closed(stream_id);
+        result?;
+        self.enforce_queue_limits(stream_id).await?;
+        self.enforce_memory_budget().await
+    }
+
+    async fn dispatch_incoming_frame(&mut self, frame: FrameH2) -> Result<(), ProtocolError> {
         self.record_frame(&frame);
+        if !self.lax_stream_states {
+            match self.check_inbound_stream_state(&frame) {
+                InboundCheck::Accept => {}
+                InboundCheck::Ignore => return self.discard_frame(&frame).await,
+                InboundCheck::StreamClosed => {
+                    self.send_rst(frame.stream_id, RstErrorCode::StreamClosed)
+                        .await?;
+                    return Ok(());
+                }
+                InboundCheck::ProtocolViolation(msg) => {
+                    let last_stream_id = self.last_stream_id;
+                    let _ = self
+                        .send_goaway(last_stream_id, H2ErrorCode::ProtocolError as u32, None)
+                        .await;
+                    let _ = self.flush().await;
+                    return Err(ProtocolError::H2ConnectionError(
+                        H2ConnectionErrorKind::ProtocolViolation(msg),
+                    ));
+                }
+            }
+        }
         match &frame.frame_type {
             FrameType::H2(FrameTypeH2::Headers) => {
                 self.handle_headers_frame(&frame).await?;
                 if let Some(event) = self.handle_header_block_fragment(&frame)? {
-                    self.enqueue_stream_event(frame.stream_id, event);
+                    self.enqueue_header_event(frame.stream_id, event).await?;
                 }
             }
             FrameType::H2(FrameTypeH2::Continuation) => {
                 if let Some(event) = self.handle_header_block_fragment(&frame)? {
-                    self.enqueue_stream_event(frame.stream_id, event);
+                    self.enqueue_header_event(frame.stream_id, event).await?;
                 }
             }
             FrameType::H2(FrameTypeH2::Data) => {
@@ -840,12 +1799,202 @@ impl H2Connection {
             FrameType::H2(FrameTypeH2::GoAway) => {
                 return self.handle_goaway_frame(&frame).await;
             }
+            FrameType::H2(FrameTypeH2::Unknown(frame_type)) => {
+                self.events.emit(ConnectionEvent::UnknownFrame {
+                    stream_id: frame.stream_id,
+                    frame_type: *frame_type as u64,
+                    payload: frame.payload.clone(),
+                });
+            }
             _ => { /* Ignore unsupported frame types */ }
         }
 
         Ok(())
     }
 
+    /// Disables stream state validation so frames on idle or closed streams are
+    /// processed as if the stream were open.
+    pub fn set_lax_stream_states(&mut self, lax: bool) {
+        self.lax_stream_states = lax;
+    }
+
+    pub fn is_lax_stream_states(&self) -> bool {
+        self.lax_stream_states
+    }
+
+    /// Classifies a received frame against the stream state machine (RFC 9113 Section 5.1).
+    fn check_inbound_stream_state(&self, frame: &FrameH2) -> InboundCheck {
+        let frame_type = match &frame.frame_type {
+            FrameType::H2(frame_type) => frame_type,
+            _ => return InboundCheck::Accept,
+        };
+        if frame.stream_id == 0
+            || !matches!(
+                frame_type,
+                FrameTypeH2::Headers
+                    | FrameTypeH2::Continuation
+                    | FrameTypeH2::Data
+                    | FrameTypeH2::RstStream
+                    | FrameTypeH2::WindowUpdate
+                    | FrameTypeH2::Priority
+            )
+        {
+            return InboundCheck::Accept;
+        }
+
+        let carries_content = matches!(
+            frame_type,
+            FrameTypeH2::Headers | FrameTypeH2::Continuation | FrameTypeH2::Data
+        );
+        let idle_violation = || {
+            InboundCheck::ProtocolViolation(format!(
+                "{:?} frame received on idle stream {}",
+                frame_type, frame.stream_id
+            ))
+        };
+
+        let stream = match self.streams.get(&frame.stream_id) {
+            Some(stream) => stream,
+            None => {
+                if frame.stream_id % 2 == 1 && frame.stream_id < self.next_stream_id {
+                    // A stream we opened and no longer track.
+                    return if carries_content {
+                        InboundCheck::StreamClosed
+                    } else {
+                        InboundCheck::Ignore
+                    };
+                }
+                return if matches!(frame_type, FrameTypeH2::Priority) {
+                    InboundCheck::Accept
+                } else {
+                    idle_violation()
+                };
+            }
+        };
+
+        match stream.state {
+            StreamState::Idle => {
+                if matches!(frame_type, FrameTypeH2::Priority) {
+                    InboundCheck::Accept
+                } else {
+                    idle_violation()
+                }
+            }
+            StreamState::Closed => {
+                if stream.reset_sent || !carries_content {
+                    InboundCheck::Ignore
+                } else {
+                    InboundCheck::StreamClosed
+                }
+            }
+            StreamState::HalfClosedRemote => {
+                let continues_block = matches!(frame_type, FrameTypeH2::Continuation)
+                    && stream.pending_headers.is_some();
+                if carries_content && !continues_block {
+                    InboundCheck::StreamClosed
+                } else {
+                    InboundCheck::Accept
+                }
+            }
+            StreamState::Open | StreamState::HalfClosedLocal => InboundCheck::Accept,
+        }
+    }
+
+    /// Applies the sender-side state transitions for a frame we write.
+    fn track_outgoing_frame(&mut self, frame: &FrameH2) {
+        let frame_type = match &frame.frame_type {
+            FrameType::H2(frame_type) => frame_type,
+            _ => return,
+        };
+        match frame_type {
+            FrameTypeH2::Settings if !frame.is_ack() => {
+                self.settings_sent_at.push_back(Instant::now());
+            }
+            FrameTypeH2::Ping if !frame.is_ack() => {
+                if let Ok(data) = <[u8; 8]>::try_from(frame.payload.as_ref()) {
+                    self.take_ping(data);
+                    if self.pings_in_flight.len() >= MAX_PINGS_IN_FLIGHT {
+                        self.pings_in_flight.pop_front();
+                    }
+                    self.pings_in_flight.push_back((data, Instant::now()));
+                }
+            }
+            FrameTypeH2::Headers if frame.stream_id != 0 => {
+                if frame.stream_id % 2 == 1 && frame.stream_id >= self.next_stream_id {
+                    self.next_stream_id = frame.stream_id + 2;
+                }
+                self.ensure_stream(frame.stream_id);
+                if let Some(stream) = self.streams.get_mut(&frame.stream_id) {
+                    stream.headers_sent = true;
+                    if matches!(stream.state, StreamState::Idle) {
+                        stream.state = StreamState::Open;
+                        self.events.emit(ConnectionEvent::StreamOpened {
+                            stream_id: frame.stream_id,
+                        });
+                    }
+                    if frame.is_end_stream() {
+                        Self::close_local(stream);
+                    }
+                }
+            }
+            FrameTypeH2::Data if frame.is_end_stream() => {
+                if let Some(stream) = self.streams.get_mut(&frame.stream_id) {
+                    Self::close_local(stream);
+                }
+            }
+            FrameTypeH2::RstStream => {
+                if let Some(stream) = self.streams.get_mut(&frame.stream_id) {
+                    stream.state = StreamState::Closed;
+                    stream.reset_sent = true;
+                }
+            }
+            _ => {}
+        }
+        self.report_stream_closed(frame.stream_id);
+    }
+
+    /// Forgets the PING with payload `data`, returning when it was sent.
+    fn take_ping(&mut self, data: [u8; 8]) -> Option<Instant> {
+        let index = self
+            .pings_in_flight
+            .iter()
+            .position(|(sent, _)| *sent == data)?;
+        self.pings_in_flight
+            .remove(index)
+            .map(|(_, sent_at)| sent_at)
+    }
+
+    /// Emits `StreamClosed` the first time `stream_id` is seen closed.
+    fn report_stream_closed(&mut self, stream_id: u32) {
+        let newly_closed = match self.streams.get_mut(&stream_id) {
+            Some(stream) if stream.state == StreamState::Closed && !stream.close_reported => {
+                stream.close_reported = true;
+                true
+            }
+            _ => false,
+        };
+        if newly_closed {
+            self.events
+                .emit(ConnectionEvent::StreamClosed { stream_id });
+        }
+    }
+
+    fn mark_closed(&mut self) {
+        if !matches!(self.state, ConnectionState::Closed) {
+            self.state = ConnectionState::Closed;
+            self.events.emit(ConnectionEvent::Closed);
+        }
+    }
+
+    fn close_local(stream: &mut StreamInfo) {
+        stream.end_stream_sent = true;
+        stream.state = match stream.state {
+            StreamState::Open => StreamState::HalfClosedLocal,
+            StreamState::HalfClosedRemote => StreamState::Closed,
+            _ => stream.state.clone(),
+        };
+    }
+
     fn ensure_stream(&mut self, stream_id: u32) {
         if !self.streams.contains_key(&stream_id) {
             let send_window = self.peer_initial_stream_window();
@@ -902,7 +2051,7 @@ impl H2Connection {
     }
 
     async fn flush_pending_writes(&mut self) -> Result<(), ProtocolError> {
-        if self.pending_writes.is_empty() {
+        if self.pending_writes.is_empty() || self.step_mode {
             return Ok(());
         }
 
@@ -915,12 +2064,46 @@ impl H2Connection {
         self.write_to_stream(&aggregate).await
     }
 
+    /// Queues a decoded header block, or resets its stream if the block is
+    /// larger than the SETTINGS_MAX_HEADER_LIST_SIZE we advertised. It was
+    /// decoded either way, so HPACK state stays in sync.
+    async fn enqueue_header_event(
+        &mut self,
+        stream_id: u32,
+        event: StreamEvent,
+    ) -> Result<(), ProtocolError> {
+        let limit = self.settings.get(&SETTINGS_MAX_HEADER_LIST_SIZE).copied();
+        let size = match &event {
+            StreamEvent::Headers { headers, .. } => Self::header_list_size(headers),
+            _ => 0,
+        };
+        match limit.filter(|limit| size > *limit as usize) {
+            Some(limit) => {
+                self.abandon_stream(stream_id).await?;
+                if let Some(stream) = self.streams.get_mut(&stream_id) {
+                    stream.header_limit = Some(HeaderLimitKind::Total {
+                        limit: limit as usize,
+                        size,
+                    });
+                }
+                Ok(())
+            }
+            None => {
+                self.enqueue_stream_event(stream_id, event);
+                Ok(())
+            }
+        }
+    }
+
     fn enqueue_stream_event(&mut self, stream_id: u32, event: StreamEvent) {
         if stream_id == 0 {
             return;
         }
         self.ensure_stream(stream_id);
         if let Some(stream) = self.streams.get_mut(&stream_id) {
+            let size = event_size(&event);
+            stream.queued_bytes += size;
+            self.memory.queued_events += size;
             stream.inbound_events.push_back(event);
         }
     }
@@ -942,7 +2125,7 @@ impl H2Connection {
             FrameType::H2(FrameTypeH2::Headers) => {
                 let fragment = self.header_fragment_bytes(frame)?;
                 let end_stream = frame.is_end_stream();
-                if frame.is_end_headers() {
+                if frame.is_end_headers() && self.header_streaming.is_none() {
                     let event =
                         self.decode_header_block(stream_id, fragment.as_ref(), end_stream)?;
                     Ok(Some(event))
@@ -950,10 +2133,7 @@ impl H2Connection {
                     let mut pending = PendingHeaderBlock::new();
                     pending.end_stream = end_stream;
                     pending.append(fragment.as_ref());
-                    if let Some(stream) = self.streams.get_mut(&stream_id) {
-                        stream.pending_headers = Some(pending);
-                    }
-                    Ok(None)
+                    self.continue_header_block(stream_id, pending, frame.is_end_headers())
                 }
             }
             FrameType::H2(FrameTypeH2::Continuation) => {
@@ -963,49 +2143,49 @@ impl H2Connection {
                     ));
                 }
 
-                let fragment = frame.payload.clone();
-                {
-                    let stream = self.streams.get_mut(&stream_id).ok_or_else(|| {
-                        ProtocolError::H2ProtocolError(
-                            "CONTINUATION frame without a preceding HEADERS frame".to_string(),
-                        )
-                    })?;
-                    if let Some(pending) = stream.pending_headers.as_mut() {
-                        pending.append(fragment.as_ref());
-                    } else {
-                        return Err(ProtocolError::H2ProtocolError(
-                            "CONTINUATION frame without pending header block".to_string(),
-                        ));
-                    }
-                }
-
-                if frame.is_end_headers() {
-                    let (block, end_stream) = {
-                        let stream = self.streams.get_mut(&stream_id).ok_or_else(|| {
-                            ProtocolError::H2ProtocolError(
-                                "Missing stream state for CONTINUATION frame".to_string(),
-                            )
-                        })?;
-                        let pending = stream.pending_headers.take().ok_or_else(|| {
-                            ProtocolError::H2ProtocolError(
-                                "CONTINUATION frame without pending header block".to_string(),
-                            )
-                        })?;
-                        let end_stream = pending.end_stream;
-                        (pending.block.freeze(), end_stream)
-                    };
-
-                    // decode_header_block will update final_headers_received as needed.
-                    let event = self.decode_header_block(stream_id, block.as_ref(), end_stream)?;
-                    Ok(Some(event))
-                } else {
-                    Ok(None)
-                }
+                let stream = self.streams.get_mut(&stream_id).ok_or_else(|| {
+                    ProtocolError::H2ProtocolError(
+                        "CONTINUATION frame without a preceding HEADERS frame".to_string(),
+                    )
+                })?;
+                let mut pending = stream.pending_headers.take().ok_or_else(|| {
+                    ProtocolError::H2ProtocolError(
+                        "CONTINUATION frame without pending header block".to_string(),
+                    )
+                })?;
+                pending.append(frame.payload.as_ref());
+                self.continue_header_block(stream_id, pending, frame.is_end_headers())
             }
             _ => Ok(None),
         }
     }
 
+    /// Parks `pending` on its stream until END_HEADERS, decoding what it
+    /// can already when header streaming is on.
+    fn continue_header_block(
+        &mut self,
+        stream_id: u32,
+        mut pending: PendingHeaderBlock,
+        end_headers: bool,
+    ) -> Result<Option<StreamEvent>, ProtocolError> {
+        self.stream_header_fragment(stream_id, &mut pending, end_headers)?;
+        if !end_headers {
+            if let Some(stream) = self.streams.get_mut(&stream_id) {
+                stream.pending_headers = Some(pending);
+            }
+            return Ok(None);
+        }
+
+        // header_block_event will update final_headers_received as needed.
+        let event = match pending.info.take() {
+            Some(info) => {
+                self.header_block_event(stream_id, pending.decoded, info, pending.end_stream)
+            }
+            None => self.decode_header_block(stream_id, &pending.block, pending.end_stream),
+        }?;
+        Ok(Some(event))
+    }
+
     fn header_fragment_bytes(&self, frame: &FrameH2) -> Result<Bytes, ProtocolError> {
         let payload = &frame.payload;
         let mut offset = 0usize;
@@ -1058,6 +2238,27 @@ impl H2Connection {
         end_stream: bool,
     ) -> Result<StreamEvent, ProtocolError> {
         let headers = self.hpack.decode(block)?;
+        let block_info = HeaderBlockInfo::hpack(stream_id, Bytes::copy_from_slice(block), &headers);
+        self.stats.decoder_table.apply(&block_info);
+        self.header_block_event(stream_id, headers, block_info, end_stream)
+    }
+
+    /// Records a decoded header block and turns it into the stream's event.
+    /// The decoder table stats must already include it.
+    fn header_block_event(
+        &mut self,
+        stream_id: u32,
+        headers: Vec<Header>,
+        block_info: HeaderBlockInfo,
+        end_stream: bool,
+    ) -> Result<StreamEvent, ProtocolError> {
+        if !self.memory.capture_dropped {
+            self.memory.captured += block_info.raw.len();
+            self.captured_header_blocks
+                .entry(stream_id)
+                .or_default()
+                .push(block_info);
+        }
 
         let status_code = headers.iter().find_map(|h| {
             (h.name == ":status")
@@ -1112,15 +2313,37 @@ impl H2Connection {
     }
     pub async fn send_frame(&mut self, frame: &FrameH2) -> Result<(), ProtocolError> {
         let serialized = frame.serialize()?;
-        self.queue_serialized_frame(serialized).await
+        self.enqueue_frame(frame, serialized);
+        self.flush_if_due().await
+    }
+
+    /// Writes bytes verbatim, e.g. a hand-crafted frame, flushing anything
+    /// queued ahead of them first, so they are on the wire when this returns
+    /// regardless of `auto_flush_bytes` (in step mode they stay queued like
+    /// everything else). They are counted in the stats but do not change
+    /// stream state or flow-control windows.
+    pub async fn write_raw(&mut self, data: Bytes) -> Result<(), ProtocolError> {
+        self.enqueue_serialized(data);
+        self.flush_pending_writes().await
+    }
+
+    /// Queues `frame`, already serialized, for the next flush and applies
+    /// it to the stream states.
+    fn enqueue_frame(&mut self, frame: &FrameH2, serialized: Bytes) {
+        self.track_outgoing_frame(frame);
+        self.enqueue_serialized(serialized);
     }
 
-    async fn queue_serialized_frame(&mut self, serialized: Bytes) -> Result<(), ProtocolError> {
+    fn enqueue_serialized(&mut self, serialized: Bytes) {
+        self.stats.on_frame_sent(&serialized);
         self.pending_write_bytes += serialized.len();
         self.pending_writes.push(serialized);
+    }
 
+    /// Writes the queue out unless `auto_flush_bytes` lets it grow further.
+    async fn flush_if_due(&mut self) -> Result<(), ProtocolError> {
         let should_flush = match self.auto_flush_bytes {
-            Some(threshold) => self.pending_write_bytes >= threshold,
+            Some(threshold) => self.pending_write_bytes >= threshold || self.over_budget(),
             None => true,
         };
 
@@ -1131,54 +2354,142 @@ impl H2Connection {
         Ok(())
     }
 
+    /// Sets how long the peer has to acknowledge each SETTINGS frame we send.
+    /// `None` disables the check.
+    pub fn set_settings_ack_timeout(&mut self, timeout: Option<Duration>) {
+        self.settings_ack_timeout = timeout;
+    }
+
+    pub fn get_settings_ack_timeout(&self) -> Option<Duration> {
+        self.settings_ack_timeout
+    }
+
+    /// Number of SETTINGS frames sent but not yet acknowledged.
+    pub fn pending_settings_acks(&self) -> usize {
+        self.settings_sent_at.len()
+    }
+
+    fn settings_ack_deadline(&self) -> Option<Instant> {
+        let timeout = self.settings_ack_timeout?;
+        self.settings_sent_at
+            .front()
+            .map(|sent_at| *sent_at + timeout)
+    }
+
+    async fn fail_settings_timeout(&mut self) -> ProtocolError {
+        let last_stream_id = self.last_stream_id;
+        let _ = self
+            .send_goaway(last_stream_id, H2ErrorCode::SettingsTimeout as u32, None)
+            .await;
+        let _ = self.flush().await;
+        ProtocolError::H2ConnectionError(H2ConnectionErrorKind::SettingsTimeout)
+    }
+
     async fn read_frame_from_wire(&mut self) -> Result<FrameH2, ProtocolError> {
-        // Read frame header (9 bytes)
-        let mut header_buf = [0u8; FRAME_HEADER_SIZE];
-        self.read_from_stream(&mut header_buf).await?;
+        if self.step_mode {
+            return Err(ProtocolError::StepModeBlocked);
+        }
+        self.read_wire_frame().await
+    }
 
-        // Parse header to get payload length
-        let length =
-            ((header_buf[0] as u32) << 16) | ((header_buf[1] as u32) << 8) | (header_buf[2] as u32);
+    /// Reads one frame, failing once a SETTINGS ACK is overdue.
+    async fn read_wire_frame(&mut self) -> Result<FrameH2, ProtocolError> {
+        let deadline = match self.settings_ack_deadline() {
+            Some(deadline) => deadline,
+            None => return self.read_frame_inner().await,
+        };
 
-        // Read payload
-        let mut payload_buf = vec![0u8; length as usize];
-        if length > 0 {
-            self.read_from_stream(&mut payload_buf).await?;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(self.fail_settings_timeout().await);
         }
+        match tokio::time::timeout(remaining, self.read_frame_inner()).await {
+            Ok(result) => result,
+            Err(_) => Err(self.fail_settings_timeout().await),
+        }
+    }
 
-        // Combine header and payload for parsing
-        let mut frame_buf = Vec::with_capacity(FRAME_HEADER_SIZE + length as usize);
-        frame_buf.extend_from_slice(&header_buf);
-        frame_buf.extend_from_slice(&payload_buf);
+    async fn read_frame_inner(&mut self) -> Result<FrameH2, ProtocolError> {
+        // The frame stays in the buffer until it is complete, so a timeout
+        // between its header and payload does not desynchronize the stream.
+        self.fill_read_buffer(FRAME_HEADER_SIZE).await?;
 
+        if !self.initial_settings_received && self.read_buffer.starts_with(b"HTTP/1.") {
+            let head = self.read_buffer.split_to(FRAME_HEADER_SIZE).to_vec();
+            let raw = self.drain_http1_response(head).await;
+            return Err(ProtocolError::H2UnexpectedHttp1Response(raw));
+        }
+
+        // Parse header to get payload length
+        let header = &self.read_buffer[..FRAME_HEADER_SIZE];
+        let length =
+            ((header[0] as usize) << 16) | ((header[1] as usize) << 8) | header[2] as usize;
+        self.fill_read_buffer(FRAME_HEADER_SIZE + length).await?;
+
+        let frame_buf = self.read_buffer.split_to(FRAME_HEADER_SIZE + length);
+        self.stats
+            .on_frame_received(&frame_buf[..FRAME_HEADER_SIZE]);
         FrameH2::parse(&frame_buf)
     }
 
+    /// Best-effort read of the HTTP/1.x response head a server sent instead of SETTINGS.
+    async fn drain_http1_response(&mut self, mut raw: Vec<u8>) -> Vec<u8> {
+        let mut byte = [0u8; 1];
+        while !raw.ends_with(b"\r\n\r\n") && raw.len() < MAX_UPGRADE_RESPONSE_SIZE {
+            if self.read_from_stream(&mut byte).await.is_err() {
+                break;
+            }
+            raw.push(byte[0]);
+        }
+        self.state = ConnectionState::Closed;
+        raw
+    }
+
     async fn write_to_stream(&mut self, data: &[u8]) -> Result<(), ProtocolError> {
         let write_timeout = self.timeouts.write;
         timeout_result(write_timeout, async {
-            match &mut self.stream {
-                TransportStream::Tcp(tcp) => tcp.write_all(data).await.map_err(ProtocolError::Io),
-                TransportStream::Tls(tls) => tls.write_all(data).await.map_err(ProtocolError::Io),
-            }
+            self.stream.write_all(data).await.map_err(ProtocolError::Io)
         })
-        .await
+        .await?;
+        self.stats.sent.wire_bytes += data.len() as u64;
+        self.wire_mirror.record(None, WireDirection::Sent, data);
+        Ok(())
     }
 
     async fn read_from_stream(&mut self, buffer: &mut [u8]) -> Result<usize, ProtocolError> {
+        self.fill_read_buffer(buffer.len()).await?;
+        let bytes = self.read_buffer.split_to(buffer.len());
+        buffer.copy_from_slice(&bytes);
+        Ok(buffer.len())
+    }
+
+    /// Reads until at least `len` bytes are buffered. Bytes are only ever
+    /// added to the buffer, so a read abandoned by a timeout loses nothing
+    /// and the next frame still starts where the last one ended.
+    async fn fill_read_buffer(&mut self, len: usize) -> Result<(), ProtocolError> {
         let read_timeout = self.timeouts.read;
-        timeout_result(read_timeout, async {
-            match &mut self.stream {
-                TransportStream::Tcp(tcp) => {
-                    tcp.read_exact(buffer).await.map_err(ProtocolError::Io)?;
-                }
-                TransportStream::Tls(tls) => {
-                    tls.read_exact(buffer).await.map_err(ProtocolError::Io)?;
-                }
+        while self.read_buffer.len() < len {
+            let buffered = self.read_buffer.len();
+            self.read_buffer
+                .reserve((len - buffered).max(READ_CHUNK_SIZE));
+            let read = timeout_result(read_timeout, async {
+                self.stream
+                    .read_buf(&mut self.read_buffer)
+                    .await
+                    .map_err(ProtocolError::Io)
+            })
+            .await?;
+            if read == 0 {
+                return Err(ProtocolError::Io(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "connection closed mid-frame",
+                )));
             }
-            Ok(buffer.len())
-        })
-        .await
+            self.stats.received.wire_bytes += read as u64;
+            self.wire_mirror
+                .record(None, WireDirection::Received, &self.read_buffer[buffered..]);
+        }
+        Ok(())
     }
 
     pub fn is_connection_open(&self) -> bool {
@@ -1188,6 +2499,22 @@ impl H2Connection {
         )
     }
 
+    /// True if `create_stream` may open another stream: the connection is
+    /// open and no GOAWAY, sent or received, rules it out.
+    pub fn accepts_new_streams(&self) -> bool {
+        self.is_connection_open()
+            && !self.goaway_sent
+            && self
+                .goaway_last_stream_id
+                .map_or(true, |last| self.next_stream_id <= last)
+    }
+
+    /// Last stream ID of the GOAWAY the peer sent. Streams we opened above
+    /// it were not processed and may be retried on another connection.
+    pub fn goaway_last_stream_id(&self) -> Option<u32> {
+        self.goaway_last_stream_id
+    }
+
     pub fn get_max_concurrent_streams(&self) -> u32 {
         self.remote_settings
             .get(&SETTINGS_MAX_CONCURRENT_STREAMS)
@@ -1209,10 +2536,111 @@ impl H2Connection {
             .count()
     }
 
+    /// Snapshot of frame and byte counters, flow-control windows and HPACK
+    /// table occupancy. Frames count as sent once queued, even before a flush.
+    pub fn stats(&self) -> H2ConnectionStats {
+        let mut streams = self.stats.streams.clone();
+        for (stream_id, stream) in streams.iter_mut() {
+            if let Some(info) = self.streams.get(stream_id) {
+                stream.send_window = Some(info.send_window);
+                stream.recv_window = Some(info.recv_window);
+            }
+        }
+
+        H2ConnectionStats {
+            sent: self.stats.sent.clone(),
+            received: self.stats.received.clone(),
+            send_window: self.send_connection_window,
+            recv_window: self.recv_connection_window,
+            active_streams: self.get_active_stream_count(),
+            streams,
+            hpack_encoder: self.stats.encoder_table.snapshot(),
+            hpack_decoder: self.stats.decoder_table.snapshot(),
+        }
+    }
+
+    /// Bytes written to and read from the transport so far, without
+    /// building a full [`stats`](Self::stats) snapshot.
+    pub fn wire_bytes(&self) -> (u64, u64) {
+        (self.stats.sent.wire_bytes, self.stats.received.wire_bytes)
+    }
+
     pub async fn close(&mut self) -> Result<(), ProtocolError> {
         self.send_goaway(self.last_stream_id, 0, None).await
     }
 
+    /// Phases of the handshake that set up the connection, with when each
+    /// started.
+    pub fn handshake(&self) -> &HandshakeTracker {
+        &self.handshake
+    }
+
+    /// Receives every [`ConnectionEvent`] from now on. Events are only
+    /// produced while the connection is driven, e.g. by reading a response
+    /// or `process_frames_for`.
+    pub fn subscribe_events(&mut self) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Sends a PING with `data` and flushes it; its ACK yields a
+    /// `ConnectionEvent::PingRtt`.
+    pub async fn ping(&mut self, data: [u8; 8]) -> Result<(), ProtocolError> {
+        FrameH2::ping(data).send(self).await?;
+        self.flush().await
+    }
+
+    /// Sends GOAWAY with `error_code` and optional debug data, then keeps the
+    /// connection running until every in-flight stream has been answered or
+    /// `drain_timeout` elapses, and only then marks it closed. `None` waits
+    /// for [`DEFAULT_DRAIN_TIMEOUT`].
+    ///
+    /// Queued request bodies are still sent while draining, and responses that
+    /// complete meanwhile can be read with `read_response` afterwards.
+    pub async fn shutdown_graceful(
+        &mut self,
+        error_code: u32,
+        debug_data: Option<&[u8]>,
+        drain_timeout: Option<Duration>,
+    ) -> Result<(), ProtocolError> {
+        // We never accept server-initiated streams, so none were processed.
+        FrameH2::goaway(0, error_code, debug_data)
+            .send(self)
+            .await?;
+        self.goaway_sent = true;
+
+        let drain_timeout = drain_timeout.unwrap_or(DEFAULT_DRAIN_TIMEOUT);
+        let drained = timeout_result(Some(drain_timeout), self.drain_streams()).await;
+        self.mark_closed();
+        match drained {
+            Ok(()) | Err(ProtocolError::Timeout(_)) => Ok(()),
+            // The peer closing the connection ends the drain as well.
+            Err(ProtocolError::H2ConnectionError(H2ConnectionErrorKind::GoAway(..))) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn drain_streams(&mut self) -> Result<(), ProtocolError> {
+        self.flush_scheduled_data().await?;
+        self.flush().await?;
+        while self.has_in_flight_streams() {
+            if matches!(self.state, ConnectionState::Closed) {
+                return Err(self.goaway_error());
+            }
+            self.pump_incoming().await?;
+        }
+        Ok(())
+    }
+
+    /// Streams still waiting for the peer to finish its response.
+    fn has_in_flight_streams(&self) -> bool {
+        self.streams.values().any(|stream| {
+            matches!(
+                stream.state,
+                StreamState::Open | StreamState::HalfClosedLocal
+            )
+        })
+    }
+
     pub async fn read_response(self: &mut Self, stream_id: u32) -> Result<Response, ProtocolError> {
         self.read_response_options(stream_id, None, None, None, None)
             .await
@@ -1225,21 +2653,73 @@ impl H2Connection {
         event_timeout: Option<Duration>,
         max_events: Option<usize>,
         event_handler: Option<&dyn Fn(&StreamEvent)>,
+    ) -> Result<Response, ProtocolError> {
+        let adapter = |event: &StreamEvent, _: &ReadBudget| {
+            if let Some(handler) = event_handler {
+                handler(event);
+            }
+        };
+        let handler = event_handler.map(|_| &adapter as &dyn Fn(&StreamEvent, &ReadBudget));
+        self.read_response_with_budget(
+            stream_id,
+            overall_timeout,
+            event_timeout,
+            max_events,
+            handler,
+        )
+        .await
+    }
+
+    /// Like [`read_response_options`](Self::read_response_options), but the
+    /// handler also sees the remaining budget. The overall timeout bounds every
+    /// wait, so a stream that goes quiet cannot hold the read past it.
+    pub async fn read_response_with_budget(
+        &mut self,
+        stream_id: u32,
+        overall_timeout: Option<Duration>,
+        event_timeout: Option<Duration>,
+        max_events: Option<usize>,
+        event_handler: Option<&dyn Fn(&StreamEvent, &ReadBudget)>,
+    ) -> Result<Response, ProtocolError> {
+        let mut handler = event_handler;
+        self.read_response_with_handler(
+            stream_id,
+            overall_timeout,
+            event_timeout,
+            max_events,
+            handler.as_mut().map(|h| h as &mut dyn StreamEventHandler),
+        )
+        .await
+    }
+
+    /// Reads a response while handing every event to `event_handler`, which
+    /// may be async: pass a channel sender to forward events to another task,
+    /// an [`AsyncEventHandler`], or a closure that mutates local state.
+    pub async fn read_response_with_handler(
+        &mut self,
+        stream_id: u32,
+        overall_timeout: Option<Duration>,
+        event_timeout: Option<Duration>,
+        max_events: Option<usize>,
+        mut event_handler: Option<&mut dyn StreamEventHandler>,
     ) -> Result<Response, ProtocolError> {
         let protocol = "HTTP/2.0".to_string();
         let mut status: Option<u16> = None;
         let mut headers = Vec::new();
         let mut body = Vec::new();
+        self.memory.bodies = 0;
         let mut trailers: Option<Vec<Header>> = None;
         let mut event_count = 0;
         let start_time = std::time::Instant::now();
+        let header_deadline = self.timeouts.header_timeout().map(|d| start_time + d);
+        let mut body_deadline: Option<Instant> = None;
+        let overall_deadline = overall_timeout.map(|d| start_time + d);
 
         loop {
             // Check overall timeout
-            if let Some(timeout) = overall_timeout {
-                if start_time.elapsed() >= timeout {
-                    break;
-                }
+            let remaining = time_remaining(overall_deadline);
+            if remaining.map_or(false, |r| r.is_zero()) {
+                break;
             }
 
             // Check max events limit
@@ -1249,14 +2729,31 @@ impl H2Connection {
                 }
             }
 
-            // Read event with optional timeout
+            // Read event with optional timeout; running out of the overall
+            // budget ends the read like an event timeout does.
             let event_result = if let Some(timeout) = event_timeout {
-                match tokio::time::timeout(timeout, self.recv_stream_event(stream_id)).await {
+                let wait = remaining.map_or(timeout, |r| r.min(timeout));
+                match tokio::time::timeout(wait, self.recv_stream_event(stream_id)).await {
                     Ok(result) => result,
                     Err(_) => break, // Timeout - no more events
                 }
             } else {
-                self.recv_stream_event(stream_id).await
+                let phase_timeout = if status.is_none() {
+                    time_remaining(header_deadline)
+                } else {
+                    self.timeouts.next_body_read_timeout(body_deadline)
+                };
+                match remaining {
+                    Some(remaining) if phase_timeout.map_or(true, |phase| remaining < phase) => {
+                        match tokio::time::timeout(remaining, self.recv_stream_event(stream_id))
+                            .await
+                        {
+                            Ok(result) => result,
+                            Err(_) => break,
+                        }
+                    }
+                    _ => timeout_result(phase_timeout, self.recv_stream_event(stream_id)).await,
+                }
             };
 
             let event = match event_result {
@@ -1264,12 +2761,24 @@ impl H2Connection {
                     event_count += 1;
 
                     // Call event handler if provided
-                    if let Some(handler) = event_handler {
-                        handler(&event);
+                    if let Some(handler) = event_handler.as_mut() {
+                        let budget = ReadBudget {
+                            elapsed: start_time.elapsed(),
+                            remaining: time_remaining(overall_deadline),
+                            events: event_count,
+                            remaining_events: max_events.map(|max| max.saturating_sub(event_count)),
+                        };
+                        handler.on_event(&event, &budget).await;
                     }
 
                     event
                 }
+                Err(
+                    e @ ProtocolError::H2StreamError(
+                        H2StreamErrorKind::DeadlineExceeded
+                        | H2StreamErrorKind::QueueOverflow { .. },
+                    ),
+                ) => return Err(e),
                 Err(e) => {
                     if event_handler.is_some() {
                         // If we have a handler, this might be expected (like for testing)
@@ -1318,6 +2827,10 @@ impl H2Connection {
 
                         status = Some(code);
                         headers = filtered;
+                        body_deadline = self
+                            .timeouts
+                            .body_total_timeout()
+                            .map(|d| Instant::now() + d);
 
                         if end_stream {
                             break;
@@ -1336,6 +2849,8 @@ impl H2Connection {
                     end_stream,
                 } => {
                     body.extend_from_slice(&payload);
+                    self.memory.bodies = body.len();
+                    self.enforce_memory_budget().await?;
                     if end_stream {
                         break;
                     }
@@ -1353,6 +2868,9 @@ impl H2Connection {
         })?;
 
         let cookies = Response::collect_cookies(&headers);
+        self.stream_deadlines.remove(&stream_id);
+        self.memory.bodies = 0;
+        let (frames, frame_times) = self.take_captured_frames(stream_id);
 
         Ok(Response {
             status,
@@ -1360,10 +2878,15 @@ impl H2Connection {
             headers,
             body: Bytes::from(body),
             trailers,
-            frames: self
-                .take_captured_frames(stream_id)
-                .map(|frames| frames.into_iter().map(ResponseFrame::Http2).collect()),
+            frames,
+            header_blocks: self.take_header_blocks(stream_id),
             cookies,
+            decoded_body: None,
+            frame_times,
+            digest: None,
+            transfer_codings: Vec::new(),
+            truncated: false,
+            truncation: None,
         })
     }
 }
@@ -1371,25 +2894,47 @@ impl H2Connection {
 #[async_trait(?Send)]
 impl FrameSink<FrameH2> for H2Connection {
     async fn write_frame(&mut self, frame: FrameH2) -> Result<(), ProtocolError> {
-        let serialized = frame.serialize()?;
-        self.queue_serialized_frame(serialized).await
+        self.send_frame(&frame).await
     }
 }
 
 impl H2Connection {
     fn record_frame(&mut self, frame: &FrameH2) {
-        if frame.stream_id == 0 {
+        let abandoned = self
+            .streams
+            .get(&frame.stream_id)
+            .map_or(false, |stream| stream.reset_sent);
+        if frame.stream_id == 0 || abandoned || self.memory.capture_dropped {
             return;
         }
 
+        self.memory.captured += captured_frame_size(frame.payload.len());
         self.captured_frames
             .entry(frame.stream_id)
             .or_default()
-            .push(frame.clone());
+            .push((Instant::now(), frame.clone()));
     }
 
-    fn take_captured_frames(&mut self, stream_id: u32) -> Option<Vec<FrameH2>> {
-        self.captured_frames.remove(&stream_id)
+    /// Captured frames of `stream_id` and their arrival times.
+    fn take_captured_frames(
+        &mut self,
+        stream_id: u32,
+    ) -> (Option<Vec<ResponseFrame>>, Option<Vec<Instant>>) {
+        match self.captured_frames.remove(&stream_id) {
+            Some(captured) => {
+                let size: usize = captured
+                    .iter()
+                    .map(|(_, frame)| captured_frame_size(frame.payload.len()))
+                    .sum();
+                self.memory.captured = self.memory.captured.saturating_sub(size);
+                let (times, frames): (Vec<_>, Vec<_>) = captured
+                    .into_iter()
+                    .map(|(at, frame)| (at, ResponseFrame::Http2(frame)))
+                    .unzip();
+                (Some(frames), Some(times))
+            }
+            None => (None, None),
+        }
     }
 }
 
@@ -1399,7 +2944,7 @@ impl HttpConnection for H2Connection {
     type ReadOptions = u32;
 
     async fn connect(options: Self::ConnectOptions) -> Result<Self, ProtocolError> {
-        H2Connection::connect(&options.target, &options.timeouts).await
+        H2Connection::connect_with_mode(&options.target, &options.timeouts, options.mode).await
     }
 
     async fn read_response(
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+/// Opaque payload identifying our bandwidth-delay probes among other PINGs.
+pub(super) const BDP_PING_PAYLOAD: [u8; 8] = *b"rhlbdp\0\0";
+
+/// Upper bound for auto-tuned windows (16 MiB), in line with browser defaults.
+pub(super) const BDP_MAX_WINDOW: u32 = 16 * 1024 * 1024;
+
+/// Estimates the bandwidth-delay product by timing a PING round trip and
+/// counting the DATA bytes that arrive meanwhile.
+///
+/// When a sample fills most of the current window the window is too small to
+/// keep the link busy, so the estimator proposes doubling it.
+pub(super) struct BdpEstimator {
+    window: u32,
+    bytes: usize,
+    ping_sent_at: Option<Instant>,
+    rtt: Option<Duration>,
+}
+
+impl BdpEstimator {
+    pub(super) fn new(window: u32) -> Self {
+        Self {
+            window,
+            bytes: 0,
+            ping_sent_at: None,
+            rtt: None,
+        }
+    }
+
+    /// Records received DATA; returns true when a probe PING should be sent.
+    pub(super) fn on_data(&mut self, len: usize) -> bool {
+        if self.window >= BDP_MAX_WINDOW {
+            return false;
+        }
+        if self.ping_sent_at.is_none() {
+            self.bytes = len;
+            self.ping_sent_at = Some(Instant::now());
+            return true;
+        }
+        self.bytes += len;
+        false
+    }
+
+    /// Completes a sample; returns the new window size when it should grow.
+    pub(super) fn on_ping_ack(&mut self) -> Option<u32> {
+        let sent_at = self.ping_sent_at.take()?;
+        let sample = sent_at.elapsed();
+        self.rtt = Some(match self.rtt {
+            Some(rtt) => (rtt * 7 + sample) / 8,
+            None => sample,
+        });
+
+        let bdp = self.bytes as u64;
+        self.bytes = 0;
+        if bdp * 3 < self.window as u64 * 2 {
+            return None;
+        }
+
+        let grown = (bdp * 2).min(BDP_MAX_WINDOW as u64) as u32;
+        if grown <= self.window {
+            return None;
+        }
+        self.window = grown;
+        Some(grown)
+    }
+
+    pub(super) fn window(&self) -> u32 {
+        self.window
+    }
+
+    pub(super) fn rtt(&self) -> Option<Duration> {
+        self.rtt
+    }
+}
@@ -0,0 +1,239 @@
+use super::state::StreamEvent;
+use super::H2Connection;
+use crate::types::{
+    H2StreamErrorKind, HeaderBlockInfo, MemoryBudget, MemoryPressure, MemoryUsage, ProtocolError,
+    QueueOverflow, StreamQueueLimits,
+};
+use std::collections::HashMap;
+
+/// Frame header bytes counted with each captured frame's payload.
+const FRAME_HEADER_LEN: usize = 9;
+
+/// Running totals behind [`H2Connection::memory_usage`].
+#[derive(Debug, Default)]
+pub(super) struct MemoryLedger {
+    pub(super) captured: usize,
+    pub(super) queued_events: usize,
+    pub(super) bodies: usize,
+    /// Set once `DropCapture` discarded the captures; nothing is captured
+    /// afterwards.
+    pub(super) capture_dropped: bool,
+    /// Under `Backpressure`: DATA is not credited back while set.
+    pub(super) withholding: bool,
+    /// Stream credit held back while withholding; the connection's goes to
+    /// `deferred_connection_credit`.
+    pub(super) withheld: HashMap<u32, u32>,
+}
+
+impl H2Connection {
+    /// Caps what this connection buffers, see [`MemoryBudget`]. `None`, the
+    /// default, leaves it unbounded.
+    pub fn set_memory_budget(&mut self, budget: Option<MemoryBudget>) {
+        self.memory_budget = budget;
+    }
+
+    pub fn memory_budget(&self) -> Option<MemoryBudget> {
+        self.memory_budget
+    }
+
+    /// Bytes the connection holds right now.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            pending_writes: self.pending_write_bytes,
+            captured: self.memory.captured,
+            queued_events: self.memory.queued_events,
+            bodies: self.memory.bodies,
+            read_buffer: self.read_buffer.len(),
+        }
+    }
+
+    /// True once a [`MemoryPressure::DropCapture`] budget discarded the
+    /// captures; responses read afterwards carry no frames.
+    pub fn capture_dropped(&self) -> bool {
+        self.memory.capture_dropped
+    }
+
+    pub(super) fn over_budget(&self) -> bool {
+        self.memory_budget
+            .map_or(false, |budget| !budget.allows(&self.memory_usage()))
+    }
+
+    /// Applies the budget's policy if the connection is over it, and returns
+    /// withheld credit once it is back under.
+    pub(super) async fn enforce_memory_budget(&mut self) -> Result<(), ProtocolError> {
+        let budget = match self.memory_budget {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+        if budget.allows(&self.memory_usage()) {
+            return self.release_withheld_credit().await;
+        }
+        match budget.on_exceed {
+            MemoryPressure::Error => budget.check(self.memory_usage()),
+            MemoryPressure::DropCapture => {
+                self.drop_capture();
+                budget.check(self.memory_usage())
+            }
+            MemoryPressure::Backpressure => {
+                // Reading relieves queued events only; anything else over
+                // budget would stall the connection for good.
+                let usage = self.memory_usage();
+                let unrelieved = MemoryUsage {
+                    queued_events: 0,
+                    ..usage
+                };
+                if !budget.allows(&unrelieved) {
+                    return budget.check(usage);
+                }
+                self.memory.withholding = true;
+                Ok(())
+            }
+        }
+    }
+
+    /// Credits `size` bytes of DATA on `stream_id` back to the peer, or holds
+    /// the credit while withholding.
+    pub(super) async fn credit_data(
+        &mut self,
+        stream_id: u32,
+        size: u32,
+    ) -> Result<(), ProtocolError> {
+        if self.memory.withholding {
+            *self.memory.withheld.entry(stream_id).or_default() += size;
+            self.deferred_connection_credit += size;
+            return Ok(());
+        }
+        self.send_window_update(stream_id, size).await?;
+        let connection_credit = size + std::mem::take(&mut self.deferred_connection_credit);
+        self.send_window_update(0, connection_credit).await
+    }
+
+    /// Ends withholding once the connection is back under budget.
+    pub(super) async fn release_withheld_credit(&mut self) -> Result<(), ProtocolError> {
+        if !self.memory.withholding || self.over_budget() {
+            return Ok(());
+        }
+        self.memory.withholding = false;
+        let withheld: Vec<(u32, u32)> = self.memory.withheld.drain().collect();
+        for (stream_id, credit) in withheld {
+            let open = self
+                .streams
+                .get(&stream_id)
+                .map_or(false, |stream| !stream.reset_sent);
+            if open {
+                self.send_window_update(stream_id, credit).await?;
+            }
+        }
+        let credit = std::mem::take(&mut self.deferred_connection_credit);
+        if credit > 0 {
+            self.send_window_update(0, credit).await?;
+        }
+        self.flush().await
+    }
+
+    fn drop_capture(&mut self) {
+        self.captured_frames.clear();
+        self.captured_header_blocks.clear();
+        self.memory.captured = 0;
+        self.memory.capture_dropped = true;
+    }
+
+    /// Removes the header blocks captured for `stream_id`.
+    pub(super) fn take_header_blocks(&mut self, stream_id: u32) -> Option<Vec<HeaderBlockInfo>> {
+        let blocks = self.captured_header_blocks.remove(&stream_id)?;
+        let size: usize = blocks.iter().map(|block| block.raw.len()).sum();
+        self.memory.captured = self.memory.captured.saturating_sub(size);
+        Some(blocks)
+    }
+
+    pub(super) fn pop_stream_event(&mut self, stream_id: u32) -> Option<StreamEvent> {
+        let stream = self.streams.get_mut(&stream_id)?;
+        let event = stream.inbound_events.pop_front()?;
+        let size = event_size(&event);
+        stream.queued_bytes = stream.queued_bytes.saturating_sub(size);
+        self.memory.queued_events = self.memory.queued_events.saturating_sub(size);
+        Some(event)
+    }
+
+    /// Caps what each stream may queue, see [`StreamQueueLimits`]. `None`,
+    /// the default, leaves the queues unbounded.
+    pub fn set_stream_queue_limits(&mut self, limits: Option<StreamQueueLimits>) {
+        self.queue_limits = limits;
+    }
+
+    pub fn stream_queue_limits(&self) -> Option<StreamQueueLimits> {
+        self.queue_limits
+    }
+
+    /// Applies the overflow policy if `stream_id` queued more than allowed.
+    pub(super) async fn enforce_queue_limits(
+        &mut self,
+        stream_id: u32,
+    ) -> Result<(), ProtocolError> {
+        let limits = match self.queue_limits {
+            Some(limits) => limits,
+            None => return Ok(()),
+        };
+        let (events, bytes) = match self.streams.get(&stream_id) {
+            Some(stream) => (stream.inbound_events.len(), stream.queued_bytes),
+            None => return Ok(()),
+        };
+        if !limits.exceeded(events, bytes) {
+            return Ok(());
+        }
+        match limits.on_overflow {
+            QueueOverflow::Error => Err(ProtocolError::H2StreamError(
+                H2StreamErrorKind::QueueOverflow { events, bytes },
+            )),
+            QueueOverflow::Reset => {
+                self.abandon_stream(stream_id).await?;
+                if let Some(stream) = self.streams.get_mut(&stream_id) {
+                    stream.overflow = Some((events, bytes));
+                }
+                Ok(())
+            }
+            QueueOverflow::DropOldest => {
+                let mut credit = 0;
+                while let Some(stream) = self.streams.get_mut(&stream_id) {
+                    if !limits.exceeded(stream.inbound_events.len(), stream.queued_bytes) {
+                        break;
+                    }
+                    let oldest_data = stream
+                        .inbound_events
+                        .iter()
+                        .position(|event| matches!(event, StreamEvent::Data { .. }));
+                    let event =
+                        match oldest_data.and_then(|index| stream.inbound_events.remove(index)) {
+                            Some(event) => event,
+                            None => break,
+                        };
+                    let size = event_size(&event);
+                    stream.queued_bytes = stream.queued_bytes.saturating_sub(size);
+                    if stream.manual_flow_control {
+                        credit += stream.pending_credits.pop_front().unwrap_or(0);
+                    }
+                    self.memory.queued_events = self.memory.queued_events.saturating_sub(size);
+                }
+                if credit > 0 {
+                    self.credit_data(stream_id, credit).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub(super) fn event_size(event: &StreamEvent) -> usize {
+    match event {
+        StreamEvent::Headers { headers, .. } => headers
+            .iter()
+            .map(|header| header.name.len() + header.value.as_ref().map_or(0, String::len))
+            .sum(),
+        StreamEvent::Data { payload, .. } => payload.len(),
+        StreamEvent::RstStream { .. } => 0,
+    }
+}
+
+pub(super) fn captured_frame_size(payload_len: usize) -> usize {
+    FRAME_HEADER_LEN + payload_len
+}
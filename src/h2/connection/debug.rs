@@ -0,0 +1,207 @@
+use super::state::{ConnectionState, StreamState};
+use super::stats::HpackTableStats;
+use super::H2Connection;
+use crate::h2::consts::{
+    SETTINGS_ENABLE_PUSH, SETTINGS_HEADER_TABLE_SIZE, SETTINGS_INITIAL_WINDOW_SIZE,
+    SETTINGS_MAX_CONCURRENT_STREAMS, SETTINGS_MAX_FRAME_SIZE, SETTINGS_MAX_HEADER_LIST_SIZE,
+};
+use crate::types::{H2ErrorCode, MemoryUsage};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+/// GOAWAY received from the peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H2GoAwayState {
+    pub last_stream_id: u32,
+    pub error_code: H2ErrorCode,
+    pub debug_data: String,
+}
+
+/// One entry of the stream table in an [`H2DebugState`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct H2StreamDebugState {
+    pub state: StreamState,
+    pub send_window: i32,
+    pub recv_window: i32,
+    pub headers_sent: bool,
+    pub final_headers_received: bool,
+    pub end_stream_sent: bool,
+    pub end_stream_received: bool,
+    pub reset_sent: bool,
+    /// Events waiting for the stream's reader, and their payload bytes.
+    pub queued_events: usize,
+    pub queued_bytes: usize,
+}
+
+/// Snapshot returned by [`H2Connection::debug_state`], for REPLs and
+/// scripting frontends that show or diff the connection between steps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct H2DebugState {
+    pub state: ConnectionState,
+    /// Settings we sent, keyed by identifier.
+    pub local_settings: BTreeMap<u16, u32>,
+    /// Settings the peer sent, keyed by identifier.
+    pub remote_settings: BTreeMap<u16, u32>,
+    pub send_window: i32,
+    pub recv_window: i32,
+    pub next_stream_id: u32,
+    pub last_stream_id: u32,
+    pub streams: BTreeMap<u32, H2StreamDebugState>,
+    pub goaway_sent: bool,
+    pub goaway_received: Option<H2GoAwayState>,
+    /// Our encoder's table, mirrored by the peer's decoder.
+    pub hpack_encoder: HpackTableStats,
+    /// Our decoder's table, filled by the peer's encoder.
+    pub hpack_decoder: HpackTableStats,
+    pub memory: MemoryUsage,
+}
+
+impl H2DebugState {
+    /// The snapshot as JSON. Settings are keyed by their RFC 9113 names,
+    /// unknown ones by hex identifier, and streams by their ID.
+    pub fn to_json(&self) -> Value {
+        let streams: Map<String, Value> = self
+            .streams
+            .iter()
+            .map(|(stream_id, stream)| (stream_id.to_string(), stream.to_json()))
+            .collect();
+        let goaway_received = self.goaway_received.as_ref().map(|goaway| {
+            json!({
+                "last_stream_id": goaway.last_stream_id,
+                "error_code": goaway.error_code as u32,
+                "error": goaway.error_code.to_string(),
+                "debug_data": goaway.debug_data,
+            })
+        });
+        json!({
+            "protocol": "h2",
+            "state": format!("{:?}", self.state),
+            "settings": {
+                "local": settings_json(&self.local_settings),
+                "remote": settings_json(&self.remote_settings),
+            },
+            "windows": {
+                "send": self.send_window,
+                "recv": self.recv_window,
+            },
+            "next_stream_id": self.next_stream_id,
+            "last_stream_id": self.last_stream_id,
+            "streams": streams,
+            "goaway": {
+                "sent": self.goaway_sent,
+                "received": goaway_received,
+            },
+            "hpack": {
+                "encoder": table_json(&self.hpack_encoder),
+                "decoder": table_json(&self.hpack_decoder),
+            },
+            "memory": {
+                "total": self.memory.total(),
+                "pending_writes": self.memory.pending_writes,
+                "captured": self.memory.captured,
+                "queued_events": self.memory.queued_events,
+                "bodies": self.memory.bodies,
+                "read_buffer": self.memory.read_buffer,
+            },
+        })
+    }
+}
+
+impl H2StreamDebugState {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "state": self.state.to_string(),
+            "send_window": self.send_window,
+            "recv_window": self.recv_window,
+            "headers_sent": self.headers_sent,
+            "final_headers_received": self.final_headers_received,
+            "end_stream_sent": self.end_stream_sent,
+            "end_stream_received": self.end_stream_received,
+            "reset_sent": self.reset_sent,
+            "queued_events": self.queued_events,
+            "queued_bytes": self.queued_bytes,
+        })
+    }
+}
+
+impl H2Connection {
+    /// Everything a frontend needs to show where the connection stands:
+    /// settings in both directions, the stream table, flow-control windows,
+    /// GOAWAY and HPACK table occupancy.
+    pub fn debug_state(&self) -> H2DebugState {
+        let streams = self
+            .streams
+            .iter()
+            .map(|(stream_id, stream)| {
+                let entry = H2StreamDebugState {
+                    state: stream.state.clone(),
+                    send_window: stream.send_window,
+                    recv_window: stream.recv_window,
+                    headers_sent: stream.headers_sent,
+                    final_headers_received: stream.final_headers_received,
+                    end_stream_sent: stream.end_stream_sent,
+                    end_stream_received: stream.end_stream_received,
+                    reset_sent: stream.reset_sent,
+                    queued_events: stream.inbound_events.len(),
+                    queued_bytes: stream.queued_bytes,
+                };
+                (*stream_id, entry)
+            })
+            .collect();
+        let goaway_received = match (&self.goaway_reason, self.goaway_last_stream_id) {
+            (Some((error_code, debug_data)), Some(last_stream_id)) if self.goaway_received => {
+                Some(H2GoAwayState {
+                    last_stream_id,
+                    error_code: *error_code,
+                    debug_data: debug_data.clone(),
+                })
+            }
+            _ => None,
+        };
+
+        H2DebugState {
+            state: self.state.clone(),
+            local_settings: self.settings.iter().map(|(k, v)| (*k, *v)).collect(),
+            remote_settings: self.remote_settings.iter().map(|(k, v)| (*k, *v)).collect(),
+            send_window: self.send_connection_window,
+            recv_window: self.recv_connection_window,
+            next_stream_id: self.next_stream_id,
+            last_stream_id: self.last_stream_id,
+            streams,
+            goaway_sent: self.goaway_sent,
+            goaway_received,
+            hpack_encoder: self.stats.encoder_table.snapshot(),
+            hpack_decoder: self.stats.decoder_table.snapshot(),
+            memory: self.memory_usage(),
+        }
+    }
+}
+
+fn setting_name(id: u16) -> String {
+    let name = match id {
+        SETTINGS_HEADER_TABLE_SIZE => "HEADER_TABLE_SIZE",
+        SETTINGS_ENABLE_PUSH => "ENABLE_PUSH",
+        SETTINGS_MAX_CONCURRENT_STREAMS => "MAX_CONCURRENT_STREAMS",
+        SETTINGS_INITIAL_WINDOW_SIZE => "INITIAL_WINDOW_SIZE",
+        SETTINGS_MAX_FRAME_SIZE => "MAX_FRAME_SIZE",
+        SETTINGS_MAX_HEADER_LIST_SIZE => "MAX_HEADER_LIST_SIZE",
+        other => return format!("0x{:x}", other),
+    };
+    name.to_string()
+}
+
+fn settings_json(settings: &BTreeMap<u16, u32>) -> Value {
+    let map: Map<String, Value> = settings
+        .iter()
+        .map(|(id, value)| (setting_name(*id), json!(value)))
+        .collect();
+    Value::Object(map)
+}
+
+fn table_json(table: &HpackTableStats) -> Value {
+    json!({
+        "entries": table.entries,
+        "size": table.size,
+        "max_size": table.max_size,
+    })
+}
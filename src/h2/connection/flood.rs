@@ -0,0 +1,98 @@
+use crate::types::H2FloodKind;
+use std::time::{Duration, Instant};
+
+const FLOOD_WINDOW: Duration = Duration::from_secs(1);
+
+/// Per-second caps on control frames received from the peer. Exceeding one
+/// ends the connection with GOAWAY(ENHANCE_YOUR_CALM) instead of letting a
+/// hostile server keep the client busy answering. `None` disables a limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct H2FloodLimits {
+    /// Non-ACK PINGs answered per second.
+    pub max_pings_per_second: Option<u32>,
+    /// Non-ACK SETTINGS frames accepted per second.
+    pub max_settings_per_second: Option<u32>,
+    pub max_resets_per_second: Option<u32>,
+}
+
+impl Default for H2FloodLimits {
+    fn default() -> Self {
+        Self {
+            max_pings_per_second: Some(100),
+            max_settings_per_second: Some(100),
+            max_resets_per_second: Some(1000),
+        }
+    }
+}
+
+impl H2FloodLimits {
+    pub fn unlimited() -> Self {
+        Self {
+            max_pings_per_second: None,
+            max_settings_per_second: None,
+            max_resets_per_second: None,
+        }
+    }
+
+    fn limit(&self, kind: H2FloodKind) -> Option<u32> {
+        match kind {
+            H2FloodKind::Ping => self.max_pings_per_second,
+            H2FloodKind::Settings => self.max_settings_per_second,
+            H2FloodKind::RstStream => self.max_resets_per_second,
+        }
+    }
+}
+
+/// Fixed one-second window counter.
+#[derive(Debug, Clone, Copy)]
+struct RateCounter {
+    window_start: Instant,
+    count: u32,
+}
+
+impl RateCounter {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            count: 0,
+        }
+    }
+
+    fn hit(&mut self, now: Instant) -> u32 {
+        if now.duration_since(self.window_start) >= FLOOD_WINDOW {
+            self.window_start = now;
+            self.count = 0;
+        }
+        self.count = self.count.saturating_add(1);
+        self.count
+    }
+}
+
+pub(super) struct FloodGuard {
+    pub(super) limits: H2FloodLimits,
+    pings: RateCounter,
+    settings: RateCounter,
+    resets: RateCounter,
+}
+
+impl FloodGuard {
+    pub(super) fn new(limits: H2FloodLimits) -> Self {
+        Self {
+            limits,
+            pings: RateCounter::new(),
+            settings: RateCounter::new(),
+            resets: RateCounter::new(),
+        }
+    }
+
+    /// Counts one frame of `kind`; returns the exceeded limit, if any.
+    pub(super) fn record(&mut self, kind: H2FloodKind) -> Option<u32> {
+        let limit = self.limits.limit(kind)?;
+        let counter = match kind {
+            H2FloodKind::Ping => &mut self.pings,
+            H2FloodKind::Settings => &mut self.settings,
+            H2FloodKind::RstStream => &mut self.resets,
+        };
+        (counter.hit(Instant::now()) > limit).then_some(limit)
+    }
+}
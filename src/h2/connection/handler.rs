@@ -0,0 +1,55 @@
+use super::{ReadBudget, StreamEvent};
+use async_trait::async_trait;
+use std::future::Future;
+use tokio::sync::mpsc;
+
+/// Observes stream events while [`H2Connection::read_response_with_handler`]
+/// assembles a response. The read waits for each call to finish, so a
+/// bounded channel applies backpressure to the connection.
+///
+/// [`H2Connection::read_response_with_handler`]: super::H2Connection::read_response_with_handler
+#[async_trait(?Send)]
+pub trait StreamEventHandler {
+    async fn on_event(&mut self, event: &StreamEvent, budget: &ReadBudget);
+}
+
+/// Synchronous closures, which may mutate captured state.
+#[async_trait(?Send)]
+impl<F> StreamEventHandler for F
+where
+    F: FnMut(&StreamEvent, &ReadBudget),
+{
+    async fn on_event(&mut self, event: &StreamEvent, budget: &ReadBudget) {
+        self(event, budget)
+    }
+}
+
+/// Forwards a copy of each event; events are dropped once the receiver is gone.
+#[async_trait(?Send)]
+impl StreamEventHandler for mpsc::Sender<StreamEvent> {
+    async fn on_event(&mut self, event: &StreamEvent, _budget: &ReadBudget) {
+        let _ = self.send(event.clone()).await;
+    }
+}
+
+#[async_trait(?Send)]
+impl StreamEventHandler for mpsc::UnboundedSender<StreamEvent> {
+    async fn on_event(&mut self, event: &StreamEvent, _budget: &ReadBudget) {
+        let _ = self.send(event.clone());
+    }
+}
+
+/// Adapts an async closure taking owned copies of the event and budget:
+/// `AsyncEventHandler(|event, budget| async move { ... })`.
+pub struct AsyncEventHandler<F>(pub F);
+
+#[async_trait(?Send)]
+impl<F, Fut> StreamEventHandler for AsyncEventHandler<F>
+where
+    F: FnMut(StreamEvent, ReadBudget) -> Fut,
+    Fut: Future<Output = ()> + 'static,
+{
+    async fn on_event(&mut self, event: &StreamEvent, budget: &ReadBudget) {
+        (self.0)(event.clone(), *budget).await
+    }
+}
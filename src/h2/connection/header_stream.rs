@@ -0,0 +1,154 @@
+use super::state::PendingHeaderBlock;
+use super::H2Connection;
+use crate::types::{hpack_complete_len, Header, HeaderBlockInfo, ProtocolError};
+use std::fmt;
+use std::sync::Arc;
+
+/// Headers per batch unless set otherwise.
+const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// Incremental decoding of header blocks, for servers that send header lists
+/// of megabytes across long CONTINUATION sequences.
+///
+/// Each HEADERS or CONTINUATION fragment is decoded as far as it holds whole
+/// field lines and the decoded bytes are dropped, so the encoded block is
+/// never buffered in full. A handler set with [`on_batch`](Self::on_batch)
+/// sees the headers while the block is still arriving:
+///
+/// ```ignore
+/// connection.set_header_streaming(Some(
+///     HeaderStreaming::new()
+///         .batch_size(256)
+///         .on_batch(|stream_id, headers| println!("{}: {} more", stream_id, headers.len()))
+///         .retain(false),
+/// ));
+/// ```
+///
+/// Header blocks captured for the response keep their annotated fields and
+/// table size updates but not the raw bytes.
+#[derive(Clone)]
+pub struct HeaderStreaming {
+    handler: Option<Arc<dyn Fn(u32, &[Header]) + Send + Sync>>,
+    batch_size: usize,
+    retain: bool,
+}
+
+impl fmt::Debug for HeaderStreaming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeaderStreaming")
+            .field("handler", &self.handler.as_ref().map(|_| ".."))
+            .field("batch_size", &self.batch_size)
+            .field("retain", &self.retain)
+            .finish()
+    }
+}
+
+impl Default for HeaderStreaming {
+    fn default() -> Self {
+        Self {
+            handler: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            retain: true,
+        }
+    }
+}
+
+impl HeaderStreaming {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `handler` with the stream ID and the next headers once
+    /// `batch_size` of them are decoded, and with the rest at the end of the
+    /// block. Trailers are streamed too.
+    pub fn on_batch<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(u32, &[Header]) + Send + Sync + 'static,
+    {
+        self.handler = Some(Arc::new(handler));
+        self
+    }
+
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// With `false`, headers handed to the batch handler are not kept for
+    /// the stream's headers event, which then carries the pseudo-headers
+    /// only. Without a handler nothing is dropped.
+    pub fn retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    fn retains(&self, header: &Header) -> bool {
+        self.retain || self.handler.is_none() || header.name.starts_with(':')
+    }
+}
+
+impl H2Connection {
+    /// Decodes header blocks incrementally per `streaming`; `None`, the
+    /// default, buffers each block until END_HEADERS.
+    pub fn set_header_streaming(&mut self, streaming: Option<HeaderStreaming>) {
+        self.header_streaming = streaming;
+    }
+
+    /// Decodes the whole field lines buffered in `pending`, all of them once
+    /// `end_headers` is set, and hands out full batches.
+    pub(super) fn stream_header_fragment(
+        &mut self,
+        stream_id: u32,
+        pending: &mut PendingHeaderBlock,
+        end_headers: bool,
+    ) -> Result<(), ProtocolError> {
+        let streaming = match self.header_streaming.clone() {
+            Some(streaming) => streaming,
+            None => return Ok(()),
+        };
+        let complete = if end_headers {
+            pending.block.len()
+        } else {
+            hpack_complete_len(&pending.block)
+        };
+        if complete > 0 {
+            let chunk = pending.block.split_to(complete).freeze();
+            let headers = self.hpack.decode(&chunk)?;
+            let mut chunk_info = HeaderBlockInfo::hpack(stream_id, chunk, &headers);
+            self.stats.decoder_table.apply(&chunk_info);
+
+            chunk_info.raw = Default::default();
+            if !streaming.retain && streaming.handler.is_some() {
+                chunk_info.fields.clear();
+            }
+            match pending.info.as_mut() {
+                Some(info) => {
+                    info.fields.append(&mut chunk_info.fields);
+                    info.table_size_updates
+                        .append(&mut chunk_info.table_size_updates);
+                }
+                None => pending.info = Some(chunk_info),
+            }
+
+            for header in headers {
+                if streaming.retains(&header) {
+                    pending.decoded.push(header.clone());
+                }
+                if streaming.handler.is_some() {
+                    pending.batch.push(header);
+                }
+            }
+        }
+
+        if let Some(handler) = &streaming.handler {
+            while pending.batch.len() >= streaming.batch_size
+                || (end_headers && !pending.batch.is_empty())
+            {
+                let take = streaming.batch_size.min(pending.batch.len());
+                let batch: Vec<Header> = pending.batch.drain(..take).collect();
+                handler(stream_id, &batch);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use super::H2Connection;
+use crate::stream::TransportStream;
+use crate::types::{ClientTimeouts, ProtocolError};
+use crate::wire::{WireChunk, WireSink};
+use tokio::sync::mpsc;
+
+impl H2Connection {
+    /// Performs the handshake like [`connect_over`](Self::connect_over),
+    /// mirroring the connection to `mirror` from the first preface byte.
+    pub async fn connect_over_mirrored(
+        transport: TransportStream,
+        timeouts: &ClientTimeouts,
+        mirror: Box<dyn WireSink>,
+    ) -> Result<Self, ProtocolError> {
+        Self::ensure_h2_alpn(&transport)?;
+        let mut connection = Self::new(transport, timeouts.clone());
+        connection.set_wire_mirror(Some(mirror));
+        connection.perform_handshake().await?;
+        Ok(connection)
+    }
+
+    /// Mirrors every byte from now on to `mirror`, replacing an earlier one;
+    /// `None` stops mirroring.
+    pub fn set_wire_mirror(&mut self, mirror: Option<Box<dyn WireSink>>) {
+        self.wire_mirror.set(mirror);
+    }
+
+    /// Mirrors every byte from now on into a channel.
+    pub fn mirror_wire(&mut self) -> mpsc::UnboundedReceiver<WireChunk> {
+        self.wire_mirror.channel()
+    }
+
+    pub fn is_mirroring_wire(&self) -> bool {
+        self.wire_mirror.is_active()
+    }
+}
@@ -0,0 +1,135 @@
+use super::{H2Connection, StreamState};
+use crate::types::{FrameH2, Header, ProtocolError};
+use bytes::Bytes;
+
+/// A header block encoded ahead of time, waiting for [`H2Connection::send_prepared`].
+pub(super) struct PreparedHeaders {
+    stream_id: u32,
+    frames: Vec<(FrameH2, Bytes)>,
+}
+
+impl H2Connection {
+    /// Opens the next `count` stream IDs without sending anything, for bursts
+    /// where the per-request work should happen before the clock starts:
+    ///
+    /// ```ignore
+    /// let ids = connection.reserve_streams(20)?;
+    /// for id in &ids {
+    ///     connection.prepare_headers(*id, &headers, false)?;
+    /// }
+    /// connection.send_prepared().await?; // every HEADERS in one write
+    /// for id in &ids {
+    ///     connection.queue_data(*id, Bytes::from_static(b"x"), true);
+    /// }
+    /// connection.flush_scheduled_data().await?;
+    /// ```
+    ///
+    /// Reserving never waits for a concurrency slot: streams count against
+    /// the peer's limit once their HEADERS are sent. Send those before
+    /// opening other streams: a higher stream ID going out first implicitly
+    /// closes them on the peer's side.
+    pub fn reserve_streams(&mut self, count: usize) -> Result<Vec<u32>, ProtocolError> {
+        self.check_can_open_stream()?;
+        if let Some(last) = self.goaway_last_stream_id {
+            let highest = self.next_stream_id as u64 + 2 * (count as u64).saturating_sub(1);
+            if count > 0 && highest > last as u64 {
+                return Err(ProtocolError::RequestFailed(
+                    "GOAWAY received: new streams are not allowed".to_string(),
+                ));
+            }
+        }
+        Ok((0..count).map(|_| self.open_next_stream()).collect())
+    }
+
+    /// HPACK-encodes and frames the HEADERS of a reserved stream now, to be
+    /// written by [`send_prepared`](Self::send_prepared). Streams have to be
+    /// prepared in ascending order, and the encoder state is committed, so
+    /// no other header block can go out until the prepared ones have.
+    pub fn prepare_headers(
+        &mut self,
+        stream_id: u32,
+        headers: &[Header],
+        end_stream: bool,
+    ) -> Result<(), ProtocolError> {
+        let reserved = self.streams.get(&stream_id).map_or(false, |stream| {
+            matches!(stream.state, StreamState::Idle) && !stream.headers_sent
+        });
+        if stream_id % 2 == 0 || !reserved {
+            return Err(ProtocolError::RequestFailed(format!(
+                "Stream {} is not a reserved stream",
+                stream_id
+            )));
+        }
+        if let Some(last) = self.prepared_headers.back() {
+            if stream_id <= last.stream_id {
+                return Err(ProtocolError::RequestFailed(format!(
+                    "Stream {} must be prepared after stream {}",
+                    stream_id, last.stream_id
+                )));
+            }
+        }
+        self.check_header_list_size(headers)?;
+
+        let frames = self
+            .encode_headers_frames(stream_id, headers, end_stream)?
+            .into_iter()
+            .map(|frame| {
+                let serialized = frame.serialize()?;
+                Ok((frame, serialized))
+            })
+            .collect::<Result<Vec<_>, ProtocolError>>()?;
+        self.prepared_headers
+            .push_back(PreparedHeaders { stream_id, frames });
+        Ok(())
+    }
+
+    /// Streams with a prepared header block not sent yet, in sending order.
+    pub fn prepared_streams(&self) -> Vec<u32> {
+        self.prepared_headers
+            .iter()
+            .map(|prepared| prepared.stream_id)
+            .collect()
+    }
+
+    /// Writes every prepared header block in a single write and returns
+    /// their streams.
+    pub async fn send_prepared(&mut self) -> Result<Vec<u32>, ProtocolError> {
+        let mut sent = Vec::with_capacity(self.prepared_headers.len());
+        while let Some(prepared) = self.prepared_headers.pop_front() {
+            for (frame, serialized) in prepared.frames {
+                self.enqueue_frame(&frame, serialized);
+            }
+            sent.push(prepared.stream_id);
+        }
+        self.flush().await?;
+        Ok(sent)
+    }
+
+    /// Gives back a reserved stream that will not be used. Streams with a
+    /// prepared header block cannot be released; send them and reset them
+    /// instead.
+    pub fn release_stream(&mut self, stream_id: u32) -> bool {
+        let prepared = self
+            .prepared_headers
+            .iter()
+            .any(|prepared| prepared.stream_id == stream_id);
+        let idle = self.streams.get(&stream_id).map_or(false, |stream| {
+            matches!(stream.state, StreamState::Idle) && !stream.headers_sent
+        });
+        if prepared || !idle {
+            return false;
+        }
+        self.streams.remove(&stream_id);
+        true
+    }
+
+    pub(super) fn ensure_nothing_prepared(&self) -> Result<(), ProtocolError> {
+        match self.prepared_headers.front() {
+            Some(prepared) => Err(ProtocolError::RequestFailed(format!(
+                "Prepared headers of stream {} have to be sent first",
+                prepared.stream_id
+            ))),
+            None => Ok(()),
+        }
+    }
+}
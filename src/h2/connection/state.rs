@@ -1,6 +1,7 @@
-use crate::types::{H2ErrorCode, Header};
+use crate::types::{H2ErrorCode, Header, HeaderBlockInfo, HeaderLimitKind};
 use bytes::{Bytes, BytesMut};
 use std::collections::VecDeque;
+use std::time::Duration;
 
 // Connection States
 #[derive(Debug, Clone, PartialEq)]
@@ -50,10 +51,29 @@ pub enum StreamEvent {
     },
 }
 
+/// How much of a response read's budget is left, handed to event handlers
+/// alongside each event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadBudget {
+    pub elapsed: Duration,
+    /// Time left before the overall timeout; `None` when there is none.
+    pub remaining: Option<Duration>,
+    /// Events received so far, including the one being handled.
+    pub events: usize,
+    pub remaining_events: Option<usize>,
+}
+
 #[derive(Debug, Clone)]
 pub(super) struct PendingHeaderBlock {
+    /// The block so far or, when streaming, the bytes not yet decoded.
     pub(super) block: BytesMut,
     pub(super) end_stream: bool,
+    /// Headers already decoded by header streaming.
+    pub(super) decoded: Vec<Header>,
+    /// Decoded headers not yet handed to the batch handler.
+    pub(super) batch: Vec<Header>,
+    /// Annotations of the decoded part, without its raw bytes.
+    pub(super) info: Option<HeaderBlockInfo>,
 }
 
 impl PendingHeaderBlock {
@@ -61,6 +81,9 @@ impl PendingHeaderBlock {
         Self {
             block: BytesMut::new(),
             end_stream: false,
+            decoded: Vec::new(),
+            batch: Vec::new(),
+            info: None,
         }
     }
 
@@ -78,8 +101,27 @@ pub struct StreamInfo {
     pub final_headers_received: bool,
     pub end_stream_received: bool,
     pub end_stream_sent: bool,
+    /// Set once we sent RST_STREAM; late peer frames are then ignored.
+    pub reset_sent: bool,
     pub inbound_events: VecDeque<StreamEvent>,
     pub(super) pending_headers: Option<PendingHeaderBlock>,
+    /// Flow-control credit is returned as a streaming body is consumed
+    /// instead of as soon as DATA arrives.
+    pub(super) manual_flow_control: bool,
+    /// Credit owed for each queued DATA event, in queue order.
+    pub(super) pending_credits: VecDeque<u32>,
+    /// Set once `ConnectionEvent::StreamClosed` went out for this stream.
+    pub(super) close_reported: bool,
+    /// Bytes of the events in `inbound_events`.
+    pub(super) queued_bytes: usize,
+    /// Events and bytes queued when `StreamQueueLimits` reset the stream.
+    pub(super) overflow: Option<(usize, usize)>,
+    /// Set once a WINDOW_UPDATE overflowed `send_window` and the stream was
+    /// reset with FLOW_CONTROL_ERROR.
+    pub(super) flow_control_violated: bool,
+    /// Set once a header block went over the SETTINGS_MAX_HEADER_LIST_SIZE
+    /// we advertised and the stream was reset.
+    pub(super) header_limit: Option<HeaderLimitKind>,
 }
 
 impl StreamInfo {
@@ -92,8 +134,16 @@ impl StreamInfo {
             final_headers_received: false,
             end_stream_received: false,
             end_stream_sent: false,
+            reset_sent: false,
             inbound_events: VecDeque::new(),
             pending_headers: None,
+            manual_flow_control: false,
+            pending_credits: VecDeque::new(),
+            close_reported: false,
+            queued_bytes: 0,
+            overflow: None,
+            flow_control_violated: false,
+            header_limit: None,
         }
     }
 }
@@ -0,0 +1,184 @@
+use crate::h2::consts::{DATA_FRAME_TYPE, FRAME_HEADER_SIZE};
+use crate::types::{FrameTypeH2, HeaderBlockInfo};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Dynamic table size in effect until a size update is seen (RFC 7541 Section 4.2).
+const HPACK_INITIAL_TABLE_SIZE: usize = 4096;
+
+/// Per-entry overhead counted against the table size (RFC 7541 Section 4.1).
+const HPACK_ENTRY_OVERHEAD: usize = 32;
+
+/// Frame and byte counters for one direction of a connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct H2TrafficStats {
+    /// Frame counts keyed by frame type code, so extension types show up too.
+    pub frames: BTreeMap<u8, u64>,
+    /// Everything that crossed the transport: preface, frame headers, payloads
+    /// and padding.
+    pub wire_bytes: u64,
+    /// DATA frame payloads, i.e. what is counted against flow control.
+    pub payload_bytes: u64,
+}
+
+impl H2TrafficStats {
+    pub fn frames_of(&self, frame_type: FrameTypeH2) -> u64 {
+        self.frames.get(&frame_type.as_u8()).copied().unwrap_or(0)
+    }
+
+    pub fn total_frames(&self) -> u64 {
+        self.frames.values().sum()
+    }
+
+    fn record(&mut self, frame_type: u8, payload_len: usize) {
+        *self.frames.entry(frame_type).or_insert(0) += 1;
+        if frame_type == DATA_FRAME_TYPE {
+            self.payload_bytes += payload_len as u64;
+        }
+    }
+}
+
+/// Counters for a single stream (one request/response exchange).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct H2StreamStats {
+    pub frames_sent: u64,
+    pub frames_received: u64,
+    pub payload_bytes_sent: u64,
+    pub payload_bytes_received: u64,
+    /// Current flow-control windows; `None` once the stream has been forgotten.
+    pub send_window: Option<i32>,
+    pub recv_window: Option<i32>,
+}
+
+/// Occupancy of an HPACK dynamic table, replayed from the header blocks seen
+/// on the wire rather than read from the codec.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HpackTableStats {
+    pub entries: usize,
+    /// Sum of entry sizes (name + value + 32 octets each).
+    pub size: usize,
+    pub max_size: usize,
+}
+
+/// Snapshot returned by [`H2Connection::stats`](super::H2Connection::stats).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct H2ConnectionStats {
+    pub sent: H2TrafficStats,
+    pub received: H2TrafficStats,
+    pub send_window: i32,
+    pub recv_window: i32,
+    pub active_streams: usize,
+    pub streams: BTreeMap<u32, H2StreamStats>,
+    /// Our encoder's table, mirrored by the peer's decoder.
+    pub hpack_encoder: HpackTableStats,
+    /// Our decoder's table, filled by the peer's encoder.
+    pub hpack_decoder: HpackTableStats,
+}
+
+/// Running counters kept by the connection; snapshotted into [`H2ConnectionStats`].
+pub(super) struct StatsRecorder {
+    pub(super) sent: H2TrafficStats,
+    pub(super) received: H2TrafficStats,
+    pub(super) streams: BTreeMap<u32, H2StreamStats>,
+    pub(super) encoder_table: DynamicTableTracker,
+    pub(super) decoder_table: DynamicTableTracker,
+}
+
+impl StatsRecorder {
+    pub(super) fn new() -> Self {
+        Self {
+            sent: H2TrafficStats::default(),
+            received: H2TrafficStats::default(),
+            streams: BTreeMap::new(),
+            encoder_table: DynamicTableTracker::new(),
+            decoder_table: DynamicTableTracker::new(),
+        }
+    }
+
+    /// Records a serialized frame we queued for writing.
+    pub(super) fn on_frame_sent(&mut self, serialized: &[u8]) {
+        if let Some((frame_type, stream_id, len)) = frame_header(serialized) {
+            self.sent.record(frame_type, len);
+            if stream_id != 0 {
+                let stream = self.streams.entry(stream_id).or_default();
+                stream.frames_sent += 1;
+                if frame_type == DATA_FRAME_TYPE {
+                    stream.payload_bytes_sent += len as u64;
+                }
+            }
+        }
+    }
+
+    /// Records a frame read from the wire, given its 9-byte header.
+    pub(super) fn on_frame_received(&mut self, header: &[u8]) {
+        if let Some((frame_type, stream_id, len)) = frame_header(header) {
+            self.received.record(frame_type, len);
+            if stream_id != 0 {
+                let stream = self.streams.entry(stream_id).or_default();
+                stream.frames_received += 1;
+                if frame_type == DATA_FRAME_TYPE {
+                    stream.payload_bytes_received += len as u64;
+                }
+            }
+        }
+    }
+}
+
+/// Splits a frame header into (type, stream id, payload length).
+fn frame_header(data: &[u8]) -> Option<(u8, u32, usize)> {
+    if data.len() < FRAME_HEADER_SIZE {
+        return None;
+    }
+    let len = ((data[0] as usize) << 16) | ((data[1] as usize) << 8) | data[2] as usize;
+    let stream_id = u32::from_be_bytes([data[5], data[6], data[7], data[8]]) & 0x7FFF_FFFF;
+    Some((data[3], stream_id, len))
+}
+
+/// Replays insertions and size updates to follow a dynamic table's size.
+pub(super) struct DynamicTableTracker {
+    entries: VecDeque<usize>,
+    size: usize,
+    max_size: usize,
+}
+
+impl DynamicTableTracker {
+    pub(super) fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            size: 0,
+            max_size: HPACK_INITIAL_TABLE_SIZE,
+        }
+    }
+
+    pub(super) fn apply(&mut self, block: &HeaderBlockInfo) {
+        for &update in &block.table_size_updates {
+            self.max_size = update as usize;
+            self.evict();
+        }
+        for field in block.fields.iter().filter(|field| field.adds_to_table) {
+            let size = field.header.name.len()
+                + field.header.value.as_ref().map_or(0, String::len)
+                + HPACK_ENTRY_OVERHEAD;
+            // An entry larger than the table empties it and is not kept (Section 4.4).
+            self.entries.push_front(size);
+            self.size += size;
+            self.evict();
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            match self.entries.pop_back() {
+                Some(size) => self.size -= size,
+                None => break,
+            }
+        }
+    }
+
+    pub(super) fn snapshot(&self) -> HpackTableStats {
+        HpackTableStats {
+            entries: self.entries.len(),
+            size: self.size,
+            max_size: self.max_size,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use super::H2Connection;
+use crate::types::{FrameH2, ProtocolError};
+use bytes::Bytes;
+
+impl H2Connection {
+    /// Drives the connection by hand, for interactive tools that explore the
+    /// protocol one frame at a time.
+    ///
+    /// In step mode nothing goes out until [`step_write`](Self::step_write)
+    /// and nothing is read until [`step_read`](Self::step_read). Frames the
+    /// connection sends on its own, such as SETTINGS and PING ACKs or
+    /// WINDOW_UPDATEs, are queued like any other, and `flush` leaves them
+    /// queued. Calls that would have to read from the peer, such as
+    /// `read_response` on a stream whose events have not all been stepped
+    /// in yet, fail with [`ProtocolError::StepModeBlocked`] instead.
+    ///
+    /// Leaving step mode keeps the queue; the next flush sends it.
+    pub fn set_step_mode(&mut self, enabled: bool) {
+        self.step_mode = enabled;
+    }
+
+    pub fn is_step_mode(&self) -> bool {
+        self.step_mode
+    }
+
+    /// Frames, or chunks handed to `write_raw`, waiting to be written.
+    pub fn queued_writes(&self) -> usize {
+        self.pending_writes.len()
+    }
+
+    /// Writes the oldest queued frame and returns its bytes; `None` if
+    /// nothing is queued.
+    pub async fn step_write(&mut self) -> Result<Option<Bytes>, ProtocolError> {
+        if self.pending_writes.is_empty() {
+            return Ok(None);
+        }
+        let chunk = self.pending_writes.remove(0);
+        self.pending_write_bytes -= chunk.len();
+        self.write_to_stream(&chunk).await?;
+        Ok(Some(chunk))
+    }
+
+    /// Reads one frame and handles it, returning a copy of it. Whatever the
+    /// frame makes the connection send is queued for `step_write`, and the
+    /// stream events it produces are queued for `read_response`.
+    pub async fn step_read(&mut self) -> Result<FrameH2, ProtocolError> {
+        let frame = self.read_wire_frame().await?;
+        self.process_incoming_frame(frame.clone()).await?;
+        Ok(frame)
+    }
+}
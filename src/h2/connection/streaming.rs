@@ -0,0 +1,314 @@
+use super::{H2Connection, StreamEvent};
+use crate::types::{
+    BodyConnection, BodySource, H2StreamErrorKind, Header, ProtocolError, Response, ResponseBody,
+    StreamingResponse,
+};
+use crate::utils::{time_remaining, timeout_result};
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use std::time::Instant;
+
+/// Final response head with a body that is read on demand.
+pub struct H2StreamingResponse<'a> {
+    pub status: u16,
+    pub headers: Vec<Header>,
+    pub body: H2BodyReader<'a>,
+}
+
+/// Pulls DATA for one stream lazily.
+///
+/// Stream and connection WINDOW_UPDATEs are sent as chunks are handed out,
+/// so the peer stalls once a consumer stops reading and the windows run dry.
+/// Dropping the reader early returns the connection credit of any unread
+/// DATA with the next WINDOW_UPDATE.
+pub struct H2BodyReader<'a> {
+    connection: BodyConnection<'a, H2Connection>,
+    stream_id: u32,
+    body_deadline: Option<Instant>,
+    trailers: Option<Vec<Header>>,
+    finished: bool,
+}
+
+impl<'a> H2StreamingResponse<'a> {
+    /// Reads the rest of the body and assembles a regular [`Response`].
+    pub async fn into_response(mut self) -> Result<Response, ProtocolError> {
+        let body = self.body.read_to_end().await?;
+        let stream_id = self.body.stream_id;
+        let connection = &mut *self.body.connection;
+        let (frames, frame_times) = connection.take_captured_frames(stream_id);
+        let header_blocks = connection.take_header_blocks(stream_id);
+        let cookies = Response::collect_cookies(&self.headers);
+
+        Ok(Response {
+            status: self.status,
+            protocol: "HTTP/2.0".to_string(),
+            headers: self.headers,
+            body,
+            trailers: self.body.trailers.take(),
+            frames,
+            header_blocks,
+            cookies,
+            decoded_body: None,
+            frame_times,
+            digest: None,
+            transfer_codings: Vec::new(),
+            truncated: false,
+            truncation: None,
+        })
+    }
+}
+
+impl<'a> H2BodyReader<'a> {
+    pub fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    /// True once END_STREAM has been seen.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Trailers received so far; complete once the body is finished.
+    pub fn trailers(&self) -> Option<&[Header]> {
+        self.trailers.as_deref()
+    }
+
+    /// Next non-empty DATA payload, or `None` at the end of the body.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, ProtocolError> {
+        while !self.finished {
+            let read_timeout = self
+                .connection
+                .timeouts
+                .next_body_read_timeout(self.body_deadline);
+            let event = timeout_result(
+                read_timeout,
+                self.connection.recv_stream_event(self.stream_id),
+            )
+            .await?;
+
+            match event {
+                StreamEvent::Data {
+                    payload,
+                    end_stream,
+                } => {
+                    self.finished = end_stream;
+                    self.release_credit(end_stream).await?;
+                    if !payload.is_empty() {
+                        return Ok(Some(payload));
+                    }
+                }
+                StreamEvent::Headers {
+                    headers,
+                    end_stream,
+                    ..
+                } => {
+                    self.trailers
+                        .get_or_insert_with(Vec::new)
+                        .extend(headers.into_iter().filter(|h| !h.name.starts_with(':')));
+                    self.finished = end_stream;
+                }
+                StreamEvent::RstStream { error_code } => {
+                    self.finished = true;
+                    return Err(ProtocolError::H2StreamError(H2StreamErrorKind::Reset(
+                        error_code,
+                    )));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    pub async fn read_to_end(&mut self) -> Result<Bytes, ProtocolError> {
+        let mut body = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    }
+
+    /// Returns the credit held for the DATA frame just consumed.
+    async fn release_credit(&mut self, end_stream: bool) -> Result<(), ProtocolError> {
+        let credit = self
+            .connection
+            .streams
+            .get_mut(&self.stream_id)
+            .and_then(|stream| stream.pending_credits.pop_front())
+            .unwrap_or(0);
+        if credit == 0 {
+            return Ok(());
+        }
+        // A finished stream has no use for more credit of its own.
+        if !end_stream {
+            self.connection
+                .send_window_update(self.stream_id, credit)
+                .await?;
+        }
+        let connection_credit =
+            credit + std::mem::take(&mut self.connection.deferred_connection_credit);
+        self.connection
+            .send_window_update(0, connection_credit)
+            .await
+    }
+}
+
+/// Frames captured for the stream are dropped as chunks are handed out, so
+/// a long body does not pile up in memory as frames either.
+#[async_trait(?Send)]
+impl BodySource for H2BodyReader<'_> {
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, ProtocolError>> {
+        let chunk = self.chunk().await;
+        self.connection.take_captured_frames(self.stream_id);
+        chunk.transpose()
+    }
+
+    fn trailers(&mut self) -> Option<Vec<Header>> {
+        self.trailers.take()
+    }
+}
+
+impl<'a> From<H2StreamingResponse<'a>> for StreamingResponse<'a> {
+    fn from(response: H2StreamingResponse<'a>) -> Self {
+        StreamingResponse {
+            status: response.status,
+            protocol: "HTTP/2.0".to_string(),
+            headers: response.headers,
+            body: ResponseBody::new(response.body),
+        }
+    }
+}
+
+impl Drop for H2BodyReader<'_> {
+    fn drop(&mut self) {
+        if let Some(stream) = self.connection.streams.get_mut(&self.stream_id) {
+            stream.manual_flow_control = false;
+            let owed: u32 = stream.pending_credits.drain(..).sum();
+            self.connection.deferred_connection_credit += owed;
+        }
+    }
+}
+
+impl H2Connection {
+    /// Reads up to the final response headers and returns without waiting
+    /// for the body. In contrast to [`read_response`](Self::read_response),
+    /// DATA on this stream is only credited back to the peer as the returned
+    /// body reader consumes it.
+    pub async fn read_response_streaming(
+        &mut self,
+        stream_id: u32,
+    ) -> Result<H2StreamingResponse<'_>, ProtocolError> {
+        let (status, headers, end_stream) = self.read_streaming_head(stream_id).await?;
+        let body_deadline = self.body_deadline();
+        Ok(H2StreamingResponse {
+            status,
+            headers,
+            body: H2BodyReader {
+                connection: BodyConnection::Borrowed(self),
+                stream_id,
+                body_deadline,
+                trailers: None,
+                finished: end_stream,
+            },
+        })
+    }
+
+    /// Like [`read_response_streaming`](Self::read_response_streaming), with
+    /// the body taking the connection along; it is closed once the body is
+    /// dropped.
+    pub async fn into_response_streaming(
+        mut self,
+        stream_id: u32,
+    ) -> Result<H2StreamingResponse<'static>, ProtocolError> {
+        let (status, headers, end_stream) = self.read_streaming_head(stream_id).await?;
+        let body_deadline = self.body_deadline();
+        Ok(H2StreamingResponse {
+            status,
+            headers,
+            body: H2BodyReader {
+                connection: BodyConnection::Owned(Box::new(self)),
+                stream_id,
+                body_deadline,
+                trailers: None,
+                finished: end_stream,
+            },
+        })
+    }
+
+    fn body_deadline(&self) -> Option<Instant> {
+        self.timeouts
+            .body_total_timeout()
+            .map(|d| Instant::now() + d)
+    }
+
+    /// Status, headers and END_STREAM of the final response on `stream_id`,
+    /// with the stream switched to crediting DATA as it is consumed.
+    async fn read_streaming_head(
+        &mut self,
+        stream_id: u32,
+    ) -> Result<(u16, Vec<Header>, bool), ProtocolError> {
+        self.ensure_stream(stream_id);
+        if let Some(stream) = self.streams.get_mut(&stream_id) {
+            if !stream.manual_flow_control {
+                stream.manual_flow_control = true;
+                // DATA queued before now was credited on arrival.
+                let queued = stream
+                    .inbound_events
+                    .iter()
+                    .filter(|event| matches!(event, StreamEvent::Data { .. }))
+                    .count();
+                stream
+                    .pending_credits
+                    .extend(std::iter::repeat(0).take(queued));
+            }
+        }
+
+        let header_deadline = self.timeouts.header_timeout().map(|d| Instant::now() + d);
+        loop {
+            let event = timeout_result(
+                time_remaining(header_deadline),
+                self.recv_stream_event(stream_id),
+            )
+            .await?;
+
+            match event {
+                StreamEvent::Headers {
+                    headers,
+                    end_stream,
+                    ..
+                } => {
+                    let status = headers
+                        .iter()
+                        .find(|h| h.name == ":status")
+                        .and_then(|h| h.value.as_ref()?.parse::<u16>().ok())
+                        .ok_or_else(|| {
+                            ProtocolError::InvalidResponse(
+                                "Missing :status header in response".to_string(),
+                            )
+                        })?;
+                    if status < 200 {
+                        if end_stream {
+                            return Err(ProtocolError::InvalidResponse(
+                                "Informational response closed stream".to_string(),
+                            ));
+                        }
+                        continue;
+                    }
+                    let headers = headers
+                        .into_iter()
+                        .filter(|h| !h.name.starts_with(':'))
+                        .collect::<Vec<_>>();
+                    return Ok((status, headers, end_stream));
+                }
+                StreamEvent::Data { .. } => {
+                    return Err(ProtocolError::InvalidResponse(
+                        "DATA received before response headers".to_string(),
+                    ));
+                }
+                StreamEvent::RstStream { error_code } => {
+                    return Err(ProtocolError::H2StreamError(H2StreamErrorKind::Reset(
+                        error_code,
+                    )));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use super::{H2Connection, StreamState};
+use crate::connection::StreamWriter;
+use crate::types::{H2StreamErrorKind, Header, ProtocolError, Response};
+use crate::utils::timeout_result;
+use async_trait::async_trait;
+use bytes::Bytes;
+
+/// [`StreamWriter`] for an HTTP/2 stream.
+///
+/// DATA waits for WINDOW_UPDATEs when either flow-control window is
+/// exhausted, processing incoming frames meanwhile; other streams' frames
+/// stay queued for their readers.
+pub struct H2StreamWriter<'a> {
+    connection: &'a mut H2Connection,
+    stream_id: u32,
+    finished: bool,
+}
+
+impl H2Connection {
+    /// Creates a stream and returns a writer for it.
+    pub async fn stream_writer(&mut self) -> Result<H2StreamWriter<'_>, ProtocolError> {
+        let stream_id = self.create_stream().await?;
+        Ok(self.writer_for(stream_id))
+    }
+
+    /// Writer for a stream created earlier with `create_stream`.
+    pub fn writer_for(&mut self, stream_id: u32) -> H2StreamWriter<'_> {
+        let finished = self
+            .streams
+            .get(&stream_id)
+            .map_or(false, |stream| stream.end_stream_sent);
+        H2StreamWriter {
+            connection: self,
+            stream_id,
+            finished,
+        }
+    }
+}
+
+impl<'a> H2StreamWriter<'a> {
+    /// Flushes and reads the response once the request is written.
+    pub async fn read_response(self) -> Result<Response, ProtocolError> {
+        self.connection.flush().await?;
+        self.connection.read_response(self.stream_id).await
+    }
+
+    /// Credit currently available for DATA on this stream.
+    pub fn send_window(&self) -> i32 {
+        let stream_window = self
+            .connection
+            .streams
+            .get(&self.stream_id)
+            .map_or(0, |stream| stream.send_window);
+        stream_window.min(self.connection.send_connection_window)
+    }
+
+    async fn wait_for_window(&mut self) -> Result<(), ProtocolError> {
+        self.connection.flush().await?;
+        let write_timeout = self.connection.timeouts.write;
+        while self.send_window() <= 0 {
+            let closed = self
+                .connection
+                .streams
+                .get(&self.stream_id)
+                .map_or(true, |stream| stream.state == StreamState::Closed);
+            if closed {
+                return Err(ProtocolError::H2StreamError(
+                    H2StreamErrorKind::StreamClosed,
+                ));
+            }
+            timeout_result(write_timeout, self.connection.pump_incoming()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a> StreamWriter for H2StreamWriter<'a> {
+    fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    async fn headers(&mut self, headers: &[Header], end_stream: bool) -> Result<(), ProtocolError> {
+        self.connection
+            .send_headers(self.stream_id, headers, end_stream)
+            .await?;
+        self.finished |= end_stream;
+        Ok(())
+    }
+
+    async fn data(&mut self, chunk: Bytes, end_stream: bool) -> Result<(), ProtocolError> {
+        let mut remaining = chunk;
+        loop {
+            if !remaining.is_empty() && self.send_window() <= 0 {
+                self.wait_for_window().await?;
+            }
+            let len = remaining
+                .len()
+                .min(self.connection.max_frame_size())
+                .min(self.send_window().max(0) as usize);
+            let piece = remaining.split_to(len);
+            let last = remaining.is_empty();
+            self.connection
+                .send_data(self.stream_id, &piece, end_stream && last)
+                .await?;
+            if last {
+                break;
+            }
+        }
+        self.finished |= end_stream;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), ProtocolError> {
+        self.connection.flush().await
+    }
+
+    async fn trailers(&mut self, trailers: &[Header]) -> Result<(), ProtocolError> {
+        self.headers(trailers, true).await
+    }
+
+    async fn finish(&mut self) -> Result<(), ProtocolError> {
+        if self.finished {
+            return Ok(());
+        }
+        self.data(Bytes::new(), true).await
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
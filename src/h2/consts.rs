@@ -16,6 +16,9 @@ pub const DEFAULT_INITIAL_WINDOW_SIZE: u32 = 65_535;
 pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16_384;
 pub const DEFAULT_MAX_HEADER_LIST_SIZE: u32 = 8_192;
 
+/// How long a sent SETTINGS frame may stay unacknowledged (RFC 7540 Section 6.5.3)
+pub const DEFAULT_SETTINGS_ACK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 /// Frame/flag constants (RFC 7540 Section 4)
 pub const FRAME_HEADER_SIZE: usize = 9;
 
@@ -37,3 +40,4 @@ pub const PADDED_FLAG: u8 = 0x8;
 pub const PRIORITY_FLAG: u8 = 0x20;
 
 pub const MAX_FRAME_SIZE_UPPER_BOUND: u32 = 16_777_215; // 2^24 - 1
+pub const MAX_WINDOW_SIZE: i64 = 0x7FFF_FFFF; // 2^31 - 1
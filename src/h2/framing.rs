@@ -1,11 +1,32 @@
 mod rst;
+mod unchecked;
 
 pub use rst::RstErrorCode;
+pub use unchecked::UncheckedFrameH2;
 
 use crate::h2::consts::*;
+use crate::h2::hpack::{HeaderCodec, StaticHpackCodec};
 use crate::types::{FrameH2, FrameSink, FrameType, FrameTypeH2, Header, ProtocolError};
 use bytes::{BufMut, Bytes, BytesMut};
-use hpack::{Decoder, Encoder};
+
+impl FrameTypeH2 {
+    /// Type code written in the frame header.
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            FrameTypeH2::Data => DATA_FRAME_TYPE,
+            FrameTypeH2::Headers => HEADERS_FRAME_TYPE,
+            FrameTypeH2::Priority => PRIORITY_FRAME_TYPE,
+            FrameTypeH2::RstStream => RST_STREAM_FRAME_TYPE,
+            FrameTypeH2::Settings => SETTINGS_FRAME_TYPE,
+            FrameTypeH2::PushPromise => PUSH_PROMISE_FRAME_TYPE,
+            FrameTypeH2::Ping => PING_FRAME_TYPE,
+            FrameTypeH2::GoAway => GOAWAY_FRAME_TYPE,
+            FrameTypeH2::WindowUpdate => WINDOW_UPDATE_FRAME_TYPE,
+            FrameTypeH2::Continuation => CONTINUATION_FRAME_TYPE,
+            FrameTypeH2::Unknown(value) => *value,
+        }
+    }
+}
 
 impl FrameH2 {
     pub fn new(frame_type: FrameTypeH2, flags: u8, stream_id: u32, payload: Bytes) -> Self {
@@ -22,11 +43,29 @@ impl FrameH2 {
         Self::new(FrameTypeH2::Data, flags, stream_id, data)
     }
 
+    /// Builds a HEADERS frame with `StaticHpackCodec`, which leaves the peer's
+    /// dynamic table untouched. Use `header_with_codec` to share a connection's codec.
     pub fn header(
         stream_id: u32,
         headers: &[Header],
         end_stream: bool,
         end_headers: bool,
+    ) -> Result<Self, ProtocolError> {
+        Self::header_with_codec(
+            stream_id,
+            headers,
+            end_stream,
+            end_headers,
+            &mut StaticHpackCodec,
+        )
+    }
+
+    pub fn header_with_codec(
+        stream_id: u32,
+        headers: &[Header],
+        end_stream: bool,
+        end_headers: bool,
+        codec: &mut dyn HeaderCodec,
     ) -> Result<Self, ProtocolError> {
         let mut flags = 0;
         if end_stream {
@@ -36,7 +75,7 @@ impl FrameH2 {
             flags |= END_HEADERS_FLAG;
         }
 
-        let payload = Self::encode_headers_hpack(headers)?;
+        let payload = codec.encode(headers)?;
         Ok(Self::new(FrameTypeH2::Headers, flags, stream_id, payload))
     }
 
@@ -44,9 +83,18 @@ impl FrameH2 {
         stream_id: u32,
         headers: &[Header],
         end_headers: bool,
+    ) -> Result<Self, ProtocolError> {
+        Self::continuation_with_codec(stream_id, headers, end_headers, &mut StaticHpackCodec)
+    }
+
+    pub fn continuation_with_codec(
+        stream_id: u32,
+        headers: &[Header],
+        end_headers: bool,
+        codec: &mut dyn HeaderCodec,
     ) -> Result<Self, ProtocolError> {
         let flags = if end_headers { END_HEADERS_FLAG } else { 0 };
-        let payload = Self::encode_headers_hpack(headers)?;
+        let payload = codec.encode(headers)?;
         Ok(Self::new(
             FrameTypeH2::Continuation,
             flags,
@@ -144,75 +192,9 @@ impl FrameH2 {
         async move { sink.write_frame(self).await }
     }
 
-    fn encode_headers_hpack(headers: &[Header]) -> Result<Bytes, ProtocolError> {
-        let mut encoder = Encoder::new();
-
-        let header_tuples: Vec<(&[u8], &[u8])> = headers
-            .iter()
-            .map(|h| {
-                let name = h.name.as_bytes();
-                let value = h.value.as_ref().map(|v| v.as_bytes()).unwrap_or(&[]);
-                (name, value)
-            })
-            .collect();
-
-        let encoded = encoder.encode(header_tuples);
-        Ok(Bytes::from(encoded))
-    }
-
-    fn decode_headers_hpack(payload: &[u8]) -> Result<Vec<Header>, ProtocolError> {
-        let mut decoder = Decoder::new();
-
-        match decoder.decode(payload) {
-            Ok(header_list) => {
-                let headers = header_list
-                    .into_iter()
-                    .map(|(name, value)| {
-                        let name_str = String::from_utf8(name).map_err(|e| {
-                            ProtocolError::HeaderEncodingError(format!(
-                                "Invalid UTF-8 in header name: {}",
-                                e
-                            ))
-                        })?;
-                        let value_str = if value.is_empty() {
-                            None
-                        } else {
-                            Some(String::from_utf8(value).map_err(|e| {
-                                ProtocolError::HeaderEncodingError(format!(
-                                    "Invalid UTF-8 in header value: {}",
-                                    e
-                                ))
-                            })?)
-                        };
-                        Ok(Header {
-                            name: name_str,
-                            value: value_str,
-                        })
-                    })
-                    .collect::<Result<Vec<_>, ProtocolError>>()?;
-                Ok(headers)
-            }
-            Err(e) => Err(ProtocolError::H2CompressionError(format!(
-                "HPACK decode error: {:?}",
-                e
-            ))),
-        }
-    }
-
     pub fn get_frame_type_u8(&self) -> u8 {
         match &self.frame_type {
-            FrameType::H2(frame_type) => match frame_type {
-                FrameTypeH2::Data => DATA_FRAME_TYPE,
-                FrameTypeH2::Headers => HEADERS_FRAME_TYPE,
-                FrameTypeH2::Priority => PRIORITY_FRAME_TYPE,
-                FrameTypeH2::RstStream => RST_STREAM_FRAME_TYPE,
-                FrameTypeH2::Settings => SETTINGS_FRAME_TYPE,
-                FrameTypeH2::PushPromise => PUSH_PROMISE_FRAME_TYPE,
-                FrameTypeH2::Ping => PING_FRAME_TYPE,
-                FrameTypeH2::GoAway => GOAWAY_FRAME_TYPE,
-                FrameTypeH2::WindowUpdate => WINDOW_UPDATE_FRAME_TYPE,
-                FrameTypeH2::Continuation => CONTINUATION_FRAME_TYPE,
-            },
+            FrameType::H2(frame_type) => frame_type.as_u8(),
             FrameType::H3(_) => 0, // Not applicable for H2 framing
         }
     }
@@ -237,10 +219,18 @@ impl FrameH2 {
         (self.flags & PRIORITY_FLAG) != 0
     }
 
+    /// Decodes the payload with a fresh HPACK table; dynamic table references fail.
     pub fn decode_headers(&self) -> Result<Vec<Header>, ProtocolError> {
+        self.decode_headers_with(&mut StaticHpackCodec)
+    }
+
+    pub fn decode_headers_with(
+        &self,
+        codec: &mut dyn HeaderCodec,
+    ) -> Result<Vec<Header>, ProtocolError> {
         match &self.frame_type {
             FrameType::H2(FrameTypeH2::Headers) | FrameType::H2(FrameTypeH2::Continuation) => {
-                Self::decode_headers_hpack(&self.payload)
+                codec.decode(&self.payload)
             }
             _ => Err(ProtocolError::RequestFailed(
                 "Frame is not a header frame".to_string(),
@@ -326,12 +316,8 @@ impl FrameH2 {
             GOAWAY_FRAME_TYPE => FrameTypeH2::GoAway,
             WINDOW_UPDATE_FRAME_TYPE => FrameTypeH2::WindowUpdate,
             CONTINUATION_FRAME_TYPE => FrameTypeH2::Continuation,
-            _ => {
-                return Err(ProtocolError::InvalidResponse(format!(
-                    "Unknown frame type: {}",
-                    frame_type_u8
-                )));
-            }
+            // RFC 9113 Section 4.1: unknown types must be ignored, not rejected.
+            other => FrameTypeH2::Unknown(other),
         };
 
         let payload =
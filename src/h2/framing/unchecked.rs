@@ -0,0 +1,105 @@
+use crate::h2::connection::H2Connection;
+use crate::h2::consts::FRAME_HEADER_SIZE;
+use crate::types::{FrameH2, ProtocolError};
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// A frame written exactly as configured, for conformance and robustness
+/// testing. Unlike [`FrameH2::serialize`] nothing is validated or masked:
+/// the declared length may disagree with the payload, the reserved bit may
+/// be set and any stream ID, type and flags are written as given.
+///
+/// ```ignore
+/// FrameH2::data(2, Bytes::from("abc"), true)
+///     .unchecked()
+///     .length(100)
+///     .reserved_bit(true)
+///     .send(&mut connection)
+///     .await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct UncheckedFrameH2 {
+    frame: FrameH2,
+    length: Option<u32>,
+    frame_type: Option<u8>,
+    reserved_bit: bool,
+}
+
+impl FrameH2 {
+    pub fn unchecked(self) -> UncheckedFrameH2 {
+        UncheckedFrameH2 {
+            frame: self,
+            length: None,
+            frame_type: None,
+            reserved_bit: false,
+        }
+    }
+}
+
+impl UncheckedFrameH2 {
+    /// Declared payload length; only the low 24 bits fit the header.
+    pub fn length(mut self, length: u32) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    /// Type code written instead of the frame's own, e.g. an unknown type.
+    pub fn frame_type(mut self, frame_type: u8) -> Self {
+        self.frame_type = Some(frame_type);
+        self
+    }
+
+    /// Sets the reserved bit in front of the stream ID.
+    pub fn reserved_bit(mut self, set: bool) -> Self {
+        self.reserved_bit = set;
+        self
+    }
+
+    /// Any stream ID, including 0 and even IDs on frames that need a client stream.
+    pub fn stream_id(mut self, stream_id: u32) -> Self {
+        self.frame.stream_id = stream_id;
+        self
+    }
+
+    pub fn flags(mut self, flags: u8) -> Self {
+        self.frame.flags = flags;
+        self
+    }
+
+    pub fn payload(mut self, payload: Bytes) -> Self {
+        self.frame.payload = payload;
+        self
+    }
+
+    pub fn frame(&self) -> &FrameH2 {
+        &self.frame
+    }
+
+    pub fn serialize(&self) -> Bytes {
+        let payload = &self.frame.payload;
+        let length = self.length.unwrap_or(payload.len() as u32);
+        let frame_type = self
+            .frame_type
+            .unwrap_or_else(|| self.frame.get_frame_type_u8());
+        let mut stream_id = self.frame.stream_id;
+        if self.reserved_bit {
+            stream_id |= 0x8000_0000;
+        }
+
+        let mut result = BytesMut::with_capacity(FRAME_HEADER_SIZE + payload.len());
+        result.put_u8(((length >> 16) & 0xFF) as u8);
+        result.put_u8(((length >> 8) & 0xFF) as u8);
+        result.put_u8((length & 0xFF) as u8);
+        result.put_u8(frame_type);
+        result.put_u8(self.frame.flags);
+        result.put_u32(stream_id);
+        result.put_slice(payload);
+        result.freeze()
+    }
+
+    /// Writes the frame through `connection`. Stream state and flow
+    /// control are left untouched, since the frame may not mean what its
+    /// type says.
+    pub async fn send(self, connection: &mut H2Connection) -> Result<(), ProtocolError> {
+        connection.write_raw(self.serialize()).await
+    }
+}
@@ -1,8 +1,24 @@
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
 use hpack::{Decoder, Encoder};
 
 use crate::types::{Header, ProtocolError};
 
+/// Header block compression used for HEADERS/CONTINUATION payloads.
+///
+/// `H2Connection` owns one stateful codec per connection; raw frame builders
+/// accept any implementation so their output matches what the connection sends.
+pub trait HeaderCodec {
+    fn encode(&mut self, headers: &[Header]) -> Result<Bytes, ProtocolError>;
+    fn decode(&mut self, block: &[u8]) -> Result<Vec<Header>, ProtocolError>;
+
+    /// Peer's SETTINGS_HEADER_TABLE_SIZE; bounds the encoder's dynamic table.
+    fn set_encoder_max_table_size(&mut self, _size: usize) {}
+
+    /// Our advertised SETTINGS_HEADER_TABLE_SIZE; bounds the decoder's dynamic table.
+    fn set_decoder_max_table_size(&mut self, _size: usize) {}
+}
+
+/// Stateful HPACK codec backed by the `hpack` crate (dynamic table, Huffman).
 pub struct HpackCodec {
     encoder: Encoder<'static>,
     decoder: Decoder<'static>,
@@ -22,18 +38,6 @@ impl HpackCodec {
         codec
     }
 
-    pub fn set_encoder_max_table_size(&mut self, size: usize) {
-        self.encoder_max_table_size = size;
-        // The hpack encoder crate does not expose an API to bound the dynamic
-        // table size directly; this value is tracked so we can emit SETTINGS
-        // updates when necessary.
-    }
-
-    pub fn set_decoder_max_table_size(&mut self, size: usize) {
-        self.decoder_max_table_size = size;
-        self.apply_decoder_table_size(size);
-    }
-
     pub fn encoder_max_table_size(&self) -> usize {
         self.encoder_max_table_size
     }
@@ -42,7 +46,13 @@ impl HpackCodec {
         self.decoder_max_table_size
     }
 
-    pub fn encode(&mut self, headers: &[Header]) -> Result<Bytes, ProtocolError> {
+    fn apply_decoder_table_size(&mut self, size: usize) {
+        self.decoder.set_max_table_size(size);
+    }
+}
+
+impl HeaderCodec for HpackCodec {
+    fn encode(&mut self, headers: &[Header]) -> Result<Bytes, ProtocolError> {
         let header_tuples = headers
             .iter()
             .map(|h| {
@@ -56,38 +66,175 @@ impl HpackCodec {
         Ok(Bytes::from(encoded))
     }
 
-    pub fn decode(&mut self, payload: &[u8]) -> Result<Vec<Header>, ProtocolError> {
-        match self.decoder.decode(payload) {
-            Ok(entries) => entries
-                .into_iter()
-                .map(|(name, value)| Self::into_header(name, value))
-                .collect(),
-            Err(err) => Err(ProtocolError::H2CompressionError(format!(
-                "HPACK decode error: {:?}",
-                err
-            ))),
+    fn decode(&mut self, block: &[u8]) -> Result<Vec<Header>, ProtocolError> {
+        decode_with(&mut self.decoder, block)
+    }
+
+    fn set_encoder_max_table_size(&mut self, size: usize) {
+        self.encoder_max_table_size = size;
+        // The hpack encoder crate does not expose an API to bound the dynamic
+        // table size directly; this value is tracked so we can emit SETTINGS
+        // updates when necessary.
+    }
+
+    fn set_decoder_max_table_size(&mut self, size: usize) {
+        self.decoder_max_table_size = size;
+        self.apply_decoder_table_size(size);
+    }
+}
+
+/// HPACK codec that never touches the dynamic table.
+///
+/// Fields are emitted as static-table references or literals *without
+/// indexing*, so frames built with it cannot desynchronize the peer's table
+/// from the connection codec. Decoding uses a fresh table each call.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StaticHpackCodec;
+
+impl HeaderCodec for StaticHpackCodec {
+    fn encode(&mut self, headers: &[Header]) -> Result<Bytes, ProtocolError> {
+        let mut out = BytesMut::new();
+        for header in headers {
+            let name = header.name.as_str();
+            let value = header.value.as_deref().unwrap_or("");
+
+            let exact = STATIC_TABLE
+                .iter()
+                .position(|(n, v)| *n == name && *v == value);
+            if let Some(index) = exact {
+                encode_integer(&mut out, index as u64 + 1, 7, 0x80);
+                continue;
+            }
+
+            match STATIC_TABLE.iter().position(|(n, _)| *n == name) {
+                Some(index) => encode_integer(&mut out, index as u64 + 1, 4, 0x00),
+                None => {
+                    out.put_u8(0x00);
+                    encode_string(&mut out, name.as_bytes());
+                }
+            }
+            encode_string(&mut out, value.as_bytes());
         }
+        Ok(out.freeze())
     }
 
-    fn into_header(name: Vec<u8>, value: Vec<u8>) -> Result<Header, ProtocolError> {
-        let name_str = String::from_utf8(name).map_err(|e| {
-            ProtocolError::HeaderEncodingError(format!("Invalid UTF-8 in header name: {}", e))
-        })?;
-        let value = if value.is_empty() {
-            None
-        } else {
-            Some(String::from_utf8(value).map_err(|e| {
-                ProtocolError::HeaderEncodingError(format!("Invalid UTF-8 in header value: {}", e))
-            })?)
-        };
+    fn decode(&mut self, block: &[u8]) -> Result<Vec<Header>, ProtocolError> {
+        decode_with(&mut Decoder::new(), block)
+    }
+}
 
-        Ok(Header {
-            name: name_str,
-            value,
-        })
+fn encode_integer(out: &mut BytesMut, value: u64, prefix_bits: u8, flags: u8) {
+    let max = (1u64 << prefix_bits) - 1;
+    if value < max {
+        out.put_u8(flags | value as u8);
+        return;
+    }
+    out.put_u8(flags | max as u8);
+    let mut rest = value - max;
+    while rest >= 0x80 {
+        out.put_u8((rest as u8 & 0x7f) | 0x80);
+        rest >>= 7;
     }
+    out.put_u8(rest as u8);
+}
 
-    fn apply_decoder_table_size(&mut self, size: usize) {
-        self.decoder.set_max_table_size(size);
+fn encode_string(out: &mut BytesMut, data: &[u8]) {
+    encode_integer(out, data.len() as u64, 7, 0x00);
+    out.put_slice(data);
+}
+
+fn decode_with(decoder: &mut Decoder<'static>, block: &[u8]) -> Result<Vec<Header>, ProtocolError> {
+    match decoder.decode(block) {
+        Ok(entries) => entries
+            .into_iter()
+            .map(|(name, value)| into_header(name, value))
+            .collect(),
+        Err(err) => Err(ProtocolError::H2CompressionError(format!(
+            "HPACK decode error: {:?}",
+            err
+        ))),
     }
 }
+
+fn into_header(name: Vec<u8>, value: Vec<u8>) -> Result<Header, ProtocolError> {
+    let name_str = String::from_utf8(name).map_err(|e| {
+        ProtocolError::HeaderEncodingError(format!("Invalid UTF-8 in header name: {}", e))
+    })?;
+    let value = if value.is_empty() {
+        None
+    } else {
+        Some(String::from_utf8(value).map_err(|e| {
+            ProtocolError::HeaderEncodingError(format!("Invalid UTF-8 in header value: {}", e))
+        })?)
+    };
+
+    Ok(Header {
+        name: name_str,
+        value,
+    })
+}
+
+/// RFC 7541 Appendix A
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
@@ -1,7 +1,9 @@
+pub mod conformance;
 pub mod connection;
 pub mod consts;
 pub mod framing;
 pub mod hpack;
 pub mod protocol;
+pub mod scheduler;
 
 pub use protocol::H2;
@@ -1,10 +1,25 @@
-use crate::h2::connection::H2Connection;
-use crate::types::{ClientTimeouts, H2StreamErrorKind, Protocol, ProtocolError, Request, Response};
+use crate::h1::protocol::H1;
+use crate::h2::connection::{H2Connection, H2Mode, HeaderStreaming, StreamState};
+use crate::pool::{pool_key, ConnectionPool, PoolControl};
+use crate::types::{
+    ClientConfig, ClientTimeouts, EndStreamMode, H2ErrorCode, H2StreamErrorKind, HttpProtocol,
+    MemoryBudget, Protocol, ProtocolError, Request, Response, StreamingResponse, UserAgent,
+};
 use async_trait::async_trait;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct H2 {
-    timeouts: ClientTimeouts,
+    config: ClientConfig,
+    mode: Option<H2Mode>,
+    http1_fallback: bool,
+    /// Serves the HTTP/1.1 fallback; shares its pool with clones.
+    http1: H1,
+    adaptive_window: bool,
+    max_header_list_size: Option<u32>,
+    header_streaming: Option<HeaderStreaming>,
+    memory_budget: Option<MemoryBudget>,
+    pool: Arc<ConnectionPool<H2Connection>>,
 }
 
 impl H2 {
@@ -13,25 +28,172 @@ impl H2 {
     }
 
     pub fn timeouts(timeouts: ClientTimeouts) -> Self {
-        Self { timeouts }
+        Self::with_config(ClientConfig::new().timeouts(timeouts))
+    }
+
+    pub fn with_config(config: ClientConfig) -> Self {
+        let http1 = H1::with_config(config.clone());
+        Self {
+            config,
+            mode: None,
+            http1_fallback: false,
+            http1,
+            adaptive_window: false,
+            max_header_list_size: None,
+            header_streaming: None,
+            memory_budget: None,
+            pool: Arc::new(ConnectionPool::default()),
+        }
+    }
+
+    /// Forces how connections are established instead of inferring it from the scheme.
+    pub fn with_mode(mut self, mode: H2Mode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Retries the request over HTTP/1.1 when the server answers the preface
+    /// with HTTP/1.x or turns `h2` down during ALPN.
+    pub fn with_http1_fallback(mut self, enabled: bool) -> Self {
+        self.http1_fallback = enabled;
+        self
+    }
+
+    /// Toggles BDP-based receive window auto-tuning (off by default).
+    pub fn with_adaptive_window(mut self, enabled: bool) -> Self {
+        self.adaptive_window = enabled;
+        self
+    }
+
+    /// Advertises a larger SETTINGS_MAX_HEADER_LIST_SIZE for servers that send
+    /// very large response headers. A response whose header list is larger
+    /// than advertised, 8 KiB by default, fails with `HeaderLimitExceeded`.
+    pub fn with_max_header_list_size(mut self, size: u32) -> Self {
+        self.max_header_list_size = Some(size);
+        self
+    }
+
+    /// Decodes response header blocks as their frames arrive, see
+    /// [`HeaderStreaming`]. Pair with
+    /// [`with_max_header_list_size`](Self::with_max_header_list_size) for
+    /// servers that send megabytes of headers.
+    pub fn with_header_streaming(mut self, streaming: HeaderStreaming) -> Self {
+        self.header_streaming = Some(streaming);
+        self
+    }
+
+    /// Caps what each connection buffers, see [`MemoryBudget`].
+    pub fn with_memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(budget);
+        self
+    }
+
+    /// `User-Agent` for requests that do not pick their own.
+    pub fn with_user_agent(mut self, user_agent: UserAgent) -> Self {
+        self.http1 = self.http1.with_user_agent(user_agent.clone());
+        self.config.user_agent = user_agent;
+        self
     }
 
     pub fn get_timeouts(&self) -> &ClientTimeouts {
-        &self.timeouts
+        &self.config.timeouts
+    }
+
+    pub fn get_config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    pub fn get_mode(&self) -> Option<H2Mode> {
+        self.mode
     }
 
     pub fn session(&self) -> crate::session::H2Session {
         crate::session::H2Session::new(self.clone())
     }
 
+    /// Connections parked by [`preconnect`](Self::preconnect) or after a
+    /// response, shared with clones of this client. Each request takes one
+    /// and opens the next stream on it; a connection that received GOAWAY
+    /// is not reused.
+    pub fn pool(&self) -> &ConnectionPool<H2Connection> {
+        &self.pool
+    }
+
+    /// Client for the HTTP/1.1 fallback, with the same configuration.
+    pub fn http1(&self) -> &H1 {
+        &self.http1
+    }
+
+    /// Connects to `origin` and completes the TLS and HTTP/2 handshakes, then
+    /// parks the connection for the next request there.
+    pub async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        let mut request = Request::new(origin, "GET")?;
+        self.config.apply(&mut request);
+        if let Some(scope) = &self.config.host_scope {
+            scope.check(&request.target).await?;
+        }
+        let key = pool_key(&request).ok_or_else(|| {
+            ProtocolError::RequestFailed(
+                "Guarded or fresh-DNS connections are not pooled".to_string(),
+            )
+        })?;
+        let timeouts = request.timeouts(&self.config.timeouts);
+        let connect = self.open_connection(&request, &timeouts, false);
+        let connection = match self.pool.timed_connect(Some(&key), connect).await {
+            Ok(connection) => connection,
+            Err(err) if self.http1_fallback && refuses_h2(&err) => {
+                return self.http1.preconnect(origin).await;
+            }
+            Err(err) => return Err(err),
+        };
+        self.pool.park_and_close_evicted(&key, connection).await;
+        Ok(())
+    }
+
+    /// Connects for `request`. With `upgrade` in [`H2Mode::Upgrade`], the
+    /// request itself is the upgrade request and its response arrives on
+    /// stream 1.
+    async fn open_connection(
+        &self,
+        request: &Request,
+        timeouts: &ClientTimeouts,
+        upgrade: bool,
+    ) -> Result<H2Connection, ProtocolError> {
+        let guard = request.ssrf_guard.as_ref();
+        let socket_options = self.config.socket_options_for(request);
+        let mut connection = if upgrade && self.mode == Some(H2Mode::Upgrade) {
+            H2Connection::connect_upgrade(
+                request,
+                timeouts,
+                guard,
+                &socket_options,
+                self.max_header_list_size,
+            )
+            .await?
+        } else {
+            H2Connection::connect_with_header_list_size(
+                request.target.url.as_str(),
+                timeouts,
+                self.mode,
+                guard,
+                &socket_options,
+                self.max_header_list_size,
+            )
+            .await?
+        };
+        connection.set_adaptive_window(self.adaptive_window);
+        connection.set_header_streaming(self.header_streaming.clone());
+        connection.set_memory_budget(self.memory_budget);
+        Ok(connection)
+    }
+
     async fn send_request_inner(
         &self,
         connection: &mut H2Connection,
+        stream_id: u32,
         request: &Request,
-    ) -> Result<u32, ProtocolError> {
-        let stream_id = connection.create_stream().await?;
-
-        let prepared = request.prepare_request()?;
+    ) -> Result<(), ProtocolError> {
+        let prepared = request.prepare_once()?;
         let header_block = prepared.header_block();
 
         let has_body = prepared
@@ -40,7 +202,8 @@ impl H2 {
             .map_or(false, |body| !body.is_empty());
         let has_trailers = !prepared.trailers.is_empty();
 
-        let end_stream = !has_body && !has_trailers;
+        let defer_end = request.end_stream_mode != EndStreamMode::Immediate;
+        let end_stream = !has_body && !has_trailers && !defer_end;
         connection
             .send_headers(stream_id, &header_block, end_stream)
             .await
@@ -53,20 +216,31 @@ impl H2 {
 
         if let Some(body) = prepared.body.as_ref() {
             if !body.is_empty() {
-                let end_stream = !has_trailers;
-                connection
-                    .send_data(stream_id, body, end_stream)
-                    .await
-                    .map_err(|e| {
-                        ProtocolError::H2StreamError(H2StreamErrorKind::ProtocolViolation(format!(
-                            "Failed to send data: {}",
-                            e
-                        )))
-                    })?;
+                let end_stream = !has_trailers && !defer_end;
+                connection.queue_data(stream_id, body.clone(), end_stream);
+                connection.flush_scheduled_data().await.map_err(|e| {
+                    ProtocolError::H2StreamError(H2StreamErrorKind::ProtocolViolation(format!(
+                        "Failed to send data: {}",
+                        e
+                    )))
+                })?;
             }
         }
 
-        if !prepared.trailers.is_empty() {
+        match request.end_stream_mode {
+            EndStreamMode::Immediate => {}
+            EndStreamMode::After(hold) => {
+                connection.process_frames_for(hold).await?;
+                if !has_trailers
+                    && connection.get_stream_state(stream_id) != Some(&StreamState::Closed)
+                {
+                    connection.send_data(stream_id, &[], true).await?;
+                }
+            }
+            EndStreamMode::Never => return Ok(()),
+        }
+
+        if has_trailers && connection.get_stream_state(stream_id) != Some(&StreamState::Closed) {
             connection
                 .send_headers(stream_id, &prepared.trailers, true)
                 .await
@@ -78,19 +252,191 @@ impl H2 {
                 })?;
         }
 
-        Ok(stream_id)
+        Ok(())
     }
 
     pub async fn send_request(&self, request: Request) -> Result<Response, ProtocolError> {
         <Self as Protocol>::response(self, request).await
     }
 
+    /// Sends `request` on a connection of its own and returns once the
+    /// response head is in. The body comes off the connection as it is
+    /// consumed, and the connection is closed with it. Redirects are not
+    /// followed and there is no HTTP/1.1 fallback.
+    pub async fn send_request_streaming(
+        &self,
+        mut request: Request,
+    ) -> Result<StreamingResponse<'static>, ProtocolError> {
+        self.apply_defaults(&mut request);
+        if let Some(scope) = &self.config.host_scope {
+            scope.check(&request.target).await?;
+        }
+        request.check_headers(&HttpProtocol::Http2)?;
+        let timeouts = request.timeouts(&self.config.timeouts);
+        let mut connection = self.open_connection(&request, &timeouts, true).await?;
+        let stream_id = if self.mode == Some(H2Mode::Upgrade) {
+            // The upgrade request was this request; stream 1 answers it.
+            1
+        } else {
+            let stream_id = connection.create_stream().await?;
+            self.send_request_inner(&mut connection, stream_id, &request)
+                .await?;
+            stream_id
+        };
+        Ok(connection.into_response_streaming(stream_id).await?.into())
+    }
+
     async fn perform_request(&self, request: &Request) -> Result<Response, ProtocolError> {
-        let timeouts = request.timeouts(&self.timeouts);
-        let mut connection = H2Connection::connect(request.target.url.as_str(), &timeouts).await?;
-        let stream_id = self.send_request_inner(&mut connection, request).await?;
-        connection.read_response(stream_id).await
+        request.check_headers(&HttpProtocol::Http2)?;
+        let timeouts = request.timeouts(&self.config.timeouts);
+        let key = pool_key(request);
+        let warm = key
+            .as_deref()
+            .and_then(|key| self.pool.take(key))
+            .filter(H2Connection::accepts_new_streams);
+        if let Some(mut connection) = warm {
+            connection.set_timeouts(timeouts.clone());
+            let start = connection.wire_bytes();
+            match self
+                .exchange(connection, request, key.as_deref(), start)
+                .await
+            {
+                // The server went away or refused the stream without acting
+                // on the request, or had closed the connection while it was
+                // parked; it goes out again on a new connection.
+                Err((_, true)) => {}
+                result => return result.map_err(|(err, _)| err),
+            }
+        }
+        let connection = match self
+            .pool
+            .timed_connect(
+                key.as_deref(),
+                self.open_connection(request, &timeouts, true),
+            )
+            .await
+        {
+            Ok(connection) => connection,
+            Err(err) if self.http1_fallback && refuses_h2(&err) => {
+                return self.http1.execute(request).await;
+            }
+            Err(err) => return Err(err),
+        };
+        // A new connection's handshake is charged to the request that
+        // opened it.
+        let result = if self.mode == Some(H2Mode::Upgrade) {
+            // The upgrade request was this request; stream 1 answers it.
+            self.complete(connection, 1, Ok(()), request, key.as_deref(), (0, 0))
+                .await
+        } else {
+            self.exchange(connection, request, key.as_deref(), (0, 0))
+                .await
+        };
+        result.map_err(|(err, _)| err)
+    }
+
+    /// Sends `request` on a new stream of `connection` and reads the
+    /// response, then parks the connection under `key` if it can open more
+    /// streams. Errors come with whether the server is known not to have
+    /// processed the request (RFC 9113 Section 8.7), so that it is safe to
+    /// send again. The connection's wire bytes past `start` are charged to
+    /// the request's traffic meter, whether or not the exchange succeeds.
+    async fn exchange(
+        &self,
+        mut connection: H2Connection,
+        request: &Request,
+        key: Option<&str>,
+        start: (u64, u64),
+    ) -> Result<Response, (ProtocolError, bool)> {
+        let stream_id = match connection.create_stream().await {
+            Ok(stream_id) => stream_id,
+            Err(err) => {
+                charge(request, &connection, start);
+                return Err((err, true));
+            }
+        };
+        let sent = self
+            .send_request_inner(&mut connection, stream_id, request)
+            .await;
+        self.complete(connection, stream_id, sent, request, key, start)
+            .await
     }
+
+    /// Reads the response to `request`, sent on `stream_id` with `sent` as
+    /// the outcome, and parks the connection as [`exchange`](Self::exchange)
+    /// describes.
+    async fn complete(
+        &self,
+        mut connection: H2Connection,
+        stream_id: u32,
+        sent: Result<(), ProtocolError>,
+        request: &Request,
+        key: Option<&str>,
+        start: (u64, u64),
+    ) -> Result<Response, (ProtocolError, bool)> {
+        let result = match sent {
+            Ok(()) => connection.read_response(stream_id).await,
+            Err(err) => Err(err),
+        };
+        charge(request, &connection, start);
+        let mut response = result.map_err(|err| {
+            let unprocessed = is_refused(&err)
+                || connection
+                    .goaway_last_stream_id()
+                    .map_or(false, |last| stream_id > last)
+                // A parked connection the server closed while it sat idle:
+                // nothing came back since it was taken.
+                || (is_stale_connection(&err) && connection.wire_bytes().1 == start.1);
+            (err, unprocessed)
+        })?;
+
+        if let Some(key) = key.filter(|_| connection.accepts_new_streams()) {
+            self.pool.park_and_close_evicted(key, connection).await;
+        }
+        response
+            .capture_decoded_body(&request.decompression_limits)
+            .map_err(|err| (err, false))?;
+        Ok(response)
+    }
+}
+
+/// Charges the connection's wire bytes past `start` to `request`.
+fn charge(request: &Request, connection: &H2Connection, start: (u64, u64)) {
+    let (sent, received) = connection.wire_bytes();
+    request.charge_traffic(sent - start.0, received - start.1);
+}
+
+/// True if the peer reset the stream with REFUSED_STREAM, which it only
+/// does before processing the request.
+fn is_refused(err: &ProtocolError) -> bool {
+    matches!(
+        err,
+        ProtocolError::H2StreamError(H2StreamErrorKind::Reset(H2ErrorCode::RefusedStream))
+    )
+}
+
+/// Errors a parked connection the server has since closed fails with.
+fn is_stale_connection(err: &ProtocolError) -> bool {
+    match err {
+        ProtocolError::ConnectionFailed(_) => true,
+        ProtocolError::Io(err) => matches!(
+            err.kind(),
+            std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
+
+/// True if the connection attempt failed because the server does not speak
+/// HTTP/2, as opposed to being unreachable.
+fn refuses_h2(err: &ProtocolError) -> bool {
+    matches!(
+        err,
+        ProtocolError::H2UnexpectedHttp1Response(_) | ProtocolError::AlpnMismatch { .. }
+    )
 }
 
 #[async_trait(?Send)]
@@ -98,4 +444,17 @@ impl Protocol for H2 {
     async fn execute(&self, request: &Request) -> Result<Response, ProtocolError> {
         self.perform_request(request).await
     }
+
+    async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        H2::preconnect(self, origin).await
+    }
+
+    /// The HTTP/2 pool, then the HTTP/1.1 fallback's.
+    fn pools(&self) -> Vec<&dyn PoolControl> {
+        vec![self.pool.as_ref() as &dyn PoolControl, self.http1.pool()]
+    }
+
+    fn config(&self) -> Option<&ClientConfig> {
+        Some(&self.config)
+    }
 }
@@ -0,0 +1,154 @@
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+
+pub const DEFAULT_STREAM_WEIGHT: u8 = 1;
+
+struct PendingBody {
+    stream_id: u32,
+    data: Bytes,
+    end_stream: bool,
+    credits: u8,
+}
+
+/// A DATA frame picked by the scheduler, ready to be written.
+#[derive(Debug, Clone)]
+pub struct ScheduledFrame {
+    pub stream_id: u32,
+    pub payload: Bytes,
+    pub end_stream: bool,
+}
+
+/// Weighted round-robin scheduler for outgoing DATA frames.
+///
+/// Every stream with queued body bytes gets up to `weight` frames per turn;
+/// streams blocked on flow control are skipped until their window opens.
+#[derive(Default)]
+pub struct DataScheduler {
+    queue: VecDeque<PendingBody>,
+    /// Weights set with `set_weight`, kept while a stream's body is not
+    /// queued and forgotten once it ends.
+    weights: HashMap<u32, u8>,
+}
+
+impl DataScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues body bytes for a stream, appending to anything already pending.
+    pub fn push(&mut self, stream_id: u32, data: Bytes, end_stream: bool) {
+        if let Some(entry) = self.queue.iter_mut().find(|e| e.stream_id == stream_id) {
+            if !data.is_empty() {
+                let mut joined = Vec::with_capacity(entry.data.len() + data.len());
+                joined.extend_from_slice(&entry.data);
+                joined.extend_from_slice(&data);
+                entry.data = Bytes::from(joined);
+            }
+            entry.end_stream |= end_stream;
+            return;
+        }
+
+        if data.is_empty() && !end_stream {
+            return;
+        }
+
+        self.queue.push_back(PendingBody {
+            stream_id,
+            data,
+            end_stream,
+            credits: self.weight(stream_id),
+        });
+    }
+
+    /// Number of frames a stream may emit per round; clamped to at least 1.
+    /// May be set before the stream's body is queued.
+    pub fn set_weight(&mut self, stream_id: u32, weight: u8) {
+        let weight = weight.max(1);
+        self.weights.insert(stream_id, weight);
+        if let Some(entry) = self.queue.iter_mut().find(|e| e.stream_id == stream_id) {
+            entry.credits = weight;
+        }
+    }
+
+    pub fn weight(&self, stream_id: u32) -> u8 {
+        self.weights
+            .get(&stream_id)
+            .copied()
+            .unwrap_or(DEFAULT_STREAM_WEIGHT)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn pending_bytes(&self) -> usize {
+        self.queue.iter().map(|e| e.data.len()).sum()
+    }
+
+    pub fn is_queued(&self, stream_id: u32) -> bool {
+        self.queue.iter().any(|e| e.stream_id == stream_id)
+    }
+
+    /// Drops queued data and weights for streams that no longer satisfy
+    /// `keep`.
+    pub fn retain(&mut self, mut keep: impl FnMut(u32) -> bool) {
+        self.queue.retain(|e| keep(e.stream_id));
+        self.weights.retain(|&stream_id, _| keep(stream_id));
+    }
+
+    /// Picks the next frame that fits the current windows, or `None` when every
+    /// queued stream is blocked on flow control.
+    pub fn next_frame(
+        &mut self,
+        connection_window: i32,
+        stream_window: impl Fn(u32) -> i32,
+        max_frame_size: usize,
+    ) -> Option<ScheduledFrame> {
+        for _ in 0..self.queue.len() {
+            let entry = self.queue.front_mut()?;
+            let window = stream_window(entry.stream_id).min(connection_window);
+
+            if !entry.data.is_empty() && window <= 0 {
+                self.queue.rotate_left(1);
+                continue;
+            }
+
+            let len = entry
+                .data
+                .len()
+                .min(max_frame_size)
+                .min(window.max(0) as usize);
+            let payload = entry.data.split_to(len);
+            let stream_id = entry.stream_id;
+
+            if entry.data.is_empty() {
+                let end_stream = entry.end_stream;
+                self.queue.pop_front();
+                if end_stream {
+                    self.weights.remove(&stream_id);
+                }
+                return Some(ScheduledFrame {
+                    stream_id,
+                    payload,
+                    end_stream,
+                });
+            }
+
+            entry.credits -= 1;
+            if entry.credits == 0 {
+                entry.credits = self
+                    .weights
+                    .get(&stream_id)
+                    .copied()
+                    .unwrap_or(DEFAULT_STREAM_WEIGHT);
+                self.queue.rotate_left(1);
+            }
+            return Some(ScheduledFrame {
+                stream_id,
+                payload,
+                end_stream: false,
+            });
+        }
+        None
+    }
+}
@@ -1,23 +1,39 @@
+mod debug;
+mod mirror;
+mod priority;
 mod state;
+mod streaming;
+mod writer;
 
-pub use state::{ConnectionState, StreamInfo, StreamState};
+pub use debug::{H3DebugState, H3StreamDebugState};
+pub use state::{ConnectionState, PushState, StreamInfo, StreamState};
+pub use writer::H3StreamWriter;
 
-use crate::connection::HttpConnection;
+use crate::connection::{ConnectionEvent, ConnectionEvents, HttpConnection};
+use crate::dns::DnsCache;
 use crate::h3::consts::*;
+use crate::h3::endpoint::EndpointPool;
 use crate::h3::framing::{
-    SETTINGS_MAX_FIELD_SECTION_SIZE, SETTINGS_QPACK_BLOCKED_STREAMS,
+    PRIORITY_UPDATE_FRAME_TYPE, SETTINGS_MAX_FIELD_SECTION_SIZE, SETTINGS_QPACK_BLOCKED_STREAMS,
     SETTINGS_QPACK_MAX_TABLE_CAPACITY,
 };
 use crate::h3::qpack::{QpackDecodeStatus, SharedQpackState};
+use crate::handshake::{HandshakePhase, HandshakeTracker};
 use crate::stream::NoCertificateVerification;
 use crate::types::{
-    ClientTimeouts, FrameH3, FrameSink, FrameType, FrameTypeH3, H3StreamErrorKind, Header,
-    ProtocolError, Response, ResponseFrame, Target,
+    ClientTimeouts, ConnectFailure, ConnectMechanism, FrameH3, FrameSink, FrameType, FrameTypeH3,
+    H3StreamErrorKind, Header, HeaderBlockInfo, Priority, ProtocolError, QueueOverflow, Response,
+    ResponseFrame, SocketOptions, SsrfGuard, StreamQueueLimits, Target,
 };
-use crate::utils::{parse_target, timeout_result, HTTP_VERSION_3_0};
+use crate::utils::{parse_target, time_remaining, timeout_result, HTTP_VERSION_3_0};
+use crate::wire::{WireDirection, WireMirror, WireSink};
 use async_trait::async_trait;
 use bytes::{Bytes, BytesMut};
-use quinn::{ClientConfig as QuinnClientConfig, Connection, Endpoint, RecvStream, SendStream};
+use mirror::quic_stream_id;
+use quinn::{
+    ClientConfig as QuinnClientConfig, ConnectError, Connecting, Connection, RecvStream,
+    SendStream, VarInt,
+};
 use std::collections::HashMap;
 use std::io;
 use std::net::SocketAddr;
@@ -26,7 +42,8 @@ use quinn::crypto::rustls::QuicClientConfig;
 use rustls::crypto::ring::default_provider;
 use rustls::ClientConfig;
 use std::sync::Arc;
-use tokio::net::lookup_host;
+use std::time::Instant;
+use tokio::sync::mpsc;
 use tokio::time::{timeout, Duration};
 
 enum QpackStreamRole {
@@ -41,6 +58,7 @@ pub struct H3Connection {
     pub remote_settings: HashMap<u64, u64>,
     pub streams: HashMap<u32, StreamInfo>, // HTTP/3 request stream tracking
     pub next_stream_id: u32,
+    pub pushes: PushState,
     pub control_send_stream: Option<SendStream>,
     pub control_recv_stream: Option<RecvStream>,
     control_recv_buf: BytesMut,
@@ -49,6 +67,23 @@ pub struct H3Connection {
     pub qpack_encoder_recv: Option<RecvStream>,
     pub qpack_decoder_recv: Option<RecvStream>,
     timeouts: ClientTimeouts,
+    /// Per-stream deadlines set with `set_stream_timeout`.
+    stream_deadlines: HashMap<u32, Instant>,
+    /// Set with `set_stream_queue_limits`.
+    queue_limits: Option<StreamQueueLimits>,
+    /// QPACK stream instructions read while decoding each stream's headers,
+    /// not yet attached to its response.
+    qpack_captures: HashMap<u32, Vec<(Instant, FrameH3)>>,
+    /// Identifiers carried by the GOAWAY frames sent and received.
+    goaway_sent: Option<u64>,
+    goaway_received: Option<u64>,
+    /// Last PRIORITY_UPDATE sent and received for each request stream.
+    priorities_sent: HashMap<u32, Priority>,
+    priorities_received: HashMap<u64, Priority>,
+    events: ConnectionEvents,
+    handshake: HandshakeTracker,
+    /// Copy of the request and control stream bytes, see `set_wire_mirror`.
+    wire_mirror: WireMirror,
 }
 
 #[derive(Debug, Clone)]
@@ -69,28 +104,37 @@ impl H3Connection {
         port: u16,
         server_name: &str,
     ) -> io::Result<Connection> {
-        let _ = default_provider().install_default();
-
-        let mut rustls_config = ClientConfig::builder()
-            .dangerous()
-            .with_custom_certificate_verifier(Arc::new(NoCertificateVerification))
-            .with_no_client_auth();
-        rustls_config.alpn_protocols = vec![b"h3".to_vec()];
+        Self::create_quic_connection_with_options(
+            host,
+            port,
+            server_name,
+            &SocketOptions::default(),
+        )
+        .await
+    }
 
-        let quic_crypto = QuicClientConfig::try_from(rustls_config)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-        let client_config = QuinnClientConfig::new(Arc::new(quic_crypto));
+    /// Like [`create_quic_connection`](Self::create_quic_connection), binding
+    /// the local endpoint per `options`.
+    pub async fn create_quic_connection_with_options(
+        host: &str,
+        port: u16,
+        server_name: &str,
+        options: &SocketOptions,
+    ) -> io::Result<Connection> {
+        let resolved_addrs = Self::resolve(host, port, options.fresh_dns).await?;
+        Self::connect_quic(resolved_addrs, server_name, options).await
+    }
 
-        // Resolve hostname to addresses (DNS)
-        let resolved_addrs: Vec<SocketAddr> = lookup_host((host, port))
+    async fn resolve(host: &str, port: u16, fresh: bool) -> io::Result<Vec<SocketAddr>> {
+        let resolved_addrs: Vec<SocketAddr> = DnsCache::global()
+            .resolve_for(host, port, fresh)
             .await
             .map_err(|e| {
                 io::Error::new(
                     io::ErrorKind::Other,
                     format!("DNS lookup failed for {}:{}: {}", host, port, e),
                 )
-            })?
-            .collect();
+            })?;
 
         if resolved_addrs.is_empty() {
             return Err(io::Error::new(
@@ -98,41 +142,68 @@ impl H3Connection {
                 format!("No addresses found for {}:{}", host, port),
             ));
         }
+        Ok(resolved_addrs)
+    }
 
-        let mut addrs = resolved_addrs;
-        addrs.sort_by_key(|addr| if addr.is_ipv4() { 0 } else { 1 });
-
-        let mut last_error: Option<io::Error> = None;
+    fn quic_client_config() -> io::Result<QuinnClientConfig> {
+        let _ = default_provider().install_default();
 
-        for addr in addrs {
-            let bind_addr = if addr.is_ipv4() {
-                SocketAddr::from(([0, 0, 0, 0], 0))
-            } else {
-                SocketAddr::from(([0u16; 8], 0))
-            };
+        let mut rustls_config = ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoCertificateVerification))
+            .with_no_client_auth();
+        rustls_config.alpn_protocols = vec![b"h3".to_vec()];
 
-            let mut endpoint = Endpoint::client(bind_addr)?;
-            endpoint.set_default_client_config(client_config.clone());
+        let quic_crypto = QuicClientConfig::try_from(rustls_config)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(QuinnClientConfig::new(Arc::new(quic_crypto)))
+    }
 
-            match endpoint.connect(addr, server_name) {
-                Ok(connecting) => match connecting.await {
-                    Ok(connection) => return Ok(connection),
-                    Err(e) => {
-                        last_error = Some(io::Error::new(io::ErrorKind::ConnectionRefused, e));
-                    }
-                },
-                Err(e) => {
-                    last_error = Some(io::Error::new(io::ErrorKind::ConnectionRefused, e));
-                }
+    /// Starts a handshake from the shared endpoint for `addr`, replacing the
+    /// endpoint once if its driver has stopped.
+    fn start_connect(
+        client_config: &QuinnClientConfig,
+        addr: SocketAddr,
+        server_name: &str,
+        options: &SocketOptions,
+    ) -> io::Result<Result<Connecting, ConnectError>> {
+        let pool = EndpointPool::global();
+        let endpoint = pool.endpoint(&addr, options)?;
+        match endpoint.connect_with(client_config.clone(), addr, server_name) {
+            Err(ConnectError::EndpointStopping) => {
+                pool.evict(&addr, options);
+                let endpoint = pool.endpoint(&addr, options)?;
+                Ok(endpoint.connect_with(client_config.clone(), addr, server_name))
             }
+            result => Ok(result),
         }
+    }
 
-        Err(last_error.unwrap_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::ConnectionRefused,
-                format!("Unable to connect to {}:{}", host, port),
-            )
-        }))
+    /// Tries `addrs` in order, IPv4 first, until a QUIC handshake succeeds.
+    async fn connect_quic(
+        mut addrs: Vec<SocketAddr>,
+        server_name: &str,
+        options: &SocketOptions,
+    ) -> io::Result<Connection> {
+        let client_config = Self::quic_client_config()?;
+        addrs.sort_by_key(|addr| if addr.is_ipv4() { 0 } else { 1 });
+
+        let mut failure = ConnectFailure::new(server_name);
+        for addr in addrs {
+            let started = Instant::now();
+            let result = match Self::start_connect(&client_config, addr, server_name, options) {
+                Ok(Ok(connecting)) => connecting
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::ConnectionRefused, e)),
+                Ok(Err(e)) => Err(io::Error::new(io::ErrorKind::ConnectionRefused, e)),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(connection) => return Ok(connection),
+                Err(e) => failure.record(addr, ConnectMechanism::Quic, &e, started.elapsed()),
+            }
+        }
+        Err(failure.into_io_error())
     }
 
     pub async fn connect(target: &str) -> Result<Self, ProtocolError> {
@@ -147,6 +218,25 @@ impl H3Connection {
         Self::connect_with_target_and_timeouts(&target, timeouts).await
     }
 
+    /// Connects like [`connect_with_timeouts`](Self::connect_with_timeouts),
+    /// recording each phase of the handshake in `tracker` as it starts.
+    pub async fn connect_traced(
+        target: &str,
+        timeouts: ClientTimeouts,
+        tracker: HandshakeTracker,
+    ) -> Result<Self, ProtocolError> {
+        let target = parse_target(target)?;
+        Self::connect_inner(
+            &target,
+            timeouts,
+            None,
+            &SocketOptions::default(),
+            tracker,
+            None,
+        )
+        .await
+    }
+
     #[allow(dead_code)]
     pub(crate) async fn connect_with_target(target: &Target) -> Result<Self, ProtocolError> {
         Self::connect_with_target_and_timeouts(target, ClientTimeouts::default()).await
@@ -156,12 +246,37 @@ impl H3Connection {
         target: &Target,
         timeouts: ClientTimeouts,
     ) -> Result<Self, ProtocolError> {
-        Self::connect_inner(target, timeouts).await
+        let tracker = HandshakeTracker::new();
+        Self::connect_inner(
+            target,
+            timeouts,
+            None,
+            &SocketOptions::default(),
+            tracker,
+            None,
+        )
+        .await
+    }
+
+    /// Connects only to addresses `guard` allows, from an endpoint bound per
+    /// `socket_options`.
+    pub(crate) async fn connect_guarded(
+        target: &Target,
+        timeouts: ClientTimeouts,
+        guard: Option<&SsrfGuard>,
+        socket_options: &SocketOptions,
+    ) -> Result<Self, ProtocolError> {
+        let tracker = HandshakeTracker::new();
+        Self::connect_inner(target, timeouts, guard, socket_options, tracker, None).await
     }
 
     async fn connect_inner(
         target: &Target,
         timeouts: ClientTimeouts,
+        guard: Option<&SsrfGuard>,
+        socket_options: &SocketOptions,
+        tracker: HandshakeTracker,
+        mirror: Option<Box<dyn WireSink>>,
     ) -> Result<Self, ProtocolError> {
         let host = target
             .host()
@@ -170,11 +285,25 @@ impl H3Connection {
             .port()
             .ok_or_else(|| ProtocolError::InvalidTarget("Target missing port".to_string()))?;
 
-        let connection = H3Connection::create_quic_connection(host, port, host)
+        tracker.enter(HandshakePhase::Dns);
+        let addrs = match guard {
+            Some(guard) => {
+                guard
+                    .resolve_for(host, port, socket_options.fresh_dns)
+                    .await?
+            }
+            None => Self::resolve(host, port, socket_options.fresh_dns)
+                .await
+                .map_err(ProtocolError::from_connect_error)?,
+        };
+        tracker.enter(HandshakePhase::Quic);
+        let connection = H3Connection::connect_quic(addrs, host, socket_options)
             .await
-            .map_err(|e| ProtocolError::ConnectionFailed(e.to_string()))?;
+            .map_err(ProtocolError::from_connect_error)?;
 
         let mut h3_connection = Self::new(connection, timeouts);
+        h3_connection.handshake = tracker;
+        h3_connection.set_wire_mirror(mirror);
         h3_connection.perform_handshake().await?;
         Ok(h3_connection)
     }
@@ -206,6 +335,7 @@ impl H3Connection {
             settings,
             remote_settings,
             streams: HashMap::new(),
+            pushes: PushState::default(),
             next_stream_id: 0, // Client uses stream IDs 0, 4, 8, 12, ... (bidirectional client-initiated)
             control_send_stream: None,
             control_recv_stream: None,
@@ -214,10 +344,21 @@ impl H3Connection {
             qpack_encoder_recv: None,
             qpack_decoder_recv: None,
             timeouts,
+            stream_deadlines: HashMap::new(),
+            queue_limits: None,
+            qpack_captures: HashMap::new(),
+            goaway_sent: None,
+            goaway_received: None,
+            priorities_sent: HashMap::new(),
+            priorities_received: HashMap::new(),
+            events: ConnectionEvents::new(),
+            handshake: HandshakeTracker::new(),
+            wire_mirror: WireMirror::default(),
         }
     }
 
     async fn perform_handshake(&mut self) -> Result<(), ProtocolError> {
+        self.handshake.enter(HandshakePhase::Settings);
         // 1. Open control stream (client-initiated unidirectional)
         let send_stream = self.connection.open_uni().await.map_err(|e| {
             ProtocolError::ConnectionFailed(format!("Failed to open control stream: {}", e))
@@ -267,9 +408,14 @@ impl H3Connection {
             })?;
 
             // Read stream type varint
-            let (stream_type, _) = Self::read_stream_type(&mut recv).await?;
+            let (stream_type, prefix) = Self::read_stream_type(&mut recv).await?;
             match stream_type {
                 0x00 => {
+                    self.wire_mirror.record(
+                        Some(quic_stream_id(recv.id())),
+                        WireDirection::Received,
+                        &prefix,
+                    );
                     self.control_recv_stream = Some(recv);
                     break;
                 }
@@ -292,7 +438,7 @@ impl H3Connection {
                     "First control frame must be SETTINGS".to_string(),
                 ));
             }
-            self.handle_frame(&frame).await?;
+            self.handle_control_frame(&frame).await?;
         } else {
             return Err(ProtocolError::InvalidResponse(
                 "Control stream closed before SETTINGS".to_string(),
@@ -301,6 +447,7 @@ impl H3Connection {
 
         // 6. Connection is now open
         self.state = ConnectionState::Open;
+        self.handshake.enter(HandshakePhase::Ready);
         Ok(())
     }
 
@@ -312,6 +459,11 @@ impl H3Connection {
             send_stream.write_all(&buf).await.map_err(|e| {
                 ProtocolError::ConnectionFailed(format!("Failed to send stream type: {}", e))
             })?;
+            self.wire_mirror.record(
+                Some(quic_stream_id(send_stream.id())),
+                WireDirection::Sent,
+                &buf,
+            );
         } else {
             return Err(ProtocolError::RequestFailed(
                 "No control stream available".to_string(),
@@ -351,6 +503,11 @@ impl H3Connection {
             Ok(Ok(Some(0))) => Ok(None),
             Ok(Ok(Some(n))) => {
                 self.control_recv_buf.extend_from_slice(&local_chunk[..n]);
+                self.wire_mirror.record(
+                    Some(quic_stream_id(recv_stream.id())),
+                    WireDirection::Received,
+                    &local_chunk[..n],
+                );
                 self.read_control_frame_internal(false).await
             }
             Ok(Ok(None)) => {
@@ -396,6 +553,11 @@ impl H3Connection {
                 Some(0) => continue,
                 Some(n) => {
                     self.control_recv_buf.extend_from_slice(&local_chunk[..n]);
+                    self.wire_mirror.record(
+                        Some(quic_stream_id(recv_stream.id())),
+                        WireDirection::Received,
+                        &local_chunk[..n],
+                    );
                 }
                 None => {
                     self.control_recv_stream = None;
@@ -410,10 +572,10 @@ impl H3Connection {
         stream: &mut Option<RecvStream>,
         role: QpackStreamRole,
         wait: bool,
-    ) -> Result<bool, ProtocolError> {
+    ) -> Result<Option<Bytes>, ProtocolError> {
         let recv_stream = match stream.as_mut() {
             Some(stream) => stream,
-            None => return Ok(false),
+            None => return Ok(None),
         };
 
         let chunk_result = if wait {
@@ -426,7 +588,7 @@ impl H3Connection {
             .await
             {
                 Ok(res) => res,
-                Err(_) => return Ok(false),
+                Err(_) => return Ok(None),
             }
         };
 
@@ -434,14 +596,18 @@ impl H3Connection {
             Ok(Some(chunk)) => {
                 let bytes = chunk.bytes;
                 match role {
-                    QpackStreamRole::Encoder => qpack.handle_encoder_stream_bytes(bytes).await?,
-                    QpackStreamRole::Decoder => qpack.handle_decoder_stream_bytes(bytes).await?,
+                    QpackStreamRole::Encoder => {
+                        qpack.handle_encoder_stream_bytes(bytes.clone()).await?
+                    }
+                    QpackStreamRole::Decoder => {
+                        qpack.handle_decoder_stream_bytes(bytes.clone()).await?
+                    }
                 }
-                Ok(true)
+                Ok(Some(bytes))
             }
             Ok(None) => {
                 *stream = None;
-                Ok(false)
+                Ok(None)
             }
             Err(e) => Err(ProtocolError::H3StreamError(
                 H3StreamErrorKind::ProtocolViolation(format!("Failed to read QPACK stream: {}", e)),
@@ -449,7 +615,7 @@ impl H3Connection {
         }
     }
 
-    async fn pump_qpack_encoder(&mut self, wait: bool) -> Result<bool, ProtocolError> {
+    async fn pump_qpack_encoder(&mut self, wait: bool) -> Result<Option<Bytes>, ProtocolError> {
         Self::poll_qpack_stream(
             &self.qpack,
             &mut self.qpack_encoder_recv,
@@ -459,7 +625,7 @@ impl H3Connection {
         .await
     }
 
-    async fn pump_qpack_decoder(&mut self) -> Result<bool, ProtocolError> {
+    async fn pump_qpack_decoder(&mut self) -> Result<Option<Bytes>, ProtocolError> {
         Self::poll_qpack_stream(
             &self.qpack,
             &mut self.qpack_decoder_recv,
@@ -469,7 +635,10 @@ impl H3Connection {
         .await
     }
 
-    async fn read_stream_type(recv_stream: &mut RecvStream) -> Result<(u64, usize), ProtocolError> {
+    /// The stream type and the bytes it was read from.
+    async fn read_stream_type(
+        recv_stream: &mut RecvStream,
+    ) -> Result<(u64, Vec<u8>), ProtocolError> {
         // Read first byte to determine varint length
         let mut first = [0u8; 1];
         let n = recv_stream
@@ -516,10 +685,10 @@ impl H3Connection {
             }
             buf.extend_from_slice(&tail);
         }
-        let (val, consumed) = Self::decode_varint_from_slice(&buf).ok_or_else(|| {
+        let (val, _) = Self::decode_varint_from_slice(&buf).ok_or_else(|| {
             ProtocolError::InvalidResponse("Invalid stream type varint".to_string())
         })?;
-        Ok((val, consumed))
+        Ok((val, buf))
     }
 
     async fn send_control_frame(&mut self, frame: &FrameH3) -> Result<(), ProtocolError> {
@@ -529,6 +698,11 @@ impl H3Connection {
             send_stream.write_all(&serialized).await.map_err(|e| {
                 ProtocolError::ConnectionFailed(format!("Failed to send control frame: {}", e))
             })?;
+            self.wire_mirror.record(
+                Some(quic_stream_id(send_stream.id())),
+                WireDirection::Sent,
+                &serialized,
+            );
         } else {
             return Err(ProtocolError::RequestFailed(
                 "No control stream available".to_string(),
@@ -546,6 +720,8 @@ impl H3Connection {
         })?;
 
         self.streams.insert(stream_id, StreamInfo::new(recv_stream));
+        self.events
+            .emit(ConnectionEvent::StreamOpened { stream_id });
 
         Ok((stream_id, send_stream))
     }
@@ -563,11 +739,35 @@ impl H3Connection {
         let mut headers_received = false;
         let protocol = HTTP_VERSION_3_0.to_string();
         let mut captured_frames = Vec::new();
+        let mut frame_times = Vec::new();
+        let mut header_blocks = Vec::new();
+        let header_deadline = timeouts.header_timeout().map(|d| Instant::now() + d);
+        let mut body_deadline: Option<Instant> = None;
 
         loop {
-            timeout_result(timeouts.read, self.poll_control()).await?;
-            let frame_opt =
-                timeout_result(timeouts.read, self.read_request_frame(stream_id)).await?;
+            let phase_timeout = if headers_received {
+                timeouts.next_body_read_timeout(body_deadline)
+            } else {
+                time_remaining(header_deadline)
+            };
+            timeout_result(phase_timeout, self.poll_control()).await?;
+            let frame_opt = match self.stream_deadlines.get(&stream_id).copied() {
+                Some(deadline) => {
+                    // Stream reads are cancel-safe: bytes only leave quinn's
+                    // buffer once they are appended to the stream's own.
+                    let read = timeout_result(phase_timeout, self.read_request_frame(stream_id));
+                    match tokio::time::timeout_at(deadline.into(), read).await {
+                        Ok(frame_opt) => frame_opt?,
+                        Err(_) => {
+                            self.abandon_stream(stream_id);
+                            return Err(ProtocolError::H3StreamError(
+                                H3StreamErrorKind::DeadlineExceeded,
+                            ));
+                        }
+                    }
+                }
+                None => timeout_result(phase_timeout, self.read_request_frame(stream_id)).await?,
+            };
 
             let frame = match frame_opt {
                 Some(frame) => frame,
@@ -578,6 +778,7 @@ impl H3Connection {
             };
 
             captured_frames.push(ResponseFrame::Http3(frame.clone()));
+            frame_times.push(Instant::now());
             if let Some(handler) = frame_handler {
                 handler(&frame);
             }
@@ -589,6 +790,15 @@ impl H3Connection {
                         self.decode_headers(stream_id, &frame.payload),
                     )
                     .await?;
+                    for (at, instructions) in self.take_qpack_captures(stream_id) {
+                        captured_frames.push(ResponseFrame::Http3(instructions));
+                        frame_times.push(at);
+                    }
+                    header_blocks.push(HeaderBlockInfo::qpack(
+                        stream_id,
+                        frame.payload.clone(),
+                        &decoded_headers,
+                    ));
 
                     let mut status_code = decoded_headers.iter().find_map(|header| {
                         (header.name == ":status")
@@ -614,6 +824,7 @@ impl H3Connection {
                                 .cloned(),
                         );
                         headers_received = true;
+                        body_deadline = timeouts.body_total_timeout().map(|d| Instant::now() + d);
                     } else {
                         if let Some(code) = status_code {
                             if code < 200 {
@@ -642,8 +853,9 @@ impl H3Connection {
             }
         }
 
-        let status = status
-            .ok_or_else(|| ProtocolError::InvalidResponse("No final response received".to_string()))?;
+        let status = status.ok_or_else(|| {
+            ProtocolError::InvalidResponse("No final response received".to_string())
+        })?;
 
         let trailers = match trailers {
             Some(t) if !t.is_empty() => Some(t),
@@ -651,6 +863,7 @@ impl H3Connection {
         };
 
         self.remove_closed_stream(stream_id);
+        self.stream_deadlines.remove(&stream_id);
 
         let cookies = Response::collect_cookies(&headers);
 
@@ -665,7 +878,22 @@ impl H3Connection {
             } else {
                 Some(captured_frames)
             },
+            header_blocks: if header_blocks.is_empty() {
+                None
+            } else {
+                Some(header_blocks)
+            },
             cookies,
+            decoded_body: None,
+            frame_times: if frame_times.is_empty() {
+                None
+            } else {
+                Some(frame_times)
+            },
+            digest: None,
+            transfer_codings: Vec::new(),
+            truncated: false,
+            truncation: None,
         })
     }
 
@@ -673,6 +901,7 @@ impl H3Connection {
         &mut self,
         stream_id: u32,
     ) -> Result<Option<FrameH3>, ProtocolError> {
+        let limits = self.queue_limits;
         let stream_info = self.streams.get_mut(&stream_id).ok_or_else(|| {
             ProtocolError::RequestFailed(format!("Unknown request stream {}", stream_id))
         })?;
@@ -702,6 +931,20 @@ impl H3Connection {
                 Some(0) => continue,
                 Some(n) => {
                     buf.extend_from_slice(&local_chunk[..n]);
+                    self.wire_mirror.record(
+                        Some(quic_stream_id(recv_stream.id())),
+                        WireDirection::Received,
+                        &local_chunk[..n],
+                    );
+                    if let Some(limits) = limits.filter(|limits| limits.exceeded(0, buf.len())) {
+                        let bytes = buf.len();
+                        if limits.on_overflow != QueueOverflow::Error {
+                            self.abandon_stream(stream_id);
+                        }
+                        return Err(ProtocolError::H3StreamError(
+                            H3StreamErrorKind::QueueOverflow { bytes },
+                        ));
+                    }
                 }
                 None => {
                     if buf.is_empty() {
@@ -718,7 +961,7 @@ impl H3Connection {
 
     pub async fn poll_control(&mut self) -> Result<(), ProtocolError> {
         while let Some(frame) = self.try_read_control_frame().await? {
-            self.handle_frame(&frame).await?;
+            self.handle_control_frame(&frame).await?;
         }
         Ok(())
     }
@@ -737,18 +980,23 @@ impl H3Connection {
         payload: &[u8],
     ) -> Result<Vec<Header>, ProtocolError> {
         // Process any pending QPACK feedback
-        let _ = self.pump_qpack_decoder().await?;
-        let _ = self.pump_qpack_encoder(false).await?;
+        let instructions = self.pump_qpack_decoder().await?;
+        self.capture_qpack(stream_id, FrameTypeH3::QpackDecoder, instructions);
+        let instructions = self.pump_qpack_encoder(false).await?;
+        self.capture_qpack(stream_id, FrameTypeH3::QpackEncoder, instructions);
 
         match self.qpack.decode_headers(stream_id as u64, payload).await? {
             QpackDecodeStatus::Complete(headers) => return Ok(headers),
             QpackDecodeStatus::Blocked => loop {
-                let progressed = self.pump_qpack_encoder(true).await?;
-                if !progressed {
-                    return Err(ProtocolError::H3QpackError(
-                        "Decoder remained blocked".to_string(),
-                    ));
-                }
+                let instructions = match self.pump_qpack_encoder(true).await? {
+                    Some(instructions) => instructions,
+                    None => {
+                        return Err(ProtocolError::H3QpackError(
+                            "Decoder remained blocked".to_string(),
+                        ))
+                    }
+                };
+                self.capture_qpack(stream_id, FrameTypeH3::QpackEncoder, Some(instructions));
 
                 if let Some(status) = self.qpack.poll_unblocked(stream_id as u64).await? {
                     match status {
@@ -760,6 +1008,21 @@ impl H3Connection {
         }
     }
 
+    /// Keeps QPACK stream instructions read while decoding the headers of
+    /// `stream_id`, to be captured with its response.
+    fn capture_qpack(&mut self, stream_id: u32, kind: FrameTypeH3, instructions: Option<Bytes>) {
+        if let Some(payload) = instructions {
+            self.qpack_captures
+                .entry(stream_id)
+                .or_default()
+                .push((Instant::now(), FrameH3::new(kind, stream_id, payload)));
+        }
+    }
+
+    fn take_qpack_captures(&mut self, stream_id: u32) -> Vec<(Instant, FrameH3)> {
+        self.qpack_captures.remove(&stream_id).unwrap_or_default()
+    }
+
     fn try_parse_frame(
         buf: &BytesMut,
         stream_id: u32,
@@ -795,6 +1058,7 @@ impl H3Connection {
             0x5 => FrameTypeH3::PushPromise,
             0x7 => FrameTypeH3::GoAway,
             0x0d => FrameTypeH3::MaxPushId,
+            PRIORITY_UPDATE_FRAME_TYPE => FrameTypeH3::PriorityUpdate,
             other => FrameTypeH3::Unknown(other),
         };
 
@@ -806,12 +1070,39 @@ impl H3Connection {
         Ok(Some((frame, header_len + len as usize)))
     }
 
+    /// Handles a frame read from the peer's control stream.
+    pub async fn handle_control_frame(&mut self, frame: &FrameH3) -> Result<(), ProtocolError> {
+        match &frame.frame_type {
+            FrameType::H3(FrameTypeH3::CancelPush) => self.handle_cancel_push_frame(frame),
+            // RFC 9114 §7.2.5: PUSH_PROMISE only comes on a request stream.
+            FrameType::H3(FrameTypeH3::PushPromise) => Err(ProtocolError::H3ConnectionError(
+                "PUSH_PROMISE on the control stream".to_string(),
+            )),
+            _ => self.handle_frame(frame).await,
+        }
+    }
+
+    /// Handles a frame read from request stream `frame.stream_id`.
     pub async fn handle_frame(&mut self, frame: &FrameH3) -> Result<(), ProtocolError> {
         match &frame.frame_type {
             FrameType::H3(FrameTypeH3::Settings) => self.handle_settings_frame(frame).await,
             FrameType::H3(FrameTypeH3::Headers) => self.handle_headers_frame(frame).await,
             FrameType::H3(FrameTypeH3::Data) => self.handle_data_frame(frame).await,
             FrameType::H3(FrameTypeH3::GoAway) => self.handle_goaway_frame(frame).await,
+            FrameType::H3(FrameTypeH3::PushPromise) => self.handle_push_promise_frame(frame),
+            // RFC 9114 §7.2.3: CANCEL_PUSH only comes on the control stream.
+            FrameType::H3(FrameTypeH3::CancelPush) => Err(ProtocolError::H3ConnectionError(
+                format!("CANCEL_PUSH on request stream {}", frame.stream_id),
+            )),
+            FrameType::H3(FrameTypeH3::PriorityUpdate) => self.handle_priority_update_frame(frame),
+            FrameType::H3(FrameTypeH3::Unknown(frame_type)) => {
+                self.events.emit(ConnectionEvent::UnknownFrame {
+                    stream_id: frame.stream_id,
+                    frame_type: *frame_type,
+                    payload: frame.payload.clone(),
+                });
+                Ok(())
+            }
             _ => {
                 // Unknown or unhandled frame types are ignored per HTTP/3 spec
                 Ok(())
@@ -820,6 +1111,7 @@ impl H3Connection {
     }
 
     async fn handle_settings_frame(&mut self, frame: &FrameH3) -> Result<(), ProtocolError> {
+        let mut received = Vec::new();
         let mut offset = 0;
         while offset + 2 <= frame.payload.len() {
             let (setting_id, consumed) = Self::decode_varint_from_slice(&frame.payload[offset..])
@@ -835,7 +1127,10 @@ impl H3Connection {
             offset += consumed;
 
             self.apply_setting(setting_id, setting_value)?;
+            received.push((setting_id, setting_value));
         }
+        self.events
+            .emit(ConnectionEvent::SettingsReceived { settings: received });
 
         let max_table = *self
             .remote_settings
@@ -929,21 +1224,125 @@ impl H3Connection {
         Ok(())
     }
 
-    async fn handle_goaway_frame(&mut self, _frame: &FrameH3) -> Result<(), ProtocolError> {
-        self.state = ConnectionState::Closed;
+    async fn handle_goaway_frame(&mut self, frame: &FrameH3) -> Result<(), ProtocolError> {
+        if let Some((last_stream_id, _)) = Self::decode_varint_from_slice(&frame.payload) {
+            self.goaway_received = Some(last_stream_id);
+            self.events.emit(ConnectionEvent::GoAwayReceived {
+                last_stream_id,
+                error_code: None,
+                debug_data: Bytes::new(),
+            });
+        }
+        self.mark_closed();
+        Ok(())
+    }
+
+    fn handle_push_promise_frame(&mut self, frame: &FrameH3) -> Result<(), ProtocolError> {
+        let (push_id, _) = Self::decode_varint_from_slice(&frame.payload).ok_or_else(|| {
+            ProtocolError::InvalidResponse("Invalid push ID in PUSH_PROMISE".to_string())
+        })?;
+        if !self.pushes.allows(push_id) {
+            return Err(ProtocolError::H3ConnectionError(format!(
+                "PUSH_PROMISE with push ID {} above MAX_PUSH_ID {:?}",
+                push_id, self.pushes.max_push_id
+            )));
+        }
+        self.pushes.promised.insert(push_id, frame.stream_id);
+        Ok(())
+    }
+
+    fn handle_cancel_push_frame(&mut self, frame: &FrameH3) -> Result<(), ProtocolError> {
+        let (push_id, _) = Self::decode_varint_from_slice(&frame.payload).ok_or_else(|| {
+            ProtocolError::InvalidResponse("Invalid push ID in CANCEL_PUSH".to_string())
+        })?;
+        self.pushes.cancelled.insert(push_id);
+        Ok(())
+    }
+
+    /// Sends MAX_PUSH_ID, allowing the server to push with IDs up to
+    /// `push_id`. The limit can only be raised.
+    pub async fn set_max_push_id(&mut self, push_id: u64) -> Result<(), ProtocolError> {
+        if let Some(current) = self.pushes.max_push_id {
+            if push_id < current {
+                return Err(ProtocolError::H3MessageError(format!(
+                    "MAX_PUSH_ID cannot drop from {} to {}",
+                    current, push_id
+                )));
+            }
+        }
+        FrameH3::max_push_id(push_id).send(self).await?;
+        self.pushes.max_push_id = Some(push_id);
+        Ok(())
+    }
+
+    /// Sends CANCEL_PUSH for `push_id`, promised or not yet. IDs above the
+    /// last MAX_PUSH_ID are refused.
+    pub async fn cancel_push(&mut self, push_id: u64) -> Result<(), ProtocolError> {
+        if !self.pushes.allows(push_id) {
+            return Err(ProtocolError::H3MessageError(format!(
+                "Push ID {} is above MAX_PUSH_ID {:?}",
+                push_id, self.pushes.max_push_id
+            )));
+        }
+        FrameH3::cancel_push(push_id).send(self).await?;
+        self.pushes.cancelled.insert(push_id);
         Ok(())
     }
 
     pub async fn send_goaway(&mut self, stream_id: u64) -> Result<(), ProtocolError> {
         FrameH3::goaway(stream_id).send(self).await?;
-        self.state = ConnectionState::Closed;
+        self.goaway_sent = Some(stream_id);
+        self.mark_closed();
         Ok(())
     }
 
+    fn mark_closed(&mut self) {
+        if !matches!(self.state, ConnectionState::Closed) {
+            self.state = ConnectionState::Closed;
+            self.events.emit(ConnectionEvent::Closed);
+        }
+    }
+
+    /// Phases of the handshake that set up the connection, with when each
+    /// started.
+    pub fn handshake(&self) -> &HandshakeTracker {
+        &self.handshake
+    }
+
+    /// Receives every [`ConnectionEvent`] from now on. HTTP/3 has no PING
+    /// frame, so `PingRtt` never arrives here; QUIC's own estimate is
+    /// `connection.rtt()`.
+    pub fn subscribe_events(&mut self) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+        self.events.subscribe()
+    }
+
     pub fn is_open(&self) -> bool {
         matches!(self.state, ConnectionState::Open)
     }
 
+    /// True if another request stream may be opened: no GOAWAY was sent or
+    /// received, and QUIC has not closed the connection, e.g. on idle
+    /// timeout. A GOAWAY is only seen once `poll_control` reads it.
+    pub fn accepts_new_streams(&self) -> bool {
+        self.is_open()
+            && self.goaway_sent.is_none()
+            && self.goaway_received.is_none()
+            && self.connection.close_reason().is_none()
+    }
+
+    /// Identifier of the GOAWAY the peer sent. Requests on streams at or
+    /// above it were not processed and may be retried on another connection.
+    pub fn goaway_id(&self) -> Option<u64> {
+        self.goaway_received
+    }
+
+    /// UDP bytes sent and received on the QUIC connection so far, QUIC
+    /// framing and encryption included.
+    pub fn wire_bytes(&self) -> (u64, u64) {
+        let stats = self.connection.stats();
+        (stats.udp_tx.bytes, stats.udp_rx.bytes)
+    }
+
     pub async fn close(&mut self) -> Result<(), ProtocolError> {
         self.send_goaway(self.next_stream_id as u64).await
     }
@@ -956,6 +1355,8 @@ impl H3Connection {
                 }
                 StreamState::HalfClosedRemote => {
                     stream_info.state = StreamState::Closed;
+                    self.events
+                        .emit(ConnectionEvent::StreamClosed { stream_id });
                 }
                 StreamState::HalfClosedLocal | StreamState::Closed => {
                     // Already closed or closing
@@ -981,6 +1382,8 @@ impl H3Connection {
                 }
                 StreamState::HalfClosedLocal => {
                     stream_info.state = StreamState::Closed;
+                    self.events
+                        .emit(ConnectionEvent::StreamClosed { stream_id });
                 }
                 StreamState::HalfClosedRemote | StreamState::Closed => {
                     // Already closed from remote side
@@ -1006,12 +1409,63 @@ impl H3Connection {
         if let Some(stream_info) = self.streams.get(&stream_id) {
             if matches!(stream_info.state, StreamState::Closed) {
                 self.streams.remove(&stream_id);
+                self.qpack_captures.remove(&stream_id);
+                self.priorities_sent.remove(&stream_id);
                 return true;
             }
         }
         false
     }
 
+    /// Caps the bytes buffered per stream while a frame arrives, see
+    /// [`StreamQueueLimits`]. `None`, the default, leaves them unbounded.
+    pub fn set_stream_queue_limits(&mut self, limits: Option<StreamQueueLimits>) {
+        self.queue_limits = limits;
+    }
+
+    pub fn stream_queue_limits(&self) -> Option<StreamQueueLimits> {
+        self.queue_limits
+    }
+
+    /// Gives `stream_id` until `timeout` from now to finish; a read that is
+    /// still waiting for it then abandons the stream and fails with
+    /// [`H3StreamErrorKind::DeadlineExceeded`]. `None` removes the deadline.
+    pub fn set_stream_timeout(&mut self, stream_id: u32, timeout: Option<Duration>) {
+        match timeout {
+            Some(timeout) => {
+                self.stream_deadlines
+                    .insert(stream_id, Instant::now() + timeout);
+            }
+            None => {
+                self.stream_deadlines.remove(&stream_id);
+            }
+        }
+    }
+
+    pub fn stream_deadline(&self, stream_id: u32) -> Option<Instant> {
+        self.stream_deadlines.get(&stream_id).copied()
+    }
+
+    /// Stops receiving on a request stream with H3_REQUEST_CANCELLED and
+    /// drops its buffered data and deadline. QUIC flow control is per
+    /// stream, so the peer's remaining data is discarded by the transport
+    /// and other requests on the connection are unaffected. The send half
+    /// belongs to the caller, who should `reset` it if it is still open.
+    pub fn abandon_stream(&mut self, stream_id: u32) {
+        self.stream_deadlines.remove(&stream_id);
+        self.qpack_captures.remove(&stream_id);
+        self.priorities_sent.remove(&stream_id);
+        if let Some(mut stream_info) = self.streams.remove(&stream_id) {
+            let _ = stream_info
+                .recv_stream
+                .stop(VarInt::from_u32(H3_REQUEST_CANCELLED));
+            if stream_info.state != StreamState::Closed {
+                self.events
+                    .emit(ConnectionEvent::StreamClosed { stream_id });
+            }
+        }
+    }
+
     // Helper function to encode varint to Vec<u8>
     fn encode_varint_to_vec(buf: &mut Vec<u8>, value: u64) {
         if value < 0x40 {
@@ -1083,7 +1537,8 @@ impl FrameSink<FrameH3> for H3Connection {
                 FrameTypeH3::Settings
                 | FrameTypeH3::CancelPush
                 | FrameTypeH3::GoAway
-                | FrameTypeH3::MaxPushId => {
+                | FrameTypeH3::MaxPushId
+                | FrameTypeH3::PriorityUpdate => {
                     if frame.stream_id != 0 {
                         return Err(ProtocolError::H3MessageError(
                             "Control frames must target stream 0".to_string(),
@@ -0,0 +1,146 @@
+use super::state::{ConnectionState, StreamState};
+use super::H3Connection;
+use crate::h3::framing::{
+    SETTINGS_MAX_FIELD_SECTION_SIZE, SETTINGS_QPACK_BLOCKED_STREAMS,
+    SETTINGS_QPACK_MAX_TABLE_CAPACITY,
+};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// One entry of the stream table in an [`H3DebugState`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct H3StreamDebugState {
+    pub state: StreamState,
+    /// Bytes of the frame being received on the stream.
+    pub buffered_bytes: usize,
+}
+
+/// Snapshot returned by [`H3Connection::debug_state`], for REPLs and
+/// scripting frontends that show or diff the connection between steps.
+///
+/// The QPACK codec does not expose its dynamic tables, so only their
+/// capacities are reported: the decoder's is the one we advertised, the
+/// encoder's the one the peer did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct H3DebugState {
+    pub state: ConnectionState,
+    /// Settings we sent, keyed by identifier.
+    pub local_settings: BTreeMap<u64, u64>,
+    /// Settings the peer sent, keyed by identifier.
+    pub remote_settings: BTreeMap<u64, u64>,
+    pub next_stream_id: u32,
+    pub streams: BTreeMap<u32, H3StreamDebugState>,
+    /// Identifier carried by the GOAWAY we sent.
+    pub goaway_sent: Option<u64>,
+    /// Identifier carried by the GOAWAY the peer sent.
+    pub goaway_received: Option<u64>,
+    pub qpack_encoder_capacity: u64,
+    pub qpack_decoder_capacity: u64,
+    pub max_push_id: Option<u64>,
+    pub promised_pushes: BTreeMap<u64, u32>,
+    pub cancelled_pushes: BTreeSet<u64>,
+}
+
+impl H3DebugState {
+    /// The snapshot as JSON. Settings are keyed by their RFC 9114 names,
+    /// unknown ones by hex identifier, and streams by their ID.
+    pub fn to_json(&self) -> Value {
+        let streams: Map<String, Value> = self
+            .streams
+            .iter()
+            .map(|(stream_id, stream)| {
+                let entry = json!({
+                    "state": format!("{:?}", stream.state),
+                    "buffered_bytes": stream.buffered_bytes,
+                });
+                (stream_id.to_string(), entry)
+            })
+            .collect();
+        let promised: Map<String, Value> = self
+            .promised_pushes
+            .iter()
+            .map(|(push_id, stream_id)| (push_id.to_string(), json!(stream_id)))
+            .collect();
+        json!({
+            "protocol": "h3",
+            "state": format!("{:?}", self.state),
+            "settings": {
+                "local": settings_json(&self.local_settings),
+                "remote": settings_json(&self.remote_settings),
+            },
+            "next_stream_id": self.next_stream_id,
+            "streams": streams,
+            "goaway": {
+                "sent": self.goaway_sent,
+                "received": self.goaway_received,
+            },
+            "qpack": {
+                "encoder_capacity": self.qpack_encoder_capacity,
+                "decoder_capacity": self.qpack_decoder_capacity,
+            },
+            "pushes": {
+                "max_push_id": self.max_push_id,
+                "promised": promised,
+                "cancelled": self.cancelled_pushes,
+            },
+        })
+    }
+}
+
+impl H3Connection {
+    /// Everything a frontend needs to show where the connection stands:
+    /// settings in both directions, the stream table, GOAWAY, QPACK table
+    /// capacities and server pushes.
+    pub fn debug_state(&self) -> H3DebugState {
+        let streams = self
+            .streams
+            .iter()
+            .map(|(stream_id, stream)| {
+                let entry = H3StreamDebugState {
+                    state: stream.state.clone(),
+                    buffered_bytes: stream.recv_buf.len(),
+                };
+                (*stream_id, entry)
+            })
+            .collect();
+        let capacity = |settings: &HashMap<u64, u64>| {
+            settings
+                .get(&SETTINGS_QPACK_MAX_TABLE_CAPACITY)
+                .copied()
+                .unwrap_or(0)
+        };
+
+        H3DebugState {
+            state: self.state.clone(),
+            local_settings: self.settings.iter().map(|(k, v)| (*k, *v)).collect(),
+            remote_settings: self.remote_settings.iter().map(|(k, v)| (*k, *v)).collect(),
+            next_stream_id: self.next_stream_id,
+            streams,
+            goaway_sent: self.goaway_sent,
+            goaway_received: self.goaway_received,
+            qpack_encoder_capacity: capacity(&self.remote_settings),
+            qpack_decoder_capacity: capacity(&self.settings),
+            max_push_id: self.pushes.max_push_id,
+            promised_pushes: self.pushes.promised.clone(),
+            cancelled_pushes: self.pushes.cancelled.clone(),
+        }
+    }
+}
+
+fn setting_name(id: u64) -> String {
+    let name = match id {
+        SETTINGS_QPACK_MAX_TABLE_CAPACITY => "QPACK_MAX_TABLE_CAPACITY",
+        SETTINGS_MAX_FIELD_SECTION_SIZE => "MAX_FIELD_SECTION_SIZE",
+        SETTINGS_QPACK_BLOCKED_STREAMS => "QPACK_BLOCKED_STREAMS",
+        other => return format!("0x{:x}", other),
+    };
+    name.to_string()
+}
+
+fn settings_json(settings: &BTreeMap<u64, u64>) -> Value {
+    let map: Map<String, Value> = settings
+        .iter()
+        .map(|(id, value)| (setting_name(*id), json!(value)))
+        .collect();
+    Value::Object(map)
+}
@@ -0,0 +1,57 @@
+use super::H3Connection;
+use crate::handshake::HandshakeTracker;
+use crate::types::{ClientTimeouts, ProtocolError, SocketOptions};
+use crate::utils::parse_target;
+use crate::wire::{WireChunk, WireDirection, WireSink};
+use quinn::{StreamId, VarInt};
+use tokio::sync::mpsc;
+
+impl H3Connection {
+    /// Connects like [`connect_with_timeouts`](Self::connect_with_timeouts),
+    /// mirroring the connection to `mirror` from the control stream's type
+    /// byte on.
+    pub async fn connect_mirrored(
+        target: &str,
+        timeouts: ClientTimeouts,
+        mirror: Box<dyn WireSink>,
+    ) -> Result<Self, ProtocolError> {
+        let target = parse_target(target)?;
+        Self::connect_inner(
+            &target,
+            timeouts,
+            None,
+            &SocketOptions::default(),
+            HandshakeTracker::new(),
+            Some(mirror),
+        )
+        .await
+    }
+
+    /// Mirrors every byte from now on to `mirror`, replacing an earlier one;
+    /// `None` stops mirroring. Each write or read goes to
+    /// [`WireSink::record_stream`] with its QUIC stream ID. Request and
+    /// control streams are mirrored; QPACK encoder and decoder streams are
+    /// not.
+    pub fn set_wire_mirror(&mut self, mirror: Option<Box<dyn WireSink>>) {
+        self.wire_mirror.set(mirror);
+    }
+
+    /// Mirrors every byte from now on into a channel.
+    pub fn mirror_wire(&mut self) -> mpsc::UnboundedReceiver<WireChunk> {
+        self.wire_mirror.channel()
+    }
+
+    pub fn is_mirroring_wire(&self) -> bool {
+        self.wire_mirror.is_active()
+    }
+
+    /// Mirrors bytes written to a request stream.
+    pub(crate) fn mirror(&mut self, stream: StreamId, direction: WireDirection, data: &[u8]) {
+        self.wire_mirror
+            .record(Some(quic_stream_id(stream)), direction, data);
+    }
+}
+
+pub(super) fn quic_stream_id(stream: StreamId) -> u64 {
+    VarInt::from(stream).into_inner()
+}
@@ -0,0 +1,54 @@
+use super::H3Connection;
+use crate::types::{FrameH3, Priority, ProtocolError};
+
+impl H3Connection {
+    /// Sends PRIORITY_UPDATE on the control stream, changing the priority of
+    /// the request on `stream_id` (RFC 9218 Section 7.2). It may be sent
+    /// before the stream is opened, and then applies once it is.
+    pub async fn send_priority_update(
+        &mut self,
+        stream_id: u32,
+        priority: Priority,
+    ) -> Result<(), ProtocolError> {
+        if stream_id % 4 != 0 {
+            return Err(ProtocolError::H3MessageError(format!(
+                "Stream {} is not a client-initiated request stream",
+                stream_id
+            )));
+        }
+        FrameH3::priority_update(stream_id as u64, priority)
+            .send(self)
+            .await?;
+        self.priorities_sent.insert(stream_id, priority);
+        Ok(())
+    }
+
+    /// Priority last sent with PRIORITY_UPDATE for `stream_id`, until the
+    /// stream is removed.
+    pub fn priority(&self, stream_id: u32) -> Option<Priority> {
+        self.priorities_sent.get(&stream_id).copied()
+    }
+
+    /// Priority the peer last sent with PRIORITY_UPDATE for `element_id`.
+    /// Only clients are meant to send it, so this records misbehaving or
+    /// experimental servers.
+    pub fn peer_priority(&self, element_id: u64) -> Option<Priority> {
+        self.priorities_received.get(&element_id).copied()
+    }
+
+    pub(super) fn handle_priority_update_frame(
+        &mut self,
+        frame: &FrameH3,
+    ) -> Result<(), ProtocolError> {
+        let (element_id, consumed) =
+            Self::decode_varint_from_slice(&frame.payload).ok_or_else(|| {
+                ProtocolError::H3ConnectionError(
+                    "Invalid prioritized element ID in PRIORITY_UPDATE".to_string(),
+                )
+            })?;
+        let value = String::from_utf8_lossy(&frame.payload[consumed..]);
+        self.priorities_received
+            .insert(element_id, Priority::parse(&value));
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use bytes::BytesMut;
 use quinn::RecvStream;
+use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ConnectionState {
@@ -32,3 +33,21 @@ impl StreamInfo {
         }
     }
 }
+
+/// Server push IDs from the client's side of the connection.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PushState {
+    /// Last value sent in MAX_PUSH_ID; the server may not push until one is sent.
+    pub max_push_id: Option<u64>,
+    /// Push IDs from PUSH_PROMISE frames, with the request stream that carried each.
+    pub promised: BTreeMap<u64, u32>,
+    /// Push IDs cancelled by either side with CANCEL_PUSH.
+    pub cancelled: BTreeSet<u64>,
+}
+
+impl PushState {
+    /// True if MAX_PUSH_ID allows the server to use `push_id`.
+    pub fn allows(&self, push_id: u64) -> bool {
+        self.max_push_id.map_or(false, |max| push_id <= max)
+    }
+}
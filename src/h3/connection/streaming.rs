@@ -0,0 +1,205 @@
+use super::H3Connection;
+use crate::types::{
+    BodyConnection, BodySource, ClientTimeouts, FrameH3, FrameType, FrameTypeH3, Header,
+    ProtocolError, ResponseBody, StreamingResponse,
+};
+use crate::utils::{time_remaining, timeout_result, HTTP_VERSION_3_0};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::time::Instant;
+
+/// Pulls DATA frames for one request stream as the body is consumed. QUIC
+/// flow control holds the peer back once the consumer stops reading.
+struct H3BodyReader<'a> {
+    connection: BodyConnection<'a, H3Connection>,
+    stream_id: u32,
+    timeouts: ClientTimeouts,
+    body_deadline: Option<Instant>,
+    trailers: Option<Vec<Header>>,
+    finished: bool,
+}
+
+#[async_trait(?Send)]
+impl BodySource for H3BodyReader<'_> {
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, ProtocolError>> {
+        match self.chunk().await {
+            Ok(chunk) => chunk.map(Ok),
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn trailers(&mut self) -> Option<Vec<Header>> {
+        self.trailers.take()
+    }
+}
+
+impl H3BodyReader<'_> {
+    async fn chunk(&mut self) -> Result<Option<Bytes>, ProtocolError> {
+        while !self.finished {
+            let read_timeout = self.timeouts.next_body_read_timeout(self.body_deadline);
+            timeout_result(read_timeout, self.connection.poll_control()).await?;
+            let frame = timeout_result(
+                read_timeout,
+                self.connection.read_request_frame(self.stream_id),
+            )
+            .await?;
+            let frame = match frame {
+                Some(frame) => frame,
+                None => {
+                    self.finished = true;
+                    let _ = self.connection.stream_finished_receiving(self.stream_id);
+                    self.connection.remove_closed_stream(self.stream_id);
+                    break;
+                }
+            };
+
+            match &frame.frame_type {
+                FrameType::H3(FrameTypeH3::Headers) => {
+                    let headers = self.connection.decode_frame_headers(&frame).await?;
+                    self.trailers
+                        .get_or_insert_with(Vec::new)
+                        .extend(headers.into_iter().filter(|h| !h.name.starts_with(':')));
+                }
+                FrameType::H3(FrameTypeH3::Data) => {
+                    timeout_result(self.timeouts.read, self.connection.handle_frame(&frame))
+                        .await?;
+                    if !frame.payload.is_empty() {
+                        return Ok(Some(frame.payload));
+                    }
+                }
+                _ => {
+                    timeout_result(self.timeouts.read, self.connection.handle_frame(&frame))
+                        .await?;
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl H3Connection {
+    /// Reads up to the final response headers and returns without waiting
+    /// for the body, whose DATA frames are read off the stream as the
+    /// returned body is consumed. Frames are not captured.
+    pub async fn read_response_streaming(
+        &mut self,
+        stream_id: u32,
+    ) -> Result<StreamingResponse<'_>, ProtocolError> {
+        let (status, headers) = self.read_streaming_head(stream_id).await?;
+        Ok(streaming_response(
+            status,
+            headers,
+            BodyConnection::Borrowed(self),
+            stream_id,
+        ))
+    }
+
+    /// Like [`read_response_streaming`](Self::read_response_streaming), with
+    /// the body taking the connection along; it is closed once the body is
+    /// dropped.
+    pub async fn into_response_streaming(
+        mut self,
+        stream_id: u32,
+    ) -> Result<StreamingResponse<'static>, ProtocolError> {
+        let (status, headers) = self.read_streaming_head(stream_id).await?;
+        Ok(streaming_response(
+            status,
+            headers,
+            BodyConnection::Owned(Box::new(self)),
+            stream_id,
+        ))
+    }
+
+    async fn read_streaming_head(
+        &mut self,
+        stream_id: u32,
+    ) -> Result<(u16, Vec<Header>), ProtocolError> {
+        let timeouts = self.timeouts.clone();
+        let header_deadline = timeouts.header_timeout().map(|d| Instant::now() + d);
+        loop {
+            let remaining = time_remaining(header_deadline);
+            timeout_result(remaining, self.poll_control()).await?;
+            let frame = match timeout_result(remaining, self.read_request_frame(stream_id)).await? {
+                Some(frame) => frame,
+                None => {
+                    return Err(ProtocolError::InvalidResponse(
+                        "No final response received".to_string(),
+                    ))
+                }
+            };
+
+            match &frame.frame_type {
+                FrameType::H3(FrameTypeH3::Headers) => {
+                    let headers = self.decode_frame_headers(&frame).await?;
+                    let status = headers
+                        .iter()
+                        .find(|h| h.name == ":status")
+                        .and_then(|h| h.value.as_ref()?.parse::<u16>().ok())
+                        .ok_or_else(|| {
+                            ProtocolError::InvalidResponse(
+                                "Missing :status header in response".to_string(),
+                            )
+                        })?;
+                    if status < 200 {
+                        continue;
+                    }
+                    let headers = headers
+                        .into_iter()
+                        .filter(|h| !h.name.starts_with(':'))
+                        .collect::<Vec<_>>();
+                    return Ok((status, headers));
+                }
+                FrameType::H3(FrameTypeH3::Data) => {
+                    return Err(ProtocolError::InvalidResponse(
+                        "DATA received before response headers".to_string(),
+                    ));
+                }
+                _ => timeout_result(timeouts.read, self.handle_frame(&frame)).await?,
+            }
+        }
+    }
+
+    /// Decodes and handles a HEADERS frame, dropping the QPACK instructions
+    /// that would otherwise be kept for a capture nobody collects.
+    async fn decode_frame_headers(
+        &mut self,
+        frame: &FrameH3,
+    ) -> Result<Vec<Header>, ProtocolError> {
+        let read_timeout = self.timeouts.read;
+        let headers = timeout_result(
+            read_timeout,
+            self.decode_headers(frame.stream_id, &frame.payload),
+        )
+        .await?;
+        self.take_qpack_captures(frame.stream_id);
+        timeout_result(read_timeout, self.handle_frame(frame)).await?;
+        Ok(headers)
+    }
+}
+
+/// A streaming response whose body is read off `stream_id` of `connection`.
+fn streaming_response(
+    status: u16,
+    headers: Vec<Header>,
+    connection: BodyConnection<'_, H3Connection>,
+    stream_id: u32,
+) -> StreamingResponse<'_> {
+    let timeouts = connection.timeouts.clone();
+    let body_deadline = timeouts.body_total_timeout().map(|d| Instant::now() + d);
+    StreamingResponse {
+        status,
+        protocol: HTTP_VERSION_3_0.to_string(),
+        headers,
+        body: ResponseBody::new(H3BodyReader {
+            connection,
+            stream_id,
+            timeouts,
+            body_deadline,
+            trailers: None,
+            finished: false,
+        }),
+    }
+}
@@ -0,0 +1,123 @@
+use super::H3Connection;
+use crate::connection::StreamWriter;
+use crate::types::{FrameH3, FrameTypeH3, H3StreamErrorKind, Header, ProtocolError, Response};
+use crate::utils::timeout_result;
+use crate::wire::WireDirection;
+use async_trait::async_trait;
+use bytes::Bytes;
+use quinn::SendStream;
+
+/// [`StreamWriter`] for an HTTP/3 request stream. Each call writes one
+/// HEADERS or DATA frame to the stream; QUIC handles flow control.
+pub struct H3StreamWriter<'a> {
+    connection: &'a mut H3Connection,
+    stream_id: u32,
+    send_stream: SendStream,
+    finished: bool,
+}
+
+impl H3Connection {
+    /// Opens a request stream and returns a writer for it.
+    pub async fn stream_writer(&mut self) -> Result<H3StreamWriter<'_>, ProtocolError> {
+        let (stream_id, send_stream) = self.create_request_stream().await?;
+        Ok(H3StreamWriter {
+            connection: self,
+            stream_id,
+            send_stream,
+            finished: false,
+        })
+    }
+}
+
+impl<'a> H3StreamWriter<'a> {
+    /// Reads the response once the request is written. The stream is
+    /// finished first if the caller has not done so.
+    pub async fn read_response(mut self) -> Result<Response, ProtocolError> {
+        self.finish().await?;
+        let timeouts = self.connection.timeouts.clone();
+        self.connection
+            .read_response_with_timeouts(self.stream_id, &timeouts, None)
+            .await
+    }
+
+    async fn write_frame(&mut self, frame: FrameH3) -> Result<(), ProtocolError> {
+        let serialized = frame.serialize()?;
+        let send_stream = &mut self.send_stream;
+        timeout_result(self.connection.timeouts.write, async {
+            send_stream.write_all(&serialized).await.map_err(|e| {
+                ProtocolError::H3StreamError(H3StreamErrorKind::ProtocolViolation(format!(
+                    "Failed to write to request stream: {}",
+                    e
+                )))
+            })
+        })
+        .await?;
+        self.connection
+            .mirror(self.send_stream.id(), WireDirection::Sent, &serialized);
+        Ok(())
+    }
+
+    async fn write_header_block(&mut self, headers: &[Header]) -> Result<(), ProtocolError> {
+        let block = self
+            .connection
+            .encode_headers(self.stream_id, headers)
+            .await?;
+        self.write_frame(FrameH3::new(FrameTypeH3::Headers, self.stream_id, block))
+            .await
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a> StreamWriter for H3StreamWriter<'a> {
+    fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    async fn headers(&mut self, headers: &[Header], end_stream: bool) -> Result<(), ProtocolError> {
+        self.write_header_block(headers).await?;
+        if end_stream {
+            self.finish().await?;
+        }
+        Ok(())
+    }
+
+    async fn data(&mut self, chunk: Bytes, end_stream: bool) -> Result<(), ProtocolError> {
+        if !chunk.is_empty() {
+            self.write_frame(FrameH3::data(self.stream_id, chunk))
+                .await?;
+        }
+        if end_stream {
+            self.finish().await?;
+        }
+        Ok(())
+    }
+
+    /// quinn transmits written data without an explicit flush.
+    async fn flush(&mut self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+
+    async fn trailers(&mut self, trailers: &[Header]) -> Result<(), ProtocolError> {
+        self.write_header_block(trailers).await?;
+        self.finish().await
+    }
+
+    async fn finish(&mut self) -> Result<(), ProtocolError> {
+        if self.finished {
+            return Ok(());
+        }
+        self.send_stream.finish().map_err(|e| {
+            ProtocolError::H3StreamError(H3StreamErrorKind::ProtocolViolation(format!(
+                "Failed to finish stream: {}",
+                e
+            )))
+        })?;
+        self.connection.close_stream(self.stream_id)?;
+        self.finished = true;
+        Ok(())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
@@ -10,3 +10,6 @@ pub const QPACK_DECODER_STREAM_TYPE: u64 = 0x03;
 
 /// Client-initiated bidirectional stream IDs increment by four (RFC 9000 §2.1).
 pub const CLIENT_BIDI_STREAM_INCREMENT: u32 = 4;
+
+/// Error code for a request the client no longer wants (RFC 9114 §8.1).
+pub const H3_REQUEST_CANCELLED: u32 = 0x010c;
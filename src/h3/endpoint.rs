@@ -0,0 +1,122 @@
+//! QUIC endpoints shared between HTTP/3 connections.
+//!
+//! Every `quinn::Endpoint` owns a UDP socket and a driver task. Connections
+//! made from the same local bind address go through one endpoint from
+//! [`EndpointPool::global`], so a scan opening thousands of HTTP/3
+//! connections keeps a single socket per address family:
+//!
+//! ```ignore
+//! let a = H3Connection::connect("https://example.com/").await?;
+//! let b = H3Connection::connect("https://example.org/").await?;
+//! assert_eq!(EndpointPool::global().len(), 1);
+//!
+//! // Close the sockets once the connections are done with.
+//! EndpointPool::global().clear();
+//! ```
+//!
+//! An endpoint whose driver went away with its runtime is replaced on the
+//! next connect.
+
+use crate::types::SocketOptions;
+use quinn::{Endpoint, EndpointConfig};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+
+static GLOBAL: OnceLock<EndpointPool> = OnceLock::new();
+
+/// Endpoints keyed by bind address and reuse flags.
+#[derive(Debug, Default)]
+pub struct EndpointPool {
+    endpoints: Mutex<HashMap<EndpointKey, Endpoint>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct EndpointKey {
+    bind_addr: SocketAddr,
+    reuse_address: bool,
+    reuse_port: bool,
+}
+
+impl EndpointKey {
+    fn new(remote: &SocketAddr, options: &SocketOptions) -> Self {
+        Self {
+            bind_addr: options.bind_addr(remote),
+            reuse_address: options.reuse_address,
+            reuse_port: options.reuse_port,
+        }
+    }
+}
+
+impl EndpointPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pool H3 connections use.
+    pub fn global() -> &'static EndpointPool {
+        GLOBAL.get_or_init(EndpointPool::default)
+    }
+
+    /// The endpoint for reaching `remote` from a socket bound per `options`,
+    /// binding a new one if none exists yet.
+    pub fn endpoint(&self, remote: &SocketAddr, options: &SocketOptions) -> io::Result<Endpoint> {
+        let key = EndpointKey::new(remote, options);
+        let mut endpoints = self.lock();
+        if let Some(endpoint) = endpoints.get(&key) {
+            return Ok(endpoint.clone());
+        }
+        let endpoint = bind_endpoint(&key)?;
+        endpoints.insert(key, endpoint.clone());
+        Ok(endpoint)
+    }
+
+    /// Drops the endpoint for `remote` and `options`, e.g. after its driver
+    /// stopped. Connections already on it keep it open until they close.
+    pub fn evict(&self, remote: &SocketAddr, options: &SocketOptions) {
+        self.lock().remove(&EndpointKey::new(remote, options));
+    }
+
+    /// Drops every endpoint; each closes once its last connection does.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<EndpointKey, Endpoint>> {
+        // The map stays consistent even if a holder panicked.
+        self.endpoints.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Binds a client endpoint. Without reuse flags the OS binds the socket as
+/// usual.
+fn bind_endpoint(key: &EndpointKey) -> io::Result<Endpoint> {
+    if !key.reuse_address && !key.reuse_port {
+        return Endpoint::client(key.bind_addr);
+    }
+
+    let socket = Socket::new(
+        Domain::for_address(key.bind_addr),
+        Type::DGRAM,
+        Some(Protocol::UDP),
+    )?;
+    socket.set_reuse_address(key.reuse_address)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(key.reuse_port)?;
+    socket.bind(&key.bind_addr.into())?;
+    socket.set_nonblocking(true)?;
+
+    let runtime = quinn::default_runtime()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "No async runtime found"))?;
+    Endpoint::new(EndpointConfig::default(), None, socket.into(), runtime)
+}
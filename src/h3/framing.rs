@@ -1,4 +1,5 @@
-use crate::types::{FrameH3, FrameSink, FrameType, FrameTypeH3, ProtocolError};
+use crate::h3::consts::{QPACK_DECODER_STREAM_TYPE, QPACK_ENCODER_STREAM_TYPE};
+use crate::types::{FrameH3, FrameSink, FrameType, FrameTypeH3, Priority, ProtocolError};
 use bytes::{BufMut, Bytes, BytesMut};
 
 // HTTP/3 Frame Types (RFC 9114 Section 7.2)
@@ -9,6 +10,8 @@ pub const SETTINGS_FRAME_TYPE: u64 = 0x4;
 pub const PUSH_PROMISE_FRAME_TYPE: u64 = 0x5;
 pub const GOAWAY_FRAME_TYPE: u64 = 0x7;
 pub const MAX_PUSH_ID_FRAME_TYPE: u64 = 0x0d;
+/// RFC 9218 Section 7.2, request streams.
+pub const PRIORITY_UPDATE_FRAME_TYPE: u64 = 0xf0700;
 
 // HTTP/3 Settings Parameters (RFC 9114 Section 7.2.4.1)
 pub const SETTINGS_QPACK_MAX_TABLE_CAPACITY: u64 = 0x1;
@@ -59,6 +62,14 @@ impl FrameH3 {
         Self::new(FrameTypeH3::CancelPush, 0, payload.freeze())
     }
 
+    /// Reprioritizes the request on `stream_id`; sent on the control stream.
+    pub fn priority_update(stream_id: u64, priority: Priority) -> Self {
+        let mut payload = BytesMut::new();
+        Self::encode_varint(&mut payload, stream_id);
+        payload.put_slice(priority.to_string().as_bytes());
+        Self::new(FrameTypeH3::PriorityUpdate, 0, payload.freeze())
+    }
+
     pub fn send<'a, S>(
         self,
         sink: &'a mut S,
@@ -79,7 +90,12 @@ impl FrameH3 {
                 FrameTypeH3::PushPromise => PUSH_PROMISE_FRAME_TYPE,
                 FrameTypeH3::GoAway => GOAWAY_FRAME_TYPE,
                 FrameTypeH3::MaxPushId => MAX_PUSH_ID_FRAME_TYPE,
+                FrameTypeH3::PriorityUpdate => PRIORITY_UPDATE_FRAME_TYPE,
                 FrameTypeH3::Unknown(value) => *value,
+                // QPACK instructions travel on their own streams, which
+                // are told apart by stream type rather than frame type.
+                FrameTypeH3::QpackEncoder => QPACK_ENCODER_STREAM_TYPE,
+                FrameTypeH3::QpackDecoder => QPACK_DECODER_STREAM_TYPE,
             },
             FrameType::H2(_) => 0, // Not applicable for H3 framing
         }
@@ -137,6 +153,7 @@ impl FrameH3 {
             PUSH_PROMISE_FRAME_TYPE => FrameTypeH3::PushPromise,
             GOAWAY_FRAME_TYPE => FrameTypeH3::GoAway,
             MAX_PUSH_ID_FRAME_TYPE => FrameTypeH3::MaxPushId,
+            PRIORITY_UPDATE_FRAME_TYPE => FrameTypeH3::PriorityUpdate,
             other => FrameTypeH3::Unknown(other),
         };
 
@@ -1,7 +1,9 @@
 pub mod connection;
 pub mod consts;
+pub mod endpoint;
 pub mod framing;
 pub mod protocol;
 pub mod qpack;
 
+pub use endpoint::EndpointPool;
 pub use protocol::H3;
@@ -1,16 +1,21 @@
 use crate::h3::connection::H3Connection;
+use crate::pool::{pool_key, ConnectionPool, PoolControl};
 use crate::types::{
-    ClientTimeouts, FrameTypeH3, H3StreamErrorKind, Header, Protocol, ProtocolError, Request,
-    Response,
+    ClientConfig, ClientTimeouts, EndStreamMode, FrameTypeH3, H3StreamErrorKind, Header,
+    HttpProtocol, Protocol, ProtocolError, Request, Response, StreamingResponse, UserAgent,
 };
 use crate::utils::timeout_result;
+use crate::wire::WireDirection;
 use crate::PreparedRequest;
 use async_trait::async_trait;
 use bytes::Bytes;
+use quinn::SendStream;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct H3 {
-    timeouts: ClientTimeouts,
+    config: ClientConfig,
+    pool: Arc<ConnectionPool<H3Connection>>,
 }
 
 impl H3 {
@@ -19,17 +24,69 @@ impl H3 {
     }
 
     pub fn timeouts(timeouts: ClientTimeouts) -> Self {
-        Self { timeouts }
+        Self::with_config(ClientConfig::new().timeouts(timeouts))
+    }
+
+    pub fn with_config(config: ClientConfig) -> Self {
+        Self {
+            config,
+            pool: Arc::new(ConnectionPool::default()),
+        }
+    }
+
+    /// `User-Agent` for requests that do not pick their own.
+    pub fn with_user_agent(mut self, user_agent: UserAgent) -> Self {
+        self.config.user_agent = user_agent;
+        self
     }
 
     pub fn get_timeouts(&self) -> &ClientTimeouts {
-        &self.timeouts
+        &self.config.timeouts
+    }
+
+    pub fn get_config(&self) -> &ClientConfig {
+        &self.config
     }
 
     pub fn session(&self) -> crate::session::H3Session {
         crate::session::H3Session::new(self.clone())
     }
 
+    /// Connections parked by [`preconnect`](Self::preconnect) or after a
+    /// response, shared with clones of this client. A connection that sent
+    /// or received GOAWAY, or that QUIC closed, is not reused.
+    pub fn pool(&self) -> &ConnectionPool<H3Connection> {
+        &self.pool
+    }
+
+    /// Connects to `origin` and completes the QUIC and HTTP/3 handshakes, then
+    /// parks the connection for the next request there.
+    pub async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        let mut request = Request::new(origin, "GET")?;
+        self.config.apply(&mut request);
+        if let Some(scope) = &self.config.host_scope {
+            scope.check(&request.target).await?;
+        }
+        let key = pool_key(&request).ok_or_else(|| {
+            ProtocolError::RequestFailed(
+                "Guarded or fresh-DNS connections are not pooled".to_string(),
+            )
+        })?;
+        let timeouts = request.timeouts(&self.config.timeouts);
+        let connect = timeout_result(
+            timeouts.connect,
+            H3Connection::connect_guarded(
+                &request.target,
+                timeouts.clone(),
+                None,
+                &self.config.socket_options,
+            ),
+        );
+        let connection = self.pool.timed_connect(Some(&key), connect).await?;
+        self.pool.park_and_close_evicted(&key, connection).await;
+        Ok(())
+    }
+
     pub fn build_request(
         target: &str,
         method: impl Into<String>,
@@ -51,13 +108,12 @@ impl H3 {
     async fn send_request_inner(
         &self,
         connection: &mut H3Connection,
+        stream_id: u32,
+        mut send_stream: SendStream,
         request: &Request,
         timeouts: &ClientTimeouts,
-    ) -> Result<u32, ProtocolError> {
-        let (stream_id, mut send_stream) =
-            timeout_result(timeouts.connect, connection.create_request_stream()).await?;
-
-        let prepared = request.prepare_request()?;
+    ) -> Result<SendStream, ProtocolError> {
+        let prepared = request.prepare_once()?;
         let header_block_entries = prepared.header_block();
 
         let header_block = timeout_result(
@@ -82,6 +138,7 @@ impl H3 {
                 })
         })
         .await?;
+        connection.mirror(send_stream.id(), WireDirection::Sent, &serialized_headers);
 
         if let Some(body) = prepared.body.as_ref() {
             if !body.is_empty() {
@@ -98,9 +155,17 @@ impl H3 {
                     })
                 })
                 .await?;
+                connection.mirror(send_stream.id(), WireDirection::Sent, &serialized_data);
             }
         }
 
+        match request.end_stream_mode {
+            EndStreamMode::Immediate => {}
+            EndStreamMode::After(hold) => tokio::time::sleep(hold).await,
+            // Dropping the send stream would finish it, so it is handed back.
+            EndStreamMode::Never => return Ok(send_stream),
+        }
+
         if !prepared.trailers.is_empty() {
             let trailer_block = timeout_result(
                 timeouts.write,
@@ -124,6 +189,7 @@ impl H3 {
                     })
             })
             .await?;
+            connection.mirror(send_stream.id(), WireDirection::Sent, &serialized_trailers);
         }
 
         timeout_result(timeouts.write, async {
@@ -136,26 +202,142 @@ impl H3 {
         })
         .await?;
 
-        Ok(stream_id)
+        Ok(send_stream)
     }
 
     pub async fn send_request(&self, request: Request) -> Result<Response, ProtocolError> {
         <Self as Protocol>::response(self, request).await
     }
 
-    async fn perform_request(&self, request: &Request) -> Result<Response, ProtocolError> {
-        let timeouts = request.timeouts(&self.timeouts);
-        let connect_timeouts = timeouts.clone();
+    /// Sends `request` on a connection of its own and returns once the
+    /// response head is in. The body comes off the connection as it is
+    /// consumed, and the connection is closed with it. Redirects are not
+    /// followed.
+    pub async fn send_request_streaming(
+        &self,
+        mut request: Request,
+    ) -> Result<StreamingResponse<'static>, ProtocolError> {
+        self.apply_defaults(&mut request);
+        if let Some(scope) = &self.config.host_scope {
+            scope.check(&request.target).await?;
+        }
+        request.check_headers(&HttpProtocol::Http3)?;
+        let timeouts = request.timeouts(&self.config.timeouts);
         let mut connection = timeout_result(
             timeouts.connect,
-            H3Connection::connect_with_target_and_timeouts(&request.target, connect_timeouts),
+            H3Connection::connect_guarded(
+                &request.target,
+                timeouts.clone(),
+                request.ssrf_guard.as_ref(),
+                &self.config.socket_options_for(&request),
+            ),
         )
         .await?;
-        let stream_id = self
-            .send_request_inner(&mut connection, request, &timeouts)
+        let (stream_id, send_stream) =
+            timeout_result(timeouts.connect, connection.create_request_stream()).await?;
+        // Kept open until the response head is in, for `EndStreamMode::Never`.
+        let _send_stream = self
+            .send_request_inner(&mut connection, stream_id, send_stream, &request, &timeouts)
             .await?;
-        self.read_response(&mut connection, stream_id, &timeouts)
+        connection.into_response_streaming(stream_id).await
+    }
+
+    async fn perform_request(&self, request: &Request) -> Result<Response, ProtocolError> {
+        request.check_headers(&HttpProtocol::Http3)?;
+        let timeouts = request.timeouts(&self.config.timeouts);
+        let key = pool_key(request);
+        let warm = match key.as_deref().and_then(|key| self.pool.take(key)) {
+            // Reads a GOAWAY the server sent while the connection sat idle.
+            Some(mut connection) => connection
+                .poll_control()
+                .await
+                .is_ok()
+                .then(|| connection)
+                .filter(H3Connection::accepts_new_streams),
+            None => None,
+        };
+        if let Some(connection) = warm {
+            let start = connection.wire_bytes();
+            match self
+                .exchange(connection, request, key.as_deref(), &timeouts, start)
+                .await
+            {
+                // The server went away without acting on the request; it
+                // goes out again on a new connection.
+                Err((_, true)) => {}
+                result => return result.map_err(|(err, _)| err),
+            }
+        }
+        let connect = timeout_result(
+            timeouts.connect,
+            H3Connection::connect_guarded(
+                &request.target,
+                timeouts.clone(),
+                request.ssrf_guard.as_ref(),
+                &self.config.socket_options_for(request),
+            ),
+        );
+        let connection = self.pool.timed_connect(key.as_deref(), connect).await?;
+        // A new connection's handshake is charged to the request that
+        // opened it.
+        self.exchange(connection, request, key.as_deref(), &timeouts, (0, 0))
             .await
+            .map_err(|(err, _)| err)
+    }
+
+    /// Sends `request` on a new stream of `connection` and reads the
+    /// response, then parks the connection under `key` if it can open more
+    /// streams. Errors come with whether the server is known not to have
+    /// processed the request (RFC 9114 Section 5.2), so that it is safe to
+    /// send again. The connection's wire bytes past `start` are charged to
+    /// the request's traffic meter, whether or not the exchange succeeds.
+    async fn exchange(
+        &self,
+        mut connection: H3Connection,
+        request: &Request,
+        key: Option<&str>,
+        timeouts: &ClientTimeouts,
+        start: (u64, u64),
+    ) -> Result<Response, (ProtocolError, bool)> {
+        let (stream_id, send_stream) =
+            match timeout_result(timeouts.connect, connection.create_request_stream()).await {
+                Ok(opened) => opened,
+                Err(err) => {
+                    charge(request, &connection, start);
+                    return Err((err, true));
+                }
+            };
+        let result = match self
+            .send_request_inner(&mut connection, stream_id, send_stream, request, timeouts)
+            .await
+        {
+            // Kept alive until the response is read, for `EndStreamMode::Never`.
+            Ok(_send_stream) => {
+                self.read_response(&mut connection, stream_id, timeouts)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        if result.is_err() {
+            // A GOAWAY that came in while the response was awaited tells
+            // whether the request was processed.
+            let _ = connection.poll_control().await;
+        }
+        charge(request, &connection, start);
+        let mut response = result.map_err(|err| {
+            let unprocessed = connection
+                .goaway_id()
+                .map_or(false, |id| stream_id as u64 >= id);
+            (err, unprocessed)
+        })?;
+
+        if let Some(key) = key.filter(|_| connection.accepts_new_streams()) {
+            self.pool.park_and_close_evicted(key, connection).await;
+        }
+        response
+            .capture_decoded_body(&request.decompression_limits)
+            .map_err(|err| (err, false))?;
+        Ok(response)
     }
 
     async fn read_response(
@@ -175,4 +357,22 @@ impl Protocol for H3 {
     async fn execute(&self, request: &Request) -> Result<Response, ProtocolError> {
         self.perform_request(request).await
     }
+
+    async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        H3::preconnect(self, origin).await
+    }
+
+    fn pools(&self) -> Vec<&dyn PoolControl> {
+        vec![self.pool.as_ref() as &dyn PoolControl]
+    }
+
+    fn config(&self) -> Option<&ClientConfig> {
+        Some(&self.config)
+    }
+}
+
+/// Charges the connection's wire bytes past `start` to `request`.
+fn charge(request: &Request, connection: &H3Connection, start: (u64, u64)) {
+    let (sent, received) = connection.wire_bytes();
+    request.charge_traffic(sent - start.0, received - start.1);
 }
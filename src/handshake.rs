@@ -0,0 +1,172 @@
+//! Phases a connection goes through before it carries requests.
+//!
+//! A [`HandshakeTracker`] handed to a `connect_traced` constructor is updated
+//! as the connection is set up, so a connect that hangs can be blamed on the
+//! phase it is stuck in rather than on a generic timeout:
+//!
+//! ```ignore
+//! let tracker = HandshakeTracker::new();
+//! let connect = H2Connection::connect_traced(
+//!     "https://example.com/",
+//!     &ClientTimeouts::default(),
+//!     None,
+//!     None,
+//!     &SocketOptions::default(),
+//!     tracker.clone(),
+//! );
+//! if tokio::time::timeout(Duration::from_secs(5), connect).await.is_err() {
+//!     println!("stuck in {:?} after {:?}", tracker.phase(), tracker.time_in_phase());
+//! }
+//! ```
+//!
+//! Every connection also keeps the tracker of its own handshake, available
+//! from its `handshake()` method.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Handshake phases in the order a connection can go through them. Which
+/// ones occur depends on the protocol: HTTP/1.1 stops after TLS, HTTP/3 runs
+/// QUIC instead of TCP and TLS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HandshakePhase {
+    Dns,
+    Tcp,
+    Tls,
+    /// The QUIC handshake, TLS included.
+    Quic,
+    /// Sending the HTTP/2 preface, or the HTTP/1.1 Upgrade to h2c.
+    Preface,
+    /// Exchanging the initial SETTINGS.
+    Settings,
+    Ready,
+}
+
+impl fmt::Display for HandshakePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            HandshakePhase::Dns => "dns",
+            HandshakePhase::Tcp => "tcp",
+            HandshakePhase::Tls => "tls",
+            HandshakePhase::Quic => "quic",
+            HandshakePhase::Preface => "preface",
+            HandshakePhase::Settings => "settings",
+            HandshakePhase::Ready => "ready",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Entry into a phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandshakeTransition {
+    pub phase: HandshakePhase,
+    pub at: Instant,
+}
+
+/// Shared record of one handshake; clones observe the same handshake.
+#[derive(Debug, Clone)]
+pub struct HandshakeTracker {
+    transitions: Arc<Mutex<Vec<HandshakeTransition>>>,
+    phase: Arc<watch::Sender<Option<HandshakePhase>>>,
+}
+
+impl Default for HandshakeTracker {
+    fn default() -> Self {
+        Self {
+            transitions: Arc::new(Mutex::new(Vec::new())),
+            phase: Arc::new(watch::channel(None).0),
+        }
+    }
+}
+
+impl HandshakeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records entering `phase` now. Entering the current phase again is a
+    /// no-op.
+    pub fn enter(&self, phase: HandshakePhase) {
+        let mut transitions = self.lock();
+        if transitions.last().map(|last| last.phase) == Some(phase) {
+            return;
+        }
+        transitions.push(HandshakeTransition {
+            phase,
+            at: Instant::now(),
+        });
+        self.phase.send_replace(Some(phase));
+    }
+
+    /// The phase the handshake is in; `None` before it started.
+    pub fn phase(&self) -> Option<HandshakePhase> {
+        self.lock().last().map(|last| last.phase)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.phase() == Some(HandshakePhase::Ready)
+    }
+
+    /// Receives every phase change from now on.
+    pub fn subscribe(&self) -> watch::Receiver<Option<HandshakePhase>> {
+        self.phase.subscribe()
+    }
+
+    pub fn transitions(&self) -> Vec<HandshakeTransition> {
+        self.lock().clone()
+    }
+
+    /// Time spent in the current phase so far.
+    pub fn time_in_phase(&self) -> Option<Duration> {
+        self.lock().last().map(|last| last.at.elapsed())
+    }
+
+    /// How long each phase took, the current one counted up to now. `Ready`
+    /// is left out.
+    pub fn durations(&self) -> Vec<(HandshakePhase, Duration)> {
+        let transitions = self.lock();
+        let now = Instant::now();
+        transitions
+            .iter()
+            .enumerate()
+            .filter(|(_, transition)| transition.phase != HandshakePhase::Ready)
+            .map(|(index, transition)| {
+                let end = transitions.get(index + 1).map_or(now, |next| next.at);
+                (
+                    transition.phase,
+                    end.saturating_duration_since(transition.at),
+                )
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<HandshakeTransition>> {
+        // The list stays consistent even if a holder panicked.
+        self.transitions
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// e.g. `dns 2.1ms, tcp 31ms, tls 5s (current)`.
+impl fmt::Display for HandshakeTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let current = self.phase();
+        for (index, (phase, duration)) in self.durations().into_iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {:?}", phase, duration)?;
+            if Some(phase) == current {
+                write!(f, " (current)")?;
+            }
+        }
+        if current == Some(HandshakePhase::Ready) {
+            write!(f, ", ready")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+//! Request hedging: the same request over two clients, first answer wins.
+//!
+//! The primary client gets the request at once; if it has not answered
+//! after the hedge delay, the secondary gets it too. The first successful
+//! response is returned and the other request is dropped mid-flight, which
+//! closes its connection:
+//!
+//! ```ignore
+//! // Two protocols: HTTP/3 joins in when HTTP/2 is slow.
+//! let hedge = Hedge::new(H2::new(), H3::new(), Duration::from_millis(150));
+//! let res = hedge.send_request(Request::new("https://example.com/", "GET")?).await?;
+//!
+//! // Two connections of the same client, and which one won.
+//! let h1 = H1::new();
+//! let raced = Hedge::new(h1.clone(), h1, Duration::from_millis(100))
+//!     .race(&Request::new("https://example.com/", "GET")?)
+//!     .await?;
+//! println!("{:?} answered after {:?}", raced.winner, raced.elapsed);
+//! ```
+//!
+//! Only idempotent methods are hedged; anything else goes to the primary
+//! alone.
+
+use crate::pool::PoolControl;
+use crate::types::{ClientConfig, Protocol, ProtocolError, Request, Response};
+use async_trait::async_trait;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Methods that may be sent twice without changing the outcome (RFC 9110
+/// section 9.2.2).
+pub fn is_idempotent(method: &str) -> bool {
+    ["GET", "HEAD", "OPTIONS", "TRACE", "PUT", "DELETE"]
+        .iter()
+        .any(|idempotent| method.eq_ignore_ascii_case(idempotent))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HedgeWinner {
+    Primary,
+    Secondary,
+}
+
+#[derive(Debug)]
+pub struct HedgedResponse {
+    pub response: Response,
+    pub winner: HedgeWinner,
+    /// Whether the secondary request was sent at all.
+    pub hedged: bool,
+    /// From sending the primary request to the winning response.
+    pub elapsed: Duration,
+}
+
+#[derive(Clone)]
+pub struct Hedge<A, B> {
+    primary: A,
+    secondary: B,
+    delay: Duration,
+}
+
+impl<A, B> Hedge<A, B>
+where
+    A: Protocol,
+    B: Protocol,
+{
+    /// Sends to `secondary` once `primary` has been waiting for `delay`, or
+    /// as soon as `primary` fails.
+    pub fn new(primary: A, secondary: B, delay: Duration) -> Self {
+        Self {
+            primary,
+            secondary,
+            delay,
+        }
+    }
+
+    pub fn primary(&self) -> &A {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &B {
+        &self.secondary
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Races one request, without following redirects. Fails with the
+    /// primary's error when both requests fail.
+    pub async fn race(&self, request: &Request) -> Result<HedgedResponse, ProtocolError> {
+        let started = Instant::now();
+        let done = |response, winner, hedged| HedgedResponse {
+            response,
+            winner,
+            hedged,
+            elapsed: started.elapsed(),
+        };
+
+        let mut primary = self.primary.execute(request);
+        if !is_idempotent(&request.method) {
+            return Ok(done(primary.await?, HedgeWinner::Primary, false));
+        }
+
+        let mut primary_error = tokio::select! {
+            result = &mut primary => match result {
+                Ok(response) => return Ok(done(response, HedgeWinner::Primary, false)),
+                Err(err) => Some(err),
+            },
+            _ = sleep(self.delay) => None,
+        };
+
+        let mut secondary = self.secondary.execute(request);
+        let mut secondary_error = None;
+        loop {
+            tokio::select! {
+                result = &mut primary, if primary_error.is_none() => match result {
+                    Ok(response) => return Ok(done(response, HedgeWinner::Primary, true)),
+                    Err(err) => primary_error = Some(err),
+                },
+                result = &mut secondary, if secondary_error.is_none() => match result {
+                    Ok(response) => return Ok(done(response, HedgeWinner::Secondary, true)),
+                    Err(err) => secondary_error = Some(err),
+                },
+            }
+            if secondary_error.is_some() {
+                if let Some(err) = primary_error.take() {
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<A, B> Protocol for Hedge<A, B>
+where
+    A: Protocol,
+    B: Protocol,
+{
+    async fn execute(&self, request: &Request) -> Result<Response, ProtocolError> {
+        self.race(request).await.map(|raced| raced.response)
+    }
+
+    /// Both clients see the primary's defaults.
+    fn config(&self) -> Option<&ClientConfig> {
+        self.primary.config()
+    }
+
+    fn pools(&self) -> Vec<&dyn PoolControl> {
+        let mut pools = self.primary.pools();
+        pools.extend(self.secondary.pools());
+        pools
+    }
+}
@@ -1,20 +1,59 @@
+pub mod auto;
 pub mod connection;
 pub mod detector;
+pub mod diff;
+pub mod discovery;
+pub mod dns;
+pub mod doh;
+pub mod fault;
 pub mod h1;
 pub mod h2;
 pub mod h3;
+pub mod handshake;
+pub mod hedge;
+pub mod mutate;
+pub mod oauth;
+pub mod pacing;
+pub mod pcapng;
+pub mod pool;
 pub mod proxy;
+pub mod quota;
+pub mod scheme;
 pub mod session;
+pub mod shutdown;
+pub mod signing;
 pub mod stream;
 pub mod types;
 pub mod utils;
+pub mod violation;
+pub mod wire;
 
+pub use auto::*;
 pub use connection::*;
 pub use detector::*;
+pub use diff::*;
+pub use discovery::*;
+pub use dns::*;
+pub use doh::*;
+pub use fault::*;
 pub use h1::protocol::H1;
 pub use h2::protocol::H2;
+pub use h3::endpoint::EndpointPool;
 pub use h3::protocol::H3;
+pub use handshake::*;
+pub use hedge::*;
+pub use mutate::*;
+pub use oauth::*;
+pub use pacing::*;
+pub use pcapng::*;
+pub use pool::*;
+pub use quota::*;
+pub use scheme::*;
 pub use session::*;
+pub use shutdown::*;
+pub use signing::*;
 pub use stream::*;
 pub use types::*;
 pub use utils::*;
+pub use violation::*;
+pub use wire::*;
@@ -0,0 +1,335 @@
+//! Request mutation for fuzzing and access-control bypass testing.
+//!
+//! A [`Mutator`] holds a base [`Request`] and a list of single-axis
+//! [`Mutation`]s; iterating it yields one [`MutatedRequest`] per mutation,
+//! each a copy of the base with exactly one change applied:
+//!
+//! ```ignore
+//! let base = Request::new("https://example.com/admin", "GET")?;
+//! let mutations = Mutator::new(base)
+//!     .methods(["POST", "PUT", "FOO"])
+//!     .header_values("X-Forwarded-For", ["127.0.0.1", "localhost"])
+//!     .path_suffixes(["/", ";", "..;/", ".json"])
+//!     .path_encodings(PathEncoding::ALL)
+//!     .protocols([HttpProtocol::Http1, HttpProtocol::Http2]);
+//! for mutated in mutations {
+//!     let label = mutated.mutation.to_string();
+//!     println!("{} -> {:?}", label, mutated.send().await.map(|r| r.status));
+//! }
+//! ```
+
+use crate::h2::connection::H2Mode;
+use crate::types::{Header, HttpProtocol, ProtocolError, Request, Response};
+use crate::{H1, H2, H3};
+
+/// Marker inside a base header value that header payloads replace, so a
+/// payload can be injected into part of a value (`Bearer §`). Without it
+/// the whole value is replaced.
+pub const INSERTION_MARKER: &str = "§";
+
+/// Ways of rewriting the request path without changing what it names to a
+/// lenient server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathEncoding {
+    /// Percent-encodes every letter and digit: `/admin` → `/%61%64%6D%69%6E`.
+    /// Escapes already in the path are kept as they are.
+    PercentEncode,
+    /// Percent-encodes twice: `/admin` → `/%2561%2564...`; existing escapes
+    /// are kept as they are.
+    DoublePercentEncode,
+    /// Swaps the case of every letter: `/Admin` → `/aDMIN`.
+    CaseSwap,
+    /// Doubles the leading slash: `/admin` → `//admin`.
+    DoubleSlash,
+    /// Adds a trailing slash: `/admin` → `/admin/`.
+    TrailingSlash,
+}
+
+impl PathEncoding {
+    pub const ALL: [PathEncoding; 5] = [
+        PathEncoding::PercentEncode,
+        PathEncoding::DoublePercentEncode,
+        PathEncoding::CaseSwap,
+        PathEncoding::DoubleSlash,
+        PathEncoding::TrailingSlash,
+    ];
+
+    pub fn apply(&self, path: &str) -> String {
+        match self {
+            PathEncoding::PercentEncode => percent_encode_path(path, "%"),
+            PathEncoding::DoublePercentEncode => percent_encode_path(path, "%25"),
+            PathEncoding::CaseSwap => path
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_lowercase() {
+                        c.to_ascii_uppercase()
+                    } else {
+                        c.to_ascii_lowercase()
+                    }
+                })
+                .collect(),
+            PathEncoding::DoubleSlash => format!("/{}", path),
+            PathEncoding::TrailingSlash if path.ends_with('/') => path.to_string(),
+            PathEncoding::TrailingSlash => format!("{}/", path),
+        }
+    }
+}
+
+/// Writes every letter and digit of `path` as `escape` followed by its hex
+/// code. Escapes already in the path are passed through unchanged.
+fn percent_encode_path(path: &str, escape: &str) -> String {
+    let mut encoded = String::with_capacity(path.len() * 3);
+    let mut chars = path.char_indices();
+    while let Some((i, c)) = chars.next() {
+        let existing = path.get(i + 1..i + 3);
+        if c == '%' && existing.map_or(false, |hex| hex.bytes().all(|b| b.is_ascii_hexdigit())) {
+            encoded.push_str(&path[i..i + 3]);
+            chars.nth(1);
+        } else if c.is_ascii_alphanumeric() {
+            encoded.push_str(&format!("{}{:02X}", escape, c as u8));
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+/// A single change to the base request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mutation {
+    /// Puts `value` into header `name`, at the [`INSERTION_MARKER`] if the
+    /// base value has one, otherwise replacing or adding the header.
+    HeaderValue {
+        name: String,
+        value: String,
+    },
+    Method(String),
+    /// Appended to the path as is, e.g. `;`, `..;/` or `%20`.
+    PathSuffix(String),
+    PathEncoding(PathEncoding),
+    /// Same request over another HTTP version; see [`MutatedRequest::send`].
+    Protocol(HttpProtocol),
+}
+
+impl Mutation {
+    /// Applies the change to `request`. `Protocol` leaves the request as is.
+    pub fn apply(&self, request: &mut Request) {
+        match self {
+            Mutation::HeaderValue { name, value } => set_header_value(request, name, value),
+            Mutation::Method(method) => request.method = method.clone(),
+            Mutation::PathSuffix(suffix) => {
+                let path = format!("{}{}", request.target.path_only(), suffix);
+                request.target.url.set_path(&path);
+            }
+            Mutation::PathEncoding(encoding) => {
+                let path = encoding.apply(request.target.path());
+                request.target.url.set_path(&path);
+            }
+            Mutation::Protocol(_) => {}
+        }
+    }
+}
+
+impl std::fmt::Display for Mutation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mutation::HeaderValue { name, value } => write!(f, "header {}: {}", name, value),
+            Mutation::Method(method) => write!(f, "method {}", method),
+            Mutation::PathSuffix(suffix) => write!(f, "path suffix {:?}", suffix),
+            Mutation::PathEncoding(encoding) => write!(f, "path encoding {:?}", encoding),
+            Mutation::Protocol(protocol) => write!(f, "protocol {}", protocol),
+        }
+    }
+}
+
+fn set_header_value(request: &mut Request, name: &str, value: &str) {
+    let existing = request
+        .headers
+        .iter_mut()
+        .find(|h| h.name.eq_ignore_ascii_case(name));
+    match existing {
+        Some(header) => {
+            let current = header.value.clone().unwrap_or_default();
+            let updated = if current.contains(INSERTION_MARKER) {
+                current.replace(INSERTION_MARKER, value)
+            } else {
+                value.to_string()
+            };
+            header.value = Some(updated);
+        }
+        None => request
+            .headers
+            .push(Header::new(name.to_string(), value.to_string())),
+    }
+}
+
+/// A base request with one mutation applied.
+#[derive(Debug, Clone)]
+pub struct MutatedRequest {
+    pub request: Request,
+    pub mutation: Mutation,
+    /// HTTP version to send with; `None` keeps the caller's choice.
+    pub protocol: Option<HttpProtocol>,
+}
+
+impl MutatedRequest {
+    /// Sends the request with a default client for `protocol`, HTTP/1.1 if unset.
+    pub async fn send(self) -> Result<Response, ProtocolError> {
+        match self.protocol.unwrap_or(HttpProtocol::Http1) {
+            HttpProtocol::Http1 => H1::new().send_request(self.request).await,
+            HttpProtocol::Http2 => H2::new().send_request(self.request).await,
+            HttpProtocol::H2C => {
+                H2::new()
+                    .with_mode(H2Mode::PriorKnowledge)
+                    .send_request(self.request)
+                    .await
+            }
+            HttpProtocol::Http3 => H3::new().send_request(self.request).await,
+        }
+    }
+}
+
+/// Generates [`MutatedRequest`]s from a base request.
+#[derive(Debug, Clone)]
+pub struct Mutator {
+    base: Request,
+    mutations: Vec<Mutation>,
+}
+
+impl Mutator {
+    pub fn new(base: Request) -> Self {
+        Self {
+            base,
+            mutations: Vec::new(),
+        }
+    }
+
+    pub fn mutation(mut self, mutation: Mutation) -> Self {
+        self.mutations.push(mutation);
+        self
+    }
+
+    /// One request per payload placed into header `name`.
+    pub fn header_values<I, V>(mut self, name: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        self.mutations
+            .extend(values.into_iter().map(|value| Mutation::HeaderValue {
+                name: name.to_string(),
+                value: value.into(),
+            }));
+        self
+    }
+
+    pub fn methods<I, M>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = M>,
+        M: Into<String>,
+    {
+        self.mutations
+            .extend(methods.into_iter().map(|m| Mutation::Method(m.into())));
+        self
+    }
+
+    pub fn path_suffixes<I, S>(mut self, suffixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.mutations
+            .extend(suffixes.into_iter().map(|s| Mutation::PathSuffix(s.into())));
+        self
+    }
+
+    pub fn path_encodings<I>(mut self, encodings: I) -> Self
+    where
+        I: IntoIterator<Item = PathEncoding>,
+    {
+        self.mutations
+            .extend(encodings.into_iter().map(Mutation::PathEncoding));
+        self
+    }
+
+    pub fn protocols<I>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator<Item = HttpProtocol>,
+    {
+        self.mutations
+            .extend(protocols.into_iter().map(Mutation::Protocol));
+        self
+    }
+
+    pub fn base(&self) -> &Request {
+        &self.base
+    }
+
+    pub fn mutations(&self) -> &[Mutation] {
+        &self.mutations
+    }
+
+    pub fn len(&self) -> usize {
+        self.mutations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mutations.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = MutatedRequest> + '_ {
+        self.mutations
+            .iter()
+            .map(|mutation| mutate(&self.base, mutation.clone()))
+    }
+
+    /// Only the requests, e.g. to hand to a batch sender.
+    pub fn requests(&self) -> impl Iterator<Item = Request> + '_ {
+        self.iter().map(|mutated| mutated.request)
+    }
+}
+
+fn mutate(base: &Request, mutation: Mutation) -> MutatedRequest {
+    let mut request = base.clone();
+    mutation.apply(&mut request);
+    let protocol = match &mutation {
+        Mutation::Protocol(protocol) => Some(protocol.clone()),
+        _ => None,
+    };
+    MutatedRequest {
+        request,
+        mutation,
+        protocol,
+    }
+}
+
+/// Owning iterator over a [`Mutator`].
+pub struct MutatorIntoIter {
+    base: Request,
+    mutations: std::vec::IntoIter<Mutation>,
+}
+
+impl Iterator for MutatorIntoIter {
+    type Item = MutatedRequest;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mutation = self.mutations.next()?;
+        Some(mutate(&self.base, mutation))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.mutations.size_hint()
+    }
+}
+
+impl IntoIterator for Mutator {
+    type Item = MutatedRequest;
+    type IntoIter = MutatorIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        MutatorIntoIter {
+            base: self.base,
+            mutations: self.mutations.into_iter(),
+        }
+    }
+}
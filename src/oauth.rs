@@ -0,0 +1,338 @@
+//! OAuth 2.0 token acquisition (RFC 6749) for sessions.
+//!
+//! [`OAuthClient`] runs client-credentials and refresh-token grants against a
+//! token endpoint with any [`Protocol`] client and caches the resulting
+//! access token. Attached to a [`Session`](crate::Session) it injects
+//! `Authorization: Bearer ...` into every request, renews the token shortly
+//! before it expires and retries once when the server answers 401.
+
+use crate::types::{Header, Protocol, ProtocolError, Request, Response};
+use crate::utils::{base64_encode, AUTHORIZATION_HEADER};
+use serde_json::Value;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How the client authenticates to the token endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClientAuthMethod {
+    /// HTTP Basic with the url-encoded client id and secret (`client_secret_basic`).
+    #[default]
+    Basic,
+    /// `client_id`/`client_secret` in the form body (`client_secret_post`).
+    Body,
+}
+
+/// Access token returned by the token endpoint.
+#[derive(Clone)]
+pub struct AccessToken {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_at: Option<Instant>,
+    pub refresh_token: Option<String>,
+    pub scope: Option<String>,
+}
+
+impl fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessToken")
+            .field("token_type", &self.token_type)
+            .field("expires_at", &self.expires_at)
+            .field("has_refresh_token", &self.refresh_token.is_some())
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+impl AccessToken {
+    /// Parses a successful token response body (RFC 6749 Section 5.1).
+    pub fn from_json(value: &Value) -> Result<Self, ProtocolError> {
+        let access_token = value
+            .get("access_token")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                ProtocolError::InvalidResponse("Token response is missing access_token".to_string())
+            })?;
+        // Some servers send expires_in as a string.
+        let expires_in = value.get("expires_in").and_then(|v| {
+            v.as_u64()
+                .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+        });
+
+        Ok(Self {
+            access_token: access_token.to_string(),
+            token_type: value
+                .get("token_type")
+                .and_then(Value::as_str)
+                .unwrap_or("Bearer")
+                .to_string(),
+            expires_at: expires_in
+                .and_then(|secs| Instant::now().checked_add(Duration::from_secs(secs))),
+            refresh_token: value
+                .get("refresh_token")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            scope: value
+                .get("scope")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        })
+    }
+
+    /// True when the token expires within `leeway`. Tokens without
+    /// `expires_in` never expire on their own.
+    pub fn expires_within(&self, leeway: Duration) -> bool {
+        self.expires_at
+            .map_or(false, |expires_at| Instant::now() + leeway >= expires_at)
+    }
+
+    /// Value for the `Authorization` header.
+    pub fn authorization(&self) -> String {
+        // RFC 6750 uses "Bearer"; some servers answer with "bearer".
+        if self.token_type.eq_ignore_ascii_case("bearer") {
+            format!("Bearer {}", self.access_token)
+        } else {
+            format!("{} {}", self.token_type, self.access_token)
+        }
+    }
+}
+
+/// Client-credentials / refresh-token client with a cached access token.
+#[derive(Clone)]
+pub struct OAuthClient {
+    token_url: String,
+    client_id: String,
+    client_secret: Option<String>,
+    scope: Option<String>,
+    audience: Option<String>,
+    auth_method: ClientAuthMethod,
+    refresh_leeway: Duration,
+    refresh_token: Option<String>,
+    token: Option<AccessToken>,
+}
+
+impl fmt::Debug for OAuthClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuthClient")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("scope", &self.scope)
+            .field("audience", &self.audience)
+            .field("auth_method", &self.auth_method)
+            .field("refresh_leeway", &self.refresh_leeway)
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+impl OAuthClient {
+    pub fn new(token_url: impl Into<String>, client_id: impl Into<String>) -> Self {
+        Self {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: None,
+            scope: None,
+            audience: None,
+            auth_method: ClientAuthMethod::default(),
+            refresh_leeway: Duration::from_secs(30),
+            refresh_token: None,
+            token: None,
+        }
+    }
+
+    pub fn client_secret(mut self, secret: impl Into<String>) -> Self {
+        self.client_secret = Some(secret.into());
+        self
+    }
+
+    /// Space-separated scopes requested with each grant.
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// `audience` parameter, required by some providers for client credentials.
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    pub fn auth_method(mut self, method: ClientAuthMethod) -> Self {
+        self.auth_method = method;
+        self
+    }
+
+    /// How long before expiry a cached token is renewed (30s by default).
+    pub fn refresh_leeway(mut self, leeway: Duration) -> Self {
+        self.refresh_leeway = leeway;
+        self
+    }
+
+    /// Seeds a refresh token obtained elsewhere (e.g. an authorization-code flow).
+    pub fn refresh_token(mut self, token: impl Into<String>) -> Self {
+        self.refresh_token = Some(token.into());
+        self
+    }
+
+    /// Cached token, if one has been acquired.
+    pub fn token(&self) -> Option<&AccessToken> {
+        self.token.as_ref()
+    }
+
+    /// Installs a token acquired elsewhere.
+    pub fn set_token(&mut self, token: AccessToken) {
+        if token.refresh_token.is_some() {
+            self.refresh_token = token.refresh_token.clone();
+        }
+        self.token = Some(token);
+    }
+
+    /// Drops the cached access token so the next request acquires a new one.
+    pub fn invalidate(&mut self) {
+        self.token = None;
+    }
+
+    /// Returns the cached token, refreshing or re-acquiring it when it is
+    /// missing or about to expire.
+    pub async fn access_token<P>(&mut self, client: &P) -> Result<&AccessToken, ProtocolError>
+    where
+        P: Protocol,
+    {
+        let fresh = self
+            .token
+            .as_ref()
+            .map_or(false, |token| !token.expires_within(self.refresh_leeway));
+        if !fresh {
+            self.renew(client).await?;
+        }
+        match self.token.as_ref() {
+            Some(token) => Ok(token),
+            None => Err(ProtocolError::RequestFailed(
+                "OAuth token endpoint returned no token".to_string(),
+            )),
+        }
+    }
+
+    /// Uses the refresh token when there is one, falling back to client
+    /// credentials if the refresh grant is rejected.
+    async fn renew<P>(&mut self, client: &P) -> Result<(), ProtocolError>
+    where
+        P: Protocol,
+    {
+        if self.refresh_token.is_some() {
+            match self.refresh(client).await.map(|_| ()) {
+                Ok(()) => return Ok(()),
+                Err(err) if self.client_secret.is_none() => return Err(err),
+                Err(_) => self.refresh_token = None,
+            }
+        }
+        self.client_credentials(client).await.map(|_| ())
+    }
+
+    /// Runs the client-credentials grant (RFC 6749 Section 4.4).
+    pub async fn client_credentials<P>(&mut self, client: &P) -> Result<&AccessToken, ProtocolError>
+    where
+        P: Protocol,
+    {
+        let mut fields = vec![("grant_type", "client_credentials".to_string())];
+        if let Some(audience) = &self.audience {
+            fields.push(("audience", audience.clone()));
+        }
+        self.grant(client, fields).await
+    }
+
+    /// Runs the refresh-token grant (RFC 6749 Section 6).
+    pub async fn refresh<P>(&mut self, client: &P) -> Result<&AccessToken, ProtocolError>
+    where
+        P: Protocol,
+    {
+        let refresh_token = self.refresh_token.clone().ok_or_else(|| {
+            ProtocolError::RequestFailed("No OAuth refresh token available".to_string())
+        })?;
+        let fields = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token),
+        ];
+        self.grant(client, fields).await
+    }
+
+    async fn grant<P>(
+        &mut self,
+        client: &P,
+        mut fields: Vec<(&'static str, String)>,
+    ) -> Result<&AccessToken, ProtocolError>
+    where
+        P: Protocol,
+    {
+        if let Some(scope) = &self.scope {
+            fields.push(("scope", scope.clone()));
+        }
+        let request = self.token_request(fields)?;
+        let response = client.send_request(request).await?;
+        let token = parse_token_response(&response)?;
+        self.set_token(token);
+        Ok(self.token.as_ref().expect("token was just stored"))
+    }
+
+    fn token_request(
+        &self,
+        mut fields: Vec<(&'static str, String)>,
+    ) -> Result<Request, ProtocolError> {
+        let mut request = Request::new(&self.token_url, "POST")?;
+        request.headers.push(Header::new(
+            "accept".to_string(),
+            "application/json".to_string(),
+        ));
+
+        match (self.auth_method, &self.client_secret) {
+            (ClientAuthMethod::Basic, Some(secret)) => {
+                let credentials = format!(
+                    "{}:{}",
+                    urlencoding::encode(&self.client_id),
+                    urlencoding::encode(secret)
+                );
+                request.headers.push(Header::new(
+                    AUTHORIZATION_HEADER.to_string(),
+                    format!("Basic {}", base64_encode(credentials.as_bytes())),
+                ));
+            }
+            (ClientAuthMethod::Body, Some(secret)) => {
+                fields.push(("client_id", self.client_id.clone()));
+                fields.push(("client_secret", secret.clone()));
+            }
+            // Public clients identify themselves in the body only.
+            (_, None) => fields.push(("client_id", self.client_id.clone())),
+        }
+
+        request.set_data(fields);
+        Ok(request)
+    }
+}
+
+/// Parses a token endpoint response, surfacing `error`/`error_description`
+/// from rejected grants (RFC 6749 Section 5.2).
+pub fn parse_token_response(response: &Response) -> Result<AccessToken, ProtocolError> {
+    let body = response.json().map_err(|err| {
+        ProtocolError::InvalidResponse(format!(
+            "Token endpoint returned {} with a non-JSON body: {}",
+            response.status, err
+        ))
+    })?;
+
+    if !(200..300).contains(&response.status) || body.get("error").is_some() {
+        let error = body
+            .get("error")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown_error");
+        let description = body
+            .get("error_description")
+            .and_then(Value::as_str)
+            .map(|d| format!(": {}", d))
+            .unwrap_or_default();
+        return Err(ProtocolError::RequestFailed(format!(
+            "OAuth token request failed ({}) {}{}",
+            response.status, error, description
+        )));
+    }
+
+    AccessToken::from_json(&body)
+}
@@ -0,0 +1,244 @@
+//! Per-host backoff after `429 Too Many Requests` and `503 Service Unavailable`.
+//!
+//! A [`Pacer`] watches responses: when a host answers with a throttling
+//! status, further requests to it wait until the `Retry-After` time has
+//! passed, or for an exponential backoff when the header is missing.
+//! Sessions with [`Session::pacing`](crate::Session::pacing) also resend the
+//! throttled request after waiting, if its method is idempotent or the policy
+//! allows others. [`SendAll::pacer`](crate::SendAll::pacer) paces a batch:
+//!
+//! ```ignore
+//! let mut session = H1::new().session();
+//! session.pacing(PacingPolicy::default());
+//! for page in 1..=50 {
+//!     session.get(&format!("https://example.com/?page={page}")).send().await?;
+//! }
+//! for (host, throttle) in session.throttle_state() {
+//!     println!("{host}: throttled {} times, {:?} left", throttle.consecutive, throttle.remaining);
+//! }
+//! ```
+
+use crate::hedge::is_idempotent;
+use crate::types::Response;
+use crate::utils::header_value;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+const RETRY_AFTER_HEADER: &str = "retry-after";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacingPolicy {
+    /// Statuses that mean the server wants fewer requests.
+    pub statuses: Vec<u16>,
+    /// Honor `Retry-After` instead of the computed backoff.
+    pub respect_retry_after: bool,
+    /// Wait after the first throttled response without `Retry-After`;
+    /// doubles with each throttled response in a row.
+    pub base_backoff: Duration,
+    /// Upper bound on any wait, `Retry-After` included.
+    pub max_backoff: Duration,
+    /// How often a session resends a throttled request before returning
+    /// the throttled response.
+    pub max_retries: u32,
+    /// Resend throttled requests whose method is not idempotent, such as
+    /// POST and PATCH, as well.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for PacingPolicy {
+    fn default() -> Self {
+        Self {
+            statuses: vec![429, 503],
+            respect_retry_after: true,
+            base_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_retries: 2,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl PacingPolicy {
+    pub fn statuses(mut self, statuses: Vec<u16>) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    pub fn respect_retry_after(mut self, respect: bool) -> Self {
+        self.respect_retry_after = respect;
+        self
+    }
+
+    pub fn base_backoff(mut self, backoff: Duration) -> Self {
+        self.base_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    pub fn retry_non_idempotent(mut self, enabled: bool) -> Self {
+        self.retry_non_idempotent = enabled;
+        self
+    }
+
+    /// Whether a throttled `method` request may be sent again.
+    pub fn retries(&self, method: &str) -> bool {
+        self.retry_non_idempotent || is_idempotent(method)
+    }
+
+    /// Backoff for the `consecutive`-th throttled response in a row.
+    fn backoff(&self, consecutive: u32, retry_after: Option<Duration>) -> Duration {
+        let backoff = match retry_after.filter(|_| self.respect_retry_after) {
+            Some(retry_after) => retry_after,
+            None => {
+                let doublings = consecutive.saturating_sub(1).min(31);
+                self.base_backoff.saturating_mul(1 << doublings)
+            }
+        };
+        backoff.min(self.max_backoff)
+    }
+}
+
+/// Throttling state of one host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostThrottle {
+    /// Throttled responses in a row.
+    pub consecutive: u32,
+    /// Status of the last throttled response.
+    pub status: u16,
+    /// `Retry-After` of the last throttled response, if it had one.
+    pub retry_after: Option<Duration>,
+    /// Time left before requests to the host go out again.
+    pub remaining: Duration,
+}
+
+#[derive(Debug)]
+struct ThrottleEntry {
+    consecutive: u32,
+    status: u16,
+    retry_after: Option<Duration>,
+    until: Instant,
+}
+
+/// Per-host backoff shared by everything that sends through it.
+#[derive(Debug, Default)]
+pub struct Pacer {
+    policy: PacingPolicy,
+    hosts: Mutex<HashMap<String, ThrottleEntry>>,
+}
+
+impl Pacer {
+    pub fn new(policy: PacingPolicy) -> Self {
+        Self {
+            policy,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn policy(&self) -> &PacingPolicy {
+        &self.policy
+    }
+
+    /// How long requests to `host` still have to wait.
+    pub fn delay(&self, host: &str) -> Option<Duration> {
+        let until = self.lock().get(&host.to_ascii_lowercase())?.until;
+        let remaining = until.saturating_duration_since(Instant::now());
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    /// Sleeps until requests to `host` may go out.
+    pub async fn wait(&self, host: &str) {
+        if let Some(delay) = self.delay(host) {
+            sleep(delay).await;
+        }
+    }
+
+    /// Records the response `host` sent. Returns the backoff it starts if it
+    /// was a throttling status; any other status clears the host's state.
+    pub fn observe(&self, host: &str, response: &Response) -> Option<Duration> {
+        let host = host.to_ascii_lowercase();
+        let mut hosts = self.lock();
+        if !self.policy.statuses.contains(&response.status) {
+            hosts.remove(&host);
+            return None;
+        }
+
+        let retry_after = response.retry_after();
+        let consecutive = hosts.get(&host).map_or(0, |entry| entry.consecutive) + 1;
+        let backoff = self.policy.backoff(consecutive, retry_after);
+        hosts.insert(
+            host,
+            ThrottleEntry {
+                consecutive,
+                status: response.status,
+                retry_after,
+                until: Instant::now() + backoff,
+            },
+        );
+        Some(backoff)
+    }
+
+    /// Hosts that sent a throttling status as their last response, by name.
+    pub fn state(&self) -> BTreeMap<String, HostThrottle> {
+        let now = Instant::now();
+        self.lock()
+            .iter()
+            .map(|(host, entry)| {
+                (
+                    host.clone(),
+                    HostThrottle {
+                        consecutive: entry.consecutive,
+                        status: entry.status,
+                        retry_after: entry.retry_after,
+                        remaining: entry.until.saturating_duration_since(now),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Lets requests to `host` go out at once.
+    pub fn reset(&self, host: &str) {
+        self.lock().remove(&host.to_ascii_lowercase());
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ThrottleEntry>> {
+        // The map stays consistent even if a holder panicked.
+        self.hosts.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Response {
+    /// The `Retry-After` header as a delay from now; dates in the past are
+    /// zero.
+    pub fn retry_after(&self) -> Option<Duration> {
+        parse_retry_after(header_value(&self.headers, RETRY_AFTER_HEADER)?)
+    }
+}
+
+/// Parses `Retry-After`: either delay-seconds or an HTTP-date (RFC 9110
+/// section 10.2.3).
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        return Some(Duration::from_secs(value.parse().unwrap_or(u64::MAX)));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&Utc) - Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
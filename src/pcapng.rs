@@ -0,0 +1,411 @@
+//! Export of captured HTTP/2 exchanges as pcapng files for Wireshark.
+//!
+//! Frames are wrapped in synthetic IPv4/TCP packets between two made-up
+//! endpoints, preceded by a TCP handshake and the client connection
+//! preface, so Wireshark's HTTP/2 dissector picks the stream up without
+//! TLS keys:
+//!
+//! ```ignore
+//! let prepared = request.prepare_request()?;
+//! let response = H2::new().send_request(request).await?;
+//! PcapngExport::new()
+//!     .request(&prepared, 1)?
+//!     .response(&response)?
+//!     .write_to("exchange.pcapng")?;
+//! ```
+//!
+//! The request side is re-encoded with a fresh HPACK context, since only
+//! received frames are captured. Captured HTTP/3 frames are skipped: QUIC
+//! packets cannot be dissected without the TLS secrets, which the crate
+//! does not export.
+
+use crate::h2::consts::{
+    CONNECTION_PREFACE, DEFAULT_MAX_FRAME_SIZE, END_HEADERS_FLAG, END_STREAM_FLAG,
+};
+use crate::h2::hpack::{HeaderCodec, HpackCodec};
+use crate::types::{
+    FrameH2, FrameTypeH2, Header, PreparedRequest, ProtocolError, Response, ResponseFrame,
+};
+use bytes::Bytes;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const BLOCK_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const BLOCK_INTERFACE_DESCRIPTION: u32 = 1;
+const BLOCK_ENHANCED_PACKET: u32 = 6;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+/// LINKTYPE_RAW: packets start with the IP header.
+const LINKTYPE_RAW: u16 = 101;
+
+const IPV4_HEADER_LEN: usize = 20;
+const TCP_HEADER_LEN: usize = 20;
+/// Largest payload put in one synthetic TCP segment.
+const MAX_SEGMENT: usize = 65_495 - IPV4_HEADER_LEN - TCP_HEADER_LEN;
+/// Initial SETTINGS_HEADER_TABLE_SIZE of RFC 9113, which the request side
+/// never changes.
+const HPACK_TABLE_SIZE: usize = 4096;
+const CLIENT_ISN: u32 = 1_000;
+const SERVER_ISN: u32 = 5_000;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    ToServer,
+    ToClient,
+}
+
+#[derive(Debug, Clone)]
+struct Record {
+    direction: Direction,
+    at: Option<Instant>,
+    bytes: Bytes,
+}
+
+/// Builds a pcapng capture of one HTTP/2 connection. Requests and
+/// responses are written in the order they are added.
+pub struct PcapngExport {
+    client: SocketAddrV4,
+    server: SocketAddrV4,
+    records: Vec<Record>,
+    /// Encoder for the request side, shared by all requests like on a
+    /// real connection.
+    hpack: HpackCodec,
+}
+
+impl Default for PcapngExport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PcapngExport {
+    /// Client `10.0.0.1:49152`, server `10.0.0.2:80`; port 80 lets
+    /// Wireshark find HTTP/2 through the connection preface.
+    pub fn new() -> Self {
+        Self {
+            client: SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 49152),
+            server: SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 2), 80),
+            records: Vec::new(),
+            hpack: HpackCodec::new(HPACK_TABLE_SIZE, HPACK_TABLE_SIZE),
+        }
+    }
+
+    pub fn addresses(mut self, client: SocketAddrV4, server: SocketAddrV4) -> Self {
+        self.client = client;
+        self.server = server;
+        self
+    }
+
+    /// Adds `request` as sent on `stream_id`: HEADERS, DATA and trailers.
+    pub fn request(
+        mut self,
+        request: &PreparedRequest,
+        stream_id: u32,
+    ) -> Result<Self, ProtocolError> {
+        let body = request.body.clone().filter(|body| !body.is_empty());
+        let has_trailers = !request.trailers.is_empty();
+        let mut frames = self.header_frames(
+            stream_id,
+            &request.header_block(),
+            body.is_none() && !has_trailers,
+        )?;
+        if let Some(body) = body {
+            let chunks: Vec<Bytes> = body
+                .chunks(DEFAULT_MAX_FRAME_SIZE as usize)
+                .map(|chunk| body.slice_ref(chunk))
+                .collect();
+            let last = chunks.len() - 1;
+            for (i, chunk) in chunks.into_iter().enumerate() {
+                let flags = if i == last && !has_trailers {
+                    END_STREAM_FLAG
+                } else {
+                    0
+                };
+                frames.push(FrameH2::new(FrameTypeH2::Data, flags, stream_id, chunk));
+            }
+        }
+        if has_trailers {
+            frames.extend(self.header_frames(stream_id, &request.trailers, true)?);
+        }
+        for frame in frames {
+            self.records.push(Record {
+                direction: Direction::ToServer,
+                at: None,
+                bytes: frame.serialize()?,
+            });
+        }
+        Ok(self)
+    }
+
+    /// Adds the HTTP/2 frames captured for `response`, stamped with their
+    /// arrival times.
+    pub fn response(mut self, response: &Response) -> Result<Self, ProtocolError> {
+        let times = response.frame_times();
+        for (i, frame) in response.captured_frames().iter().enumerate() {
+            if let ResponseFrame::Http2(frame) = frame {
+                self.records.push(Record {
+                    direction: Direction::ToClient,
+                    at: times.get(i).copied(),
+                    bytes: frame.serialize()?,
+                });
+            }
+        }
+        Ok(self)
+    }
+
+    /// The capture as pcapng bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_section_header(&mut out);
+        write_interface_description(&mut out);
+
+        let timestamps = self.timestamps();
+        let start = timestamps.first().copied().unwrap_or_else(SystemTime::now);
+        let mut tcp = TcpState::new(self.client, self.server);
+
+        tcp.segment(&mut out, start, Direction::ToServer, TCP_SYN, &[]);
+        tcp.segment(&mut out, start, Direction::ToClient, TCP_SYN | TCP_ACK, &[]);
+        tcp.segment(&mut out, start, Direction::ToServer, TCP_ACK, &[]);
+        // Empty SETTINGS after the preface, as every client sends.
+        let mut preface = CONNECTION_PREFACE.to_vec();
+        preface.extend_from_slice(&[0, 0, 0, 0x04, 0, 0, 0, 0, 0]);
+        tcp.segment(
+            &mut out,
+            start,
+            Direction::ToServer,
+            TCP_PSH | TCP_ACK,
+            &preface,
+        );
+
+        for (record, at) in self.records.iter().zip(timestamps.iter().copied()) {
+            for chunk in record.bytes.chunks(MAX_SEGMENT) {
+                tcp.segment(&mut out, at, record.direction, TCP_PSH | TCP_ACK, chunk);
+            }
+        }
+
+        let end = timestamps.last().copied().unwrap_or(start);
+        tcp.segment(&mut out, end, Direction::ToServer, TCP_FIN | TCP_ACK, &[]);
+        out
+    }
+
+    pub fn write_to(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    fn header_frames(
+        &mut self,
+        stream_id: u32,
+        headers: &[Header],
+        end_stream: bool,
+    ) -> Result<Vec<FrameH2>, ProtocolError> {
+        let block = self.hpack.encode(headers)?;
+        let max = DEFAULT_MAX_FRAME_SIZE as usize;
+        let mut frames = Vec::new();
+        let mut offset = 0;
+        loop {
+            let end = (offset + max).min(block.len());
+            let last = end == block.len();
+            let mut flags = if last { END_HEADERS_FLAG } else { 0 };
+            let frame_type = if offset == 0 {
+                if end_stream {
+                    flags |= END_STREAM_FLAG;
+                }
+                FrameTypeH2::Headers
+            } else {
+                FrameTypeH2::Continuation
+            };
+            frames.push(FrameH2::new(
+                frame_type,
+                flags,
+                stream_id,
+                block.slice(offset..end),
+            ));
+            if last {
+                return Ok(frames);
+            }
+            offset = end;
+        }
+    }
+
+    /// Wall-clock time of each record. Records without an arrival time,
+    /// such as requests, take the time of the record before them, or of
+    /// the first timed record when nothing precedes them.
+    fn timestamps(&self) -> Vec<SystemTime> {
+        let now = SystemTime::now();
+        let anchor = Instant::now();
+        let wall = |at: Instant| now - anchor.saturating_duration_since(at);
+        let first = self
+            .records
+            .iter()
+            .find_map(|record| record.at)
+            .map_or(now, wall);
+
+        let mut previous = first;
+        self.records
+            .iter()
+            .map(|record| {
+                previous = record.at.map_or(previous, wall).max(previous);
+                previous
+            })
+            .collect()
+    }
+}
+
+struct TcpState {
+    client: SocketAddrV4,
+    server: SocketAddrV4,
+    client_seq: u32,
+    server_seq: u32,
+    ip_id: u16,
+}
+
+impl TcpState {
+    fn new(client: SocketAddrV4, server: SocketAddrV4) -> Self {
+        Self {
+            client,
+            server,
+            client_seq: CLIENT_ISN,
+            server_seq: SERVER_ISN,
+            ip_id: 1,
+        }
+    }
+
+    fn segment(
+        &mut self,
+        out: &mut Vec<u8>,
+        at: SystemTime,
+        direction: Direction,
+        flags: u8,
+        payload: &[u8],
+    ) {
+        let (source, destination, seq, ack) = match direction {
+            Direction::ToServer => (self.client, self.server, self.client_seq, self.server_seq),
+            Direction::ToClient => (self.server, self.client, self.server_seq, self.client_seq),
+        };
+        // The ACK field acknowledges everything the peer sent so far; a SYN
+        // without ACK carries zero.
+        let ack = if flags & TCP_ACK != 0 { ack } else { 0 };
+        let packet = ipv4_tcp_packet(source, destination, seq, ack, flags, self.ip_id, payload);
+        write_enhanced_packet(out, at, &packet);
+
+        self.ip_id = self.ip_id.wrapping_add(1);
+        let consumed = payload.len() as u32 + u32::from(flags & (TCP_SYN | TCP_FIN) != 0);
+        match direction {
+            Direction::ToServer => self.client_seq = self.client_seq.wrapping_add(consumed),
+            Direction::ToClient => self.server_seq = self.server_seq.wrapping_add(consumed),
+        }
+    }
+}
+
+fn ipv4_tcp_packet(
+    source: SocketAddrV4,
+    destination: SocketAddrV4,
+    seq: u32,
+    ack: u32,
+    flags: u8,
+    ip_id: u16,
+    payload: &[u8],
+) -> Vec<u8> {
+    let total_len = IPV4_HEADER_LEN + TCP_HEADER_LEN + payload.len();
+    let mut packet = Vec::with_capacity(total_len);
+
+    packet.push(0x45); // IPv4, 5-word header
+    packet.push(0);
+    packet.extend_from_slice(&(total_len as u16).to_be_bytes());
+    packet.extend_from_slice(&ip_id.to_be_bytes());
+    packet.extend_from_slice(&0x4000u16.to_be_bytes()); // don't fragment
+    packet.push(64); // TTL
+    packet.push(6); // TCP
+    packet.extend_from_slice(&[0, 0]);
+    packet.extend_from_slice(&source.ip().octets());
+    packet.extend_from_slice(&destination.ip().octets());
+    let ip_checksum = checksum(&packet[..IPV4_HEADER_LEN], 0);
+    packet[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
+
+    let tcp_start = packet.len();
+    packet.extend_from_slice(&source.port().to_be_bytes());
+    packet.extend_from_slice(&destination.port().to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(&ack.to_be_bytes());
+    packet.push((TCP_HEADER_LEN as u8 / 4) << 4);
+    packet.push(flags);
+    packet.extend_from_slice(&u16::MAX.to_be_bytes()); // window
+    packet.extend_from_slice(&[0, 0, 0, 0]); // checksum, urgent pointer
+    packet.extend_from_slice(payload);
+
+    let tcp_len = (packet.len() - tcp_start) as u32;
+    let mut pseudo = 0u32;
+    for address in [source.ip().octets(), destination.ip().octets()] {
+        pseudo += u32::from(u16::from_be_bytes([address[0], address[1]]));
+        pseudo += u32::from(u16::from_be_bytes([address[2], address[3]]));
+    }
+    pseudo += 6 + tcp_len;
+    let tcp_checksum = checksum(&packet[tcp_start..], pseudo);
+    packet[tcp_start + 16..tcp_start + 18].copy_from_slice(&tcp_checksum.to_be_bytes());
+    packet
+}
+
+/// Internet checksum (RFC 1071) of `data`, seeded with `initial`.
+fn checksum(data: &[u8], initial: u32) -> u16 {
+    let mut sum = initial;
+    for pair in data.chunks(2) {
+        let word = match pair {
+            [high, low] => u16::from_be_bytes([*high, *low]),
+            [high] => u16::from_be_bytes([*high, 0]),
+            _ => 0,
+        };
+        sum += u32::from(word);
+    }
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn write_block(out: &mut Vec<u8>, block_type: u32, body: &[u8]) {
+    let padding = (4 - body.len() % 4) % 4;
+    let total_len = (12 + body.len() + padding) as u32;
+    out.extend_from_slice(&block_type.to_le_bytes());
+    out.extend_from_slice(&total_len.to_le_bytes());
+    out.extend_from_slice(body);
+    out.extend(std::iter::repeat(0).take(padding));
+    out.extend_from_slice(&total_len.to_le_bytes());
+}
+
+fn write_section_header(out: &mut Vec<u8>) {
+    let mut body = Vec::with_capacity(16);
+    body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+    body.extend_from_slice(&1u16.to_le_bytes()); // major version
+    body.extend_from_slice(&0u16.to_le_bytes()); // minor version
+    body.extend_from_slice(&(-1i64).to_le_bytes()); // section length unknown
+    write_block(out, BLOCK_SECTION_HEADER, &body);
+}
+
+fn write_interface_description(out: &mut Vec<u8>) {
+    let mut body = Vec::with_capacity(8);
+    body.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&0u32.to_le_bytes()); // no snap length
+    write_block(out, BLOCK_INTERFACE_DESCRIPTION, &body);
+}
+
+/// Timestamps use the default resolution of microseconds.
+fn write_enhanced_packet(out: &mut Vec<u8>, at: SystemTime, packet: &[u8]) {
+    let micros = at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_micros() as u64;
+    let mut body = Vec::with_capacity(20 + packet.len());
+    body.extend_from_slice(&0u32.to_le_bytes()); // interface 0
+    body.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+    body.extend_from_slice(&(micros as u32).to_le_bytes());
+    body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    body.extend_from_slice(packet);
+    write_block(out, BLOCK_ENHANCED_PACKET, &body);
+}
@@ -0,0 +1,401 @@
+//! Connections opened ahead of time and parked per origin.
+//!
+//! `preconnect` on [`H1`](crate::H1), [`H2`](crate::H2), [`H3`](crate::H3)
+//! and [`Auto`](crate::Auto) resolves the host, connects and finishes the TLS
+//! and HTTP/2 or HTTP/3 handshakes, then parks the connection here. The next
+//! request to that origin takes it instead of connecting:
+//!
+//! ```ignore
+//! let h2 = H2::new();
+//! h2.preconnect("https://example.com").await?;
+//! // ... later, without the connection setup latency:
+//! let res = h2.send_request(Request::new("https://example.com/", "GET")?).await?;
+//! ```
+//!
+//! Clones of a client share its pool. Parked connections are dropped once
+//! they have been idle for the pool's `max_idle`, since servers close idle
+//! connections on their own; an [`EvictionPolicy`] and `max_per_origin`
+//! bound how many stay parked. [`ConnectionPool::stats`] counts reuse and
+//! handshake times per origin.
+
+use crate::h2::connection::H2Connection;
+use crate::h3::connection::H3Connection;
+use crate::stream::TransportStream;
+use crate::types::{ProtocolError, Request, Target};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+
+const DEFAULT_MAX_IDLE: Duration = Duration::from_secs(30);
+
+/// Which parked connection a request takes, and which one is closed when an
+/// origin has more than the pool's `max_per_origin`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Takes the most recently parked connection, so the others age out and
+    /// few stay warm. Over the limit, the oldest is closed.
+    #[default]
+    Lifo,
+    /// Takes the connection idle the longest, spreading requests over all of
+    /// them. Over the limit, the least recently used is closed.
+    Lru,
+    /// Takes the most recently parked connection and closes connections only
+    /// once they have been idle for `max_idle`; `max_per_origin` is ignored.
+    Ttl,
+}
+
+/// Counters for one origin, see [`ConnectionPool::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OriginPoolStats {
+    /// Connections parked right now.
+    pub idle: usize,
+    /// Requests served by a parked connection.
+    pub reused: u64,
+    /// Connections opened, each with its connect and handshake time.
+    pub opened: u64,
+    /// Parked connections closed for going over `max_per_origin`.
+    pub evicted: u64,
+    /// Parked connections closed for being idle longer than `max_idle`.
+    pub expired: u64,
+    pub total_handshake_time: Duration,
+    pub last_handshake_time: Option<Duration>,
+}
+
+impl OriginPoolStats {
+    pub fn average_handshake_time(&self) -> Option<Duration> {
+        let opened = u32::try_from(self.opened).ok().filter(|&n| n > 0)?;
+        Some(self.total_handshake_time / opened)
+    }
+
+    fn merge(&mut self, other: &OriginPoolStats) {
+        self.idle += other.idle;
+        self.reused += other.reused;
+        self.opened += other.opened;
+        self.evicted += other.evicted;
+        self.expired += other.expired;
+        self.total_handshake_time += other.total_handshake_time;
+        if other.last_handshake_time.is_some() {
+            self.last_handshake_time = other.last_handshake_time;
+        }
+    }
+}
+
+/// Snapshot of one or more pools, keyed by origin.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    pub origins: BTreeMap<String, OriginPoolStats>,
+}
+
+impl PoolStats {
+    /// Adds `other`'s counters to these, e.g. for a client with a pool per
+    /// protocol.
+    pub fn merge(&mut self, other: &PoolStats) {
+        for (origin, stats) in &other.origins {
+            self.origins.entry(origin.clone()).or_default().merge(stats);
+        }
+    }
+
+    pub fn idle(&self) -> usize {
+        self.origins.values().map(|stats| stats.idle).sum()
+    }
+
+    pub fn reused(&self) -> u64 {
+        self.origins.values().map(|stats| stats.reused).sum()
+    }
+
+    pub fn opened(&self) -> u64 {
+        self.origins.values().map(|stats| stats.opened).sum()
+    }
+}
+
+/// A connection that can say goodbye to the server before it is dropped.
+#[async_trait(?Send)]
+pub trait PooledConnection {
+    /// Sends GOAWAY or TLS close_notify as the protocol has it. Errors are
+    /// ignored; the connection is dropped either way.
+    async fn close_gracefully(&mut self);
+}
+
+#[async_trait(?Send)]
+impl PooledConnection for TransportStream {
+    async fn close_gracefully(&mut self) {
+        let _ = self.shutdown().await;
+    }
+}
+
+#[async_trait(?Send)]
+impl PooledConnection for H2Connection {
+    async fn close_gracefully(&mut self) {
+        let _ = self.close().await;
+    }
+}
+
+#[async_trait(?Send)]
+impl PooledConnection for H3Connection {
+    async fn close_gracefully(&mut self) {
+        let _ = self.close().await;
+    }
+}
+
+/// Settings and counters of a pool, whatever connection type it holds.
+#[async_trait(?Send)]
+pub trait PoolControl {
+    fn stats(&self) -> PoolStats;
+    fn set_eviction_policy(&self, policy: EvictionPolicy);
+    /// `None` lifts the limit.
+    fn set_max_per_origin(&self, max: Option<usize>);
+    fn set_max_idle(&self, max_idle: Duration);
+    /// Drops every parked connection.
+    fn clear(&self);
+    /// Closes every parked connection cleanly; returns how many there were.
+    async fn close_idle(&self) -> usize;
+}
+
+pub struct ConnectionPool<C> {
+    state: Mutex<PoolState<C>>,
+}
+
+struct PoolState<C> {
+    max_idle: Duration,
+    max_per_origin: Option<usize>,
+    policy: EvictionPolicy,
+    /// Oldest first.
+    idle: HashMap<String, Vec<IdleConnection<C>>>,
+    stats: BTreeMap<String, OriginPoolStats>,
+}
+
+struct IdleConnection<C> {
+    connection: C,
+    parked: Instant,
+}
+
+impl<C> Default for ConnectionPool<C> {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_IDLE)
+    }
+}
+
+impl<C> ConnectionPool<C> {
+    pub fn new(max_idle: Duration) -> Self {
+        Self {
+            state: Mutex::new(PoolState {
+                max_idle,
+                max_per_origin: None,
+                policy: EvictionPolicy::default(),
+                idle: HashMap::new(),
+                stats: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Applies to connections already parked as well.
+    pub fn set_max_idle(&self, max_idle: Duration) {
+        self.lock().max_idle = max_idle;
+    }
+
+    pub fn set_eviction_policy(&self, policy: EvictionPolicy) {
+        self.lock().policy = policy;
+    }
+
+    /// Most connections kept parked per origin; `None` lifts the limit.
+    /// Applies from the next `park`.
+    pub fn set_max_per_origin(&self, max: Option<usize>) {
+        self.lock().max_per_origin = max;
+    }
+
+    /// Parks `connection` until a request to `origin` takes it. Returns the
+    /// connections evicted to stay within `max_per_origin`, oldest first, for
+    /// the caller to close.
+    pub fn park(&self, origin: &str, connection: C) -> Vec<C> {
+        let mut state = self.lock();
+        let limit = match state.policy {
+            EvictionPolicy::Ttl => None,
+            EvictionPolicy::Lifo | EvictionPolicy::Lru => state.max_per_origin,
+        };
+        let idle = state.idle.entry(origin.to_string()).or_default();
+        idle.push(IdleConnection {
+            connection,
+            parked: Instant::now(),
+        });
+        let excess = limit.map_or(0, |limit| idle.len().saturating_sub(limit));
+        let evicted: Vec<C> = idle.drain(..excess).map(|entry| entry.connection).collect();
+        if excess > 0 {
+            state.origin_stats(origin).evicted += excess as u64;
+        }
+        evicted
+    }
+
+    /// A parked connection to `origin` that has not been idle for too long,
+    /// chosen by the eviction policy.
+    pub fn take(&self, origin: &str) -> Option<C> {
+        let mut state = self.lock();
+        let max_idle = state.max_idle;
+        let policy = state.policy;
+        let idle = state.idle.get_mut(origin)?;
+        let before = idle.len();
+        idle.retain(|entry| entry.parked.elapsed() < max_idle);
+        let expired = before - idle.len();
+        let entry = match policy {
+            EvictionPolicy::Lifo | EvictionPolicy::Ttl => idle.pop(),
+            EvictionPolicy::Lru => (!idle.is_empty()).then(|| idle.remove(0)),
+        };
+        if idle.is_empty() {
+            state.idle.remove(origin);
+        }
+
+        let stats = state.origin_stats(origin);
+        stats.expired += expired as u64;
+        if entry.is_some() {
+            stats.reused += 1;
+        }
+        entry.map(|entry| entry.connection)
+    }
+
+    /// Counts a connection opened to `origin` in `elapsed`, handshakes included.
+    pub fn record_handshake(&self, origin: &str, elapsed: Duration) {
+        let mut state = self.lock();
+        let stats = state.origin_stats(origin);
+        stats.opened += 1;
+        stats.total_handshake_time += elapsed;
+        stats.last_handshake_time = Some(elapsed);
+    }
+
+    /// Awaits `connect` and, when it succeeds and `origin` is pooled, counts
+    /// the connection with [`record_handshake`](Self::record_handshake).
+    pub async fn timed_connect<T, E>(
+        &self,
+        origin: Option<&str>,
+        connect: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let started = Instant::now();
+        let connection = connect.await?;
+        if let Some(origin) = origin {
+            self.record_handshake(origin, started.elapsed());
+        }
+        Ok(connection)
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let state = self.lock();
+        let mut origins = state.stats.clone();
+        for (origin, idle) in &state.idle {
+            origins.entry(origin.clone()).or_default().idle = idle.len();
+        }
+        PoolStats { origins }
+    }
+
+    /// Number of connections parked for `origin`, stale ones included.
+    pub fn idle_count(&self, origin: &str) -> usize {
+        self.lock().idle.get(origin).map_or(0, Vec::len)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().idle.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every parked connection; counters are kept.
+    pub fn clear(&self) {
+        self.lock().idle.clear();
+    }
+
+    /// Removes every parked connection and hands them over, oldest first
+    /// per origin.
+    pub fn drain(&self) -> Vec<C> {
+        self.lock()
+            .idle
+            .drain()
+            .flat_map(|(_, idle)| idle.into_iter().map(|entry| entry.connection))
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState<C>> {
+        // The state stays consistent even if a holder panicked.
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<C: PooledConnection> ConnectionPool<C> {
+    /// [`park`](Self::park)s `connection` and closes the connections it
+    /// evicts with GOAWAY or close_notify rather than just dropping them.
+    pub async fn park_and_close_evicted(&self, origin: &str, connection: C) {
+        for mut evicted in self.park(origin, connection) {
+            evicted.close_gracefully().await;
+        }
+    }
+}
+
+impl<C> PoolState<C> {
+    fn origin_stats(&mut self, origin: &str) -> &mut OriginPoolStats {
+        self.stats.entry(origin.to_string()).or_default()
+    }
+}
+
+#[async_trait(?Send)]
+impl<C: PooledConnection> PoolControl for ConnectionPool<C> {
+    fn stats(&self) -> PoolStats {
+        ConnectionPool::stats(self)
+    }
+
+    fn set_eviction_policy(&self, policy: EvictionPolicy) {
+        ConnectionPool::set_eviction_policy(self, policy)
+    }
+
+    fn set_max_per_origin(&self, max: Option<usize>) {
+        ConnectionPool::set_max_per_origin(self, max)
+    }
+
+    fn set_max_idle(&self, max_idle: Duration) {
+        ConnectionPool::set_max_idle(self, max_idle)
+    }
+
+    fn clear(&self) {
+        ConnectionPool::clear(self)
+    }
+
+    async fn close_idle(&self) -> usize {
+        let connections = self.drain();
+        let closed = connections.len();
+        for mut connection in connections {
+            connection.close_gracefully().await;
+        }
+        closed
+    }
+}
+
+/// Pool key for `target`, e.g. `https://example.com:443`, or the socket
+/// path for `http+unix` targets.
+pub fn origin_key(target: &Target) -> Result<String, ProtocolError> {
+    if let Some(socket) = &target.unix_socket {
+        return Ok(format!("unix:{}", socket.display()));
+    }
+    let host = target
+        .host()
+        .ok_or_else(|| ProtocolError::InvalidTarget("Target missing host".to_string()))?;
+    let port = target
+        .port()
+        .ok_or_else(|| ProtocolError::InvalidTarget("Target missing port".to_string()))?;
+    Ok(format!("{}://{}:{}", target.scheme(), host, port))
+}
+
+/// Pool key for `request`, or `None` when it must get a connection of its
+/// own: an SSRF guard has to vet the addresses it connects to, or
+/// `fresh_dns` asks for the host to be looked up again. A guard that only
+/// carries the client's host scope does not count; every connection in the
+/// pool was dialed under it.
+pub fn pool_key(request: &Request) -> Option<String> {
+    let guarded = request
+        .ssrf_guard
+        .as_ref()
+        .map_or(false, |guard| !guard.is_scope_only());
+    if guarded || request.fresh_dns {
+        return None;
+    }
+    origin_key(&request.target).ok()
+}
@@ -1,21 +1,32 @@
-use crate::stream::TransportStream;
-use crate::types::{ProtocolError, ProxyConfig, ProxyType};
+use crate::dns::DnsCache;
+use crate::h1::codec::parse_status_line;
+use crate::stream::{connect_addrs, TransportStream};
+use crate::types::{
+    is_interim_status, ConnectMechanism, Header, InformationalHandler, InformationalResponse,
+    ProtocolError, ProxyConfig, ProxyType, SocketOptions,
+};
+use crate::utils::{base64_encode, parse_header};
 use rustls::pki_types::ServerName;
 use rustls::{ClientConfig, RootCertStore};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
-use tokio::time::timeout;
+use tokio::time::{timeout_at, Instant};
 use tokio_rustls::TlsConnector;
 use webpki_roots;
 
-/// Establishes a connection through a proxy
+/// Largest CONNECT response head read before giving up on the proxy.
+const MAX_CONNECT_RESPONSE_HEAD: usize = 16 * 1024;
+
+/// Establishes a connection through a proxy. 1xx responses an HTTP proxy
+/// sends before answering CONNECT go to `on_informational`.
 pub async fn connect_through_proxy(
     proxy: &ProxyConfig,
     target_host: &str,
     target_port: u16,
     connect_timeout: Option<Duration>,
+    on_informational: Option<&InformationalHandler>,
 ) -> Result<TransportStream, ProtocolError> {
     let proxy_host = proxy
         .url
@@ -35,6 +46,7 @@ pub async fn connect_through_proxy(
                 target_port,
                 proxy,
                 connect_timeout,
+                on_informational,
             )
             .await
         }
@@ -63,15 +75,53 @@ pub async fn connect_through_proxy(
     }
 }
 
+/// Opens a plain connection to an HTTP proxy for forwarding absolute-form
+/// requests; no CONNECT tunnel is set up.
+pub async fn connect_to_forward_proxy(
+    proxy: &ProxyConfig,
+    connect_timeout: Option<Duration>,
+) -> Result<TransportStream, ProtocolError> {
+    let proxy_host = proxy
+        .url
+        .host_str()
+        .ok_or_else(|| ProtocolError::InvalidTarget("Proxy missing host".to_string()))?;
+    let proxy_port = proxy
+        .url
+        .port_or_known_default()
+        .ok_or_else(|| ProtocolError::InvalidTarget("Proxy missing port".to_string()))?;
+
+    let stream = connect_to_proxy_tcp(proxy_host, proxy_port, connect_timeout).await?;
+    Ok(TransportStream::Tcp(stream))
+}
+
+/// `Proxy-Authorization` value for proxies configured with credentials.
+pub fn proxy_authorization(proxy: &ProxyConfig) -> Option<String> {
+    match (&proxy.username, &proxy.password) {
+        (Some(username), Some(password)) => {
+            let auth_str = format!("{}:{}", username, password);
+            Some(format!("Basic {}", base64_encode(auth_str.as_bytes())))
+        }
+        _ => None,
+    }
+}
+
 /// Establishes a connection through a proxy for HTTPS target
 pub async fn connect_through_proxy_https(
     proxy: &ProxyConfig,
     target_host: &str,
     target_port: u16,
     connect_timeout: Option<Duration>,
+    on_informational: Option<&InformationalHandler>,
 ) -> Result<TransportStream, ProtocolError> {
     // First establish the proxy connection
-    let stream = connect_through_proxy(proxy, target_host, target_port, connect_timeout).await?;
+    let stream = connect_through_proxy(
+        proxy,
+        target_host,
+        target_port,
+        connect_timeout,
+        on_informational,
+    )
+    .await?;
 
     // For HTTP proxies, we need to upgrade to TLS after CONNECT
     match proxy.proxy_type {
@@ -130,6 +180,7 @@ async fn connect_http_proxy(
     target_port: u16,
     proxy: &ProxyConfig,
     connect_timeout: Option<Duration>,
+    on_informational: Option<&InformationalHandler>,
 ) -> Result<TransportStream, ProtocolError> {
     // Connect to proxy
     let mut stream = connect_to_proxy_tcp(proxy_host, proxy_port, connect_timeout).await?;
@@ -143,10 +194,8 @@ async fn connect_http_proxy(
     let mut request_lines = vec![connect_request];
 
     // Add proxy authentication if provided
-    if let (Some(username), Some(password)) = (&proxy.username, &proxy.password) {
-        let auth_str = format!("{}:{}", username, password);
-        let auth_encoded = base64_encode(auth_str.as_bytes());
-        request_lines.push(format!("Proxy-Authorization: Basic {}\r\n", auth_encoded));
+    if let Some(auth) = proxy_authorization(proxy) {
+        request_lines.push(format!("Proxy-Authorization: {}\r\n", auth));
     }
 
     request_lines.push("\r\n".to_string());
@@ -158,23 +207,67 @@ async fn connect_http_proxy(
         .await
         .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to send CONNECT: {}", e)))?;
 
-    // Read response
-    let mut buffer = vec![0; 1024];
-    let n = stream.read(&mut buffer).await.map_err(|e| {
-        ProtocolError::ConnectionFailed(format!("Failed to read CONNECT response: {}", e))
-    })?;
+    // Interim responses may come first; the tunnel is up after a 2xx.
+    loop {
+        let head = read_connect_response_head(&mut stream).await?;
+        let (status, headers) = parse_connect_response(&head)?;
+        if !is_interim_status(status) {
+            return Ok(TransportStream::Tcp(stream));
+        }
+        if let Some(handler) = on_informational {
+            handler.call(&InformationalResponse {
+                status,
+                headers,
+                from_proxy: true,
+            });
+        }
+    }
+}
 
-    let response = String::from_utf8_lossy(&buffer[..n]);
+/// Reads one response head byte-wise, so nothing the target sends through
+/// the tunnel afterwards is consumed.
+async fn read_connect_response_head(stream: &mut TcpStream) -> Result<Vec<u8>, ProtocolError> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") && !head.ends_with(b"\n\n") {
+        if head.len() >= MAX_CONNECT_RESPONSE_HEAD {
+            return Err(ProtocolError::ConnectionFailed(
+                "Proxy CONNECT response head too large".to_string(),
+            ));
+        }
+        let read = stream.read(&mut byte).await.map_err(|e| {
+            ProtocolError::ConnectionFailed(format!("Failed to read CONNECT response: {}", e))
+        })?;
+        if read == 0 {
+            return Err(ProtocolError::ConnectionFailed(
+                "Proxy closed the connection during CONNECT".to_string(),
+            ));
+        }
+        head.push(byte[0]);
+    }
+    Ok(head)
+}
 
-    // Check if CONNECT was successful (200 status code)
-    if !response.starts_with("HTTP/1.1 200") && !response.starts_with("HTTP/1.0 200") {
+/// Status and headers of a CONNECT response head: a 1xx to pass on or the
+/// 2xx that opens the tunnel. Any other status is an error.
+pub(crate) fn parse_connect_response(head: &[u8]) -> Result<(u16, Vec<Header>), ProtocolError> {
+    let text = String::from_utf8_lossy(head);
+    let mut lines = text.lines().skip_while(|line| line.trim().is_empty());
+    let status_line = lines.next().unwrap_or_default();
+    let (status, _) = parse_status_line(status_line).map_err(|_| {
+        ProtocolError::ConnectionFailed(format!("Proxy CONNECT failed: {}", status_line))
+    })?;
+    if !is_interim_status(status) && !(200..300).contains(&status) {
         return Err(ProtocolError::ConnectionFailed(format!(
             "Proxy CONNECT failed: {}",
-            response.lines().next().unwrap_or("Unknown error")
+            status_line
         )));
     }
-
-    Ok(TransportStream::Tcp(stream))
+    let headers: Vec<Header> = lines
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| parse_header(line.trim()))
+        .collect();
+    Ok((status, headers))
 }
 
 /// Connects through SOCKS5 proxy
@@ -390,9 +483,9 @@ async fn connect_socks4_proxy(
         Ok(ip) => ip,
         Err(_) => {
             // Try to resolve hostname
-            match tokio::net::lookup_host(format!("{}:{}", target_host, target_port)).await {
-                Ok(mut addrs) => {
-                    if let Some(addr) = addrs.next() {
+            match DnsCache::global().resolve(target_host, target_port).await {
+                Ok(addrs) => {
+                    if let Some(addr) = addrs.first() {
                         if let std::net::IpAddr::V4(ipv4) = addr.ip() {
                             ipv4
                         } else {
@@ -455,60 +548,30 @@ async fn connect_socks4_proxy(
     Ok(TransportStream::Tcp(stream))
 }
 
-/// Connect to proxy TCP socket with timeout
+/// Connect to proxy TCP socket with timeout, trying every address the
+/// proxy resolves to.
 async fn connect_to_proxy_tcp(
     proxy_host: &str,
     proxy_port: u16,
     connect_timeout: Option<Duration>,
 ) -> Result<TcpStream, ProtocolError> {
-    let connect_future = TcpStream::connect((proxy_host, proxy_port));
-
-    let stream = if let Some(timeout_duration) = connect_timeout {
-        timeout(timeout_duration, connect_future)
+    let deadline = connect_timeout.map(|duration| Instant::now() + duration);
+    let resolve = DnsCache::global().resolve(proxy_host, proxy_port);
+    let addrs = match deadline {
+        Some(deadline) => timeout_at(deadline, resolve)
             .await
-            .map_err(|_| ProtocolError::Timeout)?
-            .map_err(|e| {
-                ProtocolError::ConnectionFailed(format!("Failed to connect to proxy: {}", e))
-            })?
-    } else {
-        connect_future.await.map_err(|e| {
-            ProtocolError::ConnectionFailed(format!("Failed to connect to proxy: {}", e))
-        })?
-    };
-
-    Ok(stream)
-}
-
-/// Simple base64 encoding for HTTP proxy authentication
-fn base64_encode(input: &[u8]) -> String {
-    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    let mut result = String::new();
-
-    for chunk in input.chunks(3) {
-        let mut buf = [0u8; 3];
-        for (i, &b) in chunk.iter().enumerate() {
-            buf[i] = b;
-        }
-
-        let b0 = buf[0] as usize;
-        let b1 = buf[1] as usize;
-        let b2 = buf[2] as usize;
-
-        result.push(CHARS[b0 >> 2] as char);
-        result.push(CHARS[((b0 & 0x03) << 4) | (b1 >> 4)] as char);
-
-        if chunk.len() > 1 {
-            result.push(CHARS[((b1 & 0x0f) << 2) | (b2 >> 6)] as char);
-        } else {
-            result.push('=');
-        }
-
-        if chunk.len() > 2 {
-            result.push(CHARS[b2 & 0x3f] as char);
-        } else {
-            result.push('=');
-        }
+            .map_err(|_| ProtocolError::Timeout(None))?,
+        None => resolve.await,
     }
-
-    result
+    .map_err(|e| ProtocolError::ConnectionFailed(format!("Failed to connect to proxy: {}", e)))?;
+
+    connect_addrs(
+        proxy_host,
+        &addrs,
+        &SocketOptions::default(),
+        ConnectMechanism::Proxy,
+        deadline,
+    )
+    .await
+    .map_err(ProtocolError::from_connect_error)
 }
@@ -0,0 +1,159 @@
+//! Byte accounting per host, with optional quotas.
+//!
+//! Every [`Session`](crate::Session) counts what it sends and receives in a
+//! [`TrafficMeter`]. With a quota set, requests to a host that has used up
+//! its budget fail with [`ProtocolError::QuotaExceeded`] instead of going
+//! out:
+//!
+//! ```ignore
+//! let mut session = H1::new().session();
+//! session.traffic().set_host_quota(Some(50 * 1024 * 1024));
+//! while let Ok(res) = session.get("https://example.com/large").send().await {
+//!     // ...
+//! }
+//! println!("{:?}", session.traffic().host_stats("example.com"));
+//! ```
+//!
+//! Each attempt is charged what its connection actually wrote and read:
+//! header blocks as HPACK or QPACK encoded them, framing and transfer
+//! codings included. HTTP/3 counts whole UDP datagrams; on HTTP/1.1 and
+//! HTTP/2 the bytes are counted inside TLS, so record overhead is left out.
+//! Attempts that fail part-way are charged for what they moved; a
+//! connection that could not be set up is not charged.
+
+use crate::types::{ProtocolError, Request};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrafficStats {
+    /// Attempts charged, retried and failed ones included.
+    pub requests: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+impl TrafficStats {
+    pub fn total(&self) -> u64 {
+        self.bytes_sent + self.bytes_received
+    }
+
+    fn add(&mut self, sent: u64, received: u64) {
+        self.requests += 1;
+        self.bytes_sent += sent;
+        self.bytes_received += received;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TrafficMeter {
+    state: Mutex<MeterState>,
+}
+
+#[derive(Debug, Default)]
+struct MeterState {
+    total: TrafficStats,
+    hosts: BTreeMap<String, TrafficStats>,
+    /// Bytes each host may use, sent and received together.
+    host_quota: Option<u64>,
+    /// Bytes all hosts together may use.
+    total_quota: Option<u64>,
+}
+
+impl TrafficMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Budget per host in bytes, sent and received together; `None` lifts it.
+    pub fn set_host_quota(&self, quota: Option<u64>) {
+        self.lock().host_quota = quota;
+    }
+
+    /// Budget across all hosts in bytes; `None` lifts it.
+    pub fn set_total_quota(&self, quota: Option<u64>) {
+        self.lock().total_quota = quota;
+    }
+
+    /// Fails if a request to `host` would go over a quota already used up.
+    /// The request that crosses a quota is let through, since its size is
+    /// only known once it is answered.
+    pub fn check(&self, host: &str) -> Result<(), ProtocolError> {
+        let state = self.lock();
+        if let Some(limit) = state.total_quota {
+            let used = state.total.total();
+            if used >= limit {
+                return Err(ProtocolError::QuotaExceeded {
+                    host: None,
+                    limit,
+                    used,
+                });
+            }
+        }
+        if let Some(limit) = state.host_quota {
+            let used = state
+                .hosts
+                .get(&host.to_ascii_lowercase())
+                .map_or(0, TrafficStats::total);
+            if used >= limit {
+                return Err(ProtocolError::QuotaExceeded {
+                    host: Some(host.to_string()),
+                    limit,
+                    used,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts one attempt at an exchange with `host`.
+    pub fn record(&self, host: &str, sent: u64, received: u64) {
+        let mut state = self.lock();
+        state.total.add(sent, received);
+        state
+            .hosts
+            .entry(host.to_ascii_lowercase())
+            .or_default()
+            .add(sent, received);
+    }
+
+    /// Totals across all hosts.
+    pub fn stats(&self) -> TrafficStats {
+        self.lock().total
+    }
+
+    pub fn host_stats(&self, host: &str) -> TrafficStats {
+        self.lock()
+            .hosts
+            .get(&host.to_ascii_lowercase())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Every host contacted so far, by name.
+    pub fn hosts(&self) -> BTreeMap<String, TrafficStats> {
+        self.lock().hosts.clone()
+    }
+
+    /// Zeroes the counters; quotas stay.
+    pub fn reset(&self) {
+        let mut state = self.lock();
+        state.total = TrafficStats::default();
+        state.hosts.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MeterState> {
+        // The counters stay consistent even if a holder panicked.
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Request {
+    /// Charges wire bytes moved for this request to its host in
+    /// [`traffic`](Request::traffic), if set.
+    pub(crate) fn charge_traffic(&self, sent: u64, received: u64) {
+        if let Some(meter) = &self.traffic {
+            meter.record(self.target.host().unwrap_or_default(), sent, received);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Custom URL schemes mapped onto the transports the crate speaks.
+//!
+//! [`parse_target`](crate::utils::parse_target) looks every scheme up in
+//! [`SchemeRegistry::global`] first. A registered scheme is rewritten to
+//! `http` or `https`, picks up its default port, and tags the target with
+//! the protocols it asks for; requests built from it also get the scheme's
+//! proxies:
+//!
+//! ```ignore
+//! // Cleartext HTTP/2 with prior knowledge; `h2c` is registered out of the box.
+//! let res = Auto::new().send_request(Request::new("h2c://localhost:8080/", "GET")?).await?;
+//!
+//! // Plain HTTP through Tor.
+//! SchemeRegistry::global().register(
+//!     "socks-http",
+//!     SchemeSpec::plain().proxies(ProxySettings::new().socks("socks5h://127.0.0.1:9050")?),
+//! );
+//! let request = Request::new("socks-http://example.onion/", "GET")?;
+//!
+//! // HTTP/1.1 over a Unix domain socket, its path percent-encoded as the host.
+//! let res = H1::new().send_request(Request::new("http+unix://%2Fvar%2Frun%2Fdocker.sock/info", "GET")?).await?;
+//! ```
+
+use crate::types::{HttpProtocol, ProxySettings};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+static GLOBAL: OnceLock<SchemeRegistry> = OnceLock::new();
+
+/// How a registered scheme connects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemeTransport {
+    /// Cleartext TCP, like `http`.
+    Plain,
+    /// TLS over TCP, like `https`.
+    Tls,
+    /// Cleartext over the Unix domain socket whose percent-encoded path is
+    /// the target's host, like `http+unix`. The request goes out with
+    /// `localhost` as its host.
+    Unix,
+}
+
+impl SchemeTransport {
+    /// The built-in scheme the transport stands for.
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            SchemeTransport::Plain | SchemeTransport::Unix => "http",
+            SchemeTransport::Tls => "https",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SchemeSpec {
+    pub transport: SchemeTransport,
+    /// Port for targets that name none; `None` keeps 80 or 443.
+    pub default_port: Option<u16>,
+    /// Protocols the scheme asks for, added to the target's `protocols`.
+    pub protocols: Vec<HttpProtocol>,
+    /// Proxies for requests to the scheme that set none of their own.
+    pub proxies: Option<ProxySettings>,
+}
+
+impl SchemeSpec {
+    pub fn new(transport: SchemeTransport) -> Self {
+        Self {
+            transport,
+            default_port: None,
+            protocols: Vec::new(),
+            proxies: None,
+        }
+    }
+
+    pub fn plain() -> Self {
+        Self::new(SchemeTransport::Plain)
+    }
+
+    pub fn tls() -> Self {
+        Self::new(SchemeTransport::Tls)
+    }
+
+    pub fn unix() -> Self {
+        Self::new(SchemeTransport::Unix)
+    }
+
+    pub fn default_port(mut self, port: u16) -> Self {
+        self.default_port = Some(port);
+        self
+    }
+
+    pub fn protocol(mut self, protocol: HttpProtocol) -> Self {
+        if !self.protocols.contains(&protocol) {
+            self.protocols.push(protocol);
+        }
+        self
+    }
+
+    pub fn proxies(mut self, proxies: ProxySettings) -> Self {
+        self.proxies = Some(proxies);
+        self
+    }
+}
+
+/// Schemes by lowercase name.
+#[derive(Debug)]
+pub struct SchemeRegistry {
+    schemes: Mutex<HashMap<String, SchemeSpec>>,
+}
+
+impl Default for SchemeRegistry {
+    /// Knows `h2c`, cleartext HTTP/2 with prior knowledge, and `http+unix`,
+    /// HTTP over a Unix domain socket.
+    fn default() -> Self {
+        let registry = Self {
+            schemes: Mutex::new(HashMap::new()),
+        };
+        registry.register("h2c", SchemeSpec::plain().protocol(HttpProtocol::H2C));
+        registry.register("http+unix", SchemeSpec::unix());
+        registry
+    }
+}
+
+impl SchemeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The registry `parse_target` consults.
+    pub fn global() -> &'static SchemeRegistry {
+        GLOBAL.get_or_init(SchemeRegistry::default)
+    }
+
+    /// Maps `scheme` to `spec`, replacing an earlier registration. `http`
+    /// and `https` cannot be remapped.
+    pub fn register(&self, scheme: &str, spec: SchemeSpec) -> bool {
+        let scheme = scheme.to_ascii_lowercase();
+        if scheme == "http" || scheme == "https" {
+            return false;
+        }
+        self.lock().insert(scheme, spec);
+        true
+    }
+
+    pub fn unregister(&self, scheme: &str) -> Option<SchemeSpec> {
+        self.lock().remove(&scheme.to_ascii_lowercase())
+    }
+
+    pub fn get(&self, scheme: &str) -> Option<SchemeSpec> {
+        self.lock().get(&scheme.to_ascii_lowercase()).cloned()
+    }
+
+    /// `target` with a registered scheme swapped for its transport's, and
+    /// the scheme's spec; `None` for schemes that are not registered.
+    pub fn rewrite(&self, target: &str) -> Option<(String, SchemeSpec)> {
+        let (scheme, rest) = target.split_once("://")?;
+        let spec = self.get(scheme)?;
+        Some((format!("{}://{}", spec.transport.scheme(), rest), spec))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, SchemeSpec>> {
+        // The map stays consistent even if a holder panicked.
+        self.schemes.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
@@ -1,69 +1,22 @@
 use crate::h1::protocol::H1;
 use crate::h2::protocol::H2;
 use crate::h3::protocol::H3;
-use crate::parse_header;
+use crate::oauth::OAuthClient;
+use crate::pacing::{HostThrottle, Pacer, PacingPolicy};
+use crate::pool::{EvictionPolicy, PoolStats};
+use crate::quota::TrafficMeter;
+use crate::shutdown::{ShutdownHandle, ShutdownReport};
+use crate::types::redirect::{RedirectChain, RedirectHop};
 use crate::types::{
-    ClientTimeouts, Header, Protocol, ProtocolError, ProxySettings, Request, RequestBuilder,
-    RequestBuilderOps, Response,
+    ClientConfig, ClientTimeouts, CookieStore, EndStreamMode, Header, HeaderProfile, HostScope,
+    InformationalResponse, Protocol, ProtocolError, ProxySettings, RedirectPolicy, Request,
+    RequestBuilder, RequestBuilderOps, Response, UserAgent,
 };
+use crate::utils::{apply_redirect, parse_header, AUTHORIZATION_HEADER};
 use serde_json::Value;
 use std::collections::BTreeMap;
-use std::fmt;
-
-#[derive(Debug, Clone, Default)]
-pub struct CookieStore {
-    entries: BTreeMap<String, String>,
-}
-
-impl CookieStore {
-    fn apply_to_request(&self, request: &mut Request) {
-        if self.entries.is_empty() {
-            return;
-        }
-
-        let mut combined: BTreeMap<String, String> = self.entries.clone();
-        for (name, value) in &request.cookies {
-            combined.insert(name.clone(), value.clone());
-        }
-
-        request.cookies = combined.into_iter().collect();
-    }
-
-    fn update_from_response(&mut self, response: &Response) {
-        for (name, value) in &response.cookies {
-            if value.is_empty() {
-                self.entries.remove(name);
-            } else {
-                self.entries.insert(name.clone(), value.clone());
-            }
-        }
-    }
-
-    pub fn set_cookie(&mut self, name: impl Into<String>, value: impl Into<String>) {
-        self.entries.insert(name.into(), value.into());
-    }
-
-    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
-        self.entries.iter()
-    }
-}
-
-impl fmt::Display for CookieStore {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut first = true;
-        for (name, value) in &self.entries {
-            if !first {
-                write!(f, "; ")?;
-            }
-            write!(f, "{}={}", name, value)?;
-            first = false;
-        }
-        if first {
-            write!(f, "<empty cookies>")?;
-        }
-        Ok(())
-    }
-}
+use std::sync::Arc;
+use std::time::Duration;
 
 fn apply_default_headers(defaults: &[Header], request: &mut Request) {
     for header in defaults {
@@ -84,6 +37,13 @@ where
     client: P,
     default_headers: Vec<Header>,
     pub cookies: CookieStore,
+    oauth: Option<OAuthClient>,
+    user_agent: UserAgent,
+    header_profile: Option<HeaderProfile>,
+    host_scope: Option<HostScope>,
+    pacer: Option<Arc<Pacer>>,
+    shutdown: ShutdownHandle,
+    traffic: Arc<TrafficMeter>,
 }
 
 impl<P> Session<P>
@@ -95,6 +55,13 @@ where
             client,
             default_headers: Vec::new(),
             cookies: CookieStore::default(),
+            oauth: None,
+            user_agent: UserAgent::Inherit,
+            header_profile: None,
+            host_scope: None,
+            pacer: None,
+            shutdown: ShutdownHandle::new(),
+            traffic: Arc::new(TrafficMeter::new()),
         }
     }
 
@@ -116,6 +83,134 @@ where
         self.cookies.set_cookie(name, value);
     }
 
+    /// Authenticates every request with a Bearer token from `oauth`. Requests
+    /// that already carry an `Authorization` header are sent unchanged.
+    pub fn oauth(&mut self, oauth: OAuthClient) {
+        self.oauth = Some(oauth);
+    }
+
+    pub fn oauth_client(&mut self) -> Option<&mut OAuthClient> {
+        self.oauth.as_mut()
+    }
+
+    /// Defaults of the underlying client; session settings take precedence.
+    pub fn config(&self) -> Option<&ClientConfig> {
+        self.client.config()
+    }
+
+    /// `User-Agent` for requests that do not pick their own; takes precedence
+    /// over the client's setting.
+    pub fn user_agent(&mut self, user_agent: UserAgent) {
+        self.user_agent = user_agent;
+    }
+
+    /// Header profile for requests that do not choose one.
+    pub fn header_profile(&mut self, profile: HeaderProfile) {
+        self.header_profile = Some(profile);
+    }
+
+    /// Hosts the session may contact, on top of the client's scope. Every
+    /// redirect hop is checked before it is sent.
+    pub fn host_scope(&mut self, scope: HostScope) {
+        self.host_scope = Some(scope);
+    }
+
+    /// Connects to `origin` ahead of the first request there and parks the
+    /// connection in the client's pool.
+    pub async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        if let Some(scope) = &self.host_scope {
+            scope.check(&Request::new(origin, "GET")?.target).await?;
+        }
+        self.client.preconnect(origin).await
+    }
+
+    /// Backs off per host after throttling responses and resends the
+    /// throttled request, see [`PacingPolicy`].
+    pub fn pacing(&mut self, policy: PacingPolicy) {
+        self.pacer = Some(Arc::new(Pacer::new(policy)));
+    }
+
+    /// Paces this session with `pacer`, e.g. one shared with other sessions
+    /// crawling the same hosts.
+    pub fn pacer(&mut self, pacer: Arc<Pacer>) {
+        self.pacer = Some(pacer);
+    }
+
+    /// Hosts currently backed off, empty without pacing.
+    pub fn throttle_state(&self) -> BTreeMap<String, HostThrottle> {
+        self.pacer
+            .as_ref()
+            .map(|pacer| pacer.state())
+            .unwrap_or_default()
+    }
+
+    /// Bytes sent and received per host, and the quotas on them. Every
+    /// redirect hop counts against its own host.
+    pub fn traffic(&self) -> &TrafficMeter {
+        &self.traffic
+    }
+
+    /// Counts this session's traffic in `meter`, e.g. to put several
+    /// sessions under one quota.
+    pub fn traffic_meter(&mut self, meter: Arc<TrafficMeter>) {
+        self.traffic = meter;
+    }
+
+    /// Reuse counts, handshake times and idle connections per origin, summed
+    /// over the client's pools.
+    pub fn pool_stats(&self) -> PoolStats {
+        let mut stats = PoolStats::default();
+        for pool in self.client.pools() {
+            stats.merge(&pool.stats());
+        }
+        stats
+    }
+
+    /// Which parked connection requests take, and which ones are closed first.
+    pub fn eviction_policy(&mut self, policy: EvictionPolicy) {
+        for pool in self.client.pools() {
+            pool.set_eviction_policy(policy);
+        }
+    }
+
+    /// Most connections kept parked per origin; `None` lifts the limit.
+    pub fn max_idle_per_origin(&mut self, max: Option<usize>) {
+        for pool in self.client.pools() {
+            pool.set_max_per_origin(max);
+        }
+    }
+
+    /// How long a parked connection may sit unused before it is closed.
+    pub fn pool_idle_timeout(&mut self, max_idle: Duration) {
+        for pool in self.client.pools() {
+            pool.set_max_idle(max_idle);
+        }
+    }
+
+    /// Closes every parked connection.
+    pub fn clear_pool(&self) {
+        for pool in self.client.pools() {
+            pool.clear();
+        }
+    }
+
+    /// Tracks this session's requests; shutting it down from another task
+    /// stops the session from sending.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Stops taking requests, gives running ones up to `grace` to finish and
+    /// aborts the rest, then closes parked connections with GOAWAY or TLS
+    /// close_notify. Requests sent afterwards fail.
+    pub async fn shutdown(&self, grace: Duration) -> ShutdownReport {
+        let mut report = self.shutdown.shutdown(grace).await;
+        for pool in self.client.pools() {
+            report.closed_connections += pool.close_idle().await;
+        }
+        report
+    }
+
     pub fn request<'a>(&'a mut self, method: &str, url: &str) -> SessionRequestBuilder<'a, P> {
         SessionRequestBuilder::new(self, method, url)
     }
@@ -148,20 +243,124 @@ where
         self.request("OPTIONS", url)
     }
 
-    pub async fn send(&mut self, mut request: Request) -> Result<Response, ProtocolError> {
+    pub async fn send(&mut self, request: Request) -> Result<Response, ProtocolError> {
+        let shutdown = self.shutdown.clone();
+        shutdown.run(self.send_tracked(request)).await
+    }
+
+    async fn send_tracked(&mut self, mut request: Request) -> Result<Response, ProtocolError> {
         self.prepare_request(&mut request);
-        let response = self.client.send_request(request).await?;
-        self.finalize_response(&response);
+
+        let retry = self.oauth.as_ref().map(|_| request.clone());
+        let authorized = self.authorize(&mut request).await?;
+        let mut response = self.follow(request).await?;
+
+        // The token may have been revoked before it expired: fetch a new one
+        // and try once more.
+        if let (401, true, Some(mut request)) = (response.status, authorized, retry) {
+            if let Some(oauth) = self.oauth.as_mut() {
+                oauth.invalidate();
+            }
+            self.authorize(&mut request).await?;
+            response = self.follow(request).await?;
+        }
+
         Ok(response)
     }
 
-    fn prepare_request(&self, request: &mut Request) {
-        apply_default_headers(&self.default_headers, request);
-        self.cookies.apply_to_request(request);
+    /// Follows redirects here rather than in the client, so every hop
+    /// stores the cookies it is sent and gets the ones for its own URL.
+    async fn follow(&mut self, mut request: Request) -> Result<Response, ProtocolError> {
+        if let Some(config) = self.client.config() {
+            config.apply(&mut request);
+        }
+        if let Some(scope) = &self.host_scope {
+            scope.guard(&mut request);
+        }
+        let mut chain = RedirectChain::new();
+        loop {
+            if let Some(scope) = &self.host_scope {
+                scope.check(&request.target).await?;
+            }
+            let mut hop = request.clone();
+            hop.follow_redirects = false;
+            self.cookies.apply_to_request(&mut hop);
+            let response = self.dispatch(hop).await?;
+            self.cookies.store_response(&request.target.url, &response);
+
+            let sent = RedirectHop {
+                method: request.method.clone(),
+                url: request.target.url.clone(),
+                status: response.status,
+            };
+            if !apply_redirect(&mut request, &response)? {
+                return Ok(response);
+            }
+            chain.follow(sent, &request)?;
+        }
+    }
+
+    /// Sends `request` within the traffic quotas, waiting out and retrying
+    /// throttling responses when pacing is on.
+    async fn dispatch(&self, mut request: Request) -> Result<Response, ProtocolError> {
+        let host = request.target.host().unwrap_or_default().to_string();
+        let mut retries = 0;
+        loop {
+            if let Some(pacer) = &self.pacer {
+                pacer.wait(&host).await;
+            }
+            self.traffic.check(&host)?;
+            request.traffic = Some(Arc::clone(&self.traffic));
+            let retry = self
+                .pacer
+                .as_ref()
+                .filter(|pacer| retries < pacer.policy().max_retries)
+                .filter(|pacer| pacer.policy().retries(&request.method))
+                .map(|_| request.clone());
+            let response = self.client.send_request(request).await?;
+
+            let backoff = self
+                .pacer
+                .as_ref()
+                .and_then(|pacer| pacer.observe(&host, &response));
+            match (backoff, retry) {
+                (Some(_), Some(next)) => {
+                    request = next;
+                    retries += 1;
+                }
+                _ => return Ok(response),
+            }
+        }
     }
 
-    fn finalize_response(&mut self, response: &Response) {
-        self.cookies.update_from_response(response);
+    /// Adds the OAuth Bearer header; returns whether it did.
+    async fn authorize(&mut self, request: &mut Request) -> Result<bool, ProtocolError> {
+        let oauth = match self.oauth.as_mut() {
+            Some(oauth) => oauth,
+            None => return Ok(false),
+        };
+        if request
+            .headers
+            .iter()
+            .any(|h| h.name.eq_ignore_ascii_case(AUTHORIZATION_HEADER))
+        {
+            return Ok(false);
+        }
+
+        let token = oauth.access_token(&self.client).await?;
+        request.headers.push(Header::new(
+            AUTHORIZATION_HEADER.to_string(),
+            token.authorization(),
+        ));
+        Ok(true)
+    }
+
+    fn prepare_request(&self, request: &mut Request) {
+        apply_default_headers(&self.default_headers, request);
+        request.inherit_user_agent(&self.user_agent);
+        if request.header_profile.is_none() {
+            request.header_profile = self.header_profile;
+        }
     }
 
     pub fn client(&self) -> &P {
@@ -285,6 +484,11 @@ where
         self.allow_redirects(allow)
     }
 
+    pub fn redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        RequestBuilderOps::redirect_policy(&mut self, policy);
+        self
+    }
+
     pub fn timeout(mut self, timeout: ClientTimeouts) -> Self {
         RequestBuilderOps::timeout(&mut self, timeout);
         self
@@ -305,6 +509,49 @@ where
         self
     }
 
+    pub fn user_agent(mut self, user_agent: UserAgent) -> Self {
+        RequestBuilderOps::user_agent(&mut self, user_agent);
+        self
+    }
+
+    pub fn header_profile(mut self, profile: HeaderProfile) -> Self {
+        RequestBuilderOps::header_profile(&mut self, profile);
+        self
+    }
+
+    pub fn auto_headers(mut self, enabled: bool) -> Self {
+        RequestBuilderOps::auto_headers(&mut self, enabled);
+        self
+    }
+
+    pub fn end_stream_mode(mut self, mode: EndStreamMode) -> Self {
+        RequestBuilderOps::end_stream_mode(&mut self, mode);
+        self
+    }
+
+    pub fn on_informational<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&InformationalResponse) + Send + Sync + 'static,
+    {
+        RequestBuilderOps::on_informational(&mut self, handler);
+        self
+    }
+
+    pub fn verify_digest(mut self, enabled: bool) -> Self {
+        RequestBuilderOps::verify_digest(&mut self, enabled);
+        self
+    }
+
+    pub fn allow_truncated_body(mut self, enabled: bool) -> Self {
+        RequestBuilderOps::allow_truncated_body(&mut self, enabled);
+        self
+    }
+
+    pub fn fresh_dns(mut self) -> Self {
+        RequestBuilderOps::fresh_dns(&mut self);
+        self
+    }
+
     pub async fn send(self) -> Result<Response, ProtocolError> {
         let SessionRequestBuilder { session, builder } = self;
         let request = builder.build()?;
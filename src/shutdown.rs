@@ -0,0 +1,159 @@
+//! Graceful shutdown: stop taking requests, let the running ones finish for a
+//! while, then abort the rest.
+//!
+//! Every request a [`Session`](crate::Session) sends is tracked by its
+//! [`ShutdownHandle`]. Handles are cheap to clone and can be triggered from
+//! anywhere, e.g. a signal handler, while the session is busy:
+//!
+//! ```ignore
+//! let mut session = H2::new().session();
+//! let handle = session.shutdown_handle();
+//! tokio::spawn(async move {
+//!     tokio::time::sleep(Duration::from_secs(600)).await;
+//!     handle.shutdown(Duration::from_secs(5)).await;
+//! });
+//! while let Ok(res) = session.get("https://example.com/feed").send().await {
+//!     // ...
+//! }
+//! // Sends GOAWAY on parked HTTP/2 and HTTP/3 connections and closes the rest.
+//! session.shutdown(Duration::ZERO).await;
+//! ```
+
+use crate::types::ProtocolError;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// What a shutdown found and did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Requests that finished within the grace period.
+    pub completed: usize,
+    /// Requests still running when it ran out, now failed.
+    pub aborted: usize,
+    /// Parked connections closed.
+    pub closed_connections: usize,
+}
+
+/// How long an aborted request gets to notice and fail before the report
+/// counts it as still running.
+const ABORT_SETTLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+struct ShutdownState {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    /// Requests `run` has failed because of an abort.
+    aborted: AtomicUsize,
+    idle: Notify,
+    abort: Notify,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownHandle {
+    state: Arc<ShutdownState>,
+}
+
+/// Marks one request as running until dropped.
+#[derive(Debug)]
+pub struct InFlight {
+    state: Arc<ShutdownState>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.state.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.state.idle.notify_waiters();
+        }
+    }
+}
+
+impl ShutdownHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.state.closed.load(Ordering::Acquire)
+    }
+
+    /// Requests running right now.
+    pub fn in_flight(&self) -> usize {
+        self.state.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Registers a request, or fails once shutdown has begun.
+    pub fn begin(&self) -> Result<InFlight, ProtocolError> {
+        self.state.in_flight.fetch_add(1, Ordering::AcqRel);
+        let in_flight = InFlight {
+            state: Arc::clone(&self.state),
+        };
+        // Checked after counting, so a concurrent shutdown either sees this
+        // request or this request sees the shutdown.
+        if self.is_shutting_down() {
+            return Err(shutting_down());
+        }
+        Ok(in_flight)
+    }
+
+    /// Runs `request` as a tracked request that fails if the shutdown's
+    /// grace period runs out first. Dropping it drops its connection.
+    pub async fn run<T>(
+        &self,
+        request: impl Future<Output = Result<T, ProtocolError>>,
+    ) -> Result<T, ProtocolError> {
+        let _in_flight = self.begin()?;
+        let abort = self.state.abort.notified();
+        tokio::pin!(abort);
+        abort.as_mut().enable();
+        tokio::select! {
+            result = request => result,
+            _ = abort => {
+                self.state.aborted.fetch_add(1, Ordering::AcqRel);
+                Err(aborted())
+            }
+        }
+    }
+
+    /// Stops new requests, waits up to `grace` for running ones, then aborts
+    /// those still running and waits for them to fail before counting them.
+    /// Requests that do not fail within a second, e.g. ones registered with
+    /// `begin` rather than `run`, are counted as aborted too.
+    pub async fn shutdown(&self, grace: Duration) -> ShutdownReport {
+        self.state.closed.store(true, Ordering::Release);
+        let running = self.in_flight();
+        let mut aborted = 0;
+        if !self.wait_idle(grace).await {
+            let before = self.state.aborted.load(Ordering::Acquire);
+            self.state.abort.notify_waiters();
+            self.wait_idle(ABORT_SETTLE_TIMEOUT).await;
+            aborted = self.state.aborted.load(Ordering::Acquire) - before + self.in_flight();
+        }
+        ShutdownReport {
+            completed: running.saturating_sub(aborted),
+            aborted,
+            closed_connections: 0,
+        }
+    }
+
+    /// Waits up to `limit` for running requests to finish; true if they did.
+    pub async fn wait_idle(&self, limit: Duration) -> bool {
+        let idle = self.state.idle.notified();
+        tokio::pin!(idle);
+        idle.as_mut().enable();
+        if self.in_flight() == 0 {
+            return true;
+        }
+        tokio::time::timeout(limit, idle).await.is_ok()
+    }
+}
+
+fn shutting_down() -> ProtocolError {
+    ProtocolError::RequestFailed("Session is shutting down".to_string())
+}
+
+fn aborted() -> ProtocolError {
+    ProtocolError::RequestFailed("Request aborted by shutdown".to_string())
+}
@@ -0,0 +1,658 @@
+//! HTTP Message Signatures (RFC 9421): request signing and response verification.
+//!
+//! A [`RequestSigner`] attached to a [`Request`] runs once the final header
+//! list is known (after `Host`, `Content-Length`, cookies, ...) and before the
+//! request is encoded for the wire, so signatures cover what is actually sent.
+//! [`SignatureVerifier`] checks the `Signature`/`Signature-Input` pair a
+//! server attached to its response.
+
+use crate::types::{Header, ProtocolError, Request, Response};
+use crate::utils::{base64_decode, base64_encode, base64url_encode};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{self, UnparsedPublicKey, VerificationAlgorithm};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const SIGNATURE_HEADER: &str = "signature";
+pub const SIGNATURE_INPUT_HEADER: &str = "signature-input";
+
+/// Hook that adds authentication headers to an outgoing request.
+pub trait RequestSigner: fmt::Debug + Send + Sync {
+    /// Returns the headers to append. `headers` is the final regular header
+    /// list; pseudo-headers are derived from `request`.
+    fn sign(&self, request: &Request, headers: &[Header]) -> Result<Vec<Header>, ProtocolError>;
+}
+
+/// Covered component identifier with its raw parameters (e.g. `;req`).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ComponentId {
+    name: String,
+    params: String,
+}
+
+impl ComponentId {
+    pub(crate) fn plain(name: &str) -> Self {
+        Self {
+            name: name.to_ascii_lowercase(),
+            params: String::new(),
+        }
+    }
+
+    fn serialize(&self) -> String {
+        format!("\"{}\"{}", self.name, self.params)
+    }
+
+    /// `;req`: the component is taken from the request a response answers.
+    fn from_request(&self) -> bool {
+        self.params.split(';').any(|p| p.trim() == "req")
+    }
+}
+
+/// Message a signature base is computed over.
+pub(crate) enum SignedMessage<'a> {
+    Request {
+        request: &'a Request,
+        headers: &'a [Header],
+    },
+    Response {
+        status: u16,
+        headers: &'a [Header],
+        request: Option<(&'a Request, &'a [Header])>,
+    },
+}
+
+impl SignedMessage<'_> {
+    /// Canonical value of one covered component (RFC 9421 Section 2).
+    fn component_value(&self, id: &ComponentId) -> Result<String, ProtocolError> {
+        let missing = || {
+            ProtocolError::SignatureError(format!(
+                "Component {} is not present in the message",
+                id.serialize()
+            ))
+        };
+
+        let (request, headers) = match self {
+            SignedMessage::Request { .. } if id.from_request() => return Err(missing()),
+            SignedMessage::Request { request, headers } => (Some(*request), *headers),
+            SignedMessage::Response { request, .. } if id.from_request() => {
+                let (request, headers) = (*request).ok_or_else(missing)?;
+                return SignedMessage::Request { request, headers }
+                    .component_value(&ComponentId::plain(&id.name));
+            }
+            SignedMessage::Response {
+                status, headers, ..
+            } => {
+                if id.name == "@status" {
+                    return Ok(status.to_string());
+                }
+                (None, *headers)
+            }
+        };
+
+        if !id.name.starts_with('@') {
+            let values: Vec<&str> = headers
+                .iter()
+                .filter(|h| h.name.eq_ignore_ascii_case(&id.name))
+                .map(|h| h.value.as_deref().unwrap_or("").trim())
+                .collect();
+            if values.is_empty() {
+                return Err(missing());
+            }
+            return Ok(values.join(", "));
+        }
+
+        let request = request.ok_or_else(missing)?;
+        let target = &request.target;
+        let path = request.path();
+        let (path_only, query) = match path.split_once('?') {
+            Some((path_only, query)) => (path_only.to_string(), query.to_string()),
+            None => (path.clone(), String::new()),
+        };
+        let authority = target.authority().ok_or_else(missing)?.to_ascii_lowercase();
+
+        match id.name.as_str() {
+            "@method" => Ok(request.method.clone()),
+            "@authority" => Ok(authority),
+            "@scheme" => Ok(target.scheme().to_ascii_lowercase()),
+            "@target-uri" => Ok(format!(
+                "{}://{}{}",
+                target.scheme().to_ascii_lowercase(),
+                authority,
+                path
+            )),
+            "@request-target" => Ok(path),
+            "@path" => Ok(path_only),
+            "@query" => Ok(format!("?{}", query)),
+            _ => Err(missing()),
+        }
+    }
+
+    /// Builds the signature base for `components` with the serialized
+    /// `@signature-params` value (RFC 9421 Section 2.5).
+    pub(crate) fn signature_base(
+        &self,
+        components: &[ComponentId],
+        params: &str,
+    ) -> Result<String, ProtocolError> {
+        let mut base = String::new();
+        for component in components {
+            let value = self.component_value(component)?;
+            base.push_str(&format!("{}: {}\n", component.serialize(), value));
+        }
+        base.push_str(&format!("\"@signature-params\": {}", params));
+        Ok(base)
+    }
+}
+
+/// Serializes the inner list and parameters of a `Signature-Input` member.
+pub(crate) fn serialize_signature_params(
+    components: &[ComponentId],
+    params: &[(&str, SignatureParam)],
+) -> String {
+    let list = components
+        .iter()
+        .map(ComponentId::serialize)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut out = format!("({})", list);
+    for (key, value) in params {
+        match value {
+            SignatureParam::Integer(v) => out.push_str(&format!(";{}={}", key, v)),
+            SignatureParam::String(v) => out.push_str(&format!(";{}=\"{}\"", key, v)),
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SignatureParam {
+    Integer(u64),
+    String(String),
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// HMAC-SHA256 signer producing `Signature-Input` and `Signature` headers.
+///
+/// Covers `@method`, `@authority` and `@path` by default. `created` is always
+/// included; `expires` and a random `nonce` can be added for replay protection.
+#[derive(Clone)]
+pub struct HmacSigner {
+    key_id: String,
+    key: Vec<u8>,
+    label: String,
+    components: Vec<ComponentId>,
+    expires_in: Option<Duration>,
+    nonce: bool,
+    tag: Option<String>,
+}
+
+impl fmt::Debug for HmacSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacSigner")
+            .field("key_id", &self.key_id)
+            .field("label", &self.label)
+            .field("components", &self.components)
+            .field("expires_in", &self.expires_in)
+            .field("nonce", &self.nonce)
+            .field("tag", &self.tag)
+            .finish()
+    }
+}
+
+impl HmacSigner {
+    pub fn new(key_id: impl Into<String>, key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key_id: key_id.into(),
+            key: key.as_ref().to_vec(),
+            label: "sig1".to_string(),
+            components: ["@method", "@authority", "@path"]
+                .iter()
+                .map(|name| ComponentId::plain(name))
+                .collect(),
+            expires_in: None,
+            nonce: false,
+            tag: None,
+        }
+    }
+
+    /// Replaces the covered components, e.g. `["@method", "@path", "content-digest"]`.
+    pub fn components<I, S>(mut self, components: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.components = components
+            .into_iter()
+            .map(|name| ComponentId::plain(name.as_ref()))
+            .collect();
+        self
+    }
+
+    /// Dictionary key the signature is stored under (`sig1` by default).
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    pub fn expires_in(mut self, lifetime: Duration) -> Self {
+        self.expires_in = Some(lifetime);
+        self
+    }
+
+    pub fn nonce(mut self, enabled: bool) -> Self {
+        self.nonce = enabled;
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    fn params(&self) -> Result<Vec<(&'static str, SignatureParam)>, ProtocolError> {
+        let created = unix_now();
+        let mut params = vec![("created", SignatureParam::Integer(created))];
+        if let Some(lifetime) = self.expires_in {
+            params.push((
+                "expires",
+                SignatureParam::Integer(created + lifetime.as_secs()),
+            ));
+        }
+        if self.nonce {
+            let mut bytes = [0u8; 16];
+            SystemRandom::new().fill(&mut bytes).map_err(|_| {
+                ProtocolError::SignatureError("Failed to generate signature nonce".to_string())
+            })?;
+            params.push(("nonce", SignatureParam::String(base64url_encode(&bytes))));
+        }
+        params.push(("alg", SignatureParam::String("hmac-sha256".to_string())));
+        params.push(("keyid", SignatureParam::String(self.key_id.clone())));
+        if let Some(tag) = &self.tag {
+            params.push(("tag", SignatureParam::String(tag.clone())));
+        }
+        Ok(params)
+    }
+}
+
+impl RequestSigner for HmacSigner {
+    fn sign(&self, request: &Request, headers: &[Header]) -> Result<Vec<Header>, ProtocolError> {
+        let params = serialize_signature_params(&self.components, &self.params()?);
+        let base = SignedMessage::Request { request, headers }
+            .signature_base(&self.components, &params)?;
+
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &self.key);
+        let signature = hmac::sign(&key, base.as_bytes());
+
+        Ok(vec![
+            Header::new(
+                SIGNATURE_INPUT_HEADER.to_string(),
+                format!("{}={}", self.label, params),
+            ),
+            Header::new(
+                SIGNATURE_HEADER.to_string(),
+                format!("{}=:{}:", self.label, base64_encode(signature.as_ref())),
+            ),
+        ])
+    }
+}
+
+/// Key a response signature is checked against, tagged with its RFC 9421 algorithm.
+///
+/// Public keys use the encodings `ring` expects: the raw 32 bytes for Ed25519,
+/// uncompressed SEC1 points for ECDSA and PKCS#1 `RSAPublicKey` DER for RSA.
+#[derive(Clone)]
+pub enum VerificationKey {
+    HmacSha256(Vec<u8>),
+    Ed25519(Vec<u8>),
+    EcdsaP256Sha256(Vec<u8>),
+    EcdsaP384Sha384(Vec<u8>),
+    RsaPssSha512(Vec<u8>),
+    RsaV15Sha256(Vec<u8>),
+}
+
+impl fmt::Debug for VerificationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VerificationKey({})", self.algorithm())
+    }
+}
+
+impl VerificationKey {
+    /// Value of the `alg` signature parameter for this key.
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            VerificationKey::HmacSha256(_) => "hmac-sha256",
+            VerificationKey::Ed25519(_) => "ed25519",
+            VerificationKey::EcdsaP256Sha256(_) => "ecdsa-p256-sha256",
+            VerificationKey::EcdsaP384Sha384(_) => "ecdsa-p384-sha384",
+            VerificationKey::RsaPssSha512(_) => "rsa-pss-sha512",
+            VerificationKey::RsaV15Sha256(_) => "rsa-v1_5-sha256",
+        }
+    }
+
+    fn verify(&self, message: &[u8], sig: &[u8]) -> bool {
+        let (algorithm, key): (&'static dyn VerificationAlgorithm, &[u8]) = match self {
+            VerificationKey::HmacSha256(key) => {
+                let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+                return hmac::verify(&key, message, sig).is_ok();
+            }
+            VerificationKey::Ed25519(key) => (&signature::ED25519, key.as_slice()),
+            VerificationKey::EcdsaP256Sha256(key) => {
+                (&signature::ECDSA_P256_SHA256_FIXED, key.as_slice())
+            }
+            VerificationKey::EcdsaP384Sha384(key) => {
+                (&signature::ECDSA_P384_SHA384_FIXED, key.as_slice())
+            }
+            VerificationKey::RsaPssSha512(key) => {
+                (&signature::RSA_PSS_2048_8192_SHA512, key.as_slice())
+            }
+            VerificationKey::RsaV15Sha256(key) => {
+                (&signature::RSA_PKCS1_2048_8192_SHA256, key.as_slice())
+            }
+        };
+        UnparsedPublicKey::new(algorithm, key)
+            .verify(message, sig)
+            .is_ok()
+    }
+}
+
+/// Parameters of a signature that passed verification.
+#[derive(Debug, Clone)]
+pub struct VerifiedSignature {
+    pub label: String,
+    /// Covered component identifiers as serialized in `Signature-Input`.
+    pub components: Vec<String>,
+    pub key_id: Option<String>,
+    pub algorithm: Option<String>,
+    pub created: Option<u64>,
+    pub expires: Option<u64>,
+    pub nonce: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// Checks RFC 9421 signatures carried by responses.
+///
+/// Every `Signature-Input` member is tried in order (optionally narrowed by
+/// label and `keyid`); the first one that verifies wins. Expired signatures,
+/// signatures older than `max_age` (or without `created` when it is set) and
+/// ones missing a required component are rejected even when the
+/// cryptographic check would pass.
+#[derive(Debug, Clone)]
+pub struct SignatureVerifier {
+    key: VerificationKey,
+    key_id: Option<String>,
+    label: Option<String>,
+    required_components: Vec<String>,
+    max_age: Option<Duration>,
+}
+
+impl SignatureVerifier {
+    pub fn new(key: VerificationKey) -> Self {
+        Self {
+            key,
+            key_id: None,
+            label: None,
+            required_components: Vec::new(),
+            max_age: None,
+        }
+    }
+
+    /// Only consider signatures whose `keyid` parameter matches.
+    pub fn key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.key_id = Some(key_id.into());
+        self
+    }
+
+    /// Only consider the signature stored under `label`.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Components that must be covered, e.g. `["@status", "content-digest"]`.
+    pub fn require_components<I, S>(mut self, components: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.required_components = components
+            .into_iter()
+            .map(|c| c.as_ref().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Verifies `response`. Pass the originating `request` when the signature
+    /// may cover request components (`"@method";req`, `"@path";req`, ...).
+    pub fn verify_response(
+        &self,
+        response: &Response,
+        request: Option<&Request>,
+    ) -> Result<VerifiedSignature, ProtocolError> {
+        let request_headers = request.map(|r| r.prepare_headers());
+        let message = SignedMessage::Response {
+            status: response.status,
+            headers: &response.headers,
+            request: request.zip(request_headers.as_deref()),
+        };
+
+        let inputs =
+            dictionary_members(&combined_header(&response.headers, SIGNATURE_INPUT_HEADER));
+        if inputs.is_empty() {
+            return Err(ProtocolError::SignatureError(
+                "Response carries no Signature-Input header".to_string(),
+            ));
+        }
+        let signatures = dictionary_members(&combined_header(&response.headers, SIGNATURE_HEADER));
+
+        let mut last_error = None;
+        for (label, raw_params) in &inputs {
+            if self.label.as_ref().map_or(false, |wanted| wanted != label) {
+                continue;
+            }
+            let input = match parse_signature_input(raw_params) {
+                Some(input) => input,
+                None => {
+                    last_error = Some(format!("Malformed Signature-Input member '{}'", label));
+                    continue;
+                }
+            };
+            if let Some(wanted) = &self.key_id {
+                if input.string("keyid") != Some(wanted.as_str()) {
+                    continue;
+                }
+            }
+            let signature = signatures
+                .iter()
+                .find(|(sig_label, _)| sig_label == label)
+                .map(|(_, value)| value.as_str());
+
+            match self.check(&message, label, raw_params, &input, signature) {
+                Ok(verified) => return Ok(verified),
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(ProtocolError::SignatureError(last_error.unwrap_or_else(
+            || "No signature matches the expected label or key id".to_string(),
+        )))
+    }
+
+    fn check(
+        &self,
+        message: &SignedMessage<'_>,
+        label: &str,
+        raw_params: &str,
+        input: &SignatureInput,
+        signature: Option<&str>,
+    ) -> Result<VerifiedSignature, String> {
+        if let Some(alg) = input.string("alg") {
+            if alg != self.key.algorithm() {
+                return Err(format!(
+                    "Signature '{}' uses alg \"{}\" but the key is {}",
+                    label,
+                    alg,
+                    self.key.algorithm()
+                ));
+            }
+        }
+
+        for required in &self.required_components {
+            if !input.components.iter().any(|c| c.name == *required) {
+                return Err(format!("Signature '{}' does not cover {}", label, required));
+            }
+        }
+
+        let now = unix_now();
+        let created = input.integer("created");
+        let expires = input.integer("expires");
+        if expires.map_or(false, |expires| expires < now) {
+            return Err(format!("Signature '{}' has expired", label));
+        }
+        if let Some(max_age) = self.max_age {
+            // Without `created` the age is unknown and the signature could be
+            // replayed forever.
+            let created =
+                created.ok_or_else(|| format!("Signature '{}' has no created parameter", label))?;
+            if now.saturating_sub(created) > max_age.as_secs() {
+                return Err(format!("Signature '{}' is older than allowed", label));
+            }
+        }
+
+        let signature = signature
+            .and_then(|value| value.strip_prefix(':'))
+            .and_then(|value| value.strip_suffix(':'))
+            .and_then(base64_decode)
+            .ok_or_else(|| format!("Missing or malformed Signature member '{}'", label))?;
+
+        let base = message
+            .signature_base(&input.components, raw_params)
+            .map_err(|err| err.to_string())?;
+        if !self.key.verify(base.as_bytes(), &signature) {
+            return Err(format!("Signature '{}' does not match the message", label));
+        }
+
+        Ok(VerifiedSignature {
+            label: label.to_string(),
+            components: input
+                .components
+                .iter()
+                .map(ComponentId::serialize)
+                .collect(),
+            key_id: input.string("keyid").map(str::to_string),
+            algorithm: input.string("alg").map(str::to_string),
+            created,
+            expires,
+            nonce: input.string("nonce").map(str::to_string),
+            tag: input.string("tag").map(str::to_string),
+        })
+    }
+}
+
+/// Parsed `Signature-Input` member.
+struct SignatureInput {
+    components: Vec<ComponentId>,
+    params: Vec<(String, SignatureParam)>,
+}
+
+impl SignatureInput {
+    fn string(&self, key: &str) -> Option<&str> {
+        self.params.iter().find_map(|(k, v)| match v {
+            SignatureParam::String(s) if k == key => Some(s.as_str()),
+            _ => None,
+        })
+    }
+
+    fn integer(&self, key: &str) -> Option<u64> {
+        self.params.iter().find_map(|(k, v)| match v {
+            SignatureParam::Integer(i) if k == key => Some(*i),
+            _ => None,
+        })
+    }
+}
+
+fn combined_header(headers: &[Header], name: &str) -> String {
+    headers
+        .iter()
+        .filter(|h| h.name.eq_ignore_ascii_case(name))
+        .filter_map(|h| h.value.as_deref())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Splits a structured-field dictionary into `(key, raw member)` pairs. The
+/// raw member is kept verbatim because it is part of the signature base.
+fn dictionary_members(value: &str) -> Vec<(String, String)> {
+    let mut members = Vec::new();
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, b) in value.bytes().enumerate() {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' if quoted => escaped = true,
+            b'"' => quoted = !quoted,
+            b'(' if !quoted => depth += 1,
+            b')' if !quoted => depth = depth.saturating_sub(1),
+            b',' if !quoted && depth == 0 => {
+                members.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    members.push(&value[start..]);
+
+    members
+        .into_iter()
+        .filter_map(|member| {
+            let (key, raw) = member.trim().split_once('=')?;
+            Some((key.trim().to_string(), raw.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Parses `("@status" "@method";req);created=1;keyid="k"`.
+fn parse_signature_input(raw: &str) -> Option<SignatureInput> {
+    let inner = raw.strip_prefix('(')?;
+    let close = inner.find(')')?;
+    let (list, params) = (&inner[..close], &inner[close + 1..]);
+
+    let mut components = Vec::new();
+    for item in list.split_whitespace() {
+        let item = item.strip_prefix('"')?;
+        let end = item.find('"')?;
+        components.push(ComponentId {
+            name: item[..end].to_ascii_lowercase(),
+            params: item[end + 1..].to_string(),
+        });
+    }
+
+    let mut parsed = Vec::new();
+    for param in params.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = param.split_once('=')?;
+        let value = match value.strip_prefix('"') {
+            Some(quoted) => SignatureParam::String(quoted.strip_suffix('"')?.to_string()),
+            None => SignatureParam::Integer(value.parse().ok()?),
+        };
+        parsed.push((key.to_string(), value));
+    }
+
+    Some(SignatureInput {
+        components,
+        params: parsed,
+    })
+}
@@ -1,3 +1,7 @@
+use crate::dns::DnsCache;
+use crate::fault::FaultyStream;
+use crate::handshake::{HandshakePhase, HandshakeTracker};
+use crate::types::{ConnectFailure, ConnectMechanism, ProtocolError, SocketOptions, SsrfGuard};
 use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
 use rustls::crypto::ring::default_provider;
 use rustls::pki_types::ServerName;
@@ -5,9 +9,16 @@ use rustls::ClientConfig;
 use rustls::DigitallySignedStruct;
 use std::future::Future;
 use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::net::TcpStream;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::net::{TcpSocket, TcpStream};
 use tokio::time;
 use tokio_rustls::{client::TlsStream, TlsConnector};
 
@@ -66,6 +77,63 @@ impl ServerCertVerifier for NoCertificateVerification {
 pub enum TransportStream {
     Tcp(TcpStream),
     Tls(TlsStream<TcpStream>),
+    /// Cleartext over a Unix domain socket, for `http+unix` targets.
+    #[cfg(unix)]
+    Unix(UnixStream),
+    /// Another transport with simulated network faults, see [`TransportStream::with_faults`].
+    Faulty(Box<FaultyStream<TransportStream>>),
+}
+
+impl AsyncRead for TransportStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            TransportStream::Tcp(tcp) => Pin::new(tcp).poll_read(cx, buf),
+            TransportStream::Tls(tls) => Pin::new(tls).poll_read(cx, buf),
+            #[cfg(unix)]
+            TransportStream::Unix(unix) => Pin::new(unix).poll_read(cx, buf),
+            TransportStream::Faulty(faulty) => Pin::new(faulty.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for TransportStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            TransportStream::Tcp(tcp) => Pin::new(tcp).poll_write(cx, buf),
+            TransportStream::Tls(tls) => Pin::new(tls).poll_write(cx, buf),
+            #[cfg(unix)]
+            TransportStream::Unix(unix) => Pin::new(unix).poll_write(cx, buf),
+            TransportStream::Faulty(faulty) => Pin::new(faulty.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            TransportStream::Tcp(tcp) => Pin::new(tcp).poll_flush(cx),
+            TransportStream::Tls(tls) => Pin::new(tls).poll_flush(cx),
+            #[cfg(unix)]
+            TransportStream::Unix(unix) => Pin::new(unix).poll_flush(cx),
+            TransportStream::Faulty(faulty) => Pin::new(faulty.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            TransportStream::Tcp(tcp) => Pin::new(tcp).poll_shutdown(cx),
+            TransportStream::Tls(tls) => Pin::new(tls).poll_shutdown(cx),
+            #[cfg(unix)]
+            TransportStream::Unix(unix) => Pin::new(unix).poll_shutdown(cx),
+            TransportStream::Faulty(faulty) => Pin::new(faulty.as_mut()).poll_shutdown(cx),
+        }
+    }
 }
 
 const ALPN_HTTP11: &[u8] = b"http/1.1";
@@ -116,9 +184,96 @@ where
     }
 }
 
-async fn connect_tcp(host: &str, port: u16, timeout: Option<Duration>) -> io::Result<TcpStream> {
-    let connect_future = TcpStream::connect((host, port));
-    with_timeout(timeout, connect_future, "TCP connection timed out").await
+async fn connect_tcp(
+    host: &str,
+    addrs: &[SocketAddr],
+    timeout: Option<Duration>,
+    options: &SocketOptions,
+) -> io::Result<TcpStream> {
+    let deadline = timeout.map(|timeout| time::Instant::now() + timeout);
+    connect_addrs(host, addrs, options, ConnectMechanism::Tcp, deadline).await
+}
+
+/// Tries `addrs` in order, binding each socket per `options` first, until one
+/// connects or `deadline` passes. The error of a failed connect carries a
+/// [`ConnectFailure`] listing every address tried.
+pub(crate) async fn connect_addrs(
+    host: &str,
+    addrs: &[SocketAddr],
+    options: &SocketOptions,
+    mechanism: ConnectMechanism,
+    deadline: Option<time::Instant>,
+) -> io::Result<TcpStream> {
+    let mut failure = ConnectFailure::new(host);
+    for addr in addrs {
+        let started = Instant::now();
+        let attempt = connect_bound(addr, options);
+        let result = match deadline {
+            Some(deadline) => match time::timeout_at(deadline, attempt).await {
+                Ok(result) => result,
+                Err(_) => Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "TCP connection timed out",
+                )),
+            },
+            None => attempt.await,
+        };
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(e) => failure.record(*addr, mechanism, &e, started.elapsed()),
+        }
+        if deadline.map_or(false, |deadline| time::Instant::now() >= deadline) {
+            break;
+        }
+    }
+    Err(failure.into_io_error())
+}
+
+async fn connect_bound(addr: &SocketAddr, options: &SocketOptions) -> io::Result<TcpStream> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    if options.reuse_address {
+        socket.set_reuseaddr(true)?;
+    }
+    #[cfg(unix)]
+    if options.reuse_port {
+        socket.set_reuseport(true)?;
+    }
+    if options.binds() {
+        socket.bind(options.bind_addr(addr))?;
+    }
+    socket.connect(*addr).await
+}
+
+/// Resolves `host` through the shared [`DnsCache`], bypassing it when
+/// `options.fresh_dns` is set, and connects, both within `timeout`.
+async fn connect_host(
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+    options: &SocketOptions,
+    tracker: Option<&HandshakeTracker>,
+) -> io::Result<TcpStream> {
+    enter(tracker, HandshakePhase::Dns);
+    let deadline = timeout.map(|timeout| time::Instant::now() + timeout);
+    let resolve = DnsCache::global().resolve_for(host, port, options.fresh_dns);
+    let addrs = match deadline {
+        Some(deadline) => time::timeout_at(deadline, resolve)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "TCP connection timed out"))??,
+        None => resolve.await?,
+    };
+    enter(tracker, HandshakePhase::Tcp);
+    connect_addrs(host, &addrs, options, ConnectMechanism::Tcp, deadline).await
+}
+
+fn enter(tracker: Option<&HandshakeTracker>, phase: HandshakePhase) {
+    if let Some(tracker) = tracker {
+        tracker.enter(phase);
+    }
 }
 
 pub async fn create_tcp_stream(
@@ -126,20 +281,56 @@ pub async fn create_tcp_stream(
     port: u16,
     timeout: Option<Duration>,
 ) -> io::Result<TransportStream> {
-    let stream = connect_tcp(host, port, timeout).await?;
+    let stream = connect_host(host, port, timeout, &SocketOptions::default(), None).await?;
     Ok(TransportStream::Tcp(stream))
 }
 
+/// Connects to the Unix domain socket at `path`, e.g. the one an
+/// `http+unix` target names.
+pub async fn create_unix_stream(
+    path: &Path,
+    timeout: Option<Duration>,
+) -> io::Result<TransportStream> {
+    #[cfg(unix)]
+    {
+        let stream = with_timeout(
+            timeout,
+            UnixStream::connect(path),
+            "Unix socket connection timed out",
+        )
+        .await?;
+        Ok(TransportStream::Unix(stream))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (path, timeout);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix domain sockets are not supported on this platform",
+        ))
+    }
+}
+
 pub async fn create_tls_stream(
     host: &str,
     port: u16,
     timeout: Option<Duration>,
     alpn_protocols: Option<&[&[u8]]>,
 ) -> io::Result<TransportStream> {
+    let tcp_stream = connect_host(host, port, timeout, &SocketOptions::default(), None).await?;
+    tls_handshake(tcp_stream, host, timeout, alpn_protocols, None).await
+}
+
+async fn tls_handshake(
+    tcp_stream: TcpStream,
+    host: &str,
+    timeout: Option<Duration>,
+    alpn_protocols: Option<&[&[u8]]>,
+    tracker: Option<&HandshakeTracker>,
+) -> io::Result<TransportStream> {
+    enter(tracker, HandshakePhase::Tls);
     // Ensure a crypto provider is installed (required for rustls >=0.23).
     let _ = default_provider().install_default();
-    let tcp_stream = connect_tcp(host, port, timeout).await?;
-
     let connector = build_tls_connector(alpn_protocols);
     let server_name = server_name_from_str(host)?;
 
@@ -159,13 +350,93 @@ pub async fn create_stream(
     port: u16,
     timeout: Option<Duration>,
 ) -> io::Result<TransportStream> {
-    match scheme {
-        "http" => create_tcp_stream(host, port, timeout).await,
-        "https" => create_tls_stream(host, port, timeout, Some(&[ALPN_HTTP11])).await,
-        "h2" => create_tls_stream(host, port, timeout, Some(&[ALPN_H2])).await,
-        _ => Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            format!("Unsupported scheme: {}", scheme),
-        )),
+    create_stream_with_options(scheme, host, port, timeout, &SocketOptions::default()).await
+}
+
+/// Like [`create_stream`], binding the local end of the connection per
+/// `options`.
+pub async fn create_stream_with_options(
+    scheme: &str,
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+    options: &SocketOptions,
+) -> io::Result<TransportStream> {
+    open_stream(scheme, host, port, timeout, options, None).await
+}
+
+/// Like [`create_stream_with_options`], recording the DNS, TCP and TLS
+/// phases in `tracker`.
+pub async fn create_traced_stream(
+    scheme: &str,
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+    options: &SocketOptions,
+    tracker: &HandshakeTracker,
+) -> io::Result<TransportStream> {
+    open_stream(scheme, host, port, timeout, options, Some(tracker)).await
+}
+
+async fn open_stream(
+    scheme: &str,
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+    options: &SocketOptions,
+    tracker: Option<&HandshakeTracker>,
+) -> io::Result<TransportStream> {
+    let alpn = match scheme {
+        "http" => None,
+        "https" => Some(ALPN_HTTP11),
+        "h2" => Some(ALPN_H2),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported scheme: {}", scheme),
+            ))
+        }
+    };
+    let tcp_stream = connect_host(host, port, timeout, options, tracker).await?;
+    match alpn {
+        Some(alpn) => tls_handshake(tcp_stream, host, timeout, Some(&[alpn]), tracker).await,
+        None => Ok(TransportStream::Tcp(tcp_stream)),
     }
 }
+
+/// Like [`create_stream`], but resolves `host` through `guard` first and only
+/// connects to the addresses it approved. Phases are recorded in `tracker`
+/// when one is given.
+pub async fn create_guarded_stream(
+    scheme: &str,
+    host: &str,
+    port: u16,
+    timeout: Option<Duration>,
+    guard: &SsrfGuard,
+    options: &SocketOptions,
+    tracker: Option<&HandshakeTracker>,
+) -> Result<TransportStream, ProtocolError> {
+    let alpn = match scheme {
+        "http" => None,
+        "https" => Some(ALPN_HTTP11),
+        "h2" => Some(ALPN_H2),
+        _ => {
+            return Err(ProtocolError::ConnectionFailed(format!(
+                "Unsupported scheme: {}",
+                scheme
+            )))
+        }
+    };
+    enter(tracker, HandshakePhase::Dns);
+    let addrs = guard.resolve_for(host, port, options.fresh_dns).await?;
+
+    let connect = async {
+        enter(tracker, HandshakePhase::Tcp);
+        let tcp_stream = connect_tcp(host, &addrs, timeout, options).await?;
+        match alpn {
+            Some(alpn) => tls_handshake(tcp_stream, host, timeout, Some(&[alpn]), tracker).await,
+            None => Ok(TransportStream::Tcp(tcp_stream)),
+        }
+    };
+    connect.await.map_err(ProtocolError::from_connect_error)
+}
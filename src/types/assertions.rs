@@ -0,0 +1,182 @@
+use super::Response;
+use serde_json::Value;
+use std::fmt;
+
+/// Longest body excerpt quoted in an [`AssertionError`].
+const EXCERPT_CHARS: usize = 200;
+
+/// A failed check on a response: what was expected and what arrived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionError {
+    /// The check that failed, e.g. `header content-type`.
+    pub check: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl AssertionError {
+    fn new(
+        check: impl Into<String>,
+        expected: impl Into<String>,
+        actual: impl Into<String>,
+    ) -> Self {
+        Self {
+            check: check.into(),
+            expected: expected.into(),
+            actual: actual.into(),
+        }
+    }
+}
+
+impl fmt::Display for AssertionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} mismatch\n  expected: {}\n    actual: {}",
+            self.check, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for AssertionError {}
+
+/// Checks that read as a chain and stop at the first failure:
+///
+/// ```ignore
+/// let response = H1::new().send_request(request).await?;
+/// response
+///     .assert_status(201)?
+///     .assert_header("content-type", "application/json")?
+///     .assert_json_pointer("/user/name", &json!("alice"))?;
+/// ```
+pub trait ResponseAssertions {
+    fn assert_status(&self, status: u16) -> Result<&Self, AssertionError>;
+
+    fn assert_header_present(&self, name: &str) -> Result<&Self, AssertionError>;
+
+    /// Some `name` header has exactly `value`; names are case-insensitive.
+    fn assert_header(&self, name: &str, value: &str) -> Result<&Self, AssertionError>;
+
+    /// The body, decoded with its charset, contains `needle`.
+    fn assert_body_contains(&self, needle: &str) -> Result<&Self, AssertionError>;
+
+    /// The body, decoded with its charset, matches the regular expression `pattern`.
+    #[cfg(feature = "regex")]
+    fn assert_body_matches(&self, pattern: &str) -> Result<&Self, AssertionError>;
+
+    /// The JSON body has `expected` at the RFC 6901 `pointer`.
+    fn assert_json_pointer(&self, pointer: &str, expected: &Value)
+        -> Result<&Self, AssertionError>;
+}
+
+impl ResponseAssertions for Response {
+    fn assert_status(&self, status: u16) -> Result<&Self, AssertionError> {
+        if self.status == status {
+            return Ok(self);
+        }
+        Err(AssertionError::new(
+            "status",
+            status.to_string(),
+            self.status.to_string(),
+        ))
+    }
+
+    fn assert_header_present(&self, name: &str) -> Result<&Self, AssertionError> {
+        if self
+            .headers
+            .iter()
+            .any(|h| h.name.eq_ignore_ascii_case(name))
+        {
+            return Ok(self);
+        }
+        let names: Vec<&str> = self.headers.iter().map(|h| h.name.as_str()).collect();
+        Err(AssertionError::new(
+            format!("header {}", name),
+            "present",
+            format!("absent; headers are [{}]", names.join(", ")),
+        ))
+    }
+
+    fn assert_header(&self, name: &str, value: &str) -> Result<&Self, AssertionError> {
+        let values: Vec<&str> = self
+            .headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value.as_deref().unwrap_or_default())
+            .collect();
+        if values.contains(&value) {
+            return Ok(self);
+        }
+        let actual = match values.as_slice() {
+            [] => "absent".to_string(),
+            [single] => format!("{:?}", single),
+            all => format!("{:?}", all),
+        };
+        Err(AssertionError::new(
+            format!("header {}", name),
+            format!("{:?}", value),
+            actual,
+        ))
+    }
+
+    fn assert_body_contains(&self, needle: &str) -> Result<&Self, AssertionError> {
+        let body = self.text_with_charset();
+        if body.contains(needle) {
+            return Ok(self);
+        }
+        Err(AssertionError::new(
+            "body",
+            format!("to contain {:?}", needle),
+            excerpt(&body),
+        ))
+    }
+
+    #[cfg(feature = "regex")]
+    fn assert_body_matches(&self, pattern: &str) -> Result<&Self, AssertionError> {
+        let regex = regex::Regex::new(pattern).map_err(|err| {
+            AssertionError::new("body", format!("to match /{}/", pattern), err.to_string())
+        })?;
+        let body = self.text_with_charset();
+        if regex.is_match(&body) {
+            return Ok(self);
+        }
+        Err(AssertionError::new(
+            "body",
+            format!("to match /{}/", pattern),
+            excerpt(&body),
+        ))
+    }
+
+    fn assert_json_pointer(
+        &self,
+        pointer: &str,
+        expected: &Value,
+    ) -> Result<&Self, AssertionError> {
+        let check = format!("JSON {}", pointer);
+        let document = self.json().map_err(|err| {
+            AssertionError::new(
+                check.clone(),
+                expected.to_string(),
+                format!("body is not JSON ({}): {}", err, excerpt(&self.text())),
+            )
+        })?;
+        match document.pointer(pointer) {
+            Some(actual) if actual == expected => Ok(self),
+            Some(actual) => Err(AssertionError::new(
+                check,
+                expected.to_string(),
+                actual.to_string(),
+            )),
+            None => Err(AssertionError::new(check, expected.to_string(), "missing")),
+        }
+    }
+}
+
+fn excerpt(body: &str) -> String {
+    let total = body.chars().count();
+    if total <= EXCERPT_CHARS {
+        return format!("{:?}", body);
+    }
+    let head: String = body.chars().take(EXCERPT_CHARS).collect();
+    format!("{:?}... ({} chars)", head, total)
+}
@@ -0,0 +1,224 @@
+use super::{Header, ProtocolError, Response};
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Where a [`ResponseBody`] pulls its chunks from: the read path of a
+/// connection, positioned after the response head.
+#[async_trait(?Send)]
+pub trait BodySource {
+    /// The next chunk of the body; `None` once it is complete. Called again
+    /// after `None` or an error, it returns `None`.
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, ProtocolError>>;
+
+    /// Trailers that followed the body, once it is complete.
+    fn trailers(&mut self) -> Option<Vec<Header>> {
+        None
+    }
+}
+
+type PendingChunk<'a> = Pin<
+    Box<
+        dyn Future<
+                Output = (
+                    Box<dyn BodySource + 'a>,
+                    Option<Result<Bytes, ProtocolError>>,
+                ),
+            > + 'a,
+    >,
+>;
+
+enum BodyState<'a> {
+    Idle(Box<dyn BodySource + 'a>),
+    Reading(PendingChunk<'a>),
+    /// Kept after the end for its trailers.
+    Done(Box<dyn BodySource + 'a>),
+    /// Only while `poll_next` moves the state out.
+    Moving,
+}
+
+/// A response body read chunk by chunk as it arrives, so that a multi-GB
+/// download never sits in memory whole. It is a `Stream` of chunks and an
+/// `AsyncRead`; [`bytes`](Self::bytes) buffers it like [`Response::body`].
+pub struct ResponseBody<'a> {
+    state: BodyState<'a>,
+    /// Rest of the chunk `poll_read` is handing out.
+    unread: Bytes,
+}
+
+impl<'a> ResponseBody<'a> {
+    pub fn new(source: impl BodySource + 'a) -> Self {
+        Self {
+            state: BodyState::Idle(Box::new(source)),
+            unread: Bytes::new(),
+        }
+    }
+
+    /// The next chunk; `None` once the body is complete.
+    pub async fn next_chunk(&mut self) -> Option<Result<Bytes, ProtocolError>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Reads the rest of the body into one buffer.
+    pub async fn bytes(&mut self) -> Result<Bytes, ProtocolError> {
+        let mut body = BytesMut::from(std::mem::take(&mut self.unread).as_ref());
+        while let Some(chunk) = self.next_chunk().await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(body.freeze())
+    }
+
+    /// Trailers, once the body has been read to the end.
+    pub fn trailers(&mut self) -> Option<Vec<Header>> {
+        match &mut self.state {
+            BodyState::Done(source) => source.trailers(),
+            _ => None,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, BodyState::Done(_)) && self.unread.is_empty()
+    }
+}
+
+impl From<Bytes> for ResponseBody<'_> {
+    fn from(body: Bytes) -> Self {
+        Self::new(Buffered(Some(body).filter(|body| !body.is_empty())))
+    }
+}
+
+/// A body that was read before the response was returned.
+struct Buffered(Option<Bytes>);
+
+#[async_trait(?Send)]
+impl BodySource for Buffered {
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, ProtocolError>> {
+        self.0.take().map(Ok)
+    }
+}
+
+impl Stream for ResponseBody<'_> {
+    type Item = Result<Bytes, ProtocolError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            let state = std::mem::replace(&mut this.state, BodyState::Moving);
+            match state {
+                BodyState::Idle(mut source) => {
+                    this.state = BodyState::Reading(Box::pin(async move {
+                        let chunk = source.next_chunk().await;
+                        (source, chunk)
+                    }));
+                }
+                BodyState::Reading(mut pending) => match pending.as_mut().poll(cx) {
+                    Poll::Ready((source, chunk)) => {
+                        this.state = match chunk {
+                            Some(Ok(_)) => BodyState::Idle(source),
+                            None | Some(Err(_)) => BodyState::Done(source),
+                        };
+                        return Poll::Ready(chunk);
+                    }
+                    Poll::Pending => {
+                        this.state = BodyState::Reading(pending);
+                        return Poll::Pending;
+                    }
+                },
+                BodyState::Done(source) => {
+                    this.state = BodyState::Done(source);
+                    return Poll::Ready(None);
+                }
+                BodyState::Moving => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl AsyncRead for ResponseBody<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.unread.is_empty() {
+            match self.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self.unread = chunk,
+                Poll::Ready(Some(Err(ProtocolError::Io(err)))) => return Poll::Ready(Err(err)),
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(io::Error::other(err))),
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        let len = self.unread.len().min(buf.remaining());
+        let chunk = self.unread.split_to(len);
+        buf.put_slice(&chunk);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The connection a body source reads from: borrowed for a response read
+/// off a connection the caller keeps, owned for one a client opened for
+/// the response alone.
+pub(crate) enum BodyConnection<'a, C> {
+    Borrowed(&'a mut C),
+    Owned(Box<C>),
+}
+
+impl<C> std::ops::Deref for BodyConnection<'_, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        match self {
+            BodyConnection::Borrowed(connection) => connection,
+            BodyConnection::Owned(connection) => connection,
+        }
+    }
+}
+
+impl<C> std::ops::DerefMut for BodyConnection<'_, C> {
+    fn deref_mut(&mut self) -> &mut C {
+        match self {
+            BodyConnection::Borrowed(connection) => connection,
+            BodyConnection::Owned(connection) => connection,
+        }
+    }
+}
+
+/// A response returned as soon as its head is read, with the body still
+/// on the connection.
+pub struct StreamingResponse<'a> {
+    pub status: u16,
+    pub protocol: String,
+    pub headers: Vec<Header>,
+    pub body: ResponseBody<'a>,
+}
+
+impl StreamingResponse<'_> {
+    /// Reads the rest of the body and returns the response as the buffered
+    /// read paths would, without captured frames.
+    pub async fn buffer(mut self) -> Result<Response, ProtocolError> {
+        let body = self.body.bytes().await?;
+        let cookies = Response::collect_cookies(&self.headers);
+        Ok(Response {
+            status: self.status,
+            protocol: self.protocol,
+            headers: self.headers,
+            body,
+            trailers: self.body.trailers(),
+            frames: None,
+            header_blocks: None,
+            cookies,
+            decoded_body: None,
+            frame_times: None,
+            digest: None,
+            transfer_codings: Vec::new(),
+            truncated: false,
+            truncation: None,
+        })
+    }
+}
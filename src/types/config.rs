@@ -0,0 +1,153 @@
+use super::{
+    ClientTimeouts, DecompressionLimits, HeaderLimits, HeaderProfile, HostScope, ProxySettings,
+    RedirectPolicy, Request, SocketOptions, UserAgent,
+};
+
+/// Defaults shared by `H1`, `H2` and `H3` clients and the sessions built on
+/// them. Build it once and hand a clone to each client:
+///
+/// ```ignore
+/// let config = ClientConfig::new()
+///     .timeouts(ClientTimeouts::disabled())
+///     .proxies(ProxySettings::new().http("http://127.0.0.1:8080")?)
+///     .user_agent(UserAgent::Custom("scanner/1.0".to_string()));
+/// let h1 = H1::with_config(config.clone());
+/// let h2 = H2::with_config(config);
+/// ```
+///
+/// Every setting is a default: a request that sets its own timeouts, proxies,
+/// `User-Agent`, header profile, redirect policy or decompression limits keeps
+/// them.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub timeouts: ClientTimeouts,
+    /// Used by requests without proxies of their own.
+    pub proxies: Option<ProxySettings>,
+    /// When false, redirects are never followed, whatever the request asks for.
+    pub follow_redirects: bool,
+    /// Replaces the policy of requests still on `RedirectPolicy::default()`.
+    pub redirect_policy: Option<RedirectPolicy>,
+    pub user_agent: UserAgent,
+    pub header_profile: Option<HeaderProfile>,
+    /// Replaces the limits of requests still on `DecompressionLimits::default()`.
+    pub decompression_limits: Option<DecompressionLimits>,
+    /// Applied while reading HTTP/1.1 response heads and trailers.
+    pub header_limits: HeaderLimits,
+    /// Source address and port options for direct connections.
+    pub socket_options: SocketOptions,
+    /// Hosts requests and their redirects may reach; checked before each
+    /// one is sent.
+    pub host_scope: Option<HostScope>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            timeouts: ClientTimeouts::default(),
+            proxies: None,
+            follow_redirects: true,
+            redirect_policy: None,
+            user_agent: UserAgent::Inherit,
+            header_profile: None,
+            decompression_limits: None,
+            header_limits: HeaderLimits::default(),
+            socket_options: SocketOptions::default(),
+            host_scope: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn timeouts(mut self, timeouts: ClientTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn proxies(mut self, proxies: ProxySettings) -> Self {
+        self.proxies = Some(proxies);
+        self
+    }
+
+    pub fn follow_redirects(mut self, enabled: bool) -> Self {
+        self.follow_redirects = enabled;
+        self
+    }
+
+    pub fn redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect_policy = Some(policy);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: UserAgent) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+
+    pub fn header_profile(mut self, profile: HeaderProfile) -> Self {
+        self.header_profile = Some(profile);
+        self
+    }
+
+    pub fn decompression_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.decompression_limits = Some(limits);
+        self
+    }
+
+    pub fn header_limits(mut self, limits: HeaderLimits) -> Self {
+        self.header_limits = limits;
+        self
+    }
+
+    pub fn socket_options(mut self, options: SocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
+
+    pub fn host_scope(mut self, scope: HostScope) -> Self {
+        self.host_scope = Some(scope);
+        self
+    }
+
+    /// Fills in the settings `request` leaves to the client. Timeouts are not
+    /// copied; clients pass them as the fallback to [`Request::timeouts`].
+    pub fn apply(&self, request: &mut Request) {
+        if request.proxies.is_none() {
+            request.proxies = self.proxies.clone();
+        }
+        if !self.follow_redirects {
+            request.follow_redirects = false;
+        }
+        if self.socket_options.fresh_dns {
+            request.fresh_dns = true;
+        }
+        if let Some(scope) = &self.host_scope {
+            scope.guard(request);
+        }
+        if let Some(policy) = &self.redirect_policy {
+            if request.redirect_policy == RedirectPolicy::default() {
+                request.redirect_policy = policy.clone();
+            }
+        }
+        request.inherit_user_agent(&self.user_agent);
+        if request.header_profile.is_none() {
+            request.header_profile = self.header_profile;
+        }
+        if let Some(limits) = self.decompression_limits {
+            if request.decompression_limits == DecompressionLimits::default() {
+                request.decompression_limits = limits;
+            }
+        }
+    }
+
+    /// The socket options a connection for `request` is opened with.
+    pub fn socket_options_for(&self, request: &Request) -> SocketOptions {
+        SocketOptions {
+            fresh_dns: self.socket_options.fresh_dns || request.fresh_dns,
+            ..self.socket_options
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use super::Header;
+use super::{Header, Request, Response};
+use chrono::{DateTime, TimeZone, Utc};
+use std::collections::BTreeMap;
+use std::fmt;
+use url::Url;
 
 // TODO maybe duplicate code
 pub fn parse_set_cookie(input: &str) -> Option<(String, String)> {
@@ -25,3 +29,312 @@ pub fn extract_cookies(headers: &[Header]) -> Vec<(String, String)> {
         .filter_map(|value| parse_set_cookie(value))
         .collect()
 }
+
+/// A cookie held by a [`CookieStore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    /// Lowercase host or domain the cookie belongs to; `None` for cookies
+    /// set by hand, which go to every host.
+    pub domain: Option<String>,
+    /// Sent to `domain` only, not its subdomains: the cookie had no
+    /// `Domain` attribute.
+    pub host_only: bool,
+    pub path: String,
+    /// Only sent over `https`.
+    pub secure: bool,
+}
+
+impl StoredCookie {
+    /// True if the cookie goes with a request to `url` (RFC 6265 section 5.4).
+    pub fn matches(&self, url: &Url) -> bool {
+        if self.secure && url.scheme() != "https" {
+            return false;
+        }
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let domain_ok = match &self.domain {
+            None => true,
+            Some(domain) if self.host_only => host == *domain,
+            Some(domain) => domain_matches(&host, domain),
+        };
+        domain_ok && path_matches(url.path(), &self.path)
+    }
+}
+
+/// Cookies by domain, path and name, as a browser keeps them: `Set-Cookie`
+/// attributes decide which later requests, redirect hops included, get each
+/// cookie back.
+#[derive(Debug, Clone, Default)]
+pub struct CookieStore {
+    entries: BTreeMap<(String, String, String), StoredCookie>,
+}
+
+impl CookieStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a cookie sent to every host and path.
+    pub fn set_cookie(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.insert(StoredCookie {
+            value: value.into(),
+            domain: None,
+            host_only: false,
+            path: "/".to_string(),
+            secure: false,
+            name,
+        });
+    }
+
+    pub fn insert(&mut self, cookie: StoredCookie) {
+        let key = (
+            cookie.domain.clone().unwrap_or_default(),
+            cookie.path.clone(),
+            cookie.name.clone(),
+        );
+        self.entries.insert(key, cookie);
+    }
+
+    /// Names and values of every cookie, whatever its domain.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.entries
+            .values()
+            .map(|cookie| (&cookie.name, &cookie.value))
+    }
+
+    pub fn cookies(&self) -> impl Iterator<Item = &StoredCookie> {
+        self.entries.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Cookies for a request to `url`, longest path first.
+    pub fn cookies_for(&self, url: &Url) -> Vec<(String, String)> {
+        let mut matching: Vec<&StoredCookie> = self
+            .entries
+            .values()
+            .filter(|cookie| cookie.matches(url))
+            .collect();
+        matching.sort_by(|a, b| b.path.len().cmp(&a.path.len()));
+        matching
+            .into_iter()
+            .map(|cookie| (cookie.name.clone(), cookie.value.clone()))
+            .collect()
+    }
+
+    /// Adds the cookies for the request's URL to it. Cookies the request
+    /// already carries win over stored ones of the same name.
+    pub fn apply_to_request(&self, request: &mut Request) {
+        let mut cookies = self.cookies_for(&request.target.url);
+        if cookies.is_empty() {
+            return;
+        }
+        cookies.retain(|(name, _)| !request.cookies.iter().any(|(own, _)| own == name));
+        cookies.append(&mut request.cookies);
+        request.cookies = cookies;
+    }
+
+    /// Stores the `Set-Cookie` headers of a response to a request for `url`.
+    /// Expired cookies, via `Max-Age` or `Expires`, and empty values delete.
+    pub fn store_response(&mut self, url: &Url, response: &Response) {
+        for header in &response.headers {
+            if !header.name.eq_ignore_ascii_case("set-cookie") {
+                continue;
+            }
+            if let Some(value) = &header.value {
+                self.store_set_cookie(url, value);
+            }
+        }
+    }
+
+    /// Stores one `Set-Cookie` value received from `url`. Cookies for a
+    /// domain `url` does not belong to are ignored.
+    pub fn store_set_cookie(&mut self, url: &Url, set_cookie: &str) {
+        let (name, value) = match parse_set_cookie(set_cookie) {
+            Some(pair) => pair,
+            None => return,
+        };
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let mut cookie = StoredCookie {
+            name,
+            value,
+            domain: Some(host.clone()),
+            host_only: true,
+            path: default_path(url.path()),
+            secure: false,
+        };
+        // The last of each wins, and Max-Age wins over Expires.
+        let mut max_age = None;
+        let mut expires = None;
+
+        for attribute in set_cookie.split(';').skip(1) {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            if key.eq_ignore_ascii_case("domain") && !value.is_empty() {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                if !domain_matches(&host, &domain) {
+                    return;
+                }
+                cookie.domain = Some(domain);
+                cookie.host_only = false;
+            } else if key.eq_ignore_ascii_case("path") && value.starts_with('/') {
+                cookie.path = value.to_string();
+            } else if key.eq_ignore_ascii_case("secure") {
+                cookie.secure = true;
+            } else if key.eq_ignore_ascii_case("max-age") {
+                if let Ok(seconds) = value.parse::<i64>() {
+                    max_age = Some(seconds);
+                }
+            } else if key.eq_ignore_ascii_case("expires") {
+                if let Some(date) = parse_cookie_date(value) {
+                    expires = Some(date);
+                }
+            }
+        }
+
+        let expired = match (max_age, expires) {
+            (Some(seconds), _) => seconds <= 0,
+            (None, Some(date)) => date <= Utc::now(),
+            (None, None) => false,
+        };
+        if expired {
+            let key = (cookie.domain.unwrap_or_default(), cookie.path, cookie.name);
+            self.entries.remove(&key);
+        } else {
+            self.insert(cookie);
+        }
+    }
+}
+
+impl fmt::Display for CookieStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (name, value) in self.iter() {
+            if !first {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}={}", name, value)?;
+            first = false;
+        }
+        if first {
+            write!(f, "<empty cookies>")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses an `Expires` value with the cookie-date algorithm of RFC 6265
+/// §5.1.1, which takes the formats servers send in practice, such as
+/// `Sun, 06-Nov-1994 08:49:37 GMT` and `Sunday, 06 Nov 94 08:49:37 GMT`.
+fn parse_cookie_date(value: &str) -> Option<DateTime<Utc>> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+
+    for token in value
+        .split(is_date_delimiter)
+        .filter(|token| !token.is_empty())
+    {
+        if time.is_none() {
+            if let Some(parsed) = parse_cookie_time(token) {
+                time = Some(parsed);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(parsed) = leading_digits(token, 1, 2) {
+                day = Some(parsed);
+                continue;
+            }
+        }
+        if month.is_none() {
+            let prefix = token.get(..3).unwrap_or_default().to_ascii_lowercase();
+            if let Some(index) = MONTHS.iter().position(|name| *name == prefix) {
+                month = Some(index as u32 + 1);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(parsed) = leading_digits(token, 2, 4) {
+                year = Some(parsed);
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let year = match year? {
+        year @ 70..=99 => year + 1900,
+        year @ 0..=69 => year + 2000,
+        year => year,
+    };
+    if year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    Utc.with_ymd_and_hms(year as i32, month?, day?, hour, minute, second)
+        .single()
+}
+
+fn is_date_delimiter(c: char) -> bool {
+    matches!(
+        c,
+        '\x09' | '\x20'..='\x2f' | '\x3b'..='\x40' | '\x5b'..='\x60' | '\x7b'..='\x7e'
+    )
+}
+
+/// `hh:mm:ss` at the start of `token`, each part one or two digits.
+fn parse_cookie_time(token: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = token.splitn(3, ':');
+    let hour = parts.next()?;
+    let minute = parts.next()?;
+    let second = parts.next()?;
+    let whole = |part: &str| match leading_digits(part, 1, 2) {
+        Some(number) if part.len() <= 2 => Some(number),
+        _ => None,
+    };
+    Some((whole(hour)?, whole(minute)?, leading_digits(second, 1, 2)?))
+}
+
+/// The number `token` starts with, if it has `min` to `max` digits and is
+/// not followed by another digit.
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<u32> {
+    let len = token.bytes().take_while(u8::is_ascii_digit).count();
+    if len < min || len > max {
+        return None;
+    }
+    token[..len].parse().ok()
+}
+
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<std::net::IpAddr>().is_err())
+}
+
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Directory of the request path, the path of cookies without a `Path`.
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => request_path[..index].to_string(),
+    }
+}
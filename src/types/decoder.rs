@@ -0,0 +1,234 @@
+use super::{Header, ProtocolError, Response};
+use crate::utils::CONTENT_TYPE_HEADER;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+/// Turns a response body of one media type into a JSON value.
+///
+/// Decoders share `serde_json::Value` as their output so a registry can hold
+/// them as trait objects; [`Response::decode`] then deserializes the value into
+/// the caller's type.
+pub trait BodyDecoder: Send + Sync {
+    fn decode(&self, body: &[u8]) -> Result<Value, ProtocolError>;
+}
+
+impl<F> BodyDecoder for F
+where
+    F: Fn(&[u8]) -> Result<Value, ProtocolError> + Send + Sync,
+{
+    fn decode(&self, body: &[u8]) -> Result<Value, ProtocolError> {
+        self(body)
+    }
+}
+
+fn decode_error(format: &str, err: impl fmt::Display) -> ProtocolError {
+    ProtocolError::InvalidResponse(format!("Failed to decode {} body: {}", format, err))
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonDecoder;
+
+impl BodyDecoder for JsonDecoder {
+    fn decode(&self, body: &[u8]) -> Result<Value, ProtocolError> {
+        serde_json::from_slice(body).map_err(|err| decode_error("JSON", err))
+    }
+}
+
+/// Newline-delimited JSON; decodes to an array with one element per line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NdjsonDecoder;
+
+impl BodyDecoder for NdjsonDecoder {
+    fn decode(&self, body: &[u8]) -> Result<Value, ProtocolError> {
+        ndjson_lines(body)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array)
+    }
+}
+
+/// Parses each non-empty line of `body` as a JSON document.
+pub fn ndjson_lines(body: &[u8]) -> impl Iterator<Item = Result<Value, ProtocolError>> + '_ {
+    body.split(|&b| b == b'\n')
+        .enumerate()
+        .filter(|(_, line)| !line.iter().all(u8::is_ascii_whitespace))
+        .map(|(index, line)| {
+            serde_json::from_slice(line)
+                .map_err(|err| decode_error("NDJSON", format!("line {}: {}", index + 1, err)))
+        })
+}
+
+/// XML through `quick-xml`'s serde support; attributes appear as `@name` keys
+/// and text content as `$text`.
+#[cfg(feature = "xml")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XmlDecoder;
+
+#[cfg(feature = "xml")]
+impl BodyDecoder for XmlDecoder {
+    fn decode(&self, body: &[u8]) -> Result<Value, ProtocolError> {
+        let text = std::str::from_utf8(body).map_err(|err| decode_error("XML", err))?;
+        quick_xml::de::from_str(text).map_err(|err| decode_error("XML", err))
+    }
+}
+
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgpackDecoder;
+
+#[cfg(feature = "msgpack")]
+impl BodyDecoder for MsgpackDecoder {
+    fn decode(&self, body: &[u8]) -> Result<Value, ProtocolError> {
+        rmp_serde::from_slice(body).map_err(|err| decode_error("MessagePack", err))
+    }
+}
+
+/// Maps media types to [`BodyDecoder`]s.
+///
+/// Keys are matched against the response `Content-Type` without parameters,
+/// case-insensitively. A key starting with `+` matches a structured syntax
+/// suffix, so `+json` covers `application/problem+json`. Later registrations
+/// take precedence over earlier ones.
+#[derive(Clone)]
+pub struct DecoderRegistry {
+    decoders: Vec<(String, Arc<dyn BodyDecoder>)>,
+    fallback: Option<Arc<dyn BodyDecoder>>,
+}
+
+impl fmt::Debug for DecoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecoderRegistry")
+            .field(
+                "media_types",
+                &self.decoders.iter().map(|(m, _)| m).collect::<Vec<_>>(),
+            )
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+impl Default for DecoderRegistry {
+    /// JSON (including `+json` types and responses without a Content-Type),
+    /// NDJSON, and XML / MessagePack when the matching features are enabled.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("application/json", JsonDecoder);
+        registry.register("text/json", JsonDecoder);
+        registry.register("+json", JsonDecoder);
+        registry.register("application/x-ndjson", NdjsonDecoder);
+        registry.register("application/ndjson", NdjsonDecoder);
+        registry.register("application/jsonl", NdjsonDecoder);
+        #[cfg(feature = "xml")]
+        {
+            registry.register("application/xml", XmlDecoder);
+            registry.register("text/xml", XmlDecoder);
+            registry.register("+xml", XmlDecoder);
+        }
+        #[cfg(feature = "msgpack")]
+        {
+            registry.register("application/msgpack", MsgpackDecoder);
+            registry.register("application/x-msgpack", MsgpackDecoder);
+            registry.register("application/vnd.msgpack", MsgpackDecoder);
+        }
+        registry.fallback = Some(Arc::new(JsonDecoder));
+        registry
+    }
+}
+
+impl DecoderRegistry {
+    /// Registry without any decoders.
+    pub fn new() -> Self {
+        Self {
+            decoders: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    pub fn register(&mut self, media_type: impl Into<String>, decoder: impl BodyDecoder + 'static) {
+        let media_type = media_type.into().to_ascii_lowercase();
+        self.decoders.retain(|(m, _)| *m != media_type);
+        self.decoders.push((media_type, Arc::new(decoder)));
+    }
+
+    /// Decoder used when the response has no Content-Type.
+    pub fn set_fallback(&mut self, decoder: Option<Arc<dyn BodyDecoder>>) {
+        self.fallback = decoder;
+    }
+
+    pub fn decoder_for(&self, content_type: &str) -> Option<&dyn BodyDecoder> {
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+
+        let exact = self.decoders.iter().rev().find(|(m, _)| *m == media_type);
+        let suffix = || {
+            self.decoders
+                .iter()
+                .rev()
+                .find(|(m, _)| m.starts_with('+') && media_type.ends_with(m.as_str()))
+        };
+        exact.or_else(suffix).map(|(_, decoder)| decoder.as_ref())
+    }
+
+    /// Decodes the response body according to its Content-Type.
+    pub fn decode_value(&self, response: &Response) -> Result<Value, ProtocolError> {
+        let decoder = match content_type(&response.headers) {
+            Some(content_type) => self.decoder_for(content_type).ok_or_else(|| {
+                ProtocolError::InvalidResponse(format!(
+                    "No body decoder registered for content type '{}'",
+                    content_type
+                ))
+            })?,
+            None => self.fallback.as_deref().ok_or_else(|| {
+                ProtocolError::InvalidResponse(
+                    "Response has no Content-Type and no fallback decoder".to_string(),
+                )
+            })?,
+        };
+        decoder.decode(&response.body)
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, response: &Response) -> Result<T, ProtocolError> {
+        let value = self.decode_value(response)?;
+        serde_json::from_value(value).map_err(|err| {
+            ProtocolError::InvalidResponse(format!("Decoded body has unexpected shape: {}", err))
+        })
+    }
+}
+
+fn content_type(headers: &[Header]) -> Option<&str> {
+    headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case(CONTENT_TYPE_HEADER))
+        .and_then(|h| h.value.as_deref())
+}
+
+impl Response {
+    /// Deserializes the body with the decoder matching its Content-Type
+    /// (see [`DecoderRegistry::default`]).
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, ProtocolError> {
+        DecoderRegistry::default().decode(self)
+    }
+
+    pub fn decode_with<T: DeserializeOwned>(
+        &self,
+        registry: &DecoderRegistry,
+    ) -> Result<T, ProtocolError> {
+        registry.decode(self)
+    }
+
+    /// Iterates an NDJSON body line by line without collecting it first.
+    pub fn ndjson<T: DeserializeOwned>(
+        &self,
+    ) -> impl Iterator<Item = Result<T, ProtocolError>> + '_ {
+        ndjson_lines(&self.body).map(|line| {
+            line.and_then(|value| {
+                serde_json::from_value(value).map_err(|err| decode_error("NDJSON", err))
+            })
+        })
+    }
+}
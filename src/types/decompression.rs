@@ -0,0 +1,352 @@
+use super::error::{DecompressionLimitKind, ProtocolError};
+use super::Response;
+use crate::utils::header_value;
+use bytes::Bytes;
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, OnceLock};
+
+const CONTENT_ENCODING_HEADER: &str = "content-encoding";
+const DECODE_CHUNK: usize = 16 * 1024;
+
+static GLOBAL: OnceLock<CodecRegistry> = OnceLock::new();
+
+/// Bounds applied while decoding `Content-Encoding` so a hostile origin
+/// cannot exhaust memory with a small compressed body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecompressionLimits {
+    /// Largest decoded body accepted, in bytes.
+    pub max_decoded_size: Option<usize>,
+    /// Largest decoded/compressed ratio accepted once the output passes `ratio_floor`.
+    pub max_ratio: Option<u64>,
+    /// Output size below which the ratio is not checked; tiny bodies of
+    /// repeated bytes legitimately compress far beyond any sane ratio.
+    pub ratio_floor: usize,
+}
+
+impl Default for DecompressionLimits {
+    fn default() -> Self {
+        Self {
+            max_decoded_size: Some(64 * 1024 * 1024),
+            max_ratio: Some(100),
+            ratio_floor: 1024 * 1024,
+        }
+    }
+}
+
+impl DecompressionLimits {
+    pub fn unlimited() -> Self {
+        Self {
+            max_decoded_size: None,
+            max_ratio: None,
+            ratio_floor: 0,
+        }
+    }
+
+    /// Checks totals seen so far; decoders call this after each output chunk.
+    pub fn check(&self, compressed: usize, decoded: usize) -> Result<(), ProtocolError> {
+        if let Some(limit) = self.max_decoded_size {
+            if decoded > limit {
+                return Err(ProtocolError::DecompressionLimitExceeded(
+                    DecompressionLimitKind::Size { limit, decoded },
+                ));
+            }
+        }
+        if let Some(limit) = self.max_ratio {
+            let exceeded = decoded > self.ratio_floor
+                && decoded as u128 > compressed.max(1) as u128 * limit as u128;
+            if exceeded {
+                return Err(ProtocolError::DecompressionLimitExceeded(
+                    DecompressionLimitKind::Ratio {
+                        limit,
+                        compressed,
+                        decoded,
+                    },
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn guard(&self) -> DecompressionGuard {
+        DecompressionGuard {
+            limits: *self,
+            compressed: 0,
+            decoded: 0,
+        }
+    }
+}
+
+/// Running byte counts for one decoding pass, checked against the limits on
+/// every output chunk so decoding stops before the bomb fully expands.
+#[derive(Debug, Clone)]
+pub struct DecompressionGuard {
+    limits: DecompressionLimits,
+    compressed: usize,
+    decoded: usize,
+}
+
+impl DecompressionGuard {
+    pub fn on_input(&mut self, len: usize) {
+        self.compressed = self.compressed.saturating_add(len);
+    }
+
+    pub fn on_output(&mut self, len: usize) -> Result<(), ProtocolError> {
+        self.decoded = self.decoded.saturating_add(len);
+        self.limits.check(self.compressed, self.decoded)
+    }
+
+    pub fn compressed(&self) -> usize {
+        self.compressed
+    }
+
+    pub fn decoded(&self) -> usize {
+        self.decoded
+    }
+}
+
+/// A content coding (RFC 9110 section 8.4.1) such as `gzip`, registered by
+/// name in a [`CodecRegistry`].
+pub trait ContentCodec: Send + Sync {
+    /// Removes the coding from `input`. `None` when `input` is not valid in
+    /// this coding; errors are for exceeded `limits`, which codecs check
+    /// while they decode, e.g. by reading through [`read_limited`].
+    fn decode(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Option<Vec<u8>>, ProtocolError>;
+
+    /// Applies the coding to `input`; `None` for codecs that only decode.
+    fn encode(&self, _input: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// `gzip` and `x-gzip`, including bodies of several gzip members.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GzipCodec;
+
+impl ContentCodec for GzipCodec {
+    fn decode(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Option<Vec<u8>>, ProtocolError> {
+        read_limited(MultiGzDecoder::new(input), input.len(), limits)
+    }
+
+    fn encode(&self, input: &[u8]) -> Option<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(input).ok()?;
+        encoder.finish().ok()
+    }
+}
+
+/// `deflate`: zlib-wrapped as the RFC says, or raw as some servers send it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeflateCodec;
+
+impl ContentCodec for DeflateCodec {
+    fn decode(
+        &self,
+        input: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Option<Vec<u8>>, ProtocolError> {
+        match read_limited(ZlibDecoder::new(input), input.len(), limits)? {
+            Some(output) => Ok(Some(output)),
+            None => read_limited(DeflateDecoder::new(input), input.len(), limits),
+        }
+    }
+
+    fn encode(&self, input: &[u8]) -> Option<Vec<u8>> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(input).ok()?;
+        encoder.finish().ok()
+    }
+}
+
+/// Codecs by lowercase coding name. `identity` is always understood and
+/// cannot be registered.
+pub struct CodecRegistry {
+    codecs: Mutex<HashMap<String, Arc<dyn ContentCodec>>>,
+}
+
+impl fmt::Debug for CodecRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut codings: Vec<String> = self.lock().keys().cloned().collect();
+        codings.sort();
+        f.debug_struct("CodecRegistry")
+            .field("codings", &codings)
+            .finish()
+    }
+}
+
+impl Default for CodecRegistry {
+    /// Knows `gzip`, `x-gzip` and `deflate`.
+    fn default() -> Self {
+        let registry = Self::empty();
+        registry.register("gzip", GzipCodec);
+        registry.register("x-gzip", GzipCodec);
+        registry.register("deflate", DeflateCodec);
+        registry
+    }
+}
+
+impl CodecRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry without any codecs.
+    pub fn empty() -> Self {
+        Self {
+            codecs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The registry [`decode_content`] and the clients decode with.
+    pub fn global() -> &'static CodecRegistry {
+        GLOBAL.get_or_init(CodecRegistry::default)
+    }
+
+    /// Maps `coding` to `codec`, replacing an earlier registration.
+    pub fn register(&self, coding: &str, codec: impl ContentCodec + 'static) -> bool {
+        let coding = coding.trim().to_ascii_lowercase();
+        if coding.is_empty() || coding == "identity" {
+            return false;
+        }
+        self.lock().insert(coding, Arc::new(codec));
+        true
+    }
+
+    pub fn unregister(&self, coding: &str) -> Option<Arc<dyn ContentCodec>> {
+        self.lock().remove(&coding.trim().to_ascii_lowercase())
+    }
+
+    pub fn get(&self, coding: &str) -> Option<Arc<dyn ContentCodec>> {
+        self.lock()
+            .get(&coding.trim().to_ascii_lowercase())
+            .cloned()
+    }
+
+    /// Removes the codings listed in a `Content-Encoding` value, the last
+    /// one first. Returns `None` for an unknown coding or a corrupt body and
+    /// fails only when `limits` are exceeded.
+    pub fn decode(
+        &self,
+        encoding: &str,
+        body: &[u8],
+        limits: &DecompressionLimits,
+    ) -> Result<Option<Bytes>, ProtocolError> {
+        let mut decoded = Bytes::copy_from_slice(body);
+        for coding in codings(encoding).into_iter().rev() {
+            if coding == "identity" {
+                continue;
+            }
+            let output = match self.get(&coding) {
+                Some(codec) => codec.decode(&decoded, limits)?,
+                None => None,
+            };
+            match output {
+                Some(output) => decoded = Bytes::from(output),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(decoded))
+    }
+
+    /// Applies the codings listed in a `Content-Encoding` value in order.
+    /// `None` if one of them is unknown or cannot encode.
+    pub fn encode(&self, encoding: &str, body: &[u8]) -> Option<Bytes> {
+        let mut encoded = Bytes::copy_from_slice(body);
+        for coding in codings(encoding) {
+            if coding == "identity" {
+                continue;
+            }
+            encoded = Bytes::from(self.get(&coding)?.encode(&encoded)?);
+        }
+        Some(encoded)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<dyn ContentCodec>>> {
+        // The map stays consistent even if a holder panicked.
+        self.codecs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+fn codings(encoding: &str) -> Vec<String> {
+    encoding
+        .split(',')
+        .map(|coding| coding.trim().to_ascii_lowercase())
+        .filter(|coding| !coding.is_empty())
+        .collect()
+}
+
+/// Removes the codings listed in a `Content-Encoding` value with the codecs
+/// of [`CodecRegistry::global`]: `gzip` (`x-gzip`) and `deflate` unless more
+/// are registered. Returns `None` for an unsupported coding or a corrupt
+/// body and fails only when `limits` are exceeded.
+pub fn decode_content(
+    encoding: &str,
+    body: &[u8],
+    limits: &DecompressionLimits,
+) -> Result<Option<Bytes>, ProtocolError> {
+    CodecRegistry::global().decode(encoding, body, limits)
+}
+
+/// Drains `reader`, a decoder over `compressed` input bytes, checking
+/// `limits` after every chunk. `None` if the decoder fails.
+pub fn read_limited<R: Read>(
+    mut reader: R,
+    compressed: usize,
+    limits: &DecompressionLimits,
+) -> Result<Option<Vec<u8>>, ProtocolError> {
+    let mut guard = limits.guard();
+    guard.on_input(compressed);
+    let mut output = Vec::new();
+    let mut chunk = vec![0u8; DECODE_CHUNK];
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => return Ok(Some(output)),
+            Ok(read) => read,
+            Err(_) => return Ok(None),
+        };
+        guard.on_output(read)?;
+        output.extend_from_slice(&chunk[..read]);
+    }
+}
+
+impl Response {
+    /// The body with its `Content-Encoding` removed; the body itself when
+    /// there is none. `None` when the coding is unsupported or the body corrupt.
+    pub fn decode_content(
+        &self,
+        limits: &DecompressionLimits,
+    ) -> Result<Option<Bytes>, ProtocolError> {
+        match header_value(&self.headers, CONTENT_ENCODING_HEADER) {
+            Some(encoding) => decode_content(encoding, &self.body, limits),
+            None => Ok(Some(self.body.clone())),
+        }
+    }
+
+    /// Fills `decoded_body` for a compressed response whose frames were
+    /// captured, so frame and content analysis can work from one response.
+    pub fn capture_decoded_body(
+        &mut self,
+        limits: &DecompressionLimits,
+    ) -> Result<(), ProtocolError> {
+        let compressed = header_value(&self.headers, CONTENT_ENCODING_HEADER)
+            .map_or(false, |encoding| {
+                !encoding.trim().eq_ignore_ascii_case("identity")
+            });
+        if self.frames.is_some() && compressed {
+            self.decoded_body = self.decode_content(limits)?;
+        }
+        Ok(())
+    }
+}
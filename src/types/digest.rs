@@ -0,0 +1,243 @@
+use super::{Header, Response};
+use crate::utils::{base64_decode, base64_encode};
+use ring::digest::{digest, SHA256, SHA512};
+use std::fmt;
+
+const CONTENT_DIGEST_HEADER: &str = "content-digest";
+const REPR_DIGEST_HEADER: &str = "repr-digest";
+const DIGEST_HEADER: &str = "digest";
+
+/// The field a digest was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestField {
+    /// RFC 9530 `Content-Digest`, over the content as sent.
+    ContentDigest,
+    /// RFC 9530 `Repr-Digest`, over the whole selected representation.
+    ReprDigest,
+    /// RFC 3230 `Digest`, obsoleted by the two above but still common.
+    Digest,
+}
+
+impl DigestField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DigestField::ContentDigest => CONTENT_DIGEST_HEADER,
+            DigestField::ReprDigest => REPR_DIGEST_HEADER,
+            DigestField::Digest => DIGEST_HEADER,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            DigestField::ContentDigest,
+            DigestField::ReprDigest,
+            DigestField::Digest,
+        ]
+        .into_iter()
+        .find(|field| name.eq_ignore_ascii_case(field.name()))
+    }
+}
+
+/// Whether a digest came with the response headers or in its trailers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestSource {
+    Header,
+    Trailer,
+}
+
+impl fmt::Display for DigestSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DigestSource::Header => write!(f, "header"),
+            DigestSource::Trailer => write!(f, "trailer"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestOutcome {
+    Match,
+    Mismatch,
+    /// Neither SHA-256 nor SHA-512, e.g. the deprecated `md5` or `crc32c`.
+    UnsupportedAlgorithm,
+    /// The value is not a byte sequence or not valid base64.
+    Malformed,
+    /// `Repr-Digest` on a 206: it covers the whole representation, not the
+    /// part that was received.
+    Unverifiable,
+}
+
+/// One algorithm/value pair from a digest field and what came of checking it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestCheck {
+    pub field: DigestField,
+    pub source: DigestSource,
+    /// Algorithm as the server named it, lowercased.
+    pub algorithm: String,
+    /// Empty when the value could not be decoded.
+    pub expected: Vec<u8>,
+    /// Hash of the received body; `None` when it was not computed.
+    pub actual: Option<Vec<u8>>,
+    pub outcome: DigestOutcome,
+}
+
+/// A digest the body does not match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestMismatch {
+    pub field: DigestField,
+    pub source: DigestSource,
+    pub algorithm: String,
+    pub expected: Vec<u8>,
+    pub actual: Vec<u8>,
+}
+
+impl fmt::Display for DigestMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} mismatch\n  expected: {}\n    actual: {}",
+            self.field.name(),
+            self.source,
+            self.algorithm,
+            base64_encode(&self.expected),
+            base64_encode(&self.actual)
+        )
+    }
+}
+
+impl std::error::Error for DigestMismatch {}
+
+/// Every digest a response carried, checked against its body.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DigestVerification {
+    pub checks: Vec<DigestCheck>,
+}
+
+impl DigestVerification {
+    /// True when the response carried no digest at all.
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    /// At least one digest matched and none contradicted it.
+    pub fn is_verified(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.outcome == DigestOutcome::Match)
+            && !self.has_mismatch()
+    }
+
+    pub fn has_mismatch(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.outcome == DigestOutcome::Mismatch)
+    }
+
+    pub fn mismatches(&self) -> Vec<DigestMismatch> {
+        self.checks
+            .iter()
+            .filter(|check| check.outcome == DigestOutcome::Mismatch)
+            .map(|check| DigestMismatch {
+                field: check.field,
+                source: check.source,
+                algorithm: check.algorithm.clone(),
+                expected: check.expected.clone(),
+                actual: check.actual.clone().unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+impl Response {
+    /// Checks `Content-Digest`, `Repr-Digest` and `Digest` from both the
+    /// headers and the trailers against `body`, which holds the content as
+    /// received, content coding included, as both RFCs hash it.
+    pub fn verify_digest(&self) -> DigestVerification {
+        let mut checks = Vec::new();
+        let sources = [
+            (DigestSource::Header, self.headers.as_slice()),
+            (
+                DigestSource::Trailer,
+                self.trailers.as_deref().unwrap_or_default(),
+            ),
+        ];
+        for (source, headers) in sources {
+            for (field, value) in digest_fields(headers) {
+                for (algorithm, encoded) in digest_members(field, value) {
+                    checks.push(self.check_digest(field, source, algorithm, encoded));
+                }
+            }
+        }
+        DigestVerification { checks }
+    }
+
+    fn check_digest(
+        &self,
+        field: DigestField,
+        source: DigestSource,
+        algorithm: String,
+        encoded: Option<&str>,
+    ) -> DigestCheck {
+        let mut check = DigestCheck {
+            field,
+            source,
+            algorithm,
+            expected: Vec::new(),
+            actual: None,
+            outcome: DigestOutcome::Malformed,
+        };
+        check.expected = match encoded.and_then(base64_decode) {
+            Some(expected) => expected,
+            None => return check,
+        };
+        let hash = match check.algorithm.as_str() {
+            "sha-256" => digest(&SHA256, &self.body),
+            "sha-512" => digest(&SHA512, &self.body),
+            _ => {
+                check.outcome = DigestOutcome::UnsupportedAlgorithm;
+                return check;
+            }
+        };
+        if field == DigestField::ReprDigest && self.status == 206 {
+            check.outcome = DigestOutcome::Unverifiable;
+            return check;
+        }
+        let actual = hash.as_ref().to_vec();
+        check.outcome = if actual == check.expected {
+            DigestOutcome::Match
+        } else {
+            DigestOutcome::Mismatch
+        };
+        check.actual = Some(actual);
+        check
+    }
+}
+
+fn digest_fields(headers: &[Header]) -> impl Iterator<Item = (DigestField, &str)> {
+    headers.iter().filter_map(|header| {
+        let field = DigestField::from_name(&header.name)?;
+        Some((field, header.value.as_deref()?))
+    })
+}
+
+/// Splits a field value into `(algorithm, base64)` pairs. RFC 9530 values
+/// are structured-field dictionaries of byte sequences (`sha-256=:...:`);
+/// RFC 3230 values are bare base64 (`SHA-256=...`). Base64 holds no commas,
+/// so a plain split is enough for both.
+fn digest_members(field: DigestField, value: &str) -> Vec<(String, Option<&str>)> {
+    value
+        .split(',')
+        .filter_map(|member| {
+            let (algorithm, encoded) = member.split_once('=')?;
+            let algorithm = algorithm.trim().to_ascii_lowercase();
+            let encoded = encoded.split(';').next().unwrap_or_default().trim();
+            let encoded = match field {
+                DigestField::Digest => Some(encoded),
+                _ => encoded
+                    .strip_prefix(':')
+                    .and_then(|encoded| encoded.strip_suffix(':')),
+            };
+            Some((algorithm, encoded))
+        })
+        .collect()
+}
@@ -1,9 +1,15 @@
+use super::memory::MemoryUsage;
+use super::redirect::RedirectHop;
+use super::timeouts::TimeoutKind;
+
 #[derive(Debug)]
 pub enum ProtocolError {
     ConnectionFailed(String),
     RequestFailed(String),
     InvalidResponse(String),
-    Timeout,
+    /// A timeout ran out, with the phase when the path tracks it; HTTP/1.1
+    /// does, other paths report `None`.
+    Timeout(Option<TimeoutKind>),
     Io(std::io::Error),
 
     // HTTP/2 specific errors
@@ -13,6 +19,20 @@ pub enum ProtocolError {
     H2StreamError(H2StreamErrorKind),
     H2ConnectionError(H2ConnectionErrorKind),
     H2ProtocolError(String),
+    /// The peer answered the HTTP/2 preface with HTTP/1.x; carries the raw response head.
+    H2UnexpectedHttp1Response(Vec<u8>),
+    /// TLS did not settle on the protocol we asked for: the server chose
+    /// `negotiated` instead, or refused every offer when it is `None`.
+    AlpnMismatch {
+        negotiated: Option<String>,
+    },
+    /// Opening a stream would exceed the peer's MAX_CONCURRENT_STREAMS.
+    StreamLimitReached {
+        limit: u32,
+    },
+    /// A connection in step mode had to read from the peer; frames only
+    /// come in through `step_read`.
+    StepModeBlocked,
 
     // HTTP/3 specific errors
     H3StreamError(H3StreamErrorKind),
@@ -29,6 +49,182 @@ pub enum ProtocolError {
     InvalidMethod(String),
     InvalidTarget(String),
     InvalidProxy(String),
+
+    /// Signing a request or verifying a message signature failed.
+    SignatureError(String),
+
+    /// Decoding a compressed body exceeded the configured `DecompressionLimits`.
+    DecompressionLimitExceeded(DecompressionLimitKind),
+
+    /// An HTTP/1.1 response head broke the client's `HeaderLimits`, or an
+    /// HTTP/2 header list the SETTINGS_MAX_HEADER_LIST_SIZE it advertised.
+    HeaderLimitExceeded(HeaderLimitKind),
+
+    /// An `SsrfGuard` refused to connect to an address `host` resolved to.
+    AddressBlocked {
+        host: String,
+        addr: std::net::IpAddr,
+    },
+
+    /// A connection went over its `MemoryBudget` of `limit` bytes.
+    MemoryBudgetExceeded {
+        limit: usize,
+        usage: MemoryUsage,
+    },
+
+    /// A `HostScope` does not allow contacting `host`.
+    OutOfScope {
+        host: String,
+    },
+
+    /// A `TrafficMeter` quota is used up; `host` is `None` for the quota
+    /// across all hosts.
+    QuotaExceeded {
+        host: Option<String>,
+        limit: u64,
+        used: u64,
+    },
+
+    /// A redirect's `Location` could not be followed under a strict
+    /// `RedirectPolicy`.
+    InvalidLocation {
+        location: String,
+        kind: LocationErrorKind,
+    },
+
+    /// More redirects than the `RedirectPolicy` allows; `chain` holds every
+    /// request made, the first one included.
+    TooManyRedirects {
+        chain: Vec<RedirectHop>,
+    },
+
+    /// A redirect led back to a method and URL already requested in `chain`.
+    RedirectLoop {
+        chain: Vec<RedirectHop>,
+    },
+
+    /// No address of a host could be connected to; lists every attempt.
+    ConnectAttemptsFailed(ConnectFailure),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecompressionLimitKind {
+    Size {
+        limit: usize,
+        decoded: usize,
+    },
+    Ratio {
+        limit: u64,
+        compressed: usize,
+        decoded: usize,
+    },
+}
+
+/// `size` is how much had been read when the limit tripped; the rest of an
+/// oversized line is never read, so the real line may be longer still.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderLimitKind {
+    Line { limit: usize, size: usize },
+    Total { limit: usize, size: usize },
+}
+
+/// How a connect attempt tried to reach an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectMechanism {
+    Tcp,
+    Quic,
+    /// TCP to a proxy.
+    Proxy,
+}
+
+/// One address tried while connecting, and why it failed.
+#[derive(Debug, Clone)]
+pub struct ConnectAttempt {
+    pub addr: std::net::SocketAddr,
+    pub mechanism: ConnectMechanism,
+    pub kind: std::io::ErrorKind,
+    pub cause: String,
+    pub duration: std::time::Duration,
+}
+
+/// Every failed attempt to connect to `host`, in the order they were made.
+///
+/// Connectors hand it up inside an `io::Error`;
+/// [`ProtocolError::from_connect_error`] unwraps it again.
+#[derive(Debug, Clone)]
+pub struct ConnectFailure {
+    pub host: String,
+    pub attempts: Vec<ConnectAttempt>,
+}
+
+impl ConnectFailure {
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            attempts: Vec::new(),
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        addr: std::net::SocketAddr,
+        mechanism: ConnectMechanism,
+        error: &std::io::Error,
+        duration: std::time::Duration,
+    ) {
+        self.attempts.push(ConnectAttempt {
+            addr,
+            mechanism,
+            kind: error.kind(),
+            cause: error.to_string(),
+            duration,
+        });
+    }
+
+    /// Wraps the failure in an `io::Error` of the last attempt's kind.
+    pub fn into_io_error(self) -> std::io::Error {
+        let kind = self
+            .attempts
+            .last()
+            .map_or(std::io::ErrorKind::InvalidInput, |attempt| attempt.kind);
+        std::io::Error::new(kind, self)
+    }
+}
+
+impl std::fmt::Display for ConnectFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.attempts.is_empty() {
+            return write!(f, "No addresses to connect to for {}", self.host);
+        }
+        write!(f, "Could not connect to {}: ", self.host)?;
+        for (index, attempt) in self.attempts.iter().enumerate() {
+            if index > 0 {
+                write!(f, "; ")?;
+            }
+            write!(
+                f,
+                "{} over {:?} failed after {:?}: {}",
+                attempt.addr, attempt.mechanism, attempt.duration, attempt.cause
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConnectFailure {}
+
+/// Why a `Location` value was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocationErrorKind {
+    Empty,
+    /// Leading or trailing whitespace, refused in strict mode.
+    Whitespace,
+    /// A character no URI reference may contain, refused in strict mode.
+    InvalidCharacter(char),
+    /// The reference does not resolve against the request URL.
+    Unparsable(String),
+    /// Resolves to a scheme the client cannot request, e.g. `ftp` or `data`.
+    UnsupportedScheme(String),
 }
 
 #[derive(Debug)]
@@ -38,6 +234,13 @@ pub enum H2StreamErrorKind {
     StreamClosed,
     InvalidState(String),
     ProtocolViolation(String),
+    /// The stream's own deadline passed; it was reset with CANCEL.
+    DeadlineExceeded,
+    /// More was queued for the stream than its `StreamQueueLimits` allow.
+    QueueOverflow {
+        events: usize,
+        bytes: usize,
+    },
 }
 
 #[derive(Debug)]
@@ -46,6 +249,19 @@ pub enum H2ConnectionErrorKind {
     SettingsTimeout,
     ProtocolViolation(String),
     CompressionFailure,
+    /// The peer sent more control frames per second than `H2FloodLimits` allow.
+    FloodDetected {
+        kind: H2FloodKind,
+        limit: u32,
+    },
+}
+
+/// Control frame type counted by the HTTP/2 flood limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum H2FloodKind {
+    Ping,
+    Settings,
+    RstStream,
 }
 
 #[derive(Debug)]
@@ -54,6 +270,12 @@ pub enum H3StreamErrorKind {
     InvalidState(String),
     FlowControlViolation,
     ProtocolViolation(String),
+    /// The stream's own deadline passed; it was cancelled.
+    DeadlineExceeded,
+    /// A frame outgrew the stream's `StreamQueueLimits::max_bytes`.
+    QueueOverflow {
+        bytes: usize,
+    },
 }
 
 // HTTP/2 Error Codes (RFC 7540 Section 7)
@@ -81,7 +303,10 @@ impl std::fmt::Display for ProtocolError {
             ProtocolError::ConnectionFailed(msg) => write!(f, "Connection failed: {}", msg),
             ProtocolError::RequestFailed(msg) => write!(f, "Request failed: {}", msg),
             ProtocolError::InvalidResponse(msg) => write!(f, "Invalid response: {}", msg),
-            ProtocolError::Timeout => write!(f, "Request timeout"),
+            ProtocolError::Timeout(None) => write!(f, "Request timeout"),
+            ProtocolError::Timeout(Some(kind)) => {
+                write!(f, "Request timeout waiting for {}", kind)
+            }
             ProtocolError::Io(err) => write!(f, "IO error: {}", err),
 
             // HTTP/2 specific errors
@@ -97,6 +322,32 @@ impl std::fmt::Display for ProtocolError {
                 write!(f, "HTTP/2 connection error: {}", kind)
             }
             ProtocolError::H2ProtocolError(msg) => write!(f, "HTTP/2 protocol error: {}", msg),
+            ProtocolError::H2UnexpectedHttp1Response(raw) => {
+                let raw = String::from_utf8_lossy(raw);
+                write!(
+                    f,
+                    "HTTP/2 preface answered with HTTP/1.x: {}",
+                    raw.lines().next().unwrap_or_default()
+                )
+            }
+            ProtocolError::AlpnMismatch {
+                negotiated: Some(protocol),
+            } => write!(f, "Server negotiated '{}' via ALPN", protocol),
+            ProtocolError::AlpnMismatch { negotiated: None } => {
+                write!(f, "Server accepted none of the offered ALPN protocols")
+            }
+
+            ProtocolError::StreamLimitReached { limit } => write!(
+                f,
+                "HTTP/2 stream limit reached: peer allows {} concurrent streams",
+                limit
+            ),
+            ProtocolError::StepModeBlocked => {
+                write!(
+                    f,
+                    "Connection is in step mode: call step_read to receive frames"
+                )
+            }
 
             // HTTP/3 specific errors
             ProtocolError::H3StreamError(kind) => write!(f, "HTTP/3 stream error: {}", kind),
@@ -115,8 +366,81 @@ impl std::fmt::Display for ProtocolError {
             ProtocolError::InvalidMethod(msg) => write!(f, "Invalid method: {}", msg),
             ProtocolError::InvalidTarget(msg) => write!(f, "Invalid target: {}", msg),
             ProtocolError::InvalidProxy(msg) => write!(f, "Invalid proxy: {}", msg),
+
+            ProtocolError::SignatureError(msg) => write!(f, "Message signature error: {}", msg),
+            ProtocolError::DecompressionLimitExceeded(kind) => {
+                write!(f, "Decompression limit exceeded: {}", kind)
+            }
+            ProtocolError::HeaderLimitExceeded(kind) => {
+                write!(f, "Header limit exceeded: {}", kind)
+            }
+            ProtocolError::AddressBlocked { host, addr } => {
+                write!(f, "Connection to {} ({}) blocked by SSRF guard", host, addr)
+            }
+            ProtocolError::MemoryBudgetExceeded { limit, usage } => write!(
+                f,
+                "Connection memory budget of {} bytes exceeded: {}",
+                limit, usage
+            ),
+            ProtocolError::OutOfScope { host } => write!(f, "Host {} is out of scope", host),
+            ProtocolError::QuotaExceeded { host, limit, used } => match host {
+                Some(host) => write!(
+                    f,
+                    "Traffic quota for {} exceeded: {} of {} bytes used",
+                    host, used, limit
+                ),
+                None => write!(
+                    f,
+                    "Traffic quota exceeded: {} of {} bytes used",
+                    used, limit
+                ),
+            },
+            ProtocolError::InvalidLocation { location, kind } => {
+                write!(f, "Invalid Location '{}': {}", location, kind)
+            }
+            ProtocolError::TooManyRedirects { chain } => {
+                write!(f, "Too many redirects ({}): ", chain.len())?;
+                write_chain(f, chain)
+            }
+            ProtocolError::RedirectLoop { chain } => {
+                write!(f, "Redirect loop: ")?;
+                write_chain(f, chain)
+            }
+            ProtocolError::ConnectAttemptsFailed(failure) => write!(f, "{}", failure),
+        }
+    }
+}
+
+impl ProtocolError {
+    /// `ConnectAttemptsFailed` for an error carrying a [`ConnectFailure`],
+    /// `AlpnMismatch` for a TLS `no_application_protocol` alert and
+    /// `ConnectionFailed` for any other.
+    pub fn from_connect_error(err: std::io::Error) -> Self {
+        let inner = err.get_ref();
+        if let Some(failure) = inner.and_then(|inner| inner.downcast_ref::<ConnectFailure>()) {
+            return ProtocolError::ConnectAttemptsFailed(failure.clone());
+        }
+        match inner.and_then(|inner| inner.downcast_ref::<rustls::Error>()) {
+            Some(rustls::Error::AlertReceived(rustls::AlertDescription::NoApplicationProtocol)) => {
+                ProtocolError::AlpnMismatch { negotiated: None }
+            }
+            _ => ProtocolError::ConnectionFailed(err.to_string()),
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, ProtocolError::Timeout(_))
+    }
+}
+
+fn write_chain(f: &mut std::fmt::Formatter<'_>, chain: &[RedirectHop]) -> std::fmt::Result {
+    for (index, hop) in chain.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
         }
+        write!(f, "{}", hop)?;
     }
+    Ok(())
 }
 
 impl std::error::Error for ProtocolError {
@@ -136,6 +460,12 @@ impl std::fmt::Display for H2StreamErrorKind {
             H2StreamErrorKind::StreamClosed => write!(f, "stream closed"),
             H2StreamErrorKind::InvalidState(msg) => write!(f, "invalid stream state: {}", msg),
             H2StreamErrorKind::ProtocolViolation(msg) => write!(f, "protocol violation: {}", msg),
+            H2StreamErrorKind::DeadlineExceeded => write!(f, "stream deadline exceeded"),
+            H2StreamErrorKind::QueueOverflow { events, bytes } => write!(
+                f,
+                "stream queue overflowed with {} events of {} bytes",
+                events, bytes
+            ),
         }
     }
 }
@@ -155,6 +485,67 @@ impl std::fmt::Display for H2ConnectionErrorKind {
                 write!(f, "protocol violation: {}", msg)
             }
             H2ConnectionErrorKind::CompressionFailure => write!(f, "header compression failure"),
+            H2ConnectionErrorKind::FloodDetected { kind, limit } => {
+                write!(f, "{} flood: more than {} per second", kind, limit)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for H2FloodKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            H2FloodKind::Ping => write!(f, "PING"),
+            H2FloodKind::Settings => write!(f, "SETTINGS"),
+            H2FloodKind::RstStream => write!(f, "RST_STREAM"),
+        }
+    }
+}
+
+impl std::fmt::Display for DecompressionLimitKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecompressionLimitKind::Size { limit, decoded } => write!(
+                f,
+                "decoded body reached {} bytes (limit {})",
+                decoded, limit
+            ),
+            DecompressionLimitKind::Ratio {
+                limit,
+                compressed,
+                decoded,
+            } => write!(
+                f,
+                "{} compressed bytes expanded to {} (ratio limit {}:1)",
+                compressed, decoded, limit
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for LocationErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocationErrorKind::Empty => write!(f, "empty value"),
+            LocationErrorKind::Whitespace => write!(f, "surrounded by whitespace"),
+            LocationErrorKind::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+            LocationErrorKind::Unparsable(err) => write!(f, "{}", err),
+            LocationErrorKind::UnsupportedScheme(scheme) => {
+                write!(f, "unsupported scheme '{}'", scheme)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for HeaderLimitKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderLimitKind::Line { limit, size } => {
+                write!(f, "header line reached {} bytes (limit {})", size, limit)
+            }
+            HeaderLimitKind::Total { limit, size } => {
+                write!(f, "response head reached {} bytes (limit {})", size, limit)
+            }
         }
     }
 }
@@ -166,6 +557,10 @@ impl std::fmt::Display for H3StreamErrorKind {
             H3StreamErrorKind::InvalidState(msg) => write!(f, "invalid stream state: {}", msg),
             H3StreamErrorKind::FlowControlViolation => write!(f, "flow control violation"),
             H3StreamErrorKind::ProtocolViolation(msg) => write!(f, "protocol violation: {}", msg),
+            H3StreamErrorKind::DeadlineExceeded => write!(f, "stream deadline exceeded"),
+            H3StreamErrorKind::QueueOverflow { bytes } => {
+                write!(f, "stream receive buffer overflowed at {} bytes", bytes)
+            }
         }
     }
 }
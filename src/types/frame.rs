@@ -20,6 +20,9 @@ pub enum FrameTypeH2 {
     GoAway,       // 0x7
     WindowUpdate, // 0x8
     Continuation, // 0x9
+    /// A type this library does not handle, such as an extension frame
+    /// (ALTSVC, ORIGIN, PRIORITY_UPDATE) or a GREASE value.
+    Unknown(u8),
 }
 
 #[derive(Debug, Clone)]
@@ -31,7 +34,16 @@ pub enum FrameTypeH3 {
     PushPromise, // 0x5
     GoAway,      // 0x7
     MaxPushId,   // 0xd
+    /// RFC 9218 PRIORITY_UPDATE for a request stream, 0xf0700. The one
+    /// for push streams stays `Unknown(0xf0701)`.
+    PriorityUpdate,
     Unknown(u64),
+    /// Not a frame: instructions read from the peer's QPACK encoder stream,
+    /// captured with the response whose header block they were read for.
+    QpackEncoder,
+    /// Not a frame: instructions read from the peer's QPACK decoder stream,
+    /// captured like `QpackEncoder`.
+    QpackDecoder,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,317 @@
+use super::Header;
+use bytes::Bytes;
+
+/// How a single field line was represented on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldRepresentation {
+    /// The whole field came from a table entry.
+    Indexed,
+    /// The name came from a table entry, the value was sent literally.
+    LiteralIndexedName,
+    /// Both name and value were sent literally.
+    LiteralName,
+}
+
+/// Table an index refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableIndex {
+    Static(u64),
+    /// HPACK: dynamic table offset (0 = newest). QPACK: relative or post-base index.
+    Dynamic(u64),
+}
+
+/// Wire metadata for one decoded header field.
+#[derive(Debug, Clone)]
+pub struct HeaderFieldInfo {
+    pub header: Header,
+    pub representation: FieldRepresentation,
+    pub index: Option<TableIndex>,
+    /// HPACK "literal with incremental indexing": the field was added to the dynamic table.
+    pub adds_to_table: bool,
+    /// Never-indexed / `N` bit: intermediaries must not compress this field.
+    pub never_indexed: bool,
+    pub huffman_name: bool,
+    pub huffman_value: bool,
+}
+
+/// Raw encoded header block plus its decoded, annotated form.
+#[derive(Debug, Clone)]
+pub struct HeaderBlockInfo {
+    pub stream_id: u32,
+    pub raw: Bytes,
+    /// Empty when the block could not be annotated (e.g. it was malformed).
+    pub fields: Vec<HeaderFieldInfo>,
+    /// HPACK dynamic table size updates at the start of the block.
+    pub table_size_updates: Vec<u64>,
+    /// QPACK encoded field section prefix: (Required Insert Count, sign bit, Delta Base).
+    pub qpack_prefix: Option<(u64, bool, u64)>,
+}
+
+struct FieldLine {
+    representation: FieldRepresentation,
+    index: Option<TableIndex>,
+    adds_to_table: bool,
+    never_indexed: bool,
+    huffman_name: bool,
+    huffman_value: bool,
+}
+
+impl HeaderBlockInfo {
+    /// Annotates an HPACK header block (RFC 7541 Section 6) with the headers it decoded to.
+    pub fn hpack(stream_id: u32, raw: Bytes, decoded: &[Header]) -> Self {
+        let mut info = Self::empty(stream_id, raw);
+        let mut updates = Vec::new();
+        if let Some(lines) = scan_hpack(&info.raw, &mut updates) {
+            info.fields = Self::zip(lines, decoded);
+        }
+        info.table_size_updates = updates;
+        info
+    }
+
+    /// Annotates a QPACK encoded field section (RFC 9204 Section 4.5).
+    pub fn qpack(stream_id: u32, raw: Bytes, decoded: &[Header]) -> Self {
+        let mut info = Self::empty(stream_id, raw);
+        if let Some((prefix, lines)) = scan_qpack(&info.raw) {
+            info.qpack_prefix = Some(prefix);
+            info.fields = Self::zip(lines, decoded);
+        }
+        info
+    }
+
+    fn empty(stream_id: u32, raw: Bytes) -> Self {
+        Self {
+            stream_id,
+            raw,
+            fields: Vec::new(),
+            table_size_updates: Vec::new(),
+            qpack_prefix: None,
+        }
+    }
+
+    fn zip(lines: Vec<FieldLine>, decoded: &[Header]) -> Vec<HeaderFieldInfo> {
+        if lines.len() != decoded.len() {
+            return Vec::new();
+        }
+        lines
+            .into_iter()
+            .zip(decoded.iter().cloned())
+            .map(|(line, header)| HeaderFieldInfo {
+                header,
+                representation: line.representation,
+                index: line.index,
+                adds_to_table: line.adds_to_table,
+                never_indexed: line.never_indexed,
+                huffman_name: line.huffman_name,
+                huffman_value: line.huffman_value,
+            })
+            .collect()
+    }
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// Prefix integer (RFC 7541 Section 5.1), shared by HPACK and QPACK.
+    fn integer(&mut self, prefix_bits: u8) -> Option<u64> {
+        let mask = ((1u16 << prefix_bits) - 1) as u8;
+        let first = self.peek()? & mask;
+        self.pos += 1;
+        if first < mask {
+            return Some(first as u64);
+        }
+        let mut value = mask as u64;
+        let mut shift = 0u32;
+        loop {
+            let byte = self.peek()?;
+            self.pos += 1;
+            value = value.checked_add(((byte & 0x7f) as u64).checked_shl(shift)?)?;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+            shift += 7;
+            if shift > 56 {
+                return None;
+            }
+        }
+    }
+
+    /// Skips a string literal whose Huffman flag sits just above the length prefix.
+    fn string(&mut self, prefix_bits: u8) -> Option<bool> {
+        let huffman = self.peek()? & (1 << prefix_bits) != 0;
+        let len = self.integer(prefix_bits)? as usize;
+        if self.pos + len > self.data.len() {
+            return None;
+        }
+        self.pos += len;
+        Some(huffman)
+    }
+}
+
+fn literal(
+    cursor: &mut Cursor<'_>,
+    name_index: Option<TableIndex>,
+    adds_to_table: bool,
+    never_indexed: bool,
+) -> Option<FieldLine> {
+    let huffman_name = match name_index {
+        Some(_) => false,
+        None => cursor.string(7)?,
+    };
+    let huffman_value = cursor.string(7)?;
+    Some(FieldLine {
+        representation: if name_index.is_some() {
+            FieldRepresentation::LiteralIndexedName
+        } else {
+            FieldRepresentation::LiteralName
+        },
+        index: name_index,
+        adds_to_table,
+        never_indexed,
+        huffman_name,
+        huffman_value,
+    })
+}
+
+fn hpack_index(index: u64) -> Option<TableIndex> {
+    match index {
+        0 => None,
+        1..=61 => Some(TableIndex::Static(index)),
+        _ => Some(TableIndex::Dynamic(index - 62)),
+    }
+}
+
+fn scan_hpack(block: &[u8], updates: &mut Vec<u64>) -> Option<Vec<FieldLine>> {
+    let mut cursor = Cursor {
+        data: block,
+        pos: 0,
+    };
+    let mut lines = Vec::new();
+    while cursor.peek().is_some() {
+        lines.extend(hpack_line(&mut cursor, updates)?);
+    }
+    Some(lines)
+}
+
+/// Length of the longest prefix of `block` made of whole HPACK field lines
+/// and table size updates, i.e. how much of a partly received header block
+/// can be decoded already.
+pub fn hpack_complete_len(block: &[u8]) -> usize {
+    let mut cursor = Cursor {
+        data: block,
+        pos: 0,
+    };
+    let mut complete = 0;
+    while cursor.peek().is_some() && hpack_line(&mut cursor, &mut Vec::new()).is_some() {
+        complete = cursor.pos;
+    }
+    complete
+}
+
+/// Reads one representation: `Some(None)` for a table size update, which
+/// goes to `updates`, and `None` if the block ends within it.
+fn hpack_line(cursor: &mut Cursor<'_>, updates: &mut Vec<u64>) -> Option<Option<FieldLine>> {
+    let byte = cursor.peek()?;
+    let line = if byte & 0x80 != 0 {
+        let index = hpack_index(cursor.integer(7)?)?;
+        FieldLine {
+            representation: FieldRepresentation::Indexed,
+            index: Some(index),
+            adds_to_table: false,
+            never_indexed: false,
+            huffman_name: false,
+            huffman_value: false,
+        }
+    } else if byte & 0x40 != 0 {
+        let index = hpack_index(cursor.integer(6)?);
+        literal(cursor, index, true, false)?
+    } else if byte & 0x20 != 0 {
+        updates.push(cursor.integer(5)?);
+        return Some(None);
+    } else {
+        let never_indexed = byte & 0x10 != 0;
+        let index = hpack_index(cursor.integer(4)?);
+        literal(cursor, index, false, never_indexed)?
+    };
+    Some(Some(line))
+}
+
+fn scan_qpack(block: &[u8]) -> Option<((u64, bool, u64), Vec<FieldLine>)> {
+    let mut cursor = Cursor {
+        data: block,
+        pos: 0,
+    };
+    let required_insert_count = cursor.integer(8)?;
+    let sign = cursor.peek()? & 0x80 != 0;
+    let delta_base = cursor.integer(7)?;
+    let mut lines = Vec::new();
+
+    while let Some(byte) = cursor.peek() {
+        let line = if byte & 0x80 != 0 {
+            let is_static = byte & 0x40 != 0;
+            let index = cursor.integer(6)?;
+            FieldLine {
+                representation: FieldRepresentation::Indexed,
+                index: Some(if is_static {
+                    TableIndex::Static(index)
+                } else {
+                    TableIndex::Dynamic(index)
+                }),
+                adds_to_table: false,
+                never_indexed: false,
+                huffman_name: false,
+                huffman_value: false,
+            }
+        } else if byte & 0x40 != 0 {
+            let never_indexed = byte & 0x20 != 0;
+            let is_static = byte & 0x10 != 0;
+            let index = cursor.integer(4)?;
+            let index = if is_static {
+                TableIndex::Static(index)
+            } else {
+                TableIndex::Dynamic(index)
+            };
+            literal(&mut cursor, Some(index), false, never_indexed)?
+        } else if byte & 0x20 != 0 {
+            let never_indexed = byte & 0x10 != 0;
+            let huffman_name = cursor.string(3)?;
+            let huffman_value = cursor.string(7)?;
+            FieldLine {
+                representation: FieldRepresentation::LiteralName,
+                index: None,
+                adds_to_table: false,
+                never_indexed,
+                huffman_name,
+                huffman_value,
+            }
+        } else if byte & 0x10 != 0 {
+            let index = cursor.integer(4)?;
+            FieldLine {
+                representation: FieldRepresentation::Indexed,
+                index: Some(TableIndex::Dynamic(index)),
+                adds_to_table: false,
+                never_indexed: false,
+                huffman_name: false,
+                huffman_value: false,
+            }
+        } else {
+            let never_indexed = byte & 0x08 != 0;
+            let index = cursor.integer(3)?;
+            literal(
+                &mut cursor,
+                Some(TableIndex::Dynamic(index)),
+                false,
+                never_indexed,
+            )?
+        };
+        lines.push(line);
+    }
+
+    Some(((required_insert_count, sign, delta_base), lines))
+}
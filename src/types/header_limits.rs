@@ -0,0 +1,54 @@
+use super::error::{HeaderLimitKind, ProtocolError};
+
+/// Caps on an HTTP/1.1 response head, so a server streaming an endless
+/// header line or an unbounded number of headers cannot grow the client's
+/// buffers without end. Set through `ClientConfig::header_limits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderLimits {
+    /// Longest status, header or trailer line, line ending included.
+    pub max_line: Option<usize>,
+    /// Largest response head: status line plus header lines, per response.
+    pub max_total: Option<usize>,
+}
+
+impl Default for HeaderLimits {
+    fn default() -> Self {
+        Self {
+            max_line: Some(64 * 1024),
+            max_total: Some(256 * 1024),
+        }
+    }
+}
+
+impl HeaderLimits {
+    pub fn unlimited() -> Self {
+        Self {
+            max_line: None,
+            max_total: None,
+        }
+    }
+
+    pub fn check_line(&self, size: usize) -> Result<(), ProtocolError> {
+        match self.max_line {
+            Some(limit) if size > limit => {
+                Err(ProtocolError::HeaderLimitExceeded(HeaderLimitKind::Line {
+                    limit,
+                    size,
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_total(&self, size: usize) -> Result<(), ProtocolError> {
+        match self.max_total {
+            Some(limit) if size > limit => {
+                Err(ProtocolError::HeaderLimitExceeded(HeaderLimitKind::Total {
+                    limit,
+                    size,
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use super::{Header, HttpProtocol};
+
+/// Header sets that make a request look like it came from a common client.
+///
+/// Each profile fills in `User-Agent`, `Accept`, `Accept-Language`,
+/// `Accept-Encoding` and, for browsers, the `sec-ch-ua` and `sec-fetch-*`
+/// headers of a top-level navigation, then orders every header the way that
+/// client does on the wire. HTTP/2 and HTTP/3 share an order; HTTP/1.1 adds
+/// `Host` and `Connection` and uses the client's own capitalization.
+///
+/// Browsers advertise `br` and `zstd`; override `Accept-Encoding` if the
+/// response body must be readable as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeaderProfile {
+    Chrome,
+    Firefox,
+    Safari,
+    Curl,
+}
+
+/// A header slot: the name as the client writes it and the value it sends
+/// by default. Slots without a value only fix the position
+/// of a header the request supplies, such as `Cookie`.
+type Slot = (&'static str, Option<&'static str>);
+
+const CHROME_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36";
+const CHROME_SEC_CH_UA: &str =
+    "\"Google Chrome\";v=\"131\", \"Chromium\";v=\"131\", \"Not_A Brand\";v=\"24\"";
+const CHROME_ACCEPT: &str = "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7";
+
+const FIREFOX_USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:133.0) Gecko/20100101 Firefox/133.0";
+const SAFARI_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.1 Safari/605.1.15";
+const DOCUMENT_ACCEPT: &str = "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8";
+
+const CURL_USER_AGENT: &str = "curl/8.11.0";
+
+const CHROME_H1: &[Slot] = &[
+    ("Host", None),
+    ("Connection", Some("keep-alive")),
+    ("sec-ch-ua", Some(CHROME_SEC_CH_UA)),
+    ("sec-ch-ua-mobile", Some("?0")),
+    ("sec-ch-ua-platform", Some("\"Windows\"")),
+    ("Upgrade-Insecure-Requests", Some("1")),
+    ("User-Agent", Some(CHROME_USER_AGENT)),
+    ("Accept", Some(CHROME_ACCEPT)),
+    ("Sec-Fetch-Site", Some("none")),
+    ("Sec-Fetch-Mode", Some("navigate")),
+    ("Sec-Fetch-User", Some("?1")),
+    ("Sec-Fetch-Dest", Some("document")),
+    ("Accept-Encoding", Some("gzip, deflate, br, zstd")),
+    ("Accept-Language", Some("en-US,en;q=0.9")),
+    ("Cookie", None),
+];
+
+const CHROME_H2: &[Slot] = &[
+    ("sec-ch-ua", Some(CHROME_SEC_CH_UA)),
+    ("sec-ch-ua-mobile", Some("?0")),
+    ("sec-ch-ua-platform", Some("\"Windows\"")),
+    ("upgrade-insecure-requests", Some("1")),
+    ("user-agent", Some(CHROME_USER_AGENT)),
+    ("accept", Some(CHROME_ACCEPT)),
+    ("sec-fetch-site", Some("none")),
+    ("sec-fetch-mode", Some("navigate")),
+    ("sec-fetch-user", Some("?1")),
+    ("sec-fetch-dest", Some("document")),
+    ("accept-encoding", Some("gzip, deflate, br, zstd")),
+    ("accept-language", Some("en-US,en;q=0.9")),
+    ("cookie", None),
+    ("priority", Some("u=0, i")),
+];
+
+const FIREFOX_H1: &[Slot] = &[
+    ("Host", None),
+    ("User-Agent", Some(FIREFOX_USER_AGENT)),
+    ("Accept", Some(DOCUMENT_ACCEPT)),
+    ("Accept-Language", Some("en-US,en;q=0.5")),
+    ("Accept-Encoding", Some("gzip, deflate, br, zstd")),
+    ("Connection", Some("keep-alive")),
+    ("Cookie", None),
+    ("Upgrade-Insecure-Requests", Some("1")),
+    ("Sec-Fetch-Dest", Some("document")),
+    ("Sec-Fetch-Mode", Some("navigate")),
+    ("Sec-Fetch-Site", Some("none")),
+    ("Sec-Fetch-User", Some("?1")),
+    ("Priority", Some("u=0, i")),
+];
+
+const FIREFOX_H2: &[Slot] = &[
+    ("user-agent", Some(FIREFOX_USER_AGENT)),
+    ("accept", Some(DOCUMENT_ACCEPT)),
+    ("accept-language", Some("en-US,en;q=0.5")),
+    ("accept-encoding", Some("gzip, deflate, br, zstd")),
+    ("cookie", None),
+    ("upgrade-insecure-requests", Some("1")),
+    ("sec-fetch-dest", Some("document")),
+    ("sec-fetch-mode", Some("navigate")),
+    ("sec-fetch-site", Some("none")),
+    ("sec-fetch-user", Some("?1")),
+    ("priority", Some("u=0, i")),
+    ("te", Some("trailers")),
+];
+
+const SAFARI_H1: &[Slot] = &[
+    ("Host", None),
+    ("Accept", Some(DOCUMENT_ACCEPT)),
+    ("Sec-Fetch-Site", Some("none")),
+    ("Cookie", None),
+    ("Sec-Fetch-Dest", Some("document")),
+    ("Accept-Language", Some("en-US,en;q=0.9")),
+    ("Sec-Fetch-Mode", Some("navigate")),
+    ("User-Agent", Some(SAFARI_USER_AGENT)),
+    ("Accept-Encoding", Some("gzip, deflate, br")),
+    ("Connection", Some("keep-alive")),
+];
+
+const SAFARI_H2: &[Slot] = &[
+    ("accept", Some(DOCUMENT_ACCEPT)),
+    ("sec-fetch-site", Some("none")),
+    ("cookie", None),
+    ("sec-fetch-dest", Some("document")),
+    ("accept-language", Some("en-US,en;q=0.9")),
+    ("sec-fetch-mode", Some("navigate")),
+    ("user-agent", Some(SAFARI_USER_AGENT)),
+    ("accept-encoding", Some("gzip, deflate, br")),
+    ("priority", Some("u=0, i")),
+];
+
+const CURL_H1: &[Slot] = &[
+    ("Host", None),
+    ("User-Agent", Some(CURL_USER_AGENT)),
+    ("Accept", Some("*/*")),
+];
+
+const CURL_H2: &[Slot] = &[
+    ("user-agent", Some(CURL_USER_AGENT)),
+    ("accept", Some("*/*")),
+];
+
+impl HeaderProfile {
+    pub const ALL: [HeaderProfile; 4] = [
+        HeaderProfile::Chrome,
+        HeaderProfile::Firefox,
+        HeaderProfile::Safari,
+        HeaderProfile::Curl,
+    ];
+
+    pub fn user_agent(&self) -> &'static str {
+        match self {
+            HeaderProfile::Chrome => CHROME_USER_AGENT,
+            HeaderProfile::Firefox => FIREFOX_USER_AGENT,
+            HeaderProfile::Safari => SAFARI_USER_AGENT,
+            HeaderProfile::Curl => CURL_USER_AGENT,
+        }
+    }
+
+    fn slots(&self, protocol: &HttpProtocol) -> &'static [Slot] {
+        let h1 = *protocol == HttpProtocol::Http1;
+        match (self, h1) {
+            (HeaderProfile::Chrome, true) => CHROME_H1,
+            (HeaderProfile::Chrome, false) => CHROME_H2,
+            (HeaderProfile::Firefox, true) => FIREFOX_H1,
+            (HeaderProfile::Firefox, false) => FIREFOX_H2,
+            (HeaderProfile::Safari, true) => SAFARI_H1,
+            (HeaderProfile::Safari, false) => SAFARI_H2,
+            (HeaderProfile::Curl, true) => CURL_H1,
+            (HeaderProfile::Curl, false) => CURL_H2,
+        }
+    }
+
+    /// The profile's default headers in wire order for `protocol`.
+    pub fn headers(&self, protocol: &HttpProtocol) -> Vec<Header> {
+        self.slots(protocol)
+            .iter()
+            .filter_map(|(name, value)| {
+                value.map(|value| Header::new(name.to_string(), value.to_string()))
+            })
+            .collect()
+    }
+
+    /// Merges `headers` into the profile: a header the caller set replaces
+    /// the profile's default in its slot, defaults fill the remaining slots
+    /// and headers the profile does not know follow in their original order.
+    /// `skip` names profile defaults to leave out, e.g. an omitted `User-Agent`.
+    pub fn arrange(
+        &self,
+        headers: Vec<Header>,
+        protocol: &HttpProtocol,
+        skip: &[&str],
+    ) -> Vec<Header> {
+        let mut remaining: Vec<Option<Header>> = headers.into_iter().map(Some).collect();
+        let mut arranged = Vec::with_capacity(remaining.len());
+        for (name, default) in self.slots(protocol) {
+            let mut supplied = false;
+            for slot in remaining.iter_mut() {
+                if slot
+                    .as_ref()
+                    .map_or(false, |h| h.name.eq_ignore_ascii_case(name))
+                {
+                    arranged.extend(slot.take());
+                    supplied = true;
+                }
+            }
+            let skipped = skip.iter().any(|s| s.eq_ignore_ascii_case(name));
+            if let (false, false, Some(value)) = (supplied, skipped, default) {
+                arranged.push(Header::new(name.to_string(), value.to_string()));
+            }
+        }
+        arranged.extend(remaining.into_iter().flatten());
+        arranged
+    }
+}
+
+impl std::fmt::Display for HeaderProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            HeaderProfile::Chrome => "Chrome",
+            HeaderProfile::Firefox => "Firefox",
+            HeaderProfile::Safari => "Safari",
+            HeaderProfile::Curl => "curl",
+        };
+        write!(f, "{}", label)
+    }
+}
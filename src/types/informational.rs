@@ -0,0 +1,42 @@
+use super::Header;
+use std::fmt;
+use std::sync::Arc;
+
+/// A 1xx response received ahead of the final one.
+#[derive(Debug, Clone)]
+pub struct InformationalResponse {
+    pub status: u16,
+    pub headers: Vec<Header>,
+    /// Sent by the proxy while the CONNECT tunnel was being set up rather
+    /// than by the origin.
+    pub from_proxy: bool,
+}
+
+/// Callback set with `Request::on_informational`.
+#[derive(Clone)]
+pub struct InformationalHandler(Arc<dyn Fn(&InformationalResponse) + Send + Sync>);
+
+impl InformationalHandler {
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&InformationalResponse) + Send + Sync + 'static,
+    {
+        Self(Arc::new(handler))
+    }
+
+    pub fn call(&self, response: &InformationalResponse) {
+        (self.0)(response)
+    }
+}
+
+impl fmt::Debug for InformationalHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("InformationalHandler(..)")
+    }
+}
+
+/// 1xx statuses that are followed by another response. `101 Switching
+/// Protocols` is final: whatever comes next is no longer HTTP/1.1.
+pub fn is_interim_status(status: u16) -> bool {
+    (100..200).contains(&status) && status != 101
+}
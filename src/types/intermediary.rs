@@ -0,0 +1,214 @@
+use super::{Header, Response};
+
+/// Headers a proxy, cache or CDN adds on the way back to the client.
+const INTERMEDIARY_HEADERS: &[&str] = &[
+    "via",
+    "age",
+    "forwarded",
+    "x-forwarded-for",
+    "x-forwarded-host",
+    "x-forwarded-proto",
+    "x-real-ip",
+    "x-cache",
+    "x-cache-hits",
+    "x-cache-status",
+    "x-proxy-cache",
+    "cache-status",
+    "x-served-by",
+];
+
+/// Cache verdict headers, most specific first.
+const CACHE_STATUS_HEADERS: &[&str] = &[
+    "cache-status",
+    "cf-cache-status",
+    "x-vercel-cache",
+    "x-cache-status",
+    "x-proxy-cache",
+    "x-cache",
+];
+
+/// `(header, value substring, vendor)`: the header alone is enough when the
+/// substring is empty. Values are matched case-insensitively.
+const VENDOR_FINGERPRINTS: &[(&str, &str, &str)] = &[
+    ("cf-ray", "", "Cloudflare"),
+    ("cf-cache-status", "", "Cloudflare"),
+    ("server", "cloudflare", "Cloudflare"),
+    ("x-amz-cf-id", "", "CloudFront"),
+    ("x-amz-cf-pop", "", "CloudFront"),
+    ("via", "cloudfront", "CloudFront"),
+    ("x-fastly-request-id", "", "Fastly"),
+    ("fastly-debug-digest", "", "Fastly"),
+    ("x-served-by", "cache-", "Fastly"),
+    ("x-akamai-transformed", "", "Akamai"),
+    ("akamai-grn", "", "Akamai"),
+    ("server", "akamaighost", "Akamai"),
+    ("x-azure-ref", "", "Azure Front Door"),
+    ("x-msedge-ref", "", "Azure Front Door"),
+    ("via", "google", "Google Cloud"),
+    ("x-vercel-id", "", "Vercel"),
+    ("x-vercel-cache", "", "Vercel"),
+    ("x-nf-request-id", "", "Netlify"),
+    ("x-sucuri-id", "", "Sucuri"),
+    ("x-iinfo", "", "Imperva"),
+    ("x-cdn", "incapsula", "Imperva"),
+    ("x-varnish", "", "Varnish"),
+    ("via", "varnish", "Varnish"),
+    ("x-squid-error", "", "Squid"),
+    ("via", "squid", "Squid"),
+];
+
+/// One `Via` entry (RFC 9110 section 7.6.3).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViaHop {
+    /// Protocol the hop received the message with, e.g. `1.1` or `HTTP/2`.
+    pub protocol: String,
+    /// Host or pseudonym of the hop.
+    pub received_by: String,
+    /// Parenthesized comment, often the software, without the parentheses.
+    pub comment: Option<String>,
+}
+
+/// A header that gave an intermediary away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntermediaryFingerprint {
+    /// Lowercased header name.
+    pub header: String,
+    pub value: String,
+    /// Product the header belongs to, when it is specific to one.
+    pub vendor: Option<&'static str>,
+}
+
+/// What a response tells about the proxies, caches and CDNs it went through.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntermediaryReport {
+    /// `Via` entries, from the hop nearest the origin to the one nearest the
+    /// client.
+    pub via: Vec<ViaHop>,
+    /// Vendors fingerprinted, in order of first evidence, without duplicates.
+    pub vendors: Vec<&'static str>,
+    /// Cache verdict such as `HIT` or `MISS`, as sent.
+    pub cache_status: Option<String>,
+    /// `Age` in seconds: how long the response sat in a cache.
+    pub age: Option<u64>,
+    /// Every header that points at an intermediary, in response order.
+    pub fingerprints: Vec<IntermediaryFingerprint>,
+}
+
+impl IntermediaryReport {
+    pub fn from_headers(headers: &[Header]) -> Self {
+        let mut report = IntermediaryReport::default();
+        for header in headers {
+            let name = header.name.to_ascii_lowercase();
+            let value = header.value.clone().unwrap_or_default();
+            let lower_value = value.to_ascii_lowercase();
+
+            let vendor = VENDOR_FINGERPRINTS
+                .iter()
+                .find(|(known, needle, _)| *known == name && lower_value.contains(needle))
+                .map(|(_, _, vendor)| *vendor);
+            if vendor.is_none() && !INTERMEDIARY_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+            if let Some(vendor) = vendor {
+                if !report.vendors.contains(&vendor) {
+                    report.vendors.push(vendor);
+                }
+            }
+
+            match name.as_str() {
+                "via" => report.via.extend(parse_via(&value)),
+                "age" => report.age = value.trim().parse().ok(),
+                _ => {}
+            }
+            report.fingerprints.push(IntermediaryFingerprint {
+                header: name,
+                value,
+                vendor,
+            });
+        }
+
+        report.cache_status = CACHE_STATUS_HEADERS.iter().find_map(|name| {
+            report
+                .fingerprints
+                .iter()
+                .find(|fingerprint| fingerprint.header == *name)
+                .map(|fingerprint| fingerprint.value.clone())
+        });
+        report
+    }
+
+    /// True if nothing points at an intermediary.
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty()
+    }
+
+    /// Inferred hops from the origin towards the client: the `Via` entries,
+    /// then vendors no `Via` entry names, e.g. `["1.1 varnish", "Cloudflare"]`.
+    pub fn chain(&self) -> Vec<String> {
+        let mut chain: Vec<String> = self
+            .via
+            .iter()
+            .map(|hop| format!("{} {}", hop.protocol, hop.received_by))
+            .collect();
+        for vendor in &self.vendors {
+            let vendor_lower = vendor.to_ascii_lowercase();
+            let named = self.via.iter().any(|hop| {
+                hop.received_by.to_ascii_lowercase().contains(&vendor_lower)
+                    || hop.comment.as_ref().map_or(false, |comment| {
+                        comment.to_ascii_lowercase().contains(&vendor_lower)
+                    })
+            });
+            if !named {
+                chain.push(vendor.to_string());
+            }
+        }
+        chain
+    }
+}
+
+impl Response {
+    /// Proxies, caches and CDNs the response shows signs of.
+    pub fn intermediaries(&self) -> IntermediaryReport {
+        IntermediaryReport::from_headers(&self.headers)
+    }
+}
+
+/// Splits a `Via` value into hops. Commas inside comments do not split.
+pub fn parse_via(value: &str) -> Vec<ViaHop> {
+    let mut entries = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                entries.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(&value[start..]);
+
+    entries.into_iter().filter_map(parse_via_hop).collect()
+}
+
+fn parse_via_hop(entry: &str) -> Option<ViaHop> {
+    let entry = entry.trim();
+    let (head, comment) = match entry.find('(') {
+        Some(open) => {
+            let comment = entry[open + 1..].trim_end().trim_end_matches(')');
+            (&entry[..open], Some(comment.trim().to_string()))
+        }
+        None => (entry, None),
+    };
+    let mut parts = head.split_whitespace();
+    let protocol = parts.next()?.to_string();
+    let received_by = parts.next()?.to_string();
+    Some(ViaHop {
+        protocol,
+        received_by,
+        comment,
+    })
+}
@@ -0,0 +1,156 @@
+use super::error::ProtocolError;
+use std::fmt;
+
+/// What a connection does once it holds more than its [`MemoryBudget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MemoryPressure {
+    /// Fails the read or write that went over with
+    /// [`ProtocolError::MemoryBudgetExceeded`].
+    #[default]
+    Error,
+    /// Discards captured frames and header blocks and stops capturing on
+    /// this connection; fails like `Error` if that is not enough.
+    DropCapture,
+    /// Withholds WINDOW_UPDATE credit, so the peer stops sending DATA, until
+    /// queued events are read and the connection is back under budget.
+    /// Fails like `Error` when reading could not help, e.g. for a body
+    /// larger than the budget.
+    Backpressure,
+}
+
+/// Cap on the bytes one connection buffers: unflushed writes, captured
+/// frames, events queued for streams nobody is reading yet, bodies being
+/// assembled and the read buffer. Protects long-running services from peers
+/// that send faster than they are read, or from captures that grow without
+/// end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryBudget {
+    pub max_bytes: usize,
+    pub on_exceed: MemoryPressure,
+}
+
+impl MemoryBudget {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            on_exceed: MemoryPressure::default(),
+        }
+    }
+
+    pub fn on_exceed(mut self, pressure: MemoryPressure) -> Self {
+        self.on_exceed = pressure;
+        self
+    }
+
+    /// Fails with [`ProtocolError::MemoryBudgetExceeded`] if `usage` is over
+    /// budget, whatever the pressure policy.
+    pub fn check(&self, usage: MemoryUsage) -> Result<(), ProtocolError> {
+        if self.allows(&usage) {
+            Ok(())
+        } else {
+            Err(ProtocolError::MemoryBudgetExceeded {
+                limit: self.max_bytes,
+                usage,
+            })
+        }
+    }
+
+    pub fn allows(&self, usage: &MemoryUsage) -> bool {
+        usage.total() <= self.max_bytes
+    }
+}
+
+/// Bytes a connection holds, by what holds them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryUsage {
+    /// Serialized frames not yet written to the transport.
+    pub pending_writes: usize,
+    /// Frames and header blocks kept for responses.
+    pub captured: usize,
+    /// Payloads of events waiting for their stream's reader.
+    pub queued_events: usize,
+    /// Response bodies read so far but not yet returned.
+    pub bodies: usize,
+    /// Bytes read from the transport that do not make a whole frame yet.
+    pub read_buffer: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.pending_writes + self.captured + self.queued_events + self.bodies + self.read_buffer
+    }
+}
+
+/// e.g. `1200000 bytes (pending writes 0, captured 900000, ...)`.
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes (pending writes {}, captured {}, queued events {}, bodies {}, read buffer {})",
+            self.total(),
+            self.pending_writes,
+            self.captured,
+            self.queued_events,
+            self.bodies,
+            self.read_buffer
+        )
+    }
+}
+
+/// What a connection does with a stream whose queue goes over its
+/// [`StreamQueueLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueOverflow {
+    /// Cancels the stream and drops what it queued; reading it then fails
+    /// with a `QueueOverflow` stream error while other streams carry on.
+    #[default]
+    Reset,
+    /// Discards the oldest queued DATA until the queue fits again, which
+    /// leaves a gap in the stream's body. Headers are always kept.
+    DropOldest,
+    /// Fails the read that queued the event with a `QueueOverflow` stream
+    /// error, whichever stream it was reading.
+    Error,
+}
+
+/// Caps on what one stream may hold for a reader that has not caught up,
+/// so a peer cannot queue without end on a stream nobody reads. `None`
+/// leaves a dimension unbounded.
+///
+/// HTTP/2 counts the events queued per stream. HTTP/3 reads each stream on
+/// demand and only buffers the frame being received, so only `max_bytes`
+/// applies there, and `DropOldest` resets like `Reset` since part of a
+/// frame cannot be dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StreamQueueLimits {
+    pub max_events: Option<usize>,
+    pub max_bytes: Option<usize>,
+    pub on_overflow: QueueOverflow,
+}
+
+impl StreamQueueLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_events(mut self, max: usize) -> Self {
+        self.max_events = Some(max);
+        self
+    }
+
+    pub fn max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = Some(max);
+        self
+    }
+
+    pub fn on_overflow(mut self, overflow: QueueOverflow) -> Self {
+        self.on_overflow = overflow;
+        self
+    }
+
+    /// True if a queue of `events` holding `bytes` is over a limit.
+    pub fn exceeded(&self, events: usize, bytes: usize) -> bool {
+        self.max_events.map_or(false, |max| events > max)
+            || self.max_bytes.map_or(false, |max| bytes > max)
+    }
+}
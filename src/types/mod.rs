@@ -1,21 +1,63 @@
+pub mod assertions;
+pub mod body;
+pub mod config;
 pub mod cookie;
+pub mod decoder;
+pub mod decompression;
+pub mod digest;
 pub mod error;
 pub mod frame;
 pub mod header;
+pub mod header_block;
+pub mod header_limits;
+pub mod header_profile;
+pub mod informational;
+pub mod intermediary;
+pub mod memory;
+pub mod priority;
 pub mod protocol;
 pub mod proxy;
+pub mod redirect;
+pub mod refresh;
 pub mod request;
 pub mod response;
+pub mod response_frames;
+pub mod scope;
+pub mod similarity;
+pub mod socket_options;
+pub mod ssrf;
 pub mod target;
+pub mod template;
 pub mod timeouts;
 
+pub use assertions::*;
+pub use body::*;
+pub use config::*;
 pub use cookie::*;
+pub use decoder::*;
+pub use decompression::*;
+pub use digest::*;
 pub use error::*;
 pub use frame::*;
 pub use header::*;
+pub use header_block::*;
+pub use header_limits::*;
+pub use header_profile::*;
+pub use informational::*;
+pub use intermediary::*;
+pub use memory::*;
+pub use priority::*;
 pub use protocol::*;
 pub use proxy::*;
+pub use redirect::*;
+pub use refresh::*;
 pub use request::*;
 pub use response::*;
+pub use response_frames::*;
+pub use scope::*;
+pub use similarity::*;
+pub use socket_options::*;
+pub use ssrf::*;
 pub use target::*;
+pub use template::*;
 pub use timeouts::*;
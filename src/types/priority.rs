@@ -0,0 +1,83 @@
+use std::fmt;
+
+/// Urgency when none is given (RFC 9218 Section 4.1).
+pub const DEFAULT_URGENCY: u8 = 3;
+/// Lowest priority; urgencies above it are out of range.
+pub const MAX_URGENCY: u8 = 7;
+
+/// Extensible priority parameters of a request (RFC 9218), as carried by the
+/// `Priority` header and PRIORITY_UPDATE frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Priority {
+    /// 0 is the most urgent, 7 the least.
+    pub urgency: u8,
+    /// Whether the response may be interleaved with others of the same
+    /// urgency rather than sent whole.
+    pub incremental: bool,
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self {
+            urgency: DEFAULT_URGENCY,
+            incremental: false,
+        }
+    }
+}
+
+impl Priority {
+    /// Urgencies above 7 are clamped to 7.
+    pub fn new(urgency: u8) -> Self {
+        Self {
+            urgency: urgency.min(MAX_URGENCY),
+            incremental: false,
+        }
+    }
+
+    pub fn incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// Parses a Structured Fields dictionary such as `u=1, i`. Parameters
+    /// that are unknown, out of range or of the wrong type are ignored and
+    /// leave the default in place, as RFC 9218 Section 4 requires.
+    pub fn parse(value: &str) -> Self {
+        let mut priority = Self::default();
+        for member in value.split(',') {
+            let member = member.trim();
+            let (key, value) = match member.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (member, None),
+            };
+            match (key, value) {
+                ("u", Some(value)) => {
+                    if let Ok(urgency) = value.parse::<u8>() {
+                        if urgency <= MAX_URGENCY {
+                            priority.urgency = urgency;
+                        }
+                    }
+                }
+                ("i", None) | ("i", Some("?1")) => priority.incremental = true,
+                ("i", Some("?0")) => priority.incremental = false,
+                _ => {}
+            }
+        }
+        priority
+    }
+}
+
+/// The field value, leaving out parameters at their defaults: `u=1, i`,
+/// `i`, or empty for the default priority.
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut members = Vec::new();
+        if self.urgency != DEFAULT_URGENCY {
+            members.push(format!("u={}", self.urgency));
+        }
+        if self.incremental {
+            members.push("i".to_string());
+        }
+        write!(f, "{}", members.join(", "))
+    }
+}
@@ -1,5 +1,7 @@
-use super::{ClientRequest, Protocol};
+use super::{ClientRequest, Protocol, SendAll};
+use crate::types::{ProtocolError, Request};
 use crate::{H1, H2, H3};
+use std::sync::Arc;
 
 pub trait DefaultClient: Protocol + Send + Unpin + 'static {
     fn default_client() -> Self;
@@ -42,6 +44,12 @@ where
         TypedClient { client }
     }
 
+    /// Sends `requests` with a default client, at most `max_in_flight` at a
+    /// time; see [`SendAll`].
+    pub fn send_all(requests: Vec<Request>, max_in_flight: usize) -> SendAll<C> {
+        SendAll::new(Arc::new(C::default_client()), requests, max_in_flight)
+    }
+
     pub fn get(url: &str) -> ClientRequest<C> {
         Self::request("GET", url)
     }
@@ -104,6 +112,21 @@ where
         self.request("GET", url)
     }
 
+    /// Sends `requests` at most `max_in_flight` at a time over clones of this
+    /// client, which share its connection pool; see [`SendAll`].
+    pub fn send_all(&self, requests: Vec<Request>, max_in_flight: usize) -> SendAll<C>
+    where
+        C: Clone,
+    {
+        SendAll::new(Arc::new(self.client.clone()), requests, max_in_flight)
+    }
+
+    /// Connects to `origin` ahead of the first request there, see
+    /// [`Protocol::preconnect`].
+    pub async fn preconnect(&self, origin: &str) -> Result<(), ProtocolError> {
+        self.client.preconnect(origin).await
+    }
+
     pub fn post(&self, url: &str) -> ClientRequest<C>
     where
         C: Clone,
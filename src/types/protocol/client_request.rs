@@ -1,6 +1,11 @@
 use super::Protocol;
+use crate::signing::RequestSigner;
 use crate::types::request::RequestBuilderOps;
-use crate::types::{ClientTimeouts, ProtocolError, ProxySettings, RequestBuilder, Response};
+use crate::types::{
+    ClientTimeouts, DecompressionLimits, EndStreamMode, HeaderProfile, InformationalResponse,
+    ProtocolError, ProxySettings, RedirectPolicy, RequestBuilder, RequestTargetForm, Response,
+    SsrfGuard, UserAgent,
+};
 use serde_json::Value;
 use std::future::Future;
 use std::pin::Pin;
@@ -21,9 +26,11 @@ where
 {
     pub(super) fn new(client: C, method: &str, url: &str) -> Self {
         let method_upper = method.to_ascii_uppercase();
+        let mut builder = RequestBuilder::new(url, method_upper);
+        builder.validate_headers(true);
         Self {
             client: Some(client),
-            builder: RequestBuilder::new(url, method_upper),
+            builder,
             future: None,
         }
     }
@@ -58,6 +65,21 @@ where
         self
     }
 
+    pub fn json_patch(mut self, patch: Value) -> Self {
+        RequestBuilderOps::json_patch(&mut self, patch);
+        self
+    }
+
+    pub fn merge_patch(mut self, patch: Value) -> Self {
+        RequestBuilderOps::merge_patch(&mut self, patch);
+        self
+    }
+
+    pub fn nested_data(mut self, value: Value) -> Self {
+        RequestBuilderOps::nested_data(&mut self, value);
+        self
+    }
+
     pub fn query<I, K, V>(mut self, query: I) -> Self
     where
         I: IntoIterator<Item = (K, V)>,
@@ -93,11 +115,26 @@ where
         self
     }
 
+    pub fn follow_refresh(mut self, follow: bool) -> Self {
+        RequestBuilderOps::follow_refresh(&mut self, follow);
+        self
+    }
+
+    pub fn redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        RequestBuilderOps::redirect_policy(&mut self, policy);
+        self
+    }
+
     pub fn timeout(mut self, timeout: ClientTimeouts) -> Self {
         RequestBuilderOps::timeout(&mut self, timeout);
         self
     }
 
+    pub fn decompression_limits(mut self, limits: DecompressionLimits) -> Self {
+        RequestBuilderOps::decompression_limits(&mut self, limits);
+        self
+    }
+
     pub fn proxies(mut self, proxies: ProxySettings) -> Self {
         RequestBuilderOps::proxies(&mut self, proxies);
         self
@@ -112,6 +149,71 @@ where
         RequestBuilderOps::without_proxies(&mut self);
         self
     }
+
+    /// Header validation is on by default for client requests; disable it to send
+    /// deliberately malformed headers.
+    pub fn validate_headers(mut self, enabled: bool) -> Self {
+        RequestBuilderOps::validate_headers(&mut self, enabled);
+        self
+    }
+
+    pub fn target_form(mut self, form: RequestTargetForm) -> Self {
+        RequestBuilderOps::target_form(&mut self, form);
+        self
+    }
+
+    pub fn signer<S: RequestSigner + 'static>(mut self, signer: S) -> Self {
+        RequestBuilderOps::signer(&mut self, signer);
+        self
+    }
+
+    pub fn ssrf_guard(mut self, guard: SsrfGuard) -> Self {
+        RequestBuilderOps::ssrf_guard(&mut self, guard);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: UserAgent) -> Self {
+        RequestBuilderOps::user_agent(&mut self, user_agent);
+        self
+    }
+
+    pub fn header_profile(mut self, profile: HeaderProfile) -> Self {
+        RequestBuilderOps::header_profile(&mut self, profile);
+        self
+    }
+
+    pub fn auto_headers(mut self, enabled: bool) -> Self {
+        RequestBuilderOps::auto_headers(&mut self, enabled);
+        self
+    }
+
+    pub fn end_stream_mode(mut self, mode: EndStreamMode) -> Self {
+        RequestBuilderOps::end_stream_mode(&mut self, mode);
+        self
+    }
+
+    pub fn on_informational<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&InformationalResponse) + Send + Sync + 'static,
+    {
+        RequestBuilderOps::on_informational(&mut self, handler);
+        self
+    }
+
+    pub fn verify_digest(mut self, enabled: bool) -> Self {
+        RequestBuilderOps::verify_digest(&mut self, enabled);
+        self
+    }
+
+    pub fn allow_truncated_body(mut self, enabled: bool) -> Self {
+        RequestBuilderOps::allow_truncated_body(&mut self, enabled);
+        self
+    }
+
+    pub fn fresh_dns(mut self) -> Self {
+        RequestBuilderOps::fresh_dns(&mut self);
+        self
+    }
 }
 
 impl<C> RequestBuilderOps for ClientRequest<C>
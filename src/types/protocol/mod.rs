@@ -1,14 +1,18 @@
 use super::error::ProtocolError;
-use super::{Request, Response};
+use super::redirect::{RedirectChain, RedirectHop};
+use super::{ClientConfig, Request, Response, UserAgent};
+use crate::pool::PoolControl;
 use crate::utils::apply_redirect;
 use async_trait::async_trait;
 
 mod client;
 mod client_request;
+mod send_all;
 
 use bytes::Bytes;
 pub use client::{Client, DefaultClient, TypedClient};
 pub use client_request::ClientRequest;
+pub use send_all::SendAll;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum HttpProtocol {
@@ -34,25 +38,56 @@ impl std::fmt::Display for HttpProtocol {
 pub trait Protocol {
     async fn execute(&self, request: &Request) -> Result<Response, ProtocolError>;
 
+    /// Defaults applied to every request before it is sent.
+    fn config(&self) -> Option<&ClientConfig> {
+        None
+    }
+
+    /// Client-wide `User-Agent` for requests that leave it as [`UserAgent::Inherit`].
+    fn user_agent(&self) -> Option<&UserAgent> {
+        self.config().map(|config| &config.user_agent)
+    }
+
+    /// Connection pools the client parks connections in, for stats and
+    /// eviction settings. Empty for clients without a pool.
+    fn pools(&self) -> Vec<&dyn PoolControl> {
+        Vec::new()
+    }
+
+    /// Fills in the client's defaults, as every request gets them before it
+    /// is sent.
+    fn apply_defaults(&self, request: &mut Request) {
+        if let Some(config) = self.config() {
+            config.apply(request);
+        }
+        if let Some(user_agent) = self.user_agent() {
+            request.inherit_user_agent(user_agent);
+        }
+    }
+
     async fn response(&self, mut request: Request) -> Result<Response, ProtocolError> {
-        const MAX_REDIRECTS: u32 = 30;
-        let mut redirect_count = 0u32;
+        self.apply_defaults(&mut request);
+        let mut chain = RedirectChain::new();
 
         loop {
-            let response = self.execute(&request).await?;
+            if let Some(scope) = self.config().and_then(|config| config.host_scope.as_ref()) {
+                scope.check(&request.target).await?;
+            }
+            let mut response = self.execute(&request).await?;
 
+            let hop = RedirectHop {
+                method: request.method.clone(),
+                url: request.target.url.clone(),
+                status: response.status,
+            };
             if apply_redirect(&mut request, &response)? {
-                redirect_count += 1;
-
-                if redirect_count > MAX_REDIRECTS {
-                    return Err(ProtocolError::RequestFailed(
-                        "Too many redirects".to_string(),
-                    ));
-                }
-
+                chain.follow(hop, &request)?;
                 continue;
             }
 
+            if request.verify_digest {
+                response.digest = Some(response.verify_digest());
+            }
             return Ok(response);
         }
     }
@@ -61,6 +96,14 @@ pub trait Protocol {
         self.response(request).await
     }
 
+    /// Opens a connection to `origin` ahead of the first request there and
+    /// parks it in the client's pool.
+    async fn preconnect(&self, _origin: &str) -> Result<(), ProtocolError> {
+        Err(ProtocolError::RequestFailed(
+            "Preconnect is not supported for this protocol".to_string(),
+        ))
+    }
+
     async fn send_raw(&self, _target: &str, _request: Bytes) -> Result<Response, ProtocolError> {
         Err(ProtocolError::RequestFailed(
             "Raw requests are not supported for this protocol".to_string(),
@@ -0,0 +1,102 @@
+use super::Protocol;
+use crate::pacing::Pacer;
+use crate::types::{ProtocolError, Request, Response};
+use crate::utils::BoundedJoin;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+type PendingResponse = Pin<Box<dyn Future<Output = Result<Response, ProtocolError>>>>;
+
+/// Responses to a batch of requests, in the order the requests were given.
+///
+/// At most `max_in_flight` requests run at once, all through one client, so
+/// they share its connection pool. A failed request yields its error and the
+/// batch carries on. Requests only make progress while [`next`](Self::next)
+/// or [`collect`](Self::collect) is awaited; nothing is spawned.
+pub struct SendAll<C>
+where
+    C: Protocol + 'static,
+{
+    join: BoundedJoin<Requests<C>>,
+}
+
+impl<C> SendAll<C>
+where
+    C: Protocol + 'static,
+{
+    pub(super) fn new(client: Arc<C>, requests: Vec<Request>, max_in_flight: usize) -> Self {
+        let requests = Requests {
+            client,
+            requests: requests.into_iter(),
+            pacer: None,
+        };
+        Self {
+            join: BoundedJoin::new(requests, max_in_flight),
+        }
+    }
+
+    /// Holds each request back while its host is throttled and feeds the
+    /// responses to `pacer`. Throttled requests are not resent.
+    pub fn pacer(mut self, pacer: Arc<Pacer>) -> Self {
+        self.join.pending_mut().pacer = Some(pacer);
+        self
+    }
+
+    /// Requests not yet answered, running ones included.
+    pub fn remaining(&self) -> usize {
+        self.join.remaining()
+    }
+
+    /// The response to the next request in order, once it is in; `None`
+    /// after the last one.
+    pub async fn next(&mut self) -> Option<Result<Response, ProtocolError>> {
+        self.join.next().await
+    }
+
+    /// Every response, in request order.
+    pub async fn collect(mut self) -> Vec<Result<Response, ProtocolError>> {
+        let mut results = Vec::with_capacity(self.remaining());
+        while let Some(result) = self.next().await {
+            results.push(result);
+        }
+        results
+    }
+}
+
+/// Turns the batch's requests into responses as [`BoundedJoin`] admits them.
+struct Requests<C> {
+    client: Arc<C>,
+    requests: std::vec::IntoIter<Request>,
+    pacer: Option<Arc<Pacer>>,
+}
+
+impl<C> Iterator for Requests<C>
+where
+    C: Protocol + 'static,
+{
+    type Item = PendingResponse;
+
+    fn next(&mut self) -> Option<PendingResponse> {
+        let request = self.requests.next()?;
+        let client = Arc::clone(&self.client);
+        let pacer = self.pacer.clone();
+        Some(Box::pin(async move {
+            let host = request.target.host().unwrap_or_default().to_string();
+            if let Some(pacer) = &pacer {
+                pacer.wait(&host).await;
+            }
+            let result = client.send_request(request).await;
+            if let (Some(pacer), Ok(response)) = (&pacer, &result) {
+                pacer.observe(&host, response);
+            }
+            result
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.requests.size_hint()
+    }
+}
+
+impl<C> ExactSizeIterator for Requests<C> where C: Protocol + 'static {}
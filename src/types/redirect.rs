@@ -0,0 +1,267 @@
+use super::{ProtocolError, Request, Target};
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Redirects followed before giving up, unless the policy says otherwise.
+pub const DEFAULT_MAX_REDIRECTS: usize = 30;
+
+/// Headers that carry credentials on every hop's request.
+const CREDENTIAL_HEADERS: &[&str] = &["authorization", "cookie"];
+
+/// Headers describing the body, dropped along with it.
+const BODY_HEADERS: &[&str] = &[
+    "content-type",
+    "content-length",
+    "content-encoding",
+    "content-language",
+    "content-location",
+    "transfer-encoding",
+];
+
+/// Which redirect hops may receive the request's credentials: the
+/// `Authorization` and `Cookie` headers, the request's cookies, its signer
+/// and any [`RedirectPolicy::sensitive_header`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CredentialForwarding {
+    /// Only hops on the origin the request started on: same scheme, host and
+    /// port.
+    #[default]
+    SameOrigin,
+    /// Hops on the same host, whatever the port, unless the scheme drops from
+    /// `https` to `http`.
+    SameHost,
+    /// Every hop. Hands the credentials to whatever server a redirect names.
+    Always,
+}
+
+/// What a redirect does to the method and body of the request it rewrites.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectMethod {
+    /// Resends the same method and body.
+    Preserve,
+    /// Switches to a GET without a body; HEAD stays HEAD.
+    Get,
+    /// Switches POST to a GET without a body and keeps other methods, as
+    /// browsers do on 301 and 302.
+    PostToGet,
+}
+
+impl RedirectMethod {
+    /// Rewrites `request` for the next hop.
+    pub fn apply(&self, request: &mut Request) {
+        let to_get = match self {
+            RedirectMethod::Preserve => false,
+            RedirectMethod::Get => !request.method.eq_ignore_ascii_case("HEAD"),
+            RedirectMethod::PostToGet => request.method.eq_ignore_ascii_case("POST"),
+        };
+        if !to_get {
+            return;
+        }
+        request.method = "GET".to_string();
+        request.body = None;
+        request.json = None;
+        request.data = None;
+        request.trailers.clear();
+        request.headers.retain(|header| {
+            !BODY_HEADERS
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&header.name))
+        });
+    }
+}
+
+/// How redirects treat the request they rewrite.
+///
+/// Once a hop leaves the trusted origin the credentials are removed from the
+/// request for good, so a later redirect back does not restore them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectPolicy {
+    pub credentials: CredentialForwarding,
+    /// Lowercase names stripped along with the built-in credential headers,
+    /// e.g. `x-api-key`.
+    pub sensitive_headers: Vec<String>,
+    /// Method handling by status, over the defaults of
+    /// [`method_for`](Self::method_for).
+    pub methods: BTreeMap<u16, RedirectMethod>,
+    /// Refuse `Location` values that are not valid URI references with
+    /// [`ProtocolError::InvalidLocation`](super::ProtocolError::InvalidLocation)
+    /// instead of repairing them like a browser, or returning the redirect
+    /// when they cannot be repaired.
+    pub strict_location: bool,
+    /// Redirects followed before failing with
+    /// [`ProtocolError::TooManyRedirects`].
+    pub max_redirects: usize,
+    /// Fail with [`ProtocolError::RedirectLoop`] as soon as a redirect asks
+    /// for a method and URL already requested in the chain.
+    pub detect_loops: bool,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self {
+            credentials: CredentialForwarding::default(),
+            sensitive_headers: Vec::new(),
+            methods: BTreeMap::new(),
+            strict_location: false,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            detect_loops: true,
+        }
+    }
+}
+
+impl RedirectPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn credentials(mut self, forwarding: CredentialForwarding) -> Self {
+        self.credentials = forwarding;
+        self
+    }
+
+    pub fn sensitive_header(mut self, name: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        if !self.sensitive_headers.contains(&name) {
+            self.sensitive_headers.push(name);
+        }
+        self
+    }
+
+    pub fn strict_location(mut self, strict: bool) -> Self {
+        self.strict_location = strict;
+        self
+    }
+
+    pub fn max_redirects(mut self, max: usize) -> Self {
+        self.max_redirects = max;
+        self
+    }
+
+    pub fn detect_loops(mut self, enabled: bool) -> Self {
+        self.detect_loops = enabled;
+        self
+    }
+
+    /// Handles redirects with `status` per `method`.
+    pub fn method(mut self, status: u16, method: RedirectMethod) -> Self {
+        self.methods.insert(status, method);
+        self
+    }
+
+    /// Keeps method and body on 301, 302 and 303 too, for APIs that answer
+    /// with 302 where they mean 307.
+    pub fn preserve_method(self) -> Self {
+        self.method(301, RedirectMethod::Preserve)
+            .method(302, RedirectMethod::Preserve)
+            .method(303, RedirectMethod::Preserve)
+    }
+
+    /// Method handling for `status`: GET on 303, POST to GET on 301 and 302,
+    /// and the same method and body otherwise, unless overridden.
+    pub fn method_for(&self, status: u16) -> RedirectMethod {
+        if let Some(method) = self.methods.get(&status) {
+            return *method;
+        }
+        match status {
+            303 => RedirectMethod::Get,
+            301 | 302 => RedirectMethod::PostToGet,
+            _ => RedirectMethod::Preserve,
+        }
+    }
+
+    /// True if a header named `name` is stripped when credentials are.
+    pub fn is_sensitive(&self, name: &str) -> bool {
+        CREDENTIAL_HEADERS
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(name))
+            || self
+                .sensitive_headers
+                .iter()
+                .any(|sensitive| sensitive.eq_ignore_ascii_case(name))
+    }
+
+    /// True if credentials sent to `from` may follow a redirect to `to`.
+    pub fn forwards_credentials(&self, from: &Target, to: &Target) -> bool {
+        let same_host = match (from.host(), to.host()) {
+            (Some(from), Some(to)) => from.eq_ignore_ascii_case(to),
+            _ => false,
+        };
+        match self.credentials {
+            CredentialForwarding::SameOrigin => {
+                same_host && from.scheme() == to.scheme() && from.port() == to.port()
+            }
+            CredentialForwarding::SameHost => {
+                same_host && !(from.scheme() == "https" && to.scheme() == "http")
+            }
+            CredentialForwarding::Always => true,
+        }
+    }
+
+    /// Removes credential headers and trailers, cookies and the signer from
+    /// `request`.
+    pub fn strip_credentials(&self, request: &mut Request) {
+        request
+            .headers
+            .retain(|header| !self.is_sensitive(&header.name));
+        request
+            .trailers
+            .retain(|header| !self.is_sensitive(&header.name));
+        request.cookies.clear();
+        request.signer = None;
+    }
+}
+
+/// One request of a redirect chain and the status it was answered with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHop {
+    pub method: String,
+    pub url: Url,
+    pub status: u16,
+}
+
+impl std::fmt::Display for RedirectHop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} -> {}", self.method, self.url, self.status)
+    }
+}
+
+/// The hops of one redirect chain, checked against the policy's limit and
+/// for loops as each redirect is followed.
+#[derive(Debug, Default)]
+pub(crate) struct RedirectChain {
+    hops: Vec<RedirectHop>,
+}
+
+impl RedirectChain {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `hop` redirected to `next` and checks the chain.
+    pub(crate) fn follow(&mut self, hop: RedirectHop, next: &Request) -> Result<(), ProtocolError> {
+        self.hops.push(hop);
+        let policy = &next.redirect_policy;
+        if policy.detect_loops {
+            let looped = self.hops.iter().any(|hop| {
+                hop.method.eq_ignore_ascii_case(&next.method)
+                    && same_resource(&hop.url, &next.target.url)
+            });
+            if looped {
+                return Err(ProtocolError::RedirectLoop {
+                    chain: std::mem::take(&mut self.hops),
+                });
+            }
+        }
+        if self.hops.len() > policy.max_redirects {
+            return Err(ProtocolError::TooManyRedirects {
+                chain: std::mem::take(&mut self.hops),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// URLs equal but for their fragments, which never reach the server.
+fn same_resource(a: &Url, b: &Url) -> bool {
+    a[..url::Position::AfterQuery] == b[..url::Position::AfterQuery]
+}
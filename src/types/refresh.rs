@@ -0,0 +1,164 @@
+use super::Response;
+use crate::utils::{header_value, CONTENT_TYPE_HEADER};
+
+const REFRESH_HEADER: &str = "refresh";
+
+/// Where a declarative refresh was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshSource {
+    /// `Refresh: 5; url=/next`
+    Header,
+    /// `<meta http-equiv="refresh" content="...">`
+    MetaTag,
+}
+
+/// A redirect expressed without a 3xx status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshRedirect {
+    pub source: RefreshSource,
+    /// Delay in seconds before the refresh fires.
+    pub delay: u64,
+    /// Target as written, still relative to the response URL. `None` reloads the page.
+    pub url: Option<String>,
+}
+
+impl Response {
+    /// Detects a `Refresh` header or, for HTML bodies, a meta refresh tag.
+    /// The header takes precedence, as it does in browsers.
+    pub fn refresh_redirect(&self) -> Option<RefreshRedirect> {
+        if let Some(value) = header_value(&self.headers, REFRESH_HEADER) {
+            if let Some((delay, url)) = parse_refresh(value) {
+                return Some(RefreshRedirect {
+                    source: RefreshSource::Header,
+                    delay,
+                    url,
+                });
+            }
+        }
+
+        let html = header_value(&self.headers, CONTENT_TYPE_HEADER)
+            .map_or(true, |ct| ct.to_ascii_lowercase().contains("html"));
+        if !html {
+            return None;
+        }
+        let content = meta_refresh_content(&String::from_utf8_lossy(&self.body))?;
+        let (delay, url) = parse_refresh(&content)?;
+        Some(RefreshRedirect {
+            source: RefreshSource::MetaTag,
+            delay,
+            url,
+        })
+    }
+}
+
+/// Parses a refresh value such as `0; URL='/login'` following the WHATWG
+/// "shared declarative refresh steps".
+pub fn parse_refresh(value: &str) -> Option<(u64, Option<String>)> {
+    let value = value.trim_start();
+    let digits_end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let digits = &value[..digits_end];
+    if digits.is_empty() && !value.starts_with('.') {
+        return None;
+    }
+    // Fractions are ignored ("1.5" refreshes after one second).
+    let delay = if digits.is_empty() {
+        0
+    } else {
+        digits.parse::<u64>().unwrap_or(u64::MAX)
+    };
+
+    let mut rest = value[digits_end..].trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if let Some(c) = rest.chars().next() {
+        if !c.is_whitespace() && c != ';' && c != ',' {
+            return None;
+        }
+    }
+    rest = rest.trim_start();
+    rest = rest.strip_prefix([';', ',']).unwrap_or(rest).trim_start();
+
+    if rest
+        .get(..3)
+        .map_or(false, |prefix| prefix.eq_ignore_ascii_case("url"))
+    {
+        let after = rest[3..].trim_start();
+        if let Some(after) = after.strip_prefix('=') {
+            rest = after.trim_start();
+        }
+    }
+
+    let url = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let inner = &rest[1..];
+            inner.find(quote).map_or(inner, |end| &inner[..end])
+        }
+        _ => rest,
+    }
+    .trim();
+
+    Some((delay, (!url.is_empty()).then(|| url.to_string())))
+}
+
+/// Returns the `content` attribute of the first `<meta http-equiv="refresh">`.
+fn meta_refresh_content(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let mut offset = 0;
+    while let Some(start) = lower[offset..].find("<meta") {
+        let tag_start = offset + start + "<meta".len();
+        let tag_end = lower[tag_start..]
+            .find('>')
+            .map_or(html.len(), |end| tag_start + end);
+        let attributes = parse_attributes(&html[tag_start..tag_end]);
+
+        let is_refresh = attributes
+            .iter()
+            .any(|(name, value)| name == "http-equiv" && value.eq_ignore_ascii_case("refresh"));
+        if is_refresh {
+            if let Some((_, content)) = attributes.into_iter().find(|(name, _)| name == "content") {
+                return Some(content);
+            }
+        }
+        offset = tag_end;
+    }
+    None
+}
+
+/// Minimal HTML attribute tokenizer: names are lowercased, values unquoted.
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after[1..];
+                    let end = inner.find(quote).unwrap_or(inner.len());
+                    value = inner[..end].to_string();
+                    rest = inner.get(end + 1..).unwrap_or("");
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    value = after[..end].to_string();
+                    rest = &after[end..];
+                }
+            }
+        }
+
+        if !name.is_empty() {
+            attributes.push((name, value));
+        }
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+    }
+
+    attributes
+}
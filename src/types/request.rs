@@ -1,32 +1,84 @@
+use super::decompression::DecompressionLimits;
 use super::error::ProtocolError;
+use super::header_profile::HeaderProfile;
+use super::informational::{InformationalHandler, InformationalResponse};
+use super::redirect::RedirectPolicy;
+use super::ssrf::SsrfGuard;
 use super::timeouts::ClientTimeouts;
-use super::{Header, Target};
+use super::{Header, HttpProtocol, Target};
 use crate::parse_header;
+use crate::quota::TrafficMeter;
+use crate::signing::RequestSigner;
 use crate::types::proxy::ProxySettings;
 use crate::utils::{
-    ensure_user_agent, parse_headers, parse_target, parse_trailers, APPLICATION_JSON,
-    CONTENT_TYPE_HEADER, COOKIE_HEADER,
+    ensure_user_agent, parse_headers, parse_target_with_scheme, parse_trailers,
+    validate_request_headers, APPLICATION_JSON, CONTENT_TYPE_HEADER, COOKIE_HEADER, HOST_HEADER,
+    USER_AGENT_HEADER,
 };
 use bytes::Bytes;
 use serde_json::Value;
-use url::form_urlencoded;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use url::{form_urlencoded, Url};
 
 const APPLICATION_X_WWW_FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
 
+const APPLICATION_JSON_PATCH: &str = "application/json-patch+json";
+const APPLICATION_MERGE_PATCH: &str = "application/merge-patch+json";
+
 #[derive(Debug, Clone)]
 pub enum FormBody {
     Raw(String),
     Fields(Vec<(String, String)>),
+    /// A JSON object flattened into bracket-style keys: `{"a": {"b": "c"}}`
+    /// becomes `a[b]=c`. Non-object roots encode to an empty body.
+    Nested(Value, ArrayFormat),
+}
+
+/// How arrays inside [`FormBody::Nested`] are keyed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayFormat {
+    /// `tags[]=a&tags[]=b` (PHP, Rails).
+    #[default]
+    Brackets,
+    /// `tags[0]=a&tags[1]=b`
+    Indices,
+    /// `tags=a&tags=b`
+    Repeat,
 }
 
 impl FormBody {
+    pub fn nested(value: Value) -> Self {
+        FormBody::Nested(value, ArrayFormat::default())
+    }
+
+    /// Flattened `(key, value)` pairs, before percent-encoding.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        match self {
+            FormBody::Raw(value) => form_urlencoded::parse(value.as_bytes())
+                .into_owned()
+                .collect(),
+            FormBody::Fields(pairs) => pairs.clone(),
+            FormBody::Nested(Value::Object(map), arrays) => {
+                let mut pairs = Vec::new();
+                for (key, value) in map {
+                    flatten_form_value(key.clone(), value, *arrays, &mut pairs);
+                }
+                pairs
+            }
+            FormBody::Nested(_, _) => Vec::new(),
+        }
+    }
+
     fn encode(&self) -> String {
         match self {
             FormBody::Raw(value) => value.clone(),
-            FormBody::Fields(pairs) => {
+            _ => {
                 let mut serializer = form_urlencoded::Serializer::new(String::new());
-                for (key, value) in pairs {
-                    serializer.append_pair(key, value);
+                for (key, value) in self.fields() {
+                    serializer.append_pair(&key, &value);
                 }
                 serializer.finish()
             }
@@ -34,6 +86,37 @@ impl FormBody {
     }
 }
 
+fn flatten_form_value(
+    key: String,
+    value: &Value,
+    arrays: ArrayFormat,
+    pairs: &mut Vec<(String, String)>,
+) {
+    match value {
+        Value::Object(map) => {
+            for (child, value) in map {
+                flatten_form_value(format!("{}[{}]", key, child), value, arrays, pairs);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                // Nested containers need an index to keep their fields together.
+                let nested = matches!(item, Value::Object(_) | Value::Array(_));
+                let item_key = match arrays {
+                    _ if nested => format!("{}[{}]", key, index),
+                    ArrayFormat::Brackets => format!("{}[]", key),
+                    ArrayFormat::Indices => format!("{}[{}]", key, index),
+                    ArrayFormat::Repeat => key.clone(),
+                };
+                flatten_form_value(item_key, item, arrays, pairs);
+            }
+        }
+        Value::String(text) => pairs.push((key, text.clone())),
+        Value::Null => pairs.push((key, String::new())),
+        other => pairs.push((key, other.to_string())),
+    }
+}
+
 impl From<&str> for FormBody {
     fn from(value: &str) -> Self {
         FormBody::Raw(value.to_string())
@@ -61,6 +144,44 @@ where
     }
 }
 
+/// How the request-target is written on an HTTP/1.1 request line (RFC 7230 Section 5.3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RequestTargetForm {
+    /// Absolute-form when forwarding through an HTTP proxy, origin-form otherwise.
+    #[default]
+    Auto,
+    /// `/path?query`
+    Origin,
+    /// `scheme://host:port/path?query`, always with an explicit port.
+    Absolute,
+}
+
+/// Which `User-Agent` is added to a request that does not set one itself.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum UserAgent {
+    /// Whatever the session or client is configured with, or the crate's
+    /// own `riphttplib/<version>` when neither sets one.
+    #[default]
+    Inherit,
+    Custom(String),
+    /// Send no `User-Agent` at all.
+    Omit,
+}
+
+/// When HTTP/2 and HTTP/3 requests end their stream; HTTP/1.1 ignores it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EndStreamMode {
+    /// With the last HEADERS or DATA frame.
+    #[default]
+    Immediate,
+    /// Headers and body go out at once and the stream is held open for this
+    /// long. END_STREAM follows on the trailers if there are any, otherwise
+    /// on an empty DATA frame (HTTP/2) or a stream FIN (HTTP/3).
+    After(Duration),
+    /// The stream stays open while the response is read; trailers are not sent.
+    Never,
+}
+
 #[derive(Debug, Clone)]
 pub struct PreparedRequest {
     pub method: String,
@@ -87,6 +208,22 @@ impl PreparedRequest {
     }
 }
 
+/// Last result of [`Request::prepare_once`] with the fingerprint of the
+/// inputs it was built from. Clones start out with their own copy.
+#[derive(Debug, Default)]
+struct PreparedCache {
+    slot: Mutex<Option<(u64, Arc<PreparedRequest>)>>,
+}
+
+impl Clone for PreparedCache {
+    fn clone(&self) -> Self {
+        let slot = self.slot.lock().map(|slot| slot.clone()).unwrap_or(None);
+        Self {
+            slot: Mutex::new(slot),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RequestBuilder {
     inner: Result<Request, ProtocolError>,
@@ -239,6 +376,27 @@ pub trait RequestBuilderOps {
         self
     }
 
+    fn json_patch(&mut self, patch: Value) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_json_patch(patch);
+        }
+        self
+    }
+
+    fn merge_patch(&mut self, patch: Value) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_merge_patch(patch);
+        }
+        self
+    }
+
+    fn nested_data(&mut self, value: Value) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_nested_data(value);
+        }
+        self
+    }
+
     fn data<I, K, V>(&mut self, data: I) -> &mut Self
     where
         I: IntoIterator<Item = (K, V)>,
@@ -282,6 +440,27 @@ pub trait RequestBuilderOps {
         self
     }
 
+    fn follow_refresh(&mut self, follow: bool) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_follow_refresh(follow);
+        }
+        self
+    }
+
+    fn redirect_policy(&mut self, policy: RedirectPolicy) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_redirect_policy(policy);
+        }
+        self
+    }
+
+    fn decompression_limits(&mut self, limits: DecompressionLimits) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_decompression_limits(limits);
+        }
+        self
+    }
+
     fn timeout(&mut self, timeout: ClientTimeouts) -> &mut Self {
         if let Ok(request) = self.builder_mut().inner.as_mut() {
             request.set_timeout(timeout);
@@ -322,6 +501,93 @@ pub trait RequestBuilderOps {
         }
         self
     }
+
+    fn validate_headers(&mut self, enabled: bool) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_validate_headers(enabled);
+        }
+        self
+    }
+
+    fn target_form(&mut self, form: RequestTargetForm) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_target_form(form);
+        }
+        self
+    }
+
+    fn signer<S: RequestSigner + 'static>(&mut self, signer: S) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_signer(signer);
+        }
+        self
+    }
+
+    fn ssrf_guard(&mut self, guard: SsrfGuard) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_ssrf_guard(guard);
+        }
+        self
+    }
+
+    fn user_agent(&mut self, user_agent: UserAgent) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_user_agent(user_agent);
+        }
+        self
+    }
+
+    fn header_profile(&mut self, profile: HeaderProfile) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_header_profile(profile);
+        }
+        self
+    }
+
+    fn auto_headers(&mut self, enabled: bool) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_auto_headers(enabled);
+        }
+        self
+    }
+
+    fn end_stream_mode(&mut self, mode: EndStreamMode) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_end_stream_mode(mode);
+        }
+        self
+    }
+
+    fn on_informational<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(&InformationalResponse) + Send + Sync + 'static,
+    {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_on_informational(handler);
+        }
+        self
+    }
+
+    fn verify_digest(&mut self, enabled: bool) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_verify_digest(enabled);
+        }
+        self
+    }
+
+    fn allow_truncated_body(&mut self, enabled: bool) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_allow_truncated_body(enabled);
+        }
+        self
+    }
+
+    fn fresh_dns(&mut self) -> &mut Self {
+        if let Ok(request) = self.builder_mut().inner.as_mut() {
+            request.set_fresh_dns(true);
+        }
+        self
+    }
 }
 
 impl RequestBuilderOps for RequestBuilder {
@@ -355,6 +621,18 @@ impl RequestBuilder {
         RequestBuilderOps::json(self, value)
     }
 
+    pub fn json_patch(&mut self, patch: Value) -> &mut Self {
+        RequestBuilderOps::json_patch(self, patch)
+    }
+
+    pub fn merge_patch(&mut self, patch: Value) -> &mut Self {
+        RequestBuilderOps::merge_patch(self, patch)
+    }
+
+    pub fn nested_data(&mut self, value: Value) -> &mut Self {
+        RequestBuilderOps::nested_data(self, value)
+    }
+
     pub fn data<I, K, V>(&mut self, data: I) -> &mut Self
     where
         I: IntoIterator<Item = (K, V)>,
@@ -386,10 +664,22 @@ impl RequestBuilder {
         RequestBuilderOps::allow_redirects(self, allow)
     }
 
+    pub fn follow_refresh(&mut self, follow: bool) -> &mut Self {
+        RequestBuilderOps::follow_refresh(self, follow)
+    }
+
+    pub fn redirect_policy(&mut self, policy: RedirectPolicy) -> &mut Self {
+        RequestBuilderOps::redirect_policy(self, policy)
+    }
+
     pub fn timeout(&mut self, timeout: ClientTimeouts) -> &mut Self {
         RequestBuilderOps::timeout(self, timeout)
     }
 
+    pub fn decompression_limits(&mut self, limits: DecompressionLimits) -> &mut Self {
+        RequestBuilderOps::decompression_limits(self, limits)
+    }
+
     pub fn proxies(&mut self, proxies: ProxySettings) -> &mut Self {
         RequestBuilderOps::proxies(self, proxies)
     }
@@ -401,6 +691,57 @@ impl RequestBuilder {
     pub fn without_proxies(&mut self) -> &mut Self {
         RequestBuilderOps::without_proxies(self)
     }
+
+    pub fn validate_headers(&mut self, enabled: bool) -> &mut Self {
+        RequestBuilderOps::validate_headers(self, enabled)
+    }
+
+    pub fn target_form(&mut self, form: RequestTargetForm) -> &mut Self {
+        RequestBuilderOps::target_form(self, form)
+    }
+
+    pub fn signer<S: RequestSigner + 'static>(&mut self, signer: S) -> &mut Self {
+        RequestBuilderOps::signer(self, signer)
+    }
+
+    pub fn ssrf_guard(&mut self, guard: SsrfGuard) -> &mut Self {
+        RequestBuilderOps::ssrf_guard(self, guard)
+    }
+
+    pub fn user_agent(&mut self, user_agent: UserAgent) -> &mut Self {
+        RequestBuilderOps::user_agent(self, user_agent)
+    }
+
+    pub fn header_profile(&mut self, profile: HeaderProfile) -> &mut Self {
+        RequestBuilderOps::header_profile(self, profile)
+    }
+
+    pub fn auto_headers(&mut self, enabled: bool) -> &mut Self {
+        RequestBuilderOps::auto_headers(self, enabled)
+    }
+
+    pub fn end_stream_mode(&mut self, mode: EndStreamMode) -> &mut Self {
+        RequestBuilderOps::end_stream_mode(self, mode)
+    }
+
+    pub fn on_informational<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(&InformationalResponse) + Send + Sync + 'static,
+    {
+        RequestBuilderOps::on_informational(self, handler)
+    }
+
+    pub fn verify_digest(&mut self, enabled: bool) -> &mut Self {
+        RequestBuilderOps::verify_digest(self, enabled)
+    }
+
+    pub fn allow_truncated_body(&mut self, enabled: bool) -> &mut Self {
+        RequestBuilderOps::allow_truncated_body(self, enabled)
+    }
+
+    pub fn fresh_dns(&mut self) -> &mut Self {
+        RequestBuilderOps::fresh_dns(self)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -413,16 +754,55 @@ pub struct Request {
     pub cookies: Vec<(String, String)>,
     pub body: Option<Bytes>,
     pub json: Option<Value>,
+    /// Content-Type sent with `json`; `application/json` when unset.
+    pub json_content_type: Option<String>,
     pub data: Option<FormBody>,
     pub timeout: Option<ClientTimeouts>,
+    /// Applied when a `Content-Encoding` body is decoded.
+    pub decompression_limits: DecompressionLimits,
     pub follow_redirects: bool,
+    /// Also follow `Refresh` headers and meta refresh tags (requires `follow_redirects`).
+    pub follow_refresh: bool,
+    /// Which redirect hops get the request's credentials.
+    pub redirect_policy: RedirectPolicy,
     pub proxies: Option<ProxySettings>,
+    /// Reject forbidden or malformed headers before sending (see `validate_request_headers`).
+    pub validate_headers: bool,
+    pub target_form: RequestTargetForm,
+    pub signer: Option<Arc<dyn RequestSigner>>,
+    /// Refuses to connect to addresses it blocks, here and on every redirect hop.
+    pub ssrf_guard: Option<SsrfGuard>,
+    pub user_agent: UserAgent,
+    /// Browser-like default headers and header order; ignored without `auto_headers`.
+    pub header_profile: Option<HeaderProfile>,
+    /// When false, only the headers set on the request are sent: no
+    /// `User-Agent`, `Host`, `Content-Type`, `Content-Length` or
+    /// `Transfer-Encoding` is filled in.
+    pub auto_headers: bool,
+    pub end_stream_mode: EndStreamMode,
+    /// Sees every 1xx response of an HTTP/1.1 exchange, including those a
+    /// proxy sends while setting up a CONNECT tunnel.
+    pub on_informational: Option<InformationalHandler>,
+    /// Check `Content-Digest`, `Repr-Digest` and `Digest` against the final
+    /// response body and store the result in `Response::digest`.
+    pub verify_digest: bool,
+    /// Return what arrived of an HTTP/1.1 body the connection cut short,
+    /// marked `Response::truncated`, instead of failing.
+    pub allow_truncated_body: bool,
+    /// Look the host up again instead of using the shared `DnsCache`; the
+    /// new answer is cached for later requests unless the host is pinned.
+    pub fresh_dns: bool,
+    /// Charged with the wire bytes of every attempt, failed ones included;
+    /// sessions point it at their `TrafficMeter`.
+    pub traffic: Option<Arc<TrafficMeter>>,
+    prepared: PreparedCache,
 }
 
 impl Request {
     pub fn new(target: &str, method: impl Into<String>) -> Result<Self, ProtocolError> {
+        let (target, scheme) = parse_target_with_scheme(target)?;
         Ok(Self {
-            target: parse_target(target)?,
+            target,
             method: method.into(),
             query: Vec::new(),
             headers: Vec::new(),
@@ -430,10 +810,28 @@ impl Request {
             trailers: Vec::new(),
             body: None,
             json: None,
+            json_content_type: None,
             data: None,
             timeout: None,
+            decompression_limits: DecompressionLimits::default(),
             follow_redirects: true,
-            proxies: None,
+            follow_refresh: false,
+            redirect_policy: RedirectPolicy::default(),
+            proxies: scheme.and_then(|scheme| scheme.proxies),
+            validate_headers: false,
+            target_form: RequestTargetForm::Auto,
+            signer: None,
+            ssrf_guard: None,
+            user_agent: UserAgent::Inherit,
+            header_profile: None,
+            auto_headers: true,
+            end_stream_mode: EndStreamMode::Immediate,
+            on_informational: None,
+            verify_digest: false,
+            allow_truncated_body: false,
+            fresh_dns: false,
+            traffic: None,
+            prepared: PreparedCache::default(),
         })
     }
 
@@ -509,6 +907,29 @@ impl Request {
         self
     }
 
+    /// JSON Patch (RFC 6902) body: an array of operations.
+    pub fn json_patch(mut self, patch: Value) -> Self {
+        self.set_json_patch(patch);
+        self
+    }
+
+    /// JSON Merge Patch (RFC 7396) body.
+    pub fn merge_patch(mut self, patch: Value) -> Self {
+        self.set_merge_patch(patch);
+        self
+    }
+
+    /// Form body with nested objects and arrays in bracket notation.
+    pub fn nested_data(mut self, value: Value) -> Self {
+        self.set_nested_data(value);
+        self
+    }
+
+    pub fn form_body(mut self, form: FormBody) -> Self {
+        self.set_form_body(form);
+        self
+    }
+
     pub fn data<I, K, V>(mut self, data: I) -> Self
     where
         I: IntoIterator<Item = (K, V)>,
@@ -573,9 +994,20 @@ impl Request {
             serde_json::to_vec(&json).expect("serializing JSON body into bytes must succeed");
         self.body = Some(Bytes::from(serialized));
         self.json = Some(json);
+        self.json_content_type = None;
         self.data = None;
     }
 
+    pub fn set_json_patch(&mut self, patch: Value) {
+        self.set_json(patch);
+        self.json_content_type = Some(APPLICATION_JSON_PATCH.to_string());
+    }
+
+    pub fn set_merge_patch(&mut self, patch: Value) {
+        self.set_json(patch);
+        self.json_content_type = Some(APPLICATION_MERGE_PATCH.to_string());
+    }
+
     pub fn set_data<I, K, V>(&mut self, data: I)
     where
         I: IntoIterator<Item = (K, V)>,
@@ -587,11 +1019,16 @@ impl Request {
             .map(|(k, v)| (k.into(), v.into()))
             .collect();
 
-        let form_body = FormBody::Fields(fields);
-        let encoded = form_body.encode();
+        self.set_form_body(FormBody::Fields(fields));
+    }
+
+    pub fn set_nested_data(&mut self, value: Value) {
+        self.set_form_body(FormBody::nested(value));
+    }
 
-        self.body = Some(Bytes::from(encoded.into_bytes()));
-        self.data = Some(form_body);
+    pub fn set_form_body(&mut self, form: FormBody) {
+        self.body = Some(Bytes::from(form.encode().into_bytes()));
+        self.data = Some(form);
         self.json = None;
     }
 
@@ -623,11 +1060,155 @@ impl Request {
         self.timeout = Some(timeouts);
     }
 
+    pub fn set_decompression_limits(&mut self, limits: DecompressionLimits) {
+        self.decompression_limits = limits;
+    }
+
     pub fn set_follow_redirects(&mut self, allow: bool) {
         self.follow_redirects = allow;
     }
 
+    pub fn set_follow_refresh(&mut self, follow: bool) {
+        self.follow_refresh = follow;
+    }
+
+    pub fn set_redirect_policy(&mut self, policy: RedirectPolicy) {
+        self.redirect_policy = policy;
+    }
+
+    pub fn set_validate_headers(&mut self, enabled: bool) {
+        self.validate_headers = enabled;
+    }
+
+    pub fn set_target_form(&mut self, form: RequestTargetForm) {
+        self.target_form = form;
+    }
+
+    pub fn set_signer<S: RequestSigner + 'static>(&mut self, signer: S) {
+        self.signer = Some(Arc::new(signer));
+    }
+
+    pub fn set_ssrf_guard(&mut self, guard: SsrfGuard) {
+        self.ssrf_guard = Some(guard);
+    }
+
+    pub fn set_user_agent(&mut self, user_agent: UserAgent) {
+        self.user_agent = user_agent;
+    }
+
+    /// Adopts a session or client default unless the request chose its own.
+    pub fn inherit_user_agent(&mut self, user_agent: &UserAgent) {
+        if self.user_agent == UserAgent::Inherit {
+            self.user_agent = user_agent.clone();
+        }
+    }
+
+    pub fn set_header_profile(&mut self, profile: HeaderProfile) {
+        self.header_profile = Some(profile);
+    }
+
+    pub fn set_auto_headers(&mut self, enabled: bool) {
+        self.auto_headers = enabled;
+    }
+
+    pub fn set_end_stream_mode(&mut self, mode: EndStreamMode) {
+        self.end_stream_mode = mode;
+    }
+
+    pub fn set_on_informational<F>(&mut self, handler: F)
+    where
+        F: Fn(&InformationalResponse) + Send + Sync + 'static,
+    {
+        self.on_informational = Some(InformationalHandler::new(handler));
+    }
+
+    pub fn set_verify_digest(&mut self, enabled: bool) {
+        self.verify_digest = enabled;
+    }
+
+    pub fn set_allow_truncated_body(&mut self, enabled: bool) {
+        self.allow_truncated_body = enabled;
+    }
+
+    pub fn set_fresh_dns(&mut self, enabled: bool) {
+        self.fresh_dns = enabled;
+    }
+
+    /// Appends the signer's headers to the final, fully prepared `headers`.
+    pub fn sign_headers(&self, headers: &mut Vec<Header>) -> Result<(), ProtocolError> {
+        if let Some(signer) = &self.signer {
+            let signature_headers = signer.sign(self, headers)?;
+            headers.extend(signature_headers);
+        }
+        Ok(())
+    }
+
+    /// HTTP proxy this request is forwarded through without a CONNECT tunnel:
+    /// plain `http` targets with an HTTP proxy configured and no SOCKS proxy.
+    pub fn forward_proxy(&self) -> Option<&Url> {
+        let proxies = self.proxies.as_ref()?;
+        if proxies.socks.is_some() || self.target.scheme() != "http" {
+            return None;
+        }
+        proxies.http.as_ref()
+    }
+
+    /// Request-target for the HTTP/1.1 request line, honoring `target_form`.
+    pub fn request_target(&self) -> String {
+        let absolute = match self.target_form {
+            RequestTargetForm::Auto => self.forward_proxy().is_some(),
+            RequestTargetForm::Origin => false,
+            RequestTargetForm::Absolute => true,
+        };
+        if !absolute {
+            return self.path();
+        }
+
+        // Asterisk-form has no absolute equivalent; the authority alone
+        // addresses the server (RFC 7230 Section 5.3.4).
+        let path = if self.is_asterisk_form() {
+            String::new()
+        } else {
+            self.path()
+        };
+        let host = self.target.host().unwrap_or_default();
+        match self.target.port() {
+            Some(port) => format!("{}://{}:{}{}", self.target.scheme(), host, port, path),
+            None => format!("{}://{}{}", self.target.scheme(), host, path),
+        }
+    }
+
+    /// Runs header (and TRACE body) validation for `protocol` when it is enabled on this request.
+    pub fn check_headers(&self, protocol: &HttpProtocol) -> Result<(), ProtocolError> {
+        if !self.validate_headers {
+            return Ok(());
+        }
+        // RFC 9110 Section 9.3.8: a client MUST NOT send content in a TRACE request
+        if self.method.eq_ignore_ascii_case("TRACE")
+            && self.body.as_ref().map_or(false, |body| !body.is_empty())
+        {
+            return Err(ProtocolError::RequestFailed(
+                "TRACE requests must not carry a body".to_string(),
+            ));
+        }
+        validate_request_headers(&self.headers, &self.trailers, protocol)
+    }
+
+    /// `OPTIONS *` (RFC 9110 Section 9.3.7): an OPTIONS request whose target
+    /// path is `/*` and carries no query.
+    pub fn is_asterisk_form(&self) -> bool {
+        self.method.eq_ignore_ascii_case("OPTIONS")
+            && matches!(self.target.path_only(), "*" | "/*")
+            && self.target.url.query().is_none()
+            && self.query.is_empty()
+    }
+
+    /// Path with query, or `*` for asterisk-form OPTIONS.
     pub fn path(&self) -> String {
+        if self.is_asterisk_form() {
+            return "*".to_string();
+        }
+
         let path = self.target.url.path();
         let path = if path.is_empty() { "/" } else { path };
 
@@ -684,7 +1265,14 @@ impl Request {
         }
     }
 
+    /// Headers in HTTP/2 and HTTP/3 order; see [`Request::prepare_headers_for`].
     pub fn prepare_headers(&self) -> Vec<Header> {
+        self.prepare_headers_for(&HttpProtocol::Http2)
+    }
+
+    /// Headers as sent over `protocol`. Only a header profile makes the
+    /// result depend on the protocol, adding `Host` and reordering for HTTP/1.1.
+    pub fn prepare_headers_for(&self, protocol: &HttpProtocol) -> Vec<Header> {
         let mut headers: Vec<Header> = self
             .headers
             .iter()
@@ -698,11 +1286,19 @@ impl Request {
             }
         }
 
+        if !self.auto_headers {
+            return headers;
+        }
+
         if !Self::has_header(&headers, CONTENT_TYPE_HEADER) {
             if self.json.is_some() {
+                let content_type = self
+                    .json_content_type
+                    .as_deref()
+                    .unwrap_or(APPLICATION_JSON);
                 headers.push(Header::new(
                     CONTENT_TYPE_HEADER.to_string(),
-                    APPLICATION_JSON.to_string(),
+                    content_type.to_string(),
                 ));
             } else if self.data.is_some() {
                 headers.push(Header::new(
@@ -712,11 +1308,55 @@ impl Request {
             }
         }
 
-        ensure_user_agent(&mut headers);
+        if let Some(profile) = self.header_profile {
+            return self.arrange_for_profile(profile, headers, protocol);
+        }
+
+        match &self.user_agent {
+            UserAgent::Inherit => ensure_user_agent(&mut headers),
+            UserAgent::Custom(value) => {
+                if !Self::has_header(&headers, USER_AGENT_HEADER) {
+                    headers.push(Header::new(USER_AGENT_HEADER.to_string(), value.clone()));
+                }
+            }
+            UserAgent::Omit => {}
+        }
 
         headers
     }
 
+    fn arrange_for_profile(
+        &self,
+        profile: HeaderProfile,
+        mut headers: Vec<Header>,
+        protocol: &HttpProtocol,
+    ) -> Vec<Header> {
+        let h1 = *protocol == HttpProtocol::Http1;
+        if h1 && !Self::has_header(&headers, HOST_HEADER) {
+            headers.push(Header::new("Host".to_string(), self.host_header_value()));
+        }
+        let skip: &[&str] = match &self.user_agent {
+            UserAgent::Inherit => &[],
+            UserAgent::Custom(value) => {
+                if !Self::has_header(&headers, USER_AGENT_HEADER) {
+                    let name = if h1 { "User-Agent" } else { USER_AGENT_HEADER };
+                    headers.push(Header::new(name.to_string(), value.clone()));
+                }
+                &[]
+            }
+            UserAgent::Omit => &[USER_AGENT_HEADER],
+        };
+        profile.arrange(headers, protocol, skip)
+    }
+
+    /// Value of the `Host` header: the authority, with the port only when it
+    /// is not the scheme's default.
+    pub fn host_header_value(&self) -> String {
+        self.target
+            .authority()
+            .unwrap_or_else(|| self.target.host().unwrap_or_default().to_string())
+    }
+
     pub fn prepare_pseudo_headers(request: &Request) -> Result<Vec<Header>, ProtocolError> {
         let mut pseudo_headers: Vec<Header> = request
             .headers
@@ -748,14 +1388,9 @@ impl Request {
                 pseudo_headers.retain(|h| h.name != ":scheme" && h.name != ":path");
             }
             "OPTIONS" => {
-                // TOOD ??? remove path_only ???
-                let path_value = if request.target.path_only() == "*" {
-                    "*".to_string()
-                } else {
-                    request.target.path().to_string()
-                };
+                // `*` for asterisk-form (RFC 9113 Section 8.3.1), path with query otherwise
                 if !pseudo_headers.iter().any(|h| h.name == ":path") {
-                    pseudo_headers.push(Header::new(":path".to_string(), path_value));
+                    pseudo_headers.push(Header::new(":path".to_string(), request.path()));
                 }
                 if !pseudo_headers.iter().any(|h| h.name == ":authority") {
                     if let Some(authority) = request.target.authority() {
@@ -828,7 +1463,8 @@ impl Request {
     }
 
     pub fn prepare_request(&self) -> Result<PreparedRequest, ProtocolError> {
-        let headers = self.prepare_headers();
+        let mut headers = self.prepare_headers();
+        self.sign_headers(&mut headers)?;
         let pseudo_headers = Self::prepare_pseudo_headers(self)?;
 
         Ok(PreparedRequest {
@@ -841,6 +1477,65 @@ impl Request {
         })
     }
 
+    /// Like [`prepare_request`](Self::prepare_request), but reuses the last
+    /// result while the request is unchanged. Any edit, including direct
+    /// writes to the public fields, is picked up on the next call. Signed
+    /// requests are prepared afresh every time so each send gets its own
+    /// signature.
+    pub fn prepare_once(&self) -> Result<Arc<PreparedRequest>, ProtocolError> {
+        if self.signer.is_some() {
+            return Ok(Arc::new(self.prepare_request()?));
+        }
+        let fingerprint = self.preparation_fingerprint();
+        let mut slot = match self.prepared.slot.lock() {
+            Ok(slot) => slot,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some((cached, prepared)) = slot.as_ref() {
+            let same_body = match (&prepared.body, &self.body) {
+                (Some(a), Some(b)) => a.as_ptr() == b.as_ptr() && a.len() == b.len(),
+                (None, None) => true,
+                _ => false,
+            };
+            if *cached == fingerprint && same_body {
+                return Ok(prepared.clone());
+            }
+        }
+        let prepared = Arc::new(self.prepare_request()?);
+        *slot = Some((fingerprint, prepared.clone()));
+        Ok(prepared)
+    }
+
+    /// Hash of everything `prepare_request` reads except the body, which
+    /// `prepare_once` compares by buffer identity: `Bytes` never change in
+    /// place and the cached copy keeps the buffer alive.
+    fn preparation_fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.method.hash(&mut hasher);
+        self.target.url.as_str().hash(&mut hasher);
+        self.query.hash(&mut hasher);
+        self.headers.len().hash(&mut hasher);
+        for header in self.headers.iter().chain(&self.trailers) {
+            header.name.hash(&mut hasher);
+            header.value.hash(&mut hasher);
+        }
+        self.cookies.hash(&mut hasher);
+        self.json.is_some().hash(&mut hasher);
+        self.json_content_type.hash(&mut hasher);
+        self.data.is_some().hash(&mut hasher);
+        self.header_profile.hash(&mut hasher);
+        match &self.user_agent {
+            UserAgent::Inherit => 0u8.hash(&mut hasher),
+            UserAgent::Custom(value) => {
+                1u8.hash(&mut hasher);
+                value.hash(&mut hasher);
+            }
+            UserAgent::Omit => 2u8.hash(&mut hasher),
+        }
+        self.auto_headers.hash(&mut hasher);
+        hasher.finish()
+    }
+
     pub fn timeouts(&self, fallback: &ClientTimeouts) -> ClientTimeouts {
         self.timeout.clone().unwrap_or_else(|| fallback.clone())
     }
@@ -850,11 +1545,26 @@ impl Request {
         self
     }
 
+    pub fn decompression_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.set_decompression_limits(limits);
+        self
+    }
+
     pub fn follow_redirects(mut self, allow: bool) -> Self {
         self.set_follow_redirects(allow);
         self
     }
 
+    pub fn follow_refresh(mut self, follow: bool) -> Self {
+        self.set_follow_refresh(follow);
+        self
+    }
+
+    pub fn redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.set_redirect_policy(policy);
+        self
+    }
+
     pub fn proxies(mut self, proxies: ProxySettings) -> Self {
         self.proxies = Some(proxies);
         self
@@ -881,4 +1591,67 @@ impl Request {
         self.proxies = None;
         self
     }
+
+    pub fn validate_headers(mut self, enabled: bool) -> Self {
+        self.set_validate_headers(enabled);
+        self
+    }
+
+    pub fn target_form(mut self, form: RequestTargetForm) -> Self {
+        self.set_target_form(form);
+        self
+    }
+
+    pub fn signer<S: RequestSigner + 'static>(mut self, signer: S) -> Self {
+        self.set_signer(signer);
+        self
+    }
+
+    pub fn ssrf_guard(mut self, guard: SsrfGuard) -> Self {
+        self.set_ssrf_guard(guard);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: UserAgent) -> Self {
+        self.set_user_agent(user_agent);
+        self
+    }
+
+    pub fn header_profile(mut self, profile: HeaderProfile) -> Self {
+        self.set_header_profile(profile);
+        self
+    }
+
+    pub fn auto_headers(mut self, enabled: bool) -> Self {
+        self.set_auto_headers(enabled);
+        self
+    }
+
+    pub fn end_stream_mode(mut self, mode: EndStreamMode) -> Self {
+        self.set_end_stream_mode(mode);
+        self
+    }
+
+    pub fn on_informational<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&InformationalResponse) + Send + Sync + 'static,
+    {
+        self.set_on_informational(handler);
+        self
+    }
+
+    pub fn verify_digest(mut self, enabled: bool) -> Self {
+        self.set_verify_digest(enabled);
+        self
+    }
+
+    pub fn allow_truncated_body(mut self, enabled: bool) -> Self {
+        self.set_allow_truncated_body(enabled);
+        self
+    }
+
+    pub fn fresh_dns(mut self) -> Self {
+        self.set_fresh_dns(true);
+        self
+    }
 }
@@ -1,7 +1,10 @@
-use super::{extract_cookies, FrameH2, FrameH3, Header};
+use super::{extract_cookies, DigestVerification, FrameH2, FrameH3, Header, HeaderBlockInfo};
+use crate::utils::CONTENT_TYPE_HEADER;
 use bytes::Bytes;
+use encoding_rs::{Encoding, UTF_8};
 use serde_json::Value;
 use std::fmt::{self, Display, Formatter};
+use std::time::Instant;
 
 #[derive(Debug, Clone)]
 pub enum ResponseFrame {
@@ -17,14 +20,126 @@ pub struct Response {
     pub body: Bytes,
     pub trailers: Option<Vec<Header>>,
     pub frames: Option<Vec<ResponseFrame>>,
+    /// Raw HPACK/QPACK header blocks received for this response, annotated per field.
+    pub header_blocks: Option<Vec<HeaderBlockInfo>>,
     pub cookies: Vec<(String, String)>,
+    /// Body with `Content-Encoding` removed, kept next to the captured
+    /// frames of a compressed response: `body` and the DATA frames hold
+    /// the wire bytes.
+    pub decoded_body: Option<Bytes>,
+    /// When each captured frame was read, index-aligned with `frames`.
+    pub frame_times: Option<Vec<Instant>>,
+    /// Outcome of [`Response::verify_digest`], filled in when the request
+    /// asked for it with `verify_digest`.
+    pub digest: Option<DigestVerification>,
+    /// HTTP/1.1 transfer codings removed from `body`, in `Transfer-Encoding`
+    /// order, e.g. `["gzip", "chunked"]`.
+    pub transfer_codings: Vec<String>,
+    /// The connection ended before the body did; only returned when the
+    /// request sets `allow_truncated_body`.
+    pub truncated: bool,
+    /// Where the HTTP/1.1 body was cut off, when `truncated`.
+    pub truncation: Option<Truncation>,
+}
+
+impl Default for Response {
+    /// An empty `200` HTTP/1.1 response, to build others from with
+    /// `..Response::default()`.
+    fn default() -> Self {
+        Self {
+            status: 200,
+            protocol: "HTTP/1.1".to_string(),
+            headers: Vec::new(),
+            body: Bytes::new(),
+            trailers: None,
+            frames: None,
+            header_blocks: None,
+            cookies: Vec::new(),
+            decoded_body: None,
+            frame_times: None,
+            digest: None,
+            transfer_codings: Vec::new(),
+            truncated: false,
+            truncation: None,
+        }
+    }
+}
+
+/// How far an HTTP/1.1 body got before the connection ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Truncation {
+    /// `received` of the `expected` Content-Length bytes arrived.
+    ContentLength { expected: usize, received: usize },
+    /// A chunk announced `size` bytes and only `received` followed.
+    Chunk { size: usize, received: usize },
+    /// Every chunk that started was complete, but the zero-size last chunk
+    /// never came.
+    LastChunk,
+}
+
+impl Display for Truncation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Truncation::ContentLength { expected, received } => write!(
+                f,
+                "connection closed after {} of {} Content-Length bytes",
+                received, expected
+            ),
+            Truncation::Chunk { size, received } => write!(
+                f,
+                "connection closed after {} bytes of a {}-byte chunk",
+                received, size
+            ),
+            Truncation::LastChunk => write!(f, "connection closed before the last chunk"),
+        }
+    }
 }
 
 impl Response {
+    /// A response with `status`, `headers` and `body` and everything else
+    /// defaulted, e.g. to run checks against one built by hand.
+    pub fn new(status: u16, headers: &[(&str, &str)], body: impl AsRef<[u8]>) -> Self {
+        Self {
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| Header::new(name.to_string(), value.to_string()))
+                .collect(),
+            body: Bytes::copy_from_slice(body.as_ref()),
+            ..Self::default()
+        }
+    }
+
     pub fn text(self: &Self) -> String {
         String::from_utf8_lossy(&self.body).to_string()
     }
 
+    /// Decodes the body with the `charset` from Content-Type, falling back to
+    /// UTF-8. A byte order mark takes precedence over the header, as in
+    /// browsers. Labels follow the WHATWG Encoding Standard, so `iso-8859-1`
+    /// is decoded as windows-1252.
+    pub fn text_with_charset(&self) -> String {
+        let encoding = self.charset().unwrap_or(UTF_8);
+        let (text, _, _) = encoding.decode(&self.body);
+        text.into_owned()
+    }
+
+    /// Encoding named by the Content-Type `charset` parameter, if recognised.
+    pub fn charset(&self) -> Option<&'static Encoding> {
+        let content_type = self
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(CONTENT_TYPE_HEADER))
+            .and_then(|h| h.value.as_deref())?;
+        content_type.split(';').skip(1).find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            if !name.trim().eq_ignore_ascii_case("charset") {
+                return None;
+            }
+            Encoding::for_label(value.trim().trim_matches('"').as_bytes())
+        })
+    }
+
     pub fn json(self: &Self) -> Result<Value, serde_json::Error> {
         serde_json::from_slice(&self.body)
     }
@@ -0,0 +1,311 @@
+use super::{FrameType, FrameTypeH2, FrameTypeH3, Response, ResponseFrame};
+use crate::h2::consts::{END_HEADERS_FLAG, END_STREAM_FLAG, PADDED_FLAG, PRIORITY_FLAG};
+use bytes::{Bytes, BytesMut};
+use std::time::{Duration, Instant};
+
+impl ResponseFrame {
+    pub fn stream_id(&self) -> u32 {
+        match self {
+            ResponseFrame::Http2(frame) => frame.stream_id,
+            ResponseFrame::Http3(frame) => frame.stream_id,
+        }
+    }
+
+    /// Payload as received, padding and priority fields included.
+    pub fn payload(&self) -> &Bytes {
+        match self {
+            ResponseFrame::Http2(frame) => &frame.payload,
+            ResponseFrame::Http3(frame) => &frame.payload,
+        }
+    }
+
+    pub fn is_data(&self) -> bool {
+        matches!(
+            self.frame_type(),
+            FrameType::H2(FrameTypeH2::Data) | FrameType::H3(FrameTypeH3::Data)
+        )
+    }
+
+    pub fn is_headers(&self) -> bool {
+        matches!(
+            self.frame_type(),
+            FrameType::H2(FrameTypeH2::Headers) | FrameType::H3(FrameTypeH3::Headers)
+        )
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        matches!(self.frame_type(), FrameType::H2(FrameTypeH2::Continuation))
+    }
+
+    /// A chunk of QPACK encoder or decoder stream instructions captured with
+    /// an HTTP/3 response.
+    pub fn is_qpack_instructions(&self) -> bool {
+        matches!(
+            self.frame_type(),
+            FrameType::H3(FrameTypeH3::QpackEncoder | FrameTypeH3::QpackDecoder)
+        )
+    }
+
+    /// Type identifier of a frame the connection does not handle, such as an
+    /// extension or GREASE frame; `None` for known types.
+    pub fn unknown_type(&self) -> Option<u64> {
+        match self.frame_type() {
+            FrameType::H2(FrameTypeH2::Unknown(value)) => Some(*value as u64),
+            FrameType::H3(FrameTypeH3::Unknown(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn is_window_update(&self) -> bool {
+        matches!(self.frame_type(), FrameType::H2(FrameTypeH2::WindowUpdate))
+    }
+
+    /// END_STREAM on an HTTP/2 HEADERS or DATA frame. HTTP/3 ends streams
+    /// with a QUIC FIN, which is not a frame, so this is always false there.
+    pub fn is_end_stream(&self) -> bool {
+        match self {
+            ResponseFrame::Http2(frame) => {
+                (self.is_data() || self.is_headers()) && frame.flags & END_STREAM_FLAG != 0
+            }
+            ResponseFrame::Http3(_) => false,
+        }
+    }
+
+    /// Body bytes of a DATA frame with padding removed.
+    pub fn data(&self) -> Option<Bytes> {
+        if !self.is_data() {
+            return None;
+        }
+        match self {
+            ResponseFrame::Http2(frame) => strip_padding(&frame.payload, frame.flags, 0),
+            ResponseFrame::Http3(frame) => Some(frame.payload.clone()),
+        }
+    }
+
+    /// Header block fragment of a HEADERS or CONTINUATION frame, without
+    /// padding or the HTTP/2 priority fields.
+    pub fn header_fragment(&self) -> Option<Bytes> {
+        match self {
+            ResponseFrame::Http2(frame) if self.is_headers() => {
+                let priority = if frame.flags & PRIORITY_FLAG != 0 {
+                    5
+                } else {
+                    0
+                };
+                strip_padding(&frame.payload, frame.flags, priority)
+            }
+            ResponseFrame::Http2(frame) if self.is_continuation() => Some(frame.payload.clone()),
+            ResponseFrame::Http3(frame) if self.is_headers() => Some(frame.payload.clone()),
+            _ => None,
+        }
+    }
+
+    /// Increment of a WINDOW_UPDATE frame.
+    pub fn window_increment(&self) -> Option<u32> {
+        let payload = self.payload();
+        if !self.is_window_update() || payload.len() != 4 {
+            return None;
+        }
+        let raw = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+        Some(raw & 0x7FFF_FFFF)
+    }
+
+    fn frame_type(&self) -> &FrameType {
+        match self {
+            ResponseFrame::Http2(frame) => &frame.frame_type,
+            ResponseFrame::Http3(frame) => &frame.frame_type,
+        }
+    }
+}
+
+/// Drops the pad length byte, `skip` bytes after it and the trailing
+/// padding; `None` if the payload is too short for what the flags promise.
+fn strip_padding(payload: &Bytes, flags: u8, skip: usize) -> Option<Bytes> {
+    if flags & PADDED_FLAG == 0 {
+        return (payload.len() >= skip).then(|| payload.slice(skip..));
+    }
+    let pad_length = *payload.first()? as usize;
+    let start = 1 + skip;
+    let end = payload.len().checked_sub(pad_length)?;
+    (start <= end).then(|| payload.slice(start..end))
+}
+
+/// Typed views over captured frames, e.g. `response.captured_frames().data_frames()`.
+pub trait ResponseFrames {
+    fn headers_frames(&self) -> Vec<&ResponseFrame>;
+
+    fn data_frames(&self) -> Vec<&ResponseFrame>;
+
+    fn window_updates(&self) -> Vec<u32>;
+
+    /// QPACK stream instructions read while decoding HTTP/3 header blocks.
+    fn qpack_instructions(&self) -> Vec<&ResponseFrame>;
+
+    /// Frames of types the connection does not handle, see
+    /// [`ResponseFrame::unknown_type`].
+    fn unknown_frames(&self) -> Vec<&ResponseFrame>;
+
+    /// Encoded header blocks in arrival order: each HTTP/2 HEADERS frame
+    /// joined with its CONTINUATION frames, each HTTP/3 HEADERS frame as is.
+    /// A block still open when the frames end is returned as far as it got.
+    fn encoded_header_blocks(&self) -> Vec<Bytes>;
+
+    /// All DATA payloads, padding removed, concatenated.
+    fn data_bytes(&self) -> Bytes;
+}
+
+impl ResponseFrames for [ResponseFrame] {
+    fn headers_frames(&self) -> Vec<&ResponseFrame> {
+        self.iter().filter(|frame| frame.is_headers()).collect()
+    }
+
+    fn data_frames(&self) -> Vec<&ResponseFrame> {
+        self.iter().filter(|frame| frame.is_data()).collect()
+    }
+
+    fn window_updates(&self) -> Vec<u32> {
+        self.iter()
+            .filter_map(ResponseFrame::window_increment)
+            .collect()
+    }
+
+    fn qpack_instructions(&self) -> Vec<&ResponseFrame> {
+        self.iter()
+            .filter(|frame| frame.is_qpack_instructions())
+            .collect()
+    }
+
+    fn unknown_frames(&self) -> Vec<&ResponseFrame> {
+        self.iter()
+            .filter(|frame| frame.unknown_type().is_some())
+            .collect()
+    }
+
+    fn encoded_header_blocks(&self) -> Vec<Bytes> {
+        let mut blocks = Vec::new();
+        let mut open: Option<BytesMut> = None;
+        for frame in self {
+            let fragment = match frame.header_fragment() {
+                Some(fragment) => fragment,
+                None => continue,
+            };
+            if !frame.is_continuation() {
+                blocks.extend(open.take().map(BytesMut::freeze));
+                open = Some(BytesMut::new());
+            }
+            // A CONTINUATION without a HEADERS before it belongs to no block.
+            let block = match open.as_mut() {
+                Some(block) => block,
+                None => continue,
+            };
+            block.extend_from_slice(&fragment);
+            let complete = match frame {
+                ResponseFrame::Http2(frame) => frame.flags & END_HEADERS_FLAG != 0,
+                ResponseFrame::Http3(_) => true,
+            };
+            if complete {
+                blocks.extend(open.take().map(BytesMut::freeze));
+            }
+        }
+        blocks.extend(open.map(BytesMut::freeze));
+        blocks
+    }
+
+    fn data_bytes(&self) -> Bytes {
+        let mut body = BytesMut::new();
+        for data in self.iter().filter_map(ResponseFrame::data) {
+            body.extend_from_slice(&data);
+        }
+        body.freeze()
+    }
+}
+
+/// Inter-arrival statistics over consecutive frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameGapStats {
+    /// Number of gaps, one fewer than the frames they separate.
+    pub gaps: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub median: Duration,
+    /// From the first frame to the last.
+    pub total: Duration,
+}
+
+impl FrameGapStats {
+    /// `None` when there is no gap to measure.
+    pub fn from_gaps(gaps: &[Duration]) -> Option<Self> {
+        let mut sorted = gaps.to_vec();
+        sorted.sort_unstable();
+        let min = *sorted.first()?;
+        let max = *sorted.last()?;
+        let total: Duration = sorted.iter().sum();
+        let middle = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) / 2
+        } else {
+            sorted[middle]
+        };
+        Some(Self {
+            gaps: sorted.len(),
+            min,
+            max,
+            mean: total / sorted.len() as u32,
+            median,
+            total,
+        })
+    }
+}
+
+fn gaps(times: impl Iterator<Item = Instant>) -> Vec<Duration> {
+    let times: Vec<Instant> = times.collect();
+    times
+        .windows(2)
+        .map(|pair| pair[1].saturating_duration_since(pair[0]))
+        .collect()
+}
+
+impl Response {
+    /// Frames captured for this response; empty when capture was off.
+    pub fn captured_frames(&self) -> &[ResponseFrame] {
+        self.frames.as_deref().unwrap_or_default()
+    }
+
+    /// Arrival times of the captured frames, on the monotonic clock.
+    pub fn frame_times(&self) -> &[Instant] {
+        self.frame_times.as_deref().unwrap_or_default()
+    }
+
+    /// Captured frames paired with their arrival times.
+    pub fn timed_frames(&self) -> impl Iterator<Item = (Instant, &ResponseFrame)> {
+        self.frame_times()
+            .iter()
+            .copied()
+            .zip(self.captured_frames())
+    }
+
+    /// Time between each captured frame and the one before it.
+    pub fn frame_gaps(&self) -> Vec<Duration> {
+        gaps(self.frame_times().iter().copied())
+    }
+
+    /// Time between consecutive DATA frames. Gaps close to zero mean the
+    /// server sent a buffered body in one go; gaps matching its write
+    /// interval mean it flushes per chunk.
+    pub fn data_frame_gaps(&self) -> Vec<Duration> {
+        gaps(
+            self.timed_frames()
+                .filter(|(_, frame)| frame.is_data())
+                .map(|(at, _)| at),
+        )
+    }
+
+    pub fn frame_gap_stats(&self) -> Option<FrameGapStats> {
+        FrameGapStats::from_gaps(&self.frame_gaps())
+    }
+
+    pub fn data_frame_gap_stats(&self) -> Option<FrameGapStats> {
+        FrameGapStats::from_gaps(&self.data_frame_gaps())
+    }
+}
@@ -0,0 +1,248 @@
+use super::error::ProtocolError;
+use super::ssrf::{canonical_host, IpCidr, SsrfGuard};
+use super::{Request, Target};
+use crate::dns::DnsCache;
+use std::net::IpAddr;
+
+/// One entry of a [`HostScope`] list.
+#[derive(Debug, Clone)]
+pub enum HostPattern {
+    /// The host itself, case-insensitively.
+    Exact(String),
+    /// `*` stands for any run of characters, dots included, and `?` for one:
+    /// `*.example.com` matches every subdomain but not `example.com` itself.
+    Glob(String),
+    /// IP literals in the network, and names that resolve into it.
+    Cidr(IpCidr),
+    /// Hosts the expression finds a match in; anchor it to match whole names.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl PartialEq for HostPattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (HostPattern::Exact(a), HostPattern::Exact(b)) => a == b,
+            (HostPattern::Glob(a), HostPattern::Glob(b)) => a == b,
+            (HostPattern::Cidr(a), HostPattern::Cidr(b)) => a == b,
+            #[cfg(feature = "regex")]
+            (HostPattern::Regex(a), HostPattern::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for HostPattern {}
+
+impl HostPattern {
+    /// Reads `10.0.0.0/8` and bare addresses as [`Cidr`](Self::Cidr),
+    /// anything with `*` or `?` as [`Glob`](Self::Glob) and the rest as
+    /// [`Exact`](Self::Exact).
+    pub fn parse(pattern: &str) -> Result<Self, ProtocolError> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Err(ProtocolError::InvalidTarget(
+                "Empty host pattern".to_string(),
+            ));
+        }
+        if pattern.contains('/') || pattern.parse::<IpAddr>().is_ok() {
+            return Ok(HostPattern::Cidr(pattern.parse()?));
+        }
+        if pattern.contains(['*', '?']) {
+            return Ok(HostPattern::Glob(pattern.to_ascii_lowercase()));
+        }
+        Ok(HostPattern::Exact(canonical_host(pattern)))
+    }
+
+    /// True if the pattern covers `host`, already canonical, which resolved
+    /// to `addrs`. A network covers a name only if every address is in it.
+    pub fn matches(&self, host: &str, addrs: &[IpAddr]) -> bool {
+        match self {
+            HostPattern::Exact(name) => name == host,
+            HostPattern::Glob(glob) => glob_matches(glob.as_bytes(), host.as_bytes()),
+            HostPattern::Cidr(cidr) => {
+                !addrs.is_empty() && addrs.iter().all(|addr| cidr.contains(*addr))
+            }
+            #[cfg(feature = "regex")]
+            HostPattern::Regex(regex) => regex.is_match(host),
+        }
+    }
+}
+
+/// Hosts a client may contact, for scoped engagements where nothing out of
+/// scope may be reached, not even by a redirect.
+///
+/// A host is out of scope if any `deny` pattern covers it, or if `allow` is
+/// not empty and no `allow` pattern does. The check runs before every
+/// request, each redirect hop included, and before the connection is opened.
+/// Protocol switches such as Alt-Svc keep the host and are covered by it.
+///
+/// When networks are listed, clients also hand the scope to the request's
+/// [`SsrfGuard`], which checks the addresses each
+/// connection actually dials, so a name that resolves differently the
+/// second time cannot slip out.
+///
+/// ```ignore
+/// let scope = HostScope::new()
+///     .allow("*.example.com")?
+///     .allow("203.0.113.0/24")?
+///     .deny("admin.example.com")?;
+/// let h1 = H1::with_config(ClientConfig::new().host_scope(scope));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostScope {
+    allow: Vec<HostPattern>,
+    deny: Vec<HostPattern>,
+}
+
+impl HostScope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pattern as read by [`HostPattern::parse`] to the allowlist.
+    pub fn allow(self, pattern: &str) -> Result<Self, ProtocolError> {
+        Ok(self.allow_pattern(HostPattern::parse(pattern)?))
+    }
+
+    /// Adds a pattern as read by [`HostPattern::parse`] to the denylist.
+    pub fn deny(self, pattern: &str) -> Result<Self, ProtocolError> {
+        Ok(self.deny_pattern(HostPattern::parse(pattern)?))
+    }
+
+    pub fn allow_pattern(mut self, pattern: HostPattern) -> Self {
+        self.allow.push(pattern);
+        self
+    }
+
+    pub fn deny_pattern(mut self, pattern: HostPattern) -> Self {
+        self.deny.push(pattern);
+        self
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn allow_regex(self, pattern: &str) -> Result<Self, ProtocolError> {
+        Ok(self.allow_pattern(HostPattern::Regex(compile(pattern)?)))
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn deny_regex(self, pattern: &str) -> Result<Self, ProtocolError> {
+        Ok(self.deny_pattern(HostPattern::Regex(compile(pattern)?)))
+    }
+
+    /// True if `host`, which resolved to `addrs`, may be contacted. For a
+    /// denied network one address inside it is enough.
+    pub fn is_in_scope(&self, host: &str, addrs: &[IpAddr]) -> bool {
+        let host = canonical_host(host);
+        let denied = self.deny.iter().any(|pattern| match pattern {
+            HostPattern::Cidr(cidr) => addrs.iter().any(|addr| cidr.contains(*addr)),
+            pattern => pattern.matches(&host, addrs),
+        });
+        if denied {
+            return false;
+        }
+        self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|pattern| pattern.matches(&host, addrs))
+    }
+
+    /// Fails with [`ProtocolError::OutOfScope`] unless the host of `target`
+    /// is in scope. Names are resolved only when a network is listed.
+    pub async fn check(&self, target: &Target) -> Result<(), ProtocolError> {
+        let host = target
+            .host()
+            .map(canonical_host)
+            .ok_or_else(|| ProtocolError::InvalidTarget("Target missing host".to_string()))?;
+        let addrs = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) if self.has_networks() => {
+                let port = target.port().unwrap_or(0);
+                DnsCache::global()
+                    .resolve(&host, port)
+                    .await
+                    .map_err(|e| {
+                        ProtocolError::ConnectionFailed(format!(
+                            "DNS lookup failed for {}:{}: {}",
+                            host, port, e
+                        ))
+                    })?
+                    .into_iter()
+                    .map(|addr| addr.ip())
+                    .collect()
+            }
+            Err(_) => Vec::new(),
+        };
+        self.check_resolved(&host, &addrs)
+    }
+
+    /// Fails with [`ProtocolError::OutOfScope`] unless `host`, which resolved
+    /// to `addrs`, is in scope.
+    pub fn check_resolved(&self, host: &str, addrs: &[IpAddr]) -> Result<(), ProtocolError> {
+        if self.is_in_scope(host, addrs) {
+            Ok(())
+        } else {
+            Err(ProtocolError::OutOfScope {
+                host: canonical_host(host),
+            })
+        }
+    }
+
+    /// Hands the scope to the SSRF guard of `request`, creating one that
+    /// blocks nothing else if needed, so the addresses its connections dial
+    /// are checked too. Without networks the name alone decides and nothing
+    /// is added.
+    pub fn guard(&self, request: &mut Request) {
+        if !self.has_networks() {
+            return;
+        }
+        request.ssrf_guard = Some(match request.ssrf_guard.take() {
+            Some(guard) => guard.host_scope(self.clone()),
+            None => SsrfGuard::for_scope(self.clone()),
+        });
+    }
+
+    /// True if a network is listed, so names have to be resolved.
+    pub fn has_networks(&self) -> bool {
+        self.allow
+            .iter()
+            .chain(&self.deny)
+            .any(|pattern| matches!(pattern, HostPattern::Cidr(_)))
+    }
+}
+
+#[cfg(feature = "regex")]
+fn compile(pattern: &str) -> Result<regex::Regex, ProtocolError> {
+    regex::RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|err| ProtocolError::InvalidTarget(format!("Invalid host regex: {}", err)))
+}
+
+fn glob_matches(glob: &[u8], host: &[u8]) -> bool {
+    let (mut g, mut h) = (0, 0);
+    // Where the last `*` was and how much of `host` it has taken so far.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while h < host.len() {
+        match glob.get(g) {
+            Some(b'*') => {
+                backtrack = Some((g, h));
+                g += 1;
+            }
+            Some(&c) if c == b'?' || c == host[h] => {
+                g += 1;
+                h += 1;
+            }
+            _ => match backtrack {
+                Some((star, taken)) => {
+                    g = star + 1;
+                    h = taken + 1;
+                    backtrack = Some((star, taken + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == b'*')
+}
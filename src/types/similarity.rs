@@ -0,0 +1,185 @@
+use super::Response;
+use ring::digest::{digest, SHA256};
+
+/// Bodies up to this many normalized characters are compared by edit
+/// distance; longer ones fall back to simhash, which is linear.
+const LEVENSHTEIN_MAX_CHARS: usize = 4096;
+
+/// Default width of the length buckets in a [`PageSignature`].
+pub const DEFAULT_LENGTH_BUCKET: usize = 64;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Coarse identity of a page for grouping brute-force results: responses
+/// with equal signatures are most likely the same template (e.g. a custom
+/// 404 page) even if tokens or timestamps inside them differ.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageSignature {
+    pub status: u16,
+    /// Normalized body length divided by the bucket width.
+    pub length_bucket: usize,
+    /// `<title>` text with whitespace collapsed.
+    pub title: Option<String>,
+}
+
+impl Response {
+    /// Lowercase hex SHA-256 of the raw body.
+    pub fn body_hash(&self) -> String {
+        digest(&SHA256, &self.body)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Body text with volatile details removed, see [`normalize_body`].
+    pub fn normalized_body(&self) -> String {
+        normalize_body(&self.text_with_charset())
+    }
+
+    /// 64-bit simhash of the normalized body; similar pages differ in few bits.
+    pub fn simhash(&self) -> u64 {
+        simhash(&self.normalized_body())
+    }
+
+    /// Similarity of the normalized bodies in `0.0..=1.0`: edit distance for
+    /// short bodies, simhash distance for long ones.
+    pub fn similarity(&self, other: &Response) -> f64 {
+        text_similarity(&self.normalized_body(), &other.normalized_body())
+    }
+
+    /// Text of the first `<title>` element, whitespace collapsed.
+    pub fn html_title(&self) -> Option<String> {
+        html_title(&self.text_with_charset())
+    }
+
+    pub fn page_signature(&self) -> PageSignature {
+        self.page_signature_with_bucket(DEFAULT_LENGTH_BUCKET)
+    }
+
+    pub fn page_signature_with_bucket(&self, bucket: usize) -> PageSignature {
+        let text = self.text_with_charset();
+        PageSignature {
+            status: self.status,
+            length_bucket: normalize_body(&text).len() / bucket.max(1),
+            title: html_title(&text),
+        }
+    }
+}
+
+/// Lowercases, collapses whitespace and replaces every run of digits with a
+/// single `0`, so counters, timestamps and numeric ids do not count as changes.
+pub fn normalize_body(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut pending_space = false;
+    let mut in_digits = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            pending_space = !normalized.is_empty();
+            in_digits = false;
+            continue;
+        }
+        if pending_space {
+            normalized.push(' ');
+            pending_space = false;
+        }
+        if c.is_ascii_digit() {
+            if !in_digits {
+                normalized.push('0');
+                in_digits = true;
+            }
+            continue;
+        }
+        in_digits = false;
+        normalized.extend(c.to_lowercase());
+    }
+    normalized
+}
+
+/// Character-level edit distance.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() || b.is_empty() {
+        return a.len().max(b.len());
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Charikar simhash over word trigrams (single words for shorter texts).
+pub fn simhash(text: &str) -> u64 {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut weights = [0i64; 64];
+    let mut add = |hash: u64| {
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if (hash >> bit) & 1 == 1 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    };
+    if words.len() < 3 {
+        words.iter().for_each(|word| add(fnv1a(word.as_bytes())));
+    } else {
+        for shingle in words.windows(3) {
+            add(fnv1a(shingle.join(" ").as_bytes()));
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |hash, (bit, _)| hash | (1 << bit))
+}
+
+/// Number of differing bits between two simhashes.
+pub fn simhash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Similarity of two already normalized texts in `0.0..=1.0`.
+pub fn text_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let longest = a.chars().count().max(b.chars().count());
+    if longest <= LEVENSHTEIN_MAX_CHARS {
+        1.0 - levenshtein(a, b) as f64 / longest as f64
+    } else {
+        1.0 - simhash_distance(simhash(a), simhash(b)) as f64 / 64.0
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+fn html_title(html: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets valid for the original string.
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let content_start = open + lower[open..].find('>')? + 1;
+    let content_end = lower[content_start..]
+        .find("</title")
+        .map_or(html.len(), |end| content_start + end);
+    let title = html[content_start..content_end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!title.is_empty()).then_some(title)
+}
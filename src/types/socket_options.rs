@@ -0,0 +1,83 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Local end of outgoing TCP connections and QUIC endpoints, e.g. a fixed
+/// source port for firewall pinhole tests:
+///
+/// ```ignore
+/// let config = ClientConfig::new().socket_options(
+///     SocketOptions::new().local_port(40000).reuse_address(true),
+/// );
+/// let h3 = H3::with_config(config);
+/// ```
+///
+/// Connections to a proxy still use a port the OS picks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// Source address; the unspecified address of the peer's family when unset.
+    pub local_ip: Option<IpAddr>,
+    /// Source port; the OS picks one when unset.
+    pub local_port: Option<u16>,
+    /// `SO_REUSEADDR`, so a pinned port can be bound again while the last
+    /// connection on it is in TIME_WAIT.
+    pub reuse_address: bool,
+    /// `SO_REUSEPORT`; only applied on Unix.
+    pub reuse_port: bool,
+    /// Look the host up with the resolver instead of a cached
+    /// [`DnsCache`](crate::DnsCache) entry. Set per request by
+    /// `Request::fresh_dns`.
+    pub fresh_dns: bool,
+}
+
+impl SocketOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn local_ip(mut self, ip: IpAddr) -> Self {
+        self.local_ip = Some(ip);
+        self
+    }
+
+    pub fn local_port(mut self, port: u16) -> Self {
+        self.local_port = Some(port);
+        self
+    }
+
+    pub fn reuse_address(mut self, enabled: bool) -> Self {
+        self.reuse_address = enabled;
+        self
+    }
+
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.reuse_port = enabled;
+        self
+    }
+
+    pub fn fresh_dns(mut self, enabled: bool) -> Self {
+        self.fresh_dns = enabled;
+        self
+    }
+
+    /// True when nothing is set and the OS defaults apply.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// True when the socket must be bound before connecting.
+    pub fn binds(&self) -> bool {
+        self.local_ip.is_some()
+            || self.local_port.is_some()
+            || self.reuse_address
+            || self.reuse_port
+    }
+
+    /// Address to bind before connecting to `remote`.
+    pub fn bind_addr(&self, remote: &SocketAddr) -> SocketAddr {
+        let ip = self.local_ip.unwrap_or(if remote.is_ipv4() {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        } else {
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        });
+        SocketAddr::new(ip, self.local_port.unwrap_or(0))
+    }
+}
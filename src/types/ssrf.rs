@@ -0,0 +1,359 @@
+use super::error::ProtocolError;
+use super::scope::HostScope;
+use crate::dns::DnsCache;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+
+/// An IPv4 or IPv6 network in CIDR notation, e.g. `10.0.0.0/8` or `fe80::/10`.
+/// A bare address is a single-host network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpCidr {
+    /// Builds a network, clearing host bits below `prefix`.
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, ProtocolError> {
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(ProtocolError::InvalidTarget(format!(
+                "CIDR prefix /{} is too long for {}",
+                prefix, addr
+            )));
+        }
+        let addr = match addr {
+            IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(u32::from(v4) & v4_mask(prefix))),
+            IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(u128::from(v6) & v6_mask(prefix))),
+        };
+        Ok(Self { addr, prefix })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                u32::from(ip) & v4_mask(self.prefix) == u32::from(net)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                u128::from(ip) & v6_mask(self.prefix) == u128::from(net)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn v4_mask(prefix: u8) -> u32 {
+    u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0)
+}
+
+fn v6_mask(prefix: u8) -> u128 {
+    u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0)
+}
+
+impl FromStr for IpCidr {
+    type Err = ProtocolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ProtocolError::InvalidTarget(format!("Invalid CIDR '{}'", s));
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().map_err(|_| invalid())?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        Self::new(addr, prefix)
+    }
+}
+
+impl std::fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+const fn v4(a: u8, b: u8, c: u8, d: u8, prefix: u8) -> IpCidr {
+    IpCidr {
+        addr: IpAddr::V4(Ipv4Addr::new(a, b, c, d)),
+        prefix,
+    }
+}
+
+const fn v6(segments: [u16; 8], prefix: u8) -> IpCidr {
+    let [a, b, c, d, e, f, g, h] = segments;
+    IpCidr {
+        addr: IpAddr::V6(Ipv6Addr::new(a, b, c, d, e, f, g, h)),
+        prefix,
+    }
+}
+
+/// Ranges that never belong to a public service: loopback, private,
+/// shared (CGNAT), link-local, multicast, reserved and unspecified space,
+/// plus NAT64 prefixes that would translate to any of those and the 6to4
+/// and Teredo prefixes, whose addresses embed an IPv4 address.
+const DEFAULT_BLOCKED_RANGES: &[IpCidr] = &[
+    v4(0, 0, 0, 0, 8),
+    v4(10, 0, 0, 0, 8),
+    v4(100, 64, 0, 0, 10),
+    v4(127, 0, 0, 0, 8),
+    v4(169, 254, 0, 0, 16),
+    v4(172, 16, 0, 0, 12),
+    v4(192, 0, 0, 0, 24),
+    v4(192, 168, 0, 0, 16),
+    v4(198, 18, 0, 0, 15),
+    v4(224, 0, 0, 0, 4),
+    v4(240, 0, 0, 0, 4),
+    v6([0, 0, 0, 0, 0, 0, 0, 0], 128),
+    v6([0, 0, 0, 0, 0, 0, 0, 1], 128),
+    v6([0x64, 0xff9b, 0, 0, 0, 0, 0, 0], 96),
+    v6([0x64, 0xff9b, 1, 0, 0, 0, 0, 0], 48),
+    v6([0x2001, 0, 0, 0, 0, 0, 0, 0], 32),
+    v6([0x2002, 0, 0, 0, 0, 0, 0, 0], 16),
+    v6([0xfc00, 0, 0, 0, 0, 0, 0, 0], 7),
+    v6([0xfe80, 0, 0, 0, 0, 0, 0, 0], 10),
+    v6([0xff00, 0, 0, 0, 0, 0, 0, 0], 8),
+];
+
+/// Refuses connections to internal addresses, for services that fetch
+/// user-supplied URLs.
+///
+/// The check runs on the addresses a host resolves to, immediately before
+/// connecting, and the connection then goes to exactly those addresses, so
+/// a second DNS answer cannot swap in a different target. Redirect hops open
+/// their own connections and are checked the same way.
+///
+/// An address is blocked if it is in a `deny` range; otherwise it is allowed
+/// if it is in an `allow` range; otherwise it is blocked if it falls in the
+/// built-in private ranges. On top of that, every [`HostScope`] the guard
+/// carries must let the host and its addresses through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsrfGuard {
+    allow: Vec<IpCidr>,
+    deny: Vec<IpCidr>,
+    block_default_ranges: bool,
+    scopes: Vec<HostScope>,
+}
+
+impl Default for SsrfGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SsrfGuard {
+    pub fn new() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            block_default_ranges: true,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// A guard that blocks nothing itself and only holds connections to
+    /// `scope`.
+    pub fn for_scope(scope: HostScope) -> Self {
+        Self::new().block_default_ranges(false).host_scope(scope)
+    }
+
+    /// Exempts a range from the built-in blocks, e.g. an internal API the
+    /// service is meant to reach.
+    pub fn allow(mut self, cidr: &str) -> Result<Self, ProtocolError> {
+        self.allow.push(cidr.parse()?);
+        Ok(self)
+    }
+
+    /// Blocks a range even if it is public or listed in `allow`.
+    pub fn deny(mut self, cidr: &str) -> Result<Self, ProtocolError> {
+        self.deny.push(cidr.parse()?);
+        Ok(self)
+    }
+
+    pub fn allow_cidr(mut self, cidr: IpCidr) -> Self {
+        self.allow.push(cidr);
+        self
+    }
+
+    pub fn deny_cidr(mut self, cidr: IpCidr) -> Self {
+        self.deny.push(cidr);
+        self
+    }
+
+    /// Turns the built-in private ranges off, leaving only the `deny` list.
+    pub fn block_default_ranges(mut self, enabled: bool) -> Self {
+        self.block_default_ranges = enabled;
+        self
+    }
+
+    /// Also requires the addresses a host resolves to when it is dialed to
+    /// be in `scope`.
+    pub fn host_scope(mut self, scope: HostScope) -> Self {
+        if !self.scopes.contains(&scope) {
+            self.scopes.push(scope);
+        }
+        self
+    }
+
+    /// True if the guard only enforces host scopes, which every request of
+    /// a client carries alike.
+    pub fn is_scope_only(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty() && !self.block_default_ranges
+    }
+
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        // IPv4-mapped IPv6 addresses reach the IPv4 host.
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            ip => ip,
+        };
+        if self.deny.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
+        }
+        if self.allow.iter().any(|cidr| cidr.contains(ip)) {
+            return true;
+        }
+        let reserved = DEFAULT_BLOCKED_RANGES.iter().any(|cidr| cidr.contains(ip));
+        !(self.block_default_ranges && reserved)
+    }
+
+    pub fn check(&self, host: &str, ip: IpAddr) -> Result<(), ProtocolError> {
+        if self.is_allowed(ip) {
+            Ok(())
+        } else {
+            Err(ProtocolError::AddressBlocked {
+                host: host.to_string(),
+                addr: ip,
+            })
+        }
+    }
+
+    /// Resolves `host` and returns its addresses if every one of them is
+    /// allowed. A single blocked answer refuses the host, so a name that
+    /// mixes public and internal records cannot be used to get through.
+    pub async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, ProtocolError> {
+        self.resolve_for(host, port, false).await
+    }
+
+    /// [`resolve`](Self::resolve), asking the resolver again instead of the
+    /// cache when `fresh` is set.
+    pub async fn resolve_for(
+        &self,
+        host: &str,
+        port: u16,
+        fresh: bool,
+    ) -> Result<Vec<SocketAddr>, ProtocolError> {
+        let addrs = self.resolve_unscoped(host, port, fresh).await?;
+        let ips: Vec<IpAddr> = addrs.iter().map(|addr| addr.ip()).collect();
+        for scope in &self.scopes {
+            scope.check_resolved(host, &ips)?;
+        }
+        Ok(addrs)
+    }
+
+    /// Resolves a proxy like [`resolve`](Self::resolve) does a target, but
+    /// without the host scopes: they name the targets, not the way there.
+    pub async fn resolve_proxy(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<Vec<SocketAddr>, ProtocolError> {
+        self.resolve_unscoped(host, port, false).await
+    }
+
+    async fn resolve_unscoped(
+        &self,
+        host: &str,
+        port: u16,
+        fresh: bool,
+    ) -> Result<Vec<SocketAddr>, ProtocolError> {
+        let host = canonical_host(host);
+        let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+            Ok(ip) => vec![SocketAddr::new(ip, port)],
+            Err(_) => DnsCache::global()
+                .resolve_for(&host, port, fresh)
+                .await
+                .map_err(|e| {
+                    ProtocolError::ConnectionFailed(format!(
+                        "DNS lookup failed for {}:{}: {}",
+                        host, port, e
+                    ))
+                })?,
+        };
+        if addrs.is_empty() {
+            return Err(ProtocolError::ConnectionFailed(format!(
+                "No addresses found for {}:{}",
+                host, port
+            )));
+        }
+        for addr in &addrs {
+            self.check(&host, addr.ip())?;
+        }
+        Ok(addrs)
+    }
+}
+
+/// Normalizes a host the way it will be looked up: lowercase, without IPv6
+/// brackets or trailing dots, and with shorthand IPv4 forms such as
+/// `2130706433`, `0x7f.1` or `0177.0.0.1` written out in dotted decimal.
+pub fn canonical_host(host: &str) -> String {
+    let host = host.trim();
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    match parse_legacy_ipv4(&host) {
+        Some(ip) => ip.to_string(),
+        None => host,
+    }
+}
+
+/// Parses the `inet_aton` spellings of an IPv4 address: one to four parts,
+/// each decimal, octal (leading `0`) or hex (`0x`), the last filling the
+/// remaining bytes.
+fn parse_legacy_ipv4(host: &str) -> Option<Ipv4Addr> {
+    let parts: Vec<&str> = host.split('.').collect();
+    if parts.is_empty() || parts.len() > 4 {
+        return None;
+    }
+    let mut values = Vec::with_capacity(parts.len());
+    for part in &parts {
+        let value = if let Some(hex) = part.strip_prefix("0x") {
+            if hex.is_empty() {
+                0
+            } else {
+                u32::from_str_radix(hex, 16).ok()?
+            }
+        } else if part.len() > 1 && part.starts_with('0') {
+            u32::from_str_radix(&part[1..], 8).ok()?
+        } else {
+            part.parse::<u32>().ok()?
+        };
+        values.push(value);
+    }
+
+    let (last, leading) = values.split_last()?;
+    if leading.iter().any(|&v| v > 0xff) {
+        return None;
+    }
+    let last_bits = 8 * (4 - leading.len() as u32);
+    if last_bits < 32 && *last >= 1 << last_bits {
+        return None;
+    }
+    let mut addr = *last;
+    for (i, &value) in leading.iter().enumerate() {
+        addr |= value << (24 - 8 * i as u32);
+    }
+    Some(Ipv4Addr::from(addr))
+}
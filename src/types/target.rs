@@ -1,11 +1,15 @@
 use super::protocol::HttpProtocol;
 use std::collections::HashSet;
+use std::path::PathBuf;
 use url::Url;
 
 #[derive(Debug, Clone)]
 pub struct Target {
     pub url: Url,
     pub protocols: HashSet<HttpProtocol>,
+    /// Unix domain socket connected to instead of the host, for `http+unix`
+    /// targets.
+    pub unix_socket: Option<PathBuf>,
 }
 
 impl Target {
@@ -13,6 +17,7 @@ impl Target {
         Self {
             url,
             protocols: HashSet::new(),
+            unix_socket: None,
         }
     }
 
@@ -0,0 +1,248 @@
+use super::Request;
+use bytes::Bytes;
+use serde_json::Value;
+use std::sync::Arc;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+
+/// A request with `{{name}}` placeholders, parsed once and rendered many
+/// times:
+///
+/// ```ignore
+/// let template = Request::new("https://example.com/users/{{id}}?sort={{order}}", "GET")?
+///     .header("X-Request-Id: {{id}}")
+///     .template();
+/// for id in 0..1000 {
+///     let request = template.render(&[("id", id.to_string()), ("order", "asc".to_string())]);
+///     client.send_request(request).await?;
+/// }
+/// ```
+///
+/// Placeholders are substituted in the path, the query, header names and
+/// values, a text body and the string values of a JSON body. Only the parts that contain
+/// a placeholder are rewritten; the URL is never parsed again as a whole.
+/// Placeholders without a matching variable are left in place.
+#[derive(Debug, Clone)]
+pub struct RequestTemplate {
+    parts: Arc<TemplateParts>,
+}
+
+#[derive(Debug)]
+struct TemplateParts {
+    request: Request,
+    /// Path with percent-encoded braces turned back into placeholders.
+    path: Option<String>,
+    url_query: Option<String>,
+    query: Vec<usize>,
+    headers: Vec<usize>,
+    body: Option<String>,
+    json: bool,
+}
+
+impl Request {
+    /// Freezes the request into a [`RequestTemplate`].
+    pub fn template(&self) -> RequestTemplate {
+        RequestTemplate::new(self.clone())
+    }
+}
+
+impl RequestTemplate {
+    pub fn new(request: Request) -> Self {
+        // The URL parser percent-encodes braces in the path but not in the query.
+        let path = request
+            .target
+            .url
+            .path()
+            .replace("%7B%7B", OPEN)
+            .replace("%7D%7D", CLOSE);
+        let path = has_placeholder(&path).then_some(path);
+        let url_query = request
+            .target
+            .url
+            .query()
+            .filter(|query| has_placeholder(query))
+            .map(str::to_string);
+        let query = indices(&request.query, |(name, value)| {
+            has_placeholder(name) || has_placeholder(value)
+        });
+        let headers = indices(&request.headers, |header| {
+            has_placeholder(&header.name) || header.value.as_deref().map_or(false, has_placeholder)
+        });
+        // A JSON body is re-serialized from `json` so values get escaped.
+        let body = request
+            .body
+            .as_ref()
+            .filter(|_| request.json.is_none())
+            .and_then(|body| std::str::from_utf8(body).ok())
+            .filter(|body| has_placeholder(body))
+            .map(str::to_string);
+        let json = request.json.as_ref().map_or(false, json_has_placeholder);
+
+        Self {
+            parts: Arc::new(TemplateParts {
+                request,
+                path,
+                url_query,
+                query,
+                headers,
+                body,
+                json,
+            }),
+        }
+    }
+
+    pub fn request(&self) -> &Request {
+        &self.parts.request
+    }
+
+    /// Names of all placeholders, in order of first appearance.
+    pub fn placeholders(&self) -> Vec<String> {
+        let parts = &self.parts;
+        let request = &parts.request;
+        let mut texts: Vec<&str> = Vec::new();
+        texts.extend(parts.path.as_deref());
+        texts.extend(parts.url_query.as_deref());
+        for &i in &parts.query {
+            texts.push(&request.query[i].0);
+            texts.push(&request.query[i].1);
+        }
+        for &i in &parts.headers {
+            texts.push(&request.headers[i].name);
+            texts.extend(request.headers[i].value.as_deref());
+        }
+        texts.extend(parts.body.as_deref());
+        if let (true, Some(json)) = (parts.json, request.json.as_ref()) {
+            json_strings(json, &mut texts);
+        }
+
+        let mut names: Vec<String> = Vec::new();
+        for text in texts {
+            for name in placeholder_names(text) {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    /// A copy of the request with every known placeholder replaced.
+    pub fn render<K, V>(&self, vars: &[(K, V)]) -> Request
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let parts = &self.parts;
+        let lookup = |name: &str| {
+            vars.iter()
+                .find(|(key, _)| key.as_ref() == name)
+                .map(|(_, value)| value.as_ref())
+        };
+        let mut request = parts.request.clone();
+
+        if let Some(path) = &parts.path {
+            request.target.url.set_path(&substitute(path, &lookup));
+        }
+        if let Some(query) = &parts.url_query {
+            request
+                .target
+                .url
+                .set_query(Some(&substitute(query, &lookup)));
+        }
+        for &i in &parts.query {
+            let (name, value) = &mut request.query[i];
+            *name = substitute(name, &lookup);
+            *value = substitute(value, &lookup);
+        }
+        for &i in &parts.headers {
+            let header = &mut request.headers[i];
+            header.name = substitute(&header.name, &lookup);
+            if let Some(value) = header.value.as_mut() {
+                *value = substitute(value, &lookup);
+            }
+        }
+        if let Some(body) = &parts.body {
+            request.body = Some(Bytes::from(substitute(body, &lookup)));
+        }
+        if let (true, Some(json)) = (parts.json, request.json.as_mut()) {
+            substitute_json(json, &lookup);
+            let serialized =
+                serde_json::to_vec(json).expect("serializing JSON body into bytes must succeed");
+            request.body = Some(Bytes::from(serialized));
+        }
+        request
+    }
+}
+
+fn indices<T>(items: &[T], matches: impl Fn(&T) -> bool) -> Vec<usize> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| matches(item))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn has_placeholder(text: &str) -> bool {
+    placeholder_names(text).next().is_some()
+}
+
+fn placeholder_names(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let start = rest.find(OPEN)? + OPEN.len();
+        let end = start + rest[start..].find(CLOSE)?;
+        let name = rest[start..end].trim();
+        rest = &rest[end + CLOSE.len()..];
+        Some(name)
+    })
+}
+
+fn substitute<'v>(text: &str, lookup: &impl Fn(&str) -> Option<&'v str>) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find(OPEN) {
+        let start = open + OPEN.len();
+        let end = match rest[start..].find(CLOSE) {
+            Some(offset) => start + offset,
+            None => break,
+        };
+        rendered.push_str(&rest[..open]);
+        match lookup(rest[start..end].trim()) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(&rest[open..end + CLOSE.len()]),
+        }
+        rest = &rest[end + CLOSE.len()..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn json_has_placeholder(value: &Value) -> bool {
+    let mut strings = Vec::new();
+    json_strings(value, &mut strings);
+    strings.into_iter().any(has_placeholder)
+}
+
+fn json_strings<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::String(text) => out.push(text),
+        Value::Array(items) => items.iter().for_each(|item| json_strings(item, out)),
+        Value::Object(map) => map.values().for_each(|item| json_strings(item, out)),
+        _ => {}
+    }
+}
+
+fn substitute_json<'v>(value: &mut Value, lookup: &impl Fn(&str) -> Option<&'v str>) {
+    match value {
+        Value::String(text) => *text = substitute(text, lookup),
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| substitute_json(item, lookup)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| substitute_json(item, lookup)),
+        _ => {}
+    }
+}
@@ -1,10 +1,22 @@
-use std::time::Duration;
+use crate::utils::time_remaining;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClientTimeouts {
     pub connect: Option<Duration>,
+    /// Fallback for the header and body-idle phases when they are unset.
     pub read: Option<Duration>,
     pub write: Option<Duration>,
+    /// Time allowed from sending the request to the first response byte.
+    /// HTTP/1.1 only; when it is set, `header_read` starts at that byte.
+    /// HTTP/2 and HTTP/3 count the wait under `header_read`.
+    pub first_byte: Option<Duration>,
+    /// Time allowed to receive the final status line and headers.
+    pub header_read: Option<Duration>,
+    /// Longest gap between two body reads (chunks, DATA frames).
+    pub body_idle: Option<Duration>,
+    /// Cap on the whole body transfer, started once headers are in. Unbounded when `None`.
+    pub body_total: Option<Duration>,
 }
 
 impl Default for ClientTimeouts {
@@ -13,6 +25,10 @@ impl Default for ClientTimeouts {
             connect: Some(Duration::from_secs(10)),
             read: Some(Duration::from_secs(30)),
             write: Some(Duration::from_secs(30)),
+            first_byte: None,
+            header_read: None,
+            body_idle: None,
+            body_total: None,
         }
     }
 }
@@ -23,6 +39,56 @@ impl ClientTimeouts {
             connect: None,
             read: None,
             write: None,
+            first_byte: None,
+            header_read: None,
+            body_idle: None,
+            body_total: None,
         }
     }
+
+    pub fn header_timeout(&self) -> Option<Duration> {
+        self.header_read.or(self.read)
+    }
+
+    pub fn body_idle_timeout(&self) -> Option<Duration> {
+        self.body_idle.or(self.read)
+    }
+
+    pub fn body_total_timeout(&self) -> Option<Duration> {
+        self.body_total
+    }
+
+    /// Timeout for the next body read: the idle timeout, capped by whatever is
+    /// left until `body_deadline`.
+    pub fn next_body_read_timeout(&self, body_deadline: Option<Instant>) -> Option<Duration> {
+        match (self.body_idle_timeout(), time_remaining(body_deadline)) {
+            (Some(idle), Some(total)) => Some(idle.min(total)),
+            (idle, total) => idle.or(total),
+        }
+    }
+}
+
+/// Which of the [`ClientTimeouts`] ran out, see [`ProtocolError::Timeout`].
+///
+/// [`ProtocolError::Timeout`]: crate::types::ProtocolError::Timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    Write,
+    FirstByte,
+    Headers,
+    BodyIdle,
+    BodyTotal,
+}
+
+impl std::fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let phase = match self {
+            TimeoutKind::Write => "writing the request",
+            TimeoutKind::FirstByte => "the first response byte",
+            TimeoutKind::Headers => "the response headers",
+            TimeoutKind::BodyIdle => "the next body read",
+            TimeoutKind::BodyTotal => "the whole body",
+        };
+        write!(f, "{}", phase)
+    }
 }
@@ -1,6 +1,14 @@
-use crate::types::{Header, ProtocolError, Request, Response, Target};
-use std::future::Future;
-use std::time::Duration;
+use crate::scheme::{SchemeRegistry, SchemeSpec, SchemeTransport};
+use crate::types::{
+    Header, HttpProtocol, LocationErrorKind, ProtocolError, RedirectMethod, Request, Response,
+    Target, TimeoutKind,
+};
+use std::collections::BTreeMap;
+use std::future::{poll_fn, Future};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::{Duration, Instant};
 use tokio::time::timeout;
 use url::Url;
 
@@ -18,6 +26,8 @@ pub const CHUNKED_ENCODING: &str = "chunked";
 // Common header names as constants to avoid allocations
 pub const CONTENT_TYPE_HEADER: &str = "content-type";
 pub const COOKIE_HEADER: &str = "cookie";
+pub const AUTHORIZATION_HEADER: &str = "authorization";
+pub const CONNECTION_HEADER: &str = "connection";
 pub const APPLICATION_JSON: &str = "application/json";
 
 pub fn ensure_user_agent(headers: &mut Vec<Header>) {
@@ -33,7 +43,29 @@ pub fn ensure_user_agent(headers: &mut Vec<Header>) {
 }
 
 pub fn parse_target(target: &str) -> Result<Target, ProtocolError> {
-    let url = Url::parse(target)
+    parse_target_with_scheme(target).map(|(target, _)| target)
+}
+
+/// [`parse_target`], also returning the [`SchemeSpec`] when `target` uses a
+/// scheme from [`SchemeRegistry::global`].
+pub fn parse_target_with_scheme(
+    target: &str,
+) -> Result<(Target, Option<SchemeSpec>), ProtocolError> {
+    let (rewritten, spec) = match SchemeRegistry::global().rewrite(target) {
+        Some((rewritten, spec)) => (rewritten, Some(spec)),
+        None => (target.to_string(), None),
+    };
+    // Checked before parsing, which drops an explicit default port.
+    let explicit_port =
+        split_authority(target).map_or(false, |(_, authority, _)| has_explicit_port(authority));
+    let (rewritten, unix_socket) = match spec.as_ref().map(|spec| spec.transport) {
+        Some(SchemeTransport::Unix) => {
+            let (socket, rewritten) = unix_socket_target(target, &rewritten)?;
+            (rewritten, Some(socket))
+        }
+        _ => (rewritten, None),
+    };
+    let mut url = Url::parse(&rewritten)
         .map_err(|e| ProtocolError::InvalidTarget(format!("{} ({})", target, e)))?;
 
     if url.host_str().is_none() {
@@ -50,7 +82,60 @@ pub fn parse_target(target: &str) -> Result<Target, ProtocolError> {
         )));
     }
 
-    Ok(Target::new(url))
+    if let Some(port) = spec.as_ref().and_then(|spec| spec.default_port) {
+        if !explicit_port {
+            let _ = url.set_port(Some(port));
+        }
+    }
+    let mut parsed = Target::new(url);
+    parsed.unix_socket = unix_socket;
+    if let Some(spec) = &spec {
+        parsed.protocols.extend(spec.protocols.iter().cloned());
+    }
+    Ok((parsed, spec))
+}
+
+/// `target` split into its scheme with `://`, its authority and the rest.
+fn split_authority(target: &str) -> Option<(&str, &str, &str)> {
+    let start = target.find("://")? + 3;
+    let end = target[start..]
+        .find(|c| matches!(c, '/' | '?' | '#'))
+        .map_or(target.len(), |end| start + end);
+    Some((&target[..start], &target[start..end], &target[end..]))
+}
+
+/// Whether `authority` names a port, as `host:443` and `[::1]:443` do.
+fn has_explicit_port(authority: &str) -> bool {
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let after_host = match host.rfind(']') {
+        Some(end) => &host[end + 1..],
+        None => host,
+    };
+    after_host
+        .split_once(':')
+        .map_or(false, |(_, port)| !port.is_empty())
+}
+
+/// The socket path an `http+unix` style `target` names as its host, and
+/// `rewritten` with `localhost` in its place.
+fn unix_socket_target(target: &str, rewritten: &str) -> Result<(PathBuf, String), ProtocolError> {
+    let invalid = || {
+        ProtocolError::InvalidTarget(format!(
+            "Target '{}' does not name a socket path as its host",
+            target
+        ))
+    };
+    let (scheme, authority, rest) = split_authority(rewritten).ok_or_else(invalid)?;
+    let path = urlencoding::decode(authority).map_err(|_| invalid())?;
+    if path.is_empty() {
+        return Err(invalid());
+    }
+    Ok((
+        PathBuf::from(path.into_owned()),
+        format!("{}localhost{}", scheme, rest),
+    ))
 }
 
 pub fn convert_escape_sequences(input: &str) -> String {
@@ -106,6 +191,150 @@ pub fn parse_trailers(trailers: Vec<String>) -> Result<Vec<Header>, ProtocolErro
     parse_header_list(trailers, "trailer")
 }
 
+/// Headers that only make sense for a single HTTP/1.1 hop (RFC 9113 Section 8.2.2).
+const CONNECTION_SPECIFIC_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+const REQUEST_PSEUDO_HEADERS: &[&str] = &[":method", ":scheme", ":authority", ":path", ":protocol"];
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// Rejects headers and trailers that are malformed or forbidden for `protocol`.
+///
+/// The error message names the offending field so it can be fixed (or kept on
+/// purpose by turning validation off).
+pub fn validate_request_headers(
+    headers: &[Header],
+    trailers: &[Header],
+    protocol: &HttpProtocol,
+) -> Result<(), ProtocolError> {
+    let multiplexed = !matches!(protocol, HttpProtocol::Http1);
+    let mut seen_pseudo: Vec<&str> = Vec::new();
+    let mut seen_regular = false;
+
+    for (kind, fields) in [("Header", headers), ("Trailer", trailers)] {
+        for header in fields {
+            let name = header.name.as_str();
+            if name.is_empty() {
+                return Err(ProtocolError::MalformedHeaders(format!(
+                    "Empty {} name",
+                    kind.to_lowercase()
+                )));
+            }
+
+            if let Some(value) = &header.value {
+                if value.contains(['\r', '\n', '\0']) {
+                    return Err(ProtocolError::MalformedHeaders(format!(
+                        "{} '{}' has a value containing CR, LF or NUL",
+                        kind, name
+                    )));
+                }
+            }
+
+            if name.starts_with(':') {
+                if !multiplexed {
+                    return Err(ProtocolError::MalformedHeaders(format!(
+                        "Pseudo-header '{}' is not valid in {}",
+                        name, protocol
+                    )));
+                }
+                if kind == "Trailer" {
+                    return Err(ProtocolError::MalformedHeaders(format!(
+                        "Pseudo-header '{}' is not allowed in trailers",
+                        name
+                    )));
+                }
+                if !REQUEST_PSEUDO_HEADERS.contains(&name) {
+                    return Err(ProtocolError::MalformedHeaders(format!(
+                        "Unknown request pseudo-header '{}'",
+                        name
+                    )));
+                }
+                if seen_regular {
+                    return Err(ProtocolError::MalformedHeaders(format!(
+                        "Pseudo-header '{}' appears after regular headers",
+                        name
+                    )));
+                }
+                if seen_pseudo.contains(&name) {
+                    return Err(ProtocolError::MalformedHeaders(format!(
+                        "Duplicate pseudo-header '{}'",
+                        name
+                    )));
+                }
+                seen_pseudo.push(name);
+                continue;
+            }
+            if kind == "Header" {
+                seen_regular = true;
+            }
+
+            if let Some(c) = name.chars().find(|c| !is_token_char(*c)) {
+                let problem = if c.is_whitespace() {
+                    "whitespace".to_string()
+                } else {
+                    format!("invalid character {:?}", c)
+                };
+                return Err(ProtocolError::MalformedHeaders(format!(
+                    "{} name '{}' contains {}",
+                    kind, name, problem
+                )));
+            }
+
+            if multiplexed {
+                if CONNECTION_SPECIFIC_HEADERS
+                    .iter()
+                    .any(|forbidden| name.eq_ignore_ascii_case(forbidden))
+                {
+                    return Err(ProtocolError::MalformedHeaders(format!(
+                        "Connection-specific {} '{}' is not allowed in {}",
+                        kind.to_lowercase(),
+                        name,
+                        protocol
+                    )));
+                }
+                if name.eq_ignore_ascii_case("te")
+                    && !header
+                        .value
+                        .as_deref()
+                        .map_or(false, |v| v.trim().eq_ignore_ascii_case("trailers"))
+                {
+                    return Err(ProtocolError::MalformedHeaders(format!(
+                        "{} 'TE' may only be \"trailers\" in {}, got '{}'",
+                        kind,
+                        protocol,
+                        header.value.as_deref().unwrap_or_default()
+                    )));
+                }
+            }
+        }
+    }
+
+    for singleton in [HOST_HEADER, CONTENT_LENGTH_HEADER] {
+        let mut values = headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case(singleton))
+            .map(|h| h.value.as_deref().unwrap_or_default().trim());
+        if let Some(first) = values.next() {
+            if let Some(other) = values.find(|v| *v != first) {
+                return Err(ProtocolError::MalformedHeaders(format!(
+                    "Conflicting '{}' headers: '{}' and '{}'",
+                    singleton, first, other
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub fn header_value<'a>(headers: &'a [Header], name: &str) -> Option<&'a str> {
     headers
         .iter()
@@ -117,43 +346,126 @@ pub fn is_redirect_status(status: u16) -> bool {
     (300..400).contains(&status)
 }
 
-pub fn resolve_redirect_url(base_url: &Url, location: &str) -> Result<Url, url::ParseError> {
-    if location.starts_with("http://") || location.starts_with("https://") {
-        Url::parse(location)
+/// Resolves a `Location` value leniently, see [`resolve_location`].
+pub fn resolve_redirect_url(base_url: &Url, location: &str) -> Result<Url, LocationErrorKind> {
+    resolve_location(base_url, location, false)
+}
+
+/// Resolves `location` against `base_url`: absolute, protocol-relative
+/// (`//host/path`), path, query and fragment-only references alike. A result
+/// without a fragment keeps the base's (RFC 9110 section 10.2.2).
+///
+/// Leniently, like a browser, surrounding whitespace and control characters
+/// are trimmed, tabs and newlines dropped, backslashes read as slashes and
+/// spaces percent-encoded. Strictly, anything that is not a valid URI
+/// reference is refused.
+pub fn resolve_location(
+    base_url: &Url,
+    location: &str,
+    strict: bool,
+) -> Result<Url, LocationErrorKind> {
+    let trimmed = location.trim_matches(|c: char| c <= ' ');
+    let reference = if strict {
+        if trimmed.len() != location.len() {
+            return Err(LocationErrorKind::Whitespace);
+        }
+        if let Some(c) = location.chars().find(|c| !is_uri_char(*c)) {
+            return Err(LocationErrorKind::InvalidCharacter(c));
+        }
+        location.to_string()
     } else {
-        base_url.join(location)
+        trimmed
+            .chars()
+            .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+            .collect()
+    };
+    if reference.is_empty() {
+        return Err(LocationErrorKind::Empty);
+    }
+
+    let mut url = base_url
+        .join(&reference)
+        .map_err(|err| LocationErrorKind::Unparsable(err.to_string()))?;
+    let scheme = url.scheme();
+    if scheme != "http" && scheme != "https" && SchemeRegistry::global().get(scheme).is_none() {
+        return Err(LocationErrorKind::UnsupportedScheme(scheme.to_string()));
     }
+    if url.fragment().is_none() {
+        url.set_fragment(base_url.fragment());
+    }
+    Ok(url)
+}
+
+/// Characters RFC 3986 allows in a URI reference, percent signs included.
+fn is_uri_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~:/?#[]@!$&'()*+,;=%".contains(c)
 }
 
 pub fn apply_redirect(request: &mut Request, response: &Response) -> Result<bool, ProtocolError> {
-    if !request.follow_redirects || !is_redirect_status(response.status) {
+    if !request.follow_redirects {
         return Ok(false);
     }
+    if !is_redirect_status(response.status) {
+        return apply_refresh(request, response);
+    }
 
     let location = match header_value(&response.headers, "location") {
         Some(value) => value,
         None => return Ok(false),
     };
 
-    let redirect_url = match resolve_redirect_url(&request.target.url, location) {
+    let strict = request.redirect_policy.strict_location;
+    let redirect_url = match resolve_location(&request.target.url, location, strict) {
         Ok(url) => url,
+        Err(kind) if strict => {
+            return Err(ProtocolError::InvalidLocation {
+                location: location.to_string(),
+                kind,
+            })
+        }
         Err(_) => return Ok(false),
     };
 
-    request.target = parse_target(redirect_url.as_str())?;
+    retarget(request, parse_target(redirect_url.as_str())?);
+    request
+        .redirect_policy
+        .method_for(response.status)
+        .apply(request);
 
-    if response.status == 303
-        || ((response.status == 301 || response.status == 302)
-            && matches!(request.method.as_str(), "GET" | "HEAD"))
-    {
-        request.method = "GET".to_string();
-        request.body = None;
-        request.json = None;
+    Ok(true)
+}
+
+/// Follows a `Refresh` header or meta refresh like a navigation: the next
+/// request is a bodyless GET. Refreshes that reload the same URL are ignored.
+fn apply_refresh(request: &mut Request, response: &Response) -> Result<bool, ProtocolError> {
+    if !request.follow_refresh {
+        return Ok(false);
     }
+    let location = match response.refresh_redirect().and_then(|refresh| refresh.url) {
+        Some(url) => url,
+        None => return Ok(false),
+    };
+    let redirect_url = match resolve_redirect_url(&request.target.url, &location) {
+        Ok(url) if url != request.target.url => url,
+        _ => return Ok(false),
+    };
 
+    retarget(request, parse_target(redirect_url.as_str())?);
+    RedirectMethod::Get.apply(request);
+    request.method = "GET".to_string();
     Ok(true)
 }
 
+/// Points `request` at the next hop, dropping its credentials when the
+/// redirect policy does not trust the new origin with them.
+fn retarget(request: &mut Request, target: Target) {
+    let policy = &request.redirect_policy;
+    if !policy.forwards_credentials(&request.target, &target) {
+        policy.clone().strip_credentials(request);
+    }
+    request.target = target;
+}
+
 pub async fn timeout_result<F, T>(duration: Option<Duration>, future: F) -> Result<T, ProtocolError>
 where
     F: Future<Output = Result<T, ProtocolError>>,
@@ -161,9 +473,209 @@ where
     if let Some(dur) = duration {
         match timeout(dur, future).await {
             Ok(result) => result,
-            Err(_) => Err(ProtocolError::Timeout),
+            Err(_) => Err(ProtocolError::Timeout(None)),
         }
     } else {
         future.await
     }
 }
+
+/// [`timeout_result`] that reports which phase ran out.
+pub async fn phase_timeout<F, T>(
+    duration: Option<Duration>,
+    kind: TimeoutKind,
+    future: F,
+) -> Result<T, ProtocolError>
+where
+    F: Future<Output = Result<T, ProtocolError>>,
+{
+    match duration {
+        Some(dur) => timeout(dur, future)
+            .await
+            .unwrap_or(Err(ProtocolError::Timeout(Some(kind)))),
+        None => future.await,
+    }
+}
+
+/// Time left until `deadline`; zero once it has passed so the next read times out.
+pub fn time_remaining(deadline: Option<Instant>) -> Option<Duration> {
+    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+}
+
+/// Drives `futures` on the current task with at most `limit` in flight and
+/// returns their outputs in input order. Works for the `!Send` futures the
+/// protocol clients return, so no runtime spawning is involved.
+pub async fn join_bounded<I, F>(futures: I, limit: usize) -> Vec<F::Output>
+where
+    I: IntoIterator<Item = F>,
+    F: Future,
+{
+    let mut join = BoundedJoin::new(futures.into_iter(), limit);
+    let mut results = Vec::new();
+    while let Some(output) = join.next().await {
+        results.push(output);
+    }
+    results
+}
+
+/// Runs the futures `pending` yields with at most `limit` in flight and
+/// hands their outputs back in order. Outputs that finished ahead of an
+/// earlier future count against the limit, so a slow head cannot pile up
+/// an unbounded backlog. Futures only make progress while
+/// [`next`](Self::next) is awaited; nothing is spawned.
+pub struct BoundedJoin<I>
+where
+    I: Iterator,
+    I::Item: Future,
+{
+    pending: I,
+    started: usize,
+    in_flight: Vec<(usize, Pin<Box<I::Item>>)>,
+    /// Finished out of order, waiting for earlier futures.
+    done: BTreeMap<usize, <I::Item as Future>::Output>,
+    next_index: usize,
+    limit: usize,
+}
+
+impl<I> BoundedJoin<I>
+where
+    I: Iterator,
+    I::Item: Future,
+{
+    pub fn new(pending: I, limit: usize) -> Self {
+        Self {
+            pending,
+            started: 0,
+            in_flight: Vec::new(),
+            done: BTreeMap::new(),
+            next_index: 0,
+            limit: limit.max(1),
+        }
+    }
+
+    /// The iterator futures are still taken from.
+    pub fn pending_mut(&mut self) -> &mut I {
+        &mut self.pending
+    }
+
+    /// Outputs not handed out yet, running futures included.
+    pub fn remaining(&self) -> usize
+    where
+        I: ExactSizeIterator,
+    {
+        self.pending.len() + self.in_flight.len() + self.done.len()
+    }
+
+    /// The output of the next future in order, once it is in; `None` after
+    /// the last one.
+    pub async fn next(&mut self) -> Option<<I::Item as Future>::Output> {
+        loop {
+            if let Some(output) = self.done.remove(&self.next_index) {
+                self.next_index += 1;
+                return Some(output);
+            }
+            while self.in_flight.len() + self.done.len() < self.limit {
+                match self.pending.next() {
+                    Some(future) => {
+                        self.in_flight.push((self.started, Box::pin(future)));
+                        self.started += 1;
+                    }
+                    None => break,
+                }
+            }
+            if self.in_flight.is_empty() {
+                return None;
+            }
+
+            let in_flight = &mut self.in_flight;
+            let (position, output) = poll_fn(|cx| {
+                for (position, (_, future)) in in_flight.iter_mut().enumerate() {
+                    if let Poll::Ready(output) = future.as_mut().poll(cx) {
+                        return Poll::Ready((position, output));
+                    }
+                }
+                Poll::Pending
+            })
+            .await;
+            let (index, _) = self.in_flight.swap_remove(position);
+            self.done.insert(index, output);
+        }
+    }
+}
+
+/// Simple base64 encoding (standard alphabet, padded)
+pub fn base64_encode(input: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::new();
+
+    for chunk in input.chunks(3) {
+        let mut buf = [0u8; 3];
+        for (i, &b) in chunk.iter().enumerate() {
+            buf[i] = b;
+        }
+
+        let b0 = buf[0] as usize;
+        let b1 = buf[1] as usize;
+        let b2 = buf[2] as usize;
+
+        result.push(CHARS[b0 >> 2] as char);
+        result.push(CHARS[((b0 & 0x03) << 4) | (b1 >> 4)] as char);
+
+        if chunk.len() > 1 {
+            result.push(CHARS[((b1 & 0x0f) << 2) | (b2 >> 6)] as char);
+        } else {
+            result.push('=');
+        }
+
+        if chunk.len() > 2 {
+            result.push(CHARS[b2 & 0x3f] as char);
+        } else {
+            result.push('=');
+        }
+    }
+
+    result
+}
+
+/// Decodes standard base64; padding is optional. Returns `None` on invalid input.
+pub fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+            b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let input = input.trim_end_matches('=').as_bytes();
+    if input.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(input.len() * 3 / 4);
+    for chunk in input.chunks(4) {
+        let mut acc = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            acc |= value(c)? << (18 - 6 * i);
+        }
+        result.push((acc >> 16) as u8);
+        if chunk.len() > 2 {
+            result.push((acc >> 8) as u8);
+        }
+        if chunk.len() > 3 {
+            result.push(acc as u8);
+        }
+    }
+    Some(result)
+}
+
+/// URL-safe base64 without padding (RFC 4648 §5)
+pub fn base64url_encode(input: &[u8]) -> String {
+    base64_encode(input)
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
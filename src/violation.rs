@@ -0,0 +1,132 @@
+//! Request frame sequences that break the HTTP/2 and HTTP/3 stream state
+//! machine (RFC 9113 Section 8.1, RFC 9114 Section 4.1).
+//!
+//! Each [`StreamViolation`] turns a prepared request into a list of
+//! [`WriteStep`]s that a [`StreamWriter`] sends as is, so a server's handling
+//! of misplaced header blocks can be probed without building frames by hand:
+//!
+//! ```ignore
+//! let prepared = Request::new("https://example.com/", "POST")?.body("x").prepare_request()?;
+//! let mut writer = connection.stream_writer().await?;
+//! write_violation(&mut writer, &prepared, StreamViolation::TrailersBeforeData).await?;
+//! let response = writer.read_response().await;
+//! ```
+
+use crate::connection::StreamWriter;
+use crate::types::{Header, PreparedRequest, ProtocolError};
+use bytes::Bytes;
+
+/// Sent as the trailer block when the request has no trailers of its own.
+const PLACEHOLDER_TRAILER: (&str, &str) = ("x-trailer", "1");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StreamViolation {
+    /// The request header block twice, the second time in the middle of
+    /// the stream where only trailers are allowed, then the body.
+    DuplicateHeaders,
+    /// Trailers without END_STREAM, followed by the body.
+    TrailersBeforeData,
+    /// A complete request, then one more HEADERS frame on the stream it
+    /// already ended. HTTP/3 refuses to write after FIN, so this only
+    /// reaches the wire over HTTP/2.
+    HeadersAfterEndStream,
+}
+
+/// One write on a request stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteStep {
+    Headers {
+        headers: Vec<Header>,
+        end_stream: bool,
+    },
+    Data {
+        chunk: Bytes,
+        end_stream: bool,
+    },
+}
+
+impl StreamViolation {
+    pub const ALL: [StreamViolation; 3] = [
+        StreamViolation::DuplicateHeaders,
+        StreamViolation::TrailersBeforeData,
+        StreamViolation::HeadersAfterEndStream,
+    ];
+
+    /// The writes that deliver `request` with this violation.
+    pub fn plan(&self, request: &PreparedRequest) -> Vec<WriteStep> {
+        let header_block = request.header_block();
+        let body = request.body.clone().unwrap_or_default();
+        let has_trailers = !request.trailers.is_empty();
+        let trailers = if has_trailers {
+            request.trailers.clone()
+        } else {
+            let (name, value) = PLACEHOLDER_TRAILER;
+            vec![Header::new(name.to_string(), value.to_string())]
+        };
+        let headers = |headers: Vec<Header>, end_stream| WriteStep::Headers {
+            headers,
+            end_stream,
+        };
+        let data = |chunk: Bytes, end_stream| WriteStep::Data { chunk, end_stream };
+
+        match self {
+            StreamViolation::DuplicateHeaders => {
+                let mut steps = vec![
+                    headers(header_block.clone(), false),
+                    headers(header_block, false),
+                    data(body, !has_trailers),
+                ];
+                if has_trailers {
+                    steps.push(headers(trailers, true));
+                }
+                steps
+            }
+            StreamViolation::TrailersBeforeData => vec![
+                headers(header_block, false),
+                headers(trailers, false),
+                data(body, true),
+            ],
+            StreamViolation::HeadersAfterEndStream => {
+                let late = if has_trailers {
+                    trailers
+                } else {
+                    header_block.clone()
+                };
+                vec![
+                    headers(header_block, false),
+                    data(body, true),
+                    headers(late, true),
+                ]
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for StreamViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            StreamViolation::DuplicateHeaders => "duplicate HEADERS",
+            StreamViolation::TrailersBeforeData => "trailers before DATA",
+            StreamViolation::HeadersAfterEndStream => "HEADERS after END_STREAM",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Sends `request` on `writer` with `violation` and flushes.
+pub async fn write_violation<W: StreamWriter + ?Sized>(
+    writer: &mut W,
+    request: &PreparedRequest,
+    violation: StreamViolation,
+) -> Result<(), ProtocolError> {
+    for step in violation.plan(request) {
+        match step {
+            WriteStep::Headers {
+                headers,
+                end_stream,
+            } => writer.headers(&headers, end_stream).await?,
+            WriteStep::Data { chunk, end_stream } => writer.data(chunk, end_stream).await?,
+        }
+    }
+    writer.flush().await
+}
@@ -0,0 +1,187 @@
+//! Copies of the raw bytes a connection writes to and reads from its
+//! transport, taken below the framing layer: the preface, padding, frames
+//! the recorder skips and bytes that never parse are all included. TLS is
+//! below this layer, so the bytes are the plaintext HTTP stream.
+//!
+//! `H1Connection`, `H2Connection` and `H3Connection` take a [`WireSink`]
+//! with `set_wire_mirror`. A sink that fails is dropped and mirroring
+//! stops; the connection carries on.
+
+use bytes::Bytes;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc as std_mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireDirection {
+    Sent,
+    Received,
+}
+
+/// Bytes that crossed the transport in one write or read.
+#[derive(Debug, Clone)]
+pub struct WireChunk {
+    pub direction: WireDirection,
+    /// QUIC stream the bytes were on; `None` for HTTP/1.1 and HTTP/2,
+    /// whose transport is a single byte stream.
+    pub stream_id: Option<u64>,
+    pub at: Instant,
+    pub data: Bytes,
+}
+
+/// Receives a copy of every byte a connection mirrors. Called on the
+/// connection's task, so it should hand the bytes off rather than block.
+pub trait WireSink: Send {
+    fn record(&mut self, direction: WireDirection, data: &[u8]) -> io::Result<()>;
+
+    /// Bytes on one QUIC stream of an `H3Connection`. By default they are
+    /// recorded like any other, interleaved with the other streams.
+    fn record_stream(
+        &mut self,
+        _stream_id: u64,
+        direction: WireDirection,
+        data: &[u8],
+    ) -> io::Result<()> {
+        self.record(direction, data)
+    }
+}
+
+impl WireSink for mpsc::UnboundedSender<WireChunk> {
+    fn record(&mut self, direction: WireDirection, data: &[u8]) -> io::Result<()> {
+        send_chunk(self, None, direction, data)
+    }
+
+    fn record_stream(
+        &mut self,
+        stream_id: u64,
+        direction: WireDirection,
+        data: &[u8],
+    ) -> io::Result<()> {
+        send_chunk(self, Some(stream_id), direction, data)
+    }
+}
+
+fn send_chunk(
+    sender: &mpsc::UnboundedSender<WireChunk>,
+    stream_id: Option<u64>,
+    direction: WireDirection,
+    data: &[u8],
+) -> io::Result<()> {
+    let chunk = WireChunk {
+        direction,
+        stream_id,
+        at: Instant::now(),
+        data: Bytes::copy_from_slice(data),
+    };
+    sender
+        .send(chunk)
+        .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "wire mirror receiver dropped"))
+}
+
+/// Writes each direction verbatim to its own writer, e.g. two files that
+/// together are the exact transcript of the connection.
+///
+/// The writes happen on a thread of their own, so a slow disk never holds
+/// up the connection; the writers are flushed whenever that thread catches
+/// up. Once a write fails the sink fails too, and [`finish`](Self::finish)
+/// returns the error.
+pub struct WireWriter<W> {
+    chunks: std_mpsc::Sender<(WireDirection, Bytes)>,
+    worker: JoinHandle<io::Result<(W, W)>>,
+}
+
+impl<W: Write + Send + 'static> WireWriter<W> {
+    pub fn new(sent: W, received: W) -> Self {
+        let (chunks, queued) = std_mpsc::channel();
+        let worker = thread::spawn(move || write_chunks(queued, sent, received));
+        Self { chunks, worker }
+    }
+
+    /// Waits for everything recorded so far to be written and flushed, and
+    /// hands the writers back.
+    pub fn finish(self) -> io::Result<(W, W)> {
+        drop(self.chunks);
+        self.worker
+            .join()
+            .map_err(|_| io::Error::other("wire writer thread panicked"))?
+    }
+}
+
+impl WireWriter<BufWriter<File>> {
+    /// Creates or truncates both files.
+    pub fn create(sent: impl AsRef<Path>, received: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(
+            BufWriter::new(File::create(sent)?),
+            BufWriter::new(File::create(received)?),
+        ))
+    }
+}
+
+impl<W: Write + Send + 'static> WireSink for WireWriter<W> {
+    fn record(&mut self, direction: WireDirection, data: &[u8]) -> io::Result<()> {
+        self.chunks
+            .send((direction, Bytes::copy_from_slice(data)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "wire writer stopped"))
+    }
+}
+
+/// Body of the [`WireWriter`] thread: writes chunks as they come and
+/// flushes once the queue is drained.
+fn write_chunks<W: Write>(
+    queued: std_mpsc::Receiver<(WireDirection, Bytes)>,
+    mut sent: W,
+    mut received: W,
+) -> io::Result<(W, W)> {
+    while let Ok(first) = queued.recv() {
+        let mut next = Some(first);
+        while let Some((direction, data)) = next {
+            match direction {
+                WireDirection::Sent => sent.write_all(&data)?,
+                WireDirection::Received => received.write_all(&data)?,
+            }
+            next = queued.try_recv().ok();
+        }
+        sent.flush()?;
+        received.flush()?;
+    }
+    Ok((sent, received))
+}
+
+/// The sink a connection mirrors to, if any.
+#[derive(Default)]
+pub(crate) struct WireMirror(Option<Box<dyn WireSink>>);
+
+impl WireMirror {
+    pub(crate) fn set(&mut self, sink: Option<Box<dyn WireSink>>) {
+        self.0 = sink;
+    }
+
+    /// Mirrors into a channel from now on.
+    pub(crate) fn channel(&mut self) -> mpsc::UnboundedReceiver<WireChunk> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.set(Some(Box::new(sender)));
+        receiver
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Hands `data` to the sink, dropping the sink if it fails.
+    pub(crate) fn record(&mut self, stream_id: Option<u64>, direction: WireDirection, data: &[u8]) {
+        let failed = match (self.0.as_mut(), stream_id) {
+            (Some(sink), Some(stream_id)) => {
+                sink.record_stream(stream_id, direction, data).is_err()
+            }
+            (Some(sink), None) => sink.record(direction, data).is_err(),
+            (None, _) => false,
+        };
+        if failed {
+            self.0 = None;
+        }
+    }
+}
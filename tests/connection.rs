@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_reach_every_live_subscriber() {
+        let mut events = ConnectionEvents::new();
+        let mut first = events.subscribe();
+        let second = events.subscribe();
+        drop(second);
+
+        events.emit(ConnectionEvent::StreamOpened { stream_id: 1 });
+        events.emit(ConnectionEvent::Closed);
+
+        assert_eq!(
+            first.try_recv().unwrap(),
+            ConnectionEvent::StreamOpened { stream_id: 1 }
+        );
+        assert_eq!(first.try_recv().unwrap(), ConnectionEvent::Closed);
+        assert!(first.try_recv().is_err());
+        assert!(events.has_subscribers());
+
+        drop(first);
+        events.emit(ConnectionEvent::SettingsAcknowledged);
+        assert!(!events.has_subscribers());
+    }
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_reads_settings_frame() {
+        let mut reply = vec![0, 0, 6, SETTINGS_FRAME_TYPE, 0, 0, 0, 0, 0];
+        reply.extend_from_slice(&[0, 3, 0, 0, 0, 100]);
+
+        assert_eq!(classify_h2c_reply(&reply[..12]), None);
+        assert_eq!(
+            classify_h2c_reply(&reply),
+            Some(H2cProbe::Settings(vec![(3, 100)]))
+        );
+    }
+
+    #[test]
+    fn classify_detects_http1_answer() {
+        let head = b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n";
+
+        assert_eq!(classify_h2c_reply(b"HTTP/"), None);
+        assert_eq!(classify_h2c_reply(&head[..20]), None);
+        assert_eq!(
+            classify_h2c_reply(head),
+            Some(H2cProbe::Http1 {
+                status: 400,
+                raw: head.to_vec()
+            })
+        );
+    }
+
+    #[test]
+    fn classify_rejects_other_frames_and_garbage() {
+        let goaway = [0, 0, 8, 0x7, 0, 0, 0, 0, 0];
+        assert!(matches!(
+            classify_h2c_reply(&goaway),
+            Some(H2cProbe::Unrecognized(_))
+        ));
+        assert!(matches!(
+            classify_h2c_reply(b"\x15\x03\x01\x00\x02\x02\x46\x00\x00"),
+            Some(H2cProbe::Unrecognized(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_replies_are_closed_or_unrecognized() {
+        assert_eq!(finish_h2c_reply(b""), H2cProbe::Closed);
+        assert!(matches!(
+            finish_h2c_reply(b"HTTP/1.0 200 OK\r\nServer"),
+            H2cProbe::Http1 { status: 200, .. }
+        ));
+        assert!(matches!(
+            finish_h2c_reply(&[0, 0]),
+            H2cProbe::Unrecognized(_)
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(protocol: HttpProtocol, status: u16, headers: &[(&str, &str)]) -> ProtocolResult {
+        ProtocolResult {
+            protocol,
+            outcome: Ok(ResponseSummary {
+                status,
+                headers: headers
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                body_hash: "e3b0c442".to_string(),
+                body_len: 0,
+            }),
+            elapsed: Duration::from_millis(status as u64),
+        }
+    }
+
+    #[test]
+    fn reports_header_value_and_order_differences() {
+        let report = ProtocolDiff {
+            results: vec![
+                result(
+                    HttpProtocol::Http1,
+                    200,
+                    &[
+                        ("server", "edge"),
+                        ("connection", "keep-alive"),
+                        ("x-cache", "HIT"),
+                    ],
+                ),
+                result(
+                    HttpProtocol::Http2,
+                    200,
+                    &[("x-cache", "MISS"), ("server", "edge")],
+                ),
+                ProtocolResult {
+                    protocol: HttpProtocol::Http3,
+                    outcome: Err("timed out".to_string()),
+                    elapsed: Duration::from_secs(5),
+                },
+            ],
+        };
+
+        assert!(report.statuses_match());
+        assert!(report.bodies_match());
+        assert!(!report.header_order_matches());
+        assert_eq!(report.failures(), vec![&HttpProtocol::Http3]);
+        assert_eq!(
+            report.header_differences(),
+            vec![HeaderDifference {
+                name: "x-cache".to_string(),
+                values: vec![
+                    (HttpProtocol::Http1, vec!["HIT".to_string()]),
+                    (HttpProtocol::Http2, vec!["MISS".to_string()]),
+                ],
+            }]
+        );
+        assert_eq!(
+            report.fastest().map(|r| &r.protocol),
+            Some(&HttpProtocol::Http1)
+        );
+    }
+}
@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_ignores_reflected_path() {
+        let probe = "3f9a1c0d2b7e";
+        let wildcard = Wildcard::from_response(
+            probe,
+            &Response::new(
+                200,
+                &[],
+                "<title>Oops</title> The page /3f9a1c0d2b7e was not found",
+            ),
+        );
+        let echoed = Response::new(
+            200,
+            &[],
+            "<title>Oops</title> The page /backup.zip was not found",
+        );
+        assert!(wildcard.matches("backup.zip", &echoed, 0.95));
+
+        let real = Response::new(200, &[], "<title>Admin</title> Sign in to continue");
+        assert!(!wildcard.matches("admin", &real, 0.95));
+        let other_status = Response::new(
+            403,
+            &[],
+            "<title>Oops</title> The page /admin was not found",
+        );
+        assert!(!wildcard.matches("admin", &other_status, 0.95));
+    }
+
+    #[test]
+    fn base_gains_trailing_slash() -> Result<(), ProtocolError> {
+        let discovery = ContentDiscovery::new(crate::H1::new(), "https://example.com/app")?;
+        let request = discovery.build_request("/login")?;
+        assert_eq!(request.target.url.as_str(), "https://example.com/app/login");
+        Ok(())
+    }
+}
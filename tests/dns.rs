@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinned_entries_answer_without_a_lookup() -> io::Result<()> {
+        let cache = DnsCache::default();
+        let ip: IpAddr = "192.0.2.7".parse().unwrap();
+        cache.insert("Example.COM.", vec![ip], None);
+
+        let addrs = tokio_test::block_on(cache.resolve("example.com", 8443))?;
+        assert_eq!(addrs, vec![SocketAddr::new(ip, 8443)]);
+
+        let literal = tokio_test::block_on(cache.resolve("[::1]", 80))?;
+        assert_eq!(literal, vec!["[::1]:80".parse().unwrap()]);
+        assert_eq!(cache.len(), 1);
+
+        cache.remove("EXAMPLE.com");
+        assert!(cache.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn failures_are_cached_for_the_negative_ttl() {
+        let cache = DnsCache::new(Duration::from_secs(60), Duration::from_secs(60));
+        let failure = Err(io::Error::new(io::ErrorKind::NotFound, "no such host"));
+        cache.remember("missing.test", &failure, None);
+
+        let cached = cache.cached("missing.test").expect("negative entry");
+        assert_eq!(cached.unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        cache.set_ttl(Duration::from_secs(60), Duration::ZERO);
+        cache.remember("other.test", &failure, None);
+        assert!(cache.cached("other.test").is_none());
+    }
+
+    #[test]
+    fn expired_entries_are_dropped() {
+        let cache = DnsCache::default();
+        cache.insert(
+            "stale.test",
+            vec![IpAddr::from([192, 0, 2, 1])],
+            Some(Duration::ZERO),
+        );
+        assert!(cache.cached("stale.test").is_none());
+        assert!(cache.is_empty());
+    }
+
+    struct FixedResolver(Mutex<usize>);
+
+    #[async_trait(?Send)]
+    impl Resolver for FixedResolver {
+        async fn lookup(&self, host: &str, _port: u16) -> io::Result<Vec<IpAddr>> {
+            *self.0.lock().unwrap() += 1;
+            assert_eq!(host, "custom.test");
+            Ok(vec![IpAddr::from([192, 0, 2, 9])])
+        }
+    }
+
+    #[test]
+    fn lookups_go_to_the_installed_resolver() -> io::Result<()> {
+        let cache = DnsCache::default();
+        let resolver = Arc::new(FixedResolver(Mutex::new(0)));
+        cache.set_resolver(resolver.clone());
+
+        let addrs = tokio_test::block_on(cache.resolve("Custom.Test.", 80))?;
+        assert_eq!(addrs, vec!["192.0.2.9:80".parse().unwrap()]);
+        tokio_test::block_on(cache.resolve("custom.test", 443))?;
+        assert_eq!(*resolver.0.lock().unwrap(), 1);
+        Ok(())
+    }
+
+    struct TtlResolver;
+
+    #[async_trait(?Send)]
+    impl Resolver for TtlResolver {
+        async fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<IpAddr>> {
+            Ok(self.lookup_with_ttl(host, port).await?.0)
+        }
+
+        async fn lookup_with_ttl(
+            &self,
+            host: &str,
+            _port: u16,
+        ) -> io::Result<(Vec<IpAddr>, Option<Duration>)> {
+            let ttl = if host == "short.test" { 0 } else { 3600 };
+            Ok((
+                vec![IpAddr::from([192, 0, 2, 5])],
+                Some(Duration::from_secs(ttl)),
+            ))
+        }
+    }
+
+    #[test]
+    fn answers_are_kept_for_their_record_ttl() -> io::Result<()> {
+        let cache = DnsCache::new(Duration::from_secs(60), Duration::from_secs(5));
+        cache.set_resolver(Arc::new(TtlResolver));
+
+        tokio_test::block_on(cache.resolve("short.test", 80))?;
+        assert!(cache.cached("short.test").is_none());
+        tokio_test::block_on(cache.resolve("long.test", 80))?;
+        let expires = cache.lock().entries["long.test"].expires.unwrap();
+        assert!(expires > Instant::now() + Duration::from_secs(3000));
+        Ok(())
+    }
+
+    #[test]
+    fn fresh_lookups_keep_pinned_entries() -> io::Result<()> {
+        let cache = DnsCache::default();
+        cache.set_resolver(Arc::new(TtlResolver));
+        let pinned = IpAddr::from([192, 0, 2, 1]);
+        cache.insert("pinned.test", vec![pinned], None);
+
+        let fresh = tokio_test::block_on(cache.resolve_for("pinned.test", 80, true))?;
+        assert_eq!(fresh, vec!["192.0.2.5:80".parse().unwrap()]);
+        let cached = tokio_test::block_on(cache.resolve_for("pinned.test", 80, false))?;
+        assert_eq!(cached, vec![SocketAddr::new(pinned, 80)]);
+        Ok(())
+    }
+
+    #[test]
+    fn entries_past_the_cap_evict_the_soonest_to_expire() {
+        let cache = DnsCache::default();
+        cache.set_max_entries(2);
+        let ip = vec![IpAddr::from([192, 0, 2, 1])];
+        cache.insert("pinned.test", ip.clone(), None);
+        cache.remember("a.test", &Ok(ip.clone()), Some(Duration::from_secs(10)));
+        cache.remember("b.test", &Ok(ip.clone()), Some(Duration::from_secs(20)));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.cached("pinned.test").is_some());
+        assert!(cache.cached("b.test").is_some());
+
+        // Pinned entries alone fill the cache, so the answer is not kept.
+        cache.set_max_entries(1);
+        cache.remember("c.test", &Ok(ip), None);
+        assert!(cache.cached("c.test").is_none());
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_are_encoded_in_wire_format() -> io::Result<()> {
+        let query = encode_query("Example.com.", RecordType::Aaaa)?;
+        let mut expected = vec![0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        expected.extend_from_slice(b"\x07Example\x03com\x00");
+        expected.extend_from_slice(&[0, 28, 0, 1]);
+        assert_eq!(query, expected);
+
+        assert!(encode_query("bad..name", RecordType::A).is_err());
+        assert!(encode_query(&"a".repeat(64), RecordType::A).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn answers_follow_compressed_names_past_cnames() -> io::Result<()> {
+        let mut response = encode_query("www.example.com", RecordType::A)?;
+        // QR and RA set, two answers.
+        response[2..4].copy_from_slice(&[0x81, 0x80]);
+        response[6..8].copy_from_slice(&[0, 2]);
+        // www.example.com CNAME example.com, pointing into the question.
+        response.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xc0, 16]);
+        // example.com A 192.0.2.1
+        response.extend_from_slice(&[0xc0, 16, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 192, 0, 2, 1]);
+
+        let ips = parse_answer(&response, RecordType::A)?;
+        assert_eq!(ips, vec!["192.0.2.1".parse::<IpAddr>().unwrap()]);
+        assert!(parse_answer(&response, RecordType::Aaaa)?.is_empty());
+
+        response.truncate(response.len() - 2);
+        assert_eq!(
+            parse_answer(&response, RecordType::A).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn answers_report_the_smallest_address_ttl() -> io::Result<()> {
+        let mut response = encode_query("example.com", RecordType::A)?;
+        response[2..4].copy_from_slice(&[0x81, 0x80]);
+        response[6..8].copy_from_slice(&[0, 3]);
+        // A CNAME with a TTL of 5, then A records with TTLs of 300 and 120.
+        response.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 5, 0, 2, 0xc0, 12]);
+        response.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 1, 44, 0, 4, 192, 0, 2, 1]);
+        response.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 120, 0, 4, 192, 0, 2, 2]);
+
+        let (ips, ttl) = parse_answer_with_ttl(&response, RecordType::A)?;
+        assert_eq!(ips.len(), 2);
+        assert_eq!(ttl, Some(Duration::from_secs(120)));
+        assert_eq!(parse_answer_with_ttl(&response, RecordType::Aaaa)?.1, None);
+        Ok(())
+    }
+
+    #[test]
+    fn nxdomain_is_not_found() -> io::Result<()> {
+        let mut response = encode_query("missing.test", RecordType::A)?;
+        response[2..4].copy_from_slice(&[0x81, 0x83]);
+        assert_eq!(
+            parse_answer(&response, RecordType::A).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn the_resolver_host_answers_from_the_bootstrap() -> Result<(), ProtocolError> {
+        let bootstrap: Vec<IpAddr> = vec!["1.1.1.1".parse().unwrap()];
+        let doh = DohResolver::new("https://DNS.example/dns-query", bootstrap.clone())?;
+        let ips = tokio_test::block_on(doh.lookup("dns.example", 443))?;
+        assert_eq!(ips, bootstrap);
+
+        assert!(DohResolver::new("https://dns.example/dns-query", Vec::new()).is_err());
+        assert!(DohResolver::new("https://1.1.1.1/dns-query", Vec::new()).is_ok());
+        assert!(DohResolver::new("http://1.1.1.1/dns-query", Vec::new()).is_err());
+        Ok(())
+    }
+}
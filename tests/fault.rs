@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_stays_within_bounds_and_is_seeded() {
+        let config = FaultConfig::new()
+            .latency(Duration::from_millis(10))
+            .jitter(Duration::from_millis(5))
+            .seed(42);
+        let mut first = FaultyStream::new((), config.clone());
+        let mut second = FaultyStream::new((), config);
+        for _ in 0..100 {
+            let delay = first.next_delay();
+            assert!(delay >= Duration::from_millis(10));
+            assert!(delay <= Duration::from_millis(15));
+            assert_eq!(delay, second.next_delay());
+        }
+    }
+
+    #[test]
+    fn nearest_limit_wins() {
+        assert_eq!(remaining_before(10, &[None, None]), None);
+        assert_eq!(remaining_before(10, &[Some(100), Some(30)]), Some(20));
+        assert_eq!(remaining_before(50, &[Some(30)]), Some(0));
+    }
+}
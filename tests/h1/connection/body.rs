@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::HttpConnection;
+    use crate::h1::protocol::H1;
+    use crate::stream::TransportStream;
+    use crate::types::{ClientTimeouts, Request};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn connection_reading(raw: &'static [u8]) -> H1Connection {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut peer, _) = listener.accept().await.unwrap();
+        peer.write_all(raw).await.unwrap();
+        H1Connection::from_stream(TransportStream::Tcp(client), ClientTimeouts::disabled())
+    }
+
+    #[tokio::test]
+    async fn chunks_arrive_as_they_are_framed() {
+        let mut connection = connection_reading(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+              3\r\nabc\r\n2\r\nde\r\n0\r\nX-Sum: 5\r\n\r\n\
+              HTTP/1.1 204 No Content\r\n\r\n",
+        )
+        .await;
+
+        let mut response = connection.read_response_streaming(true).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body.next_chunk().await.unwrap().unwrap(), "abc");
+        assert_eq!(response.body.next_chunk().await.unwrap().unwrap(), "de");
+        assert!(response.body.next_chunk().await.is_none());
+        let trailers = response.body.trailers().unwrap();
+        assert_eq!(trailers[0].name, "X-Sum");
+        drop(response);
+
+        // The next response starts where the body ended.
+        let next = connection.read_response(true).await.unwrap();
+        assert_eq!(next.status, 204);
+        assert_eq!(connection.stats().payload_bytes_received, 5);
+    }
+
+    #[tokio::test]
+    async fn buffering_a_length_delimited_body() {
+        let mut connection =
+            connection_reading(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello").await;
+
+        let response = connection.read_response_streaming(true).await.unwrap();
+        let response = response.buffer().await.unwrap();
+        assert_eq!(response.body.as_ref(), b"hello");
+        assert!(response.trailers.is_none());
+    }
+
+    #[tokio::test]
+    async fn a_body_cut_short_is_an_error() {
+        let mut connection =
+            connection_reading(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello").await;
+
+        let mut response = connection.read_response_streaming(true).await.unwrap();
+        assert_eq!(response.body.next_chunk().await.unwrap().unwrap(), "hello");
+        assert!(matches!(
+            response.body.next_chunk().await,
+            Some(Err(ProtocolError::Io(_)))
+        ));
+        assert!(response.body.next_chunk().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn a_chunk_without_its_crlf_is_an_error() {
+        let mut connection = connection_reading(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabcXY0\r\n\r\n",
+        )
+        .await;
+
+        let mut response = connection.read_response_streaming(true).await.unwrap();
+        assert_eq!(response.body.next_chunk().await.unwrap().unwrap(), "abc");
+        assert!(matches!(
+            response.body.next_chunk().await,
+            Some(Err(ProtocolError::InvalidResponse(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn the_protocol_client_streams_on_a_connection_of_its_own() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut peer, _) = listener.accept().await.unwrap();
+            let mut head = [0u8; 1024];
+            let _ = peer.read(&mut head).await.unwrap();
+            peer.write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n")
+                .await
+                .unwrap();
+            // The head is handed out before the rest of the body is written.
+            let _ = peer.read(&mut head).await;
+        });
+
+        let request = Request::new(&url, "GET").unwrap();
+        let mut response = H1::new().send_request_streaming(request).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body.next_chunk().await.unwrap().unwrap(), "abc");
+        drop(response);
+        server.await.unwrap();
+    }
+}
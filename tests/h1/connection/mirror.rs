@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::HttpConnection;
+    use crate::stream::TransportStream;
+    use crate::types::ClientTimeouts;
+    use crate::wire::WireDirection;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn open_pair() -> (H1Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (peer, _) = listener.accept().await.unwrap();
+        let connection =
+            H1Connection::from_stream(TransportStream::Tcp(client), ClientTimeouts::disabled());
+        (connection, peer)
+    }
+
+    /// Everything mirrored in `direction` so far.
+    fn drain(chunks: &mut mpsc::UnboundedReceiver<WireChunk>, direction: WireDirection) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while let Ok(chunk) = chunks.try_recv() {
+            if chunk.direction == direction {
+                bytes.extend_from_slice(&chunk.data);
+            }
+        }
+        bytes
+    }
+
+    #[tokio::test]
+    async fn requests_and_responses_are_mirrored_verbatim() {
+        let (mut connection, mut peer) = open_pair().await;
+        let mut chunks = connection.mirror_wire();
+
+        let request = b"GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        connection.write_raw(request).await.unwrap();
+        let mut on_wire = vec![0u8; request.len()];
+        peer.read_exact(&mut on_wire).await.unwrap();
+        assert_eq!(drain(&mut chunks, WireDirection::Sent), on_wire);
+
+        // Bytes past the response are mirrored when read, not when used.
+        let reply = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nokHTTP/1.1 204";
+        peer.write_all(reply).await.unwrap();
+        let response = connection.read_response(true).await.unwrap();
+        assert_eq!(response.body.as_ref(), b"ok");
+        assert_eq!(drain(&mut chunks, WireDirection::Received), reply);
+    }
+
+    #[tokio::test]
+    async fn streamed_bodies_are_mirrored_as_they_are_read() {
+        let (mut connection, mut peer) = open_pair().await;
+        let mut chunks = connection.mirror_wire();
+        let head = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+        peer.write_all(head).await.unwrap();
+
+        let mut response = connection.read_response_streaming(true).await.unwrap();
+        let mut mirrored = drain(&mut chunks, WireDirection::Received);
+        assert_eq!(mirrored, head);
+        peer.write_all(b"3\r\nabc\r\n0\r\n\r\n").await.unwrap();
+        while let Some(chunk) = response.body.next_chunk().await {
+            chunk.unwrap();
+        }
+        drop(response);
+        mirrored.extend(drain(&mut chunks, WireDirection::Received));
+        assert_eq!(
+            mirrored,
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n"
+        );
+    }
+}
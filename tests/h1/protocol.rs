@@ -0,0 +1,371 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::HeaderLimitKind;
+
+    fn read(raw: &'static [u8], allow_truncated: bool) -> Result<Response, ProtocolError> {
+        let request =
+            Request::new("http://example.com/", "GET")?.allow_truncated_body(allow_truncated);
+        tokio_test::block_on(H1::new().read_response_from(
+            raw,
+            true,
+            &ClientTimeouts::disabled(),
+            Some(&request),
+        ))
+    }
+
+    #[test]
+    fn short_content_length_is_an_error_unless_allowed() -> Result<(), ProtocolError> {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort";
+        assert!(matches!(read(raw, false), Err(ProtocolError::Io(_))));
+
+        let response = read(raw, true)?;
+        assert!(response.truncated);
+        assert_eq!(response.body.as_ref(), b"short");
+        assert_eq!(
+            response.truncation,
+            Some(Truncation::ContentLength {
+                expected: 10,
+                received: 5
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_chunked_body_reports_where_it_stopped() -> Result<(), ProtocolError> {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\na\r\nde";
+        let response = read(raw, true)?;
+        assert_eq!(response.body.as_ref(), b"abcde");
+        assert_eq!(
+            response.truncation,
+            Some(Truncation::Chunk {
+                size: 10,
+                received: 2
+            })
+        );
+
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n";
+        let response = read(raw, true)?;
+        assert_eq!(response.truncation, Some(Truncation::LastChunk));
+        assert!(read(raw, false).is_err());
+
+        let complete = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        assert!(!read(complete, true)?.truncated);
+        Ok(())
+    }
+
+    #[test]
+    fn header_limits_report_the_offending_size() {
+        let client = H1::with_config(ClientConfig::new().header_limits(HeaderLimits {
+            max_line: Some(32),
+            max_total: Some(64),
+        }));
+        let read = |raw: &'static [u8]| {
+            tokio_test::block_on(client.read_response_from(
+                raw,
+                true,
+                &ClientTimeouts::disabled(),
+                None,
+            ))
+        };
+
+        let long_line = b"HTTP/1.1 200 OK\r\nX-Long: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\n\r\n";
+        assert!(matches!(
+            read(long_line),
+            Err(ProtocolError::HeaderLimitExceeded(HeaderLimitKind::Line {
+                limit: 32,
+                size: 46
+            }))
+        ));
+
+        let many_lines = b"HTTP/1.1 200 OK\r\nA: 1\r\nA: 1\r\nA: 1\r\nA: 1\r\nA: 1\r\nA: 1\r\nA: 1\r\nA: 1\r\nA: 1\r\n\r\n";
+        assert!(matches!(
+            read(many_lines),
+            Err(ProtocolError::HeaderLimitExceeded(HeaderLimitKind::Total {
+                limit: 64,
+                size: 65
+            }))
+        ));
+    }
+
+    #[test]
+    fn chunked_trailers_stop_at_the_next_response() -> Result<(), ProtocolError> {
+        let raw: &[u8] = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\nX-Checksum: 1\r\n\r\nHTTP/1.1 204 No Content\r\nX-Next: 1\r\n\r\n";
+        let mut reader = tokio::io::BufReader::new(raw);
+        let client = H1::new();
+        let timeouts = ClientTimeouts::disabled();
+
+        let first = tokio_test::block_on(client.read_response_buffered(
+            &mut reader,
+            true,
+            &timeouts,
+            None,
+        ))?;
+        assert_eq!(first.body.as_ref(), b"hi");
+        assert_eq!(first.trailers.as_ref().map(Vec::len), Some(1));
+        assert!(reader.buffer().starts_with(b"HTTP/1.1 204"));
+
+        let second = tokio_test::block_on(client.read_response_buffered(
+            &mut reader,
+            true,
+            &timeouts,
+            None,
+        ))?;
+        assert_eq!(second.status, 204);
+        assert_eq!(second.headers[0].name, "X-Next");
+        assert!(reader.buffer().is_empty());
+        Ok(())
+    }
+
+    /// Reads a response the server sends as `parts`, pausing `pause` before each.
+    async fn read_paced(
+        parts: &'static [(u64, &'static [u8])],
+        timeouts: ClientTimeouts,
+    ) -> Result<Response, ProtocolError> {
+        use std::time::Duration;
+        use tokio::io::AsyncWriteExt;
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            for (pause, part) in parts {
+                tokio::time::sleep(Duration::from_millis(*pause)).await;
+                if server.write_all(part).await.is_err() {
+                    return;
+                }
+            }
+        });
+        let mut reader = tokio::io::BufReader::new(client);
+        H1::new()
+            .read_response_buffered(&mut reader, true, &timeouts, None)
+            .await
+    }
+
+    #[tokio::test]
+    async fn timeouts_name_the_phase_that_ran_out() {
+        use std::time::Duration;
+
+        let timeouts = ClientTimeouts {
+            first_byte: Some(Duration::from_millis(20)),
+            header_read: Some(Duration::from_millis(20)),
+            ..ClientTimeouts::disabled()
+        };
+        let slow_start: &[(u64, &[u8])] = &[(200, b"HTTP/1.1 204 No Content\r\n\r\n")];
+        assert!(matches!(
+            read_paced(slow_start, timeouts.clone()).await,
+            Err(ProtocolError::Timeout(Some(TimeoutKind::FirstByte)))
+        ));
+
+        let slow_headers: &[(u64, &[u8])] = &[(0, b"HTTP/1.1 204"), (200, b" No Content\r\n\r\n")];
+        assert!(matches!(
+            read_paced(slow_headers, timeouts.clone()).await,
+            Err(ProtocolError::Timeout(Some(TimeoutKind::Headers)))
+        ));
+
+        // The body is not held to the first-byte or header timeouts.
+        let slow_body: &[(u64, &[u8])] = &[
+            (0, b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n"),
+            (60, b"ok"),
+        ];
+        let response = read_paced(slow_body, timeouts.clone()).await.unwrap();
+        assert_eq!(response.body.as_ref(), b"ok");
+
+        let idle = ClientTimeouts {
+            body_idle: Some(Duration::from_millis(20)),
+            ..timeouts
+        };
+        let err = read_paced(slow_body, idle).await.unwrap_err();
+        assert!(err.is_timeout());
+        assert!(matches!(
+            err,
+            ProtocolError::Timeout(Some(TimeoutKind::BodyIdle))
+        ));
+    }
+
+    #[tokio::test]
+    async fn sequential_requests_reuse_a_kept_alive_connection() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            // A single connection answers both requests; the second closes it.
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let responses: [&[u8]; 2] = [
+                b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+                b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n",
+            ];
+            for response in responses {
+                let mut request = [0u8; 4096];
+                if socket.read(&mut request).await.is_err() {
+                    return;
+                }
+                socket.write_all(response).await.unwrap();
+            }
+        });
+
+        let client = H1::timeouts(ClientTimeouts::disabled());
+        let first = client
+            .send_request(Request::new(&url, "GET").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(first.body.as_ref(), b"ok");
+        assert_eq!(client.pool().len(), 1);
+
+        let second = client
+            .send_request(Request::new(&url, "GET").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(second.status, 204);
+        assert!(client.pool().is_empty());
+
+        let stats = client.pool().stats();
+        assert_eq!((stats.opened(), stats.reused()), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn a_reset_after_the_response_started_is_not_replayed() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            socket.read(&mut request).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+            // The POST is acted on and half answered, then the connection
+            // is reset.
+            socket.read(&mut request).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 10\r\n\r\nab")
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            socket.set_linger(Some(Duration::ZERO)).unwrap();
+            drop(socket);
+            // A replay would be answered here.
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket.read(&mut request).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let client = H1::timeouts(ClientTimeouts::disabled());
+        client
+            .send_request(Request::new(&url, "GET").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(client.pool().len(), 1);
+
+        let post = Request::new(&url, "POST").unwrap().body("order");
+        assert!(client.send_request(post).await.is_err());
+        assert_eq!(client.pool().stats().opened(), 1);
+    }
+
+    #[tokio::test]
+    async fn interim_responses_through_a_forward_proxy_reach_the_handler() {
+        use std::sync::{Arc, Mutex};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut head = [0u8; 4096];
+            let read = socket.read(&mut head).await.unwrap();
+            assert!(head[..read].starts_with(b"GET http://example.com"));
+            socket
+                .write_all(
+                    b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>\r\n\r\n\
+                      HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok",
+                )
+                .await
+                .unwrap();
+        });
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&seen);
+        let request = Request::new("http://example.com/a", "GET")
+            .unwrap()
+            .proxy(&proxy)
+            .unwrap()
+            .on_informational(move |interim| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((interim.status, interim.from_proxy));
+            });
+        let response = H1::new().send_request(request).await.unwrap();
+        assert_eq!(response.body.as_ref(), b"ok");
+        assert_eq!(*seen.lock().unwrap(), vec![(103, false)]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn http_unix_targets_go_over_the_socket() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("riphttplib-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                let mut byte = [0u8; 1];
+                socket.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                .await
+                .unwrap();
+            String::from_utf8(head).unwrap()
+        });
+
+        let url = format!(
+            "http+unix://{}/v1/info",
+            urlencoding::encode(path.to_str().unwrap())
+        );
+        let response = H1::new()
+            .send_request(Request::new(&url, "GET").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.body.as_ref(), b"ok");
+        let head = server.await.unwrap();
+        assert!(head.starts_with("GET /v1/info HTTP/1.1\r\n"), "{}", head);
+        assert!(head.contains("host: localhost\r\n"), "{}", head);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn the_guard_vets_the_proxy_as_well() {
+        use crate::types::SsrfGuard;
+
+        let request = Request::new("http://93.184.216.34/", "GET")
+            .unwrap()
+            .proxy("http://127.0.0.1:3128")
+            .unwrap()
+            .ssrf_guard(SsrfGuard::default());
+
+        match H1::new().send_request(request).await {
+            Err(ProtocolError::AddressBlocked { addr, .. }) => {
+                assert_eq!(addr, "127.0.0.1".parse::<std::net::IpAddr>().unwrap())
+            }
+            other => panic!(
+                "expected the proxy to be blocked, got {:?}",
+                other.map(|r| r.status)
+            ),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_cycles_and_self_dependencies() {
+        assert!(!PriorityTree::new().chain(&[1, 3, 5], 15).has_cycle());
+        assert!(PriorityTree::new().cycle(&[1, 3, 5], 15).has_cycle());
+        assert!(PriorityTree::new().self_dependency(7, 0).has_cycle());
+        // A later PRIORITY frame replaces the dependency that closed the loop.
+        assert!(!PriorityTree::new()
+            .depend(1, 3, 0)
+            .depend(3, 1, 0)
+            .depend(1, 0, 0)
+            .has_cycle());
+    }
+
+    #[test]
+    fn frames_encode_dependency_and_weight() -> Result<(), ProtocolError> {
+        let frames = PriorityTree::new().depend_exclusive(3, 1, 200).frames()?;
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].stream_id, 3);
+        assert_eq!(&frames[0].payload[..], &[0x80, 0, 0, 1, 200]);
+        Ok(())
+    }
+
+    #[test]
+    fn trace_reports_first_data_order_and_starvation() {
+        let trace = ScheduleTrace {
+            data_frames: vec![(3, 100), (3, 100), (1, 0), (5, 50), (1, 10)],
+            completion_order: vec![3, 5, 1],
+        };
+        assert_eq!(trace.first_data_order(), vec![3, 1, 5]);
+        assert_eq!(trace.bytes_before_first_data(1), 250);
+        assert_eq!(trace.bytes_before_first_data(3), 0);
+        assert_eq!(trace.bytes_before_first_data(9), 260);
+    }
+}
@@ -0,0 +1,620 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Connection past its handshake, with the peer's socket.
+    async fn open_pair(timeouts: ClientTimeouts) -> (H2Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (peer, _) = listener.accept().await.unwrap();
+        let mut connection = H2Connection::new(TransportStream::Tcp(client), timeouts);
+        connection.state = ConnectionState::Open;
+        connection.initial_settings_received = true;
+        (connection, peer)
+    }
+
+    /// Opens one GET stream on `connection` and returns its ID.
+    async fn open_get(connection: &mut H2Connection) -> u32 {
+        let headers: Vec<Header> = [
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":authority", "example.com"),
+            (":path", "/"),
+        ]
+        .iter()
+        .map(|(name, value)| Header::new(name.to_string(), value.to_string()))
+        .collect();
+        let stream_id = connection.reserve_streams(1).unwrap()[0];
+        connection
+            .prepare_headers(stream_id, &headers, true)
+            .unwrap();
+        connection.send_prepared().await.unwrap();
+        stream_id
+    }
+
+    /// Type, stream ID and payload of the next frame the peer receives.
+    async fn read_frame(peer: &mut TcpStream) -> (u8, u32, Vec<u8>) {
+        let mut header = [0u8; 9];
+        peer.read_exact(&mut header).await.unwrap();
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        let mut payload = vec![0u8; len];
+        peer.read_exact(&mut payload).await.unwrap();
+        let stream_id =
+            u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7FFF_FFFF;
+        (header[3], stream_id, payload)
+    }
+
+    /// HEADERS carrying `:status 200` with END_HEADERS, plus END_STREAM when
+    /// `end_stream`.
+    fn status_200(stream_id: u32, end_stream: bool) -> Vec<u8> {
+        let flags = if end_stream { 0x5 } else { 0x4 };
+        let mut frame = vec![0, 0, 1, 1, flags];
+        frame.extend_from_slice(&stream_id.to_be_bytes());
+        frame.push(0x88);
+        frame
+    }
+
+    #[tokio::test]
+    async fn waiting_for_a_slot_fails_fast_on_a_zero_limit() {
+        let (mut connection, _peer) = open_pair(ClientTimeouts::disabled()).await;
+        connection
+            .remote_settings
+            .insert(SETTINGS_MAX_CONCURRENT_STREAMS, 0);
+
+        assert!(matches!(
+            connection.create_stream().await,
+            Err(ProtocolError::StreamLimitReached { limit: 0 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn waiting_for_a_slot_is_bounded_by_the_read_timeout() {
+        let mut timeouts = ClientTimeouts::disabled();
+        timeouts.read = Some(Duration::from_millis(50));
+        let (mut connection, _peer) = open_pair(timeouts).await;
+        connection
+            .remote_settings
+            .insert(SETTINGS_MAX_CONCURRENT_STREAMS, 1);
+        let stream_id = connection.create_stream().await.unwrap();
+        connection
+            .update_stream_state(stream_id, StreamState::Open)
+            .unwrap();
+
+        assert!(connection.create_stream().await.unwrap_err().is_timeout());
+    }
+
+    #[tokio::test]
+    async fn idle_streams_leave_slots_free() {
+        let (mut connection, _peer) = open_pair(ClientTimeouts::disabled()).await;
+        connection
+            .remote_settings
+            .insert(SETTINGS_MAX_CONCURRENT_STREAMS, 1);
+        connection.set_stream_limit_policy(StreamLimitPolicy::Error);
+
+        let first = connection.create_stream().await.unwrap();
+        connection.create_stream().await.unwrap();
+        assert_eq!(connection.available_stream_slots(), 1);
+
+        connection
+            .update_stream_state(first, StreamState::Open)
+            .unwrap();
+        assert_eq!(connection.available_stream_slots(), 0);
+        assert!(matches!(
+            connection.create_stream().await,
+            Err(ProtocolError::StreamLimitReached { limit: 1 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn frames_split_across_reads_are_reassembled() {
+        let mut timeouts = ClientTimeouts::disabled();
+        timeouts.read = Some(Duration::from_millis(20));
+        let (mut connection, mut peer) = open_pair(timeouts).await;
+        let ping = FrameH2::ping_ack([7; 8]).serialize().unwrap();
+
+        // A read that times out between the header and the payload keeps
+        // what it buffered.
+        peer.write_all(&ping[..5]).await.unwrap();
+        assert!(connection
+            .read_next_frame(None)
+            .await
+            .unwrap_err()
+            .is_timeout());
+        peer.write_all(&ping[5..12]).await.unwrap();
+        assert!(connection
+            .read_next_frame(None)
+            .await
+            .unwrap_err()
+            .is_timeout());
+        peer.write_all(&ping[12..]).await.unwrap();
+        let frame = connection.read_next_frame(None).await.unwrap();
+        assert_eq!(&frame.payload[..], &[7; 8]);
+
+        // Two frames in one read come out one at a time.
+        let mut both = FrameH2::ping_ack([1; 8]).serialize().unwrap().to_vec();
+        both.extend_from_slice(&FrameH2::ping_ack([2; 8]).serialize().unwrap());
+        peer.write_all(&both).await.unwrap();
+        let first = connection.read_next_frame(None).await.unwrap();
+        let second = connection.read_next_frame(None).await.unwrap();
+        assert_eq!(&first.payload[..], &[1; 8]);
+        assert_eq!(&second.payload[..], &[2; 8]);
+        assert!(connection.read_buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn frames_for_an_abandoned_stream_are_discarded() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let abandoned = connection.create_stream().await.unwrap();
+        let kept = connection.create_stream().await.unwrap();
+        for stream_id in [abandoned, kept] {
+            connection
+                .update_stream_state(stream_id, StreamState::HalfClosedLocal)
+                .unwrap();
+        }
+        connection.abandon_stream(abandoned).await.unwrap();
+        let window = connection.recv_connection_window;
+
+        let mut frames = status_200(abandoned, false);
+        frames.extend_from_slice(
+            &FrameH2::data(abandoned, Bytes::from_static(b"late"), true)
+                .serialize()
+                .unwrap(),
+        );
+        frames.extend_from_slice(&status_200(kept, true));
+        peer.write_all(&frames).await.unwrap();
+
+        let response = connection.read_response(kept).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert!(connection
+            .streams
+            .get(&abandoned)
+            .map_or(true, |stream| stream.inbound_events.is_empty()));
+        // The discarded DATA was credited straight back to the connection.
+        assert_eq!(connection.recv_connection_window, window);
+    }
+
+    #[tokio::test]
+    async fn a_stream_window_overflow_resets_that_stream_only() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let overflowed = connection.create_stream().await.unwrap();
+        let kept = connection.create_stream().await.unwrap();
+        for stream_id in [overflowed, kept] {
+            connection
+                .update_stream_state(stream_id, StreamState::HalfClosedLocal)
+                .unwrap();
+        }
+        connection.streams.get_mut(&overflowed).unwrap().send_window = MAX_WINDOW_SIZE as i32;
+
+        let update = FrameH2::window_update(overflowed, 1).unwrap();
+        peer.write_all(&update.serialize().unwrap()).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        connection.flush().await.unwrap();
+
+        // RST_STREAM with FLOW_CONTROL_ERROR.
+        let mut rst = [0u8; 13];
+        peer.read_exact(&mut rst).await.unwrap();
+        let mut expected = vec![0, 0, 4, 3, 0];
+        expected.extend_from_slice(&overflowed.to_be_bytes());
+        expected.extend_from_slice(&[0, 0, 0, 3]);
+        assert_eq!(&rst[..], &expected[..]);
+        assert!(matches!(
+            connection.recv_stream_event(overflowed).await,
+            Err(ProtocolError::H2StreamError(
+                H2StreamErrorKind::FlowControlViolation
+            ))
+        ));
+
+        peer.write_all(&status_200(kept, true)).await.unwrap();
+        assert_eq!(connection.read_response(kept).await.unwrap().status, 200);
+    }
+
+    #[tokio::test]
+    async fn a_connection_window_overflow_fails_the_connection() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        connection.send_connection_window = MAX_WINDOW_SIZE as i32;
+
+        let update = FrameH2::window_update(0, 1).unwrap();
+        peer.write_all(&update.serialize().unwrap()).await.unwrap();
+        assert!(matches!(
+            connection.read_next_frame(None).await,
+            Err(ProtocolError::H2FlowControlError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn the_header_list_size_goes_out_with_the_initial_settings() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut preface = [0u8; 24];
+            socket.read_exact(&mut preface).await.unwrap();
+            let mut header = [0u8; 9];
+            socket.read_exact(&mut header).await.unwrap();
+            let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+            let mut payload = vec![0u8; len];
+            socket.read_exact(&mut payload).await.unwrap();
+            socket
+                .write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            (header[3], payload)
+        });
+
+        let _connection = H2Connection::connect_with_header_list_size(
+            &url,
+            &ClientTimeouts::disabled(),
+            Some(H2Mode::PriorKnowledge),
+            None,
+            &SocketOptions::default(),
+            Some(65_536),
+        )
+        .await
+        .unwrap();
+        let (frame_type, payload) = server.await.unwrap();
+        assert_eq!(frame_type, 0x4);
+        let settings: Vec<(u16, u32)> = payload
+            .chunks(6)
+            .map(|s| {
+                (
+                    u16::from_be_bytes([s[0], s[1]]),
+                    u32::from_be_bytes([s[2], s[3], s[4], s[5]]),
+                )
+            })
+            .collect();
+        assert!(settings.contains(&(SETTINGS_MAX_HEADER_LIST_SIZE, 65_536)));
+    }
+
+    #[tokio::test]
+    async fn a_header_list_over_the_advertised_size_resets_its_stream() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        connection
+            .settings
+            .insert(SETTINGS_MAX_HEADER_LIST_SIZE, 100);
+        let oversized = connection.create_stream().await.unwrap();
+        let kept = connection.create_stream().await.unwrap();
+        for stream_id in [oversized, kept] {
+            connection
+                .update_stream_state(stream_id, StreamState::HalfClosedLocal)
+                .unwrap();
+        }
+
+        // `:status 200` and a literal `x-big` of 100 bytes, END_HEADERS.
+        let mut block = vec![0x88, 0x00, 5];
+        block.extend_from_slice(b"x-big");
+        block.push(100);
+        block.extend_from_slice(&[b'x'; 100]);
+        let mut frame = vec![0, 0, block.len() as u8, 1, 0x4];
+        frame.extend_from_slice(&oversized.to_be_bytes());
+        frame.extend_from_slice(&block);
+        peer.write_all(&frame).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+
+        assert!(matches!(
+            connection.recv_stream_event(oversized).await,
+            Err(ProtocolError::HeaderLimitExceeded(HeaderLimitKind::Total {
+                limit: 100,
+                size: 179
+            }))
+        ));
+        peer.write_all(&status_200(kept, true)).await.unwrap();
+        assert_eq!(connection.read_response(kept).await.unwrap().status, 200);
+    }
+
+    #[tokio::test]
+    async fn stream_lifecycle_is_reported_as_events() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let mut events = connection.subscribe_events();
+        let stream_id = open_get(&mut connection).await;
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ConnectionEvent::StreamOpened { stream_id }
+        );
+
+        peer.write_all(&status_200(stream_id, true)).await.unwrap();
+        assert_eq!(
+            connection.read_response(stream_id).await.unwrap().status,
+            200
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ConnectionEvent::StreamClosed { stream_id }
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn raw_writes_go_out_at_once_behind_queued_frames() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        connection.set_auto_flush_bytes(Some(usize::MAX));
+        connection.send_frame(&FrameH2::ping([1; 8])).await.unwrap();
+        assert_eq!(connection.queued_writes(), 1);
+
+        let raw = FrameH2::ping_ack([2; 8]).serialize().unwrap();
+        connection.write_raw(raw.clone()).await.unwrap();
+        assert_eq!(connection.queued_writes(), 0);
+        let mut written = [0u8; 34];
+        peer.read_exact(&mut written).await.unwrap();
+        assert_eq!(&written[9..17], &[1; 8]);
+        assert_eq!(&written[17..], raw.as_ref());
+    }
+
+    #[tokio::test]
+    async fn a_ping_ack_is_reported_with_its_round_trip() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let mut events = connection.subscribe_events();
+        connection.ping([9; 8]).await.unwrap();
+        let mut ping = [0u8; 17];
+        peer.read_exact(&mut ping).await.unwrap();
+        assert_eq!(&ping[9..], &[9; 8]);
+
+        let ack = FrameH2::ping_ack([9; 8]).serialize().unwrap();
+        peer.write_all(&ack).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ConnectionEvent::PingRtt { .. })
+        ));
+        assert!(connection.pings_in_flight.is_empty());
+
+        // An unsolicited ACK has no round trip to report.
+        peer.write_all(&ack).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn unanswered_pings_are_forgotten_oldest_first() {
+        let (mut connection, _peer) = open_pair(ClientTimeouts::disabled()).await;
+        for n in 0..MAX_PINGS_IN_FLIGHT as u64 + 8 {
+            connection.ping(n.to_be_bytes()).await.unwrap();
+        }
+
+        assert_eq!(connection.pings_in_flight.len(), MAX_PINGS_IN_FLIGHT);
+        assert_eq!(connection.pings_in_flight[0].0, 8u64.to_be_bytes());
+        assert_eq!(
+            connection.pings_in_flight.back().unwrap().0,
+            (MAX_PINGS_IN_FLIGHT as u64 + 7).to_be_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn goaway_is_reported_with_its_debug_data() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let mut events = connection.subscribe_events();
+
+        let goaway = FrameH2::goaway(1, 0, Some(b"bye"));
+        peer.write_all(&goaway.serialize().unwrap()).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ConnectionEvent::GoAwayReceived {
+                last_stream_id: 1,
+                error_code: Some(0),
+                debug_data: Bytes::from_static(b"bye"),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn a_graceful_shutdown_waits_for_in_flight_streams() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let stream_id = open_get(&mut connection).await;
+        peer.write_all(&status_200(stream_id, true)).await.unwrap();
+
+        connection
+            .shutdown_graceful(0, None, Some(Duration::from_secs(5)))
+            .await
+            .unwrap();
+        assert!(matches!(connection.state, ConnectionState::Closed));
+        assert_eq!(
+            connection.read_response(stream_id).await.unwrap().status,
+            200
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_graceful_shutdown_gives_up_on_a_silent_peer() {
+        let (mut connection, _peer) = open_pair(ClientTimeouts::disabled()).await;
+        open_get(&mut connection).await;
+
+        let started = tokio::time::Instant::now();
+        connection
+            .shutdown_graceful(0, None, Some(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert!(matches!(connection.state, ConnectionState::Closed));
+
+        // Without a timeout of its own the drain is still bounded.
+        let (mut connection, _peer) = open_pair(ClientTimeouts::disabled()).await;
+        open_get(&mut connection).await;
+        let started = tokio::time::Instant::now();
+        connection.shutdown_graceful(0, None, None).await.unwrap();
+        assert!(started.elapsed() >= DEFAULT_DRAIN_TIMEOUT);
+        assert!(matches!(connection.state, ConnectionState::Closed));
+    }
+
+    #[tokio::test]
+    async fn an_http1_reply_to_the_preface_is_surfaced() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        connection.initial_settings_received = false;
+        let head = b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n";
+        peer.write_all(head).await.unwrap();
+        peer.write_all(b"trailing").await.unwrap();
+
+        match connection.read_next_frame(None).await {
+            Err(ProtocolError::H2UnexpectedHttp1Response(raw)) => assert_eq!(raw, head),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+        assert!(matches!(connection.state, ConnectionState::Closed));
+    }
+
+    #[tokio::test]
+    async fn settings_acks_are_matched_to_what_was_sent() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        connection
+            .send_frame(&FrameH2::settings(&[]))
+            .await
+            .unwrap();
+        assert_eq!(connection.pending_settings_acks(), 1);
+
+        let ack = FrameH2::settings_ack().serialize().unwrap();
+        peer.write_all(&ack).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        assert_eq!(connection.pending_settings_acks(), 0);
+    }
+
+    #[tokio::test]
+    async fn an_overdue_settings_ack_fails_the_connection() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        connection.set_settings_ack_timeout(Some(Duration::from_millis(50)));
+        connection
+            .send_frame(&FrameH2::settings(&[]))
+            .await
+            .unwrap();
+        let mut settings = [0u8; 9];
+        peer.read_exact(&mut settings).await.unwrap();
+
+        assert!(matches!(
+            connection.read_next_frame(None).await,
+            Err(ProtocolError::H2ConnectionError(
+                H2ConnectionErrorKind::SettingsTimeout
+            ))
+        ));
+        // GOAWAY with SETTINGS_TIMEOUT.
+        let mut goaway = [0u8; 17];
+        peer.read_exact(&mut goaway).await.unwrap();
+        assert_eq!(goaway[3], 0x7);
+        assert_eq!(&goaway[13..], &[0, 0, 0, 0x4]);
+    }
+
+    #[tokio::test]
+    async fn frames_we_send_move_their_stream_along() {
+        let (mut connection, _peer) = open_pair(ClientTimeouts::disabled()).await;
+        // HEADERS with END_HEADERS and an indexed `:method GET`.
+        let headers = FrameH2::new(FrameTypeH2::Headers, 0x4, 1, Bytes::from_static(&[0x82]));
+        connection.send_frame(&headers).await.unwrap();
+        assert!(matches!(connection.streams[&1].state, StreamState::Open));
+        assert_eq!(connection.next_stream_id, 3);
+
+        let data = FrameH2::data(1, Bytes::from_static(b"hi"), true);
+        connection.send_frame(&data).await.unwrap();
+        assert!(matches!(
+            connection.streams[&1].state,
+            StreamState::HalfClosedLocal
+        ));
+
+        let rst = FrameH2::rst(1, RstErrorCode::Cancel);
+        connection.send_frame(&rst).await.unwrap();
+        assert!(matches!(connection.streams[&1].state, StreamState::Closed));
+        assert!(connection.streams[&1].reset_sent);
+    }
+
+    #[tokio::test]
+    async fn data_on_an_idle_stream_fails_the_connection() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let data = FrameH2::data(5, Bytes::from_static(b"hi"), false);
+        peer.write_all(&data.serialize().unwrap()).await.unwrap();
+
+        assert!(matches!(
+            connection.read_next_frame(None).await,
+            Err(ProtocolError::H2ConnectionError(
+                H2ConnectionErrorKind::ProtocolViolation(_)
+            ))
+        ));
+        let (frame_type, _, payload) = read_frame(&mut peer).await;
+        assert_eq!(frame_type, 0x7);
+        assert_eq!(&payload[4..], &[0, 0, 0, 0x1]);
+    }
+
+    #[tokio::test]
+    async fn data_after_end_stream_is_answered_with_rst_stream() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let stream_id = open_get(&mut connection).await;
+        assert_eq!(read_frame(&mut peer).await.0, 0x1);
+        peer.write_all(&status_200(stream_id, true)).await.unwrap();
+        assert_eq!(
+            connection.read_response(stream_id).await.unwrap().status,
+            200
+        );
+
+        let data = FrameH2::data(stream_id, Bytes::from_static(b"late"), false);
+        peer.write_all(&data.serialize().unwrap()).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        let (frame_type, rst_stream, payload) = read_frame(&mut peer).await;
+        assert_eq!((frame_type, rst_stream), (0x3, stream_id));
+        assert_eq!(payload, [0, 0, 0, 0x5]);
+    }
+
+    #[tokio::test]
+    async fn lax_stream_states_take_frames_on_idle_streams() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        connection.set_lax_stream_states(true);
+        peer.write_all(&status_200(5, true)).await.unwrap();
+
+        connection.read_next_frame(None).await.unwrap();
+        assert!(connection.streams.contains_key(&5));
+    }
+
+    #[tokio::test]
+    async fn the_overall_timeout_cuts_a_quiet_body_short() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let stream_id = open_get(&mut connection).await;
+        let mut response = status_200(stream_id, false);
+        let data = FrameH2::data(stream_id, Bytes::from_static(b"ab"), false);
+        response.extend_from_slice(&data.serialize().unwrap());
+        peer.write_all(&response).await.unwrap();
+
+        let budgets = std::cell::RefCell::new(Vec::new());
+        let record = |_: &StreamEvent, budget: &ReadBudget| budgets.borrow_mut().push(*budget);
+        let overall = Duration::from_millis(100);
+        let read = connection.read_response_with_budget(
+            stream_id,
+            Some(overall),
+            None,
+            None,
+            Some(&record),
+        );
+        // The peer never ends the stream and no other timeout is set.
+        let response = tokio::time::timeout(Duration::from_secs(5), read)
+            .await
+            .expect("the overall timeout was not applied")
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body.as_ref(), b"ab");
+
+        let budgets = budgets.into_inner();
+        assert_eq!(budgets.len(), 2);
+        assert_eq!(budgets[1].events, 2);
+        assert_eq!(budgets[1].remaining_events, None);
+        assert!(budgets[1].remaining.unwrap() <= overall);
+        assert!(budgets[1].elapsed >= budgets[0].elapsed);
+    }
+
+    #[tokio::test]
+    async fn the_budget_counts_down_the_events_left() {
+        let (mut connection, mut peer) = open_pair(ClientTimeouts::disabled()).await;
+        let stream_id = open_get(&mut connection).await;
+        let mut response = status_200(stream_id, false);
+        for chunk in [&b"ab"[..], &b"cd"[..]] {
+            let data = FrameH2::data(stream_id, Bytes::copy_from_slice(chunk), false);
+            response.extend_from_slice(&data.serialize().unwrap());
+        }
+        peer.write_all(&response).await.unwrap();
+
+        let left = std::cell::RefCell::new(Vec::new());
+        let record =
+            |_: &StreamEvent, budget: &ReadBudget| left.borrow_mut().push(budget.remaining_events);
+        let response = connection
+            .read_response_with_budget(stream_id, None, None, Some(2), Some(&record))
+            .await
+            .unwrap();
+        assert_eq!(response.body.as_ref(), b"ab");
+        assert_eq!(left.into_inner(), vec![Some(1), Some(0)]);
+    }
+}
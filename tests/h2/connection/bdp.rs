@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_probe_is_in_flight_at_a_time() {
+        let mut bdp = BdpEstimator::new(65_535);
+        assert!(bdp.on_data(1000));
+        assert!(!bdp.on_data(1000));
+        assert!(bdp.on_ping_ack().is_none());
+        assert!(bdp.rtt().is_some());
+
+        // Without a probe out, an ACK is not a sample.
+        assert!(bdp.on_ping_ack().is_none());
+        assert!(bdp.on_data(1000));
+    }
+
+    #[test]
+    fn a_sample_filling_the_window_grows_it() {
+        let mut bdp = BdpEstimator::new(30_000);
+        assert!(bdp.on_data(10_000));
+        bdp.on_data(10_000);
+        assert_eq!(bdp.on_ping_ack(), Some(40_000));
+        assert_eq!(bdp.window(), 40_000);
+
+        // Under two thirds of the window leaves it as it is.
+        bdp.on_data(20_000);
+        bdp.on_data(6_000);
+        assert!(bdp.on_ping_ack().is_none());
+        assert_eq!(bdp.window(), 40_000);
+    }
+
+    #[test]
+    fn growth_stops_at_the_cap() {
+        let mut bdp = BdpEstimator::new(BDP_MAX_WINDOW / 2);
+        bdp.on_data(BDP_MAX_WINDOW as usize);
+        assert_eq!(bdp.on_ping_ack(), Some(BDP_MAX_WINDOW));
+        assert!(!bdp.on_data(1000));
+        assert!(bdp.on_ping_ack().is_none());
+    }
+}
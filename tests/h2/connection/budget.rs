@@ -0,0 +1,232 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h2::connection::{ConnectionState, StreamState};
+    use crate::h2::framing::FrameH2;
+    use crate::stream::TransportStream;
+    use crate::types::{ClientTimeouts, Header};
+    use bytes::Bytes;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Connection past its handshake with one stream awaiting its response,
+    /// plus the peer's socket.
+    async fn open_pair() -> (H2Connection, u32, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (peer, _) = listener.accept().await.unwrap();
+        let mut connection =
+            H2Connection::new(TransportStream::Tcp(client), ClientTimeouts::disabled());
+        connection.state = ConnectionState::Open;
+        connection.initial_settings_received = true;
+        let stream_id = connection.create_stream().await.unwrap();
+        connection
+            .update_stream_state(stream_id, StreamState::HalfClosedLocal)
+            .unwrap();
+        (connection, stream_id, peer)
+    }
+
+    /// Has the peer send `len` bytes of DATA on `stream_id` and reads it.
+    async fn receive_data(
+        connection: &mut H2Connection,
+        peer: &mut TcpStream,
+        stream_id: u32,
+        len: usize,
+    ) -> Result<(), ProtocolError> {
+        let frame = FrameH2::data(stream_id, Bytes::from(vec![b'x'; len]), false);
+        peer.write_all(&frame.serialize().unwrap()).await.unwrap();
+        connection.read_next_frame(None).await.map(|_| ())
+    }
+
+    #[test]
+    fn events_count_their_payload() {
+        let data = StreamEvent::Data {
+            payload: Bytes::from_static(b"hello"),
+            end_stream: false,
+        };
+        assert_eq!(event_size(&data), 5);
+
+        let headers = StreamEvent::Headers {
+            headers: vec![Header::new(":status".to_string(), "200".to_string())],
+            end_stream: false,
+            is_trailer: false,
+        };
+        assert_eq!(event_size(&headers), 10);
+    }
+
+    #[test]
+    fn captured_frames_include_their_header() {
+        assert_eq!(captured_frame_size(0), 9);
+        assert_eq!(captured_frame_size(16_384), 16_393);
+    }
+
+    #[tokio::test]
+    async fn going_over_the_budget_fails_the_read() {
+        let (mut connection, stream_id, mut peer) = open_pair().await;
+        connection.set_memory_budget(Some(MemoryBudget::new(1500)));
+
+        // 1009 captured plus 1000 queued.
+        assert!(matches!(
+            receive_data(&mut connection, &mut peer, stream_id, 1000).await,
+            Err(ProtocolError::MemoryBudgetExceeded { limit: 1500, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn dropping_the_capture_makes_room_once() {
+        let (mut connection, stream_id, mut peer) = open_pair().await;
+        connection.set_memory_budget(Some(
+            MemoryBudget::new(1500).on_exceed(MemoryPressure::DropCapture),
+        ));
+
+        receive_data(&mut connection, &mut peer, stream_id, 1000)
+            .await
+            .unwrap();
+        assert!(connection.capture_dropped());
+        assert_eq!(connection.memory_usage().captured, 0);
+        assert!(connection.take_captured_frames(stream_id).0.is_none());
+
+        // Nothing is left to drop for what is queued.
+        assert!(matches!(
+            receive_data(&mut connection, &mut peer, stream_id, 1000).await,
+            Err(ProtocolError::MemoryBudgetExceeded { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn backpressure_withholds_credit_until_events_are_read() {
+        let (mut connection, stream_id, mut peer) = open_pair().await;
+        connection.set_memory_budget(Some(
+            MemoryBudget::new(4000).on_exceed(MemoryPressure::Backpressure),
+        ));
+        let window = connection.recv_connection_window;
+
+        receive_data(&mut connection, &mut peer, stream_id, 1000)
+            .await
+            .unwrap();
+        receive_data(&mut connection, &mut peer, stream_id, 1000)
+            .await
+            .unwrap();
+        assert!(connection.memory.withholding);
+        receive_data(&mut connection, &mut peer, stream_id, 100)
+            .await
+            .unwrap();
+        assert_eq!(connection.recv_connection_window, window - 100);
+
+        // Reading the first DATA brings the connection back under budget.
+        connection.recv_stream_event(stream_id).await.unwrap();
+        assert!(!connection.memory.withholding);
+        assert_eq!(connection.recv_connection_window, window);
+    }
+
+    #[tokio::test]
+    async fn backpressure_fails_when_reading_cannot_help() {
+        let (mut connection, stream_id, mut peer) = open_pair().await;
+        connection.set_memory_budget(Some(
+            MemoryBudget::new(500).on_exceed(MemoryPressure::Backpressure),
+        ));
+
+        // The captured frame alone is over budget.
+        assert!(matches!(
+            receive_data(&mut connection, &mut peer, stream_id, 1000).await,
+            Err(ProtocolError::MemoryBudgetExceeded { .. })
+        ));
+        assert!(!connection.memory.withholding);
+    }
+
+    #[tokio::test]
+    async fn an_overflowing_stream_is_reset_alone() {
+        let (mut connection, stream_id, mut peer) = open_pair().await;
+        let other = connection.create_stream().await.unwrap();
+        connection
+            .update_stream_state(other, StreamState::HalfClosedLocal)
+            .unwrap();
+        connection.set_stream_queue_limits(Some(StreamQueueLimits::new().max_events(1)));
+
+        receive_data(&mut connection, &mut peer, stream_id, 10)
+            .await
+            .unwrap();
+        receive_data(&mut connection, &mut peer, stream_id, 10)
+            .await
+            .unwrap();
+        assert!(connection.streams[&stream_id].reset_sent);
+        assert_eq!(connection.memory_usage().queued_events, 0);
+        assert!(matches!(
+            connection.recv_stream_event(stream_id).await,
+            Err(ProtocolError::H2StreamError(
+                H2StreamErrorKind::QueueOverflow {
+                    events: 2,
+                    bytes: 20
+                }
+            ))
+        ));
+
+        receive_data(&mut connection, &mut peer, other, 10)
+            .await
+            .unwrap();
+        assert!(matches!(
+            connection.recv_stream_event(other).await,
+            Ok(StreamEvent::Data { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn dropping_the_oldest_data_keeps_the_headers() {
+        let (mut connection, stream_id, mut peer) = open_pair().await;
+        connection.set_stream_queue_limits(Some(
+            StreamQueueLimits::new()
+                .max_events(2)
+                .on_overflow(QueueOverflow::DropOldest),
+        ));
+
+        // `:status 200` with END_HEADERS.
+        let mut headers = vec![0, 0, 1, 1, 0x4];
+        headers.extend_from_slice(&stream_id.to_be_bytes());
+        headers.push(0x88);
+        peer.write_all(&headers).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        receive_data(&mut connection, &mut peer, stream_id, 1)
+            .await
+            .unwrap();
+        receive_data(&mut connection, &mut peer, stream_id, 2)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            connection.recv_stream_event(stream_id).await,
+            Ok(StreamEvent::Headers { .. })
+        ));
+        match connection.recv_stream_event(stream_id).await {
+            Ok(StreamEvent::Data { payload, .. }) => assert_eq!(payload.len(), 2),
+            other => panic!("expected the newest DATA, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(connection.memory_usage().queued_events, 0);
+    }
+
+    #[tokio::test]
+    async fn an_overflow_error_fails_the_read_that_queued_it() {
+        let (mut connection, stream_id, mut peer) = open_pair().await;
+        connection.set_stream_queue_limits(Some(
+            StreamQueueLimits::new()
+                .max_bytes(15)
+                .on_overflow(QueueOverflow::Error),
+        ));
+
+        receive_data(&mut connection, &mut peer, stream_id, 10)
+            .await
+            .unwrap();
+        assert!(matches!(
+            receive_data(&mut connection, &mut peer, stream_id, 10).await,
+            Err(ProtocolError::H2StreamError(
+                H2StreamErrorKind::QueueOverflow {
+                    events: 2,
+                    bytes: 20
+                }
+            ))
+        ));
+        // The stream is left as it was, for the caller to decide.
+        assert!(!connection.streams[&stream_id].reset_sent);
+    }
+}
@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h2::consts::SETTINGS_INITIAL_WINDOW_SIZE;
+
+    fn snapshot() -> H2DebugState {
+        let mut streams = BTreeMap::new();
+        streams.insert(
+            1,
+            H2StreamDebugState {
+                state: StreamState::HalfClosedLocal,
+                send_window: 65_535,
+                recv_window: 60_000,
+                headers_sent: true,
+                final_headers_received: true,
+                end_stream_sent: true,
+                end_stream_received: false,
+                reset_sent: false,
+                queued_events: 2,
+                queued_bytes: 5_535,
+            },
+        );
+        H2DebugState {
+            state: ConnectionState::Open,
+            local_settings: BTreeMap::from([(SETTINGS_INITIAL_WINDOW_SIZE, 65_535)]),
+            remote_settings: BTreeMap::from([(SETTINGS_MAX_CONCURRENT_STREAMS, 100), (0xa, 1)]),
+            send_window: 1_000,
+            recv_window: 2_000,
+            next_stream_id: 3,
+            last_stream_id: 1,
+            streams,
+            goaway_sent: false,
+            goaway_received: Some(H2GoAwayState {
+                last_stream_id: 1,
+                error_code: H2ErrorCode::EnhanceYourCalm,
+                debug_data: "slow down".to_string(),
+            }),
+            hpack_encoder: HpackTableStats::default(),
+            hpack_decoder: HpackTableStats {
+                entries: 1,
+                size: 42,
+                max_size: 4096,
+            },
+            memory: MemoryUsage::default(),
+        }
+    }
+
+    #[test]
+    fn settings_are_keyed_by_name() {
+        assert_eq!(
+            setting_name(SETTINGS_INITIAL_WINDOW_SIZE),
+            "INITIAL_WINDOW_SIZE"
+        );
+        assert_eq!(setting_name(0xa), "0xa");
+
+        let json = snapshot().to_json();
+        assert_eq!(json["settings"]["local"]["INITIAL_WINDOW_SIZE"], 65_535);
+        assert_eq!(json["settings"]["remote"]["MAX_CONCURRENT_STREAMS"], 100);
+        assert_eq!(json["settings"]["remote"]["0xa"], 1);
+    }
+
+    #[test]
+    fn json_carries_streams_windows_goaway_and_tables() {
+        let json = snapshot().to_json();
+        assert_eq!(json["protocol"], "h2");
+        assert_eq!(json["state"], "Open");
+        assert_eq!(json["windows"]["send"], 1_000);
+        assert_eq!(json["streams"]["1"]["state"], "half-closed (local)");
+        assert_eq!(json["streams"]["1"]["queued_bytes"], 5_535);
+        assert_eq!(json["goaway"]["sent"], false);
+        assert_eq!(json["goaway"]["received"]["error_code"], 0xb);
+        assert_eq!(json["goaway"]["received"]["debug_data"], "slow down");
+        assert_eq!(json["hpack"]["decoder"]["size"], 42);
+        assert_eq!(json["memory"]["total"], 0);
+    }
+}
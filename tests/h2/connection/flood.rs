@@ -0,0 +1,25 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_limit_once_exceeded_within_a_second() {
+        let mut guard = FloodGuard::new(H2FloodLimits {
+            max_pings_per_second: Some(2),
+            ..H2FloodLimits::default()
+        });
+        assert_eq!(guard.record(H2FloodKind::Ping), None);
+        assert_eq!(guard.record(H2FloodKind::Ping), None);
+        assert_eq!(guard.record(H2FloodKind::Ping), Some(2));
+        // Counters are independent per frame type.
+        assert_eq!(guard.record(H2FloodKind::Settings), None);
+    }
+
+    #[test]
+    fn unlimited_never_trips() {
+        let mut guard = FloodGuard::new(H2FloodLimits::unlimited());
+        for _ in 0..10_000 {
+            assert_eq!(guard.record(H2FloodKind::RstStream), None);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h2::connection::{ConnectionState, H2Connection, StreamState};
+    use crate::h2::framing::FrameH2;
+    use crate::stream::TransportStream;
+    use crate::types::ClientTimeouts;
+    use bytes::Bytes;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// A connection with a request out on stream 1 and `:status 200`
+    /// followed by two DATA frames, the last ending the stream, on its way.
+    async fn answered_stream() -> (H2Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut peer, _) = listener.accept().await.unwrap();
+        let mut connection =
+            H2Connection::new(TransportStream::Tcp(client), ClientTimeouts::disabled());
+        connection.state = ConnectionState::Open;
+        connection.initial_settings_received = true;
+        let stream_id = connection.create_stream().await.unwrap();
+        connection
+            .update_stream_state(stream_id, StreamState::HalfClosedLocal)
+            .unwrap();
+
+        let mut frames = vec![0, 0, 1, 1, 0x4, 0, 0, 0, 1, 0x88];
+        for (chunk, end_stream) in [(&b"ab"[..], false), (&b"cd"[..], true)] {
+            let data = FrameH2::data(1, Bytes::copy_from_slice(chunk), end_stream);
+            frames.extend_from_slice(&data.serialize().unwrap());
+        }
+        peer.write_all(&frames).await.unwrap();
+        (connection, peer)
+    }
+
+    fn kind(event: &StreamEvent) -> &'static str {
+        match event {
+            StreamEvent::Headers { .. } => "headers",
+            StreamEvent::Data { .. } => "data",
+            StreamEvent::RstStream { .. } => "rst",
+        }
+    }
+
+    #[tokio::test]
+    async fn closures_may_keep_state_between_events() {
+        let (mut connection, _peer) = answered_stream().await;
+        let mut seen = Vec::new();
+        let mut handler = |event: &StreamEvent, budget: &ReadBudget| {
+            seen.push((kind(event), budget.events));
+        };
+
+        let response = connection
+            .read_response_with_handler(1, None, None, None, Some(&mut handler))
+            .await
+            .unwrap();
+        assert_eq!(response.body.as_ref(), b"abcd");
+        assert_eq!(seen, vec![("headers", 1), ("data", 2), ("data", 3)]);
+    }
+
+    #[tokio::test]
+    async fn channels_get_a_copy_of_each_event() {
+        let (mut connection, _peer) = answered_stream().await;
+        let (mut tx, mut rx) = mpsc::unbounded_channel();
+
+        connection
+            .read_response_with_handler(1, None, None, None, Some(&mut tx))
+            .await
+            .unwrap();
+        drop(tx);
+        let mut kinds = Vec::new();
+        while let Some(event) = rx.recv().await {
+            kinds.push(kind(&event));
+        }
+        assert_eq!(kinds, vec!["headers", "data", "data"]);
+    }
+
+    #[tokio::test]
+    async fn a_bounded_channel_holds_the_read_back() {
+        let (mut connection, _peer) = answered_stream().await;
+        let (mut tx, mut rx) = mpsc::channel(1);
+
+        let read = connection.read_response_with_handler(1, None, None, None, Some(&mut tx));
+        let consume = async {
+            let mut kinds = Vec::new();
+            // The read cannot finish until the consumer makes room.
+            while kinds.len() < 3 {
+                let event = rx.recv().await.unwrap();
+                kinds.push(kind(&event));
+            }
+            kinds
+        };
+        let (response, kinds) = tokio::join!(read, consume);
+        assert_eq!(response.unwrap().body.as_ref(), b"abcd");
+        assert_eq!(kinds, vec!["headers", "data", "data"]);
+    }
+
+    #[tokio::test]
+    async fn async_closures_are_awaited_for_each_event() {
+        let (mut connection, _peer) = answered_stream().await;
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut handler = AsyncEventHandler(|event: StreamEvent, budget: ReadBudget| {
+            let seen = seen.clone();
+            async move {
+                tokio::task::yield_now().await;
+                seen.borrow_mut().push((kind(&event), budget.events));
+            }
+        });
+
+        connection
+            .read_response_with_handler(1, None, None, None, Some(&mut handler))
+            .await
+            .unwrap();
+        assert_eq!(
+            *seen.borrow(),
+            vec![("headers", 1), ("data", 2), ("data", 3)]
+        );
+    }
+}
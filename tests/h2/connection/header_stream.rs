@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(name: &str, value: &str) -> Header {
+        Header::new(name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn headers_are_kept_unless_a_handler_takes_them() {
+        let status = header(":status", "200");
+        let cookie = header("set-cookie", "a=1");
+
+        let buffered = HeaderStreaming::new().retain(false);
+        assert!(buffered.retains(&cookie));
+
+        let streamed = HeaderStreaming::new().on_batch(|_, _| {}).retain(false);
+        assert!(streamed.retains(&status));
+        assert!(!streamed.retains(&cookie));
+
+        let kept = HeaderStreaming::new().on_batch(|_, _| {});
+        assert!(kept.retains(&cookie));
+    }
+
+    #[test]
+    fn batch_size_is_at_least_one() {
+        assert_eq!(HeaderStreaming::new().batch_size(0).batch_size, 1);
+        assert_eq!(HeaderStreaming::new().batch_size, DEFAULT_BATCH_SIZE);
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h2::connection::ConnectionState;
+    use crate::h2::framing::FrameH2;
+    use crate::wire::WireDirection;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn open_pair() -> (H2Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (peer, _) = listener.accept().await.unwrap();
+        let mut connection =
+            H2Connection::new(TransportStream::Tcp(client), ClientTimeouts::disabled());
+        connection.state = ConnectionState::Open;
+        connection.initial_settings_received = true;
+        (connection, peer)
+    }
+
+    #[tokio::test]
+    async fn both_directions_are_mirrored_verbatim() {
+        let (mut connection, mut peer) = open_pair().await;
+        let mut chunks = connection.mirror_wire();
+        assert!(connection.is_mirroring_wire());
+
+        connection.ping([1; 8]).await.unwrap();
+        let ping = FrameH2::ping([1; 8]).serialize().unwrap();
+        let mut on_wire = vec![0u8; ping.len()];
+        peer.read_exact(&mut on_wire).await.unwrap();
+        let sent = chunks.try_recv().unwrap();
+        assert_eq!(sent.direction, WireDirection::Sent);
+        assert_eq!(sent.stream_id, None);
+        assert_eq!(&sent.data[..], on_wire.as_slice());
+
+        // A frame of an unknown type is mirrored as it came in.
+        let unknown = [0, 0, 1, 0xfa, 0, 0, 0, 0, 0, 0x2a];
+        peer.write_all(&unknown).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        let received = chunks.try_recv().unwrap();
+        assert_eq!(received.direction, WireDirection::Received);
+        assert_eq!(&received.data[..], &unknown[..]);
+
+        connection.set_wire_mirror(None);
+        assert!(!connection.is_mirroring_wire());
+    }
+}
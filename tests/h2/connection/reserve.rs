@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h2::connection::{ConnectionState, StreamLimitPolicy};
+    use crate::h2::consts::SETTINGS_MAX_CONCURRENT_STREAMS;
+    use crate::stream::TransportStream;
+    use crate::types::ClientTimeouts;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn open_pair() -> (H2Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (peer, _) = listener.accept().await.unwrap();
+        let mut connection =
+            H2Connection::new(TransportStream::Tcp(client), ClientTimeouts::disabled());
+        connection.state = ConnectionState::Open;
+        connection.initial_settings_received = true;
+        (connection, peer)
+    }
+
+    fn request_headers() -> Vec<Header> {
+        [
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":authority", "example.com"),
+            (":path", "/"),
+        ]
+        .iter()
+        .map(|(name, value)| Header::new(name.to_string(), value.to_string()))
+        .collect()
+    }
+
+    /// Reads frames off the peer's socket until `count` have arrived and
+    /// returns their (type, stream ID) pairs.
+    async fn read_frames(peer: &mut TcpStream, count: usize) -> Vec<(u8, u32)> {
+        let mut buffer = Vec::new();
+        let mut frames = Vec::new();
+        while frames.len() < count {
+            let mut chunk = [0u8; 1024];
+            let read = peer.read(&mut chunk).await.unwrap();
+            assert!(read > 0, "connection closed early");
+            buffer.extend_from_slice(&chunk[..read]);
+            while buffer.len() >= 9 {
+                let len = u32::from_be_bytes([0, buffer[0], buffer[1], buffer[2]]) as usize;
+                if buffer.len() < 9 + len {
+                    break;
+                }
+                let stream_id =
+                    u32::from_be_bytes([buffer[5], buffer[6], buffer[7], buffer[8]]) & 0x7fff_ffff;
+                frames.push((buffer[3], stream_id));
+                buffer.drain(..9 + len);
+            }
+        }
+        frames
+    }
+
+    #[tokio::test]
+    async fn reserving_ignores_the_concurrency_limit() {
+        let (mut connection, _peer) = open_pair().await;
+        connection
+            .remote_settings
+            .insert(SETTINGS_MAX_CONCURRENT_STREAMS, 1);
+        connection.set_stream_limit_policy(StreamLimitPolicy::Wait);
+        let open = connection.create_stream().await.unwrap();
+        connection
+            .update_stream_state(open, StreamState::Open)
+            .unwrap();
+
+        assert_eq!(connection.reserve_streams(3).unwrap(), vec![3, 5, 7]);
+        assert_eq!(connection.available_stream_slots(), 0);
+    }
+
+    #[tokio::test]
+    async fn reserving_past_goaway_is_rejected() {
+        let (mut connection, _peer) = open_pair().await;
+        connection.goaway_last_stream_id = Some(3);
+
+        assert!(connection.reserve_streams(3).is_err());
+        assert!(connection.streams.is_empty());
+        assert_eq!(connection.reserve_streams(2).unwrap(), vec![1, 3]);
+    }
+
+    #[tokio::test]
+    async fn prepared_headers_go_out_through_the_frame_path() {
+        let (mut connection, mut peer) = open_pair().await;
+        let ids = connection.reserve_streams(2).unwrap();
+        connection
+            .prepare_headers(ids[0], &request_headers(), false)
+            .unwrap();
+        connection
+            .prepare_headers(ids[1], &request_headers(), true)
+            .unwrap();
+        assert_eq!(connection.prepared_streams(), ids);
+
+        assert_eq!(connection.send_prepared().await.unwrap(), ids);
+        assert!(connection.prepared_streams().is_empty());
+        assert_eq!(
+            connection.get_stream_state(ids[0]),
+            Some(&StreamState::Open)
+        );
+        assert_eq!(
+            connection.get_stream_state(ids[1]),
+            Some(&StreamState::HalfClosedLocal)
+        );
+        assert_eq!(connection.stats().streams[&ids[0]].frames_sent, 1);
+        assert_eq!(read_frames(&mut peer, 2).await, vec![(1, 1), (1, 3)]);
+    }
+
+    #[tokio::test]
+    async fn prepared_streams_stay_in_order_and_cannot_be_released() {
+        let (mut connection, _peer) = open_pair().await;
+        let ids = connection.reserve_streams(3).unwrap();
+        connection
+            .prepare_headers(ids[1], &request_headers(), true)
+            .unwrap();
+
+        assert!(connection
+            .prepare_headers(ids[0], &request_headers(), true)
+            .is_err());
+        assert!(connection.ensure_nothing_prepared().is_err());
+        assert!(!connection.release_stream(ids[1]));
+        assert!(connection.release_stream(ids[0]));
+        assert!(!connection.release_stream(ids[0]));
+        assert_eq!(connection.get_stream_state(ids[0]), None);
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Header;
+    use bytes::Bytes;
+
+    /// Header block of "literal with incremental indexing, new name" fields,
+    /// optionally led by a dynamic table size update.
+    fn indexed_block(size_update: Option<u8>, fields: &[(&str, &str)]) -> HeaderBlockInfo {
+        let mut raw = Vec::new();
+        if let Some(size) = size_update {
+            raw.push(0x20 | size);
+        }
+        let mut headers = Vec::new();
+        for (name, value) in fields {
+            raw.push(0x40);
+            raw.push(name.len() as u8);
+            raw.extend_from_slice(name.as_bytes());
+            raw.push(value.len() as u8);
+            raw.extend_from_slice(value.as_bytes());
+            headers.push(Header::new(name.to_string(), value.to_string()));
+        }
+        HeaderBlockInfo::hpack(1, Bytes::from(raw), &headers)
+    }
+
+    #[test]
+    fn table_tracks_insertions_and_evictions() {
+        let mut table = DynamicTableTracker::new();
+        table.apply(&indexed_block(None, &[("x-a", "1"), ("x-b", "22")]));
+        assert_eq!(
+            table.snapshot(),
+            HpackTableStats {
+                entries: 2,
+                size: 36 + 37,
+                max_size: 4096,
+            }
+        );
+
+        // Shrinking to 30 octets evicts both, and a 36-octet entry does not fit.
+        table.apply(&indexed_block(Some(30), &[("x-c", "1")]));
+        assert_eq!(
+            table.snapshot(),
+            HpackTableStats {
+                entries: 0,
+                size: 0,
+                max_size: 30,
+            }
+        );
+    }
+
+    #[test]
+    fn recorder_counts_frames_per_type_and_stream() {
+        let mut recorder = StatsRecorder::new();
+        // DATA, 5-byte payload, stream 3
+        recorder.on_frame_sent(&[0, 0, 5, 0x0, 0x1, 0, 0, 0, 3, 1, 2, 3, 4, 5]);
+        // SETTINGS ACK on stream 0
+        recorder.on_frame_received(&[0, 0, 0, 0x4, 0x1, 0, 0, 0, 0]);
+
+        assert_eq!(recorder.sent.frames_of(FrameTypeH2::Data), 1);
+        assert_eq!(recorder.sent.payload_bytes, 5);
+        assert_eq!(recorder.received.frames_of(FrameTypeH2::Settings), 1);
+        assert_eq!(recorder.streams[&3].payload_bytes_sent, 5);
+        assert!(!recorder.streams.contains_key(&0));
+    }
+}
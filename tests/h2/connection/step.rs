@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionEvent;
+    use crate::stream::TransportStream;
+    use crate::types::ClientTimeouts;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn frames_only_move_when_stepped() {
+        tokio_test::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let client = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let (mut peer, _) = listener.accept().await.unwrap();
+            let mut connection =
+                H2Connection::new(TransportStream::Tcp(client), ClientTimeouts::default());
+            connection.set_step_mode(true);
+
+            connection.ping([1; 8]).await.unwrap();
+            connection.ping([2; 8]).await.unwrap();
+            assert_eq!(connection.queued_writes(), 2);
+
+            let written = connection.step_write().await.unwrap().unwrap();
+            assert_eq!(written, FrameH2::ping([1; 8]).serialize().unwrap());
+            assert_eq!(connection.queued_writes(), 1);
+            let mut received = vec![0u8; written.len()];
+            peer.read_exact(&mut received).await.unwrap();
+            assert_eq!(received, written);
+
+            assert!(matches!(
+                connection.read_next_frame(None).await,
+                Err(ProtocolError::StepModeBlocked)
+            ));
+
+            let ack = FrameH2::ping_ack([1; 8]).serialize().unwrap();
+            peer.write_all(&ack).await.unwrap();
+            let frame = connection.step_read().await.unwrap();
+            assert_eq!(&frame.payload[..], &[1; 8]);
+
+            connection.step_write().await.unwrap();
+            assert_eq!(connection.step_write().await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn unknown_frames_are_reported() {
+        tokio_test::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let client = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let (mut peer, _) = listener.accept().await.unwrap();
+            let mut connection =
+                H2Connection::new(TransportStream::Tcp(client), ClientTimeouts::default());
+            connection.set_step_mode(true);
+            let mut events = connection.subscribe_events();
+
+            // ORIGIN (RFC 8336) on stream 0.
+            let origin = [
+                0x00, 0x00, 0x02, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ];
+            peer.write_all(&origin).await.unwrap();
+            connection.step_read().await.unwrap();
+
+            assert_eq!(
+                events.try_recv().unwrap(),
+                ConnectionEvent::UnknownFrame {
+                    stream_id: 0,
+                    frame_type: 0x0c,
+                    payload: Bytes::from_static(&[0x00, 0x00]),
+                }
+            );
+            assert_eq!(connection.queued_writes(), 0);
+        });
+    }
+}
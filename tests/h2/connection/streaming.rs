@@ -0,0 +1,241 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h2::connection::{ConnectionState, StreamState};
+    use crate::h2::framing::FrameH2;
+    use crate::stream::TransportStream;
+    use crate::types::ClientTimeouts;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn open_pair() -> (H2Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (peer, _) = listener.accept().await.unwrap();
+        let mut connection =
+            H2Connection::new(TransportStream::Tcp(client), ClientTimeouts::disabled());
+        connection.state = ConnectionState::Open;
+        connection.initial_settings_received = true;
+        (connection, peer)
+    }
+
+    /// A stream the request went out on, waiting for its response.
+    async fn sent_stream(connection: &mut H2Connection) -> u32 {
+        let stream_id = connection.create_stream().await.unwrap();
+        connection
+            .update_stream_state(stream_id, StreamState::HalfClosedLocal)
+            .unwrap();
+        stream_id
+    }
+
+    /// `:status 200`, two DATA frames, and trailers ending the stream.
+    fn response_frames(stream_id: u32) -> Vec<u8> {
+        let mut frames = vec![0, 0, 1, 1, 0x4];
+        frames.extend_from_slice(&stream_id.to_be_bytes());
+        frames.push(0x88);
+        for chunk in [&b"abc"[..], &b"de"[..]] {
+            frames.extend_from_slice(
+                &FrameH2::data(stream_id, Bytes::copy_from_slice(chunk), false)
+                    .serialize()
+                    .unwrap(),
+            );
+        }
+        // Literal `x-sum: 5` without indexing, END_HEADERS | END_STREAM.
+        let block = b"\x00\x05x-sum\x015";
+        frames.extend_from_slice(&[0, 0, block.len() as u8, 1, 0x5]);
+        frames.extend_from_slice(&stream_id.to_be_bytes());
+        frames.extend_from_slice(block);
+        frames
+    }
+
+    /// `:status 200` without END_STREAM, then `chunks` as DATA frames.
+    fn head_and_data(stream_id: u32, chunks: &[&'static [u8]]) -> Vec<u8> {
+        let mut frames = vec![0, 0, 1, 1, 0x4];
+        frames.extend_from_slice(&stream_id.to_be_bytes());
+        frames.push(0x88);
+        for chunk in chunks {
+            frames.extend_from_slice(
+                &FrameH2::data(stream_id, Bytes::from_static(chunk), false)
+                    .serialize()
+                    .unwrap(),
+            );
+        }
+        frames
+    }
+
+    /// Receive windows of the connection and of `stream_id`.
+    fn recv_windows(connection: &H2Connection, stream_id: u32) -> (i32, i32) {
+        (
+            connection.recv_connection_window,
+            connection.streams[&stream_id].recv_window,
+        )
+    }
+
+    /// Reads frames off the peer's socket until `count` have arrived and
+    /// returns their (type, stream ID) pairs.
+    async fn read_frames(peer: &mut TcpStream, count: usize) -> Vec<(u8, u32)> {
+        let mut buffer = Vec::new();
+        let mut frames = Vec::new();
+        while frames.len() < count {
+            let mut chunk = [0u8; 1024];
+            let read = peer.read(&mut chunk).await.unwrap();
+            assert!(read > 0, "connection closed early");
+            buffer.extend_from_slice(&chunk[..read]);
+            while buffer.len() >= 9 {
+                let len = u32::from_be_bytes([0, buffer[0], buffer[1], buffer[2]]) as usize;
+                if buffer.len() < 9 + len {
+                    break;
+                }
+                let stream_id =
+                    u32::from_be_bytes([buffer[5], buffer[6], buffer[7], buffer[8]]) & 0x7fff_ffff;
+                frames.push((buffer[3], stream_id));
+                buffer.drain(..9 + len);
+            }
+        }
+        frames
+    }
+
+    #[tokio::test]
+    async fn the_body_source_hands_out_data_and_trailers() {
+        let (mut connection, mut peer) = open_pair().await;
+        let stream_id = sent_stream(&mut connection).await;
+        peer.write_all(&response_frames(stream_id)).await.unwrap();
+
+        let response = connection.read_response_streaming(stream_id).await.unwrap();
+        let mut response = StreamingResponse::from(response);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body.next_chunk().await.unwrap().unwrap(), "abc");
+        assert_eq!(response.body.next_chunk().await.unwrap().unwrap(), "de");
+        assert!(response.body.next_chunk().await.is_none());
+        let trailers = response.body.trailers().unwrap();
+        assert_eq!(trailers[0].name, "x-sum");
+        drop(response);
+
+        // Each DATA was credited back to the stream and the connection as
+        // it was handed out.
+        assert_eq!(
+            read_frames(&mut peer, 4).await,
+            vec![(8, stream_id), (8, 0), (8, stream_id), (8, 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn an_owned_connection_goes_with_the_body() {
+        let (mut connection, mut peer) = open_pair().await;
+        let stream_id = sent_stream(&mut connection).await;
+        peer.write_all(&response_frames(stream_id)).await.unwrap();
+
+        let response: StreamingResponse<'static> = connection
+            .into_response_streaming(stream_id)
+            .await
+            .unwrap()
+            .into();
+        let response = response.buffer().await.unwrap();
+        assert_eq!(response.body.as_ref(), b"abcde");
+        assert_eq!(response.trailers.unwrap()[0].name, "x-sum");
+    }
+
+    #[tokio::test]
+    async fn credit_is_held_until_each_chunk_is_consumed() {
+        let (mut connection, mut peer) = open_pair().await;
+        let stream_id = sent_stream(&mut connection).await;
+        let (connection_window, stream_window) = recv_windows(&connection, stream_id);
+        peer.write_all(&head_and_data(stream_id, &[]))
+            .await
+            .unwrap();
+        let mut response = connection.read_response_streaming(stream_id).await.unwrap();
+
+        peer.write_all(&head_and_data(stream_id, &[b"abc", b"de"])[10..])
+            .await
+            .unwrap();
+        response
+            .body
+            .connection
+            .read_next_frame(None)
+            .await
+            .unwrap();
+        response
+            .body
+            .connection
+            .read_next_frame(None)
+            .await
+            .unwrap();
+        let stream = &response.body.connection.streams[&stream_id];
+        assert_eq!(stream.pending_credits, [3, 2]);
+        assert_eq!(
+            recv_windows(&response.body.connection, stream_id),
+            (connection_window - 5, stream_window - 5)
+        );
+
+        assert_eq!(response.body.chunk().await.unwrap().unwrap(), "abc");
+        assert_eq!(
+            recv_windows(&response.body.connection, stream_id),
+            (connection_window - 2, stream_window - 2)
+        );
+        assert_eq!(response.body.chunk().await.unwrap().unwrap(), "de");
+        assert_eq!(
+            recv_windows(&response.body.connection, stream_id),
+            (connection_window, stream_window)
+        );
+        assert!(response.body.connection.streams[&stream_id]
+            .pending_credits
+            .is_empty());
+        assert_eq!(
+            read_frames(&mut peer, 4).await,
+            vec![(8, stream_id), (8, 0), (8, stream_id), (8, 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn credit_is_never_returned_twice() {
+        let (mut connection, mut peer) = open_pair().await;
+        let stream_id = sent_stream(&mut connection).await;
+        let (connection_window, _) = recv_windows(&connection, stream_id);
+
+        // DATA that arrives before the stream is read lazily is credited
+        // on arrival and holds no credit for the reader.
+        peer.write_all(&head_and_data(stream_id, &[b"abc"]))
+            .await
+            .unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        connection.read_next_frame(None).await.unwrap();
+        assert_eq!(connection.recv_connection_window, connection_window);
+        let mut response = connection.read_response_streaming(stream_id).await.unwrap();
+        assert_eq!(
+            response.body.connection.streams[&stream_id].pending_credits,
+            [0]
+        );
+        assert_eq!(response.body.chunk().await.unwrap().unwrap(), "abc");
+        assert_eq!(
+            response.body.connection.recv_connection_window,
+            connection_window
+        );
+
+        // DATA left unread when the reader is dropped is owed to the
+        // connection once, however the stream is wound up afterwards.
+        peer.write_all(&head_and_data(stream_id, &[b"de"])[10..])
+            .await
+            .unwrap();
+        response
+            .body
+            .connection
+            .read_next_frame(None)
+            .await
+            .unwrap();
+        drop(response);
+        assert_eq!(connection.deferred_connection_credit, 2);
+        assert!(connection.streams[&stream_id].pending_credits.is_empty());
+        assert!(!connection.streams[&stream_id].manual_flow_control);
+
+        connection.abandon_stream(stream_id).await.unwrap();
+        connection.abandon_stream(stream_id).await.unwrap();
+        assert_eq!(connection.deferred_connection_credit, 0);
+        assert_eq!(connection.recv_connection_window, connection_window);
+        assert_eq!(
+            read_frames(&mut peer, 4).await,
+            vec![(8, stream_id), (8, 0), (3, stream_id), (8, 0)]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h2::connection::ConnectionState;
+    use crate::h2::framing::FrameH2;
+    use crate::stream::TransportStream;
+    use crate::types::ClientTimeouts;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn open_pair() -> (H2Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (peer, _) = listener.accept().await.unwrap();
+        let mut connection =
+            H2Connection::new(TransportStream::Tcp(client), ClientTimeouts::disabled());
+        connection.state = ConnectionState::Open;
+        connection.initial_settings_received = true;
+        (connection, peer)
+    }
+
+    fn request_headers() -> Vec<Header> {
+        [
+            (":method", "POST"),
+            (":scheme", "http"),
+            (":authority", "example.com"),
+            (":path", "/"),
+        ]
+        .iter()
+        .map(|(name, value)| Header::new(name.to_string(), value.to_string()))
+        .collect()
+    }
+
+    /// Type, flags and payload of the next frame the peer receives.
+    async fn read_frame(peer: &mut TcpStream) -> (u8, u8, Vec<u8>) {
+        let mut header = [0u8; 9];
+        peer.read_exact(&mut header).await.unwrap();
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        let mut payload = vec![0u8; len];
+        peer.read_exact(&mut payload).await.unwrap();
+        (header[3], header[4], payload)
+    }
+
+    #[tokio::test]
+    async fn frames_go_out_as_written() {
+        let (mut connection, mut peer) = open_pair().await;
+        let mut writer = connection.stream_writer().await.unwrap();
+        writer.headers(&request_headers(), false).await.unwrap();
+        writer
+            .data(Bytes::from_static(b"abc"), false)
+            .await
+            .unwrap();
+        writer.data(Bytes::from_static(b"de"), false).await.unwrap();
+        assert!(!writer.is_finished());
+        let trailers = [Header::new("x-sum".to_string(), "5".to_string())];
+        writer.trailers(&trailers).await.unwrap();
+        assert!(writer.is_finished());
+        writer.flush().await.unwrap();
+
+        assert_eq!(read_frame(&mut peer).await.0, 0x1);
+        assert_eq!(read_frame(&mut peer).await, (0x0, 0x0, b"abc".to_vec()));
+        assert_eq!(read_frame(&mut peer).await, (0x0, 0x0, b"de".to_vec()));
+        let (frame_type, flags, _) = read_frame(&mut peer).await;
+        assert_eq!((frame_type, flags & 0x1), (0x1, 0x1));
+    }
+
+    #[tokio::test]
+    async fn finishing_sends_an_empty_end_stream_once() {
+        let (mut connection, mut peer) = open_pair().await;
+        let mut writer = connection.stream_writer().await.unwrap();
+        writer.headers(&request_headers(), false).await.unwrap();
+        writer.finish().await.unwrap();
+        writer.finish().await.unwrap();
+        writer.flush().await.unwrap();
+        let stream_id = writer.stream_id();
+
+        assert_eq!(read_frame(&mut peer).await.0, 0x1);
+        assert_eq!(read_frame(&mut peer).await, (0x0, 0x1, Vec::new()));
+        // A PING shows nothing else was queued behind the empty DATA.
+        connection.ping([7; 8]).await.unwrap();
+        assert_eq!(read_frame(&mut peer).await.0, 0x6);
+        assert!(connection.writer_for(stream_id).is_finished());
+    }
+
+    #[tokio::test]
+    async fn data_waits_for_the_peer_to_open_the_window() {
+        let (mut connection, mut peer) = open_pair().await;
+        connection.send_connection_window = 2;
+        let mut writer = connection.stream_writer().await.unwrap();
+        writer.headers(&request_headers(), false).await.unwrap();
+
+        let write = writer.data(Bytes::from_static(b"abcd"), true);
+        let peer_side = async {
+            assert_eq!(read_frame(&mut peer).await.0, 0x1);
+            assert_eq!(read_frame(&mut peer).await, (0x0, 0x0, b"ab".to_vec()));
+            let update = FrameH2::window_update(0, 2).unwrap().serialize().unwrap();
+            peer.write_all(&update).await.unwrap();
+            read_frame(&mut peer).await
+        };
+        let (written, last) = tokio::join!(write, peer_side);
+        written.unwrap();
+        assert_eq!(last, (0x0, 0x1, b"cd".to_vec()));
+        assert!(writer.is_finished());
+    }
+}
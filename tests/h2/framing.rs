@@ -0,0 +1,19 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_frame_types_parse_and_round_trip() -> Result<(), ProtocolError> {
+        let raw = [
+            0x00, 0x00, 0x02, 0x0b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x61, 0x62,
+        ];
+        let frame = FrameH2::parse(&raw)?;
+        assert!(matches!(
+            frame.frame_type,
+            FrameType::H2(FrameTypeH2::Unknown(0x0b))
+        ));
+        assert_eq!(&frame.payload[..], b"ab");
+        assert_eq!(&frame.serialize()?[..], &raw[..]);
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_match_checked_serialization() -> Result<(), ProtocolError> {
+        let frame = FrameH2::data(1, Bytes::from("abc"), true);
+        assert_eq!(frame.clone().unchecked().serialize(), frame.serialize()?);
+        Ok(())
+    }
+
+    #[test]
+    fn overrides_are_written_verbatim() {
+        let raw = FrameH2::data(1, Bytes::from("abc"), false)
+            .unchecked()
+            .length(0x01_0000)
+            .frame_type(0xfa)
+            .stream_id(2)
+            .reserved_bit(true)
+            .serialize();
+        assert_eq!(
+            &raw[..9],
+            &[0x01, 0x00, 0x00, 0xfa, 0x00, 0x80, 0x00, 0x00, 0x02]
+        );
+        assert_eq!(&raw[9..], b"abc");
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(fields: &[(&str, &str)]) -> Vec<Header> {
+        fields
+            .iter()
+            .map(|(name, value)| Header::new(name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn pairs(headers: &[Header]) -> Vec<(String, Option<String>)> {
+        headers
+            .iter()
+            .map(|h| (h.name.clone(), h.value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn the_static_codec_never_indexes() -> Result<(), ProtocolError> {
+        let fields = headers(&[(":method", "GET"), (":path", "/x"), ("x-custom", "v")]);
+
+        let block = StaticHpackCodec.encode(&fields)?;
+        let mut expected = vec![0x82, 0x04, 0x02, b'/', b'x', 0x00, 0x08];
+        expected.extend_from_slice(b"x-custom");
+        expected.extend_from_slice(&[0x01, b'v']);
+        assert_eq!(block.as_ref(), expected.as_slice());
+
+        // Encoding again gives the same block: nothing was added to a table.
+        assert_eq!(StaticHpackCodec.encode(&fields)?, block);
+        assert_eq!(pairs(&StaticHpackCodec.decode(&block)?), pairs(&fields));
+        Ok(())
+    }
+
+    #[test]
+    fn long_strings_carry_multi_byte_lengths() -> Result<(), ProtocolError> {
+        let value = "a".repeat(200);
+        let fields = headers(&[("x-long", &value)]);
+
+        let block = StaticHpackCodec.encode(&fields)?;
+        // 200 = 127 + 73 in the 7-bit prefix.
+        assert_eq!(&block[8..10], &[0x7f, 0x49]);
+        assert_eq!(block.len(), 10 + 200);
+        assert_eq!(pairs(&StaticHpackCodec.decode(&block)?), pairs(&fields));
+        Ok(())
+    }
+
+    #[test]
+    fn the_connection_codec_shares_its_table_across_blocks() -> Result<(), ProtocolError> {
+        let fields = headers(&[(":authority", "example.com"), ("x-custom", "v")]);
+        let mut client = HpackCodec::new(4096, 4096);
+        let mut server = HpackCodec::new(4096, 4096);
+
+        let first = client.encode(&fields)?;
+        let second = client.encode(&fields)?;
+        assert!(second.len() < first.len());
+        assert_eq!(pairs(&server.decode(&first)?), pairs(&fields));
+        assert_eq!(pairs(&server.decode(&second)?), pairs(&fields));
+
+        // Through the trait, as raw frame builders take it.
+        let codec: &mut dyn HeaderCodec = &mut client;
+        codec.set_decoder_max_table_size(0);
+        assert_eq!(client.decoder_max_table_size(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// h2c peer answering each request with an empty 200. After answering
+    /// stream `goaway_after` it sends GOAWAY with that stream as the last one
+    /// processed and ignores later requests.
+    async fn serve(mut socket: TcpStream, goaway_after: Option<u32>) {
+        socket
+            .write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0])
+            .await
+            .unwrap();
+        answer(socket, goaway_after).await;
+    }
+
+    /// Reads the client preface, then answers frames as [`serve`] does.
+    async fn answer(mut socket: TcpStream, goaway_after: Option<u32>) {
+        let mut preface = [0u8; 24];
+        socket.read_exact(&mut preface).await.unwrap();
+        let mut gone_away = false;
+        loop {
+            let mut header = [0u8; 9];
+            if socket.read_exact(&mut header).await.is_err() {
+                return;
+            }
+            let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+            let mut payload = vec![0u8; len];
+            socket.read_exact(&mut payload).await.unwrap();
+            let stream_id =
+                u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7FFF_FFFF;
+            match header[3] {
+                0x4 if header[4] & 0x1 == 0 => {
+                    socket
+                        .write_all(&[0, 0, 0, 4, 1, 0, 0, 0, 0])
+                        .await
+                        .unwrap();
+                }
+                0x1 if !gone_away => {
+                    // :status 200, END_STREAM | END_HEADERS.
+                    let mut response = vec![0, 0, 1, 1, 0x5];
+                    response.extend_from_slice(&stream_id.to_be_bytes());
+                    response.push(0x88);
+                    if goaway_after == Some(stream_id) {
+                        response.extend_from_slice(&[0, 0, 8, 7, 0, 0, 0, 0, 0]);
+                        response.extend_from_slice(&stream_id.to_be_bytes());
+                        response.extend_from_slice(&[0, 0, 0, 0]);
+                        gone_away = true;
+                    }
+                    socket.write_all(&response).await.unwrap();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_share_a_connection_until_goaway() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (first, _) = listener.accept().await.unwrap();
+            tokio::spawn(serve(first, Some(3)));
+            let (second, _) = listener.accept().await.unwrap();
+            serve(second, None).await;
+        });
+
+        let client = H2::timeouts(ClientTimeouts::disabled());
+        for _ in 0..3 {
+            let response = client
+                .send_request(Request::new(&url, "GET").unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status, 200);
+            assert_eq!(client.pool().len(), 1);
+        }
+
+        // The third request went out on stream 5, above the GOAWAY's last
+        // stream, and was sent again on a second connection.
+        let stats = client.pool().stats();
+        assert_eq!((stats.opened(), stats.reused()), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn a_connection_closed_while_parked_is_replaced() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (first, _) = listener.accept().await.unwrap();
+            // Answers for a while, then closes without GOAWAY.
+            tokio::spawn(tokio::time::timeout(
+                Duration::from_millis(200),
+                serve(first, None),
+            ));
+            let (second, _) = listener.accept().await.unwrap();
+            serve(second, None).await;
+        });
+
+        let client = H2::timeouts(ClientTimeouts::disabled());
+        let response = client
+            .send_request(Request::new(&url, "GET").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let response = client
+            .send_request(Request::new(&url, "GET").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        let stats = client.pool().stats();
+        assert_eq!((stats.opened(), stats.reused()), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn prior_knowledge_sends_the_preface_first() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            serve(socket, None).await;
+        });
+
+        let client = H2::timeouts(ClientTimeouts::disabled()).with_mode(H2Mode::PriorKnowledge);
+        let response = client
+            .send_request(Request::new(&url, "GET").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+    }
+
+    #[tokio::test]
+    async fn upgrade_sends_the_request_once_and_reads_stream_one() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/submit", listener.local_addr().unwrap());
+        let (seen_tx, seen_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                socket.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            let mut body = [0u8; 4];
+            socket.read_exact(&mut body).await.unwrap();
+            seen_tx
+                .send((String::from_utf8(head).unwrap(), body))
+                .unwrap();
+            socket
+                .write_all(b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n")
+                .await
+                .unwrap();
+            // SETTINGS, then :status 204 on stream 1 with END_STREAM | END_HEADERS.
+            socket
+                .write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0x5, 0, 0, 0, 1, 0x89])
+                .await
+                .unwrap();
+            answer(socket, None).await;
+        });
+
+        let client = H2::timeouts(ClientTimeouts::disabled()).with_mode(H2Mode::Upgrade);
+        let request = Request::new(&url, "POST").unwrap().body("data");
+        let response = client.send_request(request.clone()).await.unwrap();
+        assert_eq!(response.status, 204);
+
+        let (head, body) = seen_rx.await.unwrap();
+        assert!(head.starts_with("POST /submit HTTP/1.1\r\n"), "{}", head);
+        assert!(head.contains("Upgrade: h2c\r\n"), "{}", head);
+        assert_eq!(&body, b"data");
+
+        // The next request reuses the upgraded connection on stream 3.
+        let response = client.send_request(request).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(client.pool().stats().opened(), 1);
+    }
+
+    #[tokio::test]
+    async fn an_http1_reply_to_the_preface_falls_back_when_enabled() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut start = [0u8; 3];
+                socket.read_exact(&mut start).await.unwrap();
+                let reply: &[u8] = if &start == b"PRI" {
+                    b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n"
+                } else {
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"
+                };
+                socket.write_all(reply).await.unwrap();
+            }
+        });
+
+        let client = H2::timeouts(ClientTimeouts::disabled());
+        let refused = client
+            .send_request(Request::new(&url, "GET").unwrap())
+            .await;
+        match refused {
+            Err(ProtocolError::H2UnexpectedHttp1Response(raw)) => {
+                assert!(raw.starts_with(b"HTTP/1.1 400 "));
+            }
+            other => panic!("unexpected result: {:?}", other.map(|r| r.status)),
+        }
+
+        let client = client.with_http1_fallback(true);
+        let response = client
+            .send_request(Request::new(&url, "GET").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.protocol, "HTTP/1.1");
+        assert_eq!(response.body.as_ref(), b"ok");
+    }
+
+    #[test]
+    fn alpn_mismatches_fall_back_to_http1() {
+        let h1 = ProtocolError::AlpnMismatch {
+            negotiated: Some("http/1.1".to_string()),
+        };
+        assert!(refuses_h2(&h1));
+        let alert = std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            rustls::Error::AlertReceived(rustls::AlertDescription::NoApplicationProtocol),
+        );
+        let refused = ProtocolError::from_connect_error(alert);
+        assert!(matches!(
+            refused,
+            ProtocolError::AlpnMismatch { negotiated: None }
+        ));
+        assert!(refuses_h2(&refused));
+
+        // Errors that merely mention ALPN are not mistaken for a refusal.
+        let unreachable = ProtocolError::ConnectionFailed("via ALPN instead of h2".to_string());
+        assert!(!refuses_h2(&unreachable));
+    }
+
+    #[tokio::test]
+    async fn end_stream_after_holds_back_only_the_end_of_the_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let (frames_tx, mut frames_rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket
+                .write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            let mut preface = [0u8; 24];
+            socket.read_exact(&mut preface).await.unwrap();
+            loop {
+                let mut header = [0u8; 9];
+                if socket.read_exact(&mut header).await.is_err() {
+                    return;
+                }
+                let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+                let mut payload = vec![0u8; len];
+                socket.read_exact(&mut payload).await.unwrap();
+                let end_stream = header[4] & 0x1 != 0;
+                match header[3] {
+                    0x4 if !end_stream => {
+                        socket
+                            .write_all(&[0, 0, 0, 4, 1, 0, 0, 0, 0])
+                            .await
+                            .unwrap();
+                    }
+                    0x0 | 0x1 => {
+                        frames_tx
+                            .send((header[3], end_stream, len, Instant::now()))
+                            .unwrap();
+                        if end_stream {
+                            // :status 200 on stream 1, END_STREAM | END_HEADERS.
+                            socket
+                                .write_all(&[0, 0, 1, 1, 0x5, 0, 0, 0, 1, 0x88])
+                                .await
+                                .unwrap();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        let hold = Duration::from_millis(100);
+        let request = Request::new(&url, "POST")
+            .unwrap()
+            .body("data")
+            .end_stream_mode(EndStreamMode::After(hold));
+        let client = H2::timeouts(ClientTimeouts::disabled()).with_mode(H2Mode::PriorKnowledge);
+        let response = client.send_request(request).await.unwrap();
+        assert_eq!(response.status, 200);
+
+        let mut frames = Vec::new();
+        while let Ok(frame) = frames_rx.try_recv() {
+            frames.push(frame);
+        }
+        // HEADERS and the body at once, then END_STREAM on an empty DATA.
+        let kinds: Vec<_> = frames
+            .iter()
+            .map(|(frame_type, end_stream, len, _)| (*frame_type, *end_stream, *len))
+            .collect();
+        assert_eq!(kinds[0].0, 0x1);
+        assert!(!kinds[0].1);
+        assert_eq!(&kinds[1..], &[(0x0, false, 4), (0x0, true, 0)]);
+        assert!(frames[1].3 - frames[0].3 < hold);
+        assert!(frames[2].3 - frames[1].3 >= hold / 2);
+    }
+}
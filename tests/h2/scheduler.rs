@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(scheduler: &mut DataScheduler, window: i32, max_frame: usize) -> Vec<(u32, usize)> {
+        let mut order = Vec::new();
+        while let Some(frame) = scheduler.next_frame(window, |_| window, max_frame) {
+            order.push((frame.stream_id, frame.payload.len()));
+        }
+        order
+    }
+
+    #[test]
+    fn interleaves_streams_round_robin() {
+        let mut scheduler = DataScheduler::new();
+        scheduler.push(1, Bytes::from(vec![0u8; 30]), true);
+        scheduler.push(3, Bytes::from(vec![0u8; 20]), true);
+
+        let order = drain(&mut scheduler, 1_000, 10);
+        assert_eq!(order, vec![(1, 10), (3, 10), (1, 10), (3, 10), (1, 10)]);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn weight_grants_consecutive_frames() {
+        let mut scheduler = DataScheduler::new();
+        scheduler.push(1, Bytes::from(vec![0u8; 40]), true);
+        scheduler.push(3, Bytes::from(vec![0u8; 20]), true);
+        scheduler.set_weight(1, 2);
+
+        let streams: Vec<u32> = drain(&mut scheduler, 1_000, 10)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(streams, vec![1, 1, 3, 1, 1, 3]);
+    }
+
+    #[test]
+    fn weight_outlives_the_queue_until_the_stream_ends() {
+        let mut scheduler = DataScheduler::new();
+        scheduler.set_weight(1, 2);
+        scheduler.push(1, Bytes::from(vec![0u8; 10]), false);
+        assert_eq!(drain(&mut scheduler, 1_000, 10), vec![(1, 10)]);
+
+        // The next chunk of the body is queued with the same weight.
+        scheduler.push(1, Bytes::from(vec![0u8; 40]), true);
+        scheduler.push(3, Bytes::from(vec![0u8; 20]), true);
+        let streams: Vec<u32> = drain(&mut scheduler, 1_000, 10)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(streams, vec![1, 1, 3, 1, 1, 3]);
+        assert_eq!(scheduler.weight(1), DEFAULT_STREAM_WEIGHT);
+    }
+
+    #[test]
+    fn blocked_streams_are_skipped() {
+        let mut scheduler = DataScheduler::new();
+        scheduler.push(1, Bytes::from_static(b"blocked"), true);
+        scheduler.push(3, Bytes::from_static(b"open"), true);
+
+        let frame = scheduler
+            .next_frame(100, |id| if id == 1 { 0 } else { 100 }, 16_384)
+            .expect("stream 3 has window");
+        assert_eq!(frame.stream_id, 3);
+        assert!(frame.end_stream);
+        assert!(scheduler.next_frame(100, |_| 0, 16_384).is_none());
+    }
+}
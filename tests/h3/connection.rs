@@ -0,0 +1,232 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::StreamWriter;
+    use crate::utils::base64_decode;
+    use quinn::crypto::rustls::QuicServerConfig;
+    use quinn::{Endpoint, ServerConfig};
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use tokio::task::JoinHandle;
+
+    /// Self-signed P-256 certificate for `localhost`, DER.
+    const CERT: &str = "MIIBfzCCASWgAwIBAgIUQ38cUCHyF11xYfkYjEl07/Ptb/kwCgYIKoZIzj0EAwIwFDESMBAGA1UEAwwJbG9jYWxob3N0MCAXDTI2MTAxNTAzMDUxNVoYDzIxMjYwOTIxMDMwNTE1WjAUMRIwEAYDVQQDDAlsb2NhbGhvc3QwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARkc7iPtWI8Wx3Z0anfKhmNcJfrb03fxwtqyhp6+iux8J5vlCd6vo0bO85x0a/lZcjKn9ppEWb8I2/PSlJ4Ip4Fo1MwUTAdBgNVHQ4EFgQUCiCNbGBsozzzs5zLsFK9s8FHedwwHwYDVR0jBBgwFoAUCiCNbGBsozzzs5zLsFK9s8FHedwwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiEAtIrQB9X+poV2ojrLcrTxaDnyRCtbdX7uNkyCVEB/ghUCIE3Bl2GxMPDrwPg06Bi7I/isbQHNFWCKyp8lNKSk/+jq";
+    /// PKCS#8 key for [`CERT`], DER.
+    const KEY: &str = "MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgaoim1TxCFpy9OO1RiRpqSq5maeuToYLn6ii8egUWYrihRANCAARkc7iPtWI8Wx3Z0anfKhmNcJfrb03fxwtqyhp6+iux8J5vlCd6vo0bO85x0a/lZcjKn9ppEWb8I2/PSlJ4Ip4F";
+
+    fn server_config() -> ServerConfig {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let cert = CertificateDer::from(base64_decode(CERT).unwrap());
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(base64_decode(KEY).unwrap()));
+        let mut tls = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert], key)
+            .unwrap();
+        tls.alpn_protocols = vec![b"h3".to_vec()];
+        ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls).unwrap()))
+    }
+
+    /// A one-connection HTTP/3 server that answers the first request stream,
+    /// if one is opened, with an empty 200, then sends GOAWAY with stream ID
+    /// 4. Returns the URL to reach it; the server task yields the bytes sent
+    /// on the request stream.
+    fn serve() -> (String, JoinHandle<Vec<u8>>) {
+        let endpoint = Endpoint::server(server_config(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let url = format!("https://{}/", endpoint.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let connection = endpoint.accept().await.unwrap().await.unwrap();
+            let mut control = connection.open_uni().await.unwrap();
+            // Control stream type, then an empty SETTINGS frame.
+            control.write_all(&[0x00, 0x04, 0x00]).await.unwrap();
+            let mut request = Vec::new();
+            if let Ok((mut send, mut recv)) = connection.accept_bi().await {
+                request = recv.read_to_end(1024).await.unwrap();
+                // HEADERS with `:status 200`.
+                send.write_all(&[0x01, 0x03, 0x00, 0x00, 0xd9])
+                    .await
+                    .unwrap();
+                send.finish().unwrap();
+                control.write_all(&[0x07, 0x01, 0x04]).await.unwrap();
+            }
+            connection.closed().await;
+            request
+        });
+        (url, server)
+    }
+
+    fn request_headers() -> Vec<Header> {
+        [
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":authority", "localhost"),
+            (":path", "/"),
+        ]
+        .iter()
+        .map(|(name, value)| Header::new(name.to_string(), value.to_string()))
+        .collect()
+    }
+
+    /// The next event other than the SETTINGS exchange, if one is queued.
+    fn next_event(
+        events: &mut mpsc::UnboundedReceiver<ConnectionEvent>,
+    ) -> Option<ConnectionEvent> {
+        loop {
+            match events.try_recv().ok()? {
+                ConnectionEvent::SettingsReceived { .. } => continue,
+                event => return Some(event),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_lifecycle_and_goaway_are_reported_as_events() {
+        let (url, server) = serve();
+        let mut connection = H3Connection::connect_with_timeouts(&url, ClientTimeouts::disabled())
+            .await
+            .unwrap();
+        let mut events = connection.subscribe_events();
+
+        let mut writer = connection.stream_writer().await.unwrap();
+        let stream_id = writer.stream_id();
+        writer.headers(&request_headers(), true).await.unwrap();
+        let response = writer.read_response().await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            next_event(&mut events),
+            Some(ConnectionEvent::StreamOpened { stream_id })
+        );
+        assert_eq!(
+            next_event(&mut events),
+            Some(ConnectionEvent::StreamClosed { stream_id })
+        );
+
+        // The GOAWAY follows the response on the control stream.
+        let mut goaway = None;
+        for _ in 0..100 {
+            connection.poll_control().await.unwrap();
+            goaway = next_event(&mut events);
+            if goaway.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            goaway,
+            Some(ConnectionEvent::GoAwayReceived {
+                last_stream_id: 4,
+                error_code: None,
+                debug_data: Bytes::new(),
+            })
+        );
+        assert_eq!(next_event(&mut events), Some(ConnectionEvent::Closed));
+
+        drop(connection);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn push_frames_are_only_taken_on_their_own_streams() {
+        let (url, server) = serve();
+        let mut connection = H3Connection::connect_with_timeouts(&url, ClientTimeouts::disabled())
+            .await
+            .unwrap();
+        connection.pushes.max_push_id = Some(0);
+
+        // CANCEL_PUSH for push ID 0.
+        let cancel = FrameH3::new(FrameTypeH3::CancelPush, 0, Bytes::from_static(&[0x00]));
+        assert!(matches!(
+            connection.handle_frame(&cancel).await,
+            Err(ProtocolError::H3ConnectionError(_))
+        ));
+        connection.handle_control_frame(&cancel).await.unwrap();
+        assert!(connection.pushes.cancelled.contains(&0));
+
+        // PUSH_PROMISE for push ID 0 with an empty field section.
+        let promise = FrameH3::new(
+            FrameTypeH3::PushPromise,
+            0,
+            Bytes::from_static(&[0x00, 0x00, 0x00]),
+        );
+        assert!(matches!(
+            connection.handle_control_frame(&promise).await,
+            Err(ProtocolError::H3ConnectionError(_))
+        ));
+        connection.handle_frame(&promise).await.unwrap();
+        assert_eq!(connection.pushes.promised.get(&0), Some(&0));
+
+        drop(connection);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_writer_sends_one_frame_per_call() {
+        let (url, server) = serve();
+        let mut connection = H3Connection::connect_with_timeouts(&url, ClientTimeouts::disabled())
+            .await
+            .unwrap();
+
+        let mut writer = connection.stream_writer().await.unwrap();
+        writer.headers(&request_headers(), false).await.unwrap();
+        writer
+            .data(Bytes::from_static(b"abc"), false)
+            .await
+            .unwrap();
+        writer.data(Bytes::new(), false).await.unwrap();
+        assert!(!writer.is_finished());
+        let trailers = [Header::new("x-sum".to_string(), "3".to_string())];
+        writer.trailers(&trailers).await.unwrap();
+        assert!(writer.is_finished());
+        writer.finish().await.unwrap();
+        assert_eq!(writer.read_response().await.unwrap().status, 200);
+
+        drop(connection);
+        let request = server.await.unwrap();
+        // Frame types and payloads; every length here fits one byte.
+        let mut frames = Vec::new();
+        let mut rest = request.as_slice();
+        while !rest.is_empty() {
+            let len = rest[1] as usize;
+            frames.push((rest[0], rest[2..2 + len].to_vec()));
+            rest = &rest[2 + len..];
+        }
+        let types: Vec<u8> = frames.iter().map(|(frame_type, _)| *frame_type).collect();
+        assert_eq!(types, vec![0x01, 0x00, 0x01]);
+        assert_eq!(frames[1].1, b"abc");
+    }
+
+    #[tokio::test]
+    async fn mirrored_bytes_carry_their_stream() {
+        let (url, server) = serve();
+        let (sender, mut chunks) = mpsc::unbounded_channel();
+        let mut connection =
+            H3Connection::connect_mirrored(&url, ClientTimeouts::disabled(), Box::new(sender))
+                .await
+                .unwrap();
+
+        let mut writer = connection.stream_writer().await.unwrap();
+        writer.headers(&request_headers(), true).await.unwrap();
+        assert_eq!(writer.read_response().await.unwrap().status, 200);
+        drop(connection);
+        let request = server.await.unwrap();
+
+        let mut mirrored = Vec::new();
+        while let Ok(chunk) = chunks.try_recv() {
+            mirrored.push(chunk);
+        }
+        let on_stream = |direction, stream_id| {
+            mirrored
+                .iter()
+                .filter(|chunk| chunk.direction == direction && chunk.stream_id == Some(stream_id))
+                .flat_map(|chunk| chunk.data.to_vec())
+                .collect::<Vec<u8>>()
+        };
+        // Our control stream is the first unidirectional one we open, the
+        // peer's the first it opens; the request went out on stream 0.
+        assert!(on_stream(WireDirection::Sent, 2).starts_with(&[0x00, 0x04]));
+        assert!(on_stream(WireDirection::Received, 3).starts_with(&[0x00, 0x04, 0x00]));
+        assert_eq!(on_stream(WireDirection::Sent, 0), request);
+        assert_eq!(
+            on_stream(WireDirection::Received, 0),
+            [0x01, 0x03, 0x00, 0x00, 0xd9]
+        );
+    }
+}
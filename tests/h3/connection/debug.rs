@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_names_settings_and_lists_pushes() {
+        let state = H3DebugState {
+            state: ConnectionState::Closed,
+            local_settings: BTreeMap::from([(SETTINGS_QPACK_MAX_TABLE_CAPACITY, 0)]),
+            remote_settings: BTreeMap::from([(SETTINGS_QPACK_MAX_TABLE_CAPACITY, 4096), (0x21, 7)]),
+            next_stream_id: 8,
+            streams: BTreeMap::from([(
+                4,
+                H3StreamDebugState {
+                    state: StreamState::HalfClosedLocal,
+                    buffered_bytes: 12,
+                },
+            )]),
+            goaway_sent: None,
+            goaway_received: Some(4),
+            qpack_encoder_capacity: 4096,
+            qpack_decoder_capacity: 0,
+            max_push_id: Some(2),
+            promised_pushes: BTreeMap::from([(1, 4)]),
+            cancelled_pushes: BTreeSet::from([0]),
+        };
+
+        let json = state.to_json();
+        assert_eq!(json["protocol"], "h3");
+        assert_eq!(json["settings"]["remote"]["QPACK_MAX_TABLE_CAPACITY"], 4096);
+        assert_eq!(json["settings"]["remote"]["0x21"], 7);
+        assert_eq!(json["streams"]["4"]["buffered_bytes"], 12);
+        assert_eq!(json["goaway"]["sent"], Value::Null);
+        assert_eq!(json["goaway"]["received"], 4);
+        assert_eq!(json["qpack"]["encoder_capacity"], 4096);
+        assert_eq!(json["pushes"]["promised"]["1"], 4);
+        assert_eq!(json["pushes"]["cancelled"][0], 0);
+    }
+}
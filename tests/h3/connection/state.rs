@@ -0,0 +1,15 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pushes_are_refused_until_max_push_id_is_sent() {
+        let mut pushes = PushState::default();
+        assert!(!pushes.allows(0));
+
+        pushes.max_push_id = Some(3);
+        assert!(pushes.allows(0));
+        assert!(pushes.allows(3));
+        assert!(!pushes.allows(4));
+    }
+}
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h3::protocol::H3;
+    use crate::types::Request;
+    use crate::utils::base64_decode;
+    use quinn::crypto::rustls::QuicServerConfig;
+    use quinn::{Endpoint, ServerConfig};
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use std::sync::Arc;
+    use tokio::task::JoinHandle;
+
+    /// Self-signed P-256 certificate for `localhost`, DER.
+    const CERT: &str = "MIIBfzCCASWgAwIBAgIUQ38cUCHyF11xYfkYjEl07/Ptb/kwCgYIKoZIzj0EAwIwFDESMBAGA1UEAwwJbG9jYWxob3N0MCAXDTI2MTAxNTAzMDUxNVoYDzIxMjYwOTIxMDMwNTE1WjAUMRIwEAYDVQQDDAlsb2NhbGhvc3QwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARkc7iPtWI8Wx3Z0anfKhmNcJfrb03fxwtqyhp6+iux8J5vlCd6vo0bO85x0a/lZcjKn9ppEWb8I2/PSlJ4Ip4Fo1MwUTAdBgNVHQ4EFgQUCiCNbGBsozzzs5zLsFK9s8FHedwwHwYDVR0jBBgwFoAUCiCNbGBsozzzs5zLsFK9s8FHedwwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiEAtIrQB9X+poV2ojrLcrTxaDnyRCtbdX7uNkyCVEB/ghUCIE3Bl2GxMPDrwPg06Bi7I/isbQHNFWCKyp8lNKSk/+jq";
+    /// PKCS#8 key for [`CERT`], DER.
+    const KEY: &str = "MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgaoim1TxCFpy9OO1RiRpqSq5maeuToYLn6ii8egUWYrihRANCAARkc7iPtWI8Wx3Z0anfKhmNcJfrb03fxwtqyhp6+iux8J5vlCd6vo0bO85x0a/lZcjKn9ppEWb8I2/PSlJ4Ip4F";
+
+    /// `:status 200`, DATA "abc" and "de", then trailers `x-sum: 5`.
+    const RESPONSE: &[u8] = b"\x01\x03\x00\x00\xd9\
+        \x00\x03abc\
+        \x00\x02de\
+        \x01\x0a\x00\x00\x25x-sum\x015";
+
+    fn server_config() -> ServerConfig {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let cert = CertificateDer::from(base64_decode(CERT).unwrap());
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(base64_decode(KEY).unwrap()));
+        let mut tls = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert], key)
+            .unwrap();
+        tls.alpn_protocols = vec![b"h3".to_vec()];
+        ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls).unwrap()))
+    }
+
+    /// A one-connection HTTP/3 server that answers the first request stream
+    /// with `RESPONSE`. Returns the URL to reach it.
+    fn serve() -> (String, JoinHandle<()>) {
+        let endpoint = Endpoint::server(server_config(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let url = format!("https://{}/", endpoint.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let connection = endpoint.accept().await.unwrap().await.unwrap();
+            let mut control = connection.open_uni().await.unwrap();
+            // Control stream type, then an empty SETTINGS frame.
+            control.write_all(&[0x00, 0x04, 0x00]).await.unwrap();
+            let (mut send, _recv) = connection.accept_bi().await.unwrap();
+            send.write_all(RESPONSE).await.unwrap();
+            send.finish().unwrap();
+            connection.closed().await;
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn data_frames_come_out_as_chunks() {
+        let (url, server) = serve();
+        let mut connection = H3Connection::connect_with_timeouts(&url, ClientTimeouts::disabled())
+            .await
+            .unwrap();
+        let (stream_id, mut send) = connection.create_request_stream().await.unwrap();
+        // The server answers whatever arrives on the stream.
+        send.write_all(&[0x01, 0x00]).await.unwrap();
+
+        let mut response = connection.read_response_streaming(stream_id).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body.next_chunk().await.unwrap().unwrap(), "abc");
+        assert_eq!(response.body.next_chunk().await.unwrap().unwrap(), "de");
+        assert!(response.body.next_chunk().await.is_none());
+        assert_eq!(response.body.trailers().unwrap()[0].name, "x-sum");
+        drop(response);
+
+        drop(connection);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_protocol_client_streams_on_a_connection_of_its_own() {
+        let (url, server) = serve();
+
+        let request = Request::new(&url, "GET").unwrap();
+        let response = H3::new().send_request_streaming(request).await.unwrap();
+        let response = response.buffer().await.unwrap();
+        assert_eq!(response.body.as_ref(), b"abcde");
+        assert_eq!(response.trailers.unwrap()[0].name, "x-sum");
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,29 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[tokio::test]
+    async fn connections_from_one_bind_address_share_an_endpoint() -> io::Result<()> {
+        let pool = EndpointPool::new();
+        let loopback = SocketOptions::new().local_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let a: SocketAddr = "127.0.0.1:443".parse().unwrap();
+        let b: SocketAddr = "127.0.0.2:8443".parse().unwrap();
+
+        let first = pool.endpoint(&a, &loopback)?;
+        let second = pool.endpoint(&b, &loopback)?;
+        assert_eq!(first.local_addr()?, second.local_addr()?);
+        assert_eq!(pool.len(), 1);
+
+        let reusing = loopback.reuse_address(true);
+        let third = pool.endpoint(&a, &reusing)?;
+        assert_ne!(first.local_addr()?, third.local_addr()?);
+        assert_eq!(pool.len(), 2);
+
+        pool.evict(&a, &loopback);
+        assert_eq!(pool.len(), 1);
+        pool.clear();
+        assert!(pool.is_empty());
+        Ok(())
+    }
+}
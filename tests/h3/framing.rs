@@ -0,0 +1,21 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priority_update_round_trips() -> Result<(), ProtocolError> {
+        let frame = FrameH3::priority_update(4, Priority::new(1).incremental(true));
+        let raw = frame.serialize()?;
+        // Type 0xf0700 as a four-byte varint, length 7, element ID 4.
+        assert_eq!(&raw[..6], &[0x80, 0x0f, 0x07, 0x00, 0x07, 0x04]);
+
+        let (parsed, consumed) = FrameH3::parse(&raw)?;
+        assert_eq!(consumed, raw.len());
+        assert!(matches!(
+            parsed.frame_type,
+            FrameType::H3(FrameTypeH3::PriorityUpdate)
+        ));
+        assert_eq!(&parsed.payload[1..], b"u=1, i");
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::base64_decode;
+    use quinn::crypto::rustls::QuicServerConfig;
+    use quinn::{Connection, Endpoint, ServerConfig, VarInt};
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use std::time::Duration;
+
+    /// Self-signed P-256 certificate for `localhost`, DER.
+    const CERT: &str = "MIIBfzCCASWgAwIBAgIUQ38cUCHyF11xYfkYjEl07/Ptb/kwCgYIKoZIzj0EAwIwFDESMBAGA1UEAwwJbG9jYWxob3N0MCAXDTI2MTAxNTAzMDUxNVoYDzIxMjYwOTIxMDMwNTE1WjAUMRIwEAYDVQQDDAlsb2NhbGhvc3QwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAARkc7iPtWI8Wx3Z0anfKhmNcJfrb03fxwtqyhp6+iux8J5vlCd6vo0bO85x0a/lZcjKn9ppEWb8I2/PSlJ4Ip4Fo1MwUTAdBgNVHQ4EFgQUCiCNbGBsozzzs5zLsFK9s8FHedwwHwYDVR0jBBgwFoAUCiCNbGBsozzzs5zLsFK9s8FHedwwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiEAtIrQB9X+poV2ojrLcrTxaDnyRCtbdX7uNkyCVEB/ghUCIE3Bl2GxMPDrwPg06Bi7I/isbQHNFWCKyp8lNKSk/+jq";
+    /// PKCS#8 key for [`CERT`], DER.
+    const KEY: &str = "MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgaoim1TxCFpy9OO1RiRpqSq5maeuToYLn6ii8egUWYrihRANCAARkc7iPtWI8Wx3Z0anfKhmNcJfrb03fxwtqyhp6+iux8J5vlCd6vo0bO85x0a/lZcjKn9ppEWb8I2/PSlJ4Ip4F";
+    /// H3_REQUEST_REJECTED.
+    const REQUEST_REJECTED: u32 = 0x10b;
+
+    fn server_config() -> ServerConfig {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let cert = CertificateDer::from(base64_decode(CERT).unwrap());
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(base64_decode(KEY).unwrap()));
+        let mut tls = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert], key)
+            .unwrap();
+        tls.alpn_protocols = vec![b"h3".to_vec()];
+        ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls).unwrap()))
+    }
+
+    /// HTTP/3 peer answering each request stream with an empty 200. When
+    /// stream `goaway_at` opens it sends GOAWAY with that stream ID, the
+    /// first one left unprocessed, and rejects the stream.
+    async fn serve(connection: Connection, goaway_at: Option<u64>) {
+        let mut control = connection.open_uni().await.unwrap();
+        // Control stream type, then an empty SETTINGS frame.
+        control.write_all(&[0x00, 0x04, 0x00]).await.unwrap();
+        while let Ok((mut send, _recv)) = connection.accept_bi().await {
+            let stream_id = u64::from(send.id());
+            if goaway_at == Some(stream_id) {
+                control
+                    .write_all(&[0x07, 0x01, stream_id as u8])
+                    .await
+                    .unwrap();
+                // Lets the GOAWAY land before the reset does.
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                send.reset(VarInt::from_u32(REQUEST_REJECTED)).unwrap();
+                continue;
+            }
+            // HEADERS with `:status 200`.
+            send.write_all(&[0x01, 0x03, 0x00, 0x00, 0xd9])
+                .await
+                .unwrap();
+            send.finish().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_share_a_connection_until_goaway() {
+        let endpoint = Endpoint::server(server_config(), "127.0.0.1:0".parse().unwrap()).unwrap();
+        let url = format!("https://{}/", endpoint.local_addr().unwrap());
+        tokio::spawn(async move {
+            let first = endpoint.accept().await.unwrap().await.unwrap();
+            tokio::spawn(serve(first, Some(8)));
+            let second = endpoint.accept().await.unwrap().await.unwrap();
+            serve(second, None).await;
+        });
+
+        let client = H3::timeouts(ClientTimeouts::disabled());
+        for _ in 0..3 {
+            let response = client
+                .send_request(Request::new(&url, "GET").unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status, 200);
+            assert_eq!(client.pool().len(), 1);
+        }
+
+        // The third request went out on stream 8, which the GOAWAY left
+        // unprocessed, and was sent again on a second connection.
+        let stats = client.pool().stats();
+        assert_eq!((stats.opened(), stats.reused()), (2, 2));
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_are_recorded_in_order() {
+        let tracker = HandshakeTracker::new();
+        assert_eq!(tracker.phase(), None);
+        assert!(tracker.time_in_phase().is_none());
+
+        tracker.enter(HandshakePhase::Dns);
+        tracker.enter(HandshakePhase::Tcp);
+        tracker.enter(HandshakePhase::Tcp);
+        tracker.enter(HandshakePhase::Tls);
+        assert_eq!(tracker.phase(), Some(HandshakePhase::Tls));
+        assert!(!tracker.is_ready());
+
+        let phases: Vec<HandshakePhase> = tracker
+            .transitions()
+            .iter()
+            .map(|transition| transition.phase)
+            .collect();
+        assert_eq!(
+            phases,
+            vec![
+                HandshakePhase::Dns,
+                HandshakePhase::Tcp,
+                HandshakePhase::Tls
+            ]
+        );
+        assert!(tracker.to_string().ends_with("(current)"));
+        assert!(tracker.to_string().starts_with("dns "));
+    }
+
+    #[test]
+    fn ready_ends_the_timeline() {
+        let tracker = HandshakeTracker::new();
+        tracker.enter(HandshakePhase::Quic);
+        tracker.enter(HandshakePhase::Settings);
+        tracker.enter(HandshakePhase::Ready);
+        assert!(tracker.is_ready());
+
+        let durations = tracker.durations();
+        let phases: Vec<HandshakePhase> = durations.iter().map(|(phase, _)| *phase).collect();
+        assert_eq!(phases, vec![HandshakePhase::Quic, HandshakePhase::Settings]);
+        assert!(tracker.to_string().ends_with(", ready"));
+    }
+
+    #[test]
+    fn clones_and_subscribers_see_the_same_handshake() {
+        let tracker = HandshakeTracker::new();
+        let observer = tracker.clone();
+        let mut changes = tracker.subscribe();
+        assert!(!changes.has_changed().unwrap());
+
+        tracker.enter(HandshakePhase::Preface);
+        assert_eq!(observer.phase(), Some(HandshakePhase::Preface));
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(*changes.borrow_and_update(), Some(HandshakePhase::Preface));
+    }
+}
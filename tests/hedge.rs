@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers with `status` after `delay`, or fails when `status` is 0.
+    struct Delayed {
+        delay: Duration,
+        status: u16,
+    }
+
+    #[async_trait(?Send)]
+    impl Protocol for Delayed {
+        async fn execute(&self, _request: &Request) -> Result<Response, ProtocolError> {
+            sleep(self.delay).await;
+            if self.status == 0 {
+                return Err(ProtocolError::ConnectionFailed("refused".to_string()));
+            }
+            Ok(Response {
+                status: self.status,
+                ..Response::default()
+            })
+        }
+    }
+
+    fn delayed(millis: u64, status: u16) -> Delayed {
+        Delayed {
+            delay: Duration::from_millis(millis),
+            status,
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_primary_loses_to_the_hedge() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com/", "GET")?;
+        let hedge = Hedge::new(
+            delayed(500, 200),
+            delayed(10, 203),
+            Duration::from_millis(20),
+        );
+        let raced = hedge.race(&request).await?;
+        assert_eq!(raced.winner, HedgeWinner::Secondary);
+        assert_eq!(raced.response.status, 203);
+        assert!(raced.hedged);
+
+        let fast = Hedge::new(delayed(1, 200), delayed(1, 203), Duration::from_millis(200));
+        let raced = fast.race(&request).await?;
+        assert_eq!(raced.winner, HedgeWinner::Primary);
+        assert!(!raced.hedged);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failures_fall_through_and_post_is_not_hedged() -> Result<(), ProtocolError> {
+        let get = Request::new("https://example.com/", "GET")?;
+        let hedge = Hedge::new(delayed(1, 0), delayed(1, 204), Duration::from_secs(5));
+        assert_eq!(hedge.race(&get).await?.winner, HedgeWinner::Secondary);
+
+        let post = Request::new("https://example.com/", "POST")?;
+        let both_fail = Hedge::new(delayed(1, 0), delayed(1, 204), Duration::ZERO);
+        assert!(both_fail.race(&post).await.is_err());
+        assert!(is_idempotent("put") && !is_idempotent("PATCH"));
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_encodings() {
+        assert_eq!(PathEncoding::PercentEncode.apply("/ab"), "/%61%62");
+        assert_eq!(PathEncoding::DoublePercentEncode.apply("/a"), "/%2561");
+        assert_eq!(PathEncoding::PercentEncode.apply("/a%2Fb%zz"), "/%61%2F%62%%7A%7A");
+        assert_eq!(PathEncoding::DoublePercentEncode.apply("/a%2f"), "/%2561%2f");
+        assert_eq!(PathEncoding::CaseSwap.apply("/Admin"), "/aDMIN");
+        assert_eq!(PathEncoding::DoubleSlash.apply("/admin"), "//admin");
+        assert_eq!(PathEncoding::TrailingSlash.apply("/admin/"), "/admin/");
+    }
+
+    #[test]
+    fn each_mutation_changes_only_its_axis() -> Result<(), ProtocolError> {
+        let base =
+            Request::new("https://example.com/admin", "GET")?.header("Authorization: Bearer §");
+        let mutated: Vec<MutatedRequest> = Mutator::new(base)
+            .methods(["POST"])
+            .header_values("authorization", ["x' or 1=1"])
+            .path_suffixes([";"])
+            .protocols([HttpProtocol::Http2])
+            .into_iter()
+            .collect();
+        assert_eq!(mutated.len(), 4);
+
+        assert_eq!(mutated[0].request.method, "POST");
+        assert_eq!(mutated[0].request.target.path(), "/admin");
+
+        let auth = &mutated[1].request.headers[0];
+        assert_eq!(auth.value.as_deref(), Some("Bearer x' or 1=1"));
+        assert_eq!(mutated[1].request.method, "GET");
+
+        assert_eq!(mutated[2].request.target.path(), "/admin;");
+
+        assert_eq!(mutated[3].protocol, Some(HttpProtocol::Http2));
+        assert_eq!(mutated[3].request.target.path(), "/admin");
+        Ok(())
+    }
+}
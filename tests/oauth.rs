@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_response(status: u16, body: &str) -> Response {
+        Response {
+            status,
+            body: body.to_string().into(),
+            ..Response::default()
+        }
+    }
+
+    #[test]
+    fn token_response_is_parsed_and_cached_expiry_applies() -> Result<(), ProtocolError> {
+        let response = json_response(
+            200,
+            r#"{"access_token":"abc","token_type":"bearer","expires_in":"60","refresh_token":"r1"}"#,
+        );
+        let token = parse_token_response(&response)?;
+        assert_eq!(token.authorization(), "Bearer abc");
+        assert_eq!(token.refresh_token.as_deref(), Some("r1"));
+        assert!(!token.expires_within(Duration::from_secs(30)));
+        assert!(token.expires_within(Duration::from_secs(90)));
+        Ok(())
+    }
+
+    #[test]
+    fn token_error_response_reports_oauth_error() {
+        let response = json_response(
+            400,
+            r#"{"error":"invalid_client","error_description":"bad secret"}"#,
+        );
+        match parse_token_response(&response) {
+            Err(ProtocolError::RequestFailed(message)) => {
+                assert!(
+                    message.contains("invalid_client: bad secret"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("unexpected result: {:?}", other.map(|t| t.token_type)),
+        }
+    }
+
+    #[test]
+    fn client_credentials_use_basic_auth_by_default() -> Result<(), ProtocolError> {
+        let client = OAuthClient::new("https://auth.example.com/token", "my client")
+            .client_secret("s3cr:t")
+            .scope("read write");
+        let request =
+            client.token_request(vec![("grant_type", "client_credentials".to_string())])?;
+
+        let authorization = request
+            .headers
+            .iter()
+            .find(|h| h.name == AUTHORIZATION_HEADER)
+            .and_then(|h| h.value.clone());
+        assert_eq!(
+            authorization.as_deref(),
+            Some(format!("Basic {}", base64_encode(b"my%20client:s3cr%3At")).as_str())
+        );
+        assert_eq!(
+            request.body.as_deref(),
+            Some(&b"grant_type=client_credentials"[..])
+        );
+
+        let public = OAuthClient::new("https://auth.example.com/token", "app")
+            .token_request(vec![("grant_type", "refresh_token".to_string())])?;
+        assert!(public
+            .headers
+            .iter()
+            .all(|h| h.name != AUTHORIZATION_HEADER));
+        assert_eq!(
+            public.body.as_deref(),
+            Some(&b"grant_type=refresh_token&client_id=app"[..])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_delay_seconds_and_http_dates() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-5"), None);
+    }
+
+    #[test]
+    fn backoff_doubles_without_retry_after_and_is_capped() {
+        let pacer = Pacer::new(
+            PacingPolicy::default()
+                .base_backoff(Duration::from_secs(1))
+                .max_backoff(Duration::from_secs(3)),
+        );
+        let throttled = Response::new(429, &[], "");
+        assert_eq!(
+            pacer.observe("Example.com", &throttled),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            pacer.observe("example.com", &throttled),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            pacer.observe("example.com", &throttled),
+            Some(Duration::from_secs(3))
+        );
+        assert!(pacer.delay("example.com").is_some());
+        assert_eq!(pacer.state()["example.com"].consecutive, 3);
+
+        assert_eq!(
+            pacer.observe("example.com", &Response::new(200, &[], "")),
+            None
+        );
+        assert_eq!(pacer.delay("example.com"), None);
+        assert!(pacer.state().is_empty());
+    }
+
+    #[test]
+    fn retry_after_takes_precedence_unless_disabled() {
+        let throttled = Response::new(503, &[("Retry-After", "7")], "");
+        let pacer = Pacer::default();
+        assert_eq!(
+            pacer.observe("example.com", &throttled),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            pacer.state()["example.com"].retry_after,
+            Some(Duration::from_secs(7))
+        );
+
+        let pacer = Pacer::new(PacingPolicy::default().respect_retry_after(false));
+        assert_eq!(
+            pacer.observe("example.com", &throttled),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            pacer.observe("example.com", &Response::new(500, &[], "")),
+            None
+        );
+    }
+
+    #[test]
+    fn only_idempotent_methods_are_retried_by_default() {
+        let policy = PacingPolicy::default();
+        assert!(policy.retries("GET"));
+        assert!(policy.retries("put"));
+        assert!(!policy.retries("POST"));
+        assert!(!policy.retries("PATCH"));
+        assert!(policy.retry_non_idempotent(true).retries("POST"));
+    }
+}
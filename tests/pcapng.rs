@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Request;
+
+    /// Bodies of all blocks of `kind`, in file order.
+    fn blocks(file: &[u8], kind: u32) -> Vec<&[u8]> {
+        let mut found = Vec::new();
+        let mut offset = 0;
+        while offset < file.len() {
+            let block_type = u32::from_le_bytes(file[offset..offset + 4].try_into().unwrap());
+            let len = u32::from_le_bytes(file[offset + 4..offset + 8].try_into().unwrap()) as usize;
+            if block_type == kind {
+                found.push(&file[offset + 8..offset + len - 4]);
+            }
+            offset += len;
+        }
+        found
+    }
+
+    #[test]
+    fn request_frames_follow_handshake_and_preface() {
+        let prepared = Request::new("https://example.com/", "POST")
+            .unwrap()
+            .body("hi")
+            .prepare_request()
+            .unwrap();
+        let file = PcapngExport::new()
+            .request(&prepared, 1)
+            .unwrap()
+            .to_bytes();
+
+        assert_eq!(&file[..4], &BLOCK_SECTION_HEADER.to_le_bytes());
+        let interface = blocks(&file, BLOCK_INTERFACE_DESCRIPTION);
+        assert_eq!(&interface[0][..2], &LINKTYPE_RAW.to_le_bytes());
+
+        // SYN, SYN/ACK, ACK, preface, HEADERS, DATA, FIN.
+        let packets = blocks(&file, BLOCK_ENHANCED_PACKET);
+        assert_eq!(packets.len(), 7);
+        let ip = |packet: &[u8]| -> Vec<u8> {
+            let len = u32::from_le_bytes(packet[12..16].try_into().unwrap()) as usize;
+            packet[20..20 + len].to_vec()
+        };
+        assert_eq!(ip(packets[0])[33], TCP_SYN);
+        assert_eq!(checksum(&ip(packets[0])[..IPV4_HEADER_LEN], 0), 0);
+        assert!(ip(packets[3])[40..].starts_with(CONNECTION_PREFACE));
+
+        let data = ip(packets[5]);
+        let frame = &data[IPV4_HEADER_LEN + TCP_HEADER_LEN..];
+        assert_eq!(frame[3], 0x0); // DATA
+        assert_eq!(frame[4], END_STREAM_FLAG);
+        assert_eq!(&frame[9..], b"hi");
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SsrfGuard;
+
+    #[test]
+    fn take_returns_the_newest_fresh_connection() {
+        let pool = ConnectionPool::default();
+        pool.park("https://example.com:443", 1);
+        pool.park("https://example.com:443", 2);
+        pool.park("http://example.com:80", 3);
+        assert_eq!(pool.len(), 3);
+
+        assert_eq!(pool.take("https://example.com:443"), Some(2));
+        assert_eq!(pool.take("https://example.com:443"), Some(1));
+        assert_eq!(pool.take("https://example.com:443"), None);
+        assert_eq!(pool.idle_count("http://example.com:80"), 1);
+
+        pool.set_max_idle(Duration::ZERO);
+        assert_eq!(pool.take("http://example.com:80"), None);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn lru_takes_the_oldest_and_limits_evict_it() {
+        let pool = ConnectionPool::default();
+        pool.set_eviction_policy(EvictionPolicy::Lru);
+        pool.set_max_per_origin(Some(2));
+        for connection in 1..=2 {
+            pool.park("https://example.com:443", connection);
+        }
+        assert_eq!(pool.park("https://example.com:443", 3), vec![1]);
+        assert_eq!(pool.idle_count("https://example.com:443"), 2);
+        assert_eq!(pool.take("https://example.com:443"), Some(2));
+
+        pool.set_eviction_policy(EvictionPolicy::Ttl);
+        for connection in 4..=6 {
+            pool.park("https://example.com:443", connection);
+        }
+        assert_eq!(pool.idle_count("https://example.com:443"), 4);
+        assert_eq!(pool.take("https://example.com:443"), Some(6));
+    }
+
+    #[test]
+    fn stats_count_reuse_handshakes_and_evictions() {
+        let pool = ConnectionPool::default();
+        pool.set_max_per_origin(Some(1));
+        pool.record_handshake("https://example.com:443", Duration::from_millis(30));
+        pool.record_handshake("https://example.com:443", Duration::from_millis(10));
+        pool.park("https://example.com:443", 1);
+        pool.park("https://example.com:443", 2);
+        pool.park("http://example.com:80", 3);
+        assert_eq!(pool.take("https://example.com:443"), Some(2));
+
+        let stats = pool.stats();
+        let origin = &stats.origins["https://example.com:443"];
+        assert_eq!((origin.opened, origin.reused, origin.evicted), (2, 1, 1));
+        assert_eq!(origin.idle, 0);
+        assert_eq!(origin.last_handshake_time, Some(Duration::from_millis(10)));
+        assert_eq!(
+            origin.average_handshake_time(),
+            Some(Duration::from_millis(20))
+        );
+        assert_eq!(stats.idle(), 1);
+
+        let mut merged = stats.clone();
+        merged.merge(&stats);
+        assert_eq!(merged.opened(), 4);
+        assert_eq!(merged.reused(), 2);
+    }
+
+    #[test]
+    fn keys_carry_the_default_port_and_skip_guarded_requests() {
+        let request = Request::new("https://Example.com/path?q=1", "GET").unwrap();
+        assert_eq!(
+            pool_key(&request).as_deref(),
+            Some("https://example.com:443")
+        );
+
+        let fresh = request.clone().fresh_dns();
+        assert_eq!(pool_key(&fresh), None);
+        let guarded = request.ssrf_guard(SsrfGuard::default());
+        assert_eq!(pool_key(&guarded), None);
+    }
+
+    /// Connection that counts how often it was closed gracefully.
+    struct Probe(std::rc::Rc<std::cell::Cell<u32>>);
+
+    #[async_trait(?Send)]
+    impl PooledConnection for Probe {
+        async fn close_gracefully(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn evicted_connections_are_closed_gracefully() {
+        let closed = std::rc::Rc::new(std::cell::Cell::new(0));
+        let pool = ConnectionPool::default();
+        pool.set_max_per_origin(Some(1));
+        for _ in 0..3 {
+            pool.park_and_close_evicted("https://example.com:443", Probe(closed.clone()))
+                .await;
+        }
+        assert_eq!(closed.get(), 2);
+        assert_eq!(pool.idle_count("https://example.com:443"), 1);
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+    use url::Url;
+
+    #[test]
+    fn proxy_interim_responses_are_parsed_before_the_tunnel_opens() {
+        let (status, headers) =
+            parse_connect_response(b"HTTP/1.1 100 Continue\r\nX-Proxy: squid\r\n\r\n").unwrap();
+        assert!(is_interim_status(status));
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].name, "X-Proxy");
+        assert_eq!(headers[0].value.as_deref(), Some("squid"));
+
+        let (status, _) =
+            parse_connect_response(b"HTTP/1.1 200 Connection established\r\n\r\n").unwrap();
+        assert!(!is_interim_status(status));
+    }
+
+    #[test]
+    fn refused_connect_reports_the_status_line() {
+        let err = parse_connect_response(
+            b"HTTP/1.1 407 Proxy Authentication Required\r\nProxy-Authenticate: Basic\r\n\r\n",
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("407 Proxy Authentication Required"),
+            "{}",
+            err
+        );
+        assert!(!is_interim_status(101));
+    }
+
+    #[tokio::test]
+    async fn interim_responses_to_connect_reach_the_handler() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = ProxyConfig::http(
+            Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap(),
+        );
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                socket.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            assert!(head.starts_with(b"CONNECT example.com:80 HTTP/1.1\r\n"));
+            socket
+                .write_all(
+                    b"HTTP/1.1 100 Continue\r\nX-Proxy: squid\r\n\r\n\
+                      HTTP/1.1 200 Connection established\r\n\r\n\
+                      from the target",
+                )
+                .await
+                .unwrap();
+        });
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&seen);
+        let handler = InformationalHandler::new(move |interim| {
+            recorded.lock().unwrap().push((
+                interim.status,
+                interim.from_proxy,
+                interim.headers.len(),
+            ));
+        });
+        let mut stream = connect_through_proxy(&proxy, "example.com", 80, None, Some(&handler))
+            .await
+            .unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![(100, true, 1)]);
+
+        // Nothing past the CONNECT response was consumed.
+        let mut tunneled = Vec::new();
+        stream.read_to_end(&mut tunneled).await.unwrap();
+        assert_eq!(tunneled, b"from the target");
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn counts_per_host_and_in_total() {
+        let meter = TrafficMeter::new();
+        meter.record("Example.com", 100, 1_000);
+        meter.record("example.com", 50, 500);
+        meter.record("example.org", 10, 20);
+
+        assert_eq!(
+            meter.host_stats("example.com"),
+            TrafficStats {
+                requests: 2,
+                bytes_sent: 150,
+                bytes_received: 1_500,
+            }
+        );
+        assert_eq!(meter.stats().total(), 1_680);
+        assert_eq!(meter.hosts().len(), 2);
+
+        meter.reset();
+        assert_eq!(meter.stats(), TrafficStats::default());
+    }
+
+    #[test]
+    fn quotas_stop_requests_once_used_up() {
+        let meter = TrafficMeter::new();
+        meter.set_host_quota(Some(1_000));
+        meter.record("example.com", 200, 900);
+        assert!(meter.check("example.org").is_ok());
+        match meter.check("example.com") {
+            Err(ProtocolError::QuotaExceeded { host, limit, used }) => {
+                assert_eq!(host.as_deref(), Some("example.com"));
+                assert_eq!((limit, used), (1_000, 1_100));
+            }
+            other => panic!("expected QuotaExceeded, got {:?}", other),
+        }
+
+        meter.set_host_quota(None);
+        meter.set_total_quota(Some(1_100));
+        assert!(matches!(
+            meter.check("example.org"),
+            Err(ProtocolError::QuotaExceeded { host: None, .. })
+        ));
+    }
+
+    /// Serves one connection: reads a request head and answers with
+    /// `response`, or closes without answering when it is empty. Returns
+    /// how many bytes of request it read.
+    async fn serve_once(response: &'static [u8]) -> (String, tokio::task::JoinHandle<usize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                socket.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            socket.write_all(response).await.unwrap();
+            head.len()
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn sessions_charge_the_bytes_on_the_wire() {
+        const RESPONSE: &[u8] =
+            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nhi";
+        let (url, server) = serve_once(RESPONSE).await;
+        let mut session = crate::H1::new().session();
+
+        let response = session.get(&url).send().await.unwrap();
+        assert_eq!(response.body.as_ref(), b"hi");
+        let sent = server.await.unwrap() as u64;
+        assert_eq!(
+            session.traffic().host_stats("127.0.0.1"),
+            TrafficStats {
+                requests: 1,
+                bytes_sent: sent,
+                bytes_received: RESPONSE.len() as u64,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_attempts_are_charged_too() {
+        let (url, server) = serve_once(b"").await;
+        let mut session = crate::H1::new().session();
+
+        assert!(session.get(&url).send().await.is_err());
+        let sent = server.await.unwrap() as u64;
+        assert_eq!(
+            session.traffic().host_stats("127.0.0.1"),
+            TrafficStats {
+                requests: 1,
+                bytes_sent: sent,
+                bytes_received: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn requests_without_a_meter_charge_nothing() {
+        let meter = Arc::new(TrafficMeter::new());
+        let mut request = Request::new("http://Example.com/", "GET").unwrap();
+        request.charge_traffic(10, 20);
+
+        request.traffic = Some(Arc::clone(&meter));
+        request.charge_traffic(10, 20);
+        assert_eq!(meter.host_stats("example.com").total(), 30);
+        assert_eq!(meter.stats().requests, 1);
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parse_target;
+
+    #[test]
+    fn registers_and_rewrites_schemes() {
+        let registry = SchemeRegistry::new();
+        assert!(registry.get("H2C").is_some());
+        assert!(registry.register("socks-http", SchemeSpec::plain().default_port(8080)));
+
+        let (rewritten, spec) = registry.rewrite("socks-http://example.onion/a").unwrap();
+        assert_eq!(rewritten, "http://example.onion/a");
+        assert_eq!(spec.default_port, Some(8080));
+        assert!(registry.rewrite("https://example.com/").is_none());
+
+        assert!(registry.unregister("socks-http").is_some());
+        assert!(registry.rewrite("socks-http://example.onion/").is_none());
+    }
+
+    #[test]
+    fn refuses_to_remap_builtin_schemes() {
+        let registry = SchemeRegistry::new();
+        assert!(!registry.register("HTTPS", SchemeSpec::plain()));
+        assert!(registry.get("https").is_none());
+    }
+
+    #[test]
+    fn parse_target_applies_registered_schemes() {
+        let target = parse_target("h2c://localhost/").unwrap();
+        assert_eq!(target.scheme(), "http");
+        assert_eq!(target.port(), Some(80));
+        assert!(target.protocols.contains(&HttpProtocol::H2C));
+
+        SchemeRegistry::global().register("scheme-test-tls", SchemeSpec::tls().default_port(8443));
+        let target = parse_target("scheme-test-tls://example.com/").unwrap();
+        assert_eq!(target.scheme(), "https");
+        assert_eq!(target.port(), Some(8443));
+        let target = parse_target("scheme-test-tls://example.com:9000/").unwrap();
+        assert_eq!(target.port(), Some(9000));
+    }
+
+    #[test]
+    fn an_explicit_default_port_is_kept() {
+        SchemeRegistry::global().register("scheme-test-port", SchemeSpec::tls().default_port(8443));
+        let target = parse_target("scheme-test-port://example.com:443/").unwrap();
+        assert_eq!(target.port(), Some(443));
+        let target = parse_target("scheme-test-port://user@[::1]/").unwrap();
+        assert_eq!(target.port(), Some(8443));
+    }
+
+    #[test]
+    fn unix_targets_name_the_socket_as_their_host() {
+        let target = parse_target("http+unix://%2Ftmp%2Fapp.sock/v1/info?x=1").unwrap();
+        assert_eq!(
+            target.unix_socket.as_deref(),
+            Some(std::path::Path::new("/tmp/app.sock"))
+        );
+        assert_eq!(target.host(), Some("localhost"));
+        assert_eq!(target.path(), "/v1/info");
+        assert_eq!(
+            crate::pool::origin_key(&target).unwrap(),
+            "unix:/tmp/app.sock"
+        );
+        assert!(parse_target("http+unix:///v1/info").is_err());
+    }
+}
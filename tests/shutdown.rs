@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn waits_for_running_requests_then_refuses_new_ones() {
+        let handle = ShutdownHandle::new();
+        let request = handle.run(async {
+            sleep(Duration::from_millis(20)).await;
+            Ok::<_, ProtocolError>(200)
+        });
+        let (result, report) = tokio::join!(request, async {
+            sleep(Duration::from_millis(5)).await;
+            handle.shutdown(Duration::from_secs(1)).await
+        });
+
+        assert_eq!(result.unwrap(), 200);
+        assert_eq!((report.completed, report.aborted), (1, 0));
+        assert!(handle.is_shutting_down());
+        assert!(handle.begin().is_err());
+        assert_eq!(handle.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn aborts_requests_past_the_grace_period() {
+        let handle = ShutdownHandle::new();
+        let request = handle.run(async {
+            sleep(Duration::from_secs(10)).await;
+            Ok::<_, ProtocolError>(200)
+        });
+        let (result, report) = tokio::join!(request, async {
+            sleep(Duration::from_millis(5)).await;
+            handle.shutdown(Duration::from_millis(10)).await
+        });
+
+        assert!(result.is_err());
+        assert_eq!((report.completed, report.aborted), (0, 1));
+        assert_eq!(handle.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn shutdown_without_requests_returns_at_once() {
+        let handle = ShutdownHandle::new();
+        assert!(handle.wait_idle(Duration::ZERO).await);
+        assert_eq!(
+            handle.shutdown(Duration::from_secs(60)).await,
+            ShutdownReport::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn aborted_requests_are_counted_once_they_have_failed() {
+        let handle = ShutdownHandle::new();
+        let slow = || {
+            handle.run(async {
+                sleep(Duration::from_secs(10)).await;
+                Ok::<_, ProtocolError>(200)
+            })
+        };
+        let quick = handle.run(async {
+            sleep(Duration::from_millis(5)).await;
+            Ok::<_, ProtocolError>(204)
+        });
+        let (first, second, third, (report, left)) = tokio::join!(slow(), slow(), quick, async {
+            sleep(Duration::from_millis(1)).await;
+            let report = handle.shutdown(Duration::from_millis(20)).await;
+            (report, handle.in_flight())
+        });
+
+        assert!(first.is_err() && second.is_err());
+        assert_eq!(third.unwrap(), 204);
+        assert_eq!(left, 0);
+        assert_eq!((report.completed, report.aborted), (1, 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requests_that_ignore_the_abort_still_count() {
+        let handle = ShutdownHandle::new();
+        let _held = handle.begin().unwrap();
+
+        let report = handle.shutdown(Duration::from_millis(10)).await;
+        assert_eq!((report.completed, report.aborted), (0, 1));
+        assert_eq!(handle.in_flight(), 1);
+    }
+}
@@ -0,0 +1,177 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_base_lists_components_then_params() -> Result<(), ProtocolError> {
+        let request = Request::new("https://Example.com:8443/foo?a=1", "POST")?;
+        let headers = vec![Header::new(
+            "Content-Type".to_string(),
+            " text/plain ".to_string(),
+        )];
+        let components: Vec<ComponentId> =
+            ["@method", "@authority", "@path", "@query", "content-type"]
+                .iter()
+                .map(|c| ComponentId::plain(c))
+                .collect();
+        let params = serialize_signature_params(
+            &components,
+            &[
+                ("created", SignatureParam::Integer(1618884473)),
+                ("keyid", SignatureParam::String("k".to_string())),
+            ],
+        );
+
+        let base = SignedMessage::Request {
+            request: &request,
+            headers: &headers,
+        }
+        .signature_base(&components, &params)?;
+        assert_eq!(
+            base,
+            "\"@method\": POST\n\
+             \"@authority\": example.com:8443\n\
+             \"@path\": /foo\n\
+             \"@query\": ?a=1\n\
+             \"content-type\": text/plain\n\
+             \"@signature-params\": (\"@method\" \"@authority\" \"@path\" \"@query\" \"content-type\");created=1618884473;keyid=\"k\""
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hmac_signer_emits_matching_input_and_signature() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com/", "GET")?;
+        let signer = HmacSigner::new("key-1", b"secret").label("req").nonce(true);
+
+        let headers = signer.sign(&request, &[])?;
+        assert_eq!(headers[0].name, SIGNATURE_INPUT_HEADER);
+        let input = headers[0].value.as_deref().unwrap();
+        assert!(input.starts_with("req=(\"@method\" \"@authority\" \"@path\");created="));
+        assert!(input.contains(";nonce=\""), "{}", input);
+        assert!(
+            input.ends_with(";alg=\"hmac-sha256\";keyid=\"key-1\""),
+            "{}",
+            input
+        );
+        assert_eq!(headers[1].name, SIGNATURE_HEADER);
+        assert!(headers[1].value.as_deref().unwrap().starts_with("req=:"));
+        Ok(())
+    }
+
+    #[test]
+    fn missing_header_component_fails_signing() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com/", "GET")?;
+        let signer = HmacSigner::new("key-1", b"secret").components(["content-digest"]);
+
+        assert!(matches!(
+            signer.sign(&request, &[]),
+            Err(ProtocolError::SignatureError(_))
+        ));
+        Ok(())
+    }
+
+    fn signed_response(status: u16, params: &str, key: &[u8], request: &Request) -> Response {
+        let mut headers = vec![Header::new(
+            "content-type".to_string(),
+            "text/plain".to_string(),
+        )];
+        let input = parse_signature_input(params).unwrap();
+        let request_headers = request.prepare_headers();
+        let base = SignedMessage::Response {
+            status,
+            headers: &headers,
+            request: Some((request, &request_headers)),
+        }
+        .signature_base(&input.components, params)
+        .unwrap();
+        let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), base.as_bytes());
+        headers.push(Header::new(
+            SIGNATURE_INPUT_HEADER.to_string(),
+            format!("sig1={}", params),
+        ));
+        headers.push(Header::new(
+            SIGNATURE_HEADER.to_string(),
+            format!("sig1=:{}:", base64_encode(tag.as_ref())),
+        ));
+        Response {
+            status,
+            headers,
+            ..Response::default()
+        }
+    }
+
+    #[test]
+    fn verifier_accepts_hmac_response_signature() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com/data?x=1", "GET")?;
+        let params = "(\"@status\" \"content-type\" \"@path\";req);created=1;alg=\"hmac-sha256\";keyid=\"k\"";
+        let response = signed_response(200, params, b"secret", &request);
+
+        let verifier = SignatureVerifier::new(VerificationKey::HmacSha256(b"secret".to_vec()))
+            .key_id("k")
+            .require_components(["@status"]);
+        let verified = verifier.verify_response(&response, Some(&request))?;
+        assert_eq!(verified.label, "sig1");
+        assert_eq!(verified.created, Some(1));
+        assert_eq!(verified.components[2], "\"@path\";req");
+
+        // ;req components cannot be resolved without the request.
+        assert!(verifier.verify_response(&response, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn verifier_rejects_tampered_or_expired_signatures() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com/", "GET")?;
+        let key = VerificationKey::HmacSha256(b"secret".to_vec());
+
+        let mut response = signed_response(200, "(\"@status\");created=1", b"secret", &request);
+        response.status = 500;
+        assert!(matches!(
+            SignatureVerifier::new(key.clone()).verify_response(&response, None),
+            Err(ProtocolError::SignatureError(_))
+        ));
+
+        let response = signed_response(
+            200,
+            "(\"@status\");created=1;expires=2",
+            b"secret",
+            &request,
+        );
+        assert!(SignatureVerifier::new(key)
+            .verify_response(&response, None)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn max_age_requires_a_created_parameter() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com/", "GET")?;
+        let verifier = SignatureVerifier::new(VerificationKey::HmacSha256(b"secret".to_vec()))
+            .max_age(Duration::from_secs(60));
+
+        let undated = signed_response(200, "(\"@status\")", b"secret", &request);
+        assert!(verifier.verify_response(&undated, None).is_err());
+        // The same signature passes once no age limit applies.
+        let verifier = SignatureVerifier::new(VerificationKey::HmacSha256(b"secret".to_vec()));
+        assert!(verifier.verify_response(&undated, None).is_ok());
+
+        let fresh = signed_response(
+            200,
+            &format!("(\"@status\");created={}", unix_now()),
+            b"secret",
+            &request,
+        );
+        let verifier = verifier.max_age(Duration::from_secs(60));
+        assert!(verifier.verify_response(&fresh, None).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn dictionary_members_keep_inner_lists_intact() {
+        let members = dictionary_members("a=(\"x\" \"y\");tag=\"p,q\", b=:AAA=:");
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].1, "(\"x\" \"y\");tag=\"p,q\"");
+        assert_eq!(members[1], ("b".to_string(), ":AAA=:".to_string()));
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closed_addr() -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    #[test]
+    fn every_failed_address_is_reported() {
+        let addrs = [closed_addr(), closed_addr()];
+        let err = tokio_test::block_on(connect_addrs(
+            "example.test",
+            &addrs,
+            &SocketOptions::default(),
+            ConnectMechanism::Tcp,
+            None,
+        ))
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+
+        match ProtocolError::from_connect_error(err) {
+            ProtocolError::ConnectAttemptsFailed(failure) => {
+                assert_eq!(failure.host, "example.test");
+                let tried: Vec<SocketAddr> = failure.attempts.iter().map(|a| a.addr).collect();
+                assert_eq!(tried, addrs);
+                assert!(failure
+                    .attempts
+                    .iter()
+                    .all(|attempt| attempt.mechanism == ConnectMechanism::Tcp));
+            }
+            other => panic!("expected ConnectAttemptsFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_later_address_still_connects() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addrs = [closed_addr(), listener.local_addr().unwrap()];
+        let stream = tokio_test::block_on(async {
+            connect_addrs(
+                "example.test",
+                &addrs,
+                &SocketOptions::default(),
+                ConnectMechanism::Tcp,
+                Some(time::Instant::now() + Duration::from_secs(5)),
+            )
+            .await
+        });
+        assert!(stream.is_ok());
+    }
+
+    #[test]
+    fn no_addresses_is_invalid_input() {
+        let err = tokio_test::block_on(connect_addrs(
+            "example.test",
+            &[],
+            &SocketOptions::default(),
+            ConnectMechanism::Proxy,
+            None,
+        ))
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            ProtocolError::from_connect_error(err).to_string(),
+            "No addresses to connect to for example.test"
+        );
+    }
+
+    #[test]
+    fn other_errors_stay_connection_failed() {
+        let err = io::Error::new(io::ErrorKind::Other, "handshake failed");
+        assert!(matches!(
+            ProtocolError::from_connect_error(err),
+            ProtocolError::ConnectionFailed(message) if message == "handshake failed"
+        ));
+    }
+
+    #[test]
+    fn traced_streams_record_dns_and_tcp() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let tracker = HandshakeTracker::new();
+        let stream = tokio_test::block_on(create_traced_stream(
+            "http",
+            "127.0.0.1",
+            port,
+            Some(Duration::from_secs(5)),
+            &SocketOptions::default(),
+            &tracker,
+        ));
+        assert!(stream.is_ok());
+        let phases: Vec<HandshakePhase> = tracker
+            .transitions()
+            .iter()
+            .map(|transition| transition.phase)
+            .collect();
+        assert_eq!(phases, vec![HandshakePhase::Dns, HandshakePhase::Tcp]);
+    }
+}
@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const JSON: &[(&str, &str)] = &[("Content-Type", "application/json")];
+
+    #[test]
+    fn passing_checks_chain() -> Result<(), AssertionError> {
+        let res = Response::new(200, JSON, r#"{"user": {"name": "alice"}}"#);
+        res.assert_status(200)?
+            .assert_header_present("content-type")?
+            .assert_header("CONTENT-TYPE", "application/json")?
+            .assert_body_contains("alice")?
+            .assert_json_pointer("/user/name", &json!("alice"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn failures_report_expected_and_actual() {
+        let res = Response::new(404, JSON, r#"{"user": {"name": "bob"}}"#);
+
+        let err = res.assert_status(200).unwrap_err();
+        assert_eq!((err.expected.as_str(), err.actual.as_str()), ("200", "404"));
+
+        let err = res.assert_header("content-type", "text/html").unwrap_err();
+        assert_eq!(err.actual, "\"application/json\"");
+
+        let err = res.assert_header_present("etag").unwrap_err();
+        assert!(err.actual.contains("Content-Type"));
+
+        let err = res
+            .assert_json_pointer("/user/name", &json!("alice"))
+            .unwrap_err();
+        assert_eq!(err.actual, "\"bob\"");
+        assert_eq!(
+            err.to_string(),
+            "JSON /user/name mismatch\n  expected: \"alice\"\n    actual: \"bob\""
+        );
+
+        let err = res.assert_json_pointer("/missing", &json!(1)).unwrap_err();
+        assert_eq!(err.actual, "missing");
+    }
+}
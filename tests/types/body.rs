@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    /// Hands out fixed chunks, then an error if one is given.
+    struct Chunks(Vec<Result<Bytes, ProtocolError>>);
+
+    #[async_trait(?Send)]
+    impl BodySource for Chunks {
+        async fn next_chunk(&mut self) -> Option<Result<Bytes, ProtocolError>> {
+            if self.0.is_empty() {
+                None
+            } else {
+                Some(self.0.remove(0))
+            }
+        }
+
+        fn trailers(&mut self) -> Option<Vec<Header>> {
+            Some(vec![Header::new("x-sum".to_string(), "6".to_string())])
+        }
+    }
+
+    #[tokio::test]
+    async fn buffered_bodies_come_out_whole() {
+        let mut body = ResponseBody::from(Bytes::from_static(b"hello"));
+        assert_eq!(body.next_chunk().await.unwrap().unwrap(), "hello");
+        assert!(body.next_chunk().await.is_none());
+        assert!(body.is_done());
+
+        let mut empty = ResponseBody::from(Bytes::new());
+        assert!(empty.next_chunk().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn trailers_follow_the_last_chunk() {
+        let chunks = vec![
+            Ok(Bytes::from_static(b"abc")),
+            Ok(Bytes::from_static(b"def")),
+        ];
+        let mut body = ResponseBody::new(Chunks(chunks));
+        assert!(body.trailers().is_none());
+        assert_eq!(body.bytes().await.unwrap(), "abcdef");
+        assert_eq!(body.trailers().unwrap()[0].name, "x-sum");
+    }
+
+    #[tokio::test]
+    async fn reading_stops_at_the_first_error() {
+        let chunks = vec![
+            Ok(Bytes::from_static(b"abc")),
+            Err(ProtocolError::Timeout(None)),
+            Ok(Bytes::from_static(b"def")),
+        ];
+        let mut body = ResponseBody::new(Chunks(chunks));
+        assert!(matches!(
+            body.bytes().await,
+            Err(ProtocolError::Timeout(None))
+        ));
+        assert!(body.next_chunk().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn async_read_spans_chunks() {
+        let chunks = vec![
+            Ok(Bytes::from_static(b"abc")),
+            Ok(Bytes::from_static(b"def")),
+        ];
+        let mut body = ResponseBody::new(Chunks(chunks));
+        let mut first = [0u8; 4];
+        body.read_exact(&mut first).await.unwrap();
+        assert_eq!(&first, b"abcd");
+        let mut rest = Vec::new();
+        body.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"ef");
+
+        let mut failing = ResponseBody::new(Chunks(vec![Err(ProtocolError::Timeout(None))]));
+        let err = failing.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    }
+}
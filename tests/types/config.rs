@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_fills_only_what_the_request_leaves_open() {
+        let config = ClientConfig::new()
+            .follow_redirects(false)
+            .user_agent(UserAgent::Custom("scanner/1.0".to_string()))
+            .header_profile(HeaderProfile::Curl)
+            .decompression_limits(DecompressionLimits::unlimited());
+
+        let mut inheriting = Request::new("https://example.com/", "GET").unwrap();
+        config.apply(&mut inheriting);
+        assert!(!inheriting.follow_redirects);
+        assert_eq!(
+            inheriting.user_agent,
+            UserAgent::Custom("scanner/1.0".to_string())
+        );
+        assert_eq!(inheriting.header_profile, Some(HeaderProfile::Curl));
+        assert_eq!(
+            inheriting.decompression_limits,
+            DecompressionLimits::unlimited()
+        );
+
+        let own_limits = DecompressionLimits {
+            max_decoded_size: Some(1024),
+            ..DecompressionLimits::default()
+        };
+        let mut explicit = Request::new("https://example.com/", "GET")
+            .unwrap()
+            .user_agent(UserAgent::Omit)
+            .header_profile(HeaderProfile::Firefox)
+            .decompression_limits(own_limits);
+        config.apply(&mut explicit);
+        assert_eq!(explicit.user_agent, UserAgent::Omit);
+        assert_eq!(explicit.header_profile, Some(HeaderProfile::Firefox));
+        assert_eq!(explicit.decompression_limits, own_limits);
+    }
+}
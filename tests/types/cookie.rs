@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn set_cookie_attributes_scope_cookies() {
+        let mut jar = CookieStore::new();
+        let origin = url("https://login.example.com/auth/start");
+        jar.store_set_cookie(&origin, "host=1");
+        jar.store_set_cookie(&origin, "shared=2; Domain=.example.com; Path=/");
+        jar.store_set_cookie(&origin, "api=3; Path=/api; Secure");
+        jar.store_set_cookie(&origin, "foreign=4; Domain=other.example");
+
+        assert_eq!(
+            jar.cookies_for(&url("https://login.example.com/auth/next")),
+            [
+                ("host".to_string(), "1".to_string()),
+                ("shared".to_string(), "2".to_string())
+            ]
+        );
+        assert_eq!(
+            jar.cookies_for(&url("https://login.example.com/api/v1")),
+            [
+                ("api".to_string(), "3".to_string()),
+                ("shared".to_string(), "2".to_string())
+            ]
+        );
+        assert_eq!(
+            jar.cookies_for(&url("http://login.example.com/api")),
+            [("shared".to_string(), "2".to_string())]
+        );
+        assert_eq!(
+            jar.cookies_for(&url("https://www.example.com/")),
+            [("shared".to_string(), "2".to_string())]
+        );
+        assert!(jar.cookies_for(&url("https://other.example/")).is_empty());
+        assert!(jar.cookies_for(&url("https://notexample.com/")).is_empty());
+    }
+
+    #[test]
+    fn expired_cookies_are_removed() {
+        let mut jar = CookieStore::new();
+        let origin = url("https://example.com/");
+        jar.store_set_cookie(&origin, "a=1");
+        jar.store_set_cookie(&origin, "b=2");
+        jar.store_set_cookie(&origin, "a=1; Max-Age=0");
+        jar.store_set_cookie(&origin, "b=2; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+        assert!(jar.is_empty());
+    }
+
+    #[test]
+    fn empty_values_are_cookies_too() {
+        let mut jar = CookieStore::new();
+        let origin = url("https://example.com/");
+        jar.store_set_cookie(&origin, "flag=");
+        assert_eq!(
+            jar.cookies_for(&origin),
+            [("flag".to_string(), String::new())]
+        );
+    }
+
+    #[test]
+    fn max_age_wins_over_expires() {
+        let mut jar = CookieStore::new();
+        let origin = url("https://example.com/");
+        jar.store_set_cookie(
+            &origin,
+            "a=1; Max-Age=3600; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+        );
+        jar.store_set_cookie(
+            &origin,
+            "b=2; Expires=Fri, 01 Jan 2100 00:00:00 GMT; Max-Age=0",
+        );
+        assert_eq!(
+            jar.cookies_for(&origin),
+            [("a".to_string(), "1".to_string())]
+        );
+    }
+
+    #[test]
+    fn expires_takes_the_dates_servers_send() {
+        let date = |value| parse_cookie_date(value).map(|date| date.to_rfc3339());
+        let expected = Some("1994-11-06T08:49:37+00:00".to_string());
+        assert_eq!(date("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+        assert_eq!(date("Sun, 06-Nov-1994 08:49:37 GMT"), expected);
+        assert_eq!(date("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+        assert_eq!(date("Sun Nov  6 08:49:37 1994"), expected);
+        assert_eq!(date("6 november 1994 8:49:37"), expected);
+
+        assert_eq!(date("Sun, 06 Nov 1994"), None);
+        assert_eq!(date("Sun, 31 Feb 1994 08:49:37 GMT"), None);
+        assert_eq!(date("Sun, 06 Nov 1994 24:00:00 GMT"), None);
+
+        let mut jar = CookieStore::new();
+        let origin = url("https://example.com/");
+        jar.store_set_cookie(&origin, "a=1");
+        jar.store_set_cookie(&origin, "a=1; Expires=Thu, 01-Jan-1970 00:00:00 GMT");
+        assert!(jar.is_empty());
+    }
+
+    #[test]
+    fn request_cookies_win_over_stored_ones() {
+        let mut jar = CookieStore::new();
+        jar.set_cookie("session", "stored");
+        jar.set_cookie("theme", "dark");
+        let mut request = Request::new("https://example.com/", "GET").unwrap();
+        request.set_cookies([("session", "explicit")]);
+
+        jar.apply_to_request(&mut request);
+        assert_eq!(
+            request.cookies,
+            [
+                ("theme".to_string(), "dark".to_string()),
+                ("session".to_string(), "explicit".to_string())
+            ]
+        );
+    }
+}
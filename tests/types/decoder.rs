@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn decode_picks_decoder_from_content_type() -> Result<(), ProtocolError> {
+        let res = Response::new(
+            200,
+            &[("Content-Type", "application/problem+json; charset=utf-8")],
+            r#"{"status": 404}"#,
+        );
+        let problem: BTreeMap<String, u16> = res.decode()?;
+        assert_eq!(problem["status"], 404);
+
+        let res = Response::new(
+            200,
+            &[("Content-Type", "application/x-ndjson")],
+            "[1]\n\n[2, 3]\n",
+        );
+        let lines: Vec<Vec<u8>> = res.decode()?;
+        assert_eq!(lines, vec![vec![1], vec![2, 3]]);
+        let streamed: Vec<u32> = res
+            .ndjson::<Vec<u32>>()
+            .map(|l| l.map(|v| v.len() as u32))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(streamed, vec![1, 2]);
+
+        // No Content-Type: JSON fallback.
+        let value: Vec<bool> = Response::new(200, &[], "[true]").decode()?;
+        assert_eq!(value, vec![true]);
+        Ok(())
+    }
+
+    #[test]
+    fn custom_decoders_override_and_extend_registry() -> Result<(), ProtocolError> {
+        let mut registry = DecoderRegistry::default();
+        registry.register("text/csv", |body: &[u8]| -> Result<Value, ProtocolError> {
+            Ok(Value::Array(
+                String::from_utf8_lossy(body)
+                    .split(',')
+                    .map(|field| Value::String(field.trim().to_string()))
+                    .collect(),
+            ))
+        });
+
+        let res = Response::new(200, &[("Content-Type", "Text/CSV")], "a, b");
+        let fields: Vec<String> = res.decode_with(&registry)?;
+        assert_eq!(fields, vec!["a", "b"]);
+
+        assert!(matches!(
+            res.decode::<Vec<String>>(),
+            Err(ProtocolError::InvalidResponse(_))
+        ));
+        assert!(DecoderRegistry::new()
+            .decoder_for("application/json")
+            .is_none());
+        Ok(())
+    }
+}
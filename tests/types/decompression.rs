@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_limit_is_enforced() {
+        let limits = DecompressionLimits {
+            max_decoded_size: Some(10),
+            max_ratio: None,
+            ratio_floor: 0,
+        };
+        assert!(limits.check(1, 10).is_ok());
+        assert!(matches!(
+            limits.check(1, 11),
+            Err(ProtocolError::DecompressionLimitExceeded(
+                DecompressionLimitKind::Size {
+                    limit: 10,
+                    decoded: 11
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn ratio_limit_applies_above_floor_only() {
+        let limits = DecompressionLimits {
+            max_decoded_size: None,
+            max_ratio: Some(10),
+            ratio_floor: 100,
+        };
+        // Tiny bodies may compress arbitrarily well.
+        assert!(limits.check(1, 100).is_ok());
+        assert!(limits.check(20, 200).is_ok());
+
+        let mut guard = limits.guard();
+        guard.on_input(20);
+        assert!(guard.on_output(150).is_ok());
+        match guard.on_output(60) {
+            Err(ProtocolError::DecompressionLimitExceeded(kind)) => assert_eq!(
+                kind,
+                DecompressionLimitKind::Ratio {
+                    limit: 10,
+                    compressed: 20,
+                    decoded: 210
+                }
+            ),
+            other => panic!("expected ratio error, got {:?}", other),
+        }
+        assert!(DecompressionLimits::unlimited()
+            .check(0, usize::MAX)
+            .is_ok());
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn decode_content_unwraps_stacked_codings() {
+        let limits = DecompressionLimits::default();
+        let twice = gzip(&gzip(b"hello"));
+        let decoded = decode_content("gzip, identity, x-gzip", &twice, &limits).unwrap();
+        assert_eq!(decoded.as_deref(), Some(&b"hello"[..]));
+
+        assert_eq!(decode_content("br", b"\x0b", &limits).unwrap(), None);
+        assert_eq!(decode_content("gzip", b"not gzip", &limits).unwrap(), None);
+    }
+
+    #[test]
+    fn decode_content_stops_at_the_size_limit() {
+        let limits = DecompressionLimits {
+            max_decoded_size: Some(1024),
+            max_ratio: None,
+            ratio_floor: 0,
+        };
+        let bomb = gzip(&vec![0u8; 64 * 1024]);
+        assert!(matches!(
+            decode_content("gzip", &bomb, &limits),
+            Err(ProtocolError::DecompressionLimitExceeded(
+                DecompressionLimitKind::Size { limit: 1024, .. }
+            ))
+        ));
+    }
+
+    struct Reverse;
+
+    impl ContentCodec for Reverse {
+        fn decode(
+            &self,
+            input: &[u8],
+            _limits: &DecompressionLimits,
+        ) -> Result<Option<Vec<u8>>, ProtocolError> {
+            Ok(Some(input.iter().rev().copied().collect()))
+        }
+    }
+
+    #[test]
+    fn registered_codecs_decode_by_name() {
+        let limits = DecompressionLimits::default();
+        let registry = CodecRegistry::new();
+        assert_eq!(
+            registry.decode("x-reverse", b"olleh", &limits).unwrap(),
+            None
+        );
+
+        assert!(registry.register("X-Reverse", Reverse));
+        let stacked = gzip(b"olleh");
+        assert_eq!(
+            registry
+                .decode("x-reverse, gzip", &stacked, &limits)
+                .unwrap()
+                .as_deref(),
+            Some(&b"hello"[..])
+        );
+        assert!(registry.unregister("x-reverse").is_some());
+        assert!(registry.get("x-reverse").is_none());
+    }
+
+    #[test]
+    fn identity_cannot_be_replaced() {
+        let registry = CodecRegistry::empty();
+        assert!(!registry.register("identity", Reverse));
+        assert_eq!(
+            registry
+                .decode("identity", b"abc", &DecompressionLimits::default())
+                .unwrap()
+                .as_deref(),
+            Some(&b"abc"[..])
+        );
+    }
+
+    #[test]
+    fn encode_round_trips_through_decode() {
+        let registry = CodecRegistry::new();
+        let encoded = registry.encode("deflate, gzip", b"payload").unwrap();
+        assert_eq!(
+            registry
+                .decode("deflate, gzip", &encoded, &DecompressionLimits::default())
+                .unwrap()
+                .as_deref(),
+            Some(&b"payload"[..])
+        );
+        assert!(registry.encode("br", b"payload").is_none());
+    }
+}
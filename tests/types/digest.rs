@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO_SHA256: &str = "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=";
+
+    #[test]
+    fn content_digest_in_headers_and_trailers_is_checked() {
+        let header = format!("sha-256=:{}:, md5=:XUFAKrxLKna5cZ2REBfFkg==:", HELLO_SHA256);
+        let trailer = "sha-256=:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=:";
+        let mut res = Response::new(200, &[("Content-Digest", &header)], "hello");
+        res.trailers = Some(vec![Header::new(
+            "content-digest".to_string(),
+            trailer.to_string(),
+        )]);
+
+        let verification = res.verify_digest();
+        let outcomes: Vec<_> = verification
+            .checks
+            .iter()
+            .map(|check| (check.source, check.algorithm.as_str(), check.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (DigestSource::Header, "sha-256", DigestOutcome::Match),
+                (
+                    DigestSource::Header,
+                    "md5",
+                    DigestOutcome::UnsupportedAlgorithm
+                ),
+                (DigestSource::Trailer, "sha-256", DigestOutcome::Mismatch),
+            ]
+        );
+        assert!(!verification.is_verified());
+
+        let mismatches = verification.mismatches();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].field, DigestField::ContentDigest);
+        assert_eq!(mismatches[0].expected, vec![0u8; 32]);
+        assert_eq!(base64_encode(&mismatches[0].actual), HELLO_SHA256);
+    }
+
+    #[test]
+    fn legacy_digest_and_partial_repr_digest() {
+        let legacy = format!("SHA-256={}", HELLO_SHA256);
+        let repr = format!("sha-256=:{}:", HELLO_SHA256);
+        let res = Response::new(206, &[("Digest", &legacy), ("Repr-Digest", &repr)], "hello");
+
+        let verification = res.verify_digest();
+        assert_eq!(verification.checks[0].field, DigestField::Digest);
+        assert_eq!(verification.checks[0].outcome, DigestOutcome::Match);
+        assert_eq!(verification.checks[1].outcome, DigestOutcome::Unverifiable);
+        assert!(verification.is_verified());
+
+        let res = Response::new(
+            200,
+            &[("Content-Digest", "sha-256=not-a-byte-sequence")],
+            "hello",
+        );
+        assert_eq!(
+            res.verify_digest().checks[0].outcome,
+            DigestOutcome::Malformed
+        );
+        assert!(Response::new(200, &[], "hello").verify_digest().is_empty());
+    }
+}
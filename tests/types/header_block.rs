@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(name: &str, value: &str) -> Header {
+        Header::new(name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn annotates_rfc7541_huffman_request() {
+        // RFC 7541 Appendix C.4.1
+        let raw = Bytes::from_static(&[
+            0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab,
+            0x90, 0xf4, 0xff,
+        ]);
+        let decoded = vec![
+            header(":method", "GET"),
+            header(":scheme", "http"),
+            header(":path", "/"),
+            header(":authority", "www.example.com"),
+        ];
+
+        let info = HeaderBlockInfo::hpack(1, raw, &decoded);
+        assert_eq!(info.fields.len(), 4);
+        assert_eq!(info.fields[0].representation, FieldRepresentation::Indexed);
+        assert_eq!(info.fields[0].index, Some(TableIndex::Static(2)));
+
+        let authority = &info.fields[3];
+        assert_eq!(
+            authority.representation,
+            FieldRepresentation::LiteralIndexedName
+        );
+        assert_eq!(authority.index, Some(TableIndex::Static(1)));
+        assert!(authority.adds_to_table);
+        assert!(authority.huffman_value);
+    }
+
+    #[test]
+    fn malformed_block_keeps_raw_bytes_only() {
+        let raw = Bytes::from_static(&[0x41, 0x8c, 0xf1]);
+        let info = HeaderBlockInfo::hpack(3, raw.clone(), &[]);
+        assert!(info.fields.is_empty());
+        assert_eq!(info.raw, raw);
+    }
+
+    #[test]
+    fn annotates_qpack_static_references() {
+        // Required Insert Count 0, Base 0, indexed static :path "/" (index 1)
+        // and literal with static name reference :authority (index 0).
+        let raw = Bytes::from_static(&[0x00, 0x00, 0xc1, 0x50, 0x01, b'a']);
+        let decoded = vec![header(":path", "/"), header(":authority", "a")];
+
+        let info = HeaderBlockInfo::qpack(0, raw, &decoded);
+        assert_eq!(info.qpack_prefix, Some((0, false, 0)));
+        assert_eq!(info.fields[0].index, Some(TableIndex::Static(1)));
+        assert_eq!(
+            info.fields[1].representation,
+            FieldRepresentation::LiteralIndexedName
+        );
+        assert!(!info.fields[1].huffman_value);
+    }
+
+    #[test]
+    fn complete_len_stops_before_a_split_field_line() {
+        // RFC 7541 Appendix C.4.1
+        let raw = [
+            0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab,
+            0x90, 0xf4, 0xff,
+        ];
+        assert_eq!(hpack_complete_len(&raw), raw.len());
+        assert_eq!(hpack_complete_len(&raw[..3]), 3);
+        // The :authority literal starts at 3 and ends with the block.
+        for end in 4..raw.len() {
+            assert_eq!(hpack_complete_len(&raw[..end]), 3);
+        }
+        assert_eq!(hpack_complete_len(&[]), 0);
+    }
+
+    #[test]
+    fn complete_len_counts_table_size_updates() {
+        // Size update to 0, then an indexed :method GET.
+        assert_eq!(hpack_complete_len(&[0x20, 0x82]), 2);
+        assert_eq!(hpack_complete_len(&[0x20]), 1);
+        // A multi-byte size update cut short.
+        assert_eq!(hpack_complete_len(&[0x3f, 0xe1]), 0);
+    }
+}
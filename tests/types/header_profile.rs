@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ProtocolError, Request, UserAgent};
+
+    fn names(headers: &[Header]) -> Vec<&str> {
+        headers.iter().map(|h| h.name.as_str()).collect()
+    }
+
+    #[test]
+    fn arrange_places_supplied_headers_in_profile_slots() {
+        let headers = vec![
+            Header::new("X-Trace".to_string(), "1".to_string()),
+            Header::new("accept".to_string(), "application/json".to_string()),
+            Header::new("Cookie".to_string(), "a=b".to_string()),
+        ];
+        let arranged = HeaderProfile::Curl.arrange(headers, &HttpProtocol::Http2, &[]);
+
+        assert_eq!(
+            names(&arranged),
+            ["user-agent", "accept", "Cookie", "X-Trace"]
+        );
+        assert_eq!(arranged[1].value.as_deref(), Some("application/json"));
+    }
+
+    #[test]
+    fn arrange_skips_listed_defaults() {
+        let arranged =
+            HeaderProfile::Curl.arrange(Vec::new(), &HttpProtocol::Http2, &["user-agent"]);
+        assert_eq!(names(&arranged), ["accept"]);
+    }
+
+    #[test]
+    fn request_profile_orders_http1_headers_with_host_first() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com:8443/", "GET")?
+            .header_profile(HeaderProfile::Firefox)
+            .cookies(vec![("session", "abc")]);
+
+        let headers = request.prepare_headers_for(&HttpProtocol::Http1);
+        assert_eq!(headers[0].name, "Host");
+        assert_eq!(headers[0].value.as_deref(), Some("example.com:8443"));
+        assert_eq!(
+            headers[1].value.as_deref(),
+            Some(HeaderProfile::Firefox.user_agent())
+        );
+        let cookie = headers.iter().position(|h| h.name == "cookie").unwrap();
+        let connection = headers.iter().position(|h| h.name == "Connection").unwrap();
+        assert_eq!(cookie, connection + 1);
+
+        let h2 = request.prepare_headers();
+        assert!(h2.iter().all(|h| !h.name.eq_ignore_ascii_case("host")));
+        assert_eq!(h2.last().map(|h| h.name.as_str()), Some("te"));
+        Ok(())
+    }
+
+    #[test]
+    fn request_user_agent_overrides_profile_default() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com/", "GET")?
+            .header_profile(HeaderProfile::Chrome)
+            .user_agent(UserAgent::Omit);
+
+        let headers = request.prepare_headers();
+        assert!(headers.iter().all(|h| h.name != "user-agent"));
+        assert!(headers.iter().any(|h| h.name == "sec-ch-ua"));
+        Ok(())
+    }
+}
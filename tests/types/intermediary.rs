@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(raw: &[(&str, &str)]) -> Vec<Header> {
+        raw.iter()
+            .map(|(name, value)| Header::new(name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn via_hops_keep_comments_with_commas() {
+        let hops = parse_via("1.0 fred, 1.1 p.example.net (Squid/4.0, debian),HTTP/2 edge");
+        assert_eq!(hops.len(), 3);
+        assert_eq!(hops[0].received_by, "fred");
+        assert_eq!(hops[1].protocol, "1.1");
+        assert_eq!(hops[1].comment.as_deref(), Some("Squid/4.0, debian"));
+        assert_eq!(hops[2].protocol, "HTTP/2");
+        assert!(parse_via("1.1").is_empty());
+    }
+
+    #[test]
+    fn fingerprints_vendors_cache_and_chain() {
+        let report = IntermediaryReport::from_headers(&headers(&[
+            ("Content-Type", "text/html"),
+            ("Via", "1.1 varnish (Varnish/7.1)"),
+            ("Age", "42"),
+            ("X-Cache", "HIT"),
+            ("CF-Cache-Status", "DYNAMIC"),
+            ("CF-Ray", "8a1b2c3d4e5f-AMS"),
+            ("Server", "cloudflare"),
+        ]));
+
+        assert_eq!(report.vendors, vec!["Varnish", "Cloudflare"]);
+        assert_eq!(report.age, Some(42));
+        assert_eq!(report.cache_status.as_deref(), Some("DYNAMIC"));
+        assert_eq!(report.fingerprints.len(), 6);
+        assert_eq!(report.chain(), vec!["1.1 varnish", "Cloudflare"]);
+    }
+
+    #[test]
+    fn plain_responses_report_nothing() {
+        let report = IntermediaryReport::from_headers(&headers(&[
+            ("Server", "nginx"),
+            ("Content-Length", "0"),
+        ]));
+        assert!(report.is_empty());
+        assert!(report.chain().is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_adds_every_holder() {
+        let usage = MemoryUsage {
+            pending_writes: 1,
+            captured: 2,
+            queued_events: 3,
+            bodies: 4,
+            read_buffer: 5,
+        };
+        assert_eq!(usage.total(), 15);
+    }
+
+    #[test]
+    fn check_fails_only_over_the_limit() {
+        let budget = MemoryBudget::new(10).on_exceed(MemoryPressure::Backpressure);
+        let at_limit = MemoryUsage {
+            bodies: 10,
+            ..MemoryUsage::default()
+        };
+        assert!(budget.check(at_limit).is_ok());
+
+        let over = MemoryUsage {
+            captured: 11,
+            ..MemoryUsage::default()
+        };
+        match budget.check(over) {
+            Err(ProtocolError::MemoryBudgetExceeded { limit, usage }) => {
+                assert_eq!(limit, 10);
+                assert_eq!(usage, over);
+            }
+            other => panic!("expected MemoryBudgetExceeded, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn errors_by_default() {
+        assert_eq!(MemoryBudget::new(1).on_exceed, MemoryPressure::Error);
+    }
+
+    #[test]
+    fn queue_limits_trip_on_either_dimension() {
+        let limits = StreamQueueLimits::new().max_events(2).max_bytes(100);
+        assert!(!limits.exceeded(2, 100));
+        assert!(limits.exceeded(3, 0));
+        assert!(limits.exceeded(1, 101));
+        assert_eq!(limits.on_overflow, QueueOverflow::Reset);
+
+        let unbounded = StreamQueueLimits::new();
+        assert!(!unbounded.exceeded(usize::MAX, usize::MAX));
+    }
+}
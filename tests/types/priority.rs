@@ -0,0 +1,25 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ignores_what_it_does_not_understand() {
+        assert_eq!(
+            Priority::parse("u=1, i"),
+            Priority::new(1).incremental(true)
+        );
+        assert_eq!(Priority::parse("i=?0, u=5"), Priority::new(5));
+        // Out of range, wrong type, unknown: defaults stay.
+        assert_eq!(Priority::parse("u=9, i=1, x=2"), Priority::default());
+        assert_eq!(Priority::parse(""), Priority::default());
+    }
+
+    #[test]
+    fn display_leaves_out_defaults() {
+        assert_eq!(Priority::default().to_string(), "");
+        assert_eq!(Priority::new(3).incremental(true).to_string(), "i");
+        assert_eq!(Priority::new(12).incremental(true).to_string(), "u=7, i");
+        let priority = Priority::new(0);
+        assert_eq!(Priority::parse(&priority.to_string()), priority);
+    }
+}
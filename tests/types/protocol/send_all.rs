@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pacing::PacingPolicy;
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use std::cell::Cell;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    /// Answers after the number of milliseconds in the path, tracking how
+    /// many requests run at once; `/fail` errors and `/throttled` gets a 429.
+    #[derive(Default)]
+    struct Echo {
+        running: Cell<usize>,
+        peak: Cell<usize>,
+    }
+
+    #[async_trait(?Send)]
+    impl Protocol for Echo {
+        async fn execute(&self, request: &Request) -> Result<Response, ProtocolError> {
+            self.running.set(self.running.get() + 1);
+            self.peak.set(self.peak.get().max(self.running.get()));
+            let path = request
+                .target
+                .url
+                .path()
+                .trim_start_matches('/')
+                .to_string();
+            sleep(Duration::from_millis(path.parse().unwrap_or(0))).await;
+            self.running.set(self.running.get() - 1);
+            if path == "fail" {
+                return Err(ProtocolError::ConnectionFailed("refused".to_string()));
+            }
+            Ok(Response {
+                status: if path == "throttled" { 429 } else { 200 },
+                body: Bytes::from(path),
+                ..Response::default()
+            })
+        }
+    }
+
+    fn requests(paths: &[&str]) -> Vec<Request> {
+        paths
+            .iter()
+            .map(|path| Request::new(&format!("http://example.com/{path}"), "GET").unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn yields_in_request_order_and_keeps_going_after_errors() {
+        let client = Arc::new(Echo::default());
+        let batch = SendAll::new(
+            Arc::clone(&client),
+            requests(&["30", "fail", "10", "0", "20"]),
+            2,
+        );
+        assert_eq!(batch.remaining(), 5);
+
+        let results = batch.collect().await;
+        let bodies: Vec<Option<Bytes>> = results
+            .iter()
+            .map(|result| result.as_ref().ok().map(|response| response.body.clone()))
+            .collect();
+        assert_eq!(
+            bodies,
+            vec![
+                Some(Bytes::from("30")),
+                None,
+                Some(Bytes::from("10")),
+                Some(Bytes::from("0")),
+                Some(Bytes::from("20")),
+            ]
+        );
+        assert_eq!(client.peak.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn next_ends_after_the_last_response() {
+        let mut batch = SendAll::new(Arc::new(Echo::default()), requests(&["0"]), 0);
+        assert!(batch.next().await.unwrap().is_ok());
+        assert!(batch.next().await.is_none());
+        assert_eq!(batch.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn a_paced_batch_waits_out_throttled_hosts() {
+        let pacer = Arc::new(Pacer::new(
+            PacingPolicy::default().base_backoff(Duration::from_millis(100)),
+        ));
+        let batch = SendAll::new(Arc::new(Echo::default()), requests(&["throttled", "0"]), 1)
+            .pacer(Arc::clone(&pacer));
+
+        let started = std::time::Instant::now();
+        let results = batch.collect().await;
+        assert_eq!(results[0].as_ref().unwrap().status, 429);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        // The 200 after the wait ended the throttle.
+        assert!(pacer.state().is_empty());
+    }
+}
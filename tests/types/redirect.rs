@@ -0,0 +1,220 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Header, LocationErrorKind, ProtocolError, Response};
+    use crate::utils::apply_redirect;
+
+    fn redirect(status: u16, location: &str) -> Response {
+        Response {
+            status,
+            headers: vec![Header::new("Location".to_string(), location.to_string())],
+            ..Response::default()
+        }
+    }
+
+    fn credentialed(target: &str) -> Request {
+        Request::new(target, "GET")
+            .unwrap()
+            .header("Authorization: Bearer secret")
+            .header("Cookie: session=1")
+            .header("X-Api-Key: key")
+            .header("Accept: */*")
+    }
+
+    fn header_names(request: &Request) -> Vec<String> {
+        request
+            .headers
+            .iter()
+            .map(|header| header.name.to_ascii_lowercase())
+            .collect()
+    }
+
+    #[test]
+    fn same_origin_hops_keep_credentials() {
+        let mut request = credentialed("https://example.com/a");
+        assert!(apply_redirect(&mut request, &redirect(302, "/b")).unwrap());
+        assert_eq!(
+            header_names(&request),
+            ["authorization", "cookie", "x-api-key", "accept"]
+        );
+    }
+
+    #[test]
+    fn cross_origin_hops_strip_credentials_for_good() {
+        let policy = RedirectPolicy::new().sensitive_header("X-Api-Key");
+        let mut request = credentialed("https://example.com/a").redirect_policy(policy);
+        request.set_cookies([("session", "1")]);
+
+        apply_redirect(&mut request, &redirect(302, "https://other.example/")).unwrap();
+        assert_eq!(header_names(&request), ["accept"]);
+        assert!(request.cookies.is_empty());
+
+        apply_redirect(&mut request, &redirect(302, "https://example.com/a")).unwrap();
+        assert_eq!(header_names(&request), ["accept"]);
+    }
+
+    #[test]
+    fn forwarding_modes_compare_origins() {
+        let target = |url: &str| Request::new(url, "GET").unwrap().target;
+        let from = target("https://example.com/");
+        let same_origin = RedirectPolicy::new();
+        let same_host = RedirectPolicy::new().credentials(CredentialForwarding::SameHost);
+        let always = RedirectPolicy::new().credentials(CredentialForwarding::Always);
+
+        assert!(same_origin.forwards_credentials(&from, &target("https://EXAMPLE.com:443/x")));
+        assert!(!same_origin.forwards_credentials(&from, &target("https://example.com:8443/")));
+        assert!(same_host.forwards_credentials(&from, &target("https://example.com:8443/")));
+        assert!(!same_host.forwards_credentials(&from, &target("http://example.com/")));
+        assert!(!same_host.forwards_credentials(&from, &target("https://api.example.com/")));
+        assert!(always.forwards_credentials(&from, &target("http://evil.example/")));
+    }
+
+    fn post(target: &str) -> Request {
+        Request::new(target, "POST")
+            .unwrap()
+            .header("Content-Type: text/plain")
+            .body("payload")
+    }
+
+    #[test]
+    fn default_methods_follow_browsers() {
+        let mut request = post("https://example.com/form");
+        apply_redirect(&mut request, &redirect(307, "/next")).unwrap();
+        assert_eq!(request.method, "POST");
+        assert!(request.body.is_some());
+
+        apply_redirect(&mut request, &redirect(302, "/done")).unwrap();
+        assert_eq!(request.method, "GET");
+        assert!(request.body.is_none());
+        assert!(header_names(&request).is_empty());
+
+        let mut head = Request::new("https://example.com/", "HEAD").unwrap();
+        apply_redirect(&mut head, &redirect(303, "/other")).unwrap();
+        assert_eq!(head.method, "HEAD");
+
+        let mut put = Request::new("https://example.com/", "PUT")
+            .unwrap()
+            .body("x");
+        apply_redirect(&mut put, &redirect(301, "/moved")).unwrap();
+        assert_eq!(put.method, "PUT");
+        assert!(put.body.is_some());
+    }
+
+    #[test]
+    fn methods_are_matched_whatever_their_case() {
+        let mut request = Request::new("https://example.com/form", "post")
+            .unwrap()
+            .body("payload");
+        apply_redirect(&mut request, &redirect(302, "/done")).unwrap();
+        assert_eq!(request.method, "GET");
+        assert!(request.body.is_none());
+
+        let mut head = Request::new("https://example.com/", "head").unwrap();
+        apply_redirect(&mut head, &redirect(303, "/other")).unwrap();
+        assert_eq!(head.method, "head");
+    }
+
+    #[test]
+    fn policy_overrides_methods_per_status() {
+        let mut request = post("https://example.com/api")
+            .redirect_policy(RedirectPolicy::new().preserve_method());
+        apply_redirect(&mut request, &redirect(302, "/api/v2")).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.body.as_deref(), Some(&b"payload"[..]));
+        assert_eq!(header_names(&request), ["content-type"]);
+
+        let policy = RedirectPolicy::new().method(307, RedirectMethod::Get);
+        assert_eq!(policy.method_for(307), RedirectMethod::Get);
+        assert_eq!(policy.method_for(308), RedirectMethod::Preserve);
+    }
+
+    #[test]
+    fn strict_policy_surfaces_bad_locations() {
+        let mut lenient = Request::new("https://example.com/", "GET").unwrap();
+        assert!(!apply_redirect(&mut lenient, &redirect(302, "mailto:a@example.com")).unwrap());
+
+        let mut strict = Request::new("https://example.com/", "GET")
+            .unwrap()
+            .redirect_policy(RedirectPolicy::new().strict_location(true));
+        match apply_redirect(&mut strict, &redirect(302, "/a b")) {
+            Err(ProtocolError::InvalidLocation { location, kind }) => {
+                assert_eq!(location, "/a b");
+                assert_eq!(kind, LocationErrorKind::InvalidCharacter(' '));
+            }
+            other => panic!("expected InvalidLocation, got {:?}", other),
+        }
+    }
+
+    fn hop(request: &Request, status: u16) -> RedirectHop {
+        RedirectHop {
+            method: request.method.clone(),
+            url: request.target.url.clone(),
+            status,
+        }
+    }
+
+    #[test]
+    fn chain_stops_at_the_policy_limit() {
+        let mut request = Request::new("https://example.com/0", "GET")
+            .unwrap()
+            .redirect_policy(RedirectPolicy::new().max_redirects(2));
+        let mut chain = RedirectChain::new();
+        for next in ["/1", "/2"] {
+            let sent = hop(&request, 302);
+            apply_redirect(&mut request, &redirect(302, next)).unwrap();
+            chain.follow(sent, &request).unwrap();
+        }
+
+        let sent = hop(&request, 302);
+        apply_redirect(&mut request, &redirect(302, "/3")).unwrap();
+        match chain.follow(sent, &request) {
+            Err(err @ ProtocolError::TooManyRedirects { .. }) => {
+                assert_eq!(
+                    err.to_string(),
+                    "Too many redirects (3): GET https://example.com/0 -> 302, \
+                     GET https://example.com/1 -> 302, GET https://example.com/2 -> 302"
+                );
+            }
+            other => panic!("expected TooManyRedirects, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn chain_detects_loops_by_method_and_url() {
+        let mut request = Request::new("https://example.com/a", "POST").unwrap();
+        let mut chain = RedirectChain::new();
+        let sent = hop(&request, 307);
+        apply_redirect(&mut request, &redirect(307, "/b")).unwrap();
+        chain.follow(sent, &request).unwrap();
+
+        // Same URL, different method: not a loop yet.
+        let sent = hop(&request, 303);
+        apply_redirect(&mut request, &redirect(303, "/a#again")).unwrap();
+        chain.follow(sent, &request).unwrap();
+
+        let sent = hop(&request, 302);
+        apply_redirect(&mut request, &redirect(302, "/a")).unwrap();
+        match chain.follow(sent, &request) {
+            Err(ProtocolError::RedirectLoop { chain }) => assert_eq!(chain.len(), 3),
+            other => panic!("expected RedirectLoop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn loops_are_found_whatever_the_method_case() {
+        let mut request = Request::new("https://example.com/a", "get").unwrap();
+        let mut chain = RedirectChain::new();
+        let sent = hop(&request, 302);
+        apply_redirect(&mut request, &redirect(302, "/b")).unwrap();
+        chain.follow(sent, &request).unwrap();
+
+        // The 303 sends an uppercase GET back to where the lowercase one was.
+        let sent = hop(&request, 303);
+        apply_redirect(&mut request, &redirect(303, "/a")).unwrap();
+        assert_eq!(request.method, "GET");
+        assert!(matches!(
+            chain.follow(sent, &request),
+            Err(ProtocolError::RedirectLoop { .. })
+        ));
+    }
+}
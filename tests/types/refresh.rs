@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Request;
+    use crate::utils::apply_redirect;
+
+    #[test]
+    fn parse_refresh_handles_browser_syntax() {
+        assert_eq!(parse_refresh("5"), Some((5, None)));
+        assert_eq!(
+            parse_refresh("0;URL='/next?a=1'"),
+            Some((0, Some("/next?a=1".to_string())))
+        );
+        assert_eq!(
+            parse_refresh("1.5, url = https://example.org/"),
+            Some((1, Some("https://example.org/".to_string())))
+        );
+        assert_eq!(
+            parse_refresh("3 /plain"),
+            Some((3, Some("/plain".to_string())))
+        );
+        assert_eq!(parse_refresh("soon; url=/x"), None);
+    }
+
+    #[test]
+    fn refresh_redirect_detects_header_then_meta_tag() {
+        let res = Response::new(
+            200,
+            &[("Refresh", "2; url=/from-header")],
+            "<meta http-equiv=refresh content='0;url=/from-meta'>",
+        );
+        let refresh = res.refresh_redirect().unwrap();
+        assert_eq!(refresh.source, RefreshSource::Header);
+        assert_eq!(refresh.url.as_deref(), Some("/from-header"));
+
+        let res = Response::new(
+            200,
+            &[("Content-Type", "text/html")],
+            "<html><head><META name=x><meta content=\"0; URL=/login\" HTTP-EQUIV=\"Refresh\" /></head>",
+        );
+        let refresh = res.refresh_redirect().unwrap();
+        assert_eq!(refresh.source, RefreshSource::MetaTag);
+        assert_eq!(refresh.url.as_deref(), Some("/login"));
+
+        let json = Response::new(
+            200,
+            &[("Content-Type", "application/json")],
+            "\"<meta http-equiv=refresh content='0;url=/x'>\"",
+        );
+        assert!(json.refresh_redirect().is_none());
+    }
+
+    #[test]
+    fn refresh_is_followed_only_when_enabled() -> Result<(), crate::types::ProtocolError> {
+        let res = Response::new(200, &[("Refresh", "0; url=/next")], "");
+        let mut request = Request::new("https://example.com/start", "POST")?
+            .header("Content-Type: text/plain")
+            .trailer("X-Checksum: 1")
+            .body("x");
+        assert!(!apply_redirect(&mut request, &res)?);
+
+        request.set_follow_refresh(true);
+        assert!(apply_redirect(&mut request, &res)?);
+        assert_eq!(request.target.url.as_str(), "https://example.com/next");
+        assert_eq!(request.method, "GET");
+        assert!(request.body.is_none());
+        // Nothing is left declaring the body that was dropped.
+        assert!(request.headers.is_empty());
+        assert!(request.trailers.is_empty());
+
+        let reload = Response::new(200, &[("Refresh", "0; url=/next")], "");
+        assert!(!apply_redirect(&mut request, &reload)?);
+        Ok(())
+    }
+}
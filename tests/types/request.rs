@@ -96,6 +96,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn prepare_headers_follows_user_agent_policy() -> Result<(), ProtocolError> {
+        let custom = Request::new("https://example.com", "GET")?
+            .user_agent(UserAgent::Custom("scanner/2.0".to_string()));
+        assert_eq!(
+            header_value(&custom.prepare_headers(), "user-agent"),
+            Some("scanner/2.0")
+        );
+
+        let omitted = Request::new("https://example.com", "GET")?.user_agent(UserAgent::Omit);
+        assert_eq!(header_count(&omitted.prepare_headers(), "user-agent"), 0);
+
+        let mut inherited = Request::new("https://example.com", "GET")?;
+        inherited.inherit_user_agent(&UserAgent::Omit);
+        assert_eq!(inherited.user_agent, UserAgent::Omit);
+        let mut explicit = custom.clone();
+        explicit.inherit_user_agent(&UserAgent::Omit);
+        assert_eq!(explicit.user_agent, custom.user_agent);
+        Ok(())
+    }
+
+    #[test]
+    fn prepare_headers_without_auto_headers_keeps_explicit_ones() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com", "POST")?
+            .json(json!({"a": 1}))
+            .auto_headers(false);
+        assert!(request.prepare_headers().is_empty());
+
+        let request = request.try_header("X-Probe: 1")?;
+        let headers = request.prepare_headers();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(header_value(&headers, "x-probe"), Some("1"));
+        Ok(())
+    }
+
     #[test]
     fn prepare_pseudo_headers_for_regular_request() -> Result<(), ProtocolError> {
         let request = Request::new("https://example.com/path", "GET")?;
@@ -120,6 +155,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn prepare_pseudo_headers_for_asterisk_options() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com/*", "OPTIONS")?;
+        let pseudo = Request::prepare_pseudo_headers(&request)?;
+
+        assert_eq!(header_value(&pseudo, ":path"), Some("*"));
+        assert_eq!(header_value(&pseudo, ":scheme"), Some("https"));
+        assert_eq!(header_value(&pseudo, ":authority"), Some("example.com"));
+        assert_eq!(
+            request
+                .clone()
+                .target_form(RequestTargetForm::Absolute)
+                .request_target(),
+            "https://example.com:443"
+        );
+
+        let regular = Request::new("https://example.com/*?x=1", "OPTIONS")?;
+        assert_eq!(regular.path(), "/*?x=1");
+        Ok(())
+    }
+
+    #[test]
+    fn trace_with_body_is_rejected_when_validating() -> Result<(), ProtocolError> {
+        let request = Request::new("https://example.com/", "TRACE")?.body("payload");
+
+        assert!(request.check_headers(&HttpProtocol::Http2).is_ok());
+        assert!(matches!(
+            request
+                .validate_headers(true)
+                .check_headers(&HttpProtocol::Http2),
+            Err(ProtocolError::RequestFailed(_))
+        ));
+        Ok(())
+    }
+
     #[test]
     fn prepare_request_produces_consistent_structure() -> Result<(), ProtocolError> {
         let request = Request::new("https://example.com/api", "POST")?
@@ -150,4 +220,80 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn nested_form_flattens_objects_and_arrays() {
+        let value = json!({
+            "user": {"name": "a b", "roles": ["x", "y"]},
+            "items": [{"id": 1}, {"id": null}],
+            "ok": true
+        });
+        let pairs = |arrays| FormBody::Nested(value.clone(), arrays).fields();
+
+        assert_eq!(
+            pairs(ArrayFormat::Brackets),
+            vec![
+                ("items[0][id]".to_string(), "1".to_string()),
+                ("items[1][id]".to_string(), String::new()),
+                ("ok".to_string(), "true".to_string()),
+                ("user[name]".to_string(), "a b".to_string()),
+                ("user[roles][]".to_string(), "x".to_string()),
+                ("user[roles][]".to_string(), "y".to_string()),
+            ]
+        );
+        assert_eq!(pairs(ArrayFormat::Indices)[4].0, "user[roles][0]");
+        assert_eq!(pairs(ArrayFormat::Repeat)[5].0, "user[roles]");
+        assert_eq!(
+            FormBody::nested(json!({"a": {"b": "c"}})).encode(),
+            "a%5Bb%5D=c"
+        );
+    }
+
+    #[test]
+    fn patch_helpers_set_patch_media_types() -> Result<(), ProtocolError> {
+        let patch = json!([{"op": "remove", "path": "/a"}]);
+        let request = Request::new("https://example.com/doc", "PATCH")?.json_patch(patch);
+        assert_eq!(
+            header_value(&request.prepare_headers(), CONTENT_TYPE_HEADER),
+            Some(APPLICATION_JSON_PATCH)
+        );
+
+        let request = request.merge_patch(json!({"a": null}));
+        assert_eq!(request.body.as_deref(), Some(&b"{\"a\":null}"[..]));
+        assert_eq!(
+            header_value(&request.prepare_headers(), CONTENT_TYPE_HEADER),
+            Some(APPLICATION_MERGE_PATCH)
+        );
+
+        let request = request.json(json!({}));
+        assert_eq!(
+            header_value(&request.prepare_headers(), CONTENT_TYPE_HEADER),
+            Some(APPLICATION_JSON)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prepare_once_reuses_until_the_request_changes() -> Result<(), ProtocolError> {
+        let mut request = Request::new("https://example.com/a", "GET")?;
+        let first = request.prepare_once()?;
+        assert!(Arc::ptr_eq(&first, &request.prepare_once()?));
+
+        request
+            .headers
+            .push(Header::new("x-probe".into(), "1".into()));
+        let second = request.prepare_once()?;
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_eq!(header_value(&second.headers, "x-probe"), Some("1"));
+
+        request.target.url.set_path("/b");
+        assert_eq!(request.prepare_once()?.path, "/b");
+
+        request.set_body("one");
+        let with_body = request.prepare_once()?;
+        request.set_body("two");
+        assert_eq!(request.prepare_once()?.body.as_deref(), Some(&b"two"[..]));
+        assert!(!Arc::ptr_eq(&with_body, &request.prepare_once()?));
+        Ok(())
+    }
 }
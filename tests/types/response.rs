@@ -0,0 +1,36 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_with_charset_honours_content_type_label() {
+        let res = Response::new(
+            200,
+            &[("Content-Type", "text/html; charset=\"ISO-8859-1\"")],
+            b"caf\xe9 \x80",
+        );
+        assert_eq!(res.text_with_charset(), "café €");
+        assert_eq!(res.charset().map(|e| e.name()), Some("windows-1252"));
+
+        let res = Response::new(
+            200,
+            &[("Content-Type", "text/plain; charset=utf-16le")],
+            b"h\x00i\x00",
+        );
+        assert_eq!(res.text_with_charset(), "hi");
+    }
+
+    #[test]
+    fn text_with_charset_prefers_bom_and_defaults_to_utf8() {
+        let res = Response::new(
+            200,
+            &[("Content-Type", "text/plain; charset=windows-1252")],
+            b"\xfe\xff\x00o\x00k",
+        );
+        assert_eq!(res.text_with_charset(), "ok");
+
+        let res = Response::new(200, &[("Content-Type", "text/plain")], "naïve");
+        assert!(res.charset().is_none());
+        assert_eq!(res.text_with_charset(), "naïve");
+    }
+}
@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FrameH2;
+
+    fn h2(frame_type: FrameTypeH2, flags: u8, payload: &[u8]) -> ResponseFrame {
+        ResponseFrame::Http2(FrameH2::new(
+            frame_type,
+            flags,
+            1,
+            Bytes::copy_from_slice(payload),
+        ))
+    }
+
+    #[test]
+    fn header_blocks_join_continuations_and_drop_padding() {
+        let frames = vec![
+            // PADDED | PRIORITY: pad length 2, five priority bytes, fragment, padding.
+            h2(
+                FrameTypeH2::Headers,
+                PADDED_FLAG | PRIORITY_FLAG,
+                &[2, 0, 0, 0, 3, 15, 0x82, 0x84, 0, 0],
+            ),
+            h2(FrameTypeH2::Continuation, END_HEADERS_FLAG, &[0x86]),
+            h2(FrameTypeH2::Data, PADDED_FLAG, &[1, b'h', b'i', 0]),
+            h2(FrameTypeH2::WindowUpdate, 0, &[0x80, 0, 0x01, 0]),
+            h2(FrameTypeH2::Data, END_STREAM_FLAG, b"!"),
+            h2(FrameTypeH2::Headers, END_HEADERS_FLAG, &[0x40]),
+        ];
+
+        assert_eq!(
+            frames.encoded_header_blocks(),
+            vec![
+                Bytes::from_static(&[0x82, 0x84, 0x86]),
+                Bytes::from_static(&[0x40])
+            ]
+        );
+        assert_eq!(frames.headers_frames().len(), 2);
+        assert_eq!(frames.data_frames().len(), 2);
+        assert_eq!(frames.data_bytes(), Bytes::from_static(b"hi!"));
+        assert_eq!(frames.window_updates(), vec![256]);
+        assert!(frames[4].is_end_stream());
+        assert!(!frames[2].is_end_stream());
+    }
+
+    #[test]
+    fn qpack_instructions_are_not_headers_or_data() {
+        use crate::types::FrameH3;
+
+        let h3 = |frame_type: FrameTypeH3, payload: &'static [u8]| {
+            ResponseFrame::Http3(FrameH3::new(frame_type, 0, Bytes::from_static(payload)))
+        };
+        let frames = vec![
+            h3(FrameTypeH3::Headers, &[0x02, 0x80, 0xd9]),
+            h3(FrameTypeH3::QpackEncoder, &[0x3f, 0xe1, 0x1f]),
+            h3(FrameTypeH3::Data, b"ok"),
+            h3(FrameTypeH3::QpackDecoder, &[0x80]),
+        ];
+
+        let instructions = frames.qpack_instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(
+            instructions[0].payload(),
+            &Bytes::from_static(&[0x3f, 0xe1, 0x1f])
+        );
+        assert_eq!(frames.headers_frames().len(), 1);
+        assert_eq!(frames.encoded_header_blocks().len(), 1);
+        assert_eq!(frames.data_bytes(), Bytes::from_static(b"ok"));
+    }
+
+    #[test]
+    fn unknown_frames_keep_their_type() {
+        use crate::types::FrameH3;
+
+        let frames = vec![
+            h2(FrameTypeH2::Headers, END_HEADERS_FLAG, &[0x88]),
+            h2(FrameTypeH2::Unknown(0x0a), 0, b"h3=\":443\""),
+            ResponseFrame::Http3(FrameH3::new(FrameTypeH3::Unknown(0x21), 0, Bytes::new())),
+            h2(FrameTypeH2::Data, END_STREAM_FLAG, b"ok"),
+        ];
+
+        let unknown: Vec<Option<u64>> = frames
+            .unknown_frames()
+            .iter()
+            .map(|frame| frame.unknown_type())
+            .collect();
+        assert_eq!(unknown, vec![Some(0x0a), Some(0x21)]);
+        assert_eq!(frames[0].unknown_type(), None);
+        assert_eq!(frames.data_bytes(), Bytes::from_static(b"ok"));
+    }
+
+    #[test]
+    fn data_frame_gaps_skip_other_frames() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let response = Response {
+            protocol: "HTTP/2.0".to_string(),
+            body: Bytes::from_static(b"abc"),
+            frames: Some(vec![
+                h2(FrameTypeH2::Headers, END_HEADERS_FLAG, &[0x88]),
+                h2(FrameTypeH2::Data, 0, b"a"),
+                h2(FrameTypeH2::WindowUpdate, 0, &[0, 0, 0, 1]),
+                h2(FrameTypeH2::Data, 0, b"b"),
+                h2(FrameTypeH2::Data, END_STREAM_FLAG, b"c"),
+            ]),
+            frame_times: Some(vec![at(0), at(40), at(45), at(140), at(150)]),
+            ..Response::default()
+        };
+
+        assert_eq!(
+            response.frame_gaps(),
+            vec![
+                Duration::from_millis(40),
+                Duration::from_millis(5),
+                Duration::from_millis(95),
+                Duration::from_millis(10)
+            ]
+        );
+        let stats = response.data_frame_gap_stats().unwrap();
+        assert_eq!(stats.gaps, 2);
+        assert_eq!(stats.min, Duration::from_millis(10));
+        assert_eq!(stats.max, Duration::from_millis(100));
+        assert_eq!(stats.mean, Duration::from_millis(55));
+        assert_eq!(stats.median, Duration::from_millis(55));
+        assert_eq!(stats.total, Duration::from_millis(110));
+        assert_eq!(FrameGapStats::from_gaps(&[]), None);
+    }
+}
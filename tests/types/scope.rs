@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn patterns_are_read_by_shape() {
+        assert!(matches!(
+            HostPattern::parse("10.0.0.0/8").unwrap(),
+            HostPattern::Cidr(_)
+        ));
+        assert!(matches!(
+            HostPattern::parse("192.0.2.1").unwrap(),
+            HostPattern::Cidr(_)
+        ));
+        assert!(matches!(
+            HostPattern::parse("*.Example.com").unwrap(),
+            HostPattern::Glob(glob) if glob == "*.example.com"
+        ));
+        assert!(matches!(
+            HostPattern::parse("Example.com.").unwrap(),
+            HostPattern::Exact(name) if name == "example.com"
+        ));
+        assert!(HostPattern::parse(" ").is_err());
+        assert!(HostPattern::parse("10.0.0.0/40").is_err());
+    }
+
+    #[test]
+    fn globs_match_subdomains() {
+        let glob = HostPattern::parse("*.example.com").unwrap();
+        assert!(glob.matches("api.example.com", &[]));
+        assert!(glob.matches("a.b.example.com", &[]));
+        assert!(!glob.matches("example.com", &[]));
+        assert!(!glob.matches("example.com.evil.test", &[]));
+
+        let single = HostPattern::parse("host-?.test").unwrap();
+        assert!(single.matches("host-1.test", &[]));
+        assert!(!single.matches("host-10.test", &[]));
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let scope = HostScope::new()
+            .allow("*.example.com")
+            .unwrap()
+            .deny("admin.example.com")
+            .unwrap();
+        assert!(scope.is_in_scope("www.example.com", &[]));
+        assert!(!scope.is_in_scope("ADMIN.example.com.", &[]));
+        assert!(!scope.is_in_scope("other.test", &[]));
+        assert!(HostScope::new().is_in_scope("anything.test", &[]));
+    }
+
+    #[test]
+    fn networks_cover_the_resolved_addresses() {
+        let scope = HostScope::new()
+            .allow("203.0.113.0/24")
+            .unwrap()
+            .deny("203.0.113.66")
+            .unwrap();
+        assert!(scope.is_in_scope("app.test", &[ip("203.0.113.5")]));
+        // Every address has to be allowed, one denied address is enough.
+        assert!(!scope.is_in_scope("app.test", &[ip("203.0.113.5"), ip("198.51.100.1")]));
+        assert!(!scope.is_in_scope("app.test", &[ip("203.0.113.5"), ip("203.0.113.66")]));
+        assert!(!scope.is_in_scope("app.test", &[]));
+    }
+
+    #[test]
+    fn check_refuses_out_of_scope_targets() {
+        let scope = HostScope::new().allow("192.0.2.0/24").unwrap();
+        let inside = Target::new("http://192.0.2.10/".parse().unwrap());
+        let outside = Target::new("http://[2001:db8::1]/".parse().unwrap());
+        assert!(tokio_test::block_on(scope.check(&inside)).is_ok());
+        assert!(matches!(
+            tokio_test::block_on(scope.check(&outside)),
+            Err(ProtocolError::OutOfScope { host }) if host == "2001:db8::1"
+        ));
+    }
+
+    #[test]
+    fn the_addresses_dialed_are_checked_again() {
+        let scope = HostScope::new().allow("203.0.113.0/24").unwrap();
+        let cache = DnsCache::global();
+        cache.insert("rebind.scope.test", vec![ip("203.0.113.5")], None);
+        let target = Target::new("http://rebind.scope.test/".parse().unwrap());
+        assert!(tokio_test::block_on(scope.check(&target)).is_ok());
+
+        // The name now points elsewhere; the guard sees what is dialed.
+        cache.insert("rebind.scope.test", vec![ip("127.0.0.1")], None);
+        let guard = SsrfGuard::for_scope(scope.clone());
+        assert!(matches!(
+            tokio_test::block_on(guard.resolve("rebind.scope.test", 80)),
+            Err(ProtocolError::OutOfScope { host }) if host == "rebind.scope.test"
+        ));
+        // Proxies are not held to the scope.
+        assert!(tokio_test::block_on(guard.resolve_proxy("rebind.scope.test", 80)).is_ok());
+        cache.remove("rebind.scope.test");
+    }
+
+    #[test]
+    fn requests_carry_a_scope_with_networks_to_the_connector() {
+        let mut request = Request::new("http://app.test/", "GET").unwrap();
+        HostScope::new()
+            .allow("*.test")
+            .unwrap()
+            .guard(&mut request);
+        assert!(request.ssrf_guard.is_none());
+
+        let scope = HostScope::new().allow("203.0.113.0/24").unwrap();
+        scope.guard(&mut request);
+        scope.guard(&mut request);
+        assert_eq!(
+            request.ssrf_guard,
+            Some(SsrfGuard::for_scope(scope.clone()))
+        );
+        // Such a guard leaves the connection poolable.
+        assert!(crate::pool::pool_key(&request).is_some());
+
+        let mut request = Request::new("http://app.test/", "GET")
+            .unwrap()
+            .ssrf_guard(SsrfGuard::new());
+        scope.guard(&mut request);
+        assert_eq!(request.ssrf_guard, Some(SsrfGuard::new().host_scope(scope)));
+        assert!(crate::pool::pool_key(&request).is_none());
+    }
+}
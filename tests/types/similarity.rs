@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalization_hides_numbers_and_spacing() {
+        assert_eq!(
+            normalize_body("  Request  ID 12345\n\tServed at 1699999999 "),
+            "request id 0 served at 0"
+        );
+    }
+
+    #[test]
+    fn levenshtein_counts_edits() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+
+    #[test]
+    fn pages_differing_only_in_tokens_are_identical() {
+        let a = Response::new(404, &[], "<title>Not Found</title> nonce 8812 for /admin");
+        let b = Response::new(404, &[], "<title>Not Found</title> nonce 1 for /admin");
+        assert_eq!(a.similarity(&b), 1.0);
+        assert_eq!(a.page_signature(), b.page_signature());
+        assert_ne!(a.body_hash(), b.body_hash());
+        assert_eq!(a.html_title().as_deref(), Some("Not Found"));
+    }
+
+    #[test]
+    fn simhash_separates_unrelated_pages() {
+        let base = "the quick brown fox jumps over the lazy dog near the river bank today";
+        let close = "the quick brown fox jumps over the lazy cat near the river bank today";
+        let far = "lorem ipsum dolor sit amet consectetur adipiscing elit sed do eiusmod";
+        let distance_close = simhash_distance(simhash(base), simhash(close));
+        let distance_far = simhash_distance(simhash(base), simhash(far));
+        assert!(distance_close < distance_far);
+    }
+
+    #[test]
+    fn body_hash_is_sha256_hex() {
+        assert_eq!(
+            Response::new(200, &[], "").body_hash(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
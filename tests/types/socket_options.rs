@@ -0,0 +1,22 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_addr_follows_the_peer_family_unless_pinned() {
+        let v4: SocketAddr = "192.0.2.1:443".parse().unwrap();
+        let v6: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+
+        let defaults = SocketOptions::new();
+        assert!(defaults.is_default());
+        assert_eq!(defaults.bind_addr(&v4), "0.0.0.0:0".parse().unwrap());
+        assert_eq!(defaults.bind_addr(&v6), "[::]:0".parse().unwrap());
+
+        let pinned = SocketOptions::new()
+            .local_ip(IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .local_port(40000)
+            .reuse_address(true);
+        assert!(!pinned.is_default());
+        assert_eq!(pinned.bind_addr(&v4), "127.0.0.1:40000".parse().unwrap());
+    }
+}
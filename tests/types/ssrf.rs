@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn cidr_parsing_masks_host_bits() {
+        let cidr: IpCidr = "10.1.2.3/8".parse().unwrap();
+        assert_eq!(cidr.to_string(), "10.0.0.0/8");
+        assert!(cidr.contains(ip("10.255.0.1")));
+        assert!(!cidr.contains(ip("11.0.0.1")));
+        assert_eq!("::1".parse::<IpCidr>().unwrap().prefix(), 128);
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("0.0.0.0/0"
+            .parse::<IpCidr>()
+            .unwrap()
+            .contains(ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn default_guard_blocks_internal_ranges() {
+        let guard = SsrfGuard::new();
+        for blocked in [
+            "127.0.0.1",
+            "10.0.0.5",
+            "172.31.255.255",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "224.0.0.1",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+            // 6to4 and Teredo addresses for 127.0.0.1.
+            "2002:7f00:1::1",
+            "2001:0:4136:e378:8000:63bf:80ff:fffe",
+        ] {
+            assert!(
+                !guard.is_allowed(ip(blocked)),
+                "{} should be blocked",
+                blocked
+            );
+        }
+        assert!(guard.is_allowed(ip("93.184.216.34")));
+        assert!(guard.is_allowed(ip("2606:4700::1111")));
+    }
+
+    #[test]
+    fn deny_beats_allow_beats_defaults() -> Result<(), ProtocolError> {
+        let guard = SsrfGuard::new()
+            .allow("10.20.0.0/16")?
+            .deny("10.20.30.0/24")?
+            .deny("203.0.113.7")?;
+        assert!(guard.is_allowed(ip("10.20.1.1")));
+        assert!(!guard.is_allowed(ip("10.20.30.40")));
+        assert!(!guard.is_allowed(ip("203.0.113.7")));
+        assert!(!guard.is_allowed(ip("10.21.0.1")));
+        Ok(())
+    }
+
+    #[test]
+    fn canonical_host_expands_shorthand_ipv4() {
+        assert_eq!(canonical_host("2130706433"), "127.0.0.1");
+        assert_eq!(canonical_host("0x7f.1"), "127.0.0.1");
+        assert_eq!(canonical_host("0177.0.0.01"), "127.0.0.1");
+        assert_eq!(canonical_host("[::1]"), "::1");
+        assert_eq!(canonical_host("Example.COM."), "example.com");
+        assert_eq!(canonical_host("1.2.3.256"), "1.2.3.256");
+    }
+}
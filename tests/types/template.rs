@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn render_substitutes_path_query_and_headers() {
+        let template = Request::new("https://example.com/users/{{id}}?sort={{order}}", "GET")
+            .unwrap()
+            .header("X-Request-Id: {{ id }}")
+            .template();
+        assert_eq!(template.placeholders(), vec!["id", "order"]);
+
+        let rendered = template.render(&[("id", "42"), ("order", "asc")]);
+        assert_eq!(rendered.target.url.path(), "/users/42");
+        assert_eq!(rendered.target.url.query(), Some("sort=asc"));
+        let header = rendered
+            .headers
+            .iter()
+            .find(|h| h.name == "X-Request-Id")
+            .unwrap();
+        assert_eq!(header.value.as_deref(), Some("42"));
+
+        // Unknown placeholders stay, and the template renders again.
+        let again = template.render(&[("id", "7")]);
+        assert_eq!(again.target.url.path(), "/users/7");
+        assert_eq!(again.target.url.query(), Some("sort={{order}}"));
+    }
+
+    #[test]
+    fn render_reserializes_json_and_rewrites_text_bodies() {
+        let json_template = Request::new("https://example.com/", "POST")
+            .unwrap()
+            .json(json!({"user": "{{name}}", "tags": ["fixed"]}))
+            .template();
+        let rendered = json_template.render(&[("name", "a\"b")]);
+        assert_eq!(
+            rendered.json,
+            Some(json!({"user": "a\"b", "tags": ["fixed"]}))
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(rendered.body.as_ref().unwrap()).unwrap();
+        assert_eq!(body["user"], "a\"b");
+
+        let mut text = Request::new("https://example.com/", "POST").unwrap();
+        text.set_body("id={{id}}&keep={{missing}}");
+        let rendered = text.template().render(&[("id", "42")]);
+        assert_eq!(
+            rendered.body.as_deref(),
+            Some(&b"id=42&keep={{missing}}"[..])
+        );
+    }
+}
@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phase_timeouts_fall_back_to_read() {
+        let timeouts = ClientTimeouts {
+            header_read: Some(Duration::from_secs(2)),
+            ..ClientTimeouts::default()
+        };
+
+        assert_eq!(timeouts.header_timeout(), Some(Duration::from_secs(2)));
+        assert_eq!(timeouts.body_idle_timeout(), timeouts.read);
+        assert_eq!(timeouts.body_total_timeout(), None);
+    }
+
+    #[test]
+    fn next_body_read_is_capped_by_total_deadline() {
+        let timeouts = ClientTimeouts {
+            read: None,
+            body_idle: Some(Duration::from_secs(30)),
+            ..ClientTimeouts::default()
+        };
+        let deadline = Instant::now() + Duration::from_secs(1);
+
+        let next = timeouts.next_body_read_timeout(Some(deadline)).unwrap();
+        assert!(next <= Duration::from_secs(1));
+        assert_eq!(
+            timeouts.next_body_read_timeout(None),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            ClientTimeouts::disabled().next_body_read_timeout(None),
+            None
+        );
+    }
+}
@@ -1 +1,132 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn header(name: &str, value: &str) -> Header {
+        Header::new(name.to_string(), value.to_string())
+    }
+
+    fn rejection(headers: &[Header], protocol: HttpProtocol) -> String {
+        match validate_request_headers(headers, &[], &protocol) {
+            Err(ProtocolError::MalformedHeaders(msg)) => msg,
+            other => panic!("expected MalformedHeaders, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validation_rejects_connection_specific_headers_on_h2() {
+        let headers = vec![header("Connection", "keep-alive")];
+
+        let msg = rejection(&headers, HttpProtocol::Http2);
+        assert!(msg.contains("'Connection'"), "{}", msg);
+        assert!(validate_request_headers(&headers, &[], &HttpProtocol::Http1).is_ok());
+    }
+
+    #[test]
+    fn validation_only_allows_te_trailers_on_h3() {
+        assert!(
+            validate_request_headers(&[header("te", "trailers")], &[], &HttpProtocol::Http3)
+                .is_ok()
+        );
+
+        let msg = rejection(&[header("TE", "gzip")], HttpProtocol::Http3);
+        assert!(msg.contains("gzip"), "{}", msg);
+    }
+
+    #[test]
+    fn validation_points_at_whitespace_in_names() {
+        let msg = rejection(&[header("X Bad", "1")], HttpProtocol::Http1);
+        assert_eq!(msg, "Header name 'X Bad' contains whitespace");
+    }
+
+    #[test]
+    fn validation_rejects_conflicting_content_length() {
+        let headers = vec![header("Content-Length", "1"), header("content-length", "2")];
+        let msg = rejection(&headers, HttpProtocol::Http1);
+        assert!(msg.contains("'1' and '2'"), "{}", msg);
+    }
+
+    #[test]
+    fn validation_rejects_pseudo_headers_after_regular_ones() {
+        let headers = vec![header("accept", "*/*"), header(":path", "/")];
+        let msg = rejection(&headers, HttpProtocol::Http2);
+        assert!(msg.contains("':path'"), "{}", msg);
+    }
+
+    #[test]
+    fn base64_decode_round_trips_encode() {
+        for input in [&b""[..], b"f", b"fo", b"foo", b"\xff\x00\x10"] {
+            assert_eq!(base64_decode(&base64_encode(input)).as_deref(), Some(input));
+        }
+        assert_eq!(base64_decode("Zm9v").as_deref(), Some(&b"foo"[..]));
+        assert!(base64_decode("Zm9v!").is_none());
+    }
+
+    #[test]
+    fn locations_resolve_like_a_browser() {
+        let base = Url::parse("https://example.com/a/b?x=1#frag").unwrap();
+        let resolve = |location: &str| resolve_redirect_url(&base, location).unwrap().to_string();
+
+        assert_eq!(resolve("//cdn.example/p"), "https://cdn.example/p#frag");
+        assert_eq!(resolve("#top"), "https://example.com/a/b?x=1#top");
+        assert_eq!(resolve("  c d\t\n "), "https://example.com/a/c%20d#frag");
+        assert_eq!(
+            resolve("\\\\other.example\\x"),
+            "https://other.example/x#frag"
+        );
+        assert_eq!(
+            resolve_redirect_url(&base, "ftp://example.com/"),
+            Err(LocationErrorKind::UnsupportedScheme("ftp".to_string()))
+        );
+        assert_eq!(
+            resolve_redirect_url(&base, " \t"),
+            Err(LocationErrorKind::Empty)
+        );
+    }
+
+    #[test]
+    fn strict_locations_must_be_uri_references() {
+        let base = Url::parse("https://example.com/").unwrap();
+        assert_eq!(
+            resolve_location(&base, " /next", true),
+            Err(LocationErrorKind::Whitespace)
+        );
+        assert_eq!(
+            resolve_location(&base, "/a b", true),
+            Err(LocationErrorKind::InvalidCharacter(' '))
+        );
+        assert_eq!(
+            resolve_location(&base, "/caf%C3%A9?q=1", true)
+                .unwrap()
+                .as_str(),
+            "https://example.com/caf%C3%A9?q=1"
+        );
+    }
+
+    #[tokio::test]
+    async fn bounded_join_hands_outputs_back_in_order() {
+        let delays = [30u64, 0, 10, 0];
+        let started = std::cell::Cell::new(0);
+        let futures = delays.iter().enumerate().map(|(index, delay)| {
+            started.set(started.get() + 1);
+            async move {
+                tokio::time::sleep(Duration::from_millis(*delay)).await;
+                index
+            }
+        });
+        let mut join = BoundedJoin::new(futures, 2);
+
+        assert_eq!(join.remaining(), 4);
+        assert_eq!(join.next().await, Some(0));
+        // The finished second future held its slot until the first was out.
+        assert_eq!(started.get(), 2);
+        assert_eq!(join.next().await, Some(1));
+        assert_eq!(join.next().await, Some(2));
+        assert_eq!(join.next().await, Some(3));
+        assert_eq!(join.next().await, None);
+        assert_eq!(
+            join_bounded(vec![async { 1 }, async { 2 }], 0).await,
+            vec![1, 2]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Request;
+
+    fn header_steps(steps: &[WriteStep]) -> Vec<(bool, bool)> {
+        // (carries :method, end_stream) for every HEADERS step
+        steps
+            .iter()
+            .filter_map(|step| match step {
+                WriteStep::Headers {
+                    headers,
+                    end_stream,
+                } => Some((headers.iter().any(|h| h.name == ":method"), *end_stream)),
+                WriteStep::Data { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_headers_repeats_the_request_block() -> Result<(), ProtocolError> {
+        let prepared = Request::new("https://example.com/", "POST")?
+            .body("x")
+            .prepare_request()?;
+        let steps = StreamViolation::DuplicateHeaders.plan(&prepared);
+        assert_eq!(header_steps(&steps), vec![(true, false), (true, false)]);
+        assert_eq!(
+            steps.last(),
+            Some(&WriteStep::Data {
+                chunk: Bytes::from("x"),
+                end_stream: true,
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn trailers_before_data_uses_placeholder_without_trailers() -> Result<(), ProtocolError> {
+        let prepared = Request::new("https://example.com/", "GET")?.prepare_request()?;
+        let steps = StreamViolation::TrailersBeforeData.plan(&prepared);
+        assert_eq!(header_steps(&steps), vec![(true, false), (false, false)]);
+        assert!(matches!(
+            &steps[1],
+            WriteStep::Headers { headers, .. } if headers[0].name == "x-trailer"
+        ));
+        assert!(matches!(
+            steps[2],
+            WriteStep::Data {
+                end_stream: true,
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn headers_after_end_stream_follows_the_finished_request() -> Result<(), ProtocolError> {
+        let prepared = Request::new("https://example.com/", "GET")?
+            .trailer("x-sum: 1")
+            .prepare_request()?;
+        let steps = StreamViolation::HeadersAfterEndStream.plan(&prepared);
+        assert!(matches!(
+            steps[1],
+            WriteStep::Data {
+                end_stream: true,
+                ..
+            }
+        ));
+        assert_eq!(header_steps(&steps), vec![(true, false), (false, true)]);
+        Ok(())
+    }
+}
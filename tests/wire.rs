@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A writer whose every write fails.
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writer_keeps_directions_apart() {
+        let mut writer = WireWriter::new(Vec::new(), Vec::new());
+        writer
+            .record(WireDirection::Sent, b"PRI * HTTP/2.0")
+            .unwrap();
+        writer
+            .record(WireDirection::Received, b"\x00\x00\x00")
+            .unwrap();
+        writer.record(WireDirection::Sent, b"\r\n").unwrap();
+        // Streams only label the bytes; the files stay per direction.
+        writer.record_stream(4, WireDirection::Sent, b"!").unwrap();
+
+        let (sent, received) = writer.finish().unwrap();
+        assert_eq!(sent, b"PRI * HTTP/2.0\r\n!");
+        assert_eq!(received, b"\x00\x00\x00");
+    }
+
+    #[test]
+    fn a_failed_write_comes_back_from_finish() {
+        let mut writer = WireWriter::new(Broken, Broken);
+        writer.record(WireDirection::Sent, b"abc").unwrap();
+        let error = writer.finish().err().unwrap();
+        assert_eq!(error.to_string(), "disk full");
+    }
+
+    #[test]
+    fn channel_sink_fails_once_the_receiver_is_gone() {
+        let (mut sender, mut receiver) = mpsc::unbounded_channel();
+        sender.record(WireDirection::Received, b"abc").unwrap();
+        sender.record_stream(3, WireDirection::Sent, b"d").unwrap();
+        let chunk = receiver.try_recv().unwrap();
+        assert_eq!(chunk.direction, WireDirection::Received);
+        assert_eq!(chunk.stream_id, None);
+        assert_eq!(&chunk.data[..], b"abc");
+        assert_eq!(receiver.try_recv().unwrap().stream_id, Some(3));
+
+        drop(receiver);
+        assert!(sender.record(WireDirection::Sent, b"x").is_err());
+    }
+
+    #[test]
+    fn a_failing_sink_is_dropped() {
+        let mut mirror = WireMirror::default();
+        let mut receiver = mirror.channel();
+        mirror.record(None, WireDirection::Sent, b"a");
+        assert!(mirror.is_active());
+        assert_eq!(&receiver.try_recv().unwrap().data[..], b"a");
+
+        drop(receiver);
+        mirror.record(Some(0), WireDirection::Sent, b"b");
+        assert!(!mirror.is_active());
+    }
+}